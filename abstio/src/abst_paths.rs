@@ -226,6 +226,21 @@ pub fn path_prebaked_results(name: &MapName, scenario_name: &str) -> String {
     ))
 }
 
+/// Describes how the prebaked results were generated.
+pub fn path_prebaked_metadata(name: &MapName, scenario_name: &str) -> String {
+    path(format!(
+        "system/{}/{}/prebaked_results/{}/{}.json",
+        name.city.country, name.city.city, name.map, scenario_name
+    ))
+}
+
+pub fn path_all_prebaked_results(name: &MapName) -> String {
+    path(format!(
+        "system/{}/{}/prebaked_results/{}",
+        name.city.country, name.city.city, name.map
+    ))
+}
+
 pub fn path_scenario(name: &MapName, scenario_name: &str) -> String {
     // TODO Getting complicated. Sometimes we're trying to load, so we should look for .bin, then
     // .json. But when we're writing a custom scenario, we actually want to write a .bin.
@@ -87,6 +87,14 @@ impl Progress {
                 }
             }
         }
+        if let Some(ref mut sink) = maybe_sink {
+            sink.progress(
+                &self.label,
+                self.processed_items,
+                self.total_items,
+                elapsed_seconds(self.started_at),
+            );
+        }
         None
     }
 
@@ -104,6 +112,10 @@ enum StackEntry {
 pub trait TimerSink {
     fn println(&mut self, line: String);
     fn reprintln(&mut self, line: String);
+    /// Called for every step of work when the total amount is known -- items processed or bytes
+    /// read. Lines are only printed occasionally, so this is the chance to stay responsive during
+    /// long operations. `elapsed` is seconds since the work started.
+    fn progress(&mut self, _label: &str, _processed: usize, _total: usize, _elapsed: f64) {}
 }
 
 /// Hierarchial magic
@@ -500,6 +512,17 @@ impl<'a> Read for Timer<'a> {
             );
         }
 
+        if file.processed_bytes < file.total_bytes && self.outermost_name != "throwaway" {
+            if let Some(ref mut sink) = self.sink {
+                sink.progress(
+                    &format!("Reading {}", file.path),
+                    file.processed_bytes,
+                    file.total_bytes,
+                    elapsed_seconds(file.started_at),
+                );
+            }
+        }
+
         if file.processed_bytes == file.total_bytes {
             let elapsed = elapsed_seconds(file.started_at);
             let line = format!(
@@ -1,5 +1,6 @@
 use std::collections::BTreeSet;
 use std::fmt::Write;
+use std::hash::Hasher;
 
 pub fn plain_list_names(names: BTreeSet<String>) -> String {
     let mut s = String::new();
@@ -47,3 +48,50 @@ pub fn basename<I: Into<String>>(path: I) -> String {
 pub fn parent_path(path: &str) -> String {
     format!("{}", std::path::Path::new(path).parent().unwrap().display())
 }
+
+/// The FNV-1a hash. It's not cryptographic, but unlike the standard library's hashers, it isn't
+/// randomly seeded, so it's stable between runs and builds.
+pub struct Fnv(u64);
+
+impl Fnv {
+    pub fn new() -> Fnv {
+        Fnv(0xcbf2_9ce4_8422_2325)
+    }
+}
+
+impl Default for Fnv {
+    fn default() -> Fnv {
+        Fnv::new()
+    }
+}
+
+impl Hasher for Fnv {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for b in bytes {
+            self.0 ^= u64::from(*b);
+            self.0 = self.0.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fnv() {
+        // Test vectors from http://www.isthe.com/chongo/src/fnv/test_fnv.c
+        let hash = |bytes: &[u8]| {
+            let mut hasher = Fnv::new();
+            hasher.write(bytes);
+            hasher.finish()
+        };
+        assert_eq!(0xcbf2_9ce4_8422_2325, hash(b""));
+        assert_eq!(0xaf63_dc4c_8601_ec8c, hash(b"a"));
+        assert_eq!(0x8594_4171_f739_67e8, hash(b"foobar"));
+    }
+}
@@ -4,12 +4,13 @@ use osm::{NodeID, OsmID, RelationID, WayID};
 
 use abstio::MapName;
 use abstutil::{retain_btreemap, Tags, Timer};
-use geom::{HashablePt2D, Polygon, Pt2D, Ring};
+use geom::{Distance, HashablePt2D, Polygon, Pt2D, Ring};
 use kml::{ExtraShape, ExtraShapes};
 use map_model::raw::{RawArea, RawBuilding, RawMap, RawParkingLot, RawRoad, RestrictionType};
 use map_model::{osm, Amenity, AreaType, Direction, DrivingSide, NamePerLanguage};
 
 use crate::osm_geom::{get_multipolygon_members, glue_multipolygon, multipoly_geometry};
+use crate::reader::Document;
 use crate::{transit, Options};
 
 pub struct OsmExtract {
@@ -41,12 +42,7 @@ pub fn extract_osm(map: &mut RawMap, opts: &Options, timer: &mut Timer) -> OsmEx
         }
     }
 
-    // TODO Hacks to override OSM data. There's no problem upstream, but we want to accomplish
-    // various things for A/B Street.
-    if let Some(way) = doc.ways.get_mut(&WayID(881403608)) {
-        // https://www.openstreetmap.org/way/881403608 is a roundabout that keeps causing gridlock
-        way.tags.insert("highway", "construction");
-    }
+    override_ways(&mut doc);
 
     if opts.clip.is_none() {
         // Use the boundary from .osm.
@@ -94,14 +90,8 @@ pub fn extract_osm(map: &mut RawMap, opts: &Options, timer: &mut Timer) -> OsmEx
 
         way.tags.insert(osm::OSM_WAY_ID, id.0.to_string());
 
-        if is_road(&mut way.tags, opts) {
-            // TODO Hardcoding these overrides. OSM is correct, these don't have
-            // sidewalks; there's a crosswalk mapped. But until we can snap sidewalks properly, do
-            // this to prevent the sidewalks from being disconnected.
-            if id == WayID(332060260) || id == WayID(332060236) {
-                way.tags.insert(osm::SIDEWALK, "right");
-            }
-
+        if is_road(&way.tags, opts) {
+            fill_in_road_tags(id, &mut way.tags, opts);
             out.roads.push((
                 id,
                 RawRoad {
@@ -150,6 +140,7 @@ pub fn extract_osm(map: &mut RawMap, opts: &Options, timer: &mut Timer) -> OsmEx
                     public_garage_name: None,
                     num_parking_spots: 0,
                     amenities: get_bldg_amenities(&way.tags),
+                    elevation: Distance::ZERO,
                     osm_tags: way.tags.clone(),
                 },
             );
@@ -268,6 +259,7 @@ pub fn extract_osm(map: &mut RawMap, opts: &Options, timer: &mut Timer) -> OsmEx
                             public_garage_name: None,
                             num_parking_spots: 0,
                             amenities: get_bldg_amenities(&rel.tags),
+                            elevation: Distance::ZERO,
                             osm_tags: rel.tags.clone(),
                         },
                     );
@@ -387,7 +379,16 @@ pub fn extract_osm(map: &mut RawMap, opts: &Options, timer: &mut Timer) -> OsmEx
     out
 }
 
-fn is_road(tags: &mut Tags, opts: &Options) -> bool {
+// TODO Hacks to override OSM data. There's no problem upstream, but we want to accomplish various
+// things for A/B Street.
+pub(crate) fn override_ways(doc: &mut Document) {
+    if let Some(way) = doc.ways.get_mut(&WayID(881403608)) {
+        // https://www.openstreetmap.org/way/881403608 is a roundabout that keeps causing gridlock
+        way.tags.insert("highway", "construction");
+    }
+}
+
+pub(crate) fn is_road(tags: &Tags, opts: &Options) -> bool {
     if tags.is("area", "yes") {
         return false;
     }
@@ -477,8 +478,11 @@ fn is_road(tags: &mut Tags, opts: &Options) -> bool {
         return false;
     }
 
-    // It's a road! Now fill in some possibly missing data.
+    true
+}
 
+/// For a way that `is_road`, fills in some possibly missing data.
+pub(crate) fn fill_in_road_tags(id: WayID, tags: &mut Tags, opts: &Options) {
     // If there's no parking data in OSM already, then assume no parking and mark that it's
     // inferred.
     if !tags.contains_key(osm::PARKING_LEFT)
@@ -533,7 +537,12 @@ fn is_road(tags: &mut Tags, opts: &Options) -> bool {
         }
     }
 
-    true
+    // TODO Hardcoding these overrides. OSM is correct, these don't have sidewalks; there's a
+    // crosswalk mapped. But until we can snap sidewalks properly, do this to prevent the sidewalks
+    // from being disconnected.
+    if id == WayID(332060260) || id == WayID(332060236) {
+        tags.insert(osm::SIDEWALK, "right");
+    }
 }
 
 fn is_bldg(tags: &Tags) -> bool {
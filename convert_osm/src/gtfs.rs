@@ -0,0 +1,309 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::File;
+
+use anyhow::Result;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+
+use abstutil::{Counter, MultiMap, Tags, Timer};
+use geom::{Distance, Duration, FindClosest, LonLat, PolyLine, Pt2D, Time};
+use map_model::osm::{NodeID, RelationID};
+use map_model::raw::{OriginalRoad, RawBusRoute, RawBusStop, RawMap};
+use map_model::{Direction, DrivingSide};
+
+// How far a stop can be from one side of a road and still be matched to it
+const MAX_STOP_DIST: Distance = Distance::const_meters(30.0);
+// Just used for matching stops to different sides of a road
+const DIRECTED_ROAD_THICKNESS: Distance = Distance::const_meters(2.5);
+
+/// Builds bus routes from a directory of unzipped GTFS files. Each direction of a route becomes
+/// one RawBusRoute, following the stops of its longest trip, and every trip of the most common
+/// service becomes one scheduled departure. Stops outside the map are dropped, keeping the longest
+/// stretch of the route that's inside.
+///
+/// GTFS stops and routes aren't OSM objects, so they get negative IDs.
+pub fn import(
+    map: &RawMap,
+    dir: &str,
+    driving_side: DrivingSide,
+    timer: &mut Timer,
+) -> Result<Vec<RawBusRoute>> {
+    timer.start("import GTFS");
+    let stops = match_stops(map, read_csv(format!("{}/stops.txt", dir))?, driving_side);
+    let route_records: Vec<RouteRecord> = read_csv(format!("{}/routes.txt", dir))?;
+    let trip_records: Vec<TripRecord> = read_csv(format!("{}/trips.txt", dir))?;
+
+    // Feeds cover different services, like weekdays and weekends. Just use the one with the most
+    // trips, which is hopefully a typical weekday.
+    let mut trips_per_service = Counter::new();
+    for trip in &trip_records {
+        trips_per_service.inc(trip.service_id.clone());
+    }
+    let (service, _) = trips_per_service
+        .highest_n(1)
+        .pop()
+        .ok_or_else(|| anyhow!("{} has no trips", dir))?;
+
+    let bus_routes: BTreeMap<String, RouteRecord> = route_records
+        .into_iter()
+        .filter(|r| r.route_type == 3 || (700..800).contains(&r.route_type))
+        .map(|r| (r.route_id.clone(), r))
+        .collect();
+    // (route, direction) to the trips
+    let mut trips_per_route: MultiMap<(String, usize), String> = MultiMap::new();
+    for trip in trip_records {
+        if trip.service_id == service && bus_routes.contains_key(&trip.route_id) {
+            trips_per_route.insert(
+                (trip.route_id, trip.direction_id.unwrap_or(0)),
+                trip.trip_id,
+            );
+        }
+    }
+    let all_trips: BTreeSet<&String> = trips_per_route.borrow().values().flatten().collect();
+
+    // Per trip, the stops in order and when the bus leaves each
+    let mut stop_times: BTreeMap<String, Vec<(usize, String, Time)>> = BTreeMap::new();
+    for rec in read_csv::<StopTimeRecord>(format!("{}/stop_times.txt", dir))? {
+        if !all_trips.contains(&rec.trip_id) {
+            continue;
+        }
+        let mut time = Time::parse(&rec.departure_time)?;
+        // Trips can run past midnight. The simulation only covers one day, so wrap around.
+        if time >= Time::START_OF_DAY + Duration::hours(24) {
+            time = time - Duration::hours(24);
+        }
+        stop_times
+            .entry(rec.trip_id)
+            .or_insert_with(Vec::new)
+            .push((rec.stop_sequence, rec.stop_id, time));
+    }
+    for list in stop_times.values_mut() {
+        list.sort_by_key(|(seq, _, _)| *seq);
+    }
+
+    let mut results = Vec::new();
+    for ((route_id, direction), trips) in trips_per_route.consume() {
+        let route = &bus_routes[&route_id];
+        let name = format!("{} (direction {})", route.describe(), direction);
+        match make_route(
+            route,
+            &trips,
+            &stops,
+            &stop_times,
+            RelationID(-1 - results.len() as i64),
+        ) {
+            Ok(r) => {
+                results.push(r);
+            }
+            Err(err) => {
+                warn!("Skipping GTFS route {}: {}", name, err);
+            }
+        }
+    }
+    timer.stop("import GTFS");
+    Ok(results)
+}
+
+fn make_route(
+    route: &RouteRecord,
+    trips: &BTreeSet<String>,
+    stops: &BTreeMap<String, MatchedStop>,
+    stop_times: &BTreeMap<String, Vec<(usize, String, Time)>>,
+    id: RelationID,
+) -> Result<RawBusRoute> {
+    // Trips on the same route can skip stops or run shorter, so follow the longest one. Trips are
+    // sorted, so ties are broken consistently.
+    let mut longest: Option<&Vec<(usize, String, Time)>> = None;
+    for trip in trips {
+        if let Some(list) = stop_times.get(trip) {
+            if longest.map(|x| list.len() > x.len()).unwrap_or(true) {
+                longest = Some(list);
+            }
+        }
+    }
+    let longest = longest.ok_or_else(|| anyhow!("no trips have stop times"))?;
+
+    // Keep the longest stretch of stops inside the map
+    let mut keep: Vec<&String> = Vec::new();
+    let mut current: Vec<&String> = Vec::new();
+    for (_, stop_id, _) in longest {
+        if stops.contains_key(stop_id) {
+            if current.last() != Some(&stop_id) {
+                current.push(stop_id);
+            }
+        } else {
+            current.clear();
+        }
+        if current.len() > keep.len() {
+            keep = current.clone();
+        }
+    }
+    if keep.len() < 2 {
+        bail!("only {} stops are inside the map", keep.len());
+    }
+
+    // Buses start from the first stop that's kept
+    let mut spawn_times: Vec<Time> = trips
+        .iter()
+        .filter_map(|trip| {
+            stop_times
+                .get(trip)?
+                .iter()
+                .find(|(_, stop_id, _)| stop_id == keep[0])
+                .map(|(_, _, time)| *time)
+        })
+        .collect();
+    spawn_times.sort();
+    spawn_times.dedup();
+    if spawn_times.is_empty() {
+        bail!("no trips visit the first stop inside the map");
+    }
+
+    let raw_stops: Vec<RawBusStop> = keep
+        .iter()
+        .map(|stop_id| {
+            let stop = &stops[*stop_id];
+            RawBusStop {
+                name: stop.name.clone(),
+                vehicle_pos: (stop.node, stop.pt),
+                matched_road: Some(stop.road),
+                ped_pos: None,
+                osm_tags: Tags::empty(),
+            }
+        })
+        .collect();
+    let all_pts = raw_stops.iter().map(|s| s.vehicle_pos).collect();
+    Ok(RawBusRoute {
+        full_name: route.describe(),
+        short_name: route
+            .route_short_name
+            .clone()
+            .unwrap_or_else(|| route.describe()),
+        osm_rel_id: id,
+        gtfs_trip_marker: None,
+        is_bus: true,
+        stops: raw_stops,
+        border_start: None,
+        border_end: None,
+        all_pts,
+        spawn_times: Some(spawn_times),
+    })
+}
+
+struct MatchedStop {
+    name: String,
+    node: NodeID,
+    /// Where the bus stops along its side of the road
+    pt: Pt2D,
+    road: (OriginalRoad, Direction),
+}
+
+// Match stops inside the map to the nearest side of a road.
+fn match_stops(
+    map: &RawMap,
+    records: Vec<StopRecord>,
+    driving_side: DrivingSide,
+) -> BTreeMap<String, MatchedStop> {
+    // Match to the nearest road + side (true for the right)
+    let mut closest: FindClosest<(OriginalRoad, bool)> =
+        FindClosest::new(&map.gps_bounds.to_bounds());
+    for (id, r) in &map.roads {
+        if r.is_light_rail() || r.is_footway() {
+            continue;
+        }
+        let center = PolyLine::must_new(r.center_points.clone());
+        closest.add(
+            (*id, true),
+            center.must_shift_right(DIRECTED_ROAD_THICKNESS).points(),
+        );
+        closest.add(
+            (*id, false),
+            center.must_shift_left(DIRECTED_ROAD_THICKNESS).points(),
+        );
+    }
+
+    let mut stops = BTreeMap::new();
+    for (idx, rec) in records.into_iter().enumerate() {
+        // Skip stations and entrances; only stops and platforms are served directly
+        if rec.location_type.unwrap_or(0) != 0 {
+            continue;
+        }
+        let pt = LonLat::new(rec.stop_lon, rec.stop_lat).to_pt(&map.gps_bounds);
+        if !map.boundary_polygon.contains_pt(pt) {
+            continue;
+        }
+        if let Some(((road, right_side), pt)) = closest.closest_pt(pt, MAX_STOP_DIST) {
+            // Buses can only stop going one way on a one-way road, whichever side the stop is on
+            let dir = if map.roads[&road].osm_tags.is("oneway", "yes")
+                || right_side == (driving_side == DrivingSide::Right)
+            {
+                Direction::Fwd
+            } else {
+                Direction::Back
+            };
+            stops.insert(
+                rec.stop_id,
+                MatchedStop {
+                    name: rec.stop_name,
+                    node: NodeID(-1 - idx as i64),
+                    pt,
+                    road: (road, dir),
+                },
+            );
+        } else {
+            warn!("GTFS stop {} isn't near any road", rec.stop_id);
+        }
+    }
+    stops
+}
+
+fn read_csv<T: DeserializeOwned>(path: String) -> Result<Vec<T>> {
+    let file = File::open(&path).map_err(|err| anyhow!("can't open {}: {}", path, err))?;
+    let mut results = Vec::new();
+    for rec in csv::Reader::from_reader(file).deserialize() {
+        results.push(rec?);
+    }
+    Ok(results)
+}
+
+#[derive(Deserialize)]
+struct StopRecord {
+    stop_id: String,
+    stop_name: String,
+    stop_lat: f64,
+    stop_lon: f64,
+    location_type: Option<usize>,
+}
+
+#[derive(Deserialize)]
+struct RouteRecord {
+    route_id: String,
+    route_short_name: Option<String>,
+    route_long_name: Option<String>,
+    route_type: usize,
+}
+
+impl RouteRecord {
+    fn describe(&self) -> String {
+        self.route_long_name
+            .clone()
+            .or_else(|| self.route_short_name.clone())
+            .unwrap_or_else(|| self.route_id.clone())
+    }
+}
+
+#[derive(Deserialize)]
+struct TripRecord {
+    route_id: String,
+    service_id: String,
+    trip_id: String,
+    direction_id: Option<usize>,
+}
+
+#[derive(Deserialize)]
+struct StopTimeRecord {
+    trip_id: String,
+    departure_time: String,
+    stop_id: String,
+    stop_sequence: usize,
+}
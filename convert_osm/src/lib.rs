@@ -6,7 +6,7 @@ extern crate log;
 use anyhow::Result;
 
 use abstio::MapName;
-use abstutil::{Tags, Timer};
+use abstutil::{prettyprint_usize, Tags, Timer};
 use geom::{Distance, FindClosest, GPSBounds, LonLat, Pt2D, Ring};
 use map_model::raw::RawMap;
 use map_model::{osm, raw, Amenity, MapConfig};
@@ -14,6 +14,7 @@ use serde::{Deserialize, Serialize};
 
 mod clip;
 mod extract;
+mod gtfs;
 pub mod osm_geom;
 mod parking;
 pub mod reader;
@@ -21,6 +22,9 @@ mod snappy;
 mod split_ways;
 mod srtm;
 mod transit;
+mod update;
+
+pub use self::update::{update, Update};
 
 pub struct Options {
     pub osm_input: String,
@@ -33,13 +37,16 @@ pub struct Options {
     pub onstreet_parking: OnstreetParking,
     pub public_offstreet_parking: PublicOffstreetParking,
     pub private_offstreet_parking: PrivateOffstreetParking,
-    /// If provided, pull elevation data from this SRTM file. The SRTM parser is incorrect, so the
-    /// results will be nonsense.
+    /// If provided, pull elevation data from this SRTM .hgt file, or a directory of them.
+    /// Intersections and buildings outside the tiles are left at 0m.
     pub elevation: Option<String>,
     /// OSM railway=rail will be included as light rail if so. Cosmetic only.
     pub include_railroads: bool,
     /// If provided, read polygons from this GeoJSON file and add them to the RawMap as buildings.
     pub extra_buildings: Option<String>,
+    /// If provided, build bus routes and their schedules from this directory of unzipped GTFS
+    /// files, instead of from OSM relations.
+    pub gtfs: Option<String>,
 }
 
 /// What roads will have on-street parking lanes? Data from
@@ -98,7 +105,7 @@ pub fn convert(opts: Options, timer: &mut abstutil::Timer) -> RawMap {
     let mut routes = Vec::new();
     for route in all_routes {
         let name = format!("{} ({})", route.osm_rel_id, route.full_name);
-        match transit::snap_bus_stops(route, &mut map, &pt_to_road) {
+        match transit::snap_bus_stops(route, &mut map, &pt_to_road, opts.map_config.driving_side) {
             Ok(r) => {
                 routes.push(r);
             }
@@ -109,6 +116,20 @@ pub fn convert(opts: Options, timer: &mut abstutil::Timer) -> RawMap {
     }
     map.bus_routes = routes;
 
+    if let Some(ref path) = opts.gtfs {
+        // GTFS is usually more complete than OSM and has real schedules, so it replaces all bus
+        // routes from OSM. Light rail still comes from OSM.
+        map.bus_routes.retain(|r| !r.is_bus);
+        match gtfs::import(&map, path, opts.map_config.driving_side, timer) {
+            Ok(routes) => {
+                map.bus_routes.extend(routes);
+            }
+            Err(err) => {
+                error!("Couldn't import GTFS from {}: {}", path, err);
+            }
+        }
+    }
+
     use_amenities(&mut map, amenities, timer);
 
     parking::apply_parking(&mut map, &opts, timer);
@@ -145,17 +166,52 @@ fn use_amenities(map: &mut RawMap, amenities: Vec<(Pt2D, Amenity)>, timer: &mut
 }
 
 fn use_elevation(map: &mut RawMap, path: &str, timer: &mut Timer) {
-    timer.start("apply elevation data to intersections");
-    let elevation = srtm::Elevation::load(path).unwrap();
+    timer.start("apply elevation data to intersections and buildings");
+    let elevation = match srtm::Elevation::load(path) {
+        Ok(e) => e,
+        Err(err) => {
+            warn!(
+                "Couldn't load elevation data from {}, so everything will be flat: {}",
+                path, err
+            );
+            timer.stop("apply elevation data to intersections and buildings");
+            return;
+        }
+    };
+    // Points outside of the tiles stay at 0m
+    let mut missing = 0;
     for i in map.intersections.values_mut() {
         // TODO Not sure why, but I've seen nodes from South Carolina wind up in the updated
         // Seattle extract. And I think there's a bug with clipping, because they survive to this
         // point. O_O
         if map.boundary_polygon.contains_pt(i.point) {
-            i.elevation = elevation.get(i.point.to_gps(&map.gps_bounds));
+            match elevation.get(i.point.to_gps(&map.gps_bounds)) {
+                Some(e) => {
+                    i.elevation = e;
+                }
+                None => {
+                    missing += 1;
+                }
+            }
+        }
+    }
+    for b in map.buildings.values_mut() {
+        match elevation.get(b.polygon.center().to_gps(&map.gps_bounds)) {
+            Some(e) => {
+                b.elevation = e;
+            }
+            None => {
+                missing += 1;
+            }
         }
     }
-    timer.stop("apply elevation data to intersections");
+    if missing > 0 {
+        warn!(
+            "No elevation data for {} intersections and buildings, so they're at 0m",
+            prettyprint_usize(missing)
+        );
+    }
+    timer.stop("apply elevation data to intersections and buildings");
 }
 
 fn add_extra_buildings(map: &mut RawMap, path: &str) -> Result<()> {
@@ -196,6 +252,7 @@ fn add_extra_buildings(map: &mut RawMap, path: &str) -> Result<()> {
                 public_garage_name: None,
                 num_parking_spots: 1,
                 amenities: Vec::new(),
+                elevation: Distance::ZERO,
             },
         );
         // We could use new_osm_way_id, but faster to just assume we're the only place introducing
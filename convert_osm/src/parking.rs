@@ -2,7 +2,7 @@ use abstutil::Timer;
 use geom::{Distance, FindClosest, PolyLine};
 use kml::ExtraShapes;
 use map_model::osm;
-use map_model::raw::{OriginalRoad, RawMap};
+use map_model::raw::{OriginalRoad, RawMap, RawRoad};
 
 use crate::{OnstreetParking, Options, PrivateOffstreetParking, PublicOffstreetParking};
 
@@ -16,24 +16,8 @@ pub fn apply_parking(map: &mut RawMap, opts: &Options, timer: &mut Timer) {
             use_parking_hints(map, path.clone(), timer);
         }
         OnstreetParking::SomeAdditionalWhereNoData { pct } => {
-            let pct = pct as i64;
             for (id, r) in map.roads.iter_mut() {
-                // The 20m minimum is a rough heuristic.
-                if r.osm_tags.contains_key(osm::INFERRED_PARKING)
-                    && r.osm_tags
-                        .is_any(osm::HIGHWAY, vec!["residential", "tertiary"])
-                    && !r.osm_tags.is("foot", "no")
-                    && id.osm_way_id.0 % 100 <= pct
-                    && PolyLine::unchecked_new(r.center_points.clone()).length()
-                        >= Distance::meters(20.0)
-                {
-                    if r.osm_tags.is("oneway", "yes") {
-                        r.osm_tags.remove(osm::PARKING_BOTH);
-                        r.osm_tags.insert(osm::PARKING_RIGHT, "parallel");
-                    } else {
-                        r.osm_tags.insert(osm::PARKING_BOTH, "parallel");
-                    }
-                }
+                infer_some_parking(*id, r, pct);
             }
         }
     }
@@ -46,6 +30,27 @@ pub fn apply_parking(map: &mut RawMap, opts: &Options, timer: &mut Timer) {
     apply_private_offstreet_parking(map, &opts.private_offstreet_parking);
 }
 
+/// For OnstreetParking::SomeAdditionalWhereNoData, maybe add parking to one road. Which roads get
+/// it only depends on the road itself.
+pub(crate) fn infer_some_parking(id: OriginalRoad, r: &mut RawRoad, pct: usize) {
+    // The 20m minimum is a rough heuristic.
+    if r.osm_tags.contains_key(osm::INFERRED_PARKING)
+        && r.osm_tags
+            .is_any(osm::HIGHWAY, vec!["residential", "tertiary"])
+        && !r.osm_tags.is("foot", "no")
+        && id.osm_way_id.0 % 100 <= pct as i64
+        && PolyLine::unchecked_new(r.center_points.clone()).length() >= Distance::meters(20.0)
+    {
+        if r.osm_tags.is("oneway", "yes") {
+            r.osm_tags.remove(osm::PARKING_BOTH);
+            r.osm_tags.insert(osm::PARKING_RIGHT, "parallel");
+        } else {
+            r.osm_tags.insert(osm::PARKING_BOTH, "parallel");
+        }
+        r.osm_tags.insert(osm::PARKING_SOURCE, "guessed");
+    }
+}
+
 fn use_parking_hints(map: &mut RawMap, path: String, timer: &mut Timer) {
     timer.start("apply parking hints");
     let shapes: ExtraShapes = abstio::read_binary(path, timer);
@@ -131,6 +136,7 @@ fn use_parking_hints(map: &mut RawMap, path: String, timer: &mut Timer) {
                     "no_parking"
                 },
             );
+            tags.insert(osm::PARKING_SOURCE, "blockface");
 
             // Maybe fold back into "both"
             if tags.contains_key(osm::PARKING_LEFT)
@@ -1,52 +1,219 @@
-use std::fs::File;
+use std::collections::BTreeMap;
+use std::path::Path;
 
 use anyhow::Result;
 use byteorder::{BigEndian, ReadBytesExt};
 
 use geom::{Distance, LonLat};
 
-// TODO Make sure this actually works, and link to references describing the format.
-// - It's VERY wrong! Look at 19th and Boyer, and Delmar.
-// TODO Use http://gis.ess.washington.edu/data/raster/tenmeter/byquad/seattle/index.html or
-// something else instead?
+// Reads SRTM .hgt tiles, as described in
+// https://dds.cr.usgs.gov/srtm/version2_1/Documentation/SRTM_Topo.pdf. Each tile covers one degree
+// of longitude and latitude, named after its southwest corner, like N47W122.hgt. Samples are
+// big-endian i16 meters, in rows from north to south. The rows and columns on the edges are
+// duplicated in the neighboring tiles.
 
-// Assuming the 3-second arcs
-const GRID_DIM: usize = 1201;
+// Marks samples with no data
+const VOID: i16 = -32768;
 
 pub struct Elevation {
-    lon_offset: f64,
-    lat_offset: f64,
+    /// Keyed by the (longitude, latitude) of the southwest corner
+    tiles: BTreeMap<(i64, i64), Tile>,
+}
+
+struct Tile {
+    /// 1201 for 3 arc-second data, 3601 for 1 arc-second
+    dim: usize,
     data: Vec<i16>,
 }
 
 impl Elevation {
+    /// Loads one .hgt file, or every .hgt file in a directory.
     pub fn load(path: &str) -> Result<Elevation> {
-        println!("Reading elevation data from {}", path);
-        let mut f = File::open(path)?;
-
-        let mut e = Elevation {
-            // TODO dont hardcode
-            lon_offset: -122.0,
-            lat_offset: 47.0,
-            data: Vec::with_capacity(GRID_DIM.pow(2)),
+        let paths = if Path::new(path).is_dir() {
+            abstio::list_dir(path.to_string())
+                .into_iter()
+                .filter(|p| p.ends_with(".hgt"))
+                .collect()
+        } else {
+            vec![path.to_string()]
         };
-        // TODO off by one?
-        for _ in 0..GRID_DIM.pow(2) {
-            e.data.push(f.read_i16::<BigEndian>()?);
+
+        let mut tiles = BTreeMap::new();
+        for path in paths {
+            info!("Reading elevation data from {}", path);
+            tiles.insert(
+                parse_tile_name(&abstutil::basename(&path))?,
+                Tile::load(&path)?,
+            );
+        }
+        if tiles.is_empty() {
+            bail!("no .hgt files in {}", path);
+        }
+        Ok(Elevation { tiles })
+    }
+
+    /// Interpolates between the nearest samples. Returns None if no tile covers the point, or
+    /// the tile has no data there.
+    pub fn get(&self, pt: LonLat) -> Option<Distance> {
+        let lon = pt.x().floor();
+        let lat = pt.y().floor();
+        let tile = self.tiles.get(&(lon as i64, lat as i64))?;
+        tile.get(pt.x() - lon, pt.y() - lat).map(Distance::meters)
+    }
+}
+
+impl Tile {
+    fn load(path: &str) -> Result<Tile> {
+        let bytes = abstio::slurp_file(path)?;
+        Tile::parse(&bytes).map_err(|err| anyhow!("{}: {}", path, err))
+    }
+
+    fn parse(bytes: &[u8]) -> Result<Tile> {
+        let num_samples = bytes.len() / 2;
+        let dim = (num_samples as f64).sqrt() as usize;
+        if dim * dim * 2 != bytes.len() || dim < 2 {
+            bail!("{} bytes isn't a square grid", bytes.len());
         }
-        Ok(e)
-    }
-
-    pub fn get(&self, pt: LonLat) -> Distance {
-        // TODO assert the (lon, lat) match the offsets
-        // TODO not tons of confidence in any of this.
-        // TODO interpolate from the 4 matching tiles?
-        let x = ((pt.x() - self.lon_offset).abs() * (GRID_DIM as f64)) as usize;
-        let y = ((pt.y() - self.lat_offset).abs() * (GRID_DIM as f64)) as usize;
-        let i = x + (y * GRID_DIM);
-        if i >= self.data.len() {
-            panic!("srtm lookup for out-of-bounds {}", pt);
+
+        let mut reader = bytes;
+        let mut data = Vec::with_capacity(num_samples);
+        for _ in 0..num_samples {
+            data.push(reader.read_i16::<BigEndian>()?);
+        }
+        Ok(Tile { dim, data })
+    }
+
+    /// Bilinear interpolation between the 4 samples surrounding a point, given as a fraction of
+    /// the tile from the southwest corner. Since the edges are shared with neighboring tiles, a
+    /// point right on the boundary gets the same answer from either tile.
+    fn get(&self, dx: f64, dy: f64) -> Option<f64> {
+        let scale = (self.dim - 1) as f64;
+        let x = dx * scale;
+        let y = (1.0 - dy) * scale;
+        let col = (x.floor() as usize).min(self.dim - 2);
+        let row = (y.floor() as usize).min(self.dim - 2);
+        let fx = x - col as f64;
+        let fy = y - row as f64;
+
+        let north = self.sample(col, row)? * (1.0 - fx) + self.sample(col + 1, row)? * fx;
+        let south = self.sample(col, row + 1)? * (1.0 - fx) + self.sample(col + 1, row + 1)? * fx;
+        Some(north * (1.0 - fy) + south * fy)
+    }
+
+    fn sample(&self, col: usize, row: usize) -> Option<f64> {
+        let value = self.data[row * self.dim + col];
+        if value == VOID {
+            None
+        } else {
+            Some(f64::from(value))
         }
-        Distance::meters(f64::from(self.data[i]))
+    }
+}
+
+// Like "N47W122", the southwest corner of the tile
+fn parse_tile_name(name: &str) -> Result<(i64, i64)> {
+    let name = name.to_ascii_uppercase();
+    let lon_start = match name.find(|c: char| c == 'E' || c == 'W') {
+        Some(idx) if idx >= 2 => idx,
+        _ => bail!("{} isn't named like N47W122", name),
+    };
+    let lat: i64 = name[1..lon_start].parse()?;
+    let lon: i64 = name[lon_start + 1..].parse()?;
+    let lat = match &name[0..1] {
+        "N" => lat,
+        "S" => -lat,
+        _ => bail!("{} isn't named like N47W122", name),
+    };
+    let lon = if &name[lon_start..lon_start + 1] == "E" {
+        lon
+    } else {
+        -lon
+    };
+    Ok((lon, lat))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A 3x3 tile, in rows from north to south
+    fn tile(data: Vec<i16>) -> Tile {
+        Tile { dim: 3, data }
+    }
+
+    #[test]
+    fn tile_names() {
+        assert_eq!((-122, 47), parse_tile_name("N47W122").unwrap());
+        assert_eq!((151, -33), parse_tile_name("s33e151").unwrap());
+        assert_eq!((0, 0), parse_tile_name("N00E000").unwrap());
+        assert!(parse_tile_name("N47").is_err());
+        assert!(parse_tile_name("X47W122").is_err());
+        assert!(parse_tile_name("NxxW122").is_err());
+    }
+
+    #[test]
+    fn parse_samples() {
+        let mut bytes = Vec::new();
+        for x in &[1_i16, -2, 300, VOID] {
+            bytes.extend_from_slice(&x.to_be_bytes());
+        }
+        let t = Tile::parse(&bytes).unwrap();
+        assert_eq!(2, t.dim);
+        assert_eq!(vec![1, -2, 300, VOID], t.data);
+
+        // Not square, or too small to interpolate in
+        assert!(Tile::parse(&bytes[0..6]).is_err());
+        assert!(Tile::parse(&bytes[0..2]).is_err());
+        assert!(Tile::parse(&[]).is_err());
+    }
+
+    #[test]
+    fn interpolation() {
+        let t = tile(vec![0, 10, 20, 30, 40, 50, 60, 70, 80]);
+        // The corners
+        assert_eq!(Some(0.0), t.get(0.0, 1.0));
+        assert_eq!(Some(20.0), t.get(1.0, 1.0));
+        assert_eq!(Some(60.0), t.get(0.0, 0.0));
+        assert_eq!(Some(80.0), t.get(1.0, 0.0));
+        // Right on a sample
+        assert_eq!(Some(40.0), t.get(0.5, 0.5));
+        // Between samples
+        assert_eq!(Some(5.0), t.get(0.25, 1.0));
+        assert_eq!(Some(15.0), t.get(0.0, 0.75));
+        assert_eq!(Some(20.0), t.get(0.25, 0.75));
+    }
+
+    #[test]
+    fn missing_data() {
+        let t = tile(vec![0, 10, 20, 30, 40, 50, 60, 70, VOID]);
+        // Nothing in the cell touching the void sample has an answer, even right on a valid sample
+        assert_eq!(None, t.get(1.0, 0.0));
+        assert_eq!(None, t.get(0.75, 0.25));
+        assert_eq!(None, t.get(0.5, 0.5));
+        // Other cells are fine
+        assert_eq!(Some(20.0), t.get(0.25, 0.75));
+    }
+
+    #[test]
+    fn tile_boundaries() {
+        // The east column of the western tile is the west column of the eastern tile
+        let mut tiles = BTreeMap::new();
+        tiles.insert((-122, 47), tile(vec![0, 0, 10, 0, 0, 20, 0, 0, 30]));
+        tiles.insert((-121, 47), tile(vec![10, 0, 0, 20, 0, 0, 30, 0, 0]));
+        let elevation = Elevation { tiles };
+
+        // Right on the shared edge, the eastern tile answers
+        let pt = LonLat::new(-121.0, 47.5);
+        assert_eq!(Some(Distance::meters(20.0)), elevation.get(pt));
+        // And the western tile agrees from its side
+        assert_eq!(Some(20.0), elevation.tiles[&(-122, 47)].get(1.0, 0.5));
+        // Approaching the edge from either side converges on the same value
+        let west = elevation.get(LonLat::new(-121.000001, 47.5)).unwrap();
+        let east = elevation.get(LonLat::new(-120.999999, 47.5)).unwrap();
+        assert!((west - east).abs() < Distance::meters(0.01));
+
+        // Nothing covers other places
+        assert_eq!(None, elevation.get(LonLat::new(-123.5, 47.5)));
+        assert_eq!(None, elevation.get(LonLat::new(-121.5, 48.5)));
     }
 }
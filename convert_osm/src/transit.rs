@@ -5,7 +5,7 @@ use anyhow::Result;
 use geom::{HashablePt2D, Polygon, Pt2D};
 use map_model::osm::{NodeID, OsmID, RelationID, WayID};
 use map_model::raw::{OriginalRoad, RawBusRoute, RawBusStop, RawMap};
-use map_model::{osm, Direction};
+use map_model::{osm, Direction, DrivingSide};
 
 use crate::reader::{Document, Relation};
 
@@ -53,10 +53,11 @@ pub fn extract_route(
                     vehicle_pos: (*n, node.pt),
                     matched_road: None,
                     ped_pos: None,
+                    osm_tags: node.tags.clone(),
                 });
             }
         } else if role == "platform" {
-            let (platform_name, pt) = match member {
+            let (platform_name, pt, tags) = match member {
                 OsmID::Node(n) => {
                     let node = &doc.nodes[n];
                     (
@@ -65,6 +66,7 @@ pub fn extract_route(
                             .cloned()
                             .unwrap_or_else(|| format!("stop #{}", platforms.len() + 1)),
                         node.pt,
+                        &node.tags,
                     )
                 }
                 OsmID::Way(w) => {
@@ -75,18 +77,25 @@ pub fn extract_route(
                             .cloned()
                             .unwrap_or_else(|| format!("stop #{}", platforms.len() + 1)),
                         Pt2D::center(&way.pts),
+                        &way.tags,
                     )
                 }
                 _ => continue,
             };
-            platforms.insert(platform_name, pt);
+            platforms.insert(platform_name, (pt, tags));
         } else if let OsmID::Way(w) = member {
             all_ways.push(*w);
         }
     }
     for stop in &mut stops {
-        if let Some(pt) = platforms.remove(&stop.name) {
+        if let Some((pt, tags)) = platforms.remove(&stop.name) {
             stop.ped_pos = Some(pt);
+            // Things like shelters are often only tagged on the platform
+            for (k, v) in tags.inner() {
+                if !stop.osm_tags.contains_key(k) {
+                    stop.osm_tags.insert(k, v);
+                }
+            }
         }
     }
 
@@ -139,6 +148,7 @@ pub fn extract_route(
         border_start: None,
         border_end: None,
         all_pts,
+        spawn_times: None,
     })
 }
 
@@ -199,6 +209,7 @@ pub fn snap_bus_stops(
     mut route: RawBusRoute,
     raw: &mut RawMap,
     pt_to_road: &HashMap<HashablePt2D, OriginalRoad>,
+    driving_side: DrivingSide,
 ) -> Result<RawBusRoute> {
     // TODO RawBusStop should have an osm_node_id()
 
@@ -291,19 +302,16 @@ pub fn snap_bus_stops(
             .ok_or_else(|| anyhow!("{} isn't an extracted road", road))?
             .osm_tags;
         if tags.is(osm::INFERRED_SIDEWALKS, "true") {
+            // The bus stops along the curb on the driving side
+            let side = if (dir == Direction::Fwd) == (driving_side == DrivingSide::Right) {
+                "right"
+            } else {
+                "left"
+            };
             let current = tags.get(osm::SIDEWALK).unwrap();
             if current == "none" {
-                tags.insert(
-                    osm::SIDEWALK,
-                    if dir == Direction::Fwd {
-                        "right"
-                    } else {
-                        "left"
-                    },
-                );
-            } else if current == "right" && dir == Direction::Back {
-                tags.insert(osm::SIDEWALK, "both");
-            } else if current == "left" && dir == Direction::Fwd {
+                tags.insert(osm::SIDEWALK, side);
+            } else if (current == "right" || current == "left") && current != side {
                 tags.insert(osm::SIDEWALK, "both");
             } else {
                 continue;
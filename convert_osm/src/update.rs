@@ -0,0 +1,171 @@
+use std::collections::BTreeSet;
+
+use anyhow::Result;
+
+use abstutil::Timer;
+use map_model::osm::{self, WayID};
+use map_model::raw::RawMap;
+
+use crate::extract::{fill_in_road_tags, is_road, override_ways};
+use crate::parking::infer_some_parking;
+use crate::reader::{read, Document};
+use crate::{OnstreetParking, Options};
+
+/// If more ways than this changed, it's probably faster to just rebuild everything.
+const MAX_PATCHED_WAYS: usize = 100;
+
+/// What happened when updating a RawMap from a newer OSM extract.
+pub enum Update {
+    /// The new extract has the same data; nothing was touched.
+    Unchanged,
+    /// Only the tags of these ways changed, and the roads made from them were patched in place.
+    PatchedRoads(BTreeSet<WayID>),
+    /// Something changed that can't be patched, so the whole RawMap has to be regenerated. The
+    /// RawMap wasn't modified.
+    NeedsFullRebuild(String),
+}
+
+/// Compares `opts.osm_input` against `old_osm_input`, which `map` was originally built from. If
+/// only the tags of a few roads changed, fixes up those roads in `map`. Anything touching geometry,
+/// intersections, buildings, or relations needs a full rebuild.
+pub fn update(
+    map: &mut RawMap,
+    old_osm_input: &str,
+    opts: &Options,
+    timer: &mut Timer,
+) -> Result<Update> {
+    let mut old = read(old_osm_input, &map.gps_bounds, timer)?;
+    let mut new = read(&opts.osm_input, &map.gps_bounds, timer)?;
+    override_ways(&mut old);
+    override_ways(&mut new);
+
+    let changed_ways = match diff(&old, &new, opts) {
+        Ok(ways) => ways,
+        Err(reason) => {
+            return Ok(Update::NeedsFullRebuild(reason));
+        }
+    };
+    if changed_ways.is_empty() {
+        return Ok(Update::Unchanged);
+    }
+
+    // Blockface hints are matched against every road at once, so one road changing could shift
+    // what its neighbors get.
+    if let OnstreetParking::Blockface(_) = opts.onstreet_parking {
+        return Ok(Update::NeedsFullRebuild(
+            "parking hints from Blockface have to be matched against the whole map".to_string(),
+        ));
+    }
+    // Roads near bus stops might have been given a sidewalk that the tags don't say.
+    for route in &map.bus_routes {
+        for stop in &route.stops {
+            if let Some((r, _)) = stop.matched_road {
+                if changed_ways.contains(&r.osm_way_id) {
+                    return Ok(Update::NeedsFullRebuild(format!(
+                        "{} has a bus stop on it",
+                        r.osm_way_id
+                    )));
+                }
+            }
+        }
+    }
+
+    timer.start_iter("patch roads", map.roads.len());
+    for (id, r) in map.roads.iter_mut() {
+        timer.next();
+        if !changed_ways.contains(&id.osm_way_id) {
+            continue;
+        }
+        let mut tags = new.ways[&id.osm_way_id].tags.clone();
+        tags.insert(osm::OSM_WAY_ID, id.osm_way_id.0.to_string());
+        fill_in_road_tags(id.osm_way_id, &mut tags, opts);
+        // Splitting the way marked which pieces touch its original endpoints
+        for key in [osm::ENDPT_FWD, osm::ENDPT_BACK].iter() {
+            if let Some(value) = r.osm_tags.get(key) {
+                tags.insert(*key, value.clone());
+            }
+        }
+        r.osm_tags = tags;
+        if let OnstreetParking::SomeAdditionalWhereNoData { pct } = opts.onstreet_parking {
+            infer_some_parking(*id, r, pct);
+        }
+    }
+
+    Ok(Update::PatchedRoads(changed_ways))
+}
+
+// Returns the ways that're roads before and after, and only have different tags. Anything else
+// that changed is described in the error.
+fn diff(old: &Document, new: &Document, opts: &Options) -> Result<BTreeSet<WayID>, String> {
+    if old.nodes.len() != new.nodes.len() {
+        return Err(format!(
+            "the number of nodes changed from {} to {}",
+            old.nodes.len(),
+            new.nodes.len()
+        ));
+    }
+    for (id, node) in &old.nodes {
+        let other = new
+            .nodes
+            .get(id)
+            .ok_or_else(|| format!("{} was deleted", id))?;
+        // Nodes become intersections, traffic signals, amenities, and bus stops
+        if node.pt != other.pt || node.tags != other.tags {
+            return Err(format!("{} changed", id));
+        }
+    }
+
+    if old.relations.len() != new.relations.len() {
+        return Err(format!(
+            "the number of relations changed from {} to {}",
+            old.relations.len(),
+            new.relations.len()
+        ));
+    }
+    for (id, rel) in &old.relations {
+        let other = new
+            .relations
+            .get(id)
+            .ok_or_else(|| format!("{} was deleted", id))?;
+        if rel.tags != other.tags || rel.members != other.members {
+            return Err(format!("{} changed", id));
+        }
+    }
+
+    if old.ways.len() != new.ways.len() {
+        return Err(format!(
+            "the number of ways changed from {} to {}",
+            old.ways.len(),
+            new.ways.len()
+        ));
+    }
+    let mut changed = BTreeSet::new();
+    for (id, way) in &old.ways {
+        let other = new
+            .ways
+            .get(id)
+            .ok_or_else(|| format!("{} was deleted", id))?;
+        if way.nodes != other.nodes {
+            return Err(format!("the nodes of {} changed", id));
+        }
+        if way.tags == other.tags {
+            continue;
+        }
+        // Only roads can be patched. Buildings, areas, and so on are matched against other things.
+        if !is_road(&way.tags, opts) || !is_road(&other.tags, opts) {
+            return Err(format!(
+                "the tags of {} changed, and it isn't a road both before and after",
+                id
+            ));
+        }
+        changed.insert(*id);
+    }
+    if changed.len() > MAX_PATCHED_WAYS {
+        return Err(format!(
+            "{} roads changed, more than the {} worth patching",
+            changed.len(),
+            MAX_PATCHED_WAYS
+        ));
+    }
+    Ok(changed)
+}
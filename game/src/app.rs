@@ -1,28 +1,41 @@
 use std::cell::RefCell;
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
 
 use anyhow::Result;
 use maplit::btreemap;
 use rand::seq::SliceRandom;
 
 use abstio::MapName;
-use abstutil::{Tags, Timer};
-use geom::{Bounds, Circle, Distance, Duration, LonLat, Pt2D, Ring, Time};
+use abstutil::{Counter, Tags, Timer};
+use geom::{Bounds, Circle, Distance, Duration, LonLat, Pt2D, Ring, Speed, Time};
 use map_gui::colors::ColorScheme;
 use map_gui::options::Options;
 use map_gui::render::{unzoomed_agent_radius, AgentCache, DrawMap, DrawOptions, Renderable};
 use map_gui::tools::CameraState;
 use map_gui::ID;
-use map_model::AreaType;
-use map_model::{IntersectionID, LaneID, Map, Traversable};
-use sim::{AgentID, Analytics, Scenario, Sim, SimCallback, SimFlags, VehicleType};
-use widgetry::{Canvas, EventCtx, GfxCtx, Prerender, SharedAppState, State};
-
+use map_model::{AreaID, AreaType};
+use map_model::{BuildingID, BusStopID, IntersectionID, LaneID, Map, RoadID, Traversable};
+use sim::{
+    AgentID, Analytics, QueueSpillback, Scenario, Sim, SimCallback, SimFlags, TripEndpoint, TripID,
+    VehicleType,
+};
+use widgetry::{Cached, Canvas, EventCtx, GfxCtx, Prerender, SharedAppState, State};
+
+use crate::challenges::prebake::PrebakedMetadata;
 use crate::challenges::HighScore;
 use crate::common::Warping;
+#[cfg(feature = "raw_osm")]
+use crate::debug::RawOsm;
 use crate::edit::apply_map_edits;
+use crate::info::{StopWalkingCosts, Tab};
+use crate::layer::basemap::Basemap;
+use crate::layer::pedestrians::CrosswalkWaits;
 use crate::layer::Layer;
-use crate::sandbox::{GameplayMode, TutorialState};
+use crate::query_api::{QueryApi, QueryApiConfig};
+use crate::sandbox::dashboards::FinishedTripTimes;
+use crate::sandbox::{
+    CustomTrips, GameplayMode, LaneClosure, MapExperiments, QuickEdit, TourStep, TutorialState,
+};
 
 // Convenient typedef
 pub type Transition = widgetry::Transition<App>;
@@ -32,6 +45,9 @@ pub struct App {
     // Naming is from older days when there was an A/B test, "side-by-side" mode. Keeping this
     // naming, because that mode will return someday.
     pub primary: PerMap,
+    /// Only exists while comparing two sets of edits side-by-side. Whichever side the player last
+    /// clicked on is swapped into primary, so everything else only has to handle one map.
+    pub secondary: Option<PerMap>,
     pub cs: ColorScheme,
     pub opts: Options,
 
@@ -49,7 +65,19 @@ impl App {
     pub fn prebaked(&self) -> &Analytics {
         &self.primary.prebaked.as_ref().unwrap().2
     }
+    /// Describes how the current prebaked results were generated. Results made before this was
+    /// recorded don't have any.
+    pub fn prebaked_metadata(&self) -> Option<&PrebakedMetadata> {
+        self.primary.prebaked_metadata.as_ref()
+    }
     pub fn set_prebaked(&mut self, prebaked: Option<(MapName, String, Analytics)>) {
+        self.primary.prebaked_metadata = prebaked.as_ref().and_then(|(map, scenario, _)| {
+            abstio::maybe_read_json(
+                abstio::path_prebaked_metadata(map, scenario),
+                &mut Timer::throwaway(),
+            )
+            .ok()
+        });
         self.primary.prebaked = prebaked;
 
         if false {
@@ -112,12 +140,39 @@ impl App {
     }
 
     pub fn draw(&self, g: &mut GfxCtx, opts: DrawOptions, show_objs: &dyn ShowObject) {
-        let map = &self.primary.map;
-        let draw_map = &self.primary.draw_map;
+        self.draw_per_map(g, &self.primary, self, opts, show_objs);
+    }
+
+    /// Draws the secondary map the same way as the primary one. Panics if there isn't one.
+    pub fn draw_secondary(&self, g: &mut GfxCtx, opts: DrawOptions, show_objs: &dyn ShowObject) {
+        let per_map = self.secondary.as_ref().unwrap();
+        self.draw_per_map(
+            g,
+            per_map,
+            &SecondaryApp { app: self, per_map },
+            opts,
+            show_objs,
+        );
+    }
+
+    // The renderables look up the map and sim through app_like, which must match per_map.
+    fn draw_per_map(
+        &self,
+        g: &mut GfxCtx,
+        per_map: &PerMap,
+        app_like: &dyn map_gui::ReadOnlyAppLike,
+        opts: DrawOptions,
+        show_objs: &dyn ShowObject,
+    ) {
+        let map = &per_map.map;
+        let draw_map = &per_map.draw_map;
 
         let mut sample_intersection: Option<String> = None;
 
         g.clear(self.cs.void_background);
+        if let Some(ref basemap) = per_map.basemap {
+            basemap.draw(g, map.get_gps_bounds());
+        }
         g.redraw(&draw_map.boundary_polygon);
 
         if g.canvas.cam_zoom < self.opts.min_zoom_for_detail {
@@ -127,44 +182,38 @@ impl App {
                 g.redraw(&draw_map.draw_all_areas);
             }
             if layers.show_parking_lots {
-                g.redraw(&draw_map.draw_all_unzoomed_parking_lots);
+                draw_map.draw_all_unzoomed_parking_lots(g, app_like);
             }
             if layers.show_intersections || layers.show_lanes {
-                g.redraw(
-                    &self
-                        .primary
-                        .draw_map
-                        .draw_all_unzoomed_roads_and_intersections,
-                );
+                draw_map.draw_all_unzoomed_roads_and_intersections(g, app_like);
             }
             if layers.show_buildings {
-                g.redraw(&draw_map.draw_all_buildings);
+                draw_map.draw_all_buildings(g, app_like);
                 // Not the building paths
             }
 
             // Still show some shape selection when zoomed out.
             // TODO Refactor! Ideally use get_obj
-            if let Some(ID::Area(id)) = self.primary.current_selection {
+            if let Some(ID::Area(id)) = per_map.current_selection {
                 g.draw_polygon(self.cs.selected, draw_map.get_a(id).get_outline(map));
-            } else if let Some(ID::Road(id)) = self.primary.current_selection {
+            } else if let Some(ID::Road(id)) = per_map.current_selection {
                 g.draw_polygon(self.cs.selected, draw_map.get_r(id).get_outline(map));
-            } else if let Some(ID::Intersection(id)) = self.primary.current_selection {
+            } else if let Some(ID::Intersection(id)) = per_map.current_selection {
                 // Actually, don't use get_outline here! Full polygon is easier to see.
                 g.draw_polygon(self.cs.selected, map.get_i(id).polygon.clone());
-            } else if let Some(ID::Building(id)) = self.primary.current_selection {
+            } else if let Some(ID::Building(id)) = per_map.current_selection {
                 g.draw_polygon(self.cs.selected, map.get_b(id).polygon.clone());
             }
 
-            let mut cache = self.primary.agents.borrow_mut();
-            cache.draw_unzoomed_agents(g, self);
+            let mut cache = per_map.agents.borrow_mut();
+            cache.draw_unzoomed_agents(g, app_like);
 
-            if let Some(a) = self
-                .primary
+            if let Some(a) = per_map
                 .current_selection
                 .as_ref()
                 .and_then(|id| id.agent_id())
             {
-                if let Some(pt) = self.primary.sim.canonical_pt_for_agent(a, map) {
+                if let Some(pt) = per_map.sim.canonical_pt_for_agent(a, map) {
                     // Usually we show selection with an outline, but no thickness/color is really
                     // visible for these tiny crowded dots.
                     g.draw_polygon(
@@ -174,8 +223,9 @@ impl App {
                 }
             }
         } else {
-            let mut cache = self.primary.agents.borrow_mut();
+            let mut cache = per_map.agents.borrow_mut();
             let objects = self.get_renderables_back_to_front(
+                per_map,
                 g.get_screen_bounds(),
                 &g.prerender,
                 &mut cache,
@@ -186,16 +236,16 @@ impl App {
             let mut drawn_all_areas = false;
 
             for obj in objects {
-                obj.draw(g, self, &opts);
+                obj.draw(g, app_like, &opts);
 
                 match obj.get_id() {
                     ID::Building(_) => {
                         if !drawn_all_buildings {
                             if opts.show_building_paths {
-                                g.redraw(&draw_map.draw_all_building_paths);
+                                draw_map.draw_all_building_paths(g, app_like);
                             }
-                            g.redraw(&draw_map.draw_all_buildings);
-                            g.redraw(&draw_map.draw_all_building_outlines);
+                            draw_map.draw_all_buildings(g, app_like);
+                            draw_map.draw_all_building_outlines(g, app_like);
                             drawn_all_buildings = true;
                         }
                     }
@@ -208,7 +258,7 @@ impl App {
                     _ => {}
                 }
 
-                if self.primary.current_selection == Some(obj.get_id()) {
+                if per_map.current_selection == Some(obj.get_id()) {
                     g.draw_polygon(self.cs.selected, obj.get_outline(map));
                 }
 
@@ -218,6 +268,8 @@ impl App {
                     }
                 }
             }
+
+            draw_map.prepare_nearby(g, app_like);
         }
 
         if let Some(i) = sample_intersection {
@@ -251,6 +303,13 @@ impl App {
         self.calculate_current_selection(ctx, show_objs, true, false, false)
     }
 
+    /// Like recalculate_current_selection, but for some point on the map besides the cursor. Used
+    /// when the map is drawn somewhere other than where the canvas expects.
+    pub fn recalculate_current_selection_at(&mut self, ctx: &EventCtx, pt: Pt2D) {
+        self.primary.current_selection =
+            self.calculate_selection_at(ctx, pt, &ShowEverything::new(), false, false, false);
+    }
+
     fn calculate_current_selection(
         &self,
         ctx: &EventCtx,
@@ -259,17 +318,85 @@ impl App {
         unzoomed_roads_and_intersections: bool,
         unzoomed_buildings: bool,
     ) -> Option<ID> {
+        let pt = match ctx.canvas.get_cursor_in_map_space() {
+            Some(pt) => pt,
+            None => {
+                self.primary.selection_stack.borrow_mut().clear();
+                return None;
+            }
+        };
+        self.calculate_selection_at(
+            ctx,
+            pt,
+            show_objs,
+            debug_mode,
+            unzoomed_roads_and_intersections,
+            unzoomed_buildings,
+        )
+    }
+
+    fn calculate_selection_at(
+        &self,
+        ctx: &EventCtx,
+        pt: Pt2D,
+        show_objs: &dyn ShowObject,
+        debug_mode: bool,
+        unzoomed_roads_and_intersections: bool,
+        unzoomed_buildings: bool,
+    ) -> Option<ID> {
+        let hits = self.hits_at(
+            ctx,
+            pt,
+            show_objs,
+            debug_mode,
+            unzoomed_roads_and_intersections,
+            unzoomed_buildings,
+        );
+        let mut stack: Vec<ID> = hits.iter().map(|(id, _)| id.clone()).collect();
+        let unzoomed = ctx.canvas.cam_zoom < self.opts.min_zoom_for_detail;
+        let picked = pick_selection(hits, pt, unzoomed);
+        if let Some(ref id) = picked {
+            stack.retain(|x| x != id);
+            stack.insert(0, id.clone());
+        }
+        *self.primary.selection_stack.borrow_mut() = stack;
+        picked
+    }
+
+    /// Every object under the cursor that the current mode could've selected, using the same
+    /// filters it did. The current selection is first, then the rest from the top of the draw
+    /// order down. Used to reach objects hidden beneath others. Empty if the mode picked the
+    /// current selection some other way.
+    pub fn selection_stack(&self) -> Vec<ID> {
+        let stack = self.primary.selection_stack.borrow();
+        if stack.first() == self.primary.current_selection.as_ref() {
+            stack.clone()
+        } else {
+            Vec::new()
+        }
+    }
+
+    /// All of the selectable objects containing the point, from the top of the draw order down.
+    /// Small agents also have their center, to break ties between overlapping hitboxes.
+    fn hits_at(
+        &self,
+        ctx: &EventCtx,
+        pt: Pt2D,
+        show_objs: &dyn ShowObject,
+        debug_mode: bool,
+        unzoomed_roads_and_intersections: bool,
+        unzoomed_buildings: bool,
+    ) -> Vec<(ID, Option<Pt2D>)> {
         let unzoomed = ctx.canvas.cam_zoom < self.opts.min_zoom_for_detail;
 
         // Unzoomed mode. Ignore when debugging areas.
         if unzoomed && !(debug_mode || unzoomed_roads_and_intersections || unzoomed_buildings) {
-            return None;
+            return Vec::new();
         }
 
-        let pt = ctx.canvas.get_cursor_in_map_space()?;
-
         let mut cache = self.primary.agents.borrow_mut();
         let mut objects = self.get_renderables_back_to_front(
+            &self.primary,
             Circle::new(pt, Distance::meters(3.0)).get_bounds(),
             ctx.prerender,
             &mut cache,
@@ -277,12 +404,7 @@ impl App {
         );
         objects.reverse();
 
-        let mut small_agents: Vec<(ID, Pt2D)> = Vec::new();
-
-        // In most cases, this loop is greedy -- as soon as we find the first object containing the
-        // cursor, return it. But if we happen to match a pedestrian or bike while zoomed in, there
-        // might be several overlapping hitboxes. Collect them all in small_agents, then pick the
-        // closest to the cursor.
+        let mut hits = Vec::new();
         for obj in objects {
             let id = obj.get_id();
             match id {
@@ -313,52 +435,31 @@ impl App {
                 }
             }
             if obj.contains_pt(pt, &self.primary.map) {
-                if unzoomed {
-                    // If we're selecting agents unzoomed, they're all tiny circles, and we really
-                    // don't care about picking the one closest to the cursor.
-                    return Some(id);
-                }
-
-                match id {
-                    ID::Pedestrian(_) => {}
-                    ID::Car(c) => {
-                        if c.1 != VehicleType::Bike {
-                            return Some(id);
-                        }
+                let center = match id {
+                    ID::Pedestrian(_) => Some(obj.get_outline(&self.primary.map).center()),
+                    ID::Car(c) if c.1 == VehicleType::Bike => {
+                        Some(obj.get_outline(&self.primary.map).center())
                     }
-                    _ => {
-                        // Once we match at least one small agent, keep scanning through all
-                        // possible hits. Ignore lanes and intersections and other thngs; they have
-                        // a lower z-order.
-                        if small_agents.is_empty() {
-                            return Some(id);
-                        } else {
-                            continue;
-                        }
-                    }
-                }
-
-                small_agents.push((id, obj.get_outline(&self.primary.map).center()));
+                    _ => None,
+                };
+                hits.push((id, center));
             }
         }
-
-        let (id, _) = small_agents
-            .into_iter()
-            .min_by_key(|(_, center)| center.fast_dist(pt))?;
-        Some(id)
+        hits
     }
 
     // TODO This could probably belong to DrawMap again, but it's annoying to plumb things that
     // State does, like show_icons_for() and show().
     fn get_renderables_back_to_front<'a>(
         &'a self,
+        per_map: &'a PerMap,
         bounds: Bounds,
         prerender: &Prerender,
         agents: &'a mut AgentCache,
         show_objs: &dyn ShowObject,
     ) -> Vec<&'a (dyn Renderable + 'a)> {
-        let map = &self.primary.map;
-        let draw_map = &self.primary.draw_map;
+        let map = &per_map.map;
+        let draw_map = &per_map.draw_map;
 
         let mut areas: Vec<&dyn Renderable> = Vec::new();
         let mut parking_lots: Vec<&dyn Renderable> = Vec::new();
@@ -419,7 +520,7 @@ impl App {
         // Expand all of the Traversables into agents, populating the cache if needed.
         {
             for on in &agents_on {
-                agents.populate_if_needed(*on, map, &self.primary.sim, &self.cs, prerender);
+                agents.populate_if_needed(*on, map, &per_map.sim, &self.cs, prerender);
             }
         }
 
@@ -429,7 +530,7 @@ impl App {
             }
         }
 
-        borrows.retain(|x| x.get_zorder() <= self.primary.draw_map.show_zorder);
+        borrows.retain(|x| x.get_zorder() <= draw_map.show_zorder);
 
         // This is a stable sort.
         borrows.sort_by_key(|x| x.get_zorder());
@@ -451,6 +552,47 @@ impl App {
     }
 }
 
+/// Picks one object from everything under the cursor, listed from the top of the draw order down.
+fn pick_selection(hits: Vec<(ID, Option<Pt2D>)>, pt: Pt2D, unzoomed: bool) -> Option<ID> {
+    let mut small_agents: Vec<(ID, Pt2D)> = Vec::new();
+
+    // In most cases, this loop is greedy -- pick the first object containing the cursor. But if we
+    // happen to match a pedestrian or bike while zoomed in, there might be several overlapping
+    // hitboxes. Collect them all in small_agents, then pick the closest to the cursor.
+    for (id, center) in hits {
+        if unzoomed {
+            // If we're selecting agents unzoomed, they're all tiny circles, and we really don't
+            // care about picking the one closest to the cursor.
+            return Some(id);
+        }
+
+        match id {
+            ID::Pedestrian(_) => {}
+            ID::Car(c) => {
+                if c.1 != VehicleType::Bike {
+                    return Some(id);
+                }
+            }
+            _ => {
+                // Once we match at least one small agent, keep scanning through all possible
+                // hits. Ignore lanes and intersections and other thngs; they have a lower z-order.
+                if small_agents.is_empty() {
+                    return Some(id);
+                } else {
+                    continue;
+                }
+            }
+        }
+
+        small_agents.push((id, center.unwrap()));
+    }
+
+    let (id, _) = small_agents
+        .into_iter()
+        .min_by_key(|(_, center)| center.fast_dist(pt))?;
+    Some(id)
+}
+
 impl App {
     /// If an intersection was clicked, return its ID.
     pub fn click_on_intersection<S: Into<String>>(
@@ -483,7 +625,7 @@ impl App {
 
 // I haven't measured build or runtime impact of inlining vs not, but I assume for these simple
 // accessors it makes sense.
-impl map_gui::AppLike for App {
+impl map_gui::ReadOnlyAppLike for App {
     #[inline]
     fn map(&self) -> &Map {
         &self.primary.map
@@ -497,21 +639,29 @@ impl map_gui::AppLike for App {
         &self.cs
     }
     #[inline]
-    fn mut_cs(&mut self) -> &mut ColorScheme {
-        &mut self.cs
-    }
-    #[inline]
     fn draw_map(&self) -> &DrawMap {
         &self.primary.draw_map
     }
     #[inline]
-    fn mut_draw_map(&mut self) -> &mut DrawMap {
-        &mut self.primary.draw_map
-    }
-    #[inline]
     fn opts(&self) -> &Options {
         &self.opts
     }
+
+    fn baseline_trip_time(&self, trip: TripID) -> Option<Duration> {
+        self.has_prebaked()?;
+        self.prebaked().finished_trip_time(trip)
+    }
+}
+
+impl map_gui::AppLike for App {
+    #[inline]
+    fn mut_cs(&mut self) -> &mut ColorScheme {
+        &mut self.cs
+    }
+    #[inline]
+    fn mut_draw_map(&mut self) -> &mut DrawMap {
+        &mut self.primary.draw_map
+    }
     #[inline]
     fn mut_opts(&mut self) -> &mut Options {
         &mut self.opts
@@ -545,6 +695,37 @@ impl map_gui::AppLike for App {
     ) -> Box<dyn State<App>> {
         Warping::new(ctx, pt, target_cam_zoom, id, &mut self.primary)
     }
+
+    fn agent_colors_changed(&mut self) {
+        self.primary.agents.borrow_mut().colors_changed(&self.cs);
+        if let Some(ref secondary) = self.secondary {
+            secondary.agents.borrow_mut().colors_changed(&self.cs);
+        }
+    }
+}
+
+/// Lets the renderables draw the secondary map. They only read from the app.
+struct SecondaryApp<'a> {
+    app: &'a App,
+    per_map: &'a PerMap,
+}
+
+impl<'a> map_gui::ReadOnlyAppLike for SecondaryApp<'a> {
+    fn map(&self) -> &Map {
+        &self.per_map.map
+    }
+    fn sim(&self) -> &Sim {
+        &self.per_map.sim
+    }
+    fn cs(&self) -> &ColorScheme {
+        &self.app.cs
+    }
+    fn draw_map(&self) -> &DrawMap {
+        &self.per_map.draw_map
+    }
+    fn opts(&self) -> &Options {
+        &self.app.opts
+    }
 }
 
 pub struct ShowLayers {
@@ -605,6 +786,8 @@ pub struct Flags {
     /// Display an extra area with this name on the map. This gets applied to every map loaded, if
     /// the area is within map bounds.
     pub study_area: Option<String>,
+    /// Serve a read-only HTTP API for the running simulation
+    pub query_api: Option<QueryApiConfig>,
 }
 
 /// All of the state that's bound to a specific map.
@@ -615,6 +798,9 @@ pub struct PerMap {
     pub agents: RefCell<AgentCache>,
 
     pub current_selection: Option<ID>,
+    /// Everything under the cursor the last time the selection was calculated, starting with the
+    /// object picked.
+    selection_stack: RefCell<Vec<ID>>,
     pub current_flags: Flags,
     pub last_warped_from: Option<(Pt2D, f64)>,
     pub sim_cb: Option<Box<dyn SimCallback>>,
@@ -622,17 +808,38 @@ pub struct PerMap {
     pub dirty_from_edits: bool,
     /// Any ScenarioModifiers in effect?
     pub has_modified_trips: bool,
+    /// Did building overrides add any synthetic people?
+    pub has_synthetic_trips: bool,
+    /// Land-use experiments saved for this map. Loaded once, and saved whenever they're edited.
+    pub experiments: MapExperiments,
 
     /// Sometimes we need the map before any edits have been applied. Cache it here.
     pub unedited_map: RefCell<Option<Map>>,
+    pub caches: Caches,
+    /// A lane temporarily closed from its info panel. The closure is part of the map edits, so it
+    /// lives as long as the map does.
+    pub lane_closure: Option<LaneClosure>,
+    /// The last edit made from a lane's info panel, so it can be undone from there.
+    pub quick_edit: Option<QuickEdit>,
+    /// Trips spawned by hand into the current simulation, along with the trips created for each.
+    pub custom_trips: Vec<(CustomTrips, Vec<TripID>)>,
+    /// The speed of the agent shown in the info panel over the last minute. The simulation doesn't
+    /// remember this, so it's sampled every time the panel refreshes.
+    pub recent_speeds: Option<(AgentID, Vec<(Time, Speed)>)>,
+    /// The OSM input for this map, only loaded when inspected from debug mode
+    #[cfg(feature = "raw_osm")]
+    pub raw_osm: Option<RawOsm>,
 
     pub layer: Option<Box<dyn Layer>>,
+    /// Context drawn outside the map's boundary, if configured in the options
+    pub basemap: Option<Basemap>,
     /// Only filled out in edit mode. Stored here once to avoid lots of clones. Used for preview.
     pub suspended_sim: Option<Sim>,
     /// Only exists in some gameplay modes. Must be carefully reset otherwise. Has the map and
     /// scenario name too.
     // TODO Embed that in Analytics directly instead.
     prebaked: Option<(MapName, String, Analytics)>,
+    prebaked_metadata: Option<PrebakedMetadata>,
     /// The most recent Scenario loaded from a file. Don't depend on it always matching the current
     /// gameplay mode; always verify the name matches what's needed.
     ///
@@ -641,6 +848,53 @@ pub struct PerMap {
     pub scenario: Option<Scenario>,
 }
 
+/// Things that're slow to calculate, but shown by panels and layers that refresh constantly while
+/// the simulation runs. Most are only recalculated when their key changes.
+pub struct Caches {
+    /// Road throughput over the last hour, as of some time
+    pub recent_road_thruput: RefCell<Cached<Time, Counter<RoadID>>>,
+    /// The pieces of sidewalk reachable from a building within a time limit
+    pub walkshed: RefCell<Cached<(BuildingID, Duration), Vec<(LaneID, Distance, Distance)>>>,
+    /// The queues traced back from an intersection, and when
+    pub queue_spillback: RefCell<Cached<(IntersectionID, Time), Vec<QueueSpillback>>>,
+    /// How long it takes to walk from a bus stop to nearby buildings and other stops
+    pub stop_walking_costs: RefCell<Cached<BusStopID, StopWalkingCosts>>,
+    /// Where residents of a building first go, and where its employees come from, keyed by the
+    /// number of people when counted. Going through every trip is slow on big maps.
+    pub commutes:
+        RefCell<Cached<(BuildingID, usize), (Counter<TripEndpoint>, Counter<TripEndpoint>)>>,
+    /// The buildings inside an area, the number of roads inside, and trips ending at those
+    /// buildings. Areas can be huge, so checking everything for containment isn't cheap.
+    pub area_contents: RefCell<Cached<AreaID, (BTreeSet<BuildingID>, usize, Vec<TripID>)>>,
+    /// How long finished trips took and changed from the baseline, as of some time
+    pub finished_trip_times: RefCell<Cached<Time, FinishedTripTimes>>,
+    /// Pedestrian waits at crosswalks, kept up to date as the simulation runs. Only delays recorded
+    /// since the last update are looked at, so toggling the layer doesn't scan them all again.
+    pub crosswalk_waits: RefCell<Option<CrosswalkWaits>>,
+}
+
+impl Caches {
+    fn new() -> Caches {
+        Caches {
+            recent_road_thruput: RefCell::new(Cached::new()),
+            walkshed: RefCell::new(Cached::new()),
+            queue_spillback: RefCell::new(Cached::new()),
+            stop_walking_costs: RefCell::new(Cached::new()),
+            commutes: RefCell::new(Cached::new()),
+            area_contents: RefCell::new(Cached::new()),
+            finished_trip_times: RefCell::new(Cached::new()),
+            crosswalk_waits: RefCell::new(None),
+        }
+    }
+
+    /// Forget everything that depends on the map, after it's edited.
+    pub fn map_edited(&self) {
+        self.walkshed.borrow_mut().clear();
+        self.queue_spillback.borrow_mut().clear();
+        self.stop_walking_costs.borrow_mut().clear();
+    }
+}
+
 impl PerMap {
     pub fn map_loaded(
         mut map: Map,
@@ -658,24 +912,51 @@ impl PerMap {
         }
 
         timer.start("draw_map");
-        let draw_map = DrawMap::new(ctx, &map, opts, cs, timer);
+        let draw_map = DrawMap::new(ctx, &map, cs, timer);
         timer.stop("draw_map");
 
+        let basemap = match opts.basemap {
+            Some(ref basemap_opts) if basemap_opts.enabled => {
+                match Basemap::load(ctx, map.get_gps_bounds(), basemap_opts) {
+                    Ok(basemap) => Some(basemap),
+                    Err(err) => {
+                        warn!("Not drawing basemap {}: {}", basemap_opts.source, err);
+                        None
+                    }
+                }
+            }
+            _ => None,
+        };
+
+        let experiments = MapExperiments::load(map.get_name());
+
         PerMap {
             map,
             draw_map,
             sim,
             agents: RefCell::new(AgentCache::new(cs)),
             current_selection: None,
+            selection_stack: RefCell::new(Vec::new()),
             current_flags: flags,
             last_warped_from: None,
             sim_cb: None,
             dirty_from_edits: false,
             has_modified_trips: false,
+            has_synthetic_trips: false,
+            experiments,
             unedited_map: RefCell::new(None),
+            caches: Caches::new(),
+            lane_closure: None,
+            quick_edit: None,
+            custom_trips: Vec::new(),
+            recent_speeds: None,
+            #[cfg(feature = "raw_osm")]
+            raw_osm: None,
             layer: None,
+            basemap,
             suspended_sim: None,
             prebaked: None,
+            prebaked_metadata: None,
             scenario: None,
         }
     }
@@ -708,6 +989,7 @@ impl PerMap {
     /// Returns whatever was there
     pub fn clear_sim(&mut self) -> Sim {
         self.dirty_from_edits = false;
+        self.custom_trips.clear();
         std::mem::replace(
             &mut self.sim,
             Sim::new(&self.map, self.current_flags.sim_flags.opts.clone()),
@@ -757,6 +1039,16 @@ pub struct SessionState {
     pub tutorial: Option<TutorialState>,
     pub high_scores: BTreeMap<GameplayMode, Vec<HighScore>>,
     pub info_panel_tab: BTreeMap<&'static str, &'static str>,
+    pub show_hud: bool,
+    /// (Object kind, section title) of every info panel section the player collapsed
+    pub collapsed_info_sections: BTreeSet<(String, String)>,
+    /// How far the player has gotten through the tour. This outlives the sandbox, which gets
+    /// recreated after editing the map.
+    pub tour_step: TourStep,
+    /// Only recorded while something like the tour is listening; see `record_ui_event`.
+    pub ui_events: Option<Vec<UiEvent>>,
+    /// Only exists if the flag to serve it was passed
+    pub query_api: Option<QueryApi>,
 }
 
 impl SessionState {
@@ -770,9 +1062,31 @@ impl SessionState {
                 "bldg" => "info",
                 "person" => "trips",
                 "bus" => "status",
+                "bus stop" => "info",
             },
+            show_hud: true,
+            collapsed_info_sections: BTreeSet::new(),
+            tour_step: TourStep::ClickBuilding,
+            ui_events: None,
+            query_api: None,
         }
     }
+
+    /// UI code calls this at a few interesting points, so scripted tutorials can wait for the
+    /// player to actually do something.
+    pub fn record_ui_event(&mut self, event: UiEvent) {
+        if let Some(ref mut events) = self.ui_events {
+            events.push(event);
+        }
+    }
+}
+
+/// Something the player did in the UI.
+pub enum UiEvent {
+    /// An info panel opened or switched to this tab
+    InfoPanelOpened(Tab),
+    /// The player started following an agent from the info panel
+    FollowedAgent,
 }
 
 // TODO Reconsider this; maybe it does belong in widgetry.
@@ -815,8 +1129,14 @@ impl SimCallback for FindDelayedIntersections {
 }
 
 impl SharedAppState for App {
-    fn before_event(&mut self) {
+    fn before_event(&mut self, ctx: &mut EventCtx) {
         self.per_obj.reset();
+        if let Some(ref mut basemap) = self.primary.basemap {
+            basemap.event(ctx, self.primary.map.get_gps_bounds());
+        }
+        if let Some(ref mut api) = self.session.query_api {
+            api.maybe_publish(&self.primary);
+        }
     }
 
     fn draw_default(&self, g: &mut GfxCtx) {
@@ -868,6 +1188,9 @@ impl SharedAppState for App {
 
     fn before_quit(&self, canvas: &Canvas) {
         CameraState::save(canvas, self.primary.map.get_name());
+        if let Some(ref api) = self.session.query_api {
+            api.shutdown();
+        }
     }
 }
 
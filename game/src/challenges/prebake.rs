@@ -1,3 +1,5 @@
+use serde::{Deserialize, Serialize};
+
 use abstio::MapName;
 use abstutil::{prettyprint_usize, Timer};
 use geom::{Duration, Time};
@@ -6,6 +8,19 @@ use sim::{AlertHandler, Scenario, Sim, SimFlags, SimOptions};
 
 use crate::sandbox::TutorialState;
 
+/// Stored beside prebaked results, to tell what they're a baseline for.
+#[derive(Serialize, Deserialize)]
+pub struct PrebakedMetadata {
+    pub scenario_name: String,
+    /// The edits applied to the map while simulating
+    pub edits_name: String,
+    pub edits_checksum: u64,
+    /// In local time
+    pub generated: String,
+    /// How many agents were still around at the end of the day
+    pub agents_left: usize,
+}
+
 /// Simulate a curated list of scenarios to completion, and save the analytics as "prebaked
 /// results," to later compare simulation metrics against the baseline without map edits.
 pub fn prebake_all() {
@@ -15,7 +30,7 @@ pub fn prebake_all() {
         let map = map_model::Map::new(MapName::seattle("montlake").path(), &mut timer);
         let scenario: Scenario =
             abstio::read_binary(abstio::path_scenario(map.get_name(), "weekday"), &mut timer);
-        prebake(&map, scenario, None, &mut timer);
+        check_gridlock(&map, prebake(&map, scenario, None, &mut timer));
 
         for generator in TutorialState::scenarios_to_prebake(&map) {
             let scenario = generator.generate(
@@ -23,7 +38,7 @@ pub fn prebake_all() {
                 &mut SimFlags::for_test("prebaked").make_rng(),
                 &mut timer,
             );
-            prebake(&map, scenario, None, &mut timer);
+            check_gridlock(&map, prebake(&map, scenario, None, &mut timer));
         }
     }
 
@@ -34,11 +49,18 @@ pub fn prebake_all() {
         let map = map_model::Map::new(name.path(), &mut timer);
         let scenario: Scenario =
             abstio::read_binary(abstio::path_scenario(map.get_name(), "weekday"), &mut timer);
-        prebake(&map, scenario, None, &mut timer);
+        check_gridlock(&map, prebake(&map, scenario, None, &mut timer));
     }
 }
 
-fn prebake(map: &Map, scenario: Scenario, time_limit: Option<Duration>, timer: &mut Timer) {
+/// Simulates the scenario on the map, with whatever edits it has, and saves the analytics and
+/// metadata describing them. Returns the number of agents left at the end.
+pub fn prebake(
+    map: &Map,
+    scenario: Scenario,
+    time_limit: Option<Duration>,
+    timer: &mut Timer,
+) -> usize {
     timer.start(format!(
         "prebake for {} / {}",
         scenario.map_name.describe(),
@@ -68,17 +90,30 @@ fn prebake(map: &Map, scenario: Scenario, time_limit: Option<Duration>, timer: &
     );
     let agents_left = sim.num_agents().sum();
     info!("{} agents left by end of day", agents_left);
+    abstio::write_json(
+        abstio::path_prebaked_metadata(&scenario.map_name, &scenario.scenario_name),
+        &PrebakedMetadata {
+            scenario_name: scenario.scenario_name.clone(),
+            edits_name: map.get_edits().edits_name.clone(),
+            edits_checksum: map.get_edits().checksum(map),
+            generated: chrono::Local::now().format("%Y-%m-%d %H:%M").to_string(),
+            agents_left,
+        },
+    );
     timer.stop(format!(
         "prebake for {} / {}",
         scenario.map_name.describe(),
         scenario.scenario_name
     ));
+    agents_left
+}
 
+fn check_gridlock(map: &Map, agents_left: usize) {
     if agents_left > 500 {
         panic!(
             "{} agents left by end of day on {}; gridlock may be likely",
             prettyprint_usize(agents_left),
-            scenario.map_name.describe()
+            map.get_name().describe()
         );
     }
 }
@@ -0,0 +1,73 @@
+use std::time::SystemTime;
+
+use instant::Instant;
+
+use map_gui::colors::ColorSchemeChoice;
+use map_gui::tools::PopupMsg;
+use map_gui::AppLike;
+use widgetry::EventCtx;
+
+use crate::app::{App, Transition};
+
+/// In dev mode, notices when the overrides file for the current color scheme changes, and reloads
+/// the colors. This is just for quickly iterating on a color scheme.
+pub struct ColorSchemeWatcher {
+    scheme: ColorSchemeChoice,
+    last_modified: Option<SystemTime>,
+    last_check: Instant,
+}
+
+impl ColorSchemeWatcher {
+    pub fn new(app: &App) -> ColorSchemeWatcher {
+        ColorSchemeWatcher {
+            scheme: app.opts.color_scheme,
+            last_modified: modified_time(app.opts.color_scheme),
+            last_check: Instant::now(),
+        }
+    }
+
+    pub fn event(&mut self, ctx: &mut EventCtx, app: &mut App) -> Option<Transition> {
+        // Polling the filesystem is cheap, but not free
+        if !app.opts.dev || self.last_check.elapsed() < std::time::Duration::from_secs(2) {
+            return None;
+        }
+        self.last_check = Instant::now();
+
+        // Switching schemes already reloads everything
+        if app.opts.color_scheme != self.scheme {
+            *self = ColorSchemeWatcher::new(app);
+            return None;
+        }
+
+        let modified = modified_time(self.scheme);
+        if modified == self.last_modified {
+            return None;
+        }
+        self.last_modified = modified;
+        info!("{} changed, reloading colors", self.scheme.overrides_path());
+        reload_colors(ctx, app)
+    }
+}
+
+/// Reload the current color scheme, including overrides from disk, and rerender everything with
+/// it. If the overrides are broken, keeps the current colors and explains why.
+pub fn reload_colors(ctx: &mut EventCtx, app: &mut App) -> Option<Transition> {
+    match app.reload_color_scheme(ctx) {
+        Ok(()) => None,
+        Err(err) => Some(Transition::Push(PopupMsg::new(
+            ctx,
+            "Couldn't reload colors",
+            vec![
+                format!("{}: {}", app.opts.color_scheme.overrides_path(), err),
+                "Keeping the previous colors".to_string(),
+            ],
+        ))),
+    }
+}
+
+// Doesn't work on the web, which just means the watcher never notices anything
+fn modified_time(scheme: ColorSchemeChoice) -> Option<SystemTime> {
+    std::fs::metadata(scheme.overrides_path())
+        .and_then(|m| m.modified())
+        .ok()
+}
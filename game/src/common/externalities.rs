@@ -0,0 +1,166 @@
+//! Rough estimates of the carbon emitted and the calories burned by trips. The factors are very
+//! simple averages, and they all live here, so they're easy to tweak.
+
+use std::ops::AddAssign;
+
+use geom::{Distance, UnitFmt};
+use map_model::{Map, Path, PathRequest, PathStep};
+use sim::{TripEndpoint, TripInfo, TripMode, TripPhase, TripPhaseType, VehicleType};
+
+/// Grams of CO2 per km, for one car
+pub const CAR_CO2_PER_KM: f64 = 170.0;
+/// Grams of CO2 per km, for each passenger
+pub const BUS_CO2_PER_PASSENGER_KM: f64 = 100.0;
+/// Grams of CO2 per km, for each passenger
+pub const TRAIN_CO2_PER_PASSENGER_KM: f64 = 40.0;
+/// On flat ground
+pub const WALKING_KCAL_PER_KM: f64 = 60.0;
+/// On flat ground
+pub const BIKING_KCAL_PER_KM: f64 = 30.0;
+/// Extra effort to go uphill, walking or biking
+pub const CLIMBING_KCAL_PER_METER: f64 = 0.6;
+/// Without a path, estimate the distance as the straight line between the endpoints, times this
+pub const DETOUR_FACTOR: f64 = 1.3;
+
+#[derive(Clone, Copy, Default)]
+pub struct Externalities {
+    pub co2_grams: f64,
+    pub kcal: f64,
+}
+
+impl AddAssign for Externalities {
+    fn add_assign(&mut self, other: Externalities) {
+        self.co2_grams += other.co2_grams;
+        self.kcal += other.kcal;
+    }
+}
+
+impl Externalities {
+    /// Only moving phases count; nobody burns anything while waiting or parking. `climb` is the
+    /// total elevation gained.
+    pub fn for_phase(phase_type: TripPhaseType, dist: Distance, climb: Distance) -> Externalities {
+        let km = dist.inner_meters() / 1000.0;
+        let climbing = CLIMBING_KCAL_PER_METER * climb.inner_meters();
+        match phase_type {
+            TripPhaseType::Driving => Externalities {
+                co2_grams: CAR_CO2_PER_KM * km,
+                kcal: 0.0,
+            },
+            TripPhaseType::Walking => Externalities {
+                co2_grams: 0.0,
+                kcal: WALKING_KCAL_PER_KM * km + climbing,
+            },
+            TripPhaseType::Biking => Externalities {
+                co2_grams: 0.0,
+                kcal: BIKING_KCAL_PER_KM * km + climbing,
+            },
+            TripPhaseType::RidingBus(_, _, car) => Externalities {
+                co2_grams: if car.1 == VehicleType::Train {
+                    TRAIN_CO2_PER_PASSENGER_KM * km
+                } else {
+                    BUS_CO2_PER_PASSENGER_KM * km
+                },
+                kcal: 0.0,
+            },
+            TripPhaseType::Parking
+            | TripPhaseType::WaitingForBus(_, _)
+            | TripPhaseType::Cancelled
+            | TripPhaseType::Finished
+            | TripPhaseType::DelayedStart => Externalities::default(),
+        }
+    }
+
+    pub fn for_path(map: &Map, phase_type: TripPhaseType, path: &Path) -> Externalities {
+        Externalities::for_phase(phase_type, path.total_length(), climb(map, path))
+    }
+
+    /// Cheaper than pathfinding, but ignores hills.
+    pub fn estimate(map: &Map, phase_type: TripPhaseType, req: &PathRequest) -> Externalities {
+        let dist = req.start.pt(map).dist_to(req.end.pt(map));
+        Externalities::for_phase(phase_type, DETOUR_FACTOR * dist, Distance::ZERO)
+    }
+
+    /// Each phase of the trip so far, and whether it's finished.
+    pub fn for_trip_phases(map: &Map, phases: &[TripPhase]) -> Vec<(Externalities, bool)> {
+        phases
+            .iter()
+            .filter_map(|p| {
+                let path = p.path.as_ref()?;
+                Some((
+                    Externalities::for_path(map, p.phase_type, path),
+                    p.end_time.is_some(),
+                ))
+            })
+            .collect()
+    }
+
+    /// For a trip that hasn't started yet. There's no way to know which buses somebody will take,
+    /// so the whole of a transit trip is treated like riding a bus.
+    pub fn for_planned_trip(map: &Map, trip: &TripInfo) -> Externalities {
+        let req = match TripEndpoint::path_req(trip.start.clone(), trip.end.clone(), trip.mode, map)
+        {
+            Some(req) => req,
+            None => {
+                return Externalities::default();
+            }
+        };
+        let phase_type = match trip.mode {
+            TripMode::Walk => TripPhaseType::Walking,
+            TripMode::Bike => TripPhaseType::Biking,
+            TripMode::Drive => TripPhaseType::Driving,
+            TripMode::Transit => {
+                let km = DETOUR_FACTOR * req.start.pt(map).dist_to(req.end.pt(map)).inner_meters()
+                    / 1000.0;
+                return Externalities {
+                    co2_grams: BUS_CO2_PER_PASSENGER_KM * km,
+                    kcal: 0.0,
+                };
+            }
+        };
+        match map.pathfind(req) {
+            Ok(path) => Externalities::for_path(map, phase_type, &path),
+            Err(_) => Externalities::default(),
+        }
+    }
+
+    pub fn describe_co2(&self, units: &UnitFmt) -> String {
+        if units.metric {
+            format!("{:.1} kg of CO2", self.co2_grams / 1000.0)
+        } else {
+            format!("{:.1} lbs of CO2", self.co2_grams / 453.6)
+        }
+    }
+
+    pub fn describe_kcal(&self, units: &UnitFmt) -> String {
+        if units.metric {
+            format!("{} kcal", self.kcal.round())
+        } else {
+            format!("{} Calories", self.kcal.round())
+        }
+    }
+}
+
+// Total elevation gained along a path. Turns are flat.
+fn climb(map: &Map, path: &Path) -> Distance {
+    let mut total = Distance::ZERO;
+    for step in path.get_steps() {
+        let (from, to) = match step {
+            PathStep::Lane(l) => {
+                let lane = map.get_l(*l);
+                (lane.src_i, lane.dst_i)
+            }
+            PathStep::ContraflowLane(l) => {
+                let lane = map.get_l(*l);
+                (lane.dst_i, lane.src_i)
+            }
+            PathStep::Turn(_) => {
+                continue;
+            }
+        };
+        let gain = map.get_i(to).elevation - map.get_i(from).elevation;
+        if gain > Distance::ZERO {
+            total += gain;
+        }
+    }
+    total
+}
@@ -35,7 +35,7 @@ impl MinimapControls<App> for MinimapController {
                 .agents
                 .borrow()
                 .unzoomed_agents
-                .make_vert_viz_panel(ctx)
+                .make_vert_viz_panel(ctx, &app.cs)
                 .bg(app.cs.panel_bg)
                 .padding(16),
         ]))
@@ -50,7 +50,7 @@ impl MinimapControls<App> for MinimapController {
             .agents
             .borrow()
             .unzoomed_agents
-            .make_horiz_viz_panel(ctx)
+            .make_horiz_viz_panel(ctx, &app.cs)
     }
     fn make_zoomed_side_panel(&self, ctx: &mut EventCtx, app: &App) -> Widget {
         make_tool_panel(ctx, app)
@@ -2,23 +2,31 @@ use std::collections::BTreeSet;
 
 use anyhow::Result;
 
-use geom::{Duration, Polygon};
+use geom::{Duration, Polygon, Time};
+use map_gui::tools::ColorLegend;
 use map_gui::ID;
 use map_model::{IntersectionID, Map, RoadID};
 use sim::{AgentType, TripMode, TripPhaseType};
 use widgetry::{
-    lctrl, Checkbox, Color, EventCtx, GeomBatch, GfxCtx, HorizontalAlignment, Key, Line, Panel,
-    ScreenDims, ScreenPt, ScreenRectangle, StyledButtons, Text, TextSpan, VerticalAlignment,
-    Widget,
+    lctrl, Checkbox, Color, EventCtx, GeomBatch, GfxCtx, HorizontalAlignment, Key, Line, LinePlot,
+    Panel, PlotOptions, ScreenDims, ScreenPt, ScreenRectangle, Series, StyledButtons, Text,
+    TextSpan, VerticalAlignment, Widget, Yvalue,
 };
 
+pub use self::color_watcher::{reload_colors, ColorSchemeWatcher};
+pub use self::externalities::Externalities;
 pub use self::minimap::MinimapController;
-pub use self::warp::Warping;
+pub use self::modes::{agent_type_registry, trip_mode_registry, ModeRegistry};
+pub use self::warp::{DebugWarp, Warping};
 use crate::app::App;
 use crate::app::Transition;
 use crate::info::{ContextualActions, InfoPanel, Tab};
+use crate::sandbox::SandboxMode;
 
+mod color_watcher;
+mod externalities;
 mod minimap;
+mod modes;
 mod warp;
 
 // TODO This is now just used in two modes...
@@ -27,6 +35,11 @@ pub struct CommonState {
     info_panel: Option<InfoPanel>,
     // Just for drawing the OSD
     cached_actions: Vec<Key>,
+    // When several objects overlap under the cursor, all of them, starting with the one normally
+    // selected. Empty otherwise.
+    overlapping: Vec<ID>,
+    // Which of the overlapping objects the next click picks
+    next_overlap: usize,
 }
 
 impl CommonState {
@@ -34,6 +47,8 @@ impl CommonState {
         CommonState {
             info_panel: None,
             cached_actions: Vec::new(),
+            overlapping: Vec::new(),
+            next_overlap: 0,
         }
     }
 
@@ -47,12 +62,26 @@ impl CommonState {
             app.opts.dev = !app.opts.dev;
         }
         if ctx.input.pressed(lctrl(Key::J)) {
-            return Some(Transition::Push(warp::DebugWarp::new(ctx)));
+            return Some(Transition::Push(DebugWarp::new(ctx)));
         }
 
-        if let Some(id) = app.primary.current_selection.clone() {
+        if ctx.redo_mouseover() {
+            self.overlapping = app.selection_stack();
+            if self.overlapping.len() == 1 {
+                self.overlapping.clear();
+            }
+            self.next_overlap = 0;
+        }
+
+        if let Some(mut id) = app.primary.current_selection.clone() {
             // TODO Also have a hotkey binding for this?
             if app.per_obj.left_click(ctx, "show info") {
+                // Clicking repeatedly without moving the cursor cycles through everything there
+                if let Some(next) = self.next_overlapping(app) {
+                    self.next_overlap = (self.next_overlap + 1) % self.overlapping.len();
+                    app.primary.current_selection = Some(next.clone());
+                    id = next;
+                }
                 self.info_panel =
                     Some(InfoPanel::new(ctx, app, Tab::from_id(app, id), ctx_actions));
                 return None;
@@ -74,6 +103,9 @@ impl CommonState {
             if let Some(id) = app.primary.current_selection.clone() {
                 // Allow hotkeys to work without opening the panel.
                 for (k, action) in ctx_actions.actions(app, id.clone()) {
+                    if ctx_actions.enabled(app, id.clone(), &action).is_err() {
+                        continue;
+                    }
                     if ctx.input.pressed(k) {
                         return Some(ctx_actions.execute(ctx, app, id, action, &mut false));
                     }
@@ -114,6 +146,29 @@ impl CommonState {
         }
 
         CommonState::draw_custom_osd(g, app, osd);
+
+        if let Some(id) = self.next_overlapping(app) {
+            let mut txt = CommonState::osd_for(app, id);
+            txt.add(
+                Line(format!(
+                    "Click to show this, {} of {} things here",
+                    self.next_overlap + 1,
+                    self.overlapping.len()
+                ))
+                .secondary(),
+            );
+            g.draw_mouse_tooltip(txt);
+        }
+    }
+
+    /// What clicking picks from a stack of overlapping objects. None if the cursor isn't over
+    /// several, or the current mode selected something else.
+    fn next_overlapping(&self, app: &App) -> Option<ID> {
+        let current = app.primary.current_selection.as_ref()?;
+        if !self.overlapping.contains(current) {
+            return None;
+        }
+        Some(self.overlapping[self.next_overlap].clone())
     }
 
     fn osd_for(app: &App, id: ID) -> Text {
@@ -288,6 +343,24 @@ impl CommonState {
     pub fn info_panel_open(&self, app: &App) -> Option<ID> {
         self.info_panel.as_ref().and_then(|i| i.active_id(app))
     }
+
+    /// Where some button in the info panel is on the screen, if it's open and has that button.
+    pub fn info_panel_rect_of(&self, action: &str) -> Option<ScreenRectangle> {
+        self.info_panel.as_ref().and_then(|i| i.rect_of(action))
+    }
+}
+
+/// Opens an info panel in sandbox mode, from a state that's returning there. Other states pretty
+/// much don't use info panels, so it does nothing elsewhere.
+pub fn launch_info_panel(tab: Tab) -> Transition {
+    Transition::ModifyState(Box::new(move |state, ctx, app| {
+        if let Some(ref mut s) = state.downcast_mut::<SandboxMode>() {
+            let mut actions = s.contextual_actions();
+            if let Some(ref mut common) = s.controls.common {
+                common.launch_info_panel(ctx, app, tab, &mut actions);
+            }
+        }
+    }))
 }
 
 // TODO Kinda misnomer
@@ -343,22 +416,63 @@ pub fn cmp_duration_shorter(app: &App, after: Duration, before: Duration) -> Vec
 }
 
 pub fn color_for_mode(app: &App, m: TripMode) -> Color {
-    match m {
-        TripMode::Walk => app.cs.unzoomed_pedestrian,
-        TripMode::Bike => app.cs.unzoomed_bike,
-        TripMode::Transit => app.cs.unzoomed_bus,
-        TripMode::Drive => app.cs.unzoomed_car,
-    }
+    trip_mode_registry().color(&app.cs, m)
 }
 
 pub fn color_for_agent_type(app: &App, a: AgentType) -> Color {
-    match a {
-        AgentType::Pedestrian => app.cs.unzoomed_pedestrian,
-        AgentType::Bike => app.cs.unzoomed_bike,
-        AgentType::Bus | AgentType::Train => app.cs.unzoomed_bus,
-        AgentType::TransitRider => app.cs.bus_trip,
-        AgentType::Car => app.cs.unzoomed_car,
+    agent_type_registry().color(&app.cs, a)
+}
+
+/// Plots counts broken down by agent type, like throughput, with a legend for the types actually
+/// in the data. Shows a placeholder instead if there's nothing to plot yet.
+pub fn agent_type_plot(
+    ctx: &mut EventCtx,
+    app: &App,
+    current: Vec<(AgentType, Vec<(Time, usize)>)>,
+    baseline: Vec<(AgentType, Vec<(Time, usize)>)>,
+    plot_opts: PlotOptions<usize>,
+) -> Widget {
+    plot_with_legend(
+        ctx,
+        agent_type_registry().series(&app.cs, current, baseline),
+        |ctx, series| LinePlot::new(ctx, series, plot_opts),
+    )
+}
+
+/// Draws series from a `ModeRegistry` with their legend below, or a placeholder if there's
+/// nothing to plot yet.
+pub fn plot_with_legend<T: Yvalue<T>, F: FnOnce(&mut EventCtx, Vec<Series<T>>) -> Widget>(
+    ctx: &mut EventCtx,
+    (series, legend): (Vec<Series<T>>, Vec<(Color, String)>),
+    make_plot: F,
+) -> Widget {
+    if series.is_empty() {
+        return Line("No data yet").secondary().draw(ctx);
+    }
+    Widget::col(vec![
+        make_plot(ctx, series),
+        ColorLegend::entries(ctx, legend, true),
+    ])
+}
+
+/// Adds up hourly counts from several objects. Each series must come from `count_per_hour` at the
+/// same time, so the agent types and hourly buckets line up.
+pub fn sum_counts_per_hour(
+    per_object: Vec<Vec<(AgentType, Vec<(Time, usize)>)>>,
+) -> Vec<(AgentType, Vec<(Time, usize)>)> {
+    let mut total: Vec<(AgentType, Vec<(Time, usize)>)> = Vec::new();
+    for series in per_object {
+        if total.is_empty() {
+            total = series;
+            continue;
+        }
+        for ((_, sum_pts), (_, pts)) in total.iter_mut().zip(series) {
+            for ((_, sum), (_, cnt)) in sum_pts.iter_mut().zip(pts) {
+                *sum += cnt;
+            }
+        }
     }
+    total
 }
 
 pub fn color_for_trip_phase(app: &App, tpt: TripPhaseType) -> Color {
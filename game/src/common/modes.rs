@@ -0,0 +1,189 @@
+//! Plots and legends broken down by trip mode or agent type get their colors and labels from one
+//! registry. Anything missing from a registry, like a mode added later, still shows up, just in a
+//! neutral color and labeled by its debug name.
+//!
+//! The registries are built once. They store which color from the current color scheme to use,
+//! rather than the color itself, so switching color schemes doesn't require rebuilding them.
+
+use std::fmt::Debug;
+
+use geom::Time;
+use map_gui::colors::ColorScheme;
+use sim::{AgentType, TripMode};
+use widgetry::{Color, Series, Yvalue};
+
+/// Used for anything missing from a registry
+pub const FALLBACK_MODE_COLOR: Color = Color::grey(0.6);
+
+/// Baseline data is drawn faded
+const BASELINE_ALPHA: f32 = 0.3;
+
+lazy_static::lazy_static! {
+    static ref AGENT_TYPES: ModeRegistry<AgentType> = AgentType::all()
+        .into_iter()
+        .fold(ModeRegistry::new(), |registry, a| {
+            let color: fn(&ColorScheme) -> Color = match a {
+                AgentType::Car => |cs: &ColorScheme| cs.unzoomed_car,
+                AgentType::Bike => |cs: &ColorScheme| cs.unzoomed_bike,
+                AgentType::Bus | AgentType::Train => |cs: &ColorScheme| cs.unzoomed_bus,
+                AgentType::Pedestrian => |cs: &ColorScheme| cs.unzoomed_pedestrian,
+                AgentType::TransitRider => |cs: &ColorScheme| cs.bus_trip,
+            };
+            registry.add(a, color, a.noun())
+        });
+    static ref TRIP_MODES: ModeRegistry<TripMode> = ModeRegistry::new()
+        .add(TripMode::Walk, |cs| cs.unzoomed_pedestrian, TripMode::Walk.ongoing_verb())
+        .add(TripMode::Bike, |cs| cs.unzoomed_bike, TripMode::Bike.ongoing_verb())
+        .add(TripMode::Transit, |cs| cs.unzoomed_bus, TripMode::Transit.ongoing_verb())
+        .add(TripMode::Drive, |cs| cs.unzoomed_car, TripMode::Drive.ongoing_verb());
+}
+
+/// `S` is where colors come from, normally the current color scheme.
+pub struct ModeRegistry<K, S = ColorScheme> {
+    entries: Vec<(K, fn(&S) -> Color, String)>,
+}
+
+impl<K: Copy + PartialEq + Debug, S> ModeRegistry<K, S> {
+    pub fn new() -> ModeRegistry<K, S> {
+        ModeRegistry {
+            entries: Vec::new(),
+        }
+    }
+
+    pub fn add<I: Into<String>>(mut self, key: K, color: fn(&S) -> Color, label: I) -> Self {
+        self.entries.push((key, color, label.into()));
+        self
+    }
+
+    pub fn color(&self, colors: &S, key: K) -> Color {
+        self.entries
+            .iter()
+            .find(|(k, _, _)| *k == key)
+            .map(|(_, color, _)| color(colors))
+            .unwrap_or(FALLBACK_MODE_COLOR)
+    }
+
+    pub fn label(&self, key: K) -> String {
+        self.entries
+            .iter()
+            .find(|(k, _, _)| *k == key)
+            .map(|(_, _, label)| label.clone())
+            .unwrap_or_else(|| format!("{:?}", key))
+    }
+
+    /// Turns data broken down by mode into plot series, along with entries for a legend. Series
+    /// without anything above zero are left out, so they don't clutter the legend or throw off the
+    /// plot's scale. Baseline series share the label of the current ones, so toggling one in the
+    /// plot toggles both. If nothing is left, there's no data to plot yet.
+    pub fn series<T: Yvalue<T>>(
+        &self,
+        colors: &S,
+        current: Vec<(K, Vec<(Time, T)>)>,
+        baseline: Vec<(K, Vec<(Time, T)>)>,
+    ) -> (Vec<Series<T>>, Vec<(Color, String)>) {
+        let mut series = Vec::new();
+        let mut legend = Vec::new();
+        for (key, pts) in current {
+            if !has_data(&pts) {
+                continue;
+            }
+            legend.push((self.color(colors, key), self.label(key)));
+            series.push(Series {
+                label: self.label(key),
+                color: self.color(colors, key),
+                pts,
+            });
+        }
+        for (key, pts) in baseline {
+            if !has_data(&pts) {
+                continue;
+            }
+            let color = self.color(colors, key).alpha(BASELINE_ALPHA);
+            legend.push((color, format!("{} (baseline)", self.label(key))));
+            series.push(Series {
+                label: self.label(key),
+                color,
+                pts,
+            });
+        }
+        (series, legend)
+    }
+}
+
+fn has_data<T: Yvalue<T>>(pts: &[(Time, T)]) -> bool {
+    pts.iter().any(|(_, y)| *y > T::zero())
+}
+
+pub fn agent_type_registry() -> &'static ModeRegistry<AgentType> {
+    &AGENT_TYPES
+}
+
+pub fn trip_mode_registry() -> &'static ModeRegistry<TripMode> {
+    &TRIP_MODES
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Like TripMode, but with a fifth mode the registry doesn't know about
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    enum Mode {
+        Walk,
+        Bike,
+        Transit,
+        Drive,
+        Scooter,
+    }
+
+    fn registry() -> ModeRegistry<Mode, ()> {
+        ModeRegistry::new()
+            .add(Mode::Walk, |_| Color::GREEN, "walk")
+            .add(Mode::Bike, |_| Color::BLUE, "bike")
+            .add(Mode::Transit, |_| Color::RED, "transit")
+            .add(Mode::Drive, |_| Color::PURPLE, "drive")
+    }
+
+    fn hourly(counts: Vec<usize>) -> Vec<(Time, usize)> {
+        counts
+            .into_iter()
+            .enumerate()
+            .map(|(hour, cnt)| (Time::START_OF_DAY + geom::Duration::hours(hour), cnt))
+            .collect()
+    }
+
+    #[test]
+    fn test_unknown_mode() {
+        let registry = registry();
+        assert_eq!(registry.color(&(), Mode::Scooter), FALLBACK_MODE_COLOR);
+        assert_eq!(registry.label(Mode::Scooter), "Scooter");
+
+        let (series, legend) = registry.series(
+            &(),
+            vec![
+                (Mode::Walk, hourly(vec![3, 5])),
+                (Mode::Bike, Vec::new()),
+                (Mode::Transit, hourly(vec![0, 0])),
+                (Mode::Scooter, hourly(vec![1, 2])),
+            ],
+            vec![(Mode::Scooter, hourly(vec![2, 0]))],
+        );
+        let labels: Vec<&str> = series.iter().map(|s| s.label.as_str()).collect();
+        assert_eq!(labels, vec!["walk", "Scooter", "Scooter"]);
+        assert_eq!(series[1].color, FALLBACK_MODE_COLOR);
+        assert_eq!(series[2].color, FALLBACK_MODE_COLOR.alpha(BASELINE_ALPHA));
+        let legend: Vec<&str> = legend.iter().map(|(_, label)| label.as_str()).collect();
+        assert_eq!(legend, vec!["walk", "Scooter", "Scooter (baseline)"]);
+    }
+
+    #[test]
+    fn test_no_data() {
+        let (series, legend) = registry().series(
+            &(),
+            vec![(Mode::Drive, Vec::new()), (Mode::Scooter, hourly(vec![0]))],
+            Vec::new(),
+        );
+        assert!(series.is_empty());
+        assert!(legend.is_empty());
+    }
+}
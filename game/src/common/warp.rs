@@ -1,16 +1,18 @@
 use std::collections::BTreeMap;
 
 use geom::Pt2D;
-use map_gui::tools::{grey_out_map, PopupMsg};
+use map_gui::tools::grey_out_map;
 use map_gui::ID;
+use map_model::osm::{NodeID, OsmID, WayID};
 use map_model::{AreaID, BuildingID, BusRouteID, IntersectionID, LaneID, ParkingLotID, RoadID};
-use sim::{PedestrianID, PersonID, TripID};
+use sim::{PedestrianID, PersonID, PersonState, TripID, TripResult};
 use widgetry::{
-    EventCtx, GfxCtx, Key, Line, Outcome, Panel, State, StyledButtons, Text, TextExt, Warper,
-    Widget,
+    Color, EventCtx, GfxCtx, Key, Line, Outcome, Panel, State, StyledButtons, Text, TextExt,
+    Warper, Widget,
 };
 
 use crate::app::{App, PerMap, Transition};
+use crate::common::launch_info_panel;
 use crate::info::{OpenTrip, Tab};
 use crate::sandbox::SandboxMode;
 
@@ -69,18 +71,35 @@ impl State<App> for Warping {
 
 pub struct DebugWarp {
     panel: Panel,
+    // Warp as soon as possible, without waiting for the player to press Go
+    go_immediately: bool,
 }
 
 impl DebugWarp {
     pub fn new(ctx: &mut EventCtx) -> Box<dyn State<App>> {
+        DebugWarp::make(ctx, String::new(), false)
+    }
+
+    /// Warp to an object right away, as if the player had typed it in. If something's wrong with
+    /// the input, the usual panel stays open with the error.
+    pub fn go_to(ctx: &mut EventCtx, input: String) -> Box<dyn State<App>> {
+        DebugWarp::make(ctx, input, true)
+    }
+
+    fn make(ctx: &mut EventCtx, input: String, go_immediately: bool) -> Box<dyn State<App>> {
         let c = ctx.style().hotkey_color;
         Box::new(DebugWarp {
+            go_immediately,
             panel: Panel::new(Widget::col(vec![
                 Widget::row(vec![
                     Line("Warp to an object by ID").small_heading().draw(ctx),
                     ctx.style().btn_close_widget(ctx),
                 ]),
-                "Example: r42 is Road #42".draw_text(ctx),
+                Text::from_multiline(vec![
+                    Line("Example: r42 or r/42 is Road #42"),
+                    Line("w123 is whatever came from OSM way 123, n123 from OSM node 123"),
+                ])
+                .draw(ctx),
                 // T
                 // his
                 //
@@ -118,7 +137,8 @@ impl DebugWarp {
                     Line("ump to the previous position"),
                 ])
                 .draw(ctx),
-                Widget::text_entry(ctx, String::new(), true).named("input"),
+                Widget::text_entry(ctx, input, true).named("input"),
+                Text::new().draw(ctx).named("error"),
                 ctx.style()
                     .btn_outline_light_text("Go!")
                     .hotkey(Key::Enter)
@@ -129,25 +149,31 @@ impl DebugWarp {
     }
 }
 
+impl DebugWarp {
+    fn go(&mut self, ctx: &mut EventCtx, app: &mut App) -> Transition {
+        let input = self.panel.text_box("input");
+        match warp_to_id(ctx, app, input.trim()) {
+            Ok(t) => t,
+            Err(err) => {
+                // Keep the input around, so it can be fixed
+                let label = Line(err).fg(Color::RED).draw(ctx).named("error");
+                self.panel.replace(ctx, "error", label);
+                Transition::Keep
+            }
+        }
+    }
+}
+
 impl State<App> for DebugWarp {
     fn event(&mut self, ctx: &mut EventCtx, app: &mut App) -> Transition {
+        if self.go_immediately {
+            self.go_immediately = false;
+            return self.go(ctx, app);
+        }
         match self.panel.event(ctx) {
             Outcome::Clicked(x) => match x.as_ref() {
-                "close" => {
-                    return Transition::Pop;
-                }
-                "Go!" => {
-                    let input = self.panel.text_box("input");
-                    if let Some(t) = warp_to_id(ctx, app, &input) {
-                        t
-                    } else {
-                        Transition::Replace(PopupMsg::new(
-                            ctx,
-                            "Bad warp ID",
-                            vec![format!("{} isn't a valid ID", input)],
-                        ))
-                    }
-                }
+                "close" => Transition::Pop,
+                "Go!" => self.go(ctx, app),
                 _ => unreachable!(),
             },
             _ => Transition::Keep,
@@ -160,13 +186,13 @@ impl State<App> for DebugWarp {
     }
 }
 
-fn warp_to_id(ctx: &mut EventCtx, app: &mut App, line: &str) -> Option<Transition> {
+fn warp_to_id(ctx: &mut EventCtx, app: &mut App, line: &str) -> Result<Transition, String> {
     if line.is_empty() {
-        return None;
+        return Err("Type an ID first".to_string());
     }
     if line == "j" {
         if let Some((pt, zoom)) = app.primary.last_warped_from {
-            return Some(Transition::Replace(Warping::new(
+            return Ok(Transition::Replace(Warping::new(
                 ctx,
                 pt,
                 Some(zoom),
@@ -174,101 +200,140 @@ fn warp_to_id(ctx: &mut EventCtx, app: &mut App, line: &str) -> Option<Transitio
                 &mut app.primary,
             )));
         }
-        return None;
+        return Err("You haven't warped anywhere yet".to_string());
     }
 
-    let id = match usize::from_str_radix(&line[1..line.len()], 10) {
-        Ok(idx) => match line.chars().next().unwrap() {
-            'r' => {
-                let r = app.primary.map.maybe_get_r(RoadID(idx))?;
-                ID::Lane(r.lanes_ltr()[0].0)
-            }
-            'R' => {
-                let r = BusRouteID(idx);
-                app.primary.map.maybe_get_br(r)?;
-                return Some(Transition::Multi(vec![
-                    Transition::Pop,
-                    Transition::ModifyState(Box::new(move |state, ctx, app| {
-                        // Other states pretty much don't use info panels.
-                        if let Some(ref mut s) = state.downcast_mut::<SandboxMode>() {
-                            let mut actions = s.contextual_actions();
-                            s.controls.common.as_mut().unwrap().launch_info_panel(
-                                ctx,
-                                app,
-                                Tab::BusRoute(r),
-                                &mut actions,
-                            );
-                        }
-                    })),
-                ]));
-            }
-            'l' => ID::Lane(LaneID(idx)),
-            'L' => ID::ParkingLot(ParkingLotID(idx)),
-            'i' => ID::Intersection(IntersectionID(idx)),
-            'b' => ID::Building(BuildingID(idx)),
-            'a' => ID::Area(AreaID(idx)),
-            'p' => ID::Pedestrian(PedestrianID(idx)),
-            'P' => {
-                let id = PersonID(idx);
-                app.primary.sim.lookup_person(id)?;
-                return Some(Transition::Multi(vec![
-                    Transition::Pop,
-                    Transition::ModifyState(Box::new(move |state, ctx, app| {
-                        // Other states pretty much don't use info panels.
-                        if let Some(ref mut s) = state.downcast_mut::<SandboxMode>() {
-                            let mut actions = s.contextual_actions();
-                            s.controls.common.as_mut().unwrap().launch_info_panel(
-                                ctx,
-                                app,
-                                Tab::PersonTrips(id, BTreeMap::new()),
-                                &mut actions,
-                            );
-                        }
-                    })),
-                ]));
-            }
-            'c' => {
-                // This one gets more complicated. :)
-                let c = app.primary.sim.lookup_car_id(idx)?;
-                ID::Car(c)
-            }
-            't' => {
-                let trip = TripID(idx);
-                let person = app.primary.sim.trip_to_person(trip)?;
-                return Some(Transition::Multi(vec![
-                    Transition::Pop,
-                    Transition::ModifyState(Box::new(move |state, ctx, app| {
-                        // Other states pretty much don't use info panels.
-                        if let Some(ref mut s) = state.downcast_mut::<SandboxMode>() {
-                            let mut actions = s.contextual_actions();
-                            s.controls.common.as_mut().unwrap().launch_info_panel(
-                                ctx,
-                                app,
-                                Tab::PersonTrips(person, OpenTrip::single(trip)),
-                                &mut actions,
-                            );
-                        }
-                    })),
-                ]));
-            }
-            _ => {
-                return None;
-            }
-        },
-        Err(_) => {
-            return None;
+    // Don't slice the string directly; the first character might be multiple bytes
+    let mut chars = line.chars();
+    let prefix = chars.next().unwrap();
+    // Both i421 and i/421 work
+    let rest = chars.as_str();
+    let rest = rest.strip_prefix('/').unwrap_or(rest);
+    let not_found = || format!("{} doesn't exist", line);
+
+    // OSM IDs can be much bigger than our own, and are signed
+    if prefix == 'w' {
+        let way = WayID(
+            rest.parse::<i64>()
+                .map_err(|_| format!("{} isn't an OSM way ID", rest))?,
+        );
+        let id = if let Some(r) = app
+            .primary
+            .map
+            .all_roads()
+            .iter()
+            .find(|r| r.orig_id.osm_way_id == way)
+        {
+            ID::Lane(r.lanes_ltr()[0].0)
+        } else if let Some(b) = app.primary.map.find_b_by_osm_id(OsmID::Way(way)) {
+            ID::Building(b)
+        } else {
+            return Err(format!("No road or building comes from OSM way {}", way.0));
+        };
+        return warp_to_object(ctx, app, id).ok_or_else(not_found);
+    }
+    if prefix == 'n' {
+        let node = NodeID(
+            rest.parse::<i64>()
+                .map_err(|_| format!("{} isn't an OSM node ID", rest))?,
+        );
+        let i = app
+            .primary
+            .map
+            .find_i_by_osm_id(node)
+            .map_err(|_| format!("No intersection comes from OSM node {}", node.0))?;
+        return warp_to_object(ctx, app, ID::Intersection(i)).ok_or_else(not_found);
+    }
+
+    let idx = rest
+        .parse::<usize>()
+        .map_err(|_| format!("{} isn't a number", rest))?;
+    let id = match prefix {
+        'r' => {
+            let r = app
+                .primary
+                .map
+                .maybe_get_r(RoadID(idx))
+                .ok_or_else(not_found)?;
+            ID::Lane(r.lanes_ltr()[0].0)
+        }
+        'R' => {
+            let r = BusRouteID(idx);
+            app.primary.map.maybe_get_br(r).ok_or_else(not_found)?;
+            return Ok(Transition::Multi(vec![
+                Transition::Pop,
+                launch_info_panel(Tab::BusRoute(r)),
+            ]));
+        }
+        'l' => ID::Lane(LaneID(idx)),
+        'L' => ID::ParkingLot(ParkingLotID(idx)),
+        'i' => ID::Intersection(IntersectionID(idx)),
+        'b' => ID::Building(BuildingID(idx)),
+        'a' => ID::Area(AreaID(idx)),
+        'p' => ID::Pedestrian(PedestrianID(idx)),
+        'P' => {
+            let id = PersonID(idx);
+            app.primary.sim.lookup_person(id).ok_or_else(not_found)?;
+            return Ok(warp_to_person(
+                ctx,
+                app,
+                id,
+                Tab::PersonTrips(id, BTreeMap::new()),
+            ));
+        }
+        'c' => {
+            // This one gets more complicated. :)
+            let c = app.primary.sim.lookup_car_id(idx).ok_or_else(not_found)?;
+            ID::Car(c)
         }
+        't' => {
+            let trip = TripID(idx);
+            let person = app.primary.sim.trip_to_person(trip).ok_or_else(not_found)?;
+            return Ok(warp_to_person(
+                ctx,
+                app,
+                person,
+                Tab::PersonTrips(person, OpenTrip::single(trip)),
+            ));
+        }
+        _ => {
+            return Err(format!("{} isn't a known prefix", prefix));
+        }
+    };
+    warp_to_object(ctx, app, id).ok_or_else(not_found)
+}
+
+fn warp_to_object(ctx: &mut EventCtx, app: &mut App, id: ID) -> Option<Transition> {
+    let pt = app.primary.canonical_point(id.clone())?;
+    println!("Warping to {:?}", id);
+    Some(Transition::Replace(Warping::new(
+        ctx,
+        pt,
+        Some(WARP_TO_CAM_ZOOM),
+        Some(id),
+        &mut app.primary,
+    )))
+}
+
+// Open the info panel right away, then go wherever the person currently is, if they're on the map
+fn warp_to_person(ctx: &mut EventCtx, app: &mut App, person: PersonID, tab: Tab) -> Transition {
+    let pt = match app.primary.sim.get_person(person).state {
+        PersonState::Inside(b) => app.primary.canonical_point(ID::Building(b)),
+        PersonState::Trip(t) => match app.primary.sim.trip_to_agent(t) {
+            TripResult::Ok(a) => app.primary.sim.canonical_pt_for_agent(a, &app.primary.map),
+            _ => None,
+        },
+        PersonState::OffMap => None,
     };
-    if let Some(pt) = app.primary.canonical_point(id.clone()) {
-        println!("Warping to {:?}", id);
-        Some(Transition::Replace(Warping::new(
+    let mut transitions = vec![Transition::Pop, launch_info_panel(tab)];
+    if let Some(pt) = pt {
+        transitions.push(Transition::Push(Warping::new(
             ctx,
             pt,
             Some(WARP_TO_CAM_ZOOM),
-            Some(id),
+            None,
             &mut app.primary,
-        )))
-    } else {
-        None
+        )));
     }
+    Transition::Multi(transitions)
 }
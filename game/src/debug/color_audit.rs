@@ -0,0 +1,106 @@
+use map_gui::colors::ColorScheme;
+use map_gui::tools::PopupMsg;
+use widgetry::{
+    EventCtx, GfxCtx, Line, Outcome, Panel, State, StyledButtons, Text, TextExt, Widget,
+};
+
+use crate::app::{App, Transition};
+
+/// Compares the overrides file for the current color scheme against the colors that can actually
+/// be overridden, to catch typos and show what's left to customize.
+pub struct ColorAudit {
+    panel: Panel,
+}
+
+impl ColorAudit {
+    pub fn new(ctx: &mut EventCtx, app: &App) -> Box<dyn State<App>> {
+        let scheme = app.opts.color_scheme;
+        let defaults = ColorScheme::builtin(scheme).overridable_colors();
+
+        let mut col = vec![
+            Widget::row(vec![
+                Line("Color scheme audit").small_heading().draw(ctx),
+                ctx.style().btn_close_widget(ctx),
+            ]),
+            scheme.overrides_path().draw_text(ctx),
+        ];
+
+        match ColorScheme::read_overrides(scheme) {
+            Ok(overrides) => {
+                let unknown: Vec<&String> = overrides
+                    .keys()
+                    .filter(|name| !defaults.contains_key(*name))
+                    .collect();
+                let mut txt = Text::new();
+                txt.add(
+                    Line(format!("{} unknown colors in the file", unknown.len())).small_heading(),
+                );
+                for name in unknown {
+                    txt.add(Line(format!("- {}", name)));
+                }
+                txt.add(Line(""));
+
+                let missing: Vec<(&String, &String)> = defaults
+                    .iter()
+                    .filter(|(name, _)| !overrides.contains_key(*name))
+                    .collect();
+                txt.add(
+                    Line(format!(
+                        "{} colors not in the file, using the default",
+                        missing.len()
+                    ))
+                    .small_heading(),
+                );
+                for (name, default) in missing {
+                    txt.add(Line(format!("- {}: {}", name, default)));
+                }
+                col.push(txt.draw(ctx));
+            }
+            Err(err) => {
+                col.push(format!("Couldn't read the file: {}", err).draw_text(ctx));
+            }
+        }
+
+        col.push(
+            ctx.style()
+                .btn_outline_light_text("export full template")
+                .build_def(ctx),
+        );
+
+        Box::new(ColorAudit {
+            panel: Panel::new(Widget::col(col))
+                .exact_size_percent(50, 80)
+                .build(ctx),
+        })
+    }
+}
+
+impl State<App> for ColorAudit {
+    fn event(&mut self, ctx: &mut EventCtx, app: &mut App) -> Transition {
+        match self.panel.event(ctx) {
+            Outcome::Clicked(x) => match x.as_ref() {
+                "close" => Transition::Pop,
+                "export full template" => {
+                    // Don't clobber the real overrides; the template can be copied over them
+                    let path = app
+                        .opts
+                        .color_scheme
+                        .overrides_path()
+                        .replace(".json", "_template.json");
+                    abstio::write_json(path.clone(), &app.cs.overridable_colors());
+                    Transition::Push(PopupMsg::new(
+                        ctx,
+                        "Template exported",
+                        vec![format!("Wrote every color currently in effect to {}", path)],
+                    ))
+                }
+                _ => unreachable!(),
+            },
+            _ => Transition::Keep,
+        }
+    }
+
+    fn draw(&self, g: &mut GfxCtx, _: &App) {
+        self.panel.draw(g);
+    }
+}
@@ -0,0 +1,101 @@
+use map_gui::tools::PopupMsg;
+use map_gui::ID;
+use sim::CarID;
+use widgetry::{
+    EventCtx, GfxCtx, HorizontalAlignment, Line, Outcome, Panel, State, StyledButtons, TextExt,
+    VerticalAlignment, Widget,
+};
+
+use crate::app::App;
+use crate::app::Transition;
+use crate::common::{CommonState, Warping};
+
+// Enough to find the problems without a giant panel
+const MAX_LISTED: usize = 20;
+
+/// Lists the vehicles on the map that have made others wait the longest behind them, like a bus
+/// stopped in a travel lane or a car waiting for a gap to turn left.
+pub struct DelayCausers {
+    panel: Panel,
+    cars: Vec<CarID>,
+}
+
+impl DelayCausers {
+    pub fn new(ctx: &mut EventCtx, app: &App) -> Box<dyn State<App>> {
+        let worst = app.primary.sim.worst_delay_causers(MAX_LISTED);
+
+        let mut col = vec![Widget::row(vec![
+            Line("Vehicles causing the most delay")
+                .small_heading()
+                .draw(ctx),
+            ctx.style().btn_close_widget(ctx),
+        ])];
+        if worst.is_empty() {
+            col.push("Nobody has blocked anybody yet".draw_text(ctx));
+        }
+        for (idx, (car, delay)) in worst.iter().enumerate() {
+            col.push(Widget::row(vec![
+                ctx.style()
+                    .btn_plain_light_icon("system/assets/tools/location.svg")
+                    .build_widget(ctx, &format!("jump to #{}", idx))
+                    .centered_vert(),
+                format!(
+                    "{}: {} lost by vehicles behind it",
+                    car,
+                    delay.to_string(&app.opts.units)
+                )
+                .draw_text(ctx)
+                .centered_vert(),
+            ]));
+        }
+
+        Box::new(DelayCausers {
+            panel: Panel::new(Widget::col(col))
+                .aligned(HorizontalAlignment::Center, VerticalAlignment::Top)
+                .build(ctx),
+            cars: worst.into_iter().map(|(car, _)| car).collect(),
+        })
+    }
+}
+
+impl State<App> for DelayCausers {
+    fn event(&mut self, ctx: &mut EventCtx, app: &mut App) -> Transition {
+        ctx.canvas_movement();
+        if ctx.redo_mouseover() {
+            app.recalculate_current_selection(ctx);
+        }
+
+        if let Outcome::Clicked(x) = self.panel.event(ctx) {
+            if x == "close" {
+                return Transition::Pop;
+            }
+            let idx = x
+                .strip_prefix("jump to #")
+                .unwrap()
+                .parse::<usize>()
+                .unwrap();
+            let id = ID::Car(self.cars[idx]);
+            return match app.primary.canonical_point(id.clone()) {
+                Some(pt) => Transition::Push(Warping::new(
+                    ctx,
+                    pt,
+                    Some(10.0),
+                    Some(id),
+                    &mut app.primary,
+                )),
+                None => Transition::Push(PopupMsg::new(
+                    ctx,
+                    "Vehicle is gone",
+                    vec![format!("{} isn't on the map anymore", self.cars[idx])],
+                )),
+            };
+        }
+
+        Transition::Keep
+    }
+
+    fn draw(&self, g: &mut GfxCtx, app: &App) {
+        self.panel.draw(g);
+        CommonState::draw_osd(g, app);
+    }
+}
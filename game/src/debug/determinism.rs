@@ -0,0 +1,177 @@
+use abstutil::Timer;
+use geom::{Duration, Time};
+use map_gui::tools::{grey_out_map, PopupMsg};
+use map_model::Map;
+use sim::{Scenario, Sim, SimDigest, SimFlags};
+use widgetry::{
+    Color, EventCtx, GfxCtx, Key, Line, Outcome, Panel, Spinner, State, StyledButtons, Text,
+    TextExt, Widget,
+};
+
+use crate::app::{App, Transition};
+
+/// Runs the current scenario twice from scratch with the same RNG seed, comparing the simulation
+/// state at regular checkpoints. Any difference means something in the simulation isn't
+/// deterministic, like iterating over a HashMap.
+pub struct DeterminismChecker {
+    panel: Panel,
+}
+
+impl DeterminismChecker {
+    pub fn new(ctx: &mut EventCtx, app: &App) -> Box<dyn State<App>> {
+        let scenario = match app.primary.scenario {
+            Some(ref s) => s,
+            None => {
+                return PopupMsg::new(
+                    ctx,
+                    "Can't check determinism",
+                    vec!["Start a scenario loaded from a file first"],
+                );
+            }
+        };
+
+        Box::new(DeterminismChecker {
+            panel: Panel::new(Widget::col(vec![
+                Widget::row(vec![
+                    Line("Check simulation determinism")
+                        .small_heading()
+                        .draw(ctx),
+                    ctx.style().btn_close_widget(ctx),
+                ]),
+                format!(
+                    "Simulates {} twice from midnight with the same seed, comparing the state at \
+                     each checkpoint.",
+                    scenario.scenario_name
+                )
+                .draw_text(ctx),
+                Widget::row(vec![
+                    "Run until hour".draw_text(ctx).centered_vert(),
+                    Spinner::new(ctx, (1, 24), 1).named("hours"),
+                ]),
+                Widget::row(vec![
+                    "Check every".draw_text(ctx).centered_vert(),
+                    Spinner::new(ctx, (1, 60), 5).named("minutes"),
+                    "minutes".draw_text(ctx).centered_vert(),
+                ]),
+                ctx.style()
+                    .btn_solid_dark_text("Run")
+                    .hotkey(Key::Enter)
+                    .build_def(ctx)
+                    .centered_horiz(),
+                Text::new().draw(ctx).named("results"),
+            ]))
+            .build(ctx),
+        })
+    }
+}
+
+impl State<App> for DeterminismChecker {
+    fn event(&mut self, ctx: &mut EventCtx, app: &mut App) -> Transition {
+        match self.panel.event(ctx) {
+            Outcome::Clicked(x) => match x.as_ref() {
+                "close" => Transition::Pop,
+                "Run" => {
+                    let end =
+                        Time::START_OF_DAY + Duration::hours(self.panel.spinner("hours") as usize);
+                    let interval = Duration::minutes(self.panel.spinner("minutes") as usize);
+                    let scenario = app.primary.scenario.as_ref().unwrap();
+                    let (matched, divergence) =
+                        ctx.loading_screen("check simulation determinism", |_, timer| {
+                            run_twice(
+                                &app.primary.map,
+                                scenario,
+                                &app.primary.current_flags.sim_flags,
+                                end,
+                                interval,
+                                timer,
+                            )
+                        });
+
+                    let mut txt = Text::new();
+                    match divergence {
+                        Some((digest1, digest2)) => {
+                            txt.add(
+                                Line(format!(
+                                    "The runs diverged by {}, after {} matching checkpoints",
+                                    digest1.time, matched
+                                ))
+                                .fg(Color::RED),
+                            );
+                            for diff in digest1.differences(&digest2) {
+                                txt.add(Line(format!("- {}", diff)));
+                            }
+                        }
+                        None => {
+                            txt.add(
+                                Line(format!(
+                                    "All {} checkpoints through {} matched",
+                                    matched, end
+                                ))
+                                .fg(Color::GREEN),
+                            );
+                        }
+                    }
+                    self.panel
+                        .replace(ctx, "results", txt.draw(ctx).named("results"));
+                    Transition::Keep
+                }
+                _ => unreachable!(),
+            },
+            _ => Transition::Keep,
+        }
+    }
+
+    fn draw(&self, g: &mut GfxCtx, app: &App) {
+        grey_out_map(g, app);
+        self.panel.draw(g);
+    }
+}
+
+/// Returns the number of checkpoints that matched, then the first pair of digests that didn't.
+fn run_twice(
+    map: &Map,
+    scenario: &Scenario,
+    flags: &SimFlags,
+    end: Time,
+    interval: Duration,
+    timer: &mut Timer,
+) -> (usize, Option<(SimDigest, SimDigest)>) {
+    let mut sims = Vec::new();
+    for run in 1..=2 {
+        timer.start(format!("instantiate run {}", run));
+        let mut sim = Sim::new(map, flags.opts.clone());
+        scenario.instantiate(&mut sim, map, &mut flags.make_rng(), timer);
+        sims.push(sim);
+        timer.stop(format!("instantiate run {}", run));
+    }
+
+    // The first checkpoint is right after instantiating, which has its own randomness
+    let mut checkpoint = Time::START_OF_DAY;
+    let mut matched = 0;
+    loop {
+        let name = format!("checkpoint {} ({})", matched + 1, checkpoint);
+        timer.start(&name);
+        let mut digests = Vec::new();
+        for sim in &mut sims {
+            if sim.time() < checkpoint {
+                sim.timed_step(map, checkpoint - sim.time(), &mut None, timer);
+            }
+            digests.push(sim.digest(map));
+        }
+        timer.stop(&name);
+
+        let digest2 = digests.pop().unwrap();
+        let digest1 = digests.pop().unwrap();
+        if digest1 != digest2 {
+            return (matched, Some((digest1, digest2)));
+        }
+        matched += 1;
+        if checkpoint >= end {
+            return (matched, None);
+        }
+        checkpoint = checkpoint + interval;
+        if checkpoint > end {
+            checkpoint = end;
+        }
+    }
+}
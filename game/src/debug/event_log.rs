@@ -0,0 +1,272 @@
+use std::collections::BTreeSet;
+use std::fs::File;
+use std::io::Write;
+
+use anyhow::Result;
+
+use geom::Percent;
+use map_gui::tools::PopupMsg;
+use map_gui::ID;
+use sim::{EventCategory, EventLogEntry, EventSubject};
+use widgetry::{
+    Checkbox, EventCtx, GfxCtx, HorizontalAlignment, Line, Outcome, Panel, State, StyledButtons,
+    Text, TextExt, VerticalAlignment, Widget,
+};
+
+use crate::app::{App, Transition};
+use crate::common::{CommonState, Warping};
+
+// Enough to cover a while, without the log growing forever
+const CAPACITY: usize = 10_000;
+// Building a widget per event is slow, so only list this many
+const MAX_SHOWN: usize = 100;
+
+/// Shows the most recent interesting events from the simulation, filtered by category and text.
+/// Recording keeps going in the background after this is closed, until it's turned off.
+pub struct EventLogViewer {
+    panel: Panel,
+    // What each "jump to" button goes to
+    subjects: Vec<EventSubject>,
+}
+
+impl EventLogViewer {
+    pub fn new(ctx: &mut EventCtx, app: &App) -> Box<dyn State<App>> {
+        let mut viewer = EventLogViewer {
+            panel: make_panel(ctx, app),
+            subjects: Vec::new(),
+        };
+        viewer.update_entries(ctx, app);
+        Box::new(viewer)
+    }
+
+    fn update_entries(&mut self, ctx: &mut EventCtx, app: &App) {
+        self.subjects.clear();
+        let log = match app.primary.sim.get_event_log() {
+            Some(log) => log,
+            None => {
+                self.panel.replace(
+                    ctx,
+                    "entries",
+                    "Not recording. Start, then run the simulation.".draw_text(ctx),
+                );
+                return;
+            }
+        };
+
+        let categories: BTreeSet<EventCategory> = EventCategory::all()
+            .into_iter()
+            .filter(|c| self.panel.is_checked(c.describe()))
+            .collect();
+        let filter = self.panel.text_box("filter").to_ascii_lowercase();
+        let map = &app.primary.map;
+        let mut col = Vec::new();
+        for entry in log.entries() {
+            if !categories.contains(&entry.category) {
+                continue;
+            }
+            let description = entry.describe(map);
+            if !filter.is_empty() && !description.to_ascii_lowercase().contains(&filter) {
+                continue;
+            }
+            let mut row = vec![Line(entry.time.ampm_tostring())
+                .secondary()
+                .draw(ctx)
+                .centered_vert()];
+            if let Some(subject) = entry.subject() {
+                row.push(
+                    ctx.style()
+                        .btn_plain_light_icon("system/assets/tools/location.svg")
+                        .build_widget(ctx, &format!("jump to #{}", self.subjects.len()))
+                        .centered_vert(),
+                );
+                self.subjects.push(subject);
+            }
+            row.push(
+                Text::from(Line(description))
+                    .wrap_to_pct(ctx, 30)
+                    .draw(ctx)
+                    .centered_vert(),
+            );
+            col.push(Widget::row(row));
+            if col.len() == MAX_SHOWN {
+                col.push(format!("Only the newest {} are shown", MAX_SHOWN).draw_text(ctx));
+                break;
+            }
+        }
+        if col.is_empty() {
+            col.push("Nothing matches".draw_text(ctx));
+        }
+        self.panel.replace(ctx, "entries", Widget::col(col));
+    }
+}
+
+impl State<App> for EventLogViewer {
+    fn event(&mut self, ctx: &mut EventCtx, app: &mut App) -> Transition {
+        ctx.canvas_movement();
+        if ctx.redo_mouseover() {
+            app.recalculate_current_selection(ctx);
+        }
+        if let Some(ID::Intersection(i)) = app.primary.current_selection {
+            if app.primary.map.get_i(i).is_traffic_signal()
+                && app.per_obj.left_click(ctx, "watch/unwatch this signal")
+            {
+                app.primary.sim.enable_event_log(CAPACITY);
+                app.primary
+                    .sim
+                    .get_mut_event_log()
+                    .unwrap()
+                    .toggle_watching(i);
+                self.panel = make_panel(ctx, app);
+                self.update_entries(ctx, app);
+            }
+        }
+
+        match self.panel.event(ctx) {
+            Outcome::Clicked(x) => match x.as_ref() {
+                "close" => {
+                    return Transition::Pop;
+                }
+                "start recording" => {
+                    app.primary.sim.enable_event_log(CAPACITY);
+                    self.panel = make_panel(ctx, app);
+                    self.update_entries(ctx, app);
+                }
+                "stop recording" => {
+                    app.primary.sim.disable_event_log();
+                    self.panel = make_panel(ctx, app);
+                    self.update_entries(ctx, app);
+                }
+                "export to file" => {
+                    return Transition::Push(match export(app) {
+                        Ok(path) => PopupMsg::new(
+                            ctx,
+                            "Log exported",
+                            vec![format!("Log exported to {}", path)],
+                        ),
+                        Err(err) => PopupMsg::new(ctx, "Export failed", vec![err.to_string()]),
+                    });
+                }
+                x => {
+                    let idx = x
+                        .strip_prefix("jump to #")
+                        .unwrap()
+                        .parse::<usize>()
+                        .unwrap();
+                    return jump_to(ctx, app, self.subjects[idx]);
+                }
+            },
+            Outcome::Changed => {
+                self.update_entries(ctx, app);
+            }
+            _ => {}
+        }
+
+        Transition::Keep
+    }
+
+    fn draw(&self, g: &mut GfxCtx, app: &App) {
+        self.panel.draw(g);
+        CommonState::draw_osd(g, app);
+    }
+}
+
+fn make_panel(ctx: &mut EventCtx, app: &App) -> Panel {
+    let log = app.primary.sim.get_event_log();
+    let mut col = vec![Widget::row(vec![
+        Line("Event log").small_heading().draw(ctx),
+        ctx.style().btn_close_widget(ctx),
+    ])];
+    if log.is_some() {
+        col.push(Widget::row(vec![
+            ctx.style()
+                .btn_outline_light_text("stop recording")
+                .build_def(ctx),
+            ctx.style()
+                .btn_outline_light_text("export to file")
+                .build_def(ctx),
+        ]));
+    } else {
+        col.push(
+            ctx.style()
+                .btn_outline_light_text("start recording")
+                .build_def(ctx),
+        );
+    }
+    for category in EventCategory::all() {
+        col.push(Checkbox::checkbox(ctx, category.describe(), None, true));
+    }
+    let watched = log
+        .map(|log| log.watched_intersections().len())
+        .unwrap_or(0);
+    col.push(
+        Text::from(
+            Line(format!(
+                "Watching {} traffic signals. Click on one to watch or unwatch it.",
+                watched
+            ))
+            .secondary(),
+        )
+        .wrap_to_pct(ctx, 30)
+        .draw(ctx),
+    );
+    col.push(Widget::row(vec![
+        "Search:".draw_text(ctx).centered_vert(),
+        Widget::text_entry(ctx, String::new(), false).named("filter"),
+    ]));
+    col.push(Widget::nothing().named("entries"));
+
+    Panel::new(Widget::col(col))
+        .aligned(HorizontalAlignment::Right, VerticalAlignment::Top)
+        .max_size(Percent::int(40), Percent::int(80))
+        .build(ctx)
+}
+
+fn jump_to(ctx: &mut EventCtx, app: &mut App, subject: EventSubject) -> Transition {
+    let id = match subject {
+        EventSubject::Trip(trip) => match app.primary.sim.trip_to_agent(trip).ok() {
+            Some(agent) => ID::from_agent(agent),
+            None => {
+                return Transition::Push(PopupMsg::new(
+                    ctx,
+                    "Trip is over",
+                    vec![format!("{} isn't happening right now", trip)],
+                ));
+            }
+        },
+        EventSubject::Agent(agent) => ID::from_agent(agent),
+        EventSubject::Building(b) => ID::Building(b),
+        EventSubject::Intersection(i) => ID::Intersection(i),
+        EventSubject::Lane(l) => ID::Lane(l),
+    };
+    match app.primary.canonical_point(id.clone()) {
+        Some(pt) => Transition::Push(Warping::new(
+            ctx,
+            pt,
+            Some(10.0),
+            Some(id),
+            &mut app.primary,
+        )),
+        None => Transition::Push(PopupMsg::new(
+            ctx,
+            "Gone",
+            vec![format!("{:?} isn't on the map anymore", id)],
+        )),
+    }
+}
+
+/// Writes every entry to a text file, oldest first, returning the path.
+fn export(app: &App) -> Result<String> {
+    let path = format!("event_log_{}.txt", app.primary.sim.time().as_filename());
+    let mut f = File::create(&path)?;
+    let entries: Vec<&EventLogEntry> = app.primary.sim.get_event_log().unwrap().entries().collect();
+    for entry in entries.into_iter().rev() {
+        writeln!(
+            f,
+            "{} [{:?}] {}",
+            entry.time.ampm_tostring(),
+            entry.category,
+            entry.describe(&app.primary.map)
+        )?;
+    }
+    Ok(path)
+}
@@ -0,0 +1,205 @@
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+
+use anyhow::Result;
+use geojson::feature::Id;
+use geojson::{Feature, Geometry, Value};
+use serde_json::Map as Properties;
+
+use geom::Duration;
+use map_model::RoadID;
+
+use crate::app::App;
+
+/// Exports roads, intersections, buildings, and bus stops as separate GeoJSON files, to overlay in
+/// something like QGIS. Results from the current simulation are attached as properties, along with
+/// IDs to join them back to the map. Returns the path and number of features of each file.
+pub fn export(app: &App) -> Result<Vec<(String, usize)>> {
+    let map = &app.primary.map;
+    let gps_bounds = Some(map.get_gps_bounds());
+    let analytics = app.primary.sim.get_analytics();
+    let lang = app.opts.language.as_ref();
+
+    let dir = format!(
+        "geojson_{}_{}",
+        map.get_name().as_filename(),
+        app.primary.sim.time().as_filename()
+    );
+    std::fs::create_dir_all(&dir)?;
+    let mut results = Vec::new();
+
+    // Per road, how many times agents crossed one of its lanes well under the speed limit, and
+    // what percent of the limit they managed on average those times
+    let mut slowdowns: BTreeMap<RoadID, (usize, usize)> = BTreeMap::new();
+    for per_lane in analytics.lane_speed_percentage.values() {
+        for (l, pct) in per_lane {
+            let entry = slowdowns.entry(map.get_l(*l).parent).or_insert((0, 0));
+            entry.0 += 1;
+            entry.1 += *pct as usize;
+        }
+    }
+    let mut roads = FeatureWriter::new(format!("{}/roads.geojson", dir))?;
+    for r in map.all_roads() {
+        let mut props = Properties::new();
+        props.insert("id".to_string(), r.id.0.into());
+        props.insert("osm_way_id".to_string(), r.orig_id.osm_way_id.0.into());
+        props.insert("name".to_string(), r.get_name(lang).into());
+        props.insert(
+            "speed_limit_meters_per_second".to_string(),
+            r.speed_limit.inner_meters_per_second().into(),
+        );
+        props.insert(
+            "throughput".to_string(),
+            analytics.road_thruput.total_for(r.id).into(),
+        );
+        let (num_slowdowns, sum_pct) = slowdowns.get(&r.id).cloned().unwrap_or((0, 0));
+        props.insert("slowdowns".to_string(), num_slowdowns.into());
+        if num_slowdowns > 0 {
+            props.insert(
+                "avg_pct_of_speed_limit_when_slow".to_string(),
+                (sum_pct / num_slowdowns).into(),
+            );
+        }
+        roads.add(r.id.0, r.center_pts.to_geojson(gps_bounds), props)?;
+    }
+    results.push(roads.finish()?);
+
+    let mut intersections = FeatureWriter::new(format!("{}/intersections.geojson", dir))?;
+    for i in map.all_intersections() {
+        let mut props = Properties::new();
+        props.insert("id".to_string(), i.id.0.into());
+        props.insert("osm_node_id".to_string(), i.orig_id.0.into());
+        props.insert(
+            "throughput".to_string(),
+            analytics.intersection_thruput.total_for(i.id).into(),
+        );
+        // Only recorded for traffic signals
+        if let Some(delays) = analytics.intersection_delays.get(&i.id) {
+            let total: Duration = delays.iter().map(|(_, _, dt, _)| *dt).sum();
+            let max = delays
+                .iter()
+                .map(|(_, _, dt, _)| *dt)
+                .max()
+                .unwrap_or(Duration::ZERO);
+            props.insert("delays".to_string(), delays.len().into());
+            if !delays.is_empty() {
+                props.insert(
+                    "avg_delay_seconds".to_string(),
+                    (total / (delays.len() as f64)).inner_seconds().into(),
+                );
+                props.insert("max_delay_seconds".to_string(), max.inner_seconds().into());
+            }
+        }
+        intersections.add(i.id.0, i.polygon.to_geojson(gps_bounds), props)?;
+    }
+    results.push(intersections.finish()?);
+
+    let mut bldgs = FeatureWriter::new(format!("{}/buildings.geojson", dir))?;
+    for b in map.all_buildings() {
+        let mut props = Properties::new();
+        props.insert("id".to_string(), b.id.0.into());
+        props.insert("osm_id".to_string(), b.orig_id.to_string().into());
+        props.insert("address".to_string(), b.address.clone().into());
+        if let Some(ref names) = b.name {
+            props.insert("name".to_string(), names.get(lang).into());
+        }
+        props.insert(
+            "people_inside".to_string(),
+            app.primary.sim.bldg_to_people(b.id).len().into(),
+        );
+        bldgs.add(b.id.0, b.polygon.to_geojson(gps_bounds), props)?;
+    }
+    results.push(bldgs.finish()?);
+
+    let mut stops = FeatureWriter::new(format!("{}/bus_stops.geojson", dir))?;
+    for (idx, bs) in map.all_bus_stops().values().enumerate() {
+        let mut props = Properties::new();
+        // Bus stop IDs aren't a single number, so use the same string as everywhere else
+        props.insert("id".to_string(), bs.id.to_string().into());
+        props.insert("name".to_string(), bs.name.clone().into());
+        props.insert(
+            "routes".to_string(),
+            map.get_routes_serving_stop(bs.id)
+                .into_iter()
+                .map(|r| r.short_name.clone())
+                .collect::<Vec<_>>()
+                .join(", ")
+                .into(),
+        );
+        let boardings = analytics
+            .passengers_boarding
+            .get(&bs.id)
+            .map(|x| x.as_slice())
+            .unwrap_or(&[]);
+        props.insert("boardings".to_string(), boardings.len().into());
+        if !boardings.is_empty() {
+            let total: Duration = boardings.iter().map(|(_, _, dt)| *dt).sum();
+            props.insert(
+                "avg_wait_seconds".to_string(),
+                (total / (boardings.len() as f64)).inner_seconds().into(),
+            );
+        }
+        props.insert(
+            "alightings".to_string(),
+            analytics
+                .passengers_alighting
+                .get(&bs.id)
+                .map(|x| x.len())
+                .unwrap_or(0)
+                .into(),
+        );
+        let pt = bs.sidewalk_pos.pt(map).to_gps(map.get_gps_bounds());
+        stops.add(
+            idx,
+            Geometry::new(Value::Point(vec![pt.x(), pt.y()])),
+            props,
+        )?;
+    }
+    results.push(stops.finish()?);
+
+    Ok(results)
+}
+
+/// Writes a FeatureCollection one feature at a time, so exporting a large map doesn't need all of
+/// it in memory at once.
+struct FeatureWriter {
+    path: String,
+    f: BufWriter<File>,
+    count: usize,
+}
+
+impl FeatureWriter {
+    fn new(path: String) -> Result<FeatureWriter> {
+        let mut f = BufWriter::new(File::create(&path)?);
+        writeln!(f, "{{\"type\":\"FeatureCollection\",\"features\":[")?;
+        Ok(FeatureWriter { path, f, count: 0 })
+    }
+
+    fn add(
+        &mut self,
+        id: usize,
+        geometry: Geometry,
+        properties: Properties<String, serde_json::Value>,
+    ) -> Result<()> {
+        if self.count > 0 {
+            writeln!(self.f, ",")?;
+        }
+        let feature = Feature {
+            bbox: None,
+            geometry: Some(geometry),
+            id: Some(Id::Number(id.into())),
+            properties: Some(properties),
+            foreign_members: None,
+        };
+        serde_json::to_writer(&mut self.f, &feature)?;
+        self.count += 1;
+        Ok(())
+    }
+
+    fn finish(mut self) -> Result<(String, usize)> {
+        writeln!(self.f, "\n]}}")?;
+        self.f.flush()?;
+        Ok((self.path, self.count))
+    }
+}
@@ -0,0 +1,294 @@
+use aabb_quadtree::QuadTree;
+
+use geom::{Bounds, Circle, Distance, Polygon, Pt2D, Ring, UnitFmt};
+use widgetry::{
+    Color, EventCtx, GeomBatch, GfxCtx, HorizontalAlignment, Key, Line, Outcome, Panel, State,
+    StyledButtons, Text, TextExt, VerticalAlignment, Widget,
+};
+
+use crate::app::{App, Transition};
+
+/// The cursor snaps to something this close, in pixels
+const SNAP_RADIUS: f64 = 10.0;
+/// Segments are drawn this thick, in pixels
+const THICKNESS: f64 = 3.0;
+
+/// Measures distances along paths placed by clicking on the map, and the area of paths that loop
+/// back to the start. Many measurements can be shown at once, each in a different color.
+pub struct MeasureTool {
+    panel: Panel,
+    /// Intersection centers and building corners
+    snap_to: QuadTree<Pt2D>,
+    finished: Vec<Measurement>,
+    current: Measurement,
+    /// Where the next point would go, after snapping
+    cursor: Option<Pt2D>,
+}
+
+struct Measurement {
+    pts: Vec<Pt2D>,
+    /// Only set when the path loops back to its first point
+    area: Option<Polygon>,
+    color: Color,
+}
+
+impl Measurement {
+    fn new(app: &App, idx: usize) -> Measurement {
+        Measurement {
+            pts: Vec::new(),
+            area: None,
+            color: app.cs.rotating_color_plot(idx),
+        }
+    }
+
+    fn segments(&self) -> Vec<geom::Line> {
+        let mut segments: Vec<geom::Line> = self
+            .pts
+            .windows(2)
+            .filter_map(|pair| geom::Line::new(pair[0], pair[1]))
+            .collect();
+        if self.area.is_some() {
+            segments.extend(geom::Line::new(*self.pts.last().unwrap(), self.pts[0]));
+        }
+        segments
+    }
+
+    fn length(&self) -> Distance {
+        self.segments().into_iter().map(|l| l.length()).sum()
+    }
+
+    fn describe(&self, units: &UnitFmt) -> String {
+        match self.area {
+            Some(ref polygon) => format!(
+                "{} around, enclosing {}",
+                self.length().to_string(units),
+                polygon.area_to_string(units)
+            ),
+            None => self.length().to_string(units),
+        }
+    }
+}
+
+impl MeasureTool {
+    pub fn new(ctx: &mut EventCtx, app: &App) -> Box<dyn State<App>> {
+        let map = &app.primary.map;
+        let mut snap_to = QuadTree::default(map.get_bounds().as_bbox());
+        let mut add = |pt: Pt2D| {
+            snap_to.insert_with_box(pt, Bounds::from(&vec![pt]).as_bbox());
+        };
+        for i in map.all_intersections() {
+            add(i.polygon.center());
+        }
+        for b in map.all_buildings() {
+            for pt in b.polygon.points() {
+                add(*pt);
+            }
+        }
+
+        let mut tool = MeasureTool {
+            panel: Panel::empty(ctx),
+            snap_to,
+            finished: Vec::new(),
+            current: Measurement::new(app, 0),
+            cursor: None,
+        };
+        tool.update_panel(ctx, app);
+        Box::new(tool)
+    }
+
+    fn update_panel(&mut self, ctx: &mut EventCtx, app: &App) {
+        self.panel = Panel::new(Widget::col(vec![
+            Widget::row(vec![
+                Line("Measure distances").small_heading().draw(ctx),
+                ctx.style().btn_close_widget(ctx),
+            ]),
+            "Click to add points. Click the first point again to measure an area.".draw_text(ctx),
+            self.describe(app).draw(ctx).named("measurements"),
+            Widget::row(vec![
+                ctx.style()
+                    .btn_outline_light_text("undo last point")
+                    .hotkey(Key::Backspace)
+                    .disabled(self.current.pts.is_empty() && self.finished.is_empty())
+                    .build_def(ctx),
+                ctx.style()
+                    .btn_outline_light_text("finish measurement")
+                    .hotkey(Key::Enter)
+                    .disabled(self.current.pts.len() < 2)
+                    .build_def(ctx),
+                ctx.style()
+                    .btn_outline_light_text("clear all")
+                    .disabled(self.current.pts.is_empty() && self.finished.is_empty())
+                    .build_def(ctx),
+            ]),
+        ]))
+        .aligned(HorizontalAlignment::Center, VerticalAlignment::Top)
+        .build(ctx);
+    }
+
+    fn describe(&self, app: &App) -> Text {
+        let mut txt = Text::new();
+        for (idx, m) in self.finished.iter().enumerate() {
+            txt.add(Line(format!("{}) {}", idx + 1, m.describe(&app.opts.units))).fg(m.color));
+        }
+        if let Some(last) = self.current.pts.last() {
+            // Include the segment to the cursor
+            let mut length = self.current.length();
+            if let Some(pt) = self.cursor {
+                length += last.dist_to(pt);
+            }
+            txt.add(
+                Line(format!(
+                    "{}) {} so far",
+                    self.finished.len() + 1,
+                    length.to_string(&app.opts.units)
+                ))
+                .fg(self.current.color),
+            );
+        }
+        if txt.is_empty() {
+            txt.add(Line("Click the map to start measuring"));
+        }
+        txt
+    }
+
+    /// Snaps to the first point of the current path if that would close the loop, otherwise to the
+    /// nearest intersection or building corner.
+    fn snap(&self, ctx: &EventCtx, pt: Pt2D) -> Pt2D {
+        let radius = Distance::meters(SNAP_RADIUS / ctx.canvas.cam_zoom);
+        if self.current.pts.len() >= 3 && self.current.pts[0].dist_to(pt) <= radius {
+            return self.current.pts[0];
+        }
+        self.snap_to
+            .query(Circle::new(pt, radius).get_bounds().as_bbox())
+            .into_iter()
+            .map(|(snap_pt, _, _)| *snap_pt)
+            .filter(|snap_pt| snap_pt.dist_to(pt) <= radius)
+            .min_by_key(|snap_pt| snap_pt.dist_to(pt))
+            .unwrap_or(pt)
+    }
+
+    fn finish_current(&mut self, app: &App) {
+        let next = Measurement::new(app, self.finished.len() + 1);
+        self.finished
+            .push(std::mem::replace(&mut self.current, next));
+    }
+}
+
+impl State<App> for MeasureTool {
+    fn event(&mut self, ctx: &mut EventCtx, app: &mut App) -> Transition {
+        ctx.canvas_movement();
+
+        match self.panel.event(ctx) {
+            Outcome::Clicked(x) => match x.as_ref() {
+                "close" => {
+                    return Transition::Pop;
+                }
+                "undo last point" => {
+                    if !self.current.pts.is_empty() {
+                        self.current.pts.pop();
+                    } else if let Some(m) = self.finished.pop() {
+                        // Going back past the start of a new path resumes the last one. A loop
+                        // just opens up again.
+                        self.current = m;
+                        if self.current.area.take().is_none() {
+                            self.current.pts.pop();
+                        }
+                    }
+                    self.update_panel(ctx, app);
+                    return Transition::Keep;
+                }
+                "finish measurement" => {
+                    self.finish_current(app);
+                    self.update_panel(ctx, app);
+                    return Transition::Keep;
+                }
+                "clear all" => {
+                    self.finished.clear();
+                    self.current = Measurement::new(app, 0);
+                    self.update_panel(ctx, app);
+                    return Transition::Keep;
+                }
+                _ => unreachable!(),
+            },
+            _ => {}
+        }
+
+        if ctx.redo_mouseover() {
+            self.cursor = ctx
+                .canvas
+                .get_cursor_in_map_space()
+                .map(|pt| self.snap(ctx, pt));
+            if !self.current.pts.is_empty() {
+                let txt = self.describe(app);
+                self.panel
+                    .replace(ctx, "measurements", txt.draw(ctx).named("measurements"));
+            }
+        }
+
+        if let Some(pt) = self.cursor {
+            if ctx.normal_left_click() {
+                if self.current.pts.len() >= 3 && pt == self.current.pts[0] {
+                    let mut ring = self.current.pts.clone();
+                    ring.push(pt);
+                    // If the path passes through one of its own points, it can't be closed
+                    if let Ok(ring) = Ring::new(ring) {
+                        self.current.area = Some(ring.to_polygon());
+                        self.finish_current(app);
+                    }
+                } else if self.current.pts.last() != Some(&pt) {
+                    self.current.pts.push(pt);
+                }
+                self.update_panel(ctx, app);
+            }
+        }
+
+        Transition::Keep
+    }
+
+    fn draw(&self, g: &mut GfxCtx, app: &App) {
+        let thickness = Distance::meters(THICKNESS / g.canvas.cam_zoom);
+        let mut batch = GeomBatch::new();
+        // Labels are drawn in screen-space, so zooming doesn't change the text size
+        let mut labels = GeomBatch::new();
+        for m in self.finished.iter().chain(std::iter::once(&self.current)) {
+            if let Some(ref polygon) = m.area {
+                batch.push(m.color.alpha(0.3), polygon.clone());
+            }
+            for pt in &m.pts {
+                batch.push(m.color, Circle::new(*pt, thickness * 1.5).to_polygon());
+            }
+            for line in m.segments() {
+                batch.push(m.color, line.make_polygons(thickness));
+                if let Some(middle) = line.middle() {
+                    labels.append(
+                        Text::from(Line(line.length().to_string(&app.opts.units)).fg(Color::WHITE))
+                            .bg(m.color)
+                            .render(g)
+                            .centered_on(g.canvas.map_to_screen(middle).to_pt()),
+                    );
+                }
+            }
+        }
+        // Preview the next segment
+        if let (Some(last), Some(pt)) = (self.current.pts.last(), self.cursor) {
+            if let Some(line) = geom::Line::new(*last, pt) {
+                batch.push(self.current.color.alpha(0.5), line.make_polygons(thickness));
+            }
+        }
+        if let Some(pt) = self.cursor {
+            batch.push(
+                self.current.color.alpha(0.5),
+                Circle::new(pt, thickness * 1.5).to_polygon(),
+            );
+        }
+        let draw = g.upload(batch);
+        g.redraw(&draw);
+
+        let labels = g.upload(labels);
+        g.fork_screenspace();
+        g.redraw(&labels);
+        g.unfork();
+
+        self.panel.draw(g);
+    }
+}
@@ -1,7 +1,7 @@
 use std::collections::HashSet;
 
 use abstio::MapName;
-use abstutil::{Parallelism, Tags, Timer};
+use abstutil::{prettyprint_usize, Parallelism, Tags, Timer};
 use geom::{Distance, Pt2D};
 use map_gui::colors::ColorSchemeChoice;
 use map_gui::load::MapLoader;
@@ -17,16 +17,26 @@ use widgetry::{
     UpdateType, VerticalAlignment, Widget,
 };
 
+#[cfg(feature = "raw_osm")]
+pub use self::raw_osm::RawOsm;
 use crate::app::{App, ShowLayers, ShowObject, Transition};
-use crate::common::{tool_panel, CommonState};
+use crate::common::{reload_colors, tool_panel, CommonState};
 use crate::info::ContextualActions;
 use crate::sandbox::GameplayMode;
 
 mod blocked_by;
+mod color_audit;
+mod delay_causers;
+mod determinism;
+mod event_log;
 mod floodfill;
+mod geojson_layers;
+mod measure;
 mod objects;
 pub mod path_counter;
 mod polygons;
+#[cfg(feature = "raw_osm")]
+mod raw_osm;
 mod routes;
 pub mod shared_row;
 pub mod streetmix;
@@ -105,14 +115,39 @@ impl DebugMode {
                     ctx.style()
                         .btn_outline_light_text("sim internal stats")
                         .build_def(ctx),
+                    ctx.style()
+                        .btn_outline_light_text("inspect raw OSM")
+                        .build_def(ctx),
+                    ctx.style()
+                        .btn_outline_light_text("measure distances")
+                        .hotkey(Key::M)
+                        .build_def(ctx),
+                    ctx.style()
+                        .btn_outline_light_text("check determinism")
+                        .build_def(ctx),
                     ctx.style()
                         .btn_outline_light_text("blocked-by graph")
                         .hotkey(Key::B)
                         .build_def(ctx),
+                    ctx.style()
+                        .btn_outline_light_text("vehicles causing the most delay")
+                        .build_def(ctx),
+                    ctx.style()
+                        .btn_outline_light_text("event log")
+                        .build_def(ctx),
                     ctx.style()
                         .btn_outline_light_text("render to GeoJSON")
                         .hotkey(Key::G)
                         .build_def(ctx),
+                    ctx.style()
+                        .btn_outline_light_text("export GeoJSON layers")
+                        .build_def(ctx),
+                    ctx.style()
+                        .btn_outline_light_text("reload colors")
+                        .build_def(ctx),
+                    ctx.style()
+                        .btn_outline_light_text("audit colors")
+                        .build_def(ctx),
                 ]),
                 Text::from_all(vec![
                     Line("Hold "),
@@ -297,9 +332,43 @@ impl State<App> for DebugMode {
                         app.primary.sim.describe_internal_stats(),
                     ));
                 }
+                "inspect raw OSM" => {
+                    #[cfg(feature = "raw_osm")]
+                    {
+                        return Transition::Push(raw_osm::OsmInspector::new(ctx, app));
+                    }
+                    #[cfg(not(feature = "raw_osm"))]
+                    {
+                        return Transition::Push(PopupMsg::new(
+                            ctx,
+                            "Error",
+                            vec!["Rebuild the game with the raw_osm feature to inspect raw OSM"],
+                        ));
+                    }
+                }
+                "measure distances" => {
+                    return Transition::Push(measure::MeasureTool::new(ctx, app));
+                }
+                "check determinism" => {
+                    return Transition::Push(determinism::DeterminismChecker::new(ctx, app));
+                }
                 "blocked-by graph" => {
                     return Transition::Push(blocked_by::Viewer::new(ctx, app));
                 }
+                "vehicles causing the most delay" => {
+                    return Transition::Push(delay_causers::DelayCausers::new(ctx, app));
+                }
+                "event log" => {
+                    return Transition::Push(event_log::EventLogViewer::new(ctx, app));
+                }
+                "reload colors" => {
+                    if let Some(t) = reload_colors(ctx, app) {
+                        return t;
+                    }
+                }
+                "audit colors" => {
+                    return Transition::Push(color_audit::ColorAudit::new(ctx, app));
+                }
                 "render to GeoJSON" => {
                     // TODO Loading screen doesn't actually display anything because of the rules
                     // around hiding the first few draws
@@ -316,6 +385,24 @@ impl State<App> for DebugMode {
                         timer.stop("render");
                     });
                 }
+                "export GeoJSON layers" => {
+                    let result = ctx.loading_screen("export GeoJSON layers", |_, _| {
+                        geojson_layers::export(app)
+                    });
+                    return Transition::Push(match result {
+                        Ok(files) => PopupMsg::new(
+                            ctx,
+                            "GeoJSON layers exported",
+                            files
+                                .into_iter()
+                                .map(|(path, count)| {
+                                    format!("{}: {} features", path, prettyprint_usize(count))
+                                })
+                                .collect(),
+                        ),
+                        Err(err) => PopupMsg::new(ctx, "Export failed", vec![err.to_string()]),
+                    });
+                }
                 _ => unreachable!(),
             },
             Outcome::Changed => {
@@ -0,0 +1,385 @@
+use std::collections::BTreeMap;
+
+use anyhow::Result;
+
+use abstutil::Timer;
+use convert_osm::reader::Document;
+use geom::{Circle, Distance, PolyLine};
+use map_gui::tools::PopupMsg;
+use map_gui::ID;
+use map_model::osm::{NodeID, OsmID, WayID};
+use map_model::{BuildingID, IntersectionID, Map, RoadID};
+use widgetry::{
+    Color, Drawable, EventCtx, GeomBatch, GfxCtx, HorizontalAlignment, Key, Line, Outcome, Panel,
+    State, StyledButtons, Text, TextExt, VerticalAlignment, Widget,
+};
+
+use crate::app::{App, Transition};
+use crate::common::CommonState;
+
+/// Don't list more than this many objects in the viewport; the panel would get unusable.
+const MAX_LISTED: usize = 100;
+
+/// The OSM file that the importer clipped for a map, along with what each way and node became.
+/// Only loaded the first time it's inspected, since parsing the XML is slow.
+pub struct RawOsm {
+    doc: Document,
+    /// One way is usually split into many roads
+    way_to_roads: BTreeMap<WayID, Vec<RoadID>>,
+    node_to_intersection: BTreeMap<NodeID, IntersectionID>,
+    osm_to_building: BTreeMap<OsmID, BuildingID>,
+}
+
+impl RawOsm {
+    fn load(map: &Map, timer: &mut Timer) -> Result<RawOsm> {
+        let name = map.get_name();
+        let path = name.city.input_path(format!("osm/{}.osm", name.map));
+        let doc = convert_osm::reader::read(&path, map.get_gps_bounds(), timer)?;
+
+        timer.start("match raw OSM to the map");
+        let mut way_to_roads = BTreeMap::new();
+        for r in map.all_roads() {
+            way_to_roads
+                .entry(r.orig_id.osm_way_id)
+                .or_insert_with(Vec::new)
+                .push(r.id);
+        }
+        let node_to_intersection = map
+            .all_intersections()
+            .iter()
+            .map(|i| (i.orig_id, i.id))
+            .collect();
+        let osm_to_building = map
+            .all_buildings()
+            .iter()
+            .map(|b| (b.orig_id, b.id))
+            .collect();
+        timer.stop("match raw OSM to the map");
+
+        Ok(RawOsm {
+            doc,
+            way_to_roads,
+            node_to_intersection,
+            osm_to_building,
+        })
+    }
+
+    fn exists(&self, id: OsmID) -> bool {
+        match id {
+            OsmID::Way(w) => self.doc.ways.contains_key(&w),
+            OsmID::Node(n) => self.doc.nodes.contains_key(&n),
+            OsmID::Relation(r) => self.doc.relations.contains_key(&r),
+        }
+    }
+}
+
+/// Browse the raw OSM ways and nodes in some area, or behind some map object, and see which parts
+/// of the map they became.
+pub struct OsmInspector {
+    panel: Panel,
+    listed: Vec<OsmID>,
+    selected: Option<OsmID>,
+    draw: Drawable,
+}
+
+impl OsmInspector {
+    pub fn new(ctx: &mut EventCtx, app: &mut App) -> Box<dyn State<App>> {
+        if app.primary.raw_osm.is_none() {
+            let map = &app.primary.map;
+            match ctx.loading_screen("load raw OSM", |_, timer| RawOsm::load(map, timer)) {
+                Ok(raw) => {
+                    app.primary.raw_osm = Some(raw);
+                }
+                Err(err) => {
+                    return PopupMsg::new(
+                        ctx,
+                        "Can't inspect raw OSM",
+                        vec![
+                            format!("Couldn't load the OSM input for this map: {}", err),
+                            "The importer's input files are only around when building from \
+                             source."
+                                .to_string(),
+                        ],
+                    );
+                }
+            }
+        }
+
+        let mut inspector = OsmInspector {
+            panel: Panel::empty(ctx),
+            listed: Vec::new(),
+            selected: None,
+            draw: Drawable::empty(ctx),
+        };
+        inspector.update(ctx, app);
+        Box::new(inspector)
+    }
+
+    fn select(&mut self, ctx: &mut EventCtx, app: &App, id: OsmID) -> Option<Transition> {
+        if !app.primary.raw_osm.as_ref().unwrap().exists(id) {
+            return Some(Transition::Push(PopupMsg::new(
+                ctx,
+                "Not found",
+                vec![format!("{} isn't in the raw OSM", describe(id))],
+            )));
+        }
+        self.selected = Some(id);
+        self.update(ctx, app);
+        None
+    }
+
+    fn list_in_view(&mut self, ctx: &mut EventCtx, app: &App) {
+        let raw = app.primary.raw_osm.as_ref().unwrap();
+        let bounds = ctx.canvas.get_screen_bounds();
+        self.listed.clear();
+        for (id, way) in &raw.doc.ways {
+            if way.pts.iter().any(|pt| bounds.contains(*pt)) {
+                self.listed.push(OsmID::Way(*id));
+            }
+        }
+        // Untagged nodes are just points along ways
+        for (id, node) in &raw.doc.nodes {
+            if !node.tags.is_empty() && bounds.contains(node.pt) {
+                self.listed.push(OsmID::Node(*id));
+            }
+        }
+        self.update(ctx, app);
+    }
+
+    fn update(&mut self, ctx: &mut EventCtx, app: &App) {
+        let map = &app.primary.map;
+        let raw = app.primary.raw_osm.as_ref().unwrap();
+
+        let mut batch = GeomBatch::new();
+        for id in &self.listed {
+            if Some(*id) != self.selected {
+                draw_raw(&mut batch, raw, *id, Color::CYAN.alpha(0.5), 1.0);
+            }
+        }
+
+        let mut col = vec![
+            Widget::row(vec![
+                Line("Raw OSM").small_heading().draw(ctx),
+                ctx.style().btn_close_widget(ctx),
+            ]),
+            "Click a road, intersection, or building to see where it came from".draw_text(ctx),
+            Widget::row(vec![
+                Widget::text_entry(ctx, String::new(), false)
+                    .named("query")
+                    .centered_vert(),
+                ctx.style()
+                    .btn_outline_light_text("search")
+                    .hotkey(Key::Enter)
+                    .build_def(ctx),
+            ]),
+            "Search for \"way 123\" or \"node 456\"".draw_text(ctx),
+            ctx.style()
+                .btn_outline_light_text("list everything in view")
+                .build_def(ctx),
+        ];
+
+        if !self.listed.is_empty() {
+            col.push(Widget::horiz_separator(ctx, 1.0));
+            col.push(
+                format!(
+                    "{} ways and tagged nodes in view{}",
+                    self.listed.len(),
+                    if self.listed.len() > MAX_LISTED {
+                        format!(", showing the first {}", MAX_LISTED)
+                    } else {
+                        String::new()
+                    }
+                )
+                .draw_text(ctx),
+            );
+            col.push(Widget::col(
+                self.listed
+                    .iter()
+                    .take(MAX_LISTED)
+                    .map(|id| {
+                        ctx.style()
+                            .btn_plain_light_text(&describe_with_name(raw, *id))
+                            .build_widget(ctx, &describe(*id))
+                    })
+                    .collect(),
+            ));
+        }
+
+        if let Some(id) = self.selected {
+            col.push(Widget::horiz_separator(ctx, 1.0));
+            col.push(Line(describe(id)).small_heading().draw(ctx));
+
+            let tags = match id {
+                OsmID::Way(w) => &raw.doc.ways[&w].tags,
+                OsmID::Node(n) => &raw.doc.nodes[&n].tags,
+                OsmID::Relation(r) => &raw.doc.relations[&r].tags,
+            };
+            let mut txt = Text::new();
+            if tags.is_empty() {
+                txt.add(Line("No tags").secondary());
+            }
+            for (k, v) in tags.inner() {
+                txt.add(Line(format!("{} = {}", k, v)));
+            }
+            col.push(txt.draw(ctx));
+
+            // What did it become?
+            let mut became = Vec::new();
+            if let OsmID::Way(w) = id {
+                for r in raw.way_to_roads.get(&w).cloned().unwrap_or_else(Vec::new) {
+                    became.push(r.to_string());
+                    batch.push(
+                        Color::ORANGE.alpha(0.5),
+                        map.get_r(r).get_thick_polygon(map),
+                    );
+                }
+            }
+            if let OsmID::Node(n) = id {
+                if let Some(i) = raw.node_to_intersection.get(&n) {
+                    became.push(i.to_string());
+                    batch.push(Color::ORANGE.alpha(0.5), map.get_i(*i).polygon.clone());
+                }
+            }
+            if let Some(b) = raw.osm_to_building.get(&id) {
+                became.push(b.to_string());
+                batch.push(Color::ORANGE.alpha(0.5), map.get_b(*b).polygon.clone());
+            }
+            col.push(
+                if became.is_empty() {
+                    "Didn't become anything on the map".to_string()
+                } else {
+                    format!("Became {}", became.join(", "))
+                }
+                .draw_text(ctx),
+            );
+            draw_raw(&mut batch, raw, id, Color::CYAN, 3.0);
+        }
+
+        self.panel = Panel::new(Widget::col(col))
+            .aligned(HorizontalAlignment::Right, VerticalAlignment::Top)
+            .exact_size_percent(30, 80)
+            .build(ctx);
+        self.draw = ctx.upload(batch);
+    }
+}
+
+impl State<App> for OsmInspector {
+    fn event(&mut self, ctx: &mut EventCtx, app: &mut App) -> Transition {
+        ctx.canvas_movement();
+        if ctx.redo_mouseover() {
+            app.recalculate_current_selection(ctx);
+        }
+        if let Some(id) = app.primary.current_selection.clone() {
+            if ctx.normal_left_click() {
+                let map = &app.primary.map;
+                let osm_id = match id {
+                    ID::Road(r) => Some(OsmID::Way(map.get_r(r).orig_id.osm_way_id)),
+                    ID::Lane(l) => Some(OsmID::Way(map.get_parent(l).orig_id.osm_way_id)),
+                    ID::Intersection(i) => Some(OsmID::Node(map.get_i(i).orig_id)),
+                    ID::Building(b) => Some(map.get_b(b).orig_id),
+                    _ => None,
+                };
+                if let Some(osm_id) = osm_id {
+                    if let Some(t) = self.select(ctx, app, osm_id) {
+                        return t;
+                    }
+                }
+            }
+        }
+
+        match self.panel.event(ctx) {
+            Outcome::Clicked(x) => match x.as_ref() {
+                "close" => {
+                    return Transition::Pop;
+                }
+                "search" => {
+                    let query = self.panel.text_box("query");
+                    return match parse_query(&query) {
+                        Some(id) => self.select(ctx, app, id).unwrap_or(Transition::Keep),
+                        None => Transition::Push(PopupMsg::new(
+                            ctx,
+                            "Bad search",
+                            vec![format!(
+                                "Didn't understand \"{}\". Try \"way 123\" or \"node 456\".",
+                                query
+                            )],
+                        )),
+                    };
+                }
+                "list everything in view" => {
+                    self.list_in_view(ctx, app);
+                }
+                x => {
+                    let id = parse_query(x).unwrap();
+                    if let Some(t) = self.select(ctx, app, id) {
+                        return t;
+                    }
+                }
+            },
+            _ => {}
+        }
+
+        Transition::Keep
+    }
+
+    fn draw(&self, g: &mut GfxCtx, app: &App) {
+        g.redraw(&self.draw);
+        self.panel.draw(g);
+        CommonState::draw_osd(g, app);
+    }
+}
+
+fn draw_raw(batch: &mut GeomBatch, raw: &RawOsm, id: OsmID, color: Color, thickness: f64) {
+    match id {
+        OsmID::Way(w) => {
+            if let Ok(pl) = PolyLine::deduping_new(raw.doc.ways[&w].pts.clone()) {
+                batch.push(color, pl.make_polygons(Distance::meters(thickness)));
+            }
+        }
+        OsmID::Node(n) => {
+            batch.push(
+                color,
+                Circle::new(raw.doc.nodes[&n].pt, Distance::meters(2.0 * thickness)).to_polygon(),
+            );
+        }
+        // Relations aren't listed or linked from the map
+        OsmID::Relation(_) => {}
+    }
+}
+
+fn describe(id: OsmID) -> String {
+    match id {
+        OsmID::Way(w) => format!("way {}", w.0),
+        OsmID::Node(n) => format!("node {}", n.0),
+        OsmID::Relation(r) => format!("relation {}", r.0),
+    }
+}
+
+fn describe_with_name(raw: &RawOsm, id: OsmID) -> String {
+    let tags = match id {
+        OsmID::Way(w) => &raw.doc.ways[&w].tags,
+        OsmID::Node(n) => &raw.doc.nodes[&n].tags,
+        OsmID::Relation(r) => &raw.doc.relations[&r].tags,
+    };
+    match tags.get("name") {
+        Some(name) => format!("{} ({})", describe(id), name),
+        None => describe(id),
+    }
+}
+
+/// Understands "way 123", "node 456", "w123", and "n456"
+fn parse_query(query: &str) -> Option<OsmID> {
+    let query = query.trim().to_ascii_lowercase();
+    for (prefix, is_way) in &[("way", true), ("w", true), ("node", false), ("n", false)] {
+        if let Some(num) = query.strip_prefix(prefix) {
+            if let Ok(x) = num.trim().parse::<i64>() {
+                return Some(if *is_way {
+                    OsmID::Way(WayID(x))
+                } else {
+                    OsmID::Node(NodeID(x))
+                });
+            }
+        }
+    }
+    None
+}
@@ -17,6 +17,7 @@ mod collisions;
 mod destinations;
 mod kml;
 mod polygon;
+mod prebaked;
 mod scenario;
 mod story;
 
@@ -62,6 +63,10 @@ impl DevToolsMode {
                         .btn_outline_light_text("story maps")
                         .hotkey(Key::S)
                         .build_def(ctx),
+                    ctx.style()
+                        .btn_outline_light_text("prebaked results")
+                        .hotkey(Key::B)
+                        .build_def(ctx),
                     if abstio::file_exists(
                         app.primary.map.get_city_name().input_path("collisions.bin"),
                     ) {
@@ -146,6 +151,9 @@ impl State<App> for DevToolsMode {
                 "story maps" => {
                     return Transition::Push(story::StoryMapEditor::new(ctx));
                 }
+                "prebaked results" => {
+                    return Transition::Push(prebaked::PrebakedManager::new(ctx, app));
+                }
                 "collisions" => {
                     return Transition::Push(collisions::CollisionsViewer::new(ctx, app));
                 }
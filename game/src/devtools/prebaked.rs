@@ -0,0 +1,171 @@
+use std::collections::BTreeSet;
+
+use abstutil::{prettyprint_usize, Timer};
+use map_gui::tools::{grey_out_map, PopupMsg};
+use sim::Scenario;
+use widgetry::{
+    Color, EventCtx, GfxCtx, Line, Outcome, Panel, State, StyledButtons, Text, TextExt, Widget,
+};
+
+use crate::app::{App, Transition};
+use crate::challenges::prebake::{prebake, PrebakedMetadata};
+
+/// Lists the prebaked results for the current map, which trip dashboards compare against. They
+/// can be deleted or generated again from the scenario.
+pub struct PrebakedManager {
+    panel: Panel,
+}
+
+impl PrebakedManager {
+    pub fn new(ctx: &mut EventCtx, app: &App) -> Box<dyn State<App>> {
+        let map = &app.primary.map;
+        let current_edits = map.get_edits().checksum(map);
+
+        // Scenarios that haven't been prebaked yet, and results whose scenario is gone
+        let mut names: BTreeSet<String> =
+            abstio::list_all_objects(abstio::path_all_scenarios(map.get_name()))
+                .into_iter()
+                .collect();
+        for path in abstio::list_dir(abstio::path_all_prebaked_results(map.get_name())) {
+            if path.ends_with(".bin") {
+                names.insert(abstutil::basename(&path));
+            }
+        }
+
+        let mut rows = Vec::new();
+        for name in names {
+            let has_results =
+                abstio::file_exists(abstio::path_prebaked_results(map.get_name(), &name));
+            let has_scenario = abstio::file_exists(abstio::path_scenario(map.get_name(), &name));
+            let mut txt = Text::from(Line(&name).small_heading());
+            if is_active(app, &name) {
+                txt.append(Line(" (active baseline)"));
+            }
+            if !has_results {
+                txt.add(Line("Not generated yet").secondary());
+            } else {
+                match abstio::maybe_read_json::<PrebakedMetadata>(
+                    abstio::path_prebaked_metadata(map.get_name(), &name),
+                    &mut Timer::throwaway(),
+                ) {
+                    Ok(meta) => {
+                        txt.add(Line(format!(
+                            "Generated {} with edits \"{}\"",
+                            meta.generated, meta.edits_name
+                        )));
+                        txt.add(Line(format!(
+                            "Edits checksum {:016x}, {} agents left at the end of the day",
+                            meta.edits_checksum,
+                            prettyprint_usize(meta.agents_left)
+                        )));
+                        if meta.edits_checksum != current_edits {
+                            txt.add(Line("The current edits are different").fg(Color::RED));
+                        }
+                    }
+                    Err(_) => {
+                        txt.add(Line("Generated before metadata was recorded").secondary());
+                    }
+                }
+            }
+
+            let mut buttons = Vec::new();
+            if has_scenario {
+                buttons.push(
+                    ctx.style()
+                        .btn_outline_light_text("Generate now")
+                        .build_widget(ctx, &format!("generate {}", name)),
+                );
+            }
+            if has_results {
+                buttons.push(
+                    ctx.style()
+                        .btn_outline_light_text("Delete")
+                        .build_widget(ctx, &format!("delete {}", name)),
+                );
+            }
+            rows.push(Widget::row(vec![
+                txt.draw(ctx),
+                Widget::row(buttons).align_right(),
+            ]));
+        }
+        if rows.is_empty() {
+            rows.push("This map has no scenarios".draw_text(ctx));
+        }
+
+        Box::new(PrebakedManager {
+            panel: Panel::new(Widget::col(vec![
+                Widget::row(vec![
+                    Line("Prebaked results").small_heading().draw(ctx),
+                    ctx.style().btn_close_widget(ctx),
+                ]),
+                format!(
+                    "Generating simulates a full day on this map, with the current edits \"{}\"",
+                    map.get_edits().edits_name
+                )
+                .draw_text(ctx),
+                Widget::col(rows),
+            ]))
+            .build(ctx),
+        })
+    }
+}
+
+impl State<App> for PrebakedManager {
+    fn event(&mut self, ctx: &mut EventCtx, app: &mut App) -> Transition {
+        match self.panel.event(ctx) {
+            Outcome::Clicked(x) => {
+                if x == "close" {
+                    return Transition::Pop;
+                }
+                let map_name = app.primary.map.get_name().clone();
+                if let Some(name) = x.strip_prefix("generate ") {
+                    let agents_left =
+                        ctx.loading_screen("generate prebaked results", |_, timer| {
+                            let scenario: Scenario =
+                                abstio::read_binary(abstio::path_scenario(&map_name, name), timer);
+                            prebake(&app.primary.map, scenario, None, timer)
+                        });
+                    if is_active(app, name) {
+                        let prebaked = abstio::read_binary(
+                            abstio::path_prebaked_results(&map_name, name),
+                            &mut Timer::throwaway(),
+                        );
+                        app.set_prebaked(Some((map_name, name.to_string(), prebaked)));
+                    }
+                    return Transition::Multi(vec![
+                        Transition::Replace(PrebakedManager::new(ctx, app)),
+                        Transition::Push(PopupMsg::new(
+                            ctx,
+                            "Prebaked results generated",
+                            vec![format!(
+                                "{} agents were left at the end of the day",
+                                prettyprint_usize(agents_left)
+                            )],
+                        )),
+                    ]);
+                }
+                if let Some(name) = x.strip_prefix("delete ") {
+                    abstio::delete_file(abstio::path_prebaked_results(&map_name, name));
+                    abstio::delete_file(abstio::path_prebaked_metadata(&map_name, name));
+                    if is_active(app, name) {
+                        app.set_prebaked(None);
+                    }
+                    return Transition::Replace(PrebakedManager::new(ctx, app));
+                }
+                unreachable!()
+            }
+            _ => Transition::Keep,
+        }
+    }
+
+    fn draw(&self, g: &mut GfxCtx, app: &App) {
+        grey_out_map(g, app);
+        self.panel.draw(g);
+    }
+}
+
+fn is_active(app: &App, scenario: &str) -> bool {
+    app.has_prebaked()
+        .map(|(m, s)| m == app.primary.map.get_name() && s == scenario)
+        .unwrap_or(false)
+}
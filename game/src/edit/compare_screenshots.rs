@@ -0,0 +1,314 @@
+use std::fs::File;
+use std::io::Write;
+
+use anyhow::Result;
+
+use geom::{Bounds, Distance, Pt2D};
+use map_gui::render::DrawOptions;
+use map_gui::tools::PopupMsg;
+use map_model::MapEdits;
+use widgetry::{
+    DrawBaselayer, EventCtx, GfxCtx, HorizontalAlignment, Line, Outcome, Panel, ScreenDims, State,
+    StyledButtons, TextExt, UpdateType, VerticalAlignment, Widget,
+};
+
+use crate::app::{App, ShowEverything, Transition};
+use crate::edit::apply_map_edits;
+
+// Every screenshot has exactly this size, no matter how big the window is
+const DIMS: ScreenDims = ScreenDims {
+    width: 1600.0,
+    height: 1200.0,
+};
+// Edits closer than this get framed together
+const CLUSTER_DISTANCE: Distance = Distance::const_meters(200.0);
+// Around each cluster of edits
+const PADDING: Distance = Distance::const_meters(50.0);
+// Don't zoom in on a single intersection so closely that there's no context
+const MAX_ZOOM: f64 = 10.0;
+
+/// Where to point the camera for one pair of screenshots.
+pub struct Framing {
+    pub name: String,
+    pub center: Pt2D,
+    pub zoom: f64,
+}
+
+impl Framing {
+    /// The view the player currently sees, rescaled so the same area fits into the screenshot.
+    pub fn current_view(ctx: &EventCtx) -> Framing {
+        let scale =
+            (DIMS.width / ctx.canvas.window_width).min(DIMS.height / ctx.canvas.window_height);
+        Framing {
+            name: "Current view".to_string(),
+            center: ctx.canvas.center_to_map_pt(),
+            zoom: ctx.canvas.cam_zoom * scale,
+        }
+    }
+
+    /// One framing per cluster of edited roads and intersections.
+    pub fn edited_places(app: &App) -> Vec<Framing> {
+        let map = &app.primary.map;
+        let edits = map.get_edits();
+        let mut places: Vec<(String, Bounds)> = Vec::new();
+        for r in &edits.changed_roads {
+            let road = map.get_r(*r);
+            places.push((
+                road.get_name(app.opts.language.as_ref()),
+                road.center_pts.get_bounds(),
+            ));
+        }
+        for i in edits.original_intersections.keys() {
+            let intersection = map.get_i(*i);
+            places.push((
+                intersection.name(app.opts.language.as_ref(), map),
+                intersection.polygon.get_bounds(),
+            ));
+        }
+
+        // Greedily grow clusters. Each cluster is named after the first thing in it.
+        let mut clusters: Vec<(String, Bounds)> = Vec::new();
+        for (name, bounds) in places {
+            let mut buffered = bounds.clone();
+            buffered.add_buffer(CLUSTER_DISTANCE);
+            if let Some((_, cluster)) = clusters
+                .iter_mut()
+                .find(|(_, cluster)| overlaps(cluster, &buffered))
+            {
+                cluster.union(bounds);
+            } else {
+                clusters.push((name, bounds));
+            }
+        }
+
+        clusters
+            .into_iter()
+            .map(|(name, mut bounds)| {
+                bounds.add_buffer(PADDING);
+                let zoom = (DIMS.width / bounds.width())
+                    .min(DIMS.height / bounds.height())
+                    .min(MAX_ZOOM);
+                Framing {
+                    name,
+                    center: bounds.center(),
+                    zoom,
+                }
+            })
+            .collect()
+    }
+
+    fn camera(&self) -> (f64, f64, f64) {
+        (
+            self.center.x() * self.zoom - DIMS.width / 2.0,
+            self.center.y() * self.zoom - DIMS.height / 2.0,
+            self.zoom,
+        )
+    }
+}
+
+enum Step {
+    Capture { framing: usize, before: bool },
+    RemoveEdits,
+    RestoreEdits,
+}
+
+/// Screenshots the same framings with and without the current edits, then writes an HTML page
+/// showing each pair side by side. Useful for documenting a proposal.
+pub struct CompareScreenshots {
+    dir: String,
+    framings: Vec<Framing>,
+    edits: MapEdits,
+    steps: Vec<Step>,
+    next_step: usize,
+    // True between removing the edits and restoring them, so cancelling can clean up
+    edits_removed: bool,
+    panel: Panel,
+}
+
+impl CompareScreenshots {
+    pub fn new(ctx: &mut EventCtx, app: &App, framings: Vec<Framing>) -> Box<dyn State<App>> {
+        if framings.is_empty() {
+            return PopupMsg::new(
+                ctx,
+                "Nothing to screenshot",
+                vec!["This proposal doesn't change any roads or intersections yet."],
+            );
+        }
+
+        let dir = format!(
+            "screenshots/before_after/{}_{}",
+            app.primary.map.get_name().as_filename(),
+            chrono::Local::now().format("%Y-%m-%d_%H-%M-%S")
+        );
+        let mut steps = Vec::new();
+        for framing in 0..framings.len() {
+            steps.push(Step::Capture {
+                framing,
+                before: false,
+            });
+        }
+        steps.push(Step::RemoveEdits);
+        for framing in 0..framings.len() {
+            steps.push(Step::Capture {
+                framing,
+                before: true,
+            });
+        }
+        steps.push(Step::RestoreEdits);
+
+        let mut state = CompareScreenshots {
+            dir,
+            framings,
+            edits: app.primary.map.get_edits().clone(),
+            steps,
+            next_step: 0,
+            edits_removed: false,
+            panel: Panel::empty(ctx),
+        };
+        state.recreate_panel(ctx);
+        Box::new(state)
+    }
+
+    fn recreate_panel(&mut self, ctx: &mut EventCtx) {
+        let status = match self.steps.get(self.next_step) {
+            Some(Step::Capture { framing, before }) => format!(
+                "{} changes: framing {}/{} ({})",
+                if *before { "Without" } else { "With" },
+                framing + 1,
+                self.framings.len(),
+                self.framings[*framing].name
+            ),
+            Some(Step::RemoveEdits) => "Removing edits".to_string(),
+            Some(Step::RestoreEdits) => "Restoring edits".to_string(),
+            None => "Writing index".to_string(),
+        };
+        self.panel = Panel::new(Widget::col(vec![
+            Line("Capturing before and after screenshots")
+                .small_heading()
+                .draw(ctx),
+            status.draw_text(ctx),
+            ctx.style().btn_solid_dark_text("Cancel").build_def(ctx),
+        ]))
+        .aligned(HorizontalAlignment::Center, VerticalAlignment::Center)
+        .build(ctx);
+    }
+
+    fn filename(&self, framing: usize, before: bool) -> String {
+        format!(
+            "{:03}_{}.png",
+            framing,
+            if before { "before" } else { "after" }
+        )
+    }
+
+    fn write_index(&self, app: &App) -> Result<String> {
+        let path = format!("{}/index.html", self.dir);
+        let mut f = File::create(&path)?;
+        writeln!(f, "<!DOCTYPE html>")?;
+        writeln!(
+            f,
+            "<html><head><meta charset=\"utf-8\"><title>{}</title></head><body>",
+            escape(&self.edits.edits_name)
+        )?;
+        writeln!(
+            f,
+            "<h1>{} on {}</h1>",
+            escape(&self.edits.edits_name),
+            escape(&app.primary.map.get_name().describe())
+        )?;
+        for (idx, framing) in self.framings.iter().enumerate() {
+            writeln!(f, "<h2>{}</h2>", escape(&framing.name))?;
+            writeln!(f, "<table><tr><th>Before</th><th>After</th></tr><tr>")?;
+            for before in &[true, false] {
+                writeln!(
+                    f,
+                    "<td><img src=\"{}\" style=\"width: 100%\"></td>",
+                    self.filename(idx, *before)
+                )?;
+            }
+            writeln!(f, "</tr></table>")?;
+        }
+        writeln!(f, "</body></html>")?;
+        Ok(path)
+    }
+}
+
+impl State<App> for CompareScreenshots {
+    fn event(&mut self, ctx: &mut EventCtx, app: &mut App) -> Transition {
+        if let Outcome::Clicked(x) = self.panel.event(ctx) {
+            match x.as_ref() {
+                "Cancel" => {
+                    if self.edits_removed {
+                        apply_map_edits(ctx, app, self.edits.clone());
+                    }
+                    return Transition::Pop;
+                }
+                _ => unreachable!(),
+            }
+        }
+
+        // Each capture happens right after the event that requests it, so only do one thing per
+        // event. Otherwise the edits could change before the screenshot is taken.
+        match self.steps.get(self.next_step) {
+            Some(Step::Capture { framing, before }) => {
+                ctx.request_update(UpdateType::ScreenCaptureOffscreen {
+                    filename: format!("{}/{}", self.dir, self.filename(*framing, *before)),
+                    camera: self.framings[*framing].camera(),
+                    dims: DIMS,
+                });
+            }
+            Some(Step::RemoveEdits) => {
+                apply_map_edits(ctx, app, app.primary.map.new_edits());
+                self.edits_removed = true;
+            }
+            Some(Step::RestoreEdits) => {
+                apply_map_edits(ctx, app, self.edits.clone());
+                self.edits_removed = false;
+            }
+            None => {
+                let msg = match self.write_index(app) {
+                    Ok(path) => format!("Open {} to compare them", path),
+                    Err(err) => format!(
+                        "The screenshots are in {}, but writing the index broke: {}",
+                        self.dir, err
+                    ),
+                };
+                return Transition::Replace(PopupMsg::new(
+                    ctx,
+                    "Screenshots saved",
+                    vec![
+                        format!("{} pairs of screenshots saved", self.framings.len()),
+                        msg,
+                    ],
+                ));
+            }
+        }
+        self.next_step += 1;
+        self.recreate_panel(ctx);
+        ctx.request_update(UpdateType::Game);
+
+        Transition::Keep
+    }
+
+    fn draw_baselayer(&self) -> DrawBaselayer {
+        DrawBaselayer::Custom
+    }
+
+    fn draw(&self, g: &mut GfxCtx, app: &App) {
+        app.draw(g, DrawOptions::new(), &ShowEverything::new());
+        if !g.is_screencap() {
+            self.panel.draw(g);
+        }
+    }
+}
+
+fn overlaps(b1: &Bounds, b2: &Bounds) -> bool {
+    b1.min_x <= b2.max_x && b2.min_x <= b1.max_x && b1.min_y <= b2.max_y && b2.min_y <= b1.max_y
+}
+
+fn escape(x: &str) -> String {
+    x.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
@@ -75,6 +75,11 @@ impl LaneEditor {
                     .hotkey(Key::M)
                     .build_widget(ctx, "Edit multiple lanes"),
             ]),
+            format!(
+                "Incline: {:.1}%",
+                app.primary.map.get_l(l).percent_incline(&app.primary.map) * 100.0
+            )
+            .draw_text(ctx),
             "Type of lane".draw_text(ctx),
             Widget::custom_row(row).centered(),
             ctx.style()
@@ -222,7 +227,7 @@ impl SimpleState<App> for LaneEditor {
 
 // Allow doing this anywhere. Players can create really wacky roads with many direction changes,
 // but it's not really useful to limit creativity. ;)
-fn reverse_lane(map: &Map, l: LaneID) -> EditCmd {
+pub fn reverse_lane(map: &Map, l: LaneID) -> EditCmd {
     let r = map.get_parent(l);
     let idx = r.offset(l);
     map.edit_road_cmd(r.id, |new| {
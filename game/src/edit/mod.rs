@@ -15,10 +15,11 @@ use widgetry::{
 };
 
 pub use self::cluster_traffic_signals::ClusterTrafficSignalEditor;
-pub use self::lanes::LaneEditor;
+pub use self::compare_screenshots::{CompareScreenshots, Framing};
+pub use self::lanes::{reverse_lane, LaneEditor};
 pub use self::routes::RouteEditor;
 pub use self::stop_signs::StopSignEditor;
-pub use self::traffic_signals::TrafficSignalEditor;
+pub use self::traffic_signals::{export_signal_timing, import_signal_timing, TrafficSignalEditor};
 pub use self::validate::{check_blackholes, check_sidewalk_connectivity, try_change_lt};
 use crate::app::{App, Transition};
 use crate::common::{tool_panel, CommonState, Warping};
@@ -27,6 +28,7 @@ use crate::sandbox::{GameplayMode, SandboxMode, TimeWarpScreen};
 
 mod bulk;
 mod cluster_traffic_signals;
+mod compare_screenshots;
 mod lanes;
 mod routes;
 mod select;
@@ -194,6 +196,8 @@ impl State<App> for EditMode {
                             Choice::string("open a saved proposal").multikey(lctrl(Key::L)),
                             Choice::string("create a blank proposal"),
                             Choice::string("save this proposal as..."),
+                            Choice::string("screenshot edited places before and after"),
+                            Choice::string("screenshot this view before and after"),
                             Choice::string("delete this proposal and remove all edits")
                                 .fg(Color::hex("#EB3223")),
                         ],
@@ -259,6 +263,12 @@ impl State<App> for EditMode {
                                 Some(Transition::Pop),
                                 Box::new(|_, _| {}),
                             )),
+                            "screenshot edited places before and after" => Transition::Replace(
+                                CompareScreenshots::new(ctx, app, Framing::edited_places(app)),
+                            ),
+                            "screenshot this view before and after" => Transition::Replace(
+                                CompareScreenshots::new(ctx, app, vec![Framing::current_view(ctx)]),
+                            ),
                             "delete this proposal and remove all edits" => {
                                 abstio::delete_file(abstio::path_edits(
                                     app.primary.map.get_name(),
@@ -675,10 +685,13 @@ pub fn apply_map_edits(ctx: &mut EventCtx, app: &mut App, edits: MapEdits) {
         app.primary.map.must_apply_edits(edits);
 
     if !roads_changed.is_empty() || !modified_intersections.is_empty() {
-        app.primary
-            .draw_map
-            .draw_all_unzoomed_roads_and_intersections =
-            DrawMap::regenerate_unzoomed_layer(&app.primary.map, &app.cs, ctx, &mut timer);
+        let draw = DrawMap::regenerate_unzoomed_layer(
+            &app.primary.map,
+            &app.cs,
+            ctx.prerender,
+            &mut timer,
+        );
+        app.primary.draw_map.set_unzoomed_layer(draw);
     }
 
     for r in roads_changed {
@@ -706,8 +719,17 @@ pub fn apply_map_edits(ctx: &mut EventCtx, app: &mut App, edits: MapEdits) {
         app.primary.draw_map.intersections[i.0].clear_rendering();
     }
 
-    if app.primary.layer.as_ref().and_then(|l| l.name()) == Some("map edits") {
-        app.primary.layer = Some(Box::new(crate::layer::map::Static::edits(ctx, app)));
+    app.primary.caches.map_edited();
+    app.primary.quick_edit = None;
+
+    match app.primary.layer.as_ref().and_then(|l| l.name()) {
+        Some("map edits") => {
+            app.primary.layer = Some(Box::new(crate::layer::map::Static::edits(ctx, app)));
+        }
+        Some("edits diff") => {
+            app.primary.layer = Some(Box::new(crate::layer::edits::EditsDiff::new(ctx, app)));
+        }
+        _ => {}
     }
 
     // Autosave
@@ -715,9 +737,13 @@ pub fn apply_map_edits(ctx: &mut EventCtx, app: &mut App, edits: MapEdits) {
 }
 
 pub fn can_edit_lane(mode: &GameplayMode, l: LaneID, app: &App) -> bool {
+    mode.can_edit_lanes() && is_editable_lane(l, app)
+}
+
+/// Some lanes can't be edited in any mode.
+pub fn is_editable_lane(l: LaneID, app: &App) -> bool {
     let l = app.primary.map.get_l(l);
-    mode.can_edit_lanes()
-        && !l.is_walkable()
+    !l.is_walkable()
         && l.lane_type != LaneType::SharedLeftTurn
         && !l.is_light_rail()
         && !app.primary.map.get_parent(l.id).is_service()
@@ -1,4 +1,4 @@
-use std::collections::{BTreeSet, VecDeque};
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
 
 use abstutil::Timer;
 use geom::{Distance, Line, Polygon, Pt2D};
@@ -24,6 +24,9 @@ mod edits;
 mod offsets;
 mod picker;
 mod preview;
+mod timing;
+
+pub use self::timing::{export_signal_timing, import_signal_timing};
 
 // Welcome to one of the most overwhelmingly complicated parts of the UI...
 
@@ -336,6 +339,54 @@ impl State<App> for TrafficSignalEditor {
                         );
                     }
                 }
+                "Export timing" => {
+                    let mut lines = Vec::new();
+                    for i in &self.members {
+                        lines.push(match export_signal_timing(app, *i) {
+                            Ok(path) => format!("Wrote {}", path),
+                            Err(err) => format!("Couldn't export {}: {}", i, err),
+                        });
+                    }
+                    return Transition::Push(PopupMsg::new(ctx, "Signal timing exported", lines));
+                }
+                "Import timing" => {
+                    let mut signals = BTreeMap::new();
+                    let mut errors = Vec::new();
+                    for i in &self.members {
+                        match import_signal_timing(app, *i) {
+                            Ok(signal) => {
+                                signals.insert(*i, signal);
+                            }
+                            Err(err) => {
+                                errors.push(format!("{}:", i));
+                                errors.extend(err.to_string().lines().map(|l| format!("  {}", l)));
+                            }
+                        }
+                    }
+                    // The editor shows the same stages for every member
+                    let num_stages: BTreeSet<usize> =
+                        signals.values().map(|s| s.stages.len()).collect();
+                    if errors.is_empty() && num_stages.len() > 1 {
+                        errors.push(
+                            "The imported signals have different numbers of phases".to_string(),
+                        );
+                    }
+                    if !errors.is_empty() {
+                        return Transition::Push(PopupMsg::new(
+                            ctx,
+                            "Couldn't import signal timing",
+                            errors,
+                        ));
+                    }
+                    if BundleEdits::get_current(app, &self.members).signals
+                        != signals.values().cloned().collect::<Vec<_>>()
+                    {
+                        self.add_new_edit(ctx, app, 0, |ts| {
+                            *ts = signals[&ts.id].clone();
+                        });
+                    }
+                    return Transition::Keep;
+                }
                 "Preview" => {
                     // Might have to do this first!
                     app.primary
@@ -528,6 +579,20 @@ fn make_top_panel(ctx: &mut EventCtx, app: &App, can_undo: bool, can_redo: bool)
                 .build_widget(ctx, "Edit multiple signals"),
         ]),
         Widget::row(row),
+        Widget::row(vec![
+            ctx.style()
+                .btn_outline_light_text("Export timing")
+                .tooltip(Text::from(
+                    Line("Write the timing to a JSON file you can edit or share").small(),
+                ))
+                .build_def(ctx),
+            ctx.style()
+                .btn_outline_light_text("Import timing")
+                .tooltip(Text::from(
+                    Line("Replace the timing with the file written by the export").small(),
+                ))
+                .build_def(ctx),
+        ]),
         if app.opts.dev {
             ctx.style()
                 .btn_outline_light_text("Export")
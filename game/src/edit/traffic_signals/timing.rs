@@ -0,0 +1,43 @@
+//! Moves signal timing in and out of the game as simple JSON files, so it can be tuned by hand or
+//! shared with other tools. See `map_model::SignalTiming` for the format.
+
+use anyhow::Result;
+
+use abstutil::Timer;
+use map_model::{ControlTrafficSignal, IntersectionID, SignalTiming};
+
+use crate::app::App;
+
+/// Timing for an intersection is exported to and imported from the same file, named by its OSM
+/// node, so it survives rebuilding the map.
+fn timing_path(app: &App, i: IntersectionID) -> String {
+    let map = &app.primary.map;
+    let name = map.get_name();
+    abstio::path_player(format!(
+        "signal_timing/{}/{}/{}/{}.json",
+        name.city.country,
+        name.city.city,
+        name.map,
+        map.get_i(i).orig_id.0
+    ))
+}
+
+/// Writes the current timing of a signal, returning the path.
+pub fn export_signal_timing(app: &App, i: IntersectionID) -> Result<String> {
+    let timing = app
+        .primary
+        .map
+        .get_traffic_signal(i)
+        .export_timing(&app.primary.map)?;
+    let path = timing_path(app, i);
+    abstio::write_json(path.clone(), &timing);
+    Ok(path)
+}
+
+/// Reads the file `export_signal_timing` writes, checking it against the intersection. This
+/// doesn't change the map.
+pub fn import_signal_timing(app: &App, i: IntersectionID) -> Result<ControlTrafficSignal> {
+    let timing: SignalTiming =
+        abstio::maybe_read_json(timing_path(app, i), &mut Timer::throwaway())?;
+    ControlTrafficSignal::import_timing(timing, i, &app.primary.map)
+}
@@ -0,0 +1,136 @@
+use std::collections::BTreeSet;
+
+use abstutil::prettyprint_usize;
+use map_model::{AreaID, AreaType};
+use sim::{TripEndpoint, TripResult};
+use widgetry::{Color, EventCtx, Line, StyledButtons, TextExt, Widget};
+
+use crate::app::App;
+use crate::info::{header_btns, make_table, make_tabs, Details, Tab};
+
+pub fn info(
+    ctx: &EventCtx,
+    app: &App,
+    details: &mut Details,
+    id: AreaID,
+    highlight: bool,
+) -> Vec<Widget> {
+    let mut rows = header(ctx, details, id, Tab::Area(id, highlight));
+    let map = &app.primary.map;
+    let area = map.get_a(id);
+
+    app.primary
+        .caches
+        .area_contents
+        .borrow_mut()
+        .update(Some(id), |_| {
+            let bounds = area.polygon.get_bounds();
+            let bldgs: BTreeSet<_> = map
+                .all_buildings()
+                .iter()
+                .filter(|b| {
+                    let pt = b.polygon.center();
+                    bounds.contains(pt) && area.polygon.contains_pt(pt)
+                })
+                .map(|b| b.id)
+                .collect();
+            let num_roads = map
+                .all_roads()
+                .iter()
+                .filter(|r| {
+                    let pt = r.center_pts.middle();
+                    bounds.contains(pt) && area.polygon.contains_pt(pt)
+                })
+                .count();
+            // Only parks get visitors
+            let trips = if area.area_type == AreaType::Park {
+                app.primary
+                    .sim
+                    .all_trip_info()
+                    .into_iter()
+                    .filter_map(|(t, info)| match info.end {
+                        TripEndpoint::Bldg(b) if bldgs.contains(&b) => Some(t),
+                        _ => None,
+                    })
+                    .collect()
+            } else {
+                Vec::new()
+            };
+            (bldgs, num_roads, trips)
+        });
+    let cache = app.primary.caches.area_contents.borrow();
+    let (bldgs, num_roads, trips) = cache.value().unwrap();
+
+    let mut kv = vec![
+        ("Type", format!("{:?}", area.area_type)),
+        ("Surface area", area.polygon.area_to_string(&app.opts.units)),
+        ("Buildings inside", prettyprint_usize(bldgs.len())),
+        ("Roads inside", prettyprint_usize(*num_roads)),
+    ];
+    if area.area_type == AreaType::Park {
+        let visitors: BTreeSet<_> = trips
+            .iter()
+            .filter(|t| matches!(app.primary.sim.trip_to_agent(**t), TripResult::TripDone))
+            .filter_map(|t| app.primary.sim.trip_to_person(*t))
+            .collect();
+        kv.push(("Visitors so far today", prettyprint_usize(visitors.len())));
+    }
+    rows.extend(make_table(ctx, kv));
+
+    if !bldgs.is_empty() {
+        let action = if highlight {
+            "hide contents"
+        } else {
+            "highlight contents"
+        };
+        details
+            .hyperlinks
+            .insert(action.to_string(), Tab::Area(id, !highlight));
+        rows.push(ctx.style().btn_outline_light_text(action).build_def(ctx));
+    }
+    if highlight {
+        let color = Color::PURPLE.alpha(0.7);
+        for b in bldgs {
+            let poly = &map.get_b(*b).polygon;
+            details.unzoomed.push(color, poly.clone());
+            details.zoomed.push(color, poly.clone());
+        }
+    }
+
+    if let Some(osm_id) = area.osm_id {
+        rows.push(
+            ctx.style()
+                .btn_solid_dark_text("Open in OSM")
+                .build_widget(ctx, &format!("open {}", osm_id)),
+        );
+    }
+
+    if !area.osm_tags.is_empty() {
+        rows.push("Raw OpenStreetMap data".draw_text(ctx));
+        rows.extend(make_table(
+            ctx,
+            area.osm_tags
+                .inner()
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+        ));
+    }
+
+    rows
+}
+
+fn header(ctx: &EventCtx, details: &mut Details, id: AreaID, tab: Tab) -> Vec<Widget> {
+    vec![
+        Widget::row(vec![
+            Line(id.to_string()).small_heading().draw(ctx),
+            header_btns(ctx),
+        ]),
+        make_tabs(
+            ctx,
+            &mut details.hyperlinks,
+            tab,
+            vec![("Info", Tab::Area(id, false))],
+        ),
+    ]
+}
@@ -1,16 +1,37 @@
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
 
-use geom::{Angle, Circle, Distance, Speed, Time};
+use abstutil::{prettyprint_usize, Counter};
+use geom::{Angle, ArrowCap, Circle, Distance, Duration, PolyLine, Pt2D, Speed, Time};
 use map_gui::render::DrawPedestrian;
-use map_model::{BuildingID, LaneID, OffstreetParking, Traversable, SIDEWALK_THICKNESS};
-use sim::{DrawPedestrianInput, PedestrianID, PersonID, TripMode, TripResult, VehicleType};
+use map_model::connectivity::{walkshed_from, WalkingOptions};
+use map_model::{
+    BuildingID, BuildingType, LaneID, OffstreetParking, Traversable, SIDEWALK_THICKNESS,
+};
+use sim::{
+    CarID, DrawPedestrianInput, PedestrianID, PersonID, TripEndpoint, TripMode, TripPurpose,
+    TripResult, VehicleType,
+};
 use widgetry::{Color, EventCtx, Line, StyledButtons, Text, TextExt, Widget};
 
 use crate::app::App;
 use crate::info::{header_btns, make_table, make_tabs, Details, Tab};
+use crate::sandbox::override_badge;
 
-pub fn info(ctx: &mut EventCtx, app: &App, details: &mut Details, id: BuildingID) -> Vec<Widget> {
-    let mut rows = header(ctx, app, details, id, Tab::BldgInfo(id));
+pub fn info(
+    ctx: &mut EventCtx,
+    app: &App,
+    details: &mut Details,
+    id: BuildingID,
+    walkshed: Option<Duration>,
+    desire_lines: bool,
+) -> Vec<Widget> {
+    let mut rows = header(
+        ctx,
+        app,
+        details,
+        id,
+        Tab::BldgInfo(id, walkshed, desire_lines),
+    );
     let b = app.primary.map.get_b(id);
 
     let mut kv = Vec::new();
@@ -22,6 +43,13 @@ pub fn info(ctx: &mut EventCtx, app: &App, details: &mut Details, id: BuildingID
     if app.opts.dev {
         kv.push(("OSM ID", format!("{}", b.orig_id.inner())));
     }
+    match b.bldg_type {
+        BuildingType::Residential { num_residents, .. }
+        | BuildingType::ResidentialCommercial(num_residents, _) => {
+            kv.push(("Residents", prettyprint_usize(num_residents)));
+        }
+        BuildingType::Commercial(_) | BuildingType::Empty => {}
+    }
 
     let num_spots = b.num_parking_spots();
     if app.primary.sim.infinite_parking() {
@@ -51,6 +79,10 @@ pub fn info(ctx: &mut EventCtx, app: &App, details: &mut Details, id: BuildingID
 
     rows.extend(make_table(ctx, kv));
 
+    if num_spots > 0 || app.primary.sim.infinite_parking() {
+        rows.extend(parked_cars(ctx, app, details, id, num_spots));
+    }
+
     let mut txt = Text::new();
 
     if !b.amenities.is_empty() {
@@ -99,16 +131,46 @@ pub fn info(ctx: &mut EventCtx, app: &App, details: &mut Details, id: BuildingID
         txt.add(Line("No nearby parking available"))
     }
 
+    // How the building connects to the rest of the map
+    txt.add(Line(""));
+    txt.add(Line("Front path").fg(Color::CYAN));
+    txt.append(Line(" to the sidewalk"));
+    if num_spots > 0 {
+        if let Some((_, pl)) = b.driving_connection(&app.primary.map) {
+            txt.add(Line("Driveway").fg(Color::ORANGE));
+            txt.append(Line(" to the road"));
+            let poly = pl.make_polygons(Distance::meters(2.0));
+            details.unzoomed.push(Color::ORANGE, poly.clone());
+            details.zoomed.push(Color::ORANGE, poly);
+        }
+    }
+    let poly = b.driveway_geom.make_polygons(Distance::meters(1.0));
+    details.unzoomed.push(Color::CYAN, poly.clone());
+    details.zoomed.push(Color::CYAN, poly);
+
     if !txt.is_empty() {
         rows.push(txt.draw(ctx))
     }
 
+    rows.push(walkshed_controls(
+        ctx,
+        app,
+        details,
+        id,
+        walkshed,
+        desire_lines,
+    ));
+    rows.extend(commutes(ctx, app, details, id, walkshed, desire_lines));
+
     if app.opts.dev {
-        rows.push(
+        rows.push(Widget::row(vec![
             ctx.style()
                 .btn_solid_dark_text("Open OSM")
                 .build_widget(ctx, &format!("open {}", b.orig_id)),
-        );
+            ctx.style()
+                .btn_solid_dark_text("Edit in JOSM")
+                .build_widget(ctx, "edit in JOSM"),
+        ]));
 
         if !b.osm_tags.is_empty() {
             rows.push("Raw OpenStreetMap data".draw_text(ctx));
@@ -126,6 +188,336 @@ pub fn info(ctx: &mut EventCtx, app: &App, details: &mut Details, id: BuildingID
     rows
 }
 
+fn walkshed_controls(
+    ctx: &mut EventCtx,
+    app: &App,
+    details: &mut Details,
+    id: BuildingID,
+    time_limit: Option<Duration>,
+    desire_lines: bool,
+) -> Widget {
+    let time_limit = match time_limit {
+        Some(t) => t,
+        None => {
+            let action = "show 10-minute walkshed";
+            details.hyperlinks.insert(
+                action.to_string(),
+                Tab::BldgInfo(id, Some(Duration::minutes(10)), desire_lines),
+            );
+            return ctx.style().btn_outline_light_text(action).build_def(ctx);
+        }
+    };
+
+    let mut row = vec!["Walkshed:".draw_text(ctx).centered_vert()];
+    for mins in &[5, 10, 15] {
+        let mins = *mins;
+        let action = format!("{}-minute walkshed", mins);
+        row.push(
+            ctx.style()
+                .btn_outline_light_text(&format!("{} min", mins))
+                .disabled(time_limit == Duration::minutes(mins))
+                .build_widget(ctx, &action),
+        );
+        details.hyperlinks.insert(
+            action,
+            Tab::BldgInfo(id, Some(Duration::minutes(mins)), desire_lines),
+        );
+    }
+    row.push(
+        ctx.style()
+            .btn_plain_light_text("hide walkshed")
+            .build_def(ctx),
+    );
+    details.hyperlinks.insert(
+        "hide walkshed".to_string(),
+        Tab::BldgInfo(id, None, desire_lines),
+    );
+
+    app.primary
+        .caches
+        .walkshed
+        .borrow_mut()
+        .update(Some((id, time_limit)), |(b, time_limit)| {
+            ctx.loading_screen("calculate walkshed", |_, _| {
+                walkshed_from(&app.primary.map, b, time_limit, WalkingOptions::default())
+            })
+        });
+
+    let map = &app.primary.map;
+    let color = Color::GREEN.alpha(0.5);
+    let cache = app.primary.caches.walkshed.borrow();
+    let pieces = cache.value().unwrap();
+    for (l, dist1, dist2) in pieces {
+        let lane = map.get_l(*l);
+        if let Ok(pl) = lane.lane_center_pts.maybe_exact_slice(*dist1, *dist2) {
+            let poly = pl.make_polygons(lane.width);
+            details.unzoomed.push(color, poly.clone());
+            details.zoomed.push(color, poly);
+        }
+    }
+    let num_sidewalks = pieces
+        .iter()
+        .map(|(l, _, _)| *l)
+        .collect::<BTreeSet<_>>()
+        .len();
+
+    Widget::col(vec![
+        Widget::row(row),
+        Text::from(
+            Line(format!(
+                "{} sidewalks reachable in {} at {}",
+                prettyprint_usize(num_sidewalks),
+                time_limit,
+                WalkingOptions::default_speed().to_string(&app.opts.units)
+            ))
+            .fg(Color::GREEN),
+        )
+        .draw(ctx),
+    ])
+}
+
+// How many places to list for each direction of commute
+const COMMUTES_LISTED: usize = 5;
+// More desire lines than this turn into an unreadable tangle
+const MAX_DESIRE_LINES: usize = 10;
+
+// Where the people living or working here go, according to the day's trips. Residents are people
+// whose first trip of the day starts here; employees are people heading here for work.
+fn commutes(
+    ctx: &mut EventCtx,
+    app: &App,
+    details: &mut Details,
+    id: BuildingID,
+    walkshed: Option<Duration>,
+    desire_lines: bool,
+) -> Vec<Widget> {
+    let sim = &app.primary.sim;
+    // Trips only change when more people are spawned
+    let num_people = sim.get_all_people().len();
+    app.primary
+        .caches
+        .commutes
+        .borrow_mut()
+        .update(Some((id, num_people)), |(id, _)| {
+            let mut residents_to = Counter::new();
+            let mut employees_from = Counter::new();
+            for person in sim.get_all_people() {
+                for (idx, t) in person.trips.iter().enumerate() {
+                    let trip = sim.trip_info(*t);
+                    if idx == 0 && trip.start == TripEndpoint::Bldg(id) {
+                        residents_to.inc(trip.end);
+                    }
+                    if trip.end == TripEndpoint::Bldg(id) && trip.purpose == TripPurpose::Work {
+                        employees_from.inc(trip.start);
+                    }
+                }
+            }
+            (residents_to, employees_from)
+        });
+
+    let cache = app.primary.caches.commutes.borrow();
+    let (residents_to, employees_from) = cache.value().unwrap();
+    if residents_to.sum() == 0 && employees_from.sum() == 0 {
+        return Vec::new();
+    }
+
+    let action = if desire_lines {
+        "hide desire lines"
+    } else {
+        "draw desire lines"
+    };
+    details.hyperlinks.insert(
+        action.to_string(),
+        Tab::BldgInfo(id, walkshed, !desire_lines),
+    );
+    let mut rows = vec![Widget::row(vec![
+        Line("Commute patterns")
+            .small_heading()
+            .draw(ctx)
+            .centered_vert(),
+        ctx.style()
+            .btn_plain_light_text(action)
+            .build_def(ctx)
+            .align_right(),
+    ])];
+
+    // (number of trips, color, the other end, heading here)
+    let mut lines = Vec::new();
+    for (title, counter, color, inbound) in vec![
+        (
+            "Residents' first trip of the day goes to",
+            residents_to,
+            Color::PINK,
+            false,
+        ),
+        (
+            "Employees commute from",
+            employees_from,
+            Color::YELLOW,
+            true,
+        ),
+    ] {
+        if counter.sum() == 0 {
+            continue;
+        }
+        let top = counter.highest_n(COMMUTES_LISTED);
+        let mut txt = Text::from(Line(title).fg(color));
+        for (endpoint, count) in &top {
+            txt.add(Line(format!(
+                "  {}: {}",
+                describe_endpoint(app, *endpoint),
+                prettyprint_usize(*count)
+            )));
+        }
+        let others = counter.sum() - top.iter().map(|(_, count)| *count).sum::<usize>();
+        if others > 0 {
+            txt.add(
+                Line(format!(
+                    "  ... and {} more trips",
+                    prettyprint_usize(others)
+                ))
+                .secondary(),
+            );
+        }
+        rows.push(txt.draw(ctx));
+
+        for (endpoint, count) in counter.highest_n(MAX_DESIRE_LINES) {
+            lines.push((count, color, endpoint, inbound));
+        }
+    }
+
+    if desire_lines {
+        lines.sort_by_key(|(count, _, _, _)| std::cmp::Reverse(*count));
+        lines.truncate(MAX_DESIRE_LINES);
+        let max = lines[0].0 as f64;
+        let here = app.primary.map.get_b(id).label_center;
+        for (count, color, endpoint, inbound) in lines {
+            let there = endpoint_pt(app, endpoint);
+            let pts = if inbound {
+                vec![there, here]
+            } else {
+                vec![here, there]
+            };
+            // Trips within the building itself have nothing to draw
+            if let Ok(pl) = PolyLine::new(pts) {
+                let thickness = Distance::meters(5.0 + 15.0 * (count as f64) / max);
+                details.unzoomed.push(
+                    color.alpha(0.8),
+                    pl.make_arrow(thickness, ArrowCap::Triangle),
+                );
+            }
+        }
+    }
+
+    rows
+}
+
+fn describe_endpoint(app: &App, endpoint: TripEndpoint) -> String {
+    let map = &app.primary.map;
+    match endpoint {
+        TripEndpoint::Bldg(b) => map.get_b(b).address.clone(),
+        TripEndpoint::Border(i) => format!(
+            "off-map, via {}",
+            map.get_i(i).name(app.opts.language.as_ref(), map)
+        ),
+        TripEndpoint::SuddenlyAppear(pos) => format!(
+            "somewhere on {}",
+            map.get_parent(pos.lane())
+                .get_name(app.opts.language.as_ref())
+        ),
+    }
+}
+
+fn endpoint_pt(app: &App, endpoint: TripEndpoint) -> Pt2D {
+    match endpoint {
+        TripEndpoint::Bldg(b) => app.primary.map.get_b(b).label_center,
+        TripEndpoint::Border(i) => app.primary.map.get_i(i).polygon.center(),
+        TripEndpoint::SuddenlyAppear(pos) => pos.pt(&app.primary.map),
+    }
+}
+
+// Cars parked inside a building sit there while their owners are elsewhere, so explain who they
+// belong to and whether they'll move again.
+fn parked_cars(
+    ctx: &mut EventCtx,
+    app: &App,
+    details: &mut Details,
+    id: BuildingID,
+    num_spots: usize,
+) -> Vec<Widget> {
+    // Big garages can hold hundreds of cars
+    let max_listed = 20;
+
+    let sim = &app.primary.sim;
+    let now = sim.time();
+    let mut cars: Vec<(Time, CarID)> = sim
+        .bldg_to_parked_cars(id)
+        .into_iter()
+        .filter_map(|car| sim.lookup_parked_car(car).map(|p| (p.parked_since, car)))
+        .collect();
+    // Idle the longest first
+    cars.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+
+    let mut rows = Vec::new();
+    let mut txt = Text::from(Line("Parked cars").small_heading());
+    txt.add(Line(format!(
+        "{} used, {} reserved by incoming drivers, {}",
+        cars.len(),
+        sim.bldg_to_reserved_cars(id).len(),
+        if sim.infinite_parking() {
+            "unlimited capacity".to_string()
+        } else {
+            format!("{} capacity", num_spots)
+        }
+    )));
+    rows.push(txt.draw(ctx));
+
+    let num_cars = cars.len();
+    for (parked_since, car) in cars.into_iter().take(max_listed) {
+        let mut txt = Text::from(Line(format!(
+            "Car #{}, parked since {} ({} ago)",
+            car.0,
+            parked_since.ampm_tostring(),
+            now - parked_since
+        )));
+        let owner = match sim.get_owner_of_car(car) {
+            Some(p) => p,
+            None => {
+                rows.push(txt.draw(ctx));
+                continue;
+            }
+        };
+        let needed_again = sim.get_person(owner).trips.iter().any(|t| {
+            matches!(sim.trip_to_agent(*t), TripResult::TripNotStarted)
+                && sim.trip_info(*t).mode == TripMode::Drive
+        });
+        txt.add(
+            Line(if needed_again {
+                "The owner will drive again today"
+            } else {
+                "The owner won't drive again today"
+            })
+            .secondary(),
+        );
+
+        let action = format!("examine {} (car #{})", owner, car.0);
+        details
+            .hyperlinks
+            .insert(action.clone(), Tab::PersonTrips(owner, BTreeMap::new()));
+        rows.push(Widget::row(vec![
+            txt.draw(ctx),
+            ctx.style()
+                .btn_solid_dark_text(&format!("examine {}", owner))
+                .build_widget(ctx, &action)
+                .align_right(),
+        ]));
+    }
+    if num_cars > max_listed {
+        rows.push(format!("... and {} more", num_cars - max_listed).draw_text(ctx));
+    }
+    rows
+}
+
 pub fn people(ctx: &mut EventCtx, app: &App, details: &mut Details, id: BuildingID) -> Vec<Widget> {
     let mut rows = header(ctx, app, details, id, Tab::BldgPeople(id));
 
@@ -236,11 +628,16 @@ fn header(
         header_btns(ctx),
     ]));
 
+    rows.extend(override_badge(ctx, app, id));
+
     rows.push(make_tabs(
         ctx,
         &mut details.hyperlinks,
         tab,
-        vec![("Info", Tab::BldgInfo(id)), ("People", Tab::BldgPeople(id))],
+        vec![
+            ("Info", Tab::BldgInfo(id, None, false)),
+            ("People", Tab::BldgPeople(id)),
+        ],
     ));
 
     draw_occupants(details, app, id, None);
@@ -1,26 +1,89 @@
+use std::cell::Ref;
+use std::collections::HashMap;
+
 use abstutil::{prettyprint_usize, Counter};
-use geom::{Circle, Distance, Time};
+use geom::{Circle, Distance, Duration, Percent, Polygon, Pt2D, Time};
 use map_gui::tools::ColorNetwork;
 use map_gui::ID;
-use map_model::{BusRoute, BusRouteID, BusStopID, PathStep};
-use sim::{AgentID, CarID};
-use widgetry::{Color, EventCtx, Key, Line, StyledButtons, Text, TextExt, Widget};
+use map_model::connectivity::{all_walking_costs_from_bus_stop, WalkingOptions};
+use map_model::{BuildingID, BusRoute, BusRouteID, BusStopID, PathStep};
+use sim::{AgentID, CarID, RouteVehicle};
+use widgetry::{
+    Color, EventCtx, GeomBatch, Key, Line, LinePlot, PlotOptions, Series, StyledButtons, Text,
+    TextExt, Widget,
+};
 
 use crate::app::App;
-use crate::info::{header_btns, make_tabs, Details, Tab};
+use crate::info::{
+    header_btns, make_table, make_tabs, plot_dims, section_header, trip, DataOptions, Details, Tab,
+};
+
+const BOARDING_COLOR: Color = Color::GREEN;
+const ALIGHTING_COLOR: Color = Color::RED;
+// Transfers to other routes further away than this aren't listed
+const TRANSFER_LIMIT: Duration = Duration::const_seconds(15.0 * 60.0);
+// Buildings within this walk of a stop are in its catchment
+const CATCHMENT_LIMIT: Duration = Duration::const_seconds(5.0 * 60.0);
+
+/// How long it takes to walk from one bus stop to nearby buildings and other stops, cached in
+/// `Caches`.
+pub struct StopWalkingCosts {
+    buildings: HashMap<BuildingID, Duration>,
+    stops: HashMap<BusStopID, Duration>,
+}
+
+impl StopWalkingCosts {
+    /// Pathfinding along sidewalks is too slow to redo every time the panel refreshes, so only
+    /// calculate for a new stop.
+    fn get<'a>(ctx: &mut EventCtx, app: &'a App, id: BusStopID) -> Ref<'a, StopWalkingCosts> {
+        app.primary
+            .caches
+            .stop_walking_costs
+            .borrow_mut()
+            .update(Some(id), |id| {
+                let (buildings, stops) = ctx.loading_screen("calculate walking times", |_, _| {
+                    all_walking_costs_from_bus_stop(
+                        &app.primary.map,
+                        id,
+                        TRANSFER_LIMIT.max(CATCHMENT_LIMIT),
+                        WalkingOptions::default(),
+                    )
+                });
+                StopWalkingCosts { buildings, stops }
+            });
+        Ref::map(app.primary.caches.stop_walking_costs.borrow(), |costs| {
+            costs.value().unwrap()
+        })
+    }
+}
 
-pub fn stop(ctx: &mut EventCtx, app: &App, details: &mut Details, id: BusStopID) -> Vec<Widget> {
+pub fn stop(
+    ctx: &mut EventCtx,
+    app: &App,
+    details: &mut Details,
+    id: BusStopID,
+    catchment: bool,
+) -> Vec<Widget> {
     let bs = app.primary.map.get_bs(id);
-    let mut rows = vec![];
+    let mut rows = stop_header(ctx, app, details, id, Tab::BusStop(id, catchment));
+
+    // Plenty of stops just haven't been surveyed, so don't assume a missing tag means no
+    let describe = |key: &str| {
+        bs.osm_tags
+            .get(key)
+            .cloned()
+            .unwrap_or_else(|| "unknown".to_string())
+    };
+    rows.extend(make_table(
+        ctx,
+        vec![
+            ("Shelter", describe("shelter")),
+            ("Bench", describe("bench")),
+        ],
+    ));
 
     let sim = &app.primary.sim;
 
-    rows.push(Widget::row(vec![
-        Line("Bus stop").small_heading().draw(ctx),
-        header_btns(ctx),
-    ]));
-    rows.push(Line(&bs.name).draw(ctx));
-
     let all_arrivals = &sim.get_analytics().bus_arrivals;
     for r in app.primary.map.get_routes_serving_stop(id) {
         // Full names can overlap, so include the ID
@@ -38,6 +101,8 @@ pub fn stop(ctx: &mut EventCtx, app: &App, details: &mut Details, id: BusStopID)
             .map(|(t, car, _, _)| (*t, *car))
             .collect();
         let mut txt = Text::new();
+        txt.add(Line(format!("  {}", r.full_name)).secondary());
+        txt.add(Line(format!("  {}", describe_frequency(r, sim.time()))).secondary());
         if let Some((t, _)) = arrivals.last() {
             // TODO Button to jump to the bus
             txt.add(Line(format!("  Last bus arrived {} ago", sim.time() - *t)).secondary());
@@ -47,6 +112,8 @@ pub fn stop(ctx: &mut EventCtx, app: &App, details: &mut Details, id: BusStopID)
         rows.push(txt.draw(ctx));
     }
 
+    rows.extend(transfers(ctx, app, details, id, catchment));
+
     let mut boardings: Counter<BusRouteID> = Counter::new();
     let mut alightings: Counter<BusRouteID> = Counter::new();
     if let Some(list) = app.primary.sim.get_analytics().passengers_boarding.get(&id) {
@@ -97,6 +164,248 @@ pub fn stop(ctx: &mut EventCtx, app: &App, details: &mut Details, id: BusStopID)
     rows
 }
 
+// For every route not serving this stop, the closest stop for it within walking distance, plus
+// the buildings nearby
+fn transfers(
+    ctx: &mut EventCtx,
+    app: &App,
+    details: &mut Details,
+    id: BusStopID,
+    catchment: bool,
+) -> Vec<Widget> {
+    let map = &app.primary.map;
+    let costs = StopWalkingCosts::get(ctx, app, id);
+    let mut rows = vec![Line("Nearby transfers").small_heading().draw(ctx)];
+
+    let serving: Vec<BusRouteID> = map
+        .get_routes_serving_stop(id)
+        .into_iter()
+        .map(|r| r.id)
+        .collect();
+    let mut nearest: Vec<(Duration, &BusRoute, BusStopID)> = Vec::new();
+    for r in map.all_bus_routes() {
+        if serving.contains(&r.id) {
+            continue;
+        }
+        if let Some((cost, bs)) = r
+            .stops
+            .iter()
+            .filter_map(|bs| costs.stops.get(bs).map(|cost| (*cost, *bs)))
+            .filter(|(cost, _)| *cost <= TRANSFER_LIMIT)
+            .min()
+        {
+            nearest.push((cost, r, bs));
+        }
+    }
+    nearest.sort_by_key(|(cost, r, _)| (*cost, r.short_name.clone()));
+
+    if nearest.is_empty() {
+        rows.push(
+            Line(format!("No other routes within a {} walk", TRANSFER_LIMIT))
+                .secondary()
+                .draw(ctx),
+        );
+    }
+    for (cost, r, bs) in nearest {
+        let name = format!("Route {} at {}", r.short_name, map.get_bs(bs).name);
+        rows.push(Widget::row(vec![
+            ctx.style()
+                .btn_plain_light_icon("system/assets/tools/pin.svg")
+                .build_widget(ctx, &name),
+            Text::from_all(vec![
+                Line(&name),
+                Line(format!(
+                    ": {} walk, {}",
+                    (WalkingOptions::default_speed() * cost).to_string(&app.opts.units),
+                    cost
+                ))
+                .secondary(),
+            ])
+            .draw(ctx),
+        ]));
+        details.warpers.insert(name, ID::BusStop(bs));
+    }
+
+    let within: Vec<BuildingID> = costs
+        .buildings
+        .iter()
+        .filter(|(_, cost)| **cost <= CATCHMENT_LIMIT)
+        .map(|(b, _)| *b)
+        .collect();
+    if catchment {
+        let color = Color::GREEN.alpha(0.5);
+        for b in &within {
+            let poly = map.get_b(*b).polygon.clone();
+            details.unzoomed.push(color, poly.clone());
+            details.zoomed.push(color, poly);
+        }
+    }
+    let action = if catchment {
+        "hide walking catchment"
+    } else {
+        "show walking catchment"
+    };
+    rows.push(Widget::row(vec![
+        ctx.style().btn_outline_light_text(action).build_def(ctx),
+        Line(format!(
+            "{} buildings within a {} walk",
+            prettyprint_usize(within.len()),
+            CATCHMENT_LIMIT
+        ))
+        .secondary()
+        .draw(ctx)
+        .centered_vert(),
+    ]));
+    details
+        .hyperlinks
+        .insert(action.to_string(), Tab::BusStop(id, !catchment));
+
+    rows
+}
+
+pub fn stop_throughput(
+    ctx: &mut EventCtx,
+    app: &App,
+    details: &mut Details,
+    id: BusStopID,
+    route: Option<BusRouteID>,
+    opts: &DataOptions,
+) -> Vec<Widget> {
+    let mut rows = stop_header(
+        ctx,
+        app,
+        details,
+        id,
+        Tab::BusStopThroughput(id, route, opts.clone()),
+    );
+
+    let sim = &app.primary.sim;
+    let analytics = sim.get_analytics();
+    if !analytics.passengers_boarding.contains_key(&id)
+        && !analytics.passengers_alighting.contains_key(&id)
+    {
+        rows.push("No transit activity yet".draw_text(ctx));
+        return rows;
+    }
+
+    // Filter by route, if there's more than one
+    let routes = app.primary.map.get_routes_serving_stop(id);
+    if routes.len() > 1 {
+        let mut chips = vec![ctx
+            .style()
+            .btn_outline_light_text("all routes")
+            .disabled(route.is_none())
+            .build_def(ctx)];
+        details.hyperlinks.insert(
+            "all routes".to_string(),
+            Tab::BusStopThroughput(id, None, opts.clone()),
+        );
+        for r in routes {
+            let label = format!("only route {}", r.short_name);
+            chips.push(
+                ctx.style()
+                    .btn_outline_light_text(&r.short_name)
+                    .disabled(route == Some(r.id))
+                    .build_widget(ctx, &label),
+            );
+            details
+                .hyperlinks
+                .insert(label, Tab::BusStopThroughput(id, Some(r.id), opts.clone()));
+        }
+        rows.push(Widget::custom_row(chips).flex_wrap(ctx, Percent::int(20)));
+    }
+
+    rows.push(opts.to_controls(ctx, app));
+
+    let (boardings, alightings, avg_wait) = analytics.bus_stop_activity(id, route, sim.time());
+    let mut series = vec![
+        Series {
+            label: "Boardings".to_string(),
+            color: BOARDING_COLOR,
+            pts: boardings,
+        },
+        Series {
+            label: "Alightings".to_string(),
+            color: ALIGHTING_COLOR,
+            pts: alightings,
+        },
+    ];
+    let mut txt = Text::from(Line(format!(
+        "Average wait before boarding: {}",
+        avg_wait
+            .map(|d| d.to_string())
+            .unwrap_or_else(|| "nobody has boarded".to_string())
+    )));
+    if opts.show_before {
+        let time = if opts.show_end_of_day {
+            sim.get_end_of_day()
+        } else {
+            sim.time()
+        };
+        let (boardings, alightings, avg_wait) = app.prebaked().bus_stop_activity(id, route, time);
+        series.push(Series {
+            label: "Boardings before".to_string(),
+            color: BOARDING_COLOR.alpha(0.3),
+            pts: boardings,
+        });
+        series.push(Series {
+            label: "Alightings before".to_string(),
+            color: ALIGHTING_COLOR.alpha(0.3),
+            pts: alightings,
+        });
+        if let Some(d) = avg_wait {
+            txt.append(Line(format!(" (before: {})", d)).secondary());
+        }
+    }
+    rows.push(txt.draw(ctx));
+
+    let (section, expanded) = section_header(ctx, app, "bus stop", "Boardings and alightings");
+    rows.push(section);
+    if expanded {
+        rows.push(details.plot("passengers", || {
+            let mut plot_opts = PlotOptions::filterable();
+            plot_opts.dims = Some(plot_dims(ctx, app));
+            Widget::col(vec![
+                Line("Passengers per hour").small_heading().draw(ctx),
+                LinePlot::new(ctx, series, plot_opts),
+            ])
+            .padding(10)
+            .bg(app.cs.inner_panel)
+            .outline(2.0, Color::WHITE)
+        }));
+    }
+
+    rows
+}
+
+fn stop_header(
+    ctx: &mut EventCtx,
+    app: &App,
+    details: &mut Details,
+    id: BusStopID,
+    tab: Tab,
+) -> Vec<Widget> {
+    vec![
+        Widget::row(vec![
+            Line("Bus stop").small_heading().draw(ctx),
+            header_btns(ctx),
+        ]),
+        Line(&app.primary.map.get_bs(id).name).draw(ctx),
+        make_tabs(
+            ctx,
+            &mut details.hyperlinks,
+            tab,
+            vec![
+                ("Info", Tab::BusStop(id, false)),
+                (
+                    "Throughput",
+                    Tab::BusStopThroughput(id, None, DataOptions::new()),
+                ),
+            ],
+        ),
+    ]
+}
+
 pub fn bus_status(ctx: &mut EventCtx, app: &App, details: &mut Details, id: CarID) -> Vec<Widget> {
     let mut rows = bus_header(ctx, app, details, id, Tab::BusStatus(id));
 
@@ -122,6 +431,10 @@ pub fn bus_status(ctx: &mut EventCtx, app: &App, details: &mut Details, id: CarI
         ))
         .draw(ctx),
     );
+    if let Some(widget) = trip::delay_caused(ctx, app, id) {
+        rows.push(Line("Delay caused").small_heading().draw(ctx));
+        rows.push(widget);
+    }
 
     rows
 }
@@ -189,21 +502,37 @@ pub fn route(ctx: &mut EventCtx, app: &App, details: &mut Details, id: BusRouteI
         );
     }
 
-    let buses = app.primary.sim.status_of_buses(id, map);
+    let vehicles = app.primary.sim.vehicles_along_route(id);
     let mut bus_locations = Vec::new();
-    if buses.is_empty() {
+    if vehicles.is_empty() {
         rows.push(format!("No {} running", route.plural_noun()).draw_text(ctx));
     } else {
-        for (bus, _, _, pt) in buses {
-            rows.push(
+        rows.push(route_diagram(ctx, route, &vehicles));
+        for vehicle in &vehicles {
+            let bus = vehicle.bus;
+            let mut txt = Text::new();
+            if let Some(headway) = vehicle.headway {
+                txt.append(Line(format!("{} behind the one ahead", headway)).secondary());
+                if is_bunched(vehicle) {
+                    txt.append(Line(" (bunched)").fg(Color::RED));
+                }
+            }
+            rows.push(Widget::row(vec![
                 ctx.style()
                     .btn_outline_light_text(&bus.to_string())
                     .build_def(ctx),
-            );
+                txt.draw(ctx).centered_vert(),
+            ]));
             details
                 .hyperlinks
                 .insert(bus.to_string(), Tab::BusStatus(bus));
-            bus_locations.push(pt);
+            if let Some(pt) = app
+                .primary
+                .sim
+                .canonical_pt_for_agent(AgentID::Car(bus), map)
+            {
+                bus_locations.push(pt);
+            }
         }
     }
 
@@ -259,6 +588,7 @@ pub fn route(ctx: &mut EventCtx, app: &App, details: &mut Details, id: BusRouteI
         ]));
         details.warpers.insert(name, ID::Intersection(i.id));
     }
+    let max_boardings = boardings.max().max(1);
     for (idx, bs) in route.stops.iter().enumerate() {
         let bs = map.get_bs(*bs);
         let name = format!("Stop {}: {}", idx + 1, bs.name);
@@ -266,6 +596,7 @@ pub fn route(ctx: &mut EventCtx, app: &App, details: &mut Details, id: BusRouteI
             ctx.style()
                 .btn_plain_light_icon("system/assets/tools/pin.svg")
                 .build_widget(ctx, &name),
+            boardings_bar(ctx, app, boardings.get(bs.id), max_boardings),
             Text::from_all(vec![
                 Line(&bs.name),
                 Line(format!(
@@ -348,6 +679,90 @@ pub fn route(ctx: &mut EventCtx, app: &App, details: &mut Details, id: BusRouteI
     rows
 }
 
+// Vehicles closer together than this are flagged as bunched
+const BUNCHING_HEADWAY: Duration = Duration::const_seconds(2.0 * 60.0);
+
+const DIAGRAM_WIDTH: f64 = 300.0;
+
+fn is_bunched(vehicle: &RouteVehicle) -> bool {
+    vehicle
+        .headway
+        .map(|headway| headway < BUNCHING_HEADWAY)
+        .unwrap_or(false)
+}
+
+// The route straightened out, with a tick for each stop and a dot for each vehicle
+fn route_diagram(ctx: &mut EventCtx, route: &BusRoute, vehicles: &[RouteVehicle]) -> Widget {
+    // Vehicles start before the first stop and might drive off the map after the last
+    let num_stops = route.stops.len() as f64;
+    let to_x = |progress: f64| DIAGRAM_WIDTH * (progress + 1.0) / (num_stops + 1.0);
+    let height = 20.0;
+
+    let mut batch = GeomBatch::new();
+    batch.push(
+        Color::WHITE,
+        Polygon::rectangle(DIAGRAM_WIDTH, 2.0).translate(0.0, height / 2.0 - 1.0),
+    );
+    for idx in 0..route.stops.len() {
+        batch.push(
+            Color::WHITE,
+            Polygon::rectangle(2.0, height / 2.0).translate(to_x(idx as f64) - 1.0, height / 4.0),
+        );
+    }
+    for vehicle in vehicles {
+        batch.push(
+            if is_bunched(vehicle) {
+                Color::RED
+            } else {
+                Color::BLUE
+            },
+            Circle::new(
+                Pt2D::new(to_x(vehicle.progress), height / 2.0),
+                Distance::meters(5.0),
+            )
+            .to_polygon(),
+        );
+    }
+    Widget::draw_batch(ctx, batch)
+}
+
+fn boardings_bar(ctx: &mut EventCtx, app: &App, boardings: usize, max: usize) -> Widget {
+    let width = 50.0;
+    let mut batch = GeomBatch::new();
+    batch.push(app.cs.inner_panel, Polygon::rectangle(width, 10.0));
+    if boardings > 0 {
+        batch.push(
+            app.cs.unzoomed_bus,
+            Polygon::rectangle(width * (boardings as f64) / (max as f64), 10.0),
+        );
+    }
+    Widget::draw_batch(ctx, batch).centered_vert()
+}
+
+// How often vehicles are scheduled to start the route in the hour around some time
+fn describe_frequency(route: &BusRoute, now: Time) -> String {
+    let window = Duration::minutes(30);
+    let count = route
+        .spawn_times
+        .iter()
+        .filter(|t| **t + window >= now && **t < now + window)
+        .count();
+    let daily = format!(
+        "{} {} scheduled daily",
+        prettyprint_usize(route.spawn_times.len()),
+        route.plural_noun()
+    );
+    match count {
+        0 => format!("{}, none around now", daily),
+        1 => format!("{}, once an hour around now", daily),
+        n => format!(
+            "{}, every {} around now",
+            daily,
+            Duration::hours(1) / (n as f64)
+        ),
+    }
+}
+
 // TODO Unit test
 fn describe_schedule(route: &BusRoute) -> Text {
     let mut txt = Text::new();
@@ -1,22 +1,47 @@
 use std::collections::{BTreeMap, BTreeSet};
 
+use anyhow::Result;
+
 use abstutil::prettyprint_usize;
 use geom::{ArrowCap, Distance, Duration, PolyLine, Polygon, Time};
 use map_gui::options::TrafficSignalStyle;
 use map_gui::render::traffic_signal::draw_signal_stage;
-use map_model::{IntersectionID, IntersectionType, StageType};
-use sim::AgentType;
+use map_gui::tools::ColorLegend;
+use map_gui::ID;
+use map_model::{
+    CompressedMovementID, EditCmd, EditIntersection, IntersectionID, IntersectionType, StageType,
+    Traversable, TurnType,
+};
+use sim::{AgentID, AgentType, DelayCause};
 use widgetry::{
-    Checkbox, Color, DrawWithTooltips, EventCtx, FanChart, GeomBatch, Line, PlotOptions,
-    ScatterPlot, Series, StyledButtons, Text, Widget,
+    Checkbox, Color, ControlState, DrawWithTooltips, EventCtx, FanChart, GeomBatch, Line, LinePlot,
+    PlotOptions, ScatterPlot, Series, StyledButtons, Text, TextExt, Widget,
 };
 
 use crate::app::App;
-use crate::common::color_for_agent_type;
-use crate::info::{header_btns, make_tabs, throughput, DataOptions, Details, Tab};
+use crate::common::{agent_type_registry, plot_with_legend, ModeRegistry};
+use crate::edit::import_signal_timing;
+use crate::info::{
+    header_btns, make_table, make_tabs, panel_width, plot_dims, section_header, throughput,
+    DataOptions, Details, Tab,
+};
+use crate::layer::edits::intersection_diff;
+use crate::sandbox::apply_live_edits;
 
-pub fn info(ctx: &EventCtx, app: &App, details: &mut Details, id: IntersectionID) -> Vec<Widget> {
-    let mut rows = header(ctx, app, details, id, Tab::IntersectionInfo(id));
+pub fn info(
+    ctx: &mut EventCtx,
+    app: &App,
+    details: &mut Details,
+    id: IntersectionID,
+    show_spillback: bool,
+) -> Vec<Widget> {
+    let mut rows = header(
+        ctx,
+        app,
+        details,
+        id,
+        Tab::IntersectionInfo(id, show_spillback),
+    );
     let i = app.primary.map.get_i(id);
 
     let mut txt = Text::from(Line("Connecting"));
@@ -33,15 +58,221 @@ pub fn info(ctx: &EventCtx, app: &App, details: &mut Details, id: IntersectionID
         txt.add(Line(format!("  {}", r)));
     }
     rows.push(txt.draw(ctx));
+    if let Some(diff) = intersection_diff(app, id) {
+        rows.extend(make_table(
+            ctx,
+            vec![(
+                format!("Edited {}", diff.kind.describe()),
+                diff.before_after(),
+            )],
+        ));
+    }
 
     if app.opts.dev {
-        rows.push(
+        rows.push(Widget::row(vec![
             ctx.style()
                 .btn_solid_dark_text("Open OSM node")
                 .build_widget(ctx, &format!("open {}", i.orig_id)),
+            ctx.style()
+                .btn_solid_dark_text("Edit in JOSM")
+                .build_widget(ctx, "edit in JOSM"),
+        ]));
+    }
+
+    if !i.is_border() {
+        let (section, expanded) = section_header(ctx, app, "intersection", "Waiting approaches");
+        rows.push(section);
+        if expanded {
+            rows.extend(waiting_approaches(ctx, app, details, id));
+        }
+        rows.extend(queue_spillback(ctx, app, details, id, show_spillback));
+    }
+
+    rows
+}
+
+// How often to trace the queues again while the simulation runs
+const SPILLBACK_REFRESH: Duration = Duration::const_seconds(5.0);
+// How many lanes upstream to follow a queue. During gridlock, a queue can wind through most of the
+// map.
+const SPILLBACK_MAX_DEPTH: usize = 20;
+
+/// Traces queues backwards from every approach, across upstream intersections when a queue fills
+/// an entire lane, and draws them colored by how long vehicles have been stuck.
+fn queue_spillback(
+    ctx: &mut EventCtx,
+    app: &App,
+    details: &mut Details,
+    id: IntersectionID,
+    show: bool,
+) -> Vec<Widget> {
+    if !show {
+        let action = "show upstream queue spillback";
+        details
+            .hyperlinks
+            .insert(action.to_string(), Tab::IntersectionInfo(id, true));
+        return vec![ctx.style().btn_outline_light_text(action).build_def(ctx)];
+    }
+    details.hyperlinks.insert(
+        "hide upstream queue spillback".to_string(),
+        Tab::IntersectionInfo(id, false),
+    );
+
+    let now = app.primary.sim.time();
+    let mut cache = app.primary.caches.queue_spillback.borrow_mut();
+    // Keep using queues traced recently
+    let key = match cache.key() {
+        Some((i, t)) if i == id && t <= now && now - t < SPILLBACK_REFRESH => (i, t),
+        _ => (id, now),
+    };
+    cache.update(Some(key), |(i, _)| {
+        app.primary
+            .sim
+            .get_queue_spillback(&app.primary.map, i, SPILLBACK_MAX_DEPTH)
+    });
+    drop(cache);
+
+    let map = &app.primary.map;
+    let cache = app.primary.caches.queue_spillback.borrow();
+    let queues = cache.value().unwrap();
+    let mut rows = vec![Widget::row(vec![
+        Line(format!("{} approaches with a queue", queues.len()))
+            .small_heading()
+            .draw(ctx)
+            .centered_vert(),
+        ctx.style()
+            .btn_plain_light_text("hide upstream queue spillback")
+            .build_def(ctx)
+            .align_right(),
+    ])];
+    rows.push(ColorLegend::gradient(
+        ctx,
+        &app.cs.good_to_bad_red,
+        vec!["0", "wait (minutes)", "5+"],
+    ));
+    for queue in queues {
+        for seg in &queue.segments {
+            let lane = map.get_l(seg.lane);
+            if let Ok(pl) = lane.lane_center_pts.maybe_exact_slice(seg.start, seg.end) {
+                let color = app
+                    .cs
+                    .good_to_bad_red
+                    .eval((seg.max_wait / Duration::minutes(5)).min(1.0));
+                let poly = pl.make_polygons(lane.width);
+                details.unzoomed.push(color.alpha(0.8), poly.clone());
+                details.zoomed.push(color.alpha(0.5), poly);
+            }
+        }
+
+        let mut txt = Text::from(Line(
+            map.get_parent(queue.approach)
+                .get_name(app.opts.language.as_ref()),
+        ));
+        txt.add(
+            Line(format!(
+                "{} long, {} vehicles{}",
+                queue.length(),
+                prettyprint_usize(queue.num_vehicles),
+                if queue.segments.len() > 1 {
+                    format!(", spilling back across {} lanes", queue.segments.len())
+                } else {
+                    String::new()
+                }
+            ))
+            .secondary(),
         );
+        if queue.truncated {
+            txt.add(Line("... and continuing further back").secondary());
+        }
+        rows.push(txt.draw(ctx));
+    }
+    rows
+}
+
+/// Every incoming lane with a vehicle waiting to enter right now, worst first. Only the head of
+/// each queue waits at the intersection, so there's one entry per lane.
+fn waiting_approaches(
+    ctx: &EventCtx,
+    app: &App,
+    details: &mut Details,
+    id: IntersectionID,
+) -> Vec<Widget> {
+    let map = &app.primary.map;
+    let sim = &app.primary.sim;
+    let now = sim.time();
+    let accepted: BTreeSet<AgentID> = sim
+        .get_accepted_agents(id)
+        .into_iter()
+        .map(|(a, _)| a)
+        .collect();
+    let blocked_by = sim.get_blocked_by_graph(map);
+
+    let mut approaches = Vec::new();
+    for (agent, turn, started) in sim.get_waiting_agents(id) {
+        let car = match agent {
+            AgentID::Car(c) => c,
+            _ => continue,
+        };
+        let queued = sim.get_draw_cars(Traversable::Lane(turn.src), map).len();
+        // If the blocker isn't doing a turn here, then there's no room for the head of the queue
+        // in the lane it's turning into.
+        let downstream = match blocked_by.get(&agent) {
+            Some((_, DelayCause::Agent(other))) => !accepted.contains(other),
+            _ => false,
+        };
+        approaches.push((now - started, car, turn, queued, downstream));
     }
+    approaches.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap());
+
+    let mut rows = vec![
+        Line(format!("{} approaches waiting right now", approaches.len()))
+            .small_heading()
+            .draw(ctx),
+    ];
+    let is_signal = map.get_i(id).is_traffic_signal();
+    for (wait, car, turn, queued, downstream) in approaches {
+        let turn = map.get_t(turn);
+        let movement = match turn.turn_type {
+            TurnType::Left => "turn left onto",
+            TurnType::Right => "turn right onto",
+            TurnType::UTurn => "make a U-turn onto",
+            _ => "go straight onto",
+        };
+        let mut txt = Text::from(Line(format!(
+            "{} vehicles queued on {}",
+            prettyprint_usize(queued),
+            map.get_parent(turn.id.src)
+                .get_name(app.opts.language.as_ref())
+        )));
+        txt.add(
+            Line(format!(
+                "{} has waited {} to {} {}",
+                car,
+                wait,
+                movement,
+                map.get_parent(turn.id.dst)
+                    .get_name(app.opts.language.as_ref())
+            ))
+            .secondary(),
+        );
+        txt.add(if downstream {
+            Line("Blocked by downstream").fg(Color::RED)
+        } else if is_signal {
+            Line("Waiting on the signal")
+        } else {
+            Line("Waiting on the stop sign")
+        });
 
+        let name = format!("examine {}", car);
+        rows.push(Widget::row(vec![
+            ctx.style()
+                .btn_plain_light_icon("system/assets/tools/pin.svg")
+                .build_widget(ctx, &name)
+                .centered_vert(),
+            txt.draw(ctx),
+        ]));
+        details.warpers.insert(name, ID::Car(car));
+    }
     rows
 }
 
@@ -81,19 +312,26 @@ pub fn traffic(
     } else {
         app.primary.sim.time()
     };
-    rows.push(throughput(
-        ctx,
-        app,
-        "Number of commuters and vehicles per hour",
-        move |a| {
-            if a.intersection_thruput.raw.is_empty() {
-                a.intersection_thruput.count_per_hour(id, time)
-            } else {
-                a.intersection_thruput.raw_throughput(time, id)
-            }
-        },
-        &opts,
-    ));
+    let (section, expanded) = section_header(ctx, app, "intersection", "Throughput");
+    rows.push(section);
+    if !expanded {
+        return rows;
+    }
+    rows.push(details.plot("throughput", || {
+        throughput(
+            ctx,
+            app,
+            "Number of commuters and vehicles per hour",
+            move |a| {
+                if a.intersection_thruput.raw.is_empty() {
+                    a.intersection_thruput.count_per_hour(id, time)
+                } else {
+                    a.intersection_thruput.raw_throughput(time, id)
+                }
+            },
+            &opts,
+        )
+    }));
 
     rows
 }
@@ -126,7 +364,11 @@ pub fn delay(
         fan_chart,
     ));
 
-    rows.push(delay_plot(ctx, app, id, opts, fan_chart));
+    let (section, expanded) = section_header(ctx, app, "intersection", "Delay");
+    rows.push(section);
+    if expanded {
+        rows.push(details.plot("delay", || delay_plot(ctx, app, id, opts, fan_chart)));
+    }
 
     rows
 }
@@ -160,7 +402,7 @@ pub fn current_demand(
     let polygon = app.primary.map.get_i(id).polygon.clone();
     let bounds = polygon.get_bounds();
     // Pick a zoom so that we fit a fixed width in pixels
-    let zoom = (0.25 * ctx.canvas.window_width) / bounds.width();
+    let zoom = (0.83 * panel_width(ctx, app)) / bounds.width();
     batch.push(
         app.cs.normal_intersection,
         polygon.translate(-bounds.min_x, -bounds.min_y).scale(zoom),
@@ -230,29 +472,276 @@ pub fn current_demand(
     rows
 }
 
-pub fn arrivals(
+/// How many vehicles completed each movement through a traffic signal per hour. Clicking a movement
+/// highlights it on the map.
+pub fn movements(
     ctx: &mut EventCtx,
     app: &App,
     details: &mut Details,
     id: IntersectionID,
-    opts: &DataOptions,
+    selected: Option<u8>,
 ) -> Vec<Widget> {
     let mut rows = header(
         ctx,
         app,
         details,
         id,
-        Tab::IntersectionArrivals(id, opts.clone()),
+        Tab::IntersectionMovements(id, selected),
+    );
+    let map = &app.primary.map;
+    let thruput = &app.primary.sim.get_analytics().traffic_signal_thruput;
+    let now = app.primary.sim.time();
+    let vehicles: BTreeSet<AgentType> = vec![
+        AgentType::Car,
+        AgentType::Bike,
+        AgentType::Bus,
+        AgentType::Train,
+    ]
+    .into_iter()
+    .collect();
+
+    let mut series = Vec::new();
+    let mut table = Vec::new();
+    // The analytics index movements in this order
+    for (idx, m) in map.get_traffic_signal(id).movements.values().enumerate() {
+        if m.id.crosswalk {
+            continue;
+        }
+        let idx = idx as u8;
+        let movement = CompressedMovementID { i: id, idx };
+
+        let mut pts: Vec<(Time, usize)> = Vec::new();
+        for (agent_type, type_pts) in thruput.count_per_hour(movement, now) {
+            if !vehicles.contains(&agent_type) {
+                continue;
+            }
+            if pts.is_empty() {
+                pts = type_pts;
+            } else {
+                for ((_, sum), (_, cnt)) in pts.iter_mut().zip(type_pts) {
+                    *sum += cnt;
+                }
+            }
+        }
+
+        let label = format!(
+            "{} to {} ({:?})",
+            map.get_r(m.id.from.id).get_name(app.opts.language.as_ref()),
+            map.get_r(m.id.to.id).get_name(app.opts.language.as_ref()),
+            m.turn_type
+        );
+        let color = app.cs.rotating_color_plot(idx as usize);
+        let is_selected = selected == Some(idx);
+        if is_selected {
+            let arrow = m.geom.make_arrow(Distance::meters(2.0), ArrowCap::Triangle);
+            details.unzoomed.push(color, arrow.clone());
+            details.zoomed.push(color, arrow);
+        }
+
+        let action = format!("movement {}", idx);
+        details.hyperlinks.insert(
+            action.clone(),
+            Tab::IntersectionMovements(id, if is_selected { None } else { Some(idx) }),
+        );
+        table.push(Widget::row(vec![
+            if is_selected {
+                ctx.style().btn_solid_light_text(&label)
+            } else {
+                ctx.style().btn_plain_light_text(&label)
+            }
+            .label_color(color, ControlState::Default)
+            .build_widget(ctx, &action),
+            prettyprint_usize(thruput.total_for_with_agent_types(movement, vehicles.clone()))
+                .draw_text(ctx)
+                .centered_vert()
+                .align_right(),
+        ]));
+        series.push(Series { label, color, pts });
+    }
+
+    rows.push(
+        Text::from_multiline(vec![
+            Line("Vehicles completing each movement since midnight"),
+            Line("Click one to show it on the map").secondary(),
+        ])
+        .draw(ctx),
     );
+    rows.extend(table);
+    let (section, expanded) = section_header(ctx, app, "intersection", "Vehicles per hour");
+    rows.push(section);
+    if expanded {
+        rows.push(details.plot("movements", || {
+            let mut plot_opts = PlotOptions::filterable();
+            plot_opts.dims = Some(plot_dims(ctx, app));
+            LinePlot::new(ctx, series, plot_opts)
+        }));
+    }
+
+    rows
+}
 
-    rows.push(throughput(
+pub fn stage_usage(
+    ctx: &mut EventCtx,
+    app: &App,
+    details: &mut Details,
+    id: IntersectionID,
+    selected: Option<(usize, usize)>,
+) -> Vec<Widget> {
+    let mut rows = header(
         ctx,
         app,
-        "Number of in-bound trips from this border",
-        move |_| app.primary.sim.all_arrivals_at_border(id),
-        opts,
+        details,
+        id,
+        Tab::IntersectionStageUsage(id, selected),
+    );
+    let usage = app
+        .primary
+        .sim
+        .get_analytics()
+        .signal_stage_usage(id, app.primary.sim.time());
+
+    rows.push(
+        Text::from_multiline(vec![
+            Line("How much of each stage was spent with no vehicles moving through"),
+            Line("Each column is one hour since midnight. Click one for details.").secondary(),
+        ])
+        .draw(ctx),
+    );
+    if usage.is_empty() {
+        rows.push("The signal hasn't changed stages yet".draw_text(ctx));
+        return rows;
+    }
+
+    let scale = &app.cs.good_to_bad_red;
+    let num_hours = usage.keys().map(|(_, hour)| *hour).max().unwrap() + 1;
+    let cell = Polygon::rectangle(15.0, 15.0);
+    for stage_idx in 0..app.primary.map.get_traffic_signal(id).stages.len() {
+        let mut cells = Vec::new();
+        for hour in 0..num_hours {
+            let stats = match usage.get(&(stage_idx, hour)) {
+                Some(stats) => stats,
+                None => {
+                    // The stage never ran this hour
+                    cells.push(Widget::draw_batch(
+                        ctx,
+                        GeomBatch::from(vec![(app.cs.inner_panel, cell.clone())]),
+                    ));
+                    continue;
+                }
+            };
+            let pct_served = stats.pct_served();
+            let mut batch = GeomBatch::from(vec![(scale.eval(1.0 - pct_served), cell.clone())]);
+            let mut hovered = batch.clone();
+            if let Ok(outline) = cell.to_outline(Distance::meters(2.0)) {
+                if selected == Some((stage_idx, hour)) {
+                    batch.push(Color::WHITE, outline.clone());
+                }
+                hovered.push(Color::WHITE, outline);
+            }
+
+            let action = format!("stage {} at hour {}", stage_idx + 1, hour);
+            details.hyperlinks.insert(
+                action.clone(),
+                Tab::IntersectionStageUsage(
+                    id,
+                    if selected == Some((stage_idx, hour)) {
+                        None
+                    } else {
+                        Some((stage_idx, hour))
+                    },
+                ),
+            );
+            cells.push(
+                ctx.style()
+                    .btn_plain_light()
+                    .custom_batch(batch, ControlState::Default)
+                    .custom_batch(hovered, ControlState::Hovered)
+                    .tooltip(Text::from_multiline(vec![
+                        Line(format!(
+                            "Stage {}, starting {}",
+                            stage_idx + 1,
+                            (Time::START_OF_DAY + Duration::hours(hour)).ampm_tostring()
+                        )),
+                        Line(format!(
+                            "Vehicles moving through {}% of the {} it was active",
+                            (pct_served * 100.0).round(),
+                            stats.active
+                        )),
+                    ]))
+                    .build_widget(ctx, &action),
+            );
+        }
+        rows.push(Widget::row(vec![
+            Line(format!("Stage {}", stage_idx + 1))
+                .small()
+                .draw(ctx)
+                .centered_vert(),
+            Widget::custom_row(cells).align_right(),
+        ]));
+    }
+    rows.push(ColorLegend::gradient(
+        ctx,
+        scale,
+        vec!["always served", "always empty"],
     ));
 
+    if let Some((stage_idx, hour)) = selected {
+        if let Some(stats) = usage.get(&(stage_idx, hour)) {
+            // The longest stretches are the most interesting
+            let mut empty = stats.empty.clone();
+            empty.sort_by_key(|(_, dt)| std::cmp::Reverse(*dt));
+            empty.truncate(10);
+            empty.sort_by_key(|(t, _)| *t);
+
+            let mut txt = Text::new();
+            txt.add(
+                Line(format!(
+                    "Stage {} ran empty for {} of {}, starting {}",
+                    stage_idx + 1,
+                    stats.empty.iter().map(|(_, dt)| *dt).sum::<Duration>(),
+                    stats.active,
+                    (Time::START_OF_DAY + Duration::hours(hour)).ampm_tostring()
+                ))
+                .small_heading(),
+            );
+            if !empty.is_empty() {
+                txt.add(Line("The longest times:"));
+            }
+            for (t, dt) in empty {
+                txt.add(Line(format!("- {} for {}", t.ampm_tostring(), dt)).secondary());
+            }
+            rows.push(txt.wrap_to_pct(ctx, 20).draw(ctx));
+        }
+    }
+
+    rows
+}
+
+pub fn arrivals(
+    ctx: &mut EventCtx,
+    app: &App,
+    details: &mut Details,
+    id: IntersectionID,
+    opts: &DataOptions,
+) -> Vec<Widget> {
+    let mut rows = header(
+        ctx,
+        app,
+        details,
+        id,
+        Tab::IntersectionArrivals(id, opts.clone()),
+    );
+
+    rows.push(details.plot("arrivals", || {
+        throughput(
+            ctx,
+            app,
+            "Number of in-bound trips from this border",
+            move |_| app.primary.sim.all_arrivals_at_border(id),
+            opts,
+        )
+    }));
+
     rows
 }
 
@@ -327,11 +816,38 @@ pub fn traffic_signal(
         }
     }
 
+    rows.push(Widget::row(vec![
+        ctx.style()
+            .btn_outline_light_text("export signal timing")
+            .build_def(ctx),
+        ctx.style()
+            .btn_outline_light_text("import signal timing")
+            .build_def(ctx),
+    ]));
+
     rows
 }
 
+/// Replaces a signal's timing with the file written by exporting it, as a live edit. Returns false
+/// if the file matches the current timing.
+pub fn apply_signal_timing(ctx: &mut EventCtx, app: &mut App, id: IntersectionID) -> Result<bool> {
+    let signal = import_signal_timing(app, id)?;
+    let map = &app.primary.map;
+    if &signal == map.get_traffic_signal(id) {
+        return Ok(false);
+    }
+    let mut edits = map.get_edits().clone();
+    edits.commands.push(EditCmd::ChangeIntersection {
+        i: id,
+        old: map.get_i_edit(id),
+        new: EditIntersection::TrafficSignal(signal.export(map)),
+    });
+    apply_live_edits(ctx, app, edits);
+    Ok(true)
+}
+
 fn delay_plot(
-    ctx: &EventCtx,
+    ctx: &mut EventCtx,
     app: &App,
     i: IntersectionID,
     opts: &DataOptions,
@@ -342,50 +858,75 @@ fn delay_plot(
     } else {
         app.primary.sim.get_analytics()
     };
-    let mut by_type: BTreeMap<AgentType, Vec<(Time, Duration)>> = AgentType::all()
-        .into_iter()
-        .map(|t| (t, Vec::new()))
-        .collect();
     let limit = if opts.show_end_of_day {
         app.primary.sim.get_end_of_day()
     } else {
         app.primary.sim.time()
     };
-    if let Some(list) = data.intersection_delays.get(&i) {
-        for (_, t, dt, agent_type) in list {
-            if *t > limit {
-                break;
-            }
-            by_type.get_mut(agent_type).unwrap().push((*t, *dt));
-        }
-    }
-    let series: Vec<Series<Duration>> = by_type
-        .into_iter()
-        .map(|(agent_type, pts)| Series {
-            label: agent_type.noun().to_string(),
-            color: color_for_agent_type(app, agent_type),
-            pts,
-        })
-        .collect();
+    let delays = data
+        .intersection_delays
+        .get(&i)
+        .map(|list| list.as_slice())
+        .unwrap_or(&[]);
     let plot_opts = PlotOptions {
         filterable: true,
         max_x: Some(limit),
         max_y: None,
         disabled: opts.disabled_series(),
+        dims: Some(plot_dims(ctx, app)),
     };
     Widget::col(vec![
         Line("Delay through intersection").small_heading().draw(ctx),
-        if fan_chart {
-            FanChart::new(ctx, series, plot_opts)
-        } else {
-            ScatterPlot::new(ctx, series, plot_opts)
-        },
+        plot_with_legend(
+            ctx,
+            delay_series(
+                agent_type_registry(),
+                &app.cs,
+                delays,
+                limit,
+                opts.show_before,
+            ),
+            |ctx, series| {
+                if fan_chart {
+                    FanChart::new(ctx, series, plot_opts)
+                } else {
+                    ScatterPlot::new(ctx, series, plot_opts)
+                }
+            },
+        ),
     ])
     .padding(10)
     .bg(app.cs.inner_panel)
     .outline(2.0, Color::WHITE)
 }
 
+/// Splits delays up to `limit` by agent type. Only one of the current or baseline data is shown at
+/// a time, and types nobody was delayed as are left out.
+fn delay_series<S>(
+    registry: &ModeRegistry<AgentType, S>,
+    colors: &S,
+    delays: &[(u8, Time, Duration, AgentType)],
+    limit: Time,
+    show_before: bool,
+) -> (Vec<Series<Duration>>, Vec<(Color, String)>) {
+    let mut by_type: BTreeMap<AgentType, Vec<(Time, Duration)>> = BTreeMap::new();
+    for (_, t, dt, agent_type) in delays {
+        if *t > limit {
+            break;
+        }
+        by_type
+            .entry(*agent_type)
+            .or_insert_with(Vec::new)
+            .push((*t, *dt));
+    }
+    let by_type = by_type.into_iter().collect();
+    if show_before {
+        registry.series(colors, Vec::new(), by_type)
+    } else {
+        registry.series(colors, by_type, Vec::new())
+    }
+}
+
 fn header(
     ctx: &EventCtx,
     app: &App,
@@ -410,7 +951,7 @@ fn header(
 
     rows.push(make_tabs(ctx, &mut details.hyperlinks, tab, {
         let mut tabs = vec![
-            ("Info", Tab::IntersectionInfo(id)),
+            ("Info", Tab::IntersectionInfo(id, false)),
             ("Traffic", Tab::IntersectionTraffic(id, DataOptions::new())),
         ];
         if i.is_traffic_signal() {
@@ -419,7 +960,9 @@ fn header(
                 Tab::IntersectionDelay(id, DataOptions::new(), false),
             ));
             tabs.push(("Current demand", Tab::IntersectionDemand(id)));
+            tabs.push(("Movements", Tab::IntersectionMovements(id, None)));
             tabs.push(("Signal", Tab::IntersectionTrafficSignal(id)));
+            tabs.push(("Stage usage", Tab::IntersectionStageUsage(id, None)));
         }
         if i.is_incoming_border() {
             tabs.push((
@@ -432,3 +975,77 @@ fn header(
 
     rows
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn registry() -> ModeRegistry<AgentType, ()> {
+        ModeRegistry::new()
+            .add(AgentType::Car, |_| Color::RED, AgentType::Car.noun())
+            .add(AgentType::Bike, |_| Color::GREEN, AgentType::Bike.noun())
+    }
+
+    fn delays() -> Vec<(u8, Time, Duration, AgentType)> {
+        vec![
+            (0, 0, 10.0, AgentType::Car),
+            (0, 0, 5.0, AgentType::Car),
+            (1, 0, 3.0, AgentType::Bus),
+            (1, 2, 20.0, AgentType::Bike),
+        ]
+        .into_iter()
+        .map(|(idx, hour, secs, agent_type)| {
+            (
+                idx,
+                Time::START_OF_DAY + Duration::hours(hour),
+                Duration::seconds(secs),
+                agent_type,
+            )
+        })
+        .collect()
+    }
+
+    #[test]
+    fn test_delay_series() {
+        let (series, legend) = delay_series(
+            &registry(),
+            &(),
+            &delays(),
+            Time::START_OF_DAY + Duration::hours(1),
+            false,
+        );
+        // Bikes were only delayed after the limit
+        let labels: Vec<&str> = series.iter().map(|s| s.label.as_str()).collect();
+        assert_eq!(labels, vec!["Car", "Bus"]);
+        assert_eq!(series[0].pts.len(), 2);
+        assert_eq!(series[0].color, Color::RED);
+        let legend: Vec<&str> = legend.iter().map(|(_, label)| label.as_str()).collect();
+        assert_eq!(legend, vec!["Car", "Bus"]);
+    }
+
+    #[test]
+    fn test_delay_series_baseline() {
+        let (series, legend) = delay_series(
+            &registry(),
+            &(),
+            &delays(),
+            Time::START_OF_DAY + Duration::hours(3),
+            true,
+        );
+        let labels: Vec<&str> = series.iter().map(|s| s.label.as_str()).collect();
+        assert_eq!(labels, vec!["Car", "Bike", "Bus"]);
+        assert_ne!(series[0].color, Color::RED);
+        let legend: Vec<&str> = legend.iter().map(|(_, label)| label.as_str()).collect();
+        assert_eq!(
+            legend,
+            vec!["Car (baseline)", "Bike (baseline)", "Bus (baseline)"]
+        );
+    }
+
+    #[test]
+    fn test_no_delays() {
+        let (series, legend) = delay_series(&registry(), &(), &[], Time::START_OF_DAY, false);
+        assert!(series.is_empty());
+        assert!(legend.is_empty());
+    }
+}
@@ -1,13 +1,31 @@
-use std::collections::HashSet;
+use std::collections::{BTreeSet, HashSet};
 
-use abstutil::prettyprint_usize;
-use map_model::{LaneID, PathConstraints};
+use anyhow::Result;
+use geojson::{Feature, FeatureCollection, GeoJson, Geometry, Value};
+use serde_json::Map as Properties;
+
+use abstutil::{prettyprint_usize, Tags};
+use geom::{Circle, Distance, Percent, Pt2D, Speed, Time};
+use map_gui::ID;
+use map_model::{osm, LaneID, PathConstraints, Traversable};
+use sim::{AgentID, AgentType, Analytics, SidewalkUse, LANE_SPEED_WINDOW};
 use widgetry::{
-    EventCtx, Line, LinePlot, PlotOptions, Series, StyledButtons, Text, TextExt, Widget,
+    Color, EventCtx, Line, LinePlot, PlotOptions, Series, StyledButtons, Text, TextExt, Widget,
 };
 
 use crate::app::App;
-use crate::info::{header_btns, make_table, make_tabs, throughput, DataOptions, Details, Tab};
+use crate::common::{agent_type_registry, ModeRegistry};
+use crate::info::{
+    header_btns, make_table, make_tabs, plot_dims, section_header, throughput, DataOptions,
+    Details, Tab,
+};
+use crate::layer::edits::{road_diffs, EditKind};
+use crate::sandbox::hatching;
+
+// Vertices next to a segment shorter than this, or where the line turns more sharply than this,
+// are the usual suspects for rendering artifacts after shifting and trimming.
+const SHORT_SEGMENT: Distance = Distance::const_meters(0.1);
+const SHARP_TURN_DEGREES: f64 = 90.0;
 
 pub fn info(ctx: &EventCtx, app: &App, details: &mut Details, id: LaneID) -> Vec<Widget> {
     let mut rows = header(ctx, app, details, id, Tab::LaneInfo(id));
@@ -56,10 +74,59 @@ pub fn info(ctx: &EventCtx, app: &App, details: &mut Details, id: LaneID) -> Vec
     }
 
     kv.push(("Length", l.length().to_string(&app.opts.units)));
+    kv.push(("Incline", format!("{:.1}%", l.percent_incline(map) * 100.0)));
+
+    if let Some(ref closure) = app.primary.lane_closure {
+        if closure.lane == id {
+            kv.push((
+                "Temporarily closed",
+                format!("reopens in {}", closure.until - app.primary.sim.time()),
+            ));
+            details.zoomed.append(hatching(app, id));
+            details.unzoomed.push(
+                Color::RED.alpha(0.5),
+                l.lane_center_pts.make_polygons(l.width),
+            );
+        }
+    }
 
     rows.extend(make_table(ctx, kv));
+    // Changes to individual lanes only show up on that lane
+    rows.extend(make_table(
+        ctx,
+        road_diffs(app, r.id)
+            .into_iter()
+            .filter(|diff| diff.kind != EditKind::Lanes || diff.id == ID::Lane(id))
+            .map(|diff| {
+                (
+                    format!("Edited {}", diff.kind.describe()),
+                    diff.before_after(),
+                )
+            })
+            .collect(),
+    ));
 
-    if l.is_parking() {
+    if let Some(ref quick) = app.primary.quick_edit {
+        if quick.lane == id {
+            rows.push(
+                Text::from_multiline(quick.summary.iter().map(Line).collect())
+                    .wrap_to_pct(ctx, 20)
+                    .draw(ctx),
+            );
+            rows.push(
+                ctx.style()
+                    .btn_outline_light_text("undo this edit")
+                    .build_def(ctx),
+            );
+        }
+    }
+
+    if !l.is_parking() {
+        return rows;
+    }
+    let (section, expanded) = section_header(ctx, app, "lane", "Parking spots available");
+    rows.push(section);
+    if expanded {
         let capacity = l.number_parking_spots(app.primary.map.get_config());
         let mut series = vec![Series {
             label: format!("After \"{}\"", app.primary.map.get_edits().edits_name),
@@ -81,17 +148,19 @@ pub fn info(ctx: &EventCtx, app: &App, details: &mut Details, id: LaneID) -> Vec
                 ),
             });
         }
-        rows.push("Parking spots available".draw_text(ctx));
-        rows.push(LinePlot::new(
-            ctx,
-            series,
-            PlotOptions {
-                filterable: false,
-                max_x: None,
-                max_y: Some(capacity),
-                disabled: HashSet::new(),
-            },
-        ));
+        rows.push(details.plot("spots available", || {
+            LinePlot::new(
+                ctx,
+                series,
+                PlotOptions {
+                    filterable: false,
+                    max_x: None,
+                    max_y: Some(capacity),
+                    disabled: HashSet::new(),
+                    dims: Some(plot_dims(ctx, app)),
+                },
+            )
+        }));
     }
 
     rows
@@ -106,6 +175,10 @@ pub fn debug(ctx: &EventCtx, app: &App, details: &mut Details, id: LaneID) -> Ve
     let mut kv = Vec::new();
 
     kv.push(("Parent".to_string(), r.id.to_string()));
+    kv.push((
+        "Parking from".to_string(),
+        parking_source(&r.osm_tags).to_string(),
+    ));
 
     if l.lane_type.is_for_moving_vehicles() {
         kv.push((
@@ -142,7 +215,7 @@ pub fn debug(ctx: &EventCtx, app: &App, details: &mut Details, id: LaneID) -> Ve
     ));
     kv.push((
         "Incline / grade".to_string(),
-        format!("{:.1}%", r.percent_grade(map) * 100.0),
+        format!("{:.1}%", l.percent_incline(map) * 100.0),
     ));
     kv.push((
         "Elevation details".to_string(),
@@ -165,28 +238,281 @@ pub fn debug(ctx: &EventCtx, app: &App, details: &mut Details, id: LaneID) -> Ve
 
     rows.extend(make_table(ctx, kv));
 
+    let (section, expanded) = section_header(ctx, app, "lane", "Geometry");
+    rows.push(section.margin_above(16));
+    draw_geometry(ctx, app, details, id);
+    if expanded {
+        rows.push(
+            ctx.style()
+                .btn_solid_dark_text("dump geometry to GeoJSON")
+                .build_def(ctx),
+        );
+        rows.push(
+            Text::from(
+                Line(format!(
+                    "Red vertices are next to a segment shorter than {}, or turn more than {}°",
+                    SHORT_SEGMENT, SHARP_TURN_DEGREES
+                ))
+                .secondary(),
+            )
+            .wrap_to_pct(ctx, 20)
+            .draw(ctx),
+        );
+        rows.extend(geometry_table(ctx, app, id));
+    }
+
+    rows
+}
+
+pub fn osm(ctx: &EventCtx, app: &App, details: &mut Details, id: LaneID) -> Vec<Widget> {
+    let mut rows = header(ctx, app, details, id, Tab::LaneOSM(id));
+    let map = &app.primary.map;
+    let r = map.get_r(map.get_l(id).parent);
+
+    let findings = r.lint_tags();
+    if findings.is_empty() {
+        rows.push("No tagging problems found".draw_text(ctx));
+    } else {
+        let color = Color::hex("#F9EC51");
+        let mut col = vec![
+            Line(format!("{} possible tagging problems", findings.len()))
+                .small_heading()
+                .fg(color)
+                .draw(ctx),
+        ];
+        if map.get_edits().changed_roads.contains(&r.id) {
+            col.push(
+                Text::from(Line(
+                    "This road has been edited, so some may come from the edits",
+                ))
+                .wrap_to_pct(ctx, 20)
+                .draw(ctx),
+            );
+        }
+        for finding in findings {
+            col.push(
+                Text::from_multiline(vec![
+                    Line(finding.problem),
+                    Line(finding.suggestion).secondary(),
+                ])
+                .wrap_to_pct(ctx, 20)
+                .draw(ctx),
+            );
+        }
+        rows.push(
+            Widget::col(col)
+                .padding(10)
+                .bg(color.alpha(0.2))
+                .outline(2.0, color),
+        );
+    }
+
     rows.push(
-        ctx.style()
-            .btn_solid_dark_text("Open OSM way")
-            .build_widget(ctx, &format!("open {}", r.orig_id.osm_way_id)),
+        Widget::row(vec![
+            ctx.style()
+                .btn_solid_dark_text("Open OSM way")
+                .build_widget(ctx, &format!("open {}", r.orig_id.osm_way_id)),
+            ctx.style()
+                .btn_solid_dark_text("Edit in JOSM")
+                .build_widget(ctx, "edit in JOSM"),
+        ])
+        .margin_above(16),
     );
 
-    let mut txt = Text::from(Line(""));
-    txt.add(Line("Raw OpenStreetMap data"));
-    rows.push(txt.draw(ctx));
-
-    rows.extend(make_table(
-        ctx,
-        r.osm_tags
-            .inner()
-            .iter()
-            .map(|(k, v)| (k, v.to_string()))
-            .collect(),
-    ));
+    let (section, expanded) = section_header(ctx, app, "lane", "Raw OpenStreetMap data");
+    rows.push(section.margin_above(16));
+    if expanded {
+        rows.extend(make_table(
+            ctx,
+            r.osm_tags
+                .inner()
+                .iter()
+                .map(|(k, v)| (k, v.to_string()))
+                .collect(),
+        ));
+    }
 
     rows
 }
 
+/// Numbers each vertex of the lane's center line, draws both of its boundaries, and faintly shows
+/// the neighboring lanes, so problems with shifting and trimming are easy to spot.
+fn draw_geometry(ctx: &EventCtx, app: &App, details: &mut Details, id: LaneID) {
+    let map = &app.primary.map;
+    let l = map.get_l(id);
+    let r = map.get_r(l.parent);
+
+    let lanes = r.lanes_ltr();
+    let idx = lanes.iter().position(|(x, _, _)| *x == id).unwrap();
+    if idx > 0 {
+        let left = map.get_l(lanes[idx - 1].0);
+        details.zoomed.push(
+            Color::BLUE.alpha(0.2),
+            left.lane_center_pts.make_polygons(left.width),
+        );
+    }
+    if let Some((right, _, _)) = lanes.get(idx + 1) {
+        let right = map.get_l(*right);
+        details.zoomed.push(
+            Color::GREEN.alpha(0.2),
+            right.lane_center_pts.make_polygons(right.width),
+        );
+    }
+
+    let thickness = Distance::meters(0.1);
+    if let Ok(pl) = l.lane_center_pts.shift_left(l.width / 2.0) {
+        details
+            .zoomed
+            .push(Color::ORANGE, pl.make_polygons(thickness));
+    }
+    if let Ok(pl) = l.lane_center_pts.shift_right(l.width / 2.0) {
+        details
+            .zoomed
+            .push(Color::YELLOW, pl.make_polygons(thickness));
+    }
+    details
+        .zoomed
+        .push(Color::WHITE, l.lane_center_pts.make_polygons(thickness));
+
+    let pts = l.lane_center_pts.points();
+    for (idx, (pt, degenerate)) in pts.iter().zip(degenerate_vertices(pts)).enumerate() {
+        details.zoomed.push(
+            if degenerate { Color::RED } else { Color::BLACK },
+            Circle::new(*pt, Distance::meters(0.3)).to_polygon(),
+        );
+        details.zoomed.append(
+            Text::from(Line(idx.to_string()))
+                .render_autocropped(ctx)
+                .scale(0.03)
+                .centered_on(*pt),
+        );
+    }
+}
+
+/// Each vertex of the lane's center line, and the length of the segment after it
+fn geometry_table(ctx: &EventCtx, app: &App, id: LaneID) -> Vec<Widget> {
+    let pts = app.primary.map.get_l(id).lane_center_pts.points();
+    pts.iter()
+        .zip(degenerate_vertices(pts))
+        .enumerate()
+        .map(|(idx, (pt, degenerate))| {
+            let mut txt = Text::from(
+                Line(format!("({:.2}, {:.2})", pt.x(), pt.y())).fg(if degenerate {
+                    Color::RED
+                } else {
+                    Color::WHITE
+                }),
+            );
+            if let Some(next) = pts.get(idx + 1) {
+                txt.append(Line(format!(", then {}", pt.dist_to(*next))).secondary());
+            }
+            Widget::row(vec![
+                Line(format!("Point {}", idx)).secondary().draw(ctx),
+                txt.draw(ctx).centered_vert().align_right(),
+            ])
+        })
+        .collect()
+}
+
+fn degenerate_vertices(pts: &[Pt2D]) -> Vec<bool> {
+    (0..pts.len())
+        .map(|i| {
+            let short_before = i > 0 && pts[i - 1].dist_to(pts[i]) < SHORT_SEGMENT;
+            let short_after = i + 1 < pts.len() && pts[i].dist_to(pts[i + 1]) < SHORT_SEGMENT;
+            let sharp = i > 0
+                && i + 1 < pts.len()
+                && pts[i - 1]
+                    .angle_to(pts[i])
+                    .simple_shortest_rotation_towards(pts[i].angle_to(pts[i + 1]))
+                    .abs()
+                    > SHARP_TURN_DEGREES;
+            short_before || short_after || sharp
+        })
+        .collect()
+}
+
+/// Writes the lane's center line, both boundaries, polygon, and numbered vertices as GeoJSON, to
+/// analyze in other tools. Returns the path.
+pub fn export_geometry(app: &App, id: LaneID) -> Result<String> {
+    let map = &app.primary.map;
+    let gps_bounds = map.get_gps_bounds();
+    let l = map.get_l(id);
+
+    let mut features = vec![
+        feature("center", l.lane_center_pts.to_geojson(Some(gps_bounds))),
+        feature(
+            "polygon",
+            l.lane_center_pts
+                .make_polygons(l.width)
+                .to_geojson(Some(gps_bounds)),
+        ),
+    ];
+    if let Ok(pl) = l.lane_center_pts.shift_left(l.width / 2.0) {
+        features.push(feature("left boundary", pl.to_geojson(Some(gps_bounds))));
+    }
+    if let Ok(pl) = l.lane_center_pts.shift_right(l.width / 2.0) {
+        features.push(feature("right boundary", pl.to_geojson(Some(gps_bounds))));
+    }
+    let pts = l.lane_center_pts.points();
+    for (idx, (pt, degenerate)) in pts.iter().zip(degenerate_vertices(pts)).enumerate() {
+        let gps = pt.to_gps(gps_bounds);
+        let mut f = feature(
+            "vertex",
+            Geometry::new(Value::Point(vec![gps.x(), gps.y()])),
+        );
+        let props = f.properties.as_mut().unwrap();
+        props.insert("index".to_string(), idx.into());
+        props.insert("degenerate".to_string(), degenerate.into());
+        if let Some(next) = pts.get(idx + 1) {
+            props.insert(
+                "length_to_next_meters".to_string(),
+                pt.dist_to(*next).inner_meters().into(),
+            );
+        }
+        features.push(f);
+    }
+
+    let path = abstio::path_player(format!("lane_geometry/{}.geojson", id.0));
+    let geojson = GeoJson::from(FeatureCollection {
+        bbox: None,
+        features,
+        foreign_members: None,
+    });
+    std::fs::create_dir_all(std::path::Path::new(&path).parent().unwrap())?;
+    std::fs::write(&path, serde_json::to_string_pretty(&geojson)?)?;
+    Ok(path)
+}
+
+fn feature(kind: &str, geometry: Geometry) -> Feature {
+    let mut props = Properties::new();
+    props.insert("kind".to_string(), kind.into());
+    Feature {
+        bbox: None,
+        geometry: Some(geometry),
+        id: None,
+        properties: Some(props),
+        foreign_members: None,
+    }
+}
+
+// Which data determined the parking on a road
+fn parking_source(tags: &Tags) -> &'static str {
+    if !tags.contains_key(osm::INFERRED_PARKING) {
+        if tags.contains_key(osm::PARKING_LEFT)
+            || tags.contains_key(osm::PARKING_RIGHT)
+            || tags.contains_key(osm::PARKING_BOTH)
+        {
+            return "OpenStreetMap tags";
+        }
+        return "nothing; none assumed for this type of road";
+    }
+    match tags.get(osm::PARKING_SOURCE).map(|x| x.as_str()) {
+        Some("blockface") => "Seattle blockface data, since OSM is untagged",
+        Some("guessed") => "a guess, since OSM is untagged",
+        _ => "nothing; OSM is untagged, so none assumed",
+    }
+}
+
 pub fn traffic(
     ctx: &mut EventCtx,
     app: &App,
@@ -215,24 +541,287 @@ pub fn traffic(
     } else {
         app.primary.sim.time()
     };
+    let (section, expanded) = section_header(ctx, app, "lane", "Throughput");
+    rows.push(section);
+    if !expanded {
+        return rows;
+    }
     // TODO This conflates commuters and vehicles, so we should maybe split it into different plots.
-    rows.push(throughput(
-        ctx,
-        app,
-        "Number of commuters and vehicles per hour",
-        move |a| {
-            if a.road_thruput.raw.is_empty() {
-                a.road_thruput.count_per_hour(r, time)
+    rows.push(details.plot("throughput", || {
+        throughput(
+            ctx,
+            app,
+            "Number of commuters and vehicles per hour",
+            move |a| {
+                if a.road_thruput.raw.is_empty() {
+                    a.road_thruput.count_per_hour(r, time)
+                } else {
+                    a.road_thruput.raw_throughput(time, r)
+                }
+            },
+            &opts,
+        )
+    }));
+
+    if l.is_walkable() {
+        rows.extend(sidewalk_use(ctx, app, details, id, time, opts));
+    }
+
+    rows
+}
+
+/// Splits pedestrians on a sidewalk into those walking along it, and those only using it to get
+/// to or from a crosswalk at one of the ends.
+fn sidewalk_use(
+    ctx: &mut EventCtx,
+    app: &App,
+    details: &mut Details,
+    id: LaneID,
+    time: Time,
+    opts: &DataOptions,
+) -> Vec<Widget> {
+    let mut rows = vec![Line("Pedestrians on this sidewalk")
+        .small_heading()
+        .draw(ctx)];
+    let usages = vec![
+        (
+            SidewalkUse::Along,
+            "Walking along",
+            app.cs.rotating_color_plot(0),
+        ),
+        (
+            SidewalkUse::Crossing,
+            "Crossing",
+            app.cs.rotating_color_plot(1),
+        ),
+    ];
+    // Prebaked baselines never have this
+    let baseline = if opts.show_before && !app.prebaked().sidewalk_thruput.counts.is_empty() {
+        Some(app.prebaked())
+    } else {
+        None
+    };
+
+    let mut kv = Vec::new();
+    for (usage, label, _) in &usages {
+        let now = app
+            .primary
+            .sim
+            .get_analytics()
+            .sidewalk_thruput
+            .total_for((id, *usage));
+        kv.push((
+            *label,
+            if let Some(before) = baseline {
+                format!(
+                    "{} (before: {})",
+                    prettyprint_usize(now),
+                    prettyprint_usize(before.sidewalk_thruput.total_for((id, *usage)))
+                )
             } else {
-                a.road_thruput.raw_throughput(time, r)
+                prettyprint_usize(now)
+            },
+        ));
+    }
+    rows.extend(make_table(ctx, kv));
+    if opts.show_before && baseline.is_none() {
+        rows.push(
+            Line("The baseline doesn't split up pedestrians this way")
+                .secondary()
+                .draw(ctx),
+        );
+    }
+
+    rows.push(details.plot("sidewalk use", || {
+        let pedestrians_per_hour = |a: &Analytics, usage| {
+            a.sidewalk_thruput
+                .count_per_hour((id, usage), time)
+                .into_iter()
+                .find(|(agent_type, _)| *agent_type == AgentType::Pedestrian)
+                .unwrap()
+                .1
+        };
+        let mut series = Vec::new();
+        for (usage, label, color) in usages {
+            series.push(Series {
+                label: label.to_string(),
+                color,
+                pts: pedestrians_per_hour(app.primary.sim.get_analytics(), usage),
+            });
+            if let Some(before) = baseline {
+                series.push(Series {
+                    label: label.to_string(),
+                    color: color.alpha(0.3),
+                    pts: pedestrians_per_hour(before, usage),
+                });
             }
+        }
+        let mut plot_opts = PlotOptions::filterable();
+        plot_opts.dims = Some(plot_dims(ctx, app));
+        Widget::col(vec![
+            Line("Number of pedestrians per hour")
+                .small_heading()
+                .draw(ctx),
+            LinePlot::new(ctx, series, plot_opts),
+        ])
+        .padding(10)
+        .bg(app.cs.inner_panel)
+        .outline(2.0, Color::WHITE)
+    }));
+
+    rows
+}
+
+// Averaging at least this fraction of the speed limit counts as free-flowing
+const FREE_FLOW_PCT: f64 = 0.8;
+
+pub fn speeds(
+    ctx: &mut EventCtx,
+    app: &App,
+    details: &mut Details,
+    id: LaneID,
+    opts: &DataOptions,
+) -> Vec<Widget> {
+    let mut rows = header(ctx, app, details, id, Tab::LaneSpeeds(id, opts.clone()));
+    let map = &app.primary.map;
+    let sim = &app.primary.sim;
+    let limit = map.get_parent(id).speed_limit;
+    let vehicle_types = vec![
+        AgentType::Car,
+        AgentType::Bike,
+        AgentType::Bus,
+        AgentType::Train,
+    ];
+    // Slower modes drag down the average, so the summary only covers the ones shown in the plot
+    let enabled: BTreeSet<AgentType> = vehicle_types
+        .iter()
+        .cloned()
+        .filter(|a| opts.is_enabled(*a))
+        .collect();
+
+    let mut kv = vec![("Speed limit", limit.to_string(&app.opts.units))];
+    let windows = sim.get_analytics().lane_speeds(id, &enabled, sim.time());
+    if let Some((worst_time, worst_speed)) = windows.iter().min_by_key(|(_, speed)| *speed) {
+        let free_flow = windows
+            .iter()
+            .filter(|(_, speed)| *speed >= limit * FREE_FLOW_PCT)
+            .count();
+        kv.push((
+            "Free-flowing",
+            format!(
+                "{} of the day so far",
+                Percent::of(free_flow, windows.len())
+            ),
+        ));
+        kv.push((
+            "Slowest 15 minutes",
+            format!(
+                "{} at {}",
+                worst_speed.to_string(&app.opts.units),
+                worst_time.ampm_tostring()
+            ),
+        ));
+    }
+    let current: Vec<Speed> = sim
+        .get_draw_cars(Traversable::Lane(id), map)
+        .into_iter()
+        .map(|car| AgentID::Car(car.id))
+        .filter(|a| enabled.contains(&a.to_type()))
+        .filter_map(|a| sim.agent_speed(map, a))
+        .map(|s| s.speed)
+        .collect();
+    kv.push((
+        "Right now",
+        if current.is_empty() {
+            "nobody's on the lane".to_string()
+        } else {
+            let total = current.iter().fold(Speed::ZERO, |sum, s| sum + *s);
+            (total * (1.0 / current.len() as f64)).to_string(&app.opts.units)
         },
-        &opts,
     ));
+    rows.extend(make_table(ctx, kv));
+
+    rows.push(opts.to_controls(ctx, app));
+
+    let (section, expanded) = section_header(ctx, app, "lane", "Average speed");
+    rows.push(section);
+    if !expanded {
+        return rows;
+    }
+    let time = if opts.show_end_of_day {
+        sim.get_end_of_day()
+    } else {
+        sim.time()
+    };
+    let mut current = Vec::new();
+    let mut baseline = Vec::new();
+    for agent_type in vehicle_types {
+        let types = vec![agent_type].into_iter().collect();
+        current.push((
+            agent_type,
+            speed_steps(sim.get_analytics().lane_speeds(id, &types, time), time),
+        ));
+        if opts.show_before {
+            baseline.push((
+                agent_type,
+                speed_steps(app.prebaked().lane_speeds(id, &types, time), time),
+            ));
+        }
+    }
+    let series = speed_series(
+        agent_type_registry(),
+        &app.cs,
+        current,
+        baseline,
+        limit,
+        time,
+    );
+    if series.is_empty() {
+        rows.push("No vehicles have crossed this lane yet".draw_text(ctx));
+        return rows;
+    }
+    rows.push(details.plot("speeds", || {
+        let mut plot_opts = PlotOptions::filterable();
+        plot_opts.disabled = opts.disabled_series();
+        plot_opts.dims = Some(plot_dims(ctx, app));
+        LinePlot::new(ctx, series, plot_opts)
+    }));
 
     rows
 }
 
+// Draws the average speed over each window as a flat line
+fn speed_steps(windows: Vec<(Time, Speed)>, end: Time) -> Vec<(Time, Speed)> {
+    let mut pts = Vec::new();
+    for (t, speed) in windows {
+        pts.push((t, speed));
+        pts.push(((t + LANE_SPEED_WINDOW).min(end), speed));
+    }
+    pts
+}
+
+/// One average speed line per vehicle type, with baseline lines faded, plus the speed limit for
+/// reference. The plot's own toggles act as the legend. If no vehicles have crossed yet, there's
+/// nothing to plot, not even the speed limit.
+fn speed_series<S>(
+    registry: &ModeRegistry<AgentType, S>,
+    colors: &S,
+    current: Vec<(AgentType, Vec<(Time, Speed)>)>,
+    baseline: Vec<(AgentType, Vec<(Time, Speed)>)>,
+    limit: Speed,
+    end: Time,
+) -> Vec<Series<Speed>> {
+    let (mut series, _) = registry.series(colors, current, baseline);
+    if !series.is_empty() {
+        series.push(Series {
+            label: "Speed limit".to_string(),
+            color: Color::WHITE,
+            pts: vec![(Time::START_OF_DAY, limit), (end, limit)],
+        });
+    }
+    series
+}
+
 fn header(ctx: &EventCtx, app: &App, details: &mut Details, id: LaneID, tab: Tab) -> Vec<Widget> {
     let mut rows = vec![];
 
@@ -259,6 +848,17 @@ fn header(ctx: &EventCtx, app: &App, details: &mut Details, id: LaneID, tab: Tab
     if !l.is_parking() {
         tabs.push(("Traffic", Tab::LaneTraffic(id, DataOptions::new())));
     }
+    if l.lane_type.is_for_moving_vehicles() {
+        tabs.push(("Speeds", Tab::LaneSpeeds(id, DataOptions::new())));
+    }
+    // Show how many tagging problems there are, without having to open the tab
+    let num_findings = r.lint_tags().len();
+    let osm_label = if num_findings == 0 {
+        "OSM".to_string()
+    } else {
+        format!("OSM ({})", num_findings)
+    };
+    tabs.push((osm_label.as_str(), Tab::LaneOSM(id)));
     if app.opts.dev {
         tabs.push(("Debug", Tab::LaneDebug(id)));
     }
@@ -266,3 +866,56 @@ fn header(ctx: &EventCtx, app: &App, details: &mut Details, id: LaneID, tab: Tab
 
     rows
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn registry() -> ModeRegistry<AgentType, ()> {
+        ModeRegistry::new()
+            .add(AgentType::Car, |_| Color::RED, AgentType::Car.noun())
+            .add(AgentType::Bike, |_| Color::GREEN, AgentType::Bike.noun())
+    }
+
+    fn steps(speed: f64) -> Vec<(Time, Speed)> {
+        speed_steps(
+            vec![(Time::START_OF_DAY, Speed::meters_per_second(speed))],
+            Time::START_OF_DAY + LANE_SPEED_WINDOW,
+        )
+    }
+
+    #[test]
+    fn test_speed_series() {
+        let limit = Speed::meters_per_second(15.0);
+        let series = speed_series(
+            &registry(),
+            &(),
+            vec![
+                (AgentType::Car, steps(10.0)),
+                (AgentType::Bike, Vec::new()),
+                (AgentType::Bus, steps(8.0)),
+            ],
+            vec![(AgentType::Car, steps(12.0))],
+            limit,
+            Time::START_OF_DAY + LANE_SPEED_WINDOW,
+        );
+        let labels: Vec<&str> = series.iter().map(|s| s.label.as_str()).collect();
+        assert_eq!(labels, vec!["Car", "Bus", "Car", "Speed limit"]);
+        assert_eq!(series[0].color, Color::RED);
+        assert_ne!(series[2].color, Color::RED);
+        assert_eq!(series[3].pts[1].1, limit);
+    }
+
+    #[test]
+    fn test_no_speeds() {
+        let series = speed_series(
+            &registry(),
+            &(),
+            vec![(AgentType::Car, Vec::new())],
+            Vec::new(),
+            Speed::meters_per_second(15.0),
+            Time::START_OF_DAY,
+        );
+        assert!(series.is_empty());
+    }
+}
@@ -1,30 +1,40 @@
 use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 
+use anyhow::Result;
+use instant::Instant;
+
+pub use bus::StopWalkingCosts;
 pub use trip::OpenTrip;
 
-use geom::{Circle, Distance, Time};
-use map_gui::tools::open_browser;
+use geom::{Circle, Distance, Duration, Polygon, Pt2D, Time};
+use map_gui::options::InfoPanelLayout;
+use map_gui::render::unzoomed_agent_radius;
+use map_gui::tools::{open_browser, open_in_josm, PopupMsg};
 use map_gui::ID;
-use map_model::{AreaID, BuildingID, BusRouteID, BusStopID, IntersectionID, LaneID, ParkingLotID};
+use map_model::osm::OsmID;
+use map_model::{
+    AreaID, BuildingID, BusRouteID, BusStopID, IntersectionID, LaneID, ParkingLotID,
+    SIDEWALK_THICKNESS,
+};
 use sim::{
     AgentID, AgentType, Analytics, CarID, ParkingSpot, PedestrianID, PersonID, PersonState, TripID,
     VehicleType,
 };
 use widgetry::{
-    Checkbox, Color, ControlState, Drawable, EventCtx, GeomBatch, GfxCtx, HorizontalAlignment, Key,
-    Line, LinePlot, Outcome, Panel, PlotOptions, Series, StyledButtons, TextExt, VerticalAlignment,
-    Widget,
+    Canvas, Checkbox, Color, ControlState, Drawable, EventCtx, GeomBatch, GfxCtx,
+    HorizontalAlignment, Key, Line, Outcome, Panel, PlotOptions, ScreenDims, ScreenPt,
+    ScreenRectangle, StyledButtons, Text, TextExt, UpdateType, VerticalAlignment, Widget,
 };
 
-use crate::app::{App, Transition};
-use crate::common::{color_for_agent_type, Warping};
+use crate::app::{App, Transition, UiEvent};
+use crate::common::{agent_type_plot, Warping};
 use crate::debug::path_counter::PathCounter;
-use crate::edit::{EditMode, RouteEditor};
-use crate::sandbox::{dashboards, GameplayMode, SandboxMode, TimeWarpScreen};
+use crate::edit::{export_signal_timing, EditMode, RouteEditor};
+use crate::sandbox::{dashboards, GameplayMode, QuickEdit, SandboxMode, TimeWarpScreen, WatchFor};
 
+mod area;
 mod building;
 mod bus;
-mod debug;
 mod intersection;
 mod lane;
 mod parking_lot;
@@ -43,11 +53,28 @@ pub struct InfoPanel {
     hyperlinks: HashMap<String, Tab>,
     warpers: HashMap<String, ID>,
     time_warpers: HashMap<String, (TripID, Time)>,
+    trip_watchers: HashMap<String, (TripID, WatchFor)>,
+    // Where the map marker for each warp button is
+    markers: HashMap<String, Pt2D>,
+    // The marker whose button is hovered, and when the hovering started
+    pulse: Option<(String, Instant)>,
+    // While dragging the resize handle, where the drag started and the size at that point
+    resizing: Option<(ScreenPt, usize, usize)>,
 
     // For drawing the OSD only
     cached_actions: Vec<Key>,
+    // Where each tab of the current object was last scrolled to, so switching back to it doesn't
+    // start at the top again
+    scroll_offsets: HashMap<(&'static str, &'static str), (f64, f64)>,
+    // The name of every plot in the panel, and when they were last regenerated
+    plots: Vec<String>,
+    plots_time: Time,
 }
 
+// Plots are expensive to build, so while the simulation runs, they're regenerated at most this
+// often. Everything else in the panel is cheap enough to rebuild every time.
+const PLOT_REFRESH: Duration = Duration::const_seconds(60.0);
+
 #[derive(Clone)]
 pub enum Tab {
     // What trips are open? For finished trips, show the timeline in the current simulation if
@@ -55,34 +82,47 @@ pub enum Tab {
     PersonTrips(PersonID, BTreeMap<TripID, OpenTrip>),
     PersonBio(PersonID),
     PersonSchedule(PersonID),
+    PersonLog(PersonID),
 
     BusStatus(CarID),
-    BusStop(BusStopID),
+    // Whether to show the buildings within a short walk
+    BusStop(BusStopID, bool),
+    // Only show one route, if any
+    BusStopThroughput(BusStopID, Option<BusRouteID>, DataOptions),
     BusRoute(BusRouteID),
 
     ParkedCar(CarID),
 
-    BldgInfo(BuildingID),
+    // The walkshed being shown, if any, and whether to draw where people commute
+    BldgInfo(BuildingID, Option<Duration>, bool),
     BldgPeople(BuildingID),
 
     ParkingLot(ParkingLotID),
 
     Crowd(Vec<PedestrianID>),
 
-    Area(AreaID),
+    // Whether the buildings inside are highlighted
+    Area(AreaID, bool),
 
-    IntersectionInfo(IntersectionID),
+    // Whether to show queues spilling back from every approach
+    IntersectionInfo(IntersectionID, bool),
     IntersectionTraffic(IntersectionID, DataOptions),
     // The extra bool is for fan chart. TODO Probably scatter plot should own the job of switching
     // between these?
     IntersectionDelay(IntersectionID, DataOptions, bool),
     IntersectionDemand(IntersectionID),
+    // The movement (indexed like CompressedMovementID) to highlight, if any
+    IntersectionMovements(IntersectionID, Option<u8>),
     IntersectionArrivals(IntersectionID, DataOptions),
     IntersectionTrafficSignal(IntersectionID),
+    // The (stage, hour) to explain, if any
+    IntersectionStageUsage(IntersectionID, Option<(usize, usize)>),
 
     LaneInfo(LaneID),
     LaneDebug(LaneID),
+    LaneOSM(LaneID),
     LaneTraffic(LaneID, DataOptions),
+    LaneSpeeds(LaneID, DataOptions),
 }
 
 impl Tab {
@@ -92,44 +132,60 @@ impl Tab {
             ID::Lane(l) => match app.session.info_panel_tab["lane"] {
                 "info" => Tab::LaneInfo(l),
                 "debug" => Tab::LaneDebug(l),
+                "osm" => Tab::LaneOSM(l),
                 "traffic" => Tab::LaneTraffic(l, DataOptions::new()),
+                "speeds" => Tab::LaneSpeeds(l, DataOptions::new()),
                 _ => unreachable!(),
             },
             ID::Intersection(i) => match app.session.info_panel_tab["intersection"] {
-                "info" => Tab::IntersectionInfo(i),
+                "info" => Tab::IntersectionInfo(i, false),
                 "traffic" => Tab::IntersectionTraffic(i, DataOptions::new()),
                 "delay" => {
                     if app.primary.map.get_i(i).is_traffic_signal() {
                         Tab::IntersectionDelay(i, DataOptions::new(), false)
                     } else {
-                        Tab::IntersectionInfo(i)
+                        Tab::IntersectionInfo(i, false)
                     }
                 }
                 "demand" => {
                     if app.primary.map.get_i(i).is_traffic_signal() {
                         Tab::IntersectionDemand(i)
                     } else {
-                        Tab::IntersectionInfo(i)
+                        Tab::IntersectionInfo(i, false)
+                    }
+                }
+                "movements" => {
+                    if app.primary.map.get_i(i).is_traffic_signal() {
+                        Tab::IntersectionMovements(i, None)
+                    } else {
+                        Tab::IntersectionInfo(i, false)
                     }
                 }
                 "arrivals" => {
                     if app.primary.map.get_i(i).is_incoming_border() {
                         Tab::IntersectionArrivals(i, DataOptions::new())
                     } else {
-                        Tab::IntersectionInfo(i)
+                        Tab::IntersectionInfo(i, false)
                     }
                 }
                 "traffic signal" => {
                     if app.primary.map.get_i(i).is_traffic_signal() {
                         Tab::IntersectionTrafficSignal(i)
                     } else {
-                        Tab::IntersectionInfo(i)
+                        Tab::IntersectionInfo(i, false)
+                    }
+                }
+                "stage usage" => {
+                    if app.primary.map.get_i(i).is_traffic_signal() {
+                        Tab::IntersectionStageUsage(i, None)
+                    } else {
+                        Tab::IntersectionInfo(i, false)
                     }
                 }
                 _ => unreachable!(),
             },
             ID::Building(b) => match app.session.info_panel_tab["bldg"] {
-                "info" => Tab::BldgInfo(b),
+                "info" => Tab::BldgInfo(b, None, false),
                 "people" => Tab::BldgPeople(b),
                 _ => unreachable!(),
             },
@@ -145,6 +201,7 @@ impl Tab {
                         ),
                         "bio" => Tab::PersonBio(p),
                         "schedule" => Tab::PersonSchedule(p),
+                        "log" => Tab::PersonLog(p),
                         _ => unreachable!(),
                     }
                 } else if c.1 == VehicleType::Bus || c.1 == VehicleType::Train {
@@ -174,50 +231,60 @@ impl Tab {
                     ),
                     "bio" => Tab::PersonBio(person),
                     "schedule" => Tab::PersonSchedule(person),
+                    "log" => Tab::PersonLog(person),
                     _ => unreachable!(),
                 }
             }
             ID::PedCrowd(members) => Tab::Crowd(members),
-            ID::BusStop(bs) => Tab::BusStop(bs),
-            ID::Area(a) => Tab::Area(a),
+            ID::BusStop(bs) => match app.session.info_panel_tab["bus stop"] {
+                "info" => Tab::BusStop(bs, false),
+                "throughput" => Tab::BusStopThroughput(bs, None, DataOptions::new()),
+                _ => unreachable!(),
+            },
+            ID::Area(a) => Tab::Area(a, false),
         }
     }
 
     fn to_id(&self, app: &App) -> Option<ID> {
         match self {
-            Tab::PersonTrips(p, _) | Tab::PersonBio(p) | Tab::PersonSchedule(p) => {
-                match app.primary.sim.get_person(*p).state {
-                    PersonState::Inside(b) => Some(ID::Building(b)),
-                    PersonState::Trip(t) => app
-                        .primary
-                        .sim
-                        .trip_to_agent(t)
-                        .ok()
-                        .map(|a| ID::from_agent(a)),
-                    _ => None,
-                }
-            }
+            Tab::PersonTrips(p, _)
+            | Tab::PersonBio(p)
+            | Tab::PersonSchedule(p)
+            | Tab::PersonLog(p) => match app.primary.sim.get_person(*p).state {
+                PersonState::Inside(b) => Some(ID::Building(b)),
+                PersonState::Trip(t) => app
+                    .primary
+                    .sim
+                    .trip_to_agent(t)
+                    .ok()
+                    .map(|a| ID::from_agent(a)),
+                _ => None,
+            },
             Tab::BusStatus(c) => Some(ID::Car(*c)),
-            Tab::BusStop(bs) => Some(ID::BusStop(*bs)),
+            Tab::BusStop(bs, _) | Tab::BusStopThroughput(bs, _, _) => Some(ID::BusStop(*bs)),
             Tab::BusRoute(_) => None,
-            // TODO If a parked car becomes in use while the panel is open, should update the
-            // panel better.
             Tab::ParkedCar(c) => match app.primary.sim.lookup_parked_car(*c)?.spot {
                 ParkingSpot::Onstreet(_, _) => Some(ID::Car(*c)),
                 ParkingSpot::Offstreet(b, _) => Some(ID::Building(b)),
                 ParkingSpot::Lot(_, _) => Some(ID::Car(*c)),
             },
-            Tab::BldgInfo(b) | Tab::BldgPeople(b) => Some(ID::Building(*b)),
+            Tab::BldgInfo(b, _, _) | Tab::BldgPeople(b) => Some(ID::Building(*b)),
             Tab::ParkingLot(pl) => Some(ID::ParkingLot(*pl)),
             Tab::Crowd(members) => Some(ID::PedCrowd(members.clone())),
-            Tab::Area(a) => Some(ID::Area(*a)),
-            Tab::IntersectionInfo(i)
+            Tab::Area(a, _) => Some(ID::Area(*a)),
+            Tab::IntersectionInfo(i, _)
             | Tab::IntersectionTraffic(i, _)
             | Tab::IntersectionDelay(i, _, _)
             | Tab::IntersectionDemand(i)
+            | Tab::IntersectionMovements(i, _)
             | Tab::IntersectionArrivals(i, _)
-            | Tab::IntersectionTrafficSignal(i) => Some(ID::Intersection(*i)),
-            Tab::LaneInfo(l) | Tab::LaneDebug(l) | Tab::LaneTraffic(l, _) => Some(ID::Lane(*l)),
+            | Tab::IntersectionTrafficSignal(i)
+            | Tab::IntersectionStageUsage(i, _) => Some(ID::Intersection(*i)),
+            Tab::LaneInfo(l)
+            | Tab::LaneDebug(l)
+            | Tab::LaneOSM(l)
+            | Tab::LaneTraffic(l, _)
+            | Tab::LaneSpeeds(l, _) => Some(ID::Lane(*l)),
         }
     }
 
@@ -227,7 +294,9 @@ impl Tab {
             Tab::IntersectionTraffic(_, _)
             | Tab::IntersectionDelay(_, _, _)
             | Tab::IntersectionArrivals(_, _)
-            | Tab::LaneTraffic(_, _) => {}
+            | Tab::LaneTraffic(_, _)
+            | Tab::LaneSpeeds(_, _)
+            | Tab::BusStopThroughput(_, _, _) => {}
             _ => {
                 return None;
             }
@@ -237,7 +306,9 @@ impl Tab {
         match new_tab {
             Tab::IntersectionTraffic(_, ref mut opts)
             | Tab::IntersectionArrivals(_, ref mut opts)
-            | Tab::LaneTraffic(_, ref mut opts) => {
+            | Tab::LaneTraffic(_, ref mut opts)
+            | Tab::LaneSpeeds(_, ref mut opts)
+            | Tab::BusStopThroughput(_, _, ref mut opts) => {
                 let new_opts = DataOptions::from_controls(c);
                 if *opts == new_opts {
                     return None;
@@ -263,24 +334,30 @@ impl Tab {
             Tab::PersonTrips(_, _) => ("person", "trips"),
             Tab::PersonBio(_) => ("person", "bio"),
             Tab::PersonSchedule(_) => ("person", "schedule"),
+            Tab::PersonLog(_) => ("person", "log"),
             Tab::BusStatus(_) => ("bus", "status"),
-            Tab::BusStop(_) => ("bus stop", "info"),
+            Tab::BusStop(_, _) => ("bus stop", "info"),
+            Tab::BusStopThroughput(_, _, _) => ("bus stop", "throughput"),
             Tab::BusRoute(_) => ("bus route", "info"),
             Tab::ParkedCar(_) => ("parked car", "info"),
-            Tab::BldgInfo(_) => ("bldg", "info"),
+            Tab::BldgInfo(_, _, _) => ("bldg", "info"),
             Tab::BldgPeople(_) => ("bldg", "people"),
             Tab::ParkingLot(_) => ("parking lot", "info"),
             Tab::Crowd(_) => ("crowd", "info"),
-            Tab::Area(_) => ("area", "info"),
-            Tab::IntersectionInfo(_) => ("intersection", "info"),
+            Tab::Area(_, _) => ("area", "info"),
+            Tab::IntersectionInfo(_, _) => ("intersection", "info"),
             Tab::IntersectionTraffic(_, _) => ("intersection", "traffic"),
             Tab::IntersectionDelay(_, _, _) => ("intersection", "delay"),
             Tab::IntersectionDemand(_) => ("intersection", "demand"),
+            Tab::IntersectionMovements(_, _) => ("intersection", "movements"),
             Tab::IntersectionArrivals(_, _) => ("intersection", "arrivals"),
             Tab::IntersectionTrafficSignal(_) => ("intersection", "traffic signal"),
+            Tab::IntersectionStageUsage(_, _) => ("intersection", "stage usage"),
             Tab::LaneInfo(_) => ("lane", "info"),
             Tab::LaneDebug(_) => ("lane", "debug"),
+            Tab::LaneOSM(_) => ("lane", "osm"),
             Tab::LaneTraffic(_, _) => ("lane", "traffic"),
+            Tab::LaneSpeeds(_, _) => ("lane", "speeds"),
         }
     }
 }
@@ -292,27 +369,79 @@ pub struct Details {
     pub hyperlinks: HashMap<String, Tab>,
     pub warpers: HashMap<String, ID>,
     pub time_warpers: HashMap<String, (TripID, Time)>,
+    pub trip_watchers: HashMap<String, (TripID, WatchFor)>,
+    pub markers: HashMap<String, Pt2D>,
     // It's just convenient to plumb this here
     pub can_jump_to_time: bool,
+    /// Whether the follow button in the header of agents can apply, or why not
+    pub follow: Result<(), String>,
+    // Plots in the previous version of the panel that can be moved into this one
+    reusable_plots: HashSet<String>,
+    plots: Vec<String>,
+    // Placeholders for plots to be moved over from the previous panel
+    reused_plots: Vec<String>,
+}
+
+impl Details {
+    /// Plots are expensive to build, so during live updates, the previous version is reused when
+    /// possible. Name each plot uniquely within its tab.
+    pub fn plot<F: FnOnce() -> Widget>(&mut self, name: &str, make_plot: F) -> Widget {
+        let name = format!("plot {}", name);
+        self.plots.push(name.clone());
+        if self.reusable_plots.contains(&name) {
+            self.reused_plots.push(name.clone());
+            Widget::nothing().named(name)
+        } else {
+            make_plot().named(name)
+        }
+    }
 }
 
 impl InfoPanel {
     pub fn new(
         ctx: &mut EventCtx,
         app: &mut App,
-        mut tab: Tab,
+        tab: Tab,
         ctx_actions: &mut dyn ContextualActions,
     ) -> InfoPanel {
+        app.session
+            .record_ui_event(UiEvent::InfoPanelOpened(tab.clone()));
+        InfoPanel::build(ctx, app, tab, ctx_actions, HashSet::new()).0
+    }
+
+    // Also returns the plots that need to be moved over from the previous panel
+    fn build(
+        ctx: &mut EventCtx,
+        app: &mut App,
+        mut tab: Tab,
+        ctx_actions: &mut dyn ContextualActions,
+        reusable_plots: HashSet<String>,
+    ) -> (InfoPanel, Vec<String>) {
         let (k, v) = tab.variant();
         app.session.info_panel_tab.insert(k, v);
 
+        if let Tab::PersonTrips(_, _) = tab {
+            if let Some(agent) = tab.to_id(app).and_then(|id| id.agent_id()) {
+                trip::record_speed(app, agent);
+            }
+        }
+
         let mut details = Details {
             unzoomed: GeomBatch::new(),
             zoomed: GeomBatch::new(),
             hyperlinks: HashMap::new(),
             warpers: HashMap::new(),
             time_warpers: HashMap::new(),
+            trip_watchers: HashMap::new(),
+            markers: HashMap::new(),
             can_jump_to_time: ctx_actions.gameplay_mode().can_jump_to_time(),
+            follow: match tab.to_id(app) {
+                Some(id) => ctx_actions.enabled(app, id, "follow (run the simulation)"),
+                None => Err("There's nobody to follow right now".to_string()),
+            },
+            reusable_plots,
+            plots: Vec::new(),
+            reused_plots: Vec::new(),
         };
 
         let (mut col, main_tab) = match tab {
@@ -328,19 +457,33 @@ impl InfoPanel {
                 person::schedule(ctx, app, &mut details, p, ctx_actions.is_paused()),
                 false,
             ),
+            Tab::PersonLog(p) => (
+                person::log(ctx, app, &mut details, p, ctx_actions.is_paused()),
+                true,
+            ),
             Tab::BusStatus(c) => (bus::bus_status(ctx, app, &mut details, c), true),
-            Tab::BusStop(bs) => (bus::stop(ctx, app, &mut details, bs), true),
+            Tab::BusStop(bs, catchment) => (bus::stop(ctx, app, &mut details, bs, catchment), true),
+            Tab::BusStopThroughput(bs, route, ref opts) => (
+                bus::stop_throughput(ctx, app, &mut details, bs, route, opts),
+                false,
+            ),
             Tab::BusRoute(br) => (bus::route(ctx, app, &mut details, br), true),
             Tab::ParkedCar(c) => (
                 person::parked_car(ctx, app, &mut details, c, ctx_actions.is_paused()),
                 true,
             ),
-            Tab::BldgInfo(b) => (building::info(ctx, app, &mut details, b), true),
+            Tab::BldgInfo(b, walkshed, desire_lines) => (
+                building::info(ctx, app, &mut details, b, walkshed, desire_lines),
+                true,
+            ),
             Tab::BldgPeople(b) => (building::people(ctx, app, &mut details, b), false),
             Tab::ParkingLot(pl) => (parking_lot::info(ctx, app, &mut details, pl), true),
             Tab::Crowd(ref members) => (person::crowd(ctx, app, &mut details, members), true),
-            Tab::Area(a) => (debug::area(ctx, app, &mut details, a), true),
-            Tab::IntersectionInfo(i) => (intersection::info(ctx, app, &mut details, i), true),
+            Tab::Area(a, highlight) => (area::info(ctx, app, &mut details, a, highlight), true),
+            Tab::IntersectionInfo(i, spillback) => (
+                intersection::info(ctx, app, &mut details, i, spillback),
+                true,
+            ),
             Tab::IntersectionTraffic(i, ref opts) => (
                 intersection::traffic(ctx, app, &mut details, i, opts),
                 false,
@@ -353,6 +496,10 @@ impl InfoPanel {
                 intersection::current_demand(ctx, app, &mut details, i),
                 false,
             ),
+            Tab::IntersectionMovements(i, selected) => (
+                intersection::movements(ctx, app, &mut details, i, selected),
+                false,
+            ),
             Tab::IntersectionArrivals(i, ref opts) => (
                 intersection::arrivals(ctx, app, &mut details, i, opts),
                 false,
@@ -361,30 +508,62 @@ impl InfoPanel {
                 intersection::traffic_signal(ctx, app, &mut details, i),
                 false,
             ),
+            Tab::IntersectionStageUsage(i, selected) => (
+                intersection::stage_usage(ctx, app, &mut details, i, selected),
+                false,
+            ),
             Tab::LaneInfo(l) => (lane::info(ctx, app, &mut details, l), true),
             Tab::LaneDebug(l) => (lane::debug(ctx, app, &mut details, l), false),
+            Tab::LaneOSM(l) => (lane::osm(ctx, app, &mut details, l), false),
             Tab::LaneTraffic(l, ref opts) => {
                 (lane::traffic(ctx, app, &mut details, l, opts), false)
             }
+            Tab::LaneSpeeds(l, ref opts) => (lane::speeds(ctx, app, &mut details, l, opts), false),
         };
         let maybe_id = tab.to_id(app);
         let mut cached_actions = Vec::new();
         if main_tab {
             if let Some(id) = maybe_id.clone() {
-                for (key, label) in ctx_actions.actions(app, id) {
-                    cached_actions.push(key);
-                    let button = ctx
-                        .style()
-                        .btn_solid_dark_text(&label)
-                        .hotkey(key)
-                        .build_widget(ctx, &label);
-                    col.push(button);
+                for (key, label) in ctx_actions.actions(app, id.clone()) {
+                    let button = ctx.style().btn_solid_dark_text(&label);
+                    // Live updates rebuild everything, so this stays current as the simulation
+                    // runs
+                    col.push(match ctx_actions.enabled(app, id.clone(), &label) {
+                        Ok(()) => {
+                            cached_actions.push(key);
+                            button.hotkey(key).build_widget(ctx, &label)
+                        }
+                        Err(reason) => button
+                            .disabled(true)
+                            .disabled_tooltip(Text::from(Line(reason)))
+                            .build_widget(ctx, &label),
+                    });
                 }
             }
         }
 
         // Highlight something?
-        if let Some((id, outline)) = maybe_id.clone().and_then(|id| {
+        if let Some(ID::PedCrowd(ref members)) = maybe_id {
+            // Crowds can stretch along a sidewalk, so a circle doesn't fit them well. Follow where
+            // the members actually are right now. They might've merged into a different crowd,
+            // so don't look for the crowd's draw object.
+            let circles: Vec<Polygon> = members
+                .iter()
+                .filter_map(|p| app.primary.sim.get_draw_ped(*p, &app.primary.map))
+                .map(|ped| Circle::new(ped.pos, SIDEWALK_THICKNESS / 2.0).to_polygon())
+                .collect();
+            if !circles.is_empty() {
+                match Polygon::convex_hull(circles).to_outline(Distance::meters(0.3)) {
+                    Ok(poly) => {
+                        details.unzoomed.push(app.cs.current_object, poly.clone());
+                        details.zoomed.push(app.cs.current_object, poly);
+                    }
+                    Err(err) => {
+                        warn!("No outline for crowd {:?}: {}", members, err);
+                    }
+                }
+            }
+        } else if let Some((id, outline)) = maybe_id.clone().and_then(|id| {
             app.primary
                 .draw_map
                 .get_obj(ctx, id.clone(), app, &mut app.primary.agents.borrow_mut())
@@ -392,7 +571,23 @@ impl InfoPanel {
         }) {
             // Different selection styles for different objects.
             match id {
-                ID::Car(_) | ID::Pedestrian(_) | ID::PedCrowd(_) => {
+                // Parked cars sit still, so outline exactly where they are
+                ID::Car(_) if matches!(tab, Tab::ParkedCar(_)) => {
+                    for (batch, thickness) in vec![
+                        (&mut details.unzoomed, Distance::meters(1.0)),
+                        (&mut details.zoomed, Distance::meters(0.3)),
+                    ] {
+                        match outline.to_outline(thickness) {
+                            Ok(poly) => {
+                                batch.push(app.cs.current_object, poly);
+                            }
+                            Err(err) => {
+                                warn!("No outline for {:?}: {}", id, err);
+                            }
+                        }
+                    }
+                }
+                ID::Car(_) | ID::Pedestrian(_) => {
                     // Some objects are much wider/taller than others
                     let multiplier = match id {
                         ID::Car(c) => {
@@ -403,28 +598,36 @@ impl InfoPanel {
                             }
                         }
                         ID::Pedestrian(_) => 3.0,
-                        ID::PedCrowd(_) => 0.75,
                         _ => unreachable!(),
                     };
-                    // Make a circle to cover the object.
+                    // Make a ring around the object. The details are drawn on top of agents, so
+                    // don't fill it in; that'd cover up the agent.
                     let bounds = outline.get_bounds();
                     let radius = multiplier * Distance::meters(bounds.width().max(bounds.height()));
-                    details.unzoomed.push(
-                        app.cs.current_object.alpha(0.5),
-                        Circle::new(bounds.center(), radius).to_polygon(),
-                    );
-                    match Circle::new(bounds.center(), radius).to_outline(Distance::meters(0.3)) {
-                        Ok(poly) => {
-                            details.unzoomed.push(app.cs.current_object, poly.clone());
-                            details.zoomed.push(app.cs.current_object, poly.clone());
-                        }
-                        Err(err) => {
-                            warn!("No outline for {:?}: {}", id, err);
+                    // Unzoomed, agents are drawn as larger circles, so make sure the ring is
+                    // outside of those.
+                    let vt = match id {
+                        ID::Car(c) => Some(c.1),
+                        _ => None,
+                    };
+                    let unzoomed_radius = radius.max(1.2 * unzoomed_agent_radius(vt));
+                    for (batch, radius, thickness) in vec![
+                        (
+                            &mut details.unzoomed,
+                            unzoomed_radius,
+                            Distance::meters(1.0),
+                        ),
+                        (&mut details.zoomed, radius, Distance::meters(0.3)),
+                    ] {
+                        match Circle::new(bounds.center(), radius).to_outline(thickness) {
+                            Ok(poly) => {
+                                batch.push(app.cs.current_object, poly);
+                            }
+                            Err(err) => {
+                                warn!("No outline for {:?}: {}", id, err);
+                            }
                         }
                     }
-
-                    // TODO And actually, don't cover up the agent. The Renderable API isn't quite
-                    // conducive to doing this yet.
                 }
                 _ => {
                     details
@@ -435,25 +638,35 @@ impl InfoPanel {
             }
         }
 
-        InfoPanel {
+        let layout = &app.opts.info_panel;
+        col.insert(0, layout_btns(ctx, layout));
+        let panel = InfoPanel {
             tab,
             time: app.primary.sim.time(),
             is_paused: ctx_actions.is_paused(),
             panel: Panel::new(Widget::col(col).bg(app.cs.panel_bg).padding(16))
                 .aligned(
-                    HorizontalAlignment::Percent(0.02),
-                    VerticalAlignment::Percent(0.2),
+                    HorizontalAlignment::Percent(layout_left_edge(layout)),
+                    VerticalAlignment::Percent(PANEL_TOP),
                 )
                 // TODO Some headings are too wide.. Intersection #xyz (Traffic signals)
-                .exact_size_percent(30, 60)
+                .exact_size_percent(layout.width_pct, layout.height_pct)
                 .build_custom(ctx),
             unzoomed: details.unzoomed.upload(ctx),
             zoomed: details.zoomed.upload(ctx),
             hyperlinks: details.hyperlinks,
             warpers: details.warpers,
             time_warpers: details.time_warpers,
+            trip_watchers: details.trip_watchers,
+            markers: details.markers,
+            pulse: None,
+            resizing: None,
             cached_actions,
-        }
+            scroll_offsets: HashMap::new(),
+            plots: details.plots,
+            plots_time: app.primary.sim.time(),
+        };
+        (panel, details.reused_plots)
     }
 
     // (Are we done, optional transition)
@@ -463,6 +676,36 @@ impl InfoPanel {
         app: &mut App,
         ctx_actions: &mut dyn ContextualActions,
     ) -> (bool, Option<Transition>) {
+        // Only resize when the drag is done; rebuilding constantly while dragging would be slow
+        if let Some((start, width_pct, height_pct)) = self.resizing {
+            if ctx.input.left_mouse_button_released() {
+                self.resizing = None;
+                app.opts.info_panel = resized_layout(
+                    ctx.canvas,
+                    &app.opts.info_panel,
+                    start,
+                    width_pct,
+                    height_pct,
+                );
+                self.rebuild(ctx, app, self.tab.clone(), ctx_actions);
+            }
+            return (false, None);
+        }
+        if ctx.input.left_mouse_button_pressed()
+            && self
+                .panel
+                .currently_hovering()
+                .map(|x| x == "resize info panel")
+                .unwrap_or(false)
+        {
+            self.resizing = Some((
+                ctx.canvas.get_cursor(),
+                app.opts.info_panel.width_pct,
+                app.opts.info_panel.height_pct,
+            ));
+            return (false, None);
+        }
+
         // Can click on the map to cancel
         if ctx.canvas.get_cursor_in_map_space().is_some()
             && app.primary.current_selection.is_none()
@@ -471,19 +714,56 @@ impl InfoPanel {
             return (true, None);
         }
 
+        // Time goes backwards after rewinding the simulation. Agents shown by some tabs might not
+        // exist yet, so just close those panels.
+        if app.primary.sim.time() < self.time {
+            if let Tab::BusStatus(_) | Tab::ParkedCar(_) | Tab::Crowd(_) = self.tab {
+                return (true, None);
+            }
+        }
+
+        // When a parked car drives away, follow whoever's driving it
+        if let Tab::ParkedCar(c) = self.tab {
+            if app.primary.sim.lookup_parked_car(c).is_none() {
+                if app.primary.sim.agent_to_person(AgentID::Car(c)).is_some() {
+                    let tab = Tab::from_id(app, ID::Car(c));
+                    *self = InfoPanel::new(ctx, app, tab, ctx_actions);
+                    return (false, None);
+                }
+                return (
+                    true,
+                    Some(Transition::Push(PopupMsg::new(
+                        ctx,
+                        "Car departed",
+                        vec![format!("{} isn't parked anymore", c)],
+                    ))),
+                );
+            }
+        }
+
         // Live update?
         if app.primary.sim.time() != self.time || ctx_actions.is_paused() != self.is_paused {
-            let mut new = InfoPanel::new(ctx, app, self.tab.clone(), ctx_actions);
-            new.panel.restore(ctx, &self.panel);
-            *self = new;
+            self.live_update(ctx, app, ctx_actions);
             return (false, None);
         }
 
+        let hovering = self
+            .panel
+            .currently_hovering()
+            .filter(|action| self.markers.contains_key(*action));
+        if hovering != self.pulse.as_ref().map(|(action, _)| action) {
+            self.pulse = hovering.map(|action| (action.clone(), Instant::now()));
+        }
+        if self.pulse.is_some() {
+            ctx.request_update(UpdateType::Game);
+        }
+
         let maybe_id = self.tab.to_id(app);
         match self.panel.event(ctx) {
             Outcome::Clicked(action) => {
                 if let Some(new_tab) = self.hyperlinks.get(&action).cloned() {
                     let mut new = InfoPanel::new(ctx, app, new_tab, ctx_actions);
+                    self.switch_tab(ctx, app, &mut new);
                     // TODO Most cases use changed_settings, but one doesn't. Detect that
                     // "sameness" here.
                     if let (Tab::PersonTrips(p1, _), Tab::PersonTrips(p2, _)) =
@@ -497,6 +777,24 @@ impl InfoPanel {
                     (false, None)
                 } else if action == "close" {
                     (true, None)
+                } else if action == "resize info panel" {
+                    // Handled by dragging
+                    (false, None)
+                } else if action == "dock to the left" || action == "dock to the right" {
+                    app.opts.info_panel.dock_right = action == "dock to the right";
+                    self.rebuild(ctx, app, self.tab.clone(), ctx_actions);
+                    (false, None)
+                } else if let Some(title) = action
+                    .strip_prefix("collapse ")
+                    .or_else(|| action.strip_prefix("expand "))
+                {
+                    let key = (self.tab.variant().0.to_string(), title.to_string());
+                    let collapsed = &mut app.session.collapsed_info_sections;
+                    if !collapsed.remove(&key) {
+                        collapsed.insert(key);
+                    }
+                    self.rebuild(ctx, app, self.tab.clone(), ctx_actions);
+                    (false, None)
                 } else if action == "jump to object" {
                     // TODO Messy way of doing this
                     if let Some(id) = self.tab.to_id(app) {
@@ -558,6 +856,141 @@ impl InfoPanel {
                             Box::new(move |_, _| vec![jump_to_time]),
                         ))),
                     )
+                } else if let Some((trip, watch)) = self.trip_watchers.get(&action) {
+                    let trip = *trip;
+                    let watch = *watch;
+                    (
+                        false,
+                        Some(Transition::ModifyState(Box::new(move |state, ctx, _| {
+                            // Info panels can also be opened from modes that don't run the
+                            // simulation; there's nothing to watch there.
+                            if let Some(sandbox) = state.downcast_mut::<SandboxMode>() {
+                                sandbox.controls.trip_watcher.watch(ctx, trip, watch);
+                            }
+                        }))),
+                    )
+                } else if let Some(x) = action.strip_prefix("compare free-flow route for Trip #") {
+                    let trip = TripID(x.parse::<usize>().unwrap());
+                    let mut tab = self.tab.clone();
+                    if let Tab::PersonTrips(_, ref mut open_trips) = tab {
+                        if let Some(open_trip) = open_trips.get_mut(&trip) {
+                            open_trip.show_free_flow = !open_trip.show_free_flow;
+                        }
+                    }
+                    self.rebuild(ctx, app, tab, ctx_actions);
+                    (false, None)
+                } else if let Some(x) = action.strip_prefix("cancel Trip #") {
+                    (
+                        false,
+                        Some(Transition::Push(trip::ConfirmTripAction::new(
+                            ctx,
+                            TripID(x.parse::<usize>().unwrap()),
+                            trip::TripAction::Cancel,
+                        ))),
+                    )
+                } else if let Some(x) = action.strip_prefix("reroute Trip #") {
+                    (
+                        false,
+                        Some(Transition::Push(trip::ConfirmTripAction::new(
+                            ctx,
+                            TripID(x.parse::<usize>().unwrap()),
+                            trip::TripAction::Reroute,
+                        ))),
+                    )
+                } else if action == "export log" {
+                    let p = match self.tab {
+                        Tab::PersonLog(p) => p,
+                        _ => unreachable!(),
+                    };
+                    (
+                        false,
+                        Some(Transition::Push(match person::export_log(app, p) {
+                            Ok(path) => PopupMsg::new(
+                                ctx,
+                                "Log exported",
+                                vec![format!("Log exported to {}", path)],
+                            ),
+                            Err(err) => PopupMsg::new(ctx, "Export failed", vec![err.to_string()]),
+                        })),
+                    )
+                } else if action == "dump geometry to GeoJSON" {
+                    let l = match self.tab {
+                        Tab::LaneDebug(l) => l,
+                        _ => unreachable!(),
+                    };
+                    (
+                        false,
+                        Some(Transition::Push(match lane::export_geometry(app, l) {
+                            Ok(path) => PopupMsg::new(
+                                ctx,
+                                "Geometry dumped",
+                                vec![format!("Lane geometry written to {}", path)],
+                            ),
+                            Err(err) => PopupMsg::new(ctx, "Dump failed", vec![err.to_string()]),
+                        })),
+                    )
+                } else if action == "export signal timing" {
+                    let i = match self.tab {
+                        Tab::IntersectionTrafficSignal(i) => i,
+                        _ => unreachable!(),
+                    };
+                    (
+                        false,
+                        Some(Transition::Push(match export_signal_timing(app, i) {
+                            Ok(path) => PopupMsg::new(
+                                ctx,
+                                "Signal timing exported",
+                                vec![
+                                    format!("Signal timing written to {}", path),
+                                    "Edit it, then import it again".to_string(),
+                                ],
+                            ),
+                            Err(err) => PopupMsg::new(ctx, "Export failed", vec![err.to_string()]),
+                        })),
+                    )
+                } else if action == "import signal timing" {
+                    let i = match self.tab {
+                        Tab::IntersectionTrafficSignal(i) => i,
+                        _ => unreachable!(),
+                    };
+                    match intersection::apply_signal_timing(ctx, app, i) {
+                        Ok(true) => {
+                            self.rebuild(ctx, app, self.tab.clone(), ctx_actions);
+                            (false, None)
+                        }
+                        Ok(false) => (
+                            false,
+                            Some(Transition::Push(PopupMsg::new(
+                                ctx,
+                                "Nothing to import",
+                                vec!["The file matches the current signal timing"],
+                            ))),
+                        ),
+                        Err(err) => (
+                            false,
+                            Some(Transition::Push(PopupMsg::new(
+                                ctx,
+                                "Import failed",
+                                err.to_string().lines().collect::<Vec<&str>>(),
+                            ))),
+                        ),
+                    }
+                } else if action == "undo this edit" {
+                    let t = QuickEdit::undo(ctx, app);
+                    self.rebuild(ctx, app, self.tab.clone(), ctx_actions);
+                    (false, Some(t))
+                } else if action == "edit in JOSM" {
+                    if let Err(err) = edit_in_josm(app, maybe_id.unwrap()) {
+                        return (
+                            false,
+                            Some(Transition::Push(PopupMsg::new(
+                                ctx,
+                                "Couldn't open in JOSM",
+                                vec![err.to_string()],
+                            ))),
+                        );
+                    }
+                    (false, None)
                 } else if let Some(url) = action.strip_prefix("open ") {
                     open_browser(url);
                     (false, None)
@@ -593,6 +1026,10 @@ impl InfoPanel {
                     if let Some(id) = maybe_id {
                         let mut close_panel = true;
                         let t = ctx_actions.execute(ctx, app, id, action, &mut close_panel);
+                        if !close_panel {
+                            // The action might've changed what the panel shows
+                            self.rebuild(ctx, app, self.tab.clone(), ctx_actions);
+                        }
                         (close_panel, Some(t))
                     } else {
                         // This happens when clicking the follow/unfollow button on a trip whose
@@ -609,9 +1046,7 @@ impl InfoPanel {
                 // Maybe a non-click action should change the tab. Aka, checkboxes/dropdowns/etc on
                 // a tab.
                 if let Some(new_tab) = self.tab.changed_settings(&self.panel) {
-                    let mut new = InfoPanel::new(ctx, app, new_tab, ctx_actions);
-                    new.panel.restore(ctx, &self.panel);
-                    *self = new;
+                    self.rebuild(ctx, app, new_tab, ctx_actions);
                 }
 
                 (false, None)
@@ -619,13 +1054,124 @@ impl InfoPanel {
         }
     }
 
+    // Recreate the panel in place, keeping scroll positions and widget state.
+    fn rebuild(
+        &mut self,
+        ctx: &mut EventCtx,
+        app: &mut App,
+        tab: Tab,
+        ctx_actions: &mut dyn ContextualActions,
+    ) {
+        // Not InfoPanel::new; this is the same panel, not a newly opened one
+        let mut new = InfoPanel::build(ctx, app, tab, ctx_actions, HashSet::new()).0;
+        new.panel.restore(ctx, &self.panel);
+        new.scroll_offsets = std::mem::take(&mut self.scroll_offsets);
+        // Keep pulsing smoothly while the simulation runs
+        new.pulse = self
+            .pulse
+            .take()
+            .filter(|(action, _)| new.markers.contains_key(action));
+        *self = new;
+    }
+
+    // Like rebuild, but keep the old plots if they were regenerated recently enough.
+    fn live_update(
+        &mut self,
+        ctx: &mut EventCtx,
+        app: &mut App,
+        ctx_actions: &mut dyn ContextualActions,
+    ) {
+        let now = app.primary.sim.time();
+        // After rewinding, start over
+        let reuse = now >= self.plots_time && now - self.plots_time < PLOT_REFRESH;
+        let reusable_plots = if reuse {
+            self.plots.iter().cloned().collect()
+        } else {
+            HashSet::new()
+        };
+        let (mut new, reused_plots) =
+            InfoPanel::build(ctx, app, self.tab.clone(), ctx_actions, reusable_plots);
+        if reuse {
+            new.plots_time = self.plots_time;
+        }
+        for name in reused_plots {
+            let plot = self.panel.take(&name);
+            new.panel.replace(ctx, &name, plot);
+        }
+        new.panel.restore(ctx, &self.panel);
+        new.scroll_offsets = std::mem::take(&mut self.scroll_offsets);
+        new.pulse = self
+            .pulse
+            .take()
+            .filter(|(action, _)| new.markers.contains_key(action));
+        *self = new;
+    }
+
+    // When switching between tabs of the same object, remember where the old tab was scrolled to
+    // and return to wherever the new one was last time. A different object starts over.
+    fn switch_tab(&mut self, ctx: &EventCtx, app: &App, new: &mut InfoPanel) {
+        let id = self.tab.to_id(app);
+        if id.is_none() || id != new.tab.to_id(app) {
+            return;
+        }
+        let mut offsets = std::mem::take(&mut self.scroll_offsets);
+        offsets.insert(self.tab.variant(), self.panel.scroll_offset());
+        if let Some(offset) = offsets.get(&new.tab.variant()) {
+            new.panel.set_scroll_offset(ctx, *offset);
+        }
+        new.scroll_offsets = offsets;
+    }
+
     pub fn draw(&self, g: &mut GfxCtx, app: &App) {
+        self.draw_panel(g, app);
+        self.draw_highlights(g, app);
+    }
+
+    /// Just the panel, not anything it highlights on the map
+    pub fn draw_panel(&self, g: &mut GfxCtx, app: &App) {
         self.panel.draw(g);
-        if g.canvas.cam_zoom < app.opts.min_zoom_for_detail {
+        if let Some((start, width_pct, height_pct)) = self.resizing {
+            // Preview the new size
+            let layout =
+                resized_layout(g.canvas, &app.opts.info_panel, start, width_pct, height_pct);
+            let window_width = g.canvas.window_width;
+            let window_height = g.canvas.window_height;
+            if let Ok(outline) = Polygon::rectangle(
+                (layout.width_pct as f64) / 100.0 * window_width,
+                (layout.height_pct as f64) / 100.0 * window_height,
+            )
+            .translate(
+                layout_left_edge(&layout) * window_width,
+                PANEL_TOP * window_height,
+            )
+            .to_outline(Distance::meters(3.0))
+            {
+                g.fork_screenspace();
+                g.draw_polygon(app.cs.current_object, outline);
+                g.unfork();
+            }
+        }
+    }
+
+    /// Whatever the panel highlights on the map, drawn in map-space
+    pub fn draw_highlights(&self, g: &mut GfxCtx, app: &App) {
+        let unzoomed = g.canvas.cam_zoom < app.opts.min_zoom_for_detail;
+        if unzoomed {
             g.redraw(&self.unzoomed);
         } else {
             g.redraw(&self.zoomed);
         }
+
+        if let Some((ref action, start)) = self.pulse {
+            // Grow a ring out from the marker once per second
+            let pct = Duration::realtime_elapsed(start).inner_seconds().fract();
+            let max_radius = Distance::meters(if unzoomed { 50.0 } else { 15.0 });
+            if let Ok(ring) =
+                Circle::new(self.markers[action], pct * max_radius).to_outline(0.1 * max_radius)
+            {
+                g.draw_polygon(app.cs.current_object.alpha((1.0 - pct) as f32), ring);
+            }
+        }
     }
 
     pub fn active_keys(&self) -> &Vec<Key> {
@@ -635,6 +1181,15 @@ impl InfoPanel {
     pub fn active_id(&self, app: &App) -> Option<ID> {
         self.tab.to_id(app)
     }
+
+    /// Where some button is on the screen, if this tab has it.
+    pub fn rect_of(&self, action: &str) -> Option<ScreenRectangle> {
+        if self.panel.has_widget(action) {
+            Some(self.panel.rect_of(action).clone())
+        } else {
+            None
+        }
+    }
 }
 
 fn make_table<I: Into<String>>(ctx: &EventCtx, rows: Vec<(I, String)>) -> Vec<Widget> {
@@ -650,42 +1205,60 @@ fn make_table<I: Into<String>>(ctx: &EventCtx, rows: Vec<(I, String)>) -> Vec<Wi
 }
 
 fn throughput<F: Fn(&Analytics) -> Vec<(AgentType, Vec<(Time, usize)>)>>(
-    ctx: &EventCtx,
+    ctx: &mut EventCtx,
     app: &App,
     title: &str,
     get_data: F,
     opts: &DataOptions,
 ) -> Widget {
-    let mut series = get_data(app.primary.sim.get_analytics())
-        .into_iter()
-        .map(|(agent_type, pts)| Series {
-            label: agent_type.noun().to_string(),
-            color: color_for_agent_type(app, agent_type),
-            pts,
-        })
-        .collect::<Vec<_>>();
-    if opts.show_before {
+    let current = get_data(app.primary.sim.get_analytics());
+    let baseline = if opts.show_before {
         // TODO Ahh these colors don't show up differently at all.
-        for (agent_type, pts) in get_data(app.prebaked()) {
-            series.push(Series {
-                label: agent_type.noun().to_string(),
-                color: color_for_agent_type(app, agent_type).alpha(0.3),
-                pts,
-            });
-        }
-    }
+        get_data(app.prebaked())
+    } else {
+        Vec::new()
+    };
 
     let mut plot_opts = PlotOptions::filterable();
     plot_opts.disabled = opts.disabled_series();
+    plot_opts.dims = Some(plot_dims(ctx, app));
     Widget::col(vec![
         Line(title).small_heading().draw(ctx),
-        LinePlot::new(ctx, series, plot_opts),
+        agent_type_plot(ctx, app, current, baseline, plot_opts),
     ])
     .padding(10)
     .bg(app.cs.inner_panel)
     .outline(2.0, Color::WHITE)
 }
 
+// Only lanes, intersections, and buildings have this button
+fn edit_in_josm(app: &App, id: ID) -> Result<()> {
+    let map = &app.primary.map;
+    let (osm_id, polygon) = match id {
+        ID::Lane(l) => {
+            let r = map.get_parent(l);
+            (OsmID::Way(r.orig_id.osm_way_id), r.get_thick_polygon(map))
+        }
+        ID::Intersection(i) => {
+            let i = map.get_i(i);
+            (OsmID::Node(i.orig_id), i.polygon.clone())
+        }
+        ID::Building(b) => {
+            let b = map.get_b(b);
+            (b.orig_id, b.polygon.clone())
+        }
+        _ => unreachable!(),
+    };
+    let bounds = polygon.get_bounds();
+    open_in_josm(
+        osm_id,
+        vec![
+            Pt2D::new(bounds.min_x, bounds.min_y).to_gps(map.get_gps_bounds()),
+            Pt2D::new(bounds.max_x, bounds.max_y).to_gps(map.get_gps_bounds()),
+        ],
+    )
+}
+
 fn make_tabs(
     ctx: &EventCtx,
     hyperlinks: &mut HashMap<String, Tab>,
@@ -723,6 +1296,100 @@ fn make_tabs(
     Widget::custom_row(row).bg(Color::grey(0.8)).margin_vert(16)
 }
 
+// The top of the panel, as a fraction of the window height
+const PANEL_TOP: f64 = 0.2;
+// Percents of the window; any smaller and the contents are unusable
+const MIN_WIDTH_PCT: usize = 20;
+const MIN_HEIGHT_PCT: usize = 30;
+const MAX_WIDTH_PCT: usize = 60;
+const MAX_HEIGHT_PCT: usize = 78;
+
+fn layout_btns(ctx: &EventCtx, layout: &InfoPanelLayout) -> Widget {
+    let dock = if layout.dock_right {
+        ctx.style()
+            .btn_plain_light_icon("system/assets/minimap/left.svg")
+            .build_widget(ctx, "dock to the left")
+    } else {
+        ctx.style()
+            .btn_plain_light_icon("system/assets/minimap/right.svg")
+            .build_widget(ctx, "dock to the right")
+    };
+    let resize = ctx
+        .style()
+        .btn_plain_light_icon("system/assets/tools/outward.svg")
+        .tooltip(Text::from(Line("Drag to resize")))
+        .build_widget(ctx, "resize info panel");
+    Widget::row(vec![dock, resize]).align_right()
+}
+
+// As a fraction of the window width
+fn layout_left_edge(layout: &InfoPanelLayout) -> f64 {
+    let width = (layout.width_pct as f64) / 100.0;
+    if layout.dock_right {
+        0.98 - width
+    } else {
+        0.02
+    }
+}
+
+// Dragging away from the docked edge or down grows the panel.
+fn resized_layout(
+    canvas: &Canvas,
+    layout: &InfoPanelLayout,
+    start: ScreenPt,
+    width_pct: usize,
+    height_pct: usize,
+) -> InfoPanelLayout {
+    let cursor = canvas.get_cursor();
+    let mut dx = 100.0 * (cursor.x - start.x) / canvas.window_width;
+    if layout.dock_right {
+        dx *= -1.0;
+    }
+    let dy = 100.0 * (cursor.y - start.y) / canvas.window_height;
+    InfoPanelLayout {
+        dock_right: layout.dock_right,
+        width_pct: ((width_pct as f64 + dx).round().max(0.0) as usize)
+            .max(MIN_WIDTH_PCT)
+            .min(MAX_WIDTH_PCT),
+        height_pct: ((height_pct as f64 + dy).round().max(0.0) as usize)
+            .max(MIN_HEIGHT_PCT)
+            .min(MAX_HEIGHT_PCT),
+    }
+}
+
+/// How wide the info panel is, in pixels. Plots and other fixed-size things inside should scale
+/// with this, since the player can resize the panel.
+fn panel_width(ctx: &EventCtx, app: &App) -> f64 {
+    (app.opts.info_panel.width_pct as f64) / 100.0 * ctx.canvas.window_width
+}
+
+fn plot_dims(ctx: &EventCtx, app: &App) -> ScreenDims {
+    ScreenDims::new(
+        0.77 * panel_width(ctx, app),
+        (app.opts.info_panel.height_pct as f64) / 300.0 * ctx.canvas.window_height,
+    )
+}
+
+/// A clickable heading for a section of a tab, which the player can collapse. Returns the heading
+/// and whether the section is expanded; callers shouldn't even build the contents of a collapsed
+/// section, since live updates rebuild the whole panel and plots are expensive.
+fn section_header(ctx: &EventCtx, app: &App, kind: &str, title: &str) -> (Widget, bool) {
+    let expanded = !app
+        .session
+        .collapsed_info_sections
+        .contains(&(kind.to_string(), title.to_string()));
+    let btn = if expanded {
+        ctx.style()
+            .btn_plain_light_icon_text("system/assets/minimap/down.svg", title)
+            .build_widget(ctx, &format!("collapse {}", title))
+    } else {
+        ctx.style()
+            .btn_plain_light_icon_text("system/assets/minimap/right.svg", title)
+            .build_widget(ctx, &format!("expand {}", title))
+    };
+    (btn, expanded)
+}
+
 fn header_btns(ctx: &EventCtx) -> Widget {
     Widget::row(vec![
         ctx.style()
@@ -737,6 +1404,11 @@ fn header_btns(ctx: &EventCtx) -> Widget {
 pub trait ContextualActions {
     // TODO &str?
     fn actions(&self, app: &App, id: ID) -> Vec<(Key, String)>;
+    /// Some actions only apply sometimes. Rather than failing once clicked, say why one can't
+    /// apply right now, and it's shown disabled with that reason.
+    fn enabled(&self, _: &App, _: ID, _: &str) -> Result<(), String> {
+        Ok(())
+    }
     fn execute(
         &mut self,
         ctx: &mut EventCtx,
@@ -807,6 +1479,10 @@ impl DataOptions {
         }
     }
 
+    pub fn is_enabled(&self, agent_type: AgentType) -> bool {
+        !self.disabled_types.contains(&agent_type)
+    }
+
     pub fn disabled_series(&self) -> HashSet<String> {
         self.disabled_types
             .iter()
@@ -5,7 +5,7 @@ use map_model::ParkingLotID;
 use widgetry::{EventCtx, Line, LinePlot, PlotOptions, Series, StyledButtons, TextExt, Widget};
 
 use crate::app::App;
-use crate::info::{header_btns, make_tabs, Details, Tab};
+use crate::info::{header_btns, make_tabs, plot_dims, Details, Tab};
 
 pub fn info(ctx: &mut EventCtx, app: &App, details: &mut Details, id: ParkingLotID) -> Vec<Widget> {
     let mut rows = header(ctx, details, id, Tab::ParkingLot(id));
@@ -42,16 +42,19 @@ pub fn info(ctx: &mut EventCtx, app: &App, details: &mut Details, id: ParkingLot
         });
     }
     rows.push("Parking spots available".draw_text(ctx));
-    rows.push(LinePlot::new(
-        ctx,
-        series,
-        PlotOptions {
-            filterable: false,
-            max_x: None,
-            max_y: Some(capacity),
-            disabled: HashSet::new(),
-        },
-    ));
+    rows.push(details.plot("spots available", || {
+        LinePlot::new(
+            ctx,
+            series,
+            PlotOptions {
+                filterable: false,
+                max_x: None,
+                max_y: Some(capacity),
+                disabled: HashSet::new(),
+                dims: Some(plot_dims(ctx, app)),
+            },
+        )
+    }));
 
     if app.opts.dev {
         rows.push(
@@ -1,14 +1,19 @@
 use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::Write;
+
+use anyhow::Result;
 
 use rand::seq::SliceRandom;
 use rand::{Rng, SeedableRng};
 use rand_xorshift::XorShiftRng;
 
 use geom::{Angle, Duration, Time};
+use map_gui::ID;
 use map_model::Map;
 use sim::{
-    AgentID, CarID, ParkingSpot, PedestrianID, Person, PersonID, PersonState, TripEndpoint, TripID,
-    TripMode, TripResult, VehicleType,
+    AgentID, CarID, ParkingSpot, PedestrianID, Person, PersonID, PersonLogLocation, PersonState,
+    TripEndpoint, TripID, TripMode, TripResult, VehicleType,
 };
 use widgetry::{
     Color, ControlState, CornerRounding, EdgeInsets, EventCtx, GeomBatch, Key, Line, RewriteColor,
@@ -16,6 +21,7 @@ use widgetry::{
 };
 
 use crate::app::App;
+use crate::common::Externalities;
 use crate::info::{building, header_btns, make_table, make_tabs, trip, Details, OpenTrip, Tab};
 
 pub fn trips(
@@ -174,6 +180,14 @@ pub fn trips(
             } else {
                 Widget::nothing()
             },
+            if trip.synthetic {
+                Line("synthetic override")
+                    .batch(ctx)
+                    .centered_vert()
+                    .margin_right(15)
+            } else {
+                Widget::nothing()
+            },
             if trip_status == "finished" {
                 if let Some(before) = app
                     .has_prebaked()
@@ -323,41 +337,129 @@ pub fn bio(
         );
     }
 
-    let mut has_bike = false;
-    for v in &person.vehicles {
-        if v.vehicle_type == VehicleType::Bike {
-            has_bike = true;
-        } else {
-            if app.primary.sim.lookup_parked_car(v.id).is_some() {
-                rows.push(
-                    ctx.style()
-                        .btn_solid_dark_text(&format!("Owner of {} (parked)", v.id))
-                        .build_def(ctx),
-                );
-                details
-                    .hyperlinks
-                    .insert(format!("Owner of {} (parked)", v.id), Tab::ParkedCar(v.id));
-            } else if let PersonState::Trip(t) = person.state {
-                match app.primary.sim.trip_to_agent(t) {
-                    TripResult::Ok(AgentID::Car(x)) if x == v.id => {
-                        rows.push(format!("Owner of {} (currently driving)", v.id).draw_text(ctx));
-                    }
-                    _ => {
-                        rows.push(format!("Owner of {} (off-map)", v.id).draw_text(ctx));
+    rows.extend(footprint(ctx, app, person));
+    rows.extend(owned_vehicles(ctx, app, details, person));
+
+    rows
+}
+
+// Carbon emitted and calories burned by every phase of every trip so far, and what it'll add up to
+// if the rest of the schedule goes as planned
+fn footprint(ctx: &EventCtx, app: &App, person: &Person) -> Vec<Widget> {
+    let map = &app.primary.map;
+    let sim = &app.primary.sim;
+    let mut so_far = Externalities::default();
+    let mut whole_day = Externalities::default();
+    for t in &person.trips {
+        match sim.trip_to_agent(*t) {
+            TripResult::TripNotStarted => {
+                whole_day += Externalities::for_planned_trip(map, &sim.trip_info(*t));
+            }
+            TripResult::TripCancelled | TripResult::TripDoesntExist => {}
+            TripResult::Ok(_) | TripResult::ModeChange | TripResult::TripDone => {
+                let phases = sim.get_analytics().get_trip_phases(*t, map);
+                for (e, finished) in Externalities::for_trip_phases(map, &phases) {
+                    if finished {
+                        so_far += e;
                     }
+                    whole_day += e;
                 }
-            } else {
-                rows.push(format!("Owner of {} (off-map)", v.id).draw_text(ctx));
             }
         }
     }
-    if has_bike {
-        rows.push("Owns a bike".draw_text(ctx));
+
+    let units = &app.opts.units;
+    let mut rows = vec![Line("Footprint").small_heading().draw(ctx)];
+    rows.extend(make_table(
+        ctx,
+        vec![
+            ("CO2 so far", so_far.describe_co2(units)),
+            ("Burned so far", so_far.describe_kcal(units)),
+            (
+                "Whole day, as planned",
+                format!(
+                    "{}, {} burned",
+                    whole_day.describe_co2(units),
+                    whole_day.describe_kcal(units)
+                ),
+            ),
+        ],
+    ));
+    rows
+}
+
+pub fn log(
+    ctx: &mut EventCtx,
+    app: &App,
+    details: &mut Details,
+    id: PersonID,
+    is_paused: bool,
+) -> Vec<Widget> {
+    let mut rows = header(ctx, app, details, id, Tab::PersonLog(id), is_paused);
+
+    let entries = app.primary.sim.get_analytics().person_log.get(id);
+    if entries.is_empty() {
+        rows.push("Nothing has happened yet".draw_text(ctx));
+    }
+    for (idx, entry) in entries.into_iter().enumerate() {
+        let mut row = vec![
+            Text::from(Line(entry.time.ampm_tostring()).secondary()).draw(ctx),
+            Text::from(Line(&entry.description))
+                .wrap_to_pct(ctx, 20)
+                .draw(ctx),
+        ];
+        if let Some(loc) = entry.location {
+            let name = format!("jump to log entry {}", idx + 1);
+            row.insert(
+                0,
+                ctx.style()
+                    .btn_plain_light_icon("system/assets/tools/pin.svg")
+                    .build_widget(ctx, &name)
+                    .centered_vert(),
+            );
+            details.warpers.insert(
+                name,
+                match loc {
+                    PersonLogLocation::Building(b) => ID::Building(b),
+                    PersonLogLocation::Intersection(i) => ID::Intersection(i),
+                    PersonLogLocation::Lane(l) => ID::Lane(l),
+                    PersonLogLocation::BusStop(bs) => ID::BusStop(bs),
+                },
+            );
+        }
+        rows.push(Widget::row(row));
+    }
+
+    if app.opts.dev {
+        rows.push(
+            ctx.style()
+                .btn_outline_light_text("export log")
+                .build_def(ctx),
+        );
     }
 
     rows
 }
 
+/// Writes everything in a person's log to a text file, returning the path.
+pub fn export_log(app: &App, id: PersonID) -> Result<String> {
+    let path = abstio::path_player(format!(
+        "person_logs/{}_{}.txt",
+        id.0,
+        app.primary.sim.time().as_filename()
+    ));
+    std::fs::create_dir_all(std::path::Path::new(&path).parent().unwrap())?;
+    let mut f = File::create(&path)?;
+    for entry in app.primary.sim.get_analytics().person_log.get(id) {
+        write!(f, "{}: {}", entry.time.ampm_tostring(), entry.description)?;
+        if let Some(loc) = entry.location {
+            write!(f, " ({:?})", loc)?;
+        }
+        writeln!(f)?;
+    }
+    Ok(path)
+}
+
 pub fn schedule(
     ctx: &mut EventCtx,
     app: &App,
@@ -489,69 +591,200 @@ pub fn parked_car(
             .small_heading()
             .draw(ctx),
         Widget::row(vec![
-            // Little indirect, but the handler of this action is actually the ContextualActions
-            // for SandboxMode.
-            if is_paused {
-                ctx.style()
-                    .btn_plain_light_icon("system/assets/tools/location.svg")
-                    .hotkey(Key::F)
-                    .build_widget(ctx, "follow (run the simulation)")
-            } else {
-                // TODO Blink
-                ctx.style()
-                    .btn_plain_light_icon("system/assets/tools/location.svg")
-                    .image_color(Color::hex("#7FFA4D"), ControlState::Default)
-                    .hotkey(Key::F)
-                    .build_widget(ctx, "unfollow (pause the simulation)")
-            },
+            follow_button(ctx, details, is_paused),
             ctx.style().btn_close_widget(ctx),
         ])
         .align_right(),
     ]));
 
-    // TODO prev trips, next trips, etc
-
-    let p = app.primary.sim.get_owner_of_car(id).unwrap();
-    rows.push(
-        ctx.style()
-            .btn_solid_dark_text(&format!("Owned by {}", p))
-            .build_def(ctx),
-    );
+    let map = &app.primary.map;
+    let sim = &app.primary.sim;
+    let p = sim.get_owner_of_car(id).unwrap();
+    let owner_btn = ctx
+        .style()
+        .btn_solid_dark_text(&format!("Owned by {}", p))
+        .build_def(ctx);
     details.hyperlinks.insert(
         format!("Owned by {}", p),
         Tab::PersonTrips(p, BTreeMap::new()),
     );
+    // Hovering pulses wherever the owner is, and clicking goes there
+    let owner_location = match sim.get_person(p).state {
+        PersonState::Inside(b) => Some(ID::Building(b)),
+        PersonState::Trip(t) => sim.trip_to_agent(t).ok().map(ID::from_agent),
+        PersonState::OffMap => None,
+    }
+    .and_then(|id| app.primary.canonical_point(id.clone()).map(|pt| (id, pt)));
+    rows.push(match owner_location {
+        Some((owner_id, pt)) => {
+            let action = format!("locate {}", p);
+            details.warpers.insert(action.clone(), owner_id);
+            details.markers.insert(action.clone(), pt);
+            Widget::row(vec![
+                owner_btn,
+                ctx.style()
+                    .btn_outline_light_text("Show where they are")
+                    .build_widget(ctx, &action),
+            ])
+        }
+        None => Widget::row(vec![
+            owner_btn,
+            "They're off the map".draw_text(ctx).centered_vert(),
+        ]),
+    });
+
+    let parked = match sim.lookup_parked_car(id) {
+        Some(parked) => parked,
+        None => {
+            rows.push("No longer parked".draw_text(ctx));
+            return rows;
+        }
+    };
 
-    if let Some(p) = app.primary.sim.lookup_parked_car(id) {
-        match p.spot {
-            ParkingSpot::Onstreet(_, _) | ParkingSpot::Lot(_, _) => {
-                ctx.canvas.center_on_map_pt(
-                    app.primary
-                        .sim
-                        .canonical_pt_for_agent(AgentID::Car(id), &app.primary.map)
-                        .unwrap(),
-                );
+    let spot = match parked.spot {
+        ParkingSpot::Onstreet(l, idx) => {
+            ctx.canvas
+                .center_on_map_pt(sim.canonical_pt_for_agent(AgentID::Car(id), map).unwrap());
+            let name = format!(
+                "Spot {} along {}",
+                idx + 1,
+                map.get_parent(l).get_name(app.opts.language.as_ref())
+            );
+            details.hyperlinks.insert(name.clone(), Tab::LaneInfo(l));
+            name
+        }
+        ParkingSpot::Offstreet(b, _) => {
+            ctx.canvas.center_on_map_pt(map.get_b(b).polygon.center());
+            let name = format!("Inside {}", map.get_b(b).address);
+            details
+                .hyperlinks
+                .insert(name.clone(), Tab::BldgInfo(b, None, false));
+            name
+        }
+        ParkingSpot::Lot(pl, idx) => {
+            ctx.canvas
+                .center_on_map_pt(sim.canonical_pt_for_agent(AgentID::Car(id), map).unwrap());
+            let name = format!("Spot {} in {}", idx + 1, pl);
+            details.hyperlinks.insert(name.clone(), Tab::ParkingLot(pl));
+            name
+        }
+    };
+    rows.push(ctx.style().btn_outline_light_text(&spot).build_def(ctx));
+
+    rows.push(format!("Parked here for {}", sim.time() - parked.parked_since).draw_text(ctx));
+
+    match next_drive(app, p, parked.parked_since) {
+        Some((trip, _, true)) => {
+            rows.push(format!("Reserved: {} is walking here to start {}", p, trip).draw_text(ctx));
+        }
+        Some((trip, departure, false)) => {
+            rows.push(
+                format!(
+                    "Probably needed next for {}, leaving at {}",
+                    trip,
+                    departure.ampm_tostring()
+                )
+                .draw_text(ctx),
+            );
+        }
+        None => {
+            rows.push("Not needed for any upcoming trip".draw_text(ctx));
+        }
+    }
+
+    rows
+}
+
+/// Guesses the owner's trip that'll use a parked car next, when it departs, and whether the owner
+/// is already walking to the car. Trips don't pick a car until they start, so this is only a
+/// guess for people owning several.
+fn next_drive(app: &App, owner: PersonID, parked_since: Time) -> Option<(TripID, Time, bool)> {
+    let sim = &app.primary.sim;
+    for t in &sim.get_person(owner).trips {
+        let info = sim.trip_info(*t);
+        if info.mode != TripMode::Drive {
+            continue;
+        }
+        match sim.trip_to_agent(*t) {
+            TripResult::TripNotStarted => {
+                return Some((*t, info.departure, false));
             }
-            ParkingSpot::Offstreet(b, _) => {
-                ctx.canvas
-                    .center_on_map_pt(app.primary.map.get_b(b).polygon.center());
-                rows.push(
-                    format!("Parked inside {}", app.primary.map.get_b(b).address).draw_text(ctx),
-                );
+            // Walking during a driving trip is either towards the car, or away from where it was
+            // just parked
+            TripResult::Ok(AgentID::Pedestrian(_)) if parked_since < info.departure => {
+                return Some((*t, info.departure, true));
             }
+            _ => {}
         }
+    }
+    None
+}
 
-        rows.push(
-            format!(
-                "Parked here for {}",
-                app.primary.sim.time() - p.parked_since
-            )
-            .draw_text(ctx),
-        );
-    } else {
-        rows.push("No longer parked".draw_text(ctx));
+// Explains what someone could use for their trips. Whether a car is parked nearby often explains
+// their choice of mode.
+fn owned_vehicles(
+    ctx: &mut EventCtx,
+    app: &App,
+    details: &mut Details,
+    person: &Person,
+) -> Vec<Widget> {
+    let mut rows = vec![Line("Owned vehicles").small_heading().draw(ctx)];
+    if person.vehicles.is_empty() {
+        rows.push("Owns no cars or bikes, so can only walk or take transit".draw_text(ctx));
+        return rows;
     }
 
+    let map = &app.primary.map;
+    let sim = &app.primary.sim;
+    let in_use = match person.state {
+        PersonState::Trip(t) => match sim.trip_to_agent(t) {
+            TripResult::Ok(AgentID::Car(c)) => Some((c, t)),
+            _ => None,
+        },
+        _ => None,
+    };
+    for v in &person.vehicles {
+        let location = if let Some(p) = sim.lookup_parked_car(v.id) {
+            Some(match p.spot {
+                ParkingSpot::Onstreet(l, _) => format!(
+                    "parked on-street along {}",
+                    map.get_parent(l).get_name(app.opts.language.as_ref())
+                ),
+                ParkingSpot::Offstreet(b, _) => {
+                    format!("parked inside {}", map.get_b(b).address)
+                }
+                ParkingSpot::Lot(pl, _) => format!("parked in {}", pl),
+            })
+        } else if let Some((_, t)) = in_use.filter(|(c, _)| *c == v.id) {
+            Some(format!("in use on {}", t))
+        } else {
+            None
+        };
+
+        match location {
+            Some(location) => {
+                let name = format!("{}: {}", v.id, location);
+                rows.push(Widget::row(vec![
+                    ctx.style()
+                        .btn_plain_light_icon("system/assets/tools/pin.svg")
+                        .build_widget(ctx, &name),
+                    name.clone().draw_text(ctx).centered_vert(),
+                ]));
+                details.warpers.insert(name, ID::Car(v.id));
+            }
+            None => {
+                rows.push(
+                    if v.vehicle_type == VehicleType::Bike {
+                        // Bikes aren't parked anywhere; they just travel with their owner
+                        format!("{}: not in use", v.id)
+                    } else {
+                        format!("{}: not on the map", v.id)
+                    }
+                    .draw_text(ctx),
+                );
+            }
+        }
+    }
     rows
 }
 
@@ -617,21 +850,7 @@ fn header(
             .draw(ctx)
             .margin_horiz(10),
         Widget::row(vec![
-            // Little indirect, but the handler of this action is actually the ContextualActions
-            // for SandboxMode.
-            if is_paused {
-                ctx.style()
-                    .btn_plain_light_icon("system/assets/tools/location.svg")
-                    .hotkey(Key::F)
-                    .build_widget(ctx, "follow (run the simulation)")
-            } else {
-                // TODO Blink
-                ctx.style()
-                    .btn_plain_light_icon("system/assets/tools/location.svg")
-                    .image_color(Color::hex("#7FFA4D"), ControlState::Default)
-                    .hotkey(Key::F)
-                    .build_widget(ctx, "unfollow (pause the simulation)")
-            },
+            follow_button(ctx, details, is_paused),
             ctx.style().btn_close_widget(ctx),
         ])
         .align_right(),
@@ -649,6 +868,7 @@ fn header(
     if app.opts.dev {
         tabs.push(("Schedule", Tab::PersonSchedule(id)));
     }
+    tabs.push(("Log", Tab::PersonLog(id)));
     rows.push(make_tabs(ctx, &mut details.hyperlinks, tab, tabs));
 
     rows
@@ -682,3 +902,27 @@ fn cmp_duration_shorter(after: Duration, before: Duration) -> TextSpan {
         unreachable!()
     }
 }
+
+// Little indirect, but the handler of these actions is actually the ContextualActions for
+// SandboxMode.
+fn follow_button(ctx: &EventCtx, details: &Details, is_paused: bool) -> Widget {
+    if !is_paused {
+        // TODO Blink
+        return ctx
+            .style()
+            .btn_plain_light_icon("system/assets/tools/location.svg")
+            .image_color(Color::hex("#7FFA4D"), ControlState::Default)
+            .hotkey(Key::F)
+            .build_widget(ctx, "unfollow (pause the simulation)");
+    }
+    let mut btn = ctx
+        .style()
+        .btn_plain_light_icon("system/assets/tools/location.svg")
+        .hotkey(Key::F);
+    if let Err(ref reason) = details.follow {
+        btn = btn
+            .disabled(true)
+            .disabled_tooltip(Text::from(Line(reason)));
+    }
+    btn.build_widget(ctx, "follow (run the simulation)")
+}
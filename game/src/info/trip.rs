@@ -2,33 +2,53 @@ use std::collections::BTreeMap;
 
 use maplit::btreemap;
 
-use geom::{Distance, Duration, Percent, Polygon, Pt2D, Time};
+use geom::{Distance, Duration, Percent, PolyLine, Polygon, Pt2D, Speed, Time};
+use map_gui::tools::PopupMsg;
 use map_gui::ID;
 use map_model::{Map, Path, PathStep};
-use sim::{AgentID, PersonID, TripEndpoint, TripID, TripPhase, TripPhaseType};
+use sim::{
+    AgentID, CarID, PersonID, SpeedConstraint, TripEndpoint, TripID, TripPhase, TripPhaseDurations,
+    TripPhaseType, TripResult,
+};
 use widgetry::{
-    Color, ControlState, DrawWithTooltips, EventCtx, GeomBatch, Line, LinePlot, PlotOptions,
-    RewriteColor, Series, StyledButtons, Text, TextExt, Widget,
+    Color, ControlState, DrawWithTooltips, EventCtx, GeomBatch, GfxCtx, Key, Line, LinePlot,
+    Outcome, Panel, PlotOptions, RewriteColor, Series, State, StyledButtons, Text, TextExt, Widget,
 };
 
-use crate::app::App;
-use crate::common::color_for_trip_phase;
-use crate::info::{make_table, Details, Tab};
+use crate::app::{App, Transition};
+use crate::common::{color_for_trip_phase, launch_info_panel};
+use crate::info::{make_table, panel_width, Details, Tab};
+use crate::sandbox::WatchFor;
+
+// Contrasts with all of the trip phase colors
+const FREE_FLOW_COLOR: Color = Color::CYAN;
 
 #[derive(Clone)]
 pub struct OpenTrip {
     pub show_after: bool,
+    pub show_free_flow: bool,
     // (unzoomed, zoomed). Indexed by order of TripPhase.
     cached_routes: Vec<Option<(Polygon, Vec<Polygon>)>>,
+    // None until it's first shown. The inner result is an error if there's no single-mode path.
+    cached_free_flow: Option<Result<FreeFlowRoute, String>>,
 }
 
-// Ignore cached_routes
+// Ignore the cached routes
 impl std::cmp::PartialEq for OpenTrip {
     fn eq(&self, other: &OpenTrip) -> bool {
-        self.show_after == other.show_after
+        self.show_after == other.show_after && self.show_free_flow == other.show_free_flow
     }
 }
 
+/// The route a trip would take from its original start in a world with no traffic
+#[derive(Clone)]
+struct FreeFlowRoute {
+    unzoomed: Polygon,
+    zoomed: Polygon,
+    dist: Distance,
+    time: Duration,
+}
+
 impl OpenTrip {
     pub fn single(id: TripID) -> BTreeMap<TripID, OpenTrip> {
         btreemap! { id => OpenTrip::new() }
@@ -37,7 +57,9 @@ impl OpenTrip {
     pub fn new() -> OpenTrip {
         OpenTrip {
             show_after: true,
+            show_free_flow: false,
             cached_routes: Vec::new(),
+            cached_free_flow: None,
         }
     }
 }
@@ -115,6 +137,43 @@ pub fn ongoing(
             ]),
         ]));
     }
+    if let Some(speed) = app.primary.sim.agent_speed(&app.primary.map, agent) {
+        let mut txt = Text::from_all(vec![
+            Line(speed.speed.to_string(&app.opts.units)),
+            Line(format!(
+                " / {} max",
+                speed.max_speed.to_string(&app.opts.units)
+            ))
+            .secondary(),
+        ]);
+        if let Some(change) = describe_speed_change(app) {
+            txt.add(Line(change).secondary());
+        }
+        txt.add(Line(describe_constraint(app, agent, &speed.constraint)));
+        col.push(Widget::custom_row(vec![
+            Line("Speed")
+                .secondary()
+                .draw(ctx)
+                .container()
+                .force_width_pct(ctx, col_width),
+            Widget::col(vec![
+                txt.draw(ctx),
+                speed_sparkline(ctx, app, speed.max_speed),
+            ]),
+        ]));
+    }
+    if let AgentID::Car(car) = agent {
+        if let Some(widget) = delay_caused(ctx, app, car) {
+            col.push(Widget::custom_row(vec![
+                Line("Delay caused")
+                    .secondary()
+                    .draw(ctx)
+                    .container()
+                    .force_width_pct(ctx, col_width),
+                widget,
+            ]));
+        }
+    }
     {
         col.push(Widget::custom_row(vec![
             Widget::custom_row(vec![Line("Purpose").secondary().draw(ctx)])
@@ -133,9 +192,186 @@ pub fn ongoing(
         &app.primary.map,
         Some(props.dist_crossed / props.total_dist),
     ));
+    col.push(watch_buttons(ctx, details, id, vec![WatchFor::Finish]));
     Widget::col(col)
 }
 
+/// Samples the current speed of an agent, so the info panel can show how it's changed recently.
+/// Call every time the panel refreshes.
+pub fn record_speed(app: &mut App, agent: AgentID) {
+    let now = app.primary.sim.time();
+    let speed = match app.primary.sim.agent_speed(&app.primary.map, agent) {
+        Some(speed) => speed.speed,
+        None => {
+            return;
+        }
+    };
+    // Start over for a different agent, or if the simulation was rewound
+    let reset = match app.primary.recent_speeds {
+        Some((id, ref samples)) => {
+            id != agent || samples.last().map(|(t, _)| *t > now).unwrap_or(false)
+        }
+        None => true,
+    };
+    if reset {
+        app.primary.recent_speeds = Some((agent, Vec::new()));
+    }
+    let samples = &mut app.primary.recent_speeds.as_mut().unwrap().1;
+    if samples.last().map(|(t, _)| *t == now).unwrap_or(false) {
+        return;
+    }
+    samples.push((now, speed));
+    samples.retain(|(t, _)| now - *t <= Duration::minutes(1));
+}
+
+fn describe_constraint(app: &App, agent: AgentID, constraint: &SpeedConstraint) -> String {
+    let map = &app.primary.map;
+    let walking = matches!(agent, AgentID::Pedestrian(_));
+    match constraint {
+        SpeedConstraint::FreeFlow => {
+            if walking {
+                "Walking freely".to_string()
+            } else {
+                "Moving freely".to_string()
+            }
+        }
+        SpeedConstraint::Following(car, gap) => format!(
+            "Following {}, {} behind",
+            car,
+            gap.to_string(&app.opts.units)
+        ),
+        SpeedConstraint::BlockedByParking(car) => {
+            format!("Stuck behind {}, which is parking or unparking", car)
+        }
+        SpeedConstraint::Intersection(i) => {
+            if walking {
+                format!("Waiting to cross at {}", i)
+            } else if map.get_i(*i).is_traffic_signal() {
+                format!("Waiting for the signal at {}", i)
+            } else {
+                format!("Waiting at the stop sign at {}", i)
+            }
+        }
+        SpeedConstraint::Yielding(other) => {
+            format!("Yielding to {}, which is making a conflicting turn", other)
+        }
+        SpeedConstraint::NoRoomAhead(car) => {
+            format!("No room on the next lane, which is backed up to {}", car)
+        }
+        SpeedConstraint::Maneuvering => {
+            if walking {
+                "Switching between walking and biking".to_string()
+            } else {
+                "Parking or unparking".to_string()
+            }
+        }
+        SpeedConstraint::AtTransitStop => {
+            if walking {
+                "Waiting for the bus".to_string()
+            } else {
+                "Stopped for passengers".to_string()
+            }
+        }
+    }
+}
+
+// The simulation changes speed instantly, so this is just the difference over the last few
+// seconds, not a real acceleration.
+fn describe_speed_change(app: &App) -> Option<String> {
+    let samples = &app.primary.recent_speeds.as_ref()?.1;
+    let (now, current) = *samples.last()?;
+    let (then, before) = *samples
+        .iter()
+        .find(|(t, _)| now - *t <= Duration::seconds(5.0))?;
+    if then == now || current == before {
+        return None;
+    }
+    Some(if current > before {
+        format!(
+            ", up {} in the last {}",
+            (current - before).to_string(&app.opts.units),
+            (now - then).to_string(&app.opts.units)
+        )
+    } else {
+        format!(
+            ", down {} in the last {}",
+            (before - current).to_string(&app.opts.units),
+            (now - then).to_string(&app.opts.units)
+        )
+    })
+}
+
+// Speed over the last minute, to make stop-and-go patterns obvious
+fn speed_sparkline(ctx: &EventCtx, app: &App, max_speed: Speed) -> Widget {
+    let (width, height) = (200.0, 40.0);
+    let mut batch = GeomBatch::new();
+    batch.push(app.cs.inner_panel, Polygon::rectangle(width, height));
+    if let Some((_, ref samples)) = app.primary.recent_speeds {
+        let now = app.primary.sim.time();
+        let max = samples
+            .iter()
+            .map(|(_, speed)| *speed)
+            .max()
+            .unwrap_or(Speed::ZERO)
+            .max(max_speed);
+        if max > Speed::ZERO {
+            let pts = samples
+                .iter()
+                .map(|(t, speed)| {
+                    let percent_x = 1.0 - (now - *t) / Duration::minutes(1);
+                    let percent_y = *speed / max;
+                    Pt2D::new(percent_x * width, (1.0 - percent_y) * height)
+                })
+                .collect();
+            if let Ok(pl) = PolyLine::new(pts) {
+                batch.push(Color::WHITE, pl.make_polygons(Distance::meters(2.0)));
+            }
+        }
+    }
+    Widget::draw_batch(ctx, batch)
+}
+
+/// How much time the vehicle right behind this one lost, while this one waited to turn (but not
+/// for a red light), unparked, or stopped for passengers. None if it hasn't blocked anybody.
+pub fn delay_caused(ctx: &EventCtx, app: &App, car: CarID) -> Option<Widget> {
+    let samples = app.primary.sim.delay_caused_by(car)?;
+    let (_, total) = *samples.last()?;
+    if total == Duration::ZERO {
+        return None;
+    }
+    let txt = Text::from(Line(format!(
+        "{} lost by the vehicles right behind it",
+        total.to_string(&app.opts.units)
+    )));
+
+    // The total only grows, so draw it as steps
+    let (width, height) = (200.0, 40.0);
+    let mut batch = GeomBatch::new();
+    batch.push(app.cs.inner_panel, Polygon::rectangle(width, height));
+    let start = samples[0].0;
+    let now = app.primary.sim.time();
+    if now > start {
+        let mut pts = Vec::new();
+        let mut prev = Duration::ZERO;
+        for (t, value) in samples.iter().chain(std::iter::once(&(now, total))) {
+            let percent_x = (*t - start) / (now - start);
+            pts.push(Pt2D::new(percent_x * width, (1.0 - prev / total) * height));
+            pts.push(Pt2D::new(
+                percent_x * width,
+                (1.0 - *value / total) * height,
+            ));
+            prev = *value;
+        }
+        if let Ok(pl) = PolyLine::deduping_new(pts) {
+            batch.push(Color::WHITE, pl.make_polygons(Distance::meters(2.0)));
+        }
+    }
+    Some(Widget::col(vec![
+        txt.draw(ctx),
+        Widget::draw_batch(ctx, batch),
+    ]))
+}
+
 pub fn future(
     ctx: &mut EventCtx,
     app: &App,
@@ -201,10 +437,156 @@ pub fn future(
             None,
         ));
     }
+    col.push(watch_buttons(
+        ctx,
+        details,
+        id,
+        vec![WatchFor::Start, WatchFor::Finish],
+    ));
+    col.push(Widget::row(vec![
+        ctx.style()
+            .btn_outline_light_text("Cancel this trip")
+            .build_widget(ctx, &format!("cancel {}", id)),
+        ctx.style()
+            .btn_outline_light_text("Reroute now")
+            .disabled(!matches!(agent, AgentID::Car(_)))
+            .build_widget(ctx, &format!("reroute {}", id)),
+    ]));
 
     Widget::col(col)
 }
 
+fn watch_buttons(
+    ctx: &mut EventCtx,
+    details: &mut Details,
+    id: TripID,
+    watches: Vec<WatchFor>,
+) -> Widget {
+    Widget::row(
+        watches
+            .into_iter()
+            .map(|watch| {
+                let (label, action) = match watch {
+                    WatchFor::Start => (
+                        "Pause when this starts",
+                        format!("pause when {} starts", id),
+                    ),
+                    WatchFor::Finish => (
+                        "Pause when this finishes",
+                        format!("pause when {} finishes", id),
+                    ),
+                };
+                details.trip_watchers.insert(action.clone(), (id, watch));
+                ctx.style()
+                    .btn_outline_light_text(label)
+                    .build_widget(ctx, &action)
+            })
+            .collect(),
+    )
+}
+
+#[derive(Clone, Copy)]
+pub enum TripAction {
+    Cancel,
+    Reroute,
+}
+
+/// There's no undo for cancelling or re-routing a trip, so ask first.
+pub struct ConfirmTripAction {
+    panel: Panel,
+    trip: TripID,
+    action: TripAction,
+}
+
+impl ConfirmTripAction {
+    pub fn new(ctx: &mut EventCtx, trip: TripID, action: TripAction) -> Box<dyn State<App>> {
+        let (question, confirm) = match action {
+            TripAction::Cancel => (
+                format!(
+                    "Cancel {}? The person will head back to where they started, parking their \
+                     car near there, and the trip will count as cancelled once they arrive.",
+                    trip
+                ),
+                "Yes, cancel",
+            ),
+            TripAction::Reroute => (
+                format!(
+                    "Re-plan the rest of {} from where the vehicle is now? The new route \
+                     avoids lanes that are nearly full right now, if there's another way.",
+                    trip
+                ),
+                "Yes, reroute",
+            ),
+        };
+        Box::new(ConfirmTripAction {
+            trip,
+            action,
+            panel: Panel::new(Widget::col(vec![
+                Widget::row(vec![
+                    Line("Are you sure?").small_heading().draw(ctx),
+                    ctx.style().btn_close_widget(ctx),
+                ]),
+                question.draw_text(ctx),
+                Widget::row(vec![
+                    ctx.style()
+                        .btn_solid_dark_text("Never mind")
+                        .hotkey(Key::Escape)
+                        .build_def(ctx),
+                    ctx.style()
+                        .btn_solid_destructive_text(confirm)
+                        .build_widget(ctx, "confirm"),
+                ])
+                .align_right(),
+            ]))
+            .build(ctx),
+        })
+    }
+}
+
+impl State<App> for ConfirmTripAction {
+    fn event(&mut self, ctx: &mut EventCtx, app: &mut App) -> Transition {
+        match self.panel.event(ctx) {
+            Outcome::Clicked(x) => match x.as_ref() {
+                "close" | "Never mind" => Transition::Pop,
+                "confirm" => {
+                    let result = match self.action {
+                        TripAction::Cancel => {
+                            app.primary.sim.cancel_trip(self.trip, &app.primary.map)
+                        }
+                        TripAction::Reroute => {
+                            app.primary.sim.reroute_trip(self.trip, &app.primary.map)
+                        }
+                    };
+                    if let Err(err) = result {
+                        return Transition::Replace(PopupMsg::new(
+                            ctx,
+                            "Error",
+                            vec![err.to_string()],
+                        ));
+                    }
+                    // Without time passing, the info panel won't notice the change, so reopen it
+                    match app.primary.sim.trip_to_person(self.trip) {
+                        Some(person) => Transition::Multi(vec![
+                            Transition::Pop,
+                            launch_info_panel(Tab::PersonTrips(
+                                person,
+                                OpenTrip::single(self.trip),
+                            )),
+                        ]),
+                        None => Transition::Pop,
+                    }
+                }
+                _ => unreachable!(),
+            },
+            _ => Transition::Keep,
+        }
+    }
+
+    fn draw(&self, g: &mut GfxCtx, _: &App) {
+        self.panel.draw(g);
+    }
+}
+
 pub fn finished(
     ctx: &mut EventCtx,
     app: &App,
@@ -244,7 +626,7 @@ pub fn finished(
             id,
             OpenTrip {
                 show_after: false,
-                cached_routes: Vec::new(),
+                ..OpenTrip::new()
             },
         );
         details.hyperlinks.insert(
@@ -321,6 +703,20 @@ pub fn finished(
                 .force_width_pct(ctx, col_width),
             Line(trip.purpose.to_string()).secondary().draw(ctx),
         ]));
+
+        let analytics = if open_trips[&id].show_after {
+            app.primary.sim.get_analytics()
+        } else {
+            app.prebaked()
+        };
+        // The trip might not have finished before the changes
+        if let Some((_, _, durations)) = analytics.trip_phase_durations.get(&id) {
+            col.push(Widget::custom_row(vec![
+                Widget::custom_row(vec![Line("Time split").secondary().draw(ctx)])
+                    .force_width_pct(ctx, col_width),
+                phase_breakdown(ctx, app, durations),
+            ]));
+        }
     }
 
     col.push(make_trip_details(
@@ -471,7 +867,7 @@ fn make_timeline(
     let map = &app.primary.map;
     let sim = &app.primary.sim;
 
-    let total_width = 0.22 * ctx.canvas.window_width;
+    let total_width = 0.73 * panel_width(ctx, app);
     let trip = sim.trip_info(trip_id);
     let end_time = phases.last().as_ref().and_then(|p| p.end_time);
     let total_duration_so_far = end_time.unwrap_or_else(|| sim.time()) - trip.departure;
@@ -570,6 +966,57 @@ fn make_timeline(
     DrawWithTooltips::new(ctx, batch, tooltips, Box::new(|_| GeomBatch::new()))
 }
 
+/// A small horizontal bar showing how much of a finished trip was spent in each type of phase
+fn phase_breakdown(ctx: &mut EventCtx, app: &App, durations: &TripPhaseDurations) -> Widget {
+    let total = durations.total();
+    if total == Duration::ZERO {
+        return Widget::nothing();
+    }
+    let total_width = 0.5 * panel_width(ctx, app);
+    let height = 15.0;
+
+    let mut batch = GeomBatch::new();
+    let mut tooltips: Vec<(Polygon, Text)> = Vec::new();
+    let mut x1 = 0.0;
+    // Same colors as color_for_trip_phase
+    for (color, label, dt) in &[
+        (Color::YELLOW, "Delayed start", durations.delayed_start),
+        (app.cs.unzoomed_pedestrian, "Walking", durations.walking),
+        (app.cs.bike_trip, "Biking", durations.biking),
+        (app.cs.unzoomed_car, "Driving", durations.driving),
+        (
+            app.cs.parking_trip,
+            "Looking for parking",
+            durations.parking,
+        ),
+        (
+            app.cs.bus_layer,
+            "Waiting for transit",
+            durations.waiting_for_bus,
+        ),
+        (app.cs.bus_trip, "Riding transit", durations.riding_bus),
+    ] {
+        let dt = *dt;
+        if dt == Duration::ZERO {
+            continue;
+        }
+        let width = total_width * (dt / total);
+        let rectangle = Polygon::rectangle(width, height).translate(x1, 0.0);
+        tooltips.push((
+            rectangle.clone(),
+            Text::from(Line(format!(
+                "{}: {} ({}%)",
+                label,
+                dt.to_string(&app.opts.units),
+                (100.0 * (dt / total)) as usize
+            ))),
+        ));
+        batch.push(color.alpha(0.7), rectangle);
+        x1 += width;
+    }
+    DrawWithTooltips::new(ctx, batch, tooltips, Box::new(|_| GeomBatch::new()))
+}
+
 // TODO Restore this, figuring out why some delays don't show up
 /*
     let mut sum_phase_dist = Distance::ZERO;
@@ -667,71 +1114,89 @@ fn make_trip_details(
     let trip = sim.trip_info(trip_id);
     let end_time = phases.last().as_ref().and_then(|p| p.end_time);
 
-    let start_btn = {
+    let mut markers = Vec::new();
+    {
         let (id, center, name) = endpoint(&trip.start, app);
-        details
-            .warpers
-            .insert(format!("jump to start of {}", trip_id), id);
-
-        details.unzoomed.append(
-            GeomBatch::load_svg(ctx, "system/assets/timeline/start_pos.svg")
-                .scale(3.0)
-                .color(RewriteColor::Change(Color::WHITE, Color::BLACK))
-                .color(RewriteColor::Change(
-                    Color::hex("#5B5B5B"),
-                    Color::hex("#CC4121"),
-                ))
-                .centered_on(center),
-        );
-        details.zoomed.append(
-            GeomBatch::load_svg(ctx, "system/assets/timeline/start_pos.svg")
-                .color(RewriteColor::Change(Color::WHITE, Color::BLACK))
-                .color(RewriteColor::Change(
-                    Color::hex("#5B5B5B"),
-                    Color::hex("#CC4121"),
-                ))
-                .centered_on(center),
-        );
-
-        ctx.style()
-            .btn_plain_light_icon("system/assets/timeline/start_pos.svg")
-            .tooltip(Text::from(Line(name)))
-            .build_widget(ctx, &format!("jump to start of {}", trip_id))
-    };
-
-    let goal_btn = {
+        markers.push((TripMarker::Start, id, center, name));
+    }
+    for p in &phases {
+        let req = match p.path {
+            Some(ref path) => path.get_req(),
+            None => {
+                continue;
+            }
+        };
+        match p.phase_type {
+            TripPhaseType::Parking => {
+                // If the first spot was taken, only show where they finally parked
+                markers.retain(|(marker, _, _, _)| *marker != TripMarker::ParkingSpot);
+                markers.push((
+                    TripMarker::ParkingSpot,
+                    ID::Lane(req.end.lane()),
+                    req.end.pt(map),
+                    map.get_parent(req.end.lane())
+                        .get_name(app.opts.language.as_ref()),
+                ));
+            }
+            TripPhaseType::RidingBus(route, board, _) => {
+                let stop = map.get_bs(board);
+                markers.push((
+                    TripMarker::BoardBus,
+                    ID::BusStop(stop.id),
+                    stop.sidewalk_pos.pt(map),
+                    stop.name.clone(),
+                ));
+                // Riders leaving the map don't get off at a stop
+                if let Some(stop) = map
+                    .get_br(route)
+                    .stops
+                    .iter()
+                    .map(|bs| map.get_bs(*bs))
+                    .find(|bs| bs.driving_pos == req.end)
+                {
+                    markers.push((
+                        TripMarker::AlightBus,
+                        ID::BusStop(stop.id),
+                        stop.sidewalk_pos.pt(map),
+                        stop.name.clone(),
+                    ));
+                }
+            }
+            _ => {}
+        }
+    }
+    if progress_along_path.is_some() {
+        if let TripResult::Ok(agent) = sim.trip_to_agent(trip_id) {
+            if let Some(pt) = sim.canonical_pt_for_agent(agent, map) {
+                markers.push((
+                    TripMarker::CurrentPosition,
+                    ID::from_agent(agent),
+                    pt,
+                    agent.to_string(),
+                ));
+            }
+        }
+    }
+    {
         let (id, center, name) = endpoint(&trip.end, app);
-        details
-            .warpers
-            .insert(format!("jump to goal of {}", trip_id), id);
-
-        details.unzoomed.append(
-            GeomBatch::load_svg(ctx, "system/assets/timeline/goal_pos.svg")
-                .scale(3.0)
-                .color(RewriteColor::Change(Color::WHITE, Color::BLACK))
-                .color(RewriteColor::Change(
-                    Color::hex("#5B5B5B"),
-                    Color::hex("#CC4121"),
-                ))
-                .centered_on(center),
-        );
-        details.zoomed.append(
-            GeomBatch::load_svg(ctx, "system/assets/timeline/goal_pos.svg")
-                .color(RewriteColor::Change(Color::WHITE, Color::BLACK))
-                .color(RewriteColor::Change(
-                    Color::hex("#5B5B5B"),
-                    Color::hex("#CC4121"),
-                ))
-                .centered_on(center),
-        );
-
-        ctx.style()
-            .btn_plain_light_icon("system/assets/timeline/goal_pos.svg")
-            .tooltip(Text::from(Line(name)))
-            .build_widget(ctx, &format!("jump to goal of {}", trip_id))
-    };
+        markers.push((TripMarker::Goal, id, center, name));
+    }
+    let marker_btns: Vec<Widget> = markers
+        .into_iter()
+        .map(|(marker, id, pt, name)| add_marker(ctx, details, trip_id, marker, id, pt, name))
+        .collect();
 
     let timeline = make_timeline(ctx, app, trip_id, &phases, progress_along_path);
+    // Draw this first, so the actual route is on top
+    let free_flow = compare_free_flow(
+        ctx,
+        app,
+        trip_id,
+        open_trip,
+        details,
+        &phases,
+        map_for_pathfinding,
+    );
     let mut elevation = Vec::new();
     let mut path_impossible = false;
     for (idx, p) in phases.into_iter().enumerate() {
@@ -740,13 +1205,12 @@ fn make_trip_details(
             if app.opts.dev
                 && (p.phase_type == TripPhaseType::Walking || p.phase_type == TripPhaseType::Biking)
             {
-                elevation.push(make_elevation(
-                    ctx,
-                    color,
-                    p.phase_type == TripPhaseType::Walking,
-                    path,
-                    map,
-                ));
+                let walking = p.phase_type == TripPhaseType::Walking;
+                elevation.push(
+                    details.plot(&format!("elevation {} #{}", trip_id, idx), || {
+                        make_elevation(ctx, color, walking, path, map)
+                    }),
+                );
             }
 
             // This is expensive, so cache please
@@ -779,9 +1243,9 @@ fn make_trip_details(
 
     let mut col = vec![
         Widget::custom_row(vec![
-            start_btn.align_bottom(),
+            Widget::draw_svg(ctx, TripMarker::Start.icon()).align_bottom(),
             timeline,
-            goal_btn.align_bottom(),
+            Widget::draw_svg(ctx, TripMarker::Goal.icon()).align_bottom(),
         ])
         .evenly_spaced(),
         Widget::row(vec![
@@ -835,6 +1299,8 @@ fn make_trip_details(
             },
         ]),
     ];
+    col.extend(marker_btns);
+    col.extend(free_flow);
     if path_impossible {
         col.push("Map edits have disconnected the path taken before".draw_text(ctx));
     }
@@ -847,6 +1313,204 @@ fn make_trip_details(
     Widget::col(col)
 }
 
+/// Offers to show the route the trip would take from its original start with no traffic. When
+/// it's shown, draws it underneath the actual route and returns a table comparing the two.
+fn compare_free_flow(
+    ctx: &mut EventCtx,
+    app: &App,
+    trip_id: TripID,
+    open_trip: &mut OpenTrip,
+    details: &mut Details,
+    phases: &[TripPhase],
+    map_for_pathfinding: &Map,
+) -> Vec<Widget> {
+    // Nothing to compare against until the trip has some route
+    if phases.iter().all(|p| p.path.is_none()) {
+        return Vec::new();
+    }
+    let mut col = vec![ctx
+        .style()
+        .btn_outline_light_text(if open_trip.show_free_flow {
+            "hide free-flow route"
+        } else {
+            "compare with fastest free-flow route"
+        })
+        .build_widget(ctx, &format!("compare free-flow route for {}", trip_id))];
+    if !open_trip.show_free_flow {
+        return col;
+    }
+
+    if open_trip.cached_free_flow.is_none() {
+        open_trip.cached_free_flow = Some(free_flow_route(app, trip_id, map_for_pathfinding));
+    }
+    let route = match open_trip.cached_free_flow.as_ref().unwrap() {
+        Ok(route) => route,
+        Err(err) => {
+            col.push(
+                Text::from(Line(format!("No free-flow route: {}", err)))
+                    .wrap_to_pct(ctx, 20)
+                    .draw(ctx),
+            );
+            return col;
+        }
+    };
+    // The free-flow route is wider than the actual route, so where they overlap, it peeks out on
+    // both sides of it.
+    details
+        .unzoomed
+        .push(FREE_FLOW_COLOR.alpha(0.5), route.unzoomed.clone());
+    details
+        .zoomed
+        .push(FREE_FLOW_COLOR.alpha(0.5), route.zoomed.clone());
+
+    let units = &app.opts.units;
+    let dist: Distance = phases
+        .iter()
+        .filter_map(|p| p.path.as_ref())
+        .map(|path| path.total_length())
+        .sum();
+    let mut rows = vec![
+        ("Distance taken", dist.to_string(units)),
+        ("Free-flow distance", route.dist.to_string(units)),
+        (
+            "Difference in distance",
+            if dist >= route.dist {
+                format!("{} longer", (dist - route.dist).to_string(units))
+            } else {
+                format!("{} shorter", (route.dist - dist).to_string(units))
+            },
+        ),
+    ];
+    let departure = app.primary.sim.trip_info(trip_id).departure;
+    if let Some(end_time) = phases.last().and_then(|p| p.end_time) {
+        let time = end_time - departure;
+        rows.push(("Time taken", time.to_string(units)));
+        rows.push(("Free-flow time", route.time.to_string(units)));
+        rows.push((
+            "Difference in time",
+            if time >= route.time {
+                format!("{} slower", (time - route.time).to_string(units))
+            } else {
+                format!("{} faster", (route.time - time).to_string(units))
+            },
+        ));
+    } else if let TripResult::Ok(_) = app.primary.sim.trip_to_agent(trip_id) {
+        rows.push((
+            "Time taken",
+            format!(
+                "{} so far",
+                (app.primary.sim.time() - departure).to_string(units)
+            ),
+        ));
+        rows.push(("Free-flow time", route.time.to_string(units)));
+    }
+    col.extend(make_table(ctx, rows));
+    col
+}
+
+fn free_flow_route(app: &App, id: TripID, map: &Map) -> Result<FreeFlowRoute, String> {
+    // Errors are kept as strings, because OpenTrip has to be cloneable
+    let (path, time) = app
+        .primary
+        .sim
+        .get_trip_free_flow_route(map, id)
+        .map_err(|err| err.to_string())?;
+    let trace = path
+        .trace(map)
+        .ok_or_else(|| "the path can't be drawn".to_string())?;
+    Ok(FreeFlowRoute {
+        unzoomed: trace.make_polygons(Distance::meters(20.0)),
+        zoomed: trace.make_polygons(Distance::meters(3.0)),
+        dist: path.total_length(),
+        time,
+    })
+}
+
+/// Places along a trip that get an icon on the map and a button to warp there
+#[derive(Clone, Copy, PartialEq)]
+enum TripMarker {
+    Start,
+    Goal,
+    ParkingSpot,
+    BoardBus,
+    AlightBus,
+    CurrentPosition,
+}
+
+impl TripMarker {
+    fn icon(self) -> &'static str {
+        match self {
+            TripMarker::Start => "system/assets/timeline/start_pos.svg",
+            TripMarker::Goal => "system/assets/timeline/goal_pos.svg",
+            TripMarker::ParkingSpot => "system/assets/timeline/parking.svg",
+            TripMarker::BoardBus => "system/assets/timeline/waiting_for_bus.svg",
+            TripMarker::AlightBus => "system/assets/timeline/riding_bus.svg",
+            TripMarker::CurrentPosition => "system/assets/timeline/current_pos.svg",
+        }
+    }
+
+    // Also used in the names of the warp buttons
+    fn noun(self) -> &'static str {
+        match self {
+            TripMarker::Start => "start",
+            TripMarker::Goal => "goal",
+            TripMarker::ParkingSpot => "parking spot",
+            TripMarker::BoardBus => "boarding stop",
+            TripMarker::AlightBus => "alighting stop",
+            TripMarker::CurrentPosition => "current position",
+        }
+    }
+
+    fn describe(self, name: &str) -> String {
+        match self {
+            TripMarker::Start => format!("Starts at {}", name),
+            TripMarker::Goal => format!("Ends at {}", name),
+            TripMarker::ParkingSpot => format!("Parks along {}", name),
+            TripMarker::BoardBus => format!("Boards at {}", name),
+            TripMarker::AlightBus => format!("Gets off at {}", name),
+            TripMarker::CurrentPosition => format!("{} is here now", name),
+        }
+    }
+}
+
+/// Draws the marker's icon on the map, and returns a button to warp there. Hovering on the button
+/// makes the icon on the map pulse.
+fn add_marker(
+    ctx: &mut EventCtx,
+    details: &mut Details,
+    trip_id: TripID,
+    marker: TripMarker,
+    id: ID,
+    center: Pt2D,
+    name: String,
+) -> Widget {
+    // A trip might board a few buses
+    let mut action = format!("jump to {} of {}", marker.noun(), trip_id);
+    let mut n = 2;
+    while details.warpers.contains_key(&action) {
+        action = format!("jump to {} #{} of {}", marker.noun(), n, trip_id);
+        n += 1;
+    }
+    details.warpers.insert(action.clone(), id);
+    details.markers.insert(action.clone(), center);
+
+    let icon = GeomBatch::load_svg(ctx, marker.icon())
+        .color(RewriteColor::Change(Color::WHITE, Color::BLACK))
+        .color(RewriteColor::Change(
+            Color::hex("#5B5B5B"),
+            Color::hex("#CC4121"),
+        ));
+    details
+        .unzoomed
+        .append(icon.clone().scale(3.0).centered_on(center));
+    details.zoomed.append(icon.centered_on(center));
+
+    let label = marker.describe(&name);
+    ctx.style()
+        .btn_plain_light_icon_text(marker.icon(), &label)
+        .build_widget(ctx, &action)
+}
+
 fn make_elevation(ctx: &EventCtx, color: Color, walking: bool, path: &Path, map: &Map) -> Widget {
     let mut pts: Vec<(Distance, Distance)> = Vec::new();
     let mut dist = Distance::ZERO;
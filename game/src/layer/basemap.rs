@@ -0,0 +1,593 @@
+use std::cell::RefCell;
+use std::collections::{BTreeMap, BTreeSet};
+use std::f64::consts::PI;
+
+use anyhow::Result;
+
+use geom::{Bounds, Distance, GPSBounds, LonLat, PolyLine, Polygon, Pt2D, Ring};
+use map_gui::options::BasemapOptions;
+use map_gui::tools::PopupMsg;
+use widgetry::{
+    Checkbox, Color, Drawable, EventCtx, GeomBatch, GfxCtx, HorizontalAlignment, Line, Outcome,
+    Panel, Slider, State, StyledButtons, TextExt, UpdateType, VerticalAlignment, Widget,
+};
+
+use crate::app::{App, Transition};
+
+// Each raster tile is normally uploaded as one textured quad. Shaders that can't draw images
+// (WebGL 1.0) get a grid of this many solid-colored cells per side instead.
+const CELLS_PER_TILE: u32 = 64;
+// Forget the least recently drawn tiles past this many
+const MAX_CACHED_TILES: usize = 256;
+// If covering the screen would take more tiles than this, use a coarser zoom level
+const MAX_VISIBLE_TILES: u32 = 64;
+// Decoded tiles uploaded per event, so a burst of them doesn't stall one frame
+const MAX_UPLOADS_PER_EVENT: usize = 8;
+// On the web, there's no background thread to decode in, so only this many tiles are decoded per
+// event, keeping the UI responsive
+#[cfg(target_arch = "wasm32")]
+const MAX_DECODES_PER_EVENT: usize = 2;
+// Web Mercator meters per pixel at the equator, for zoom level 0 and 256 pixel tiles
+const METERS_PER_PIXEL_AT_Z0: f64 = 156_543.03;
+const MAX_LATITUDE: f64 = 85.0511;
+
+const VECTOR_AREA: Color = Color::grey(0.8);
+const VECTOR_LINE: Color = Color::grey(0.55);
+const VECTOR_LINE_WIDTH: Distance = Distance::const_meters(8.0);
+
+/// Familiar context drawn beneath the map, since the abstract rendering stops at the imported
+/// boundary. Everything comes from local files.
+///
+/// MBTiles files aren't supported. They're SQLite databases, and reading them would need a native
+/// SQLite library, which doesn't build for the web. Tools like `mb-util` can extract one into a
+/// directory of tiles instead.
+pub struct Basemap {
+    source: Source,
+}
+
+enum Source {
+    /// Raster tiles in the usual `{z}/{x}/{y}.png` slippy map layout. Whatever's on screen is
+    /// decoded in the background as the camera moves, and drawn once it's ready.
+    Tiles {
+        zooms: Vec<u32>,
+        opacity: f64,
+        cache: RefCell<TileCache>,
+        loader: TileLoader,
+    },
+    /// Lines and areas from a GeoJSON file, all drawn at once
+    Vector(Drawable),
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+struct TileID {
+    z: u32,
+    x: u32,
+    y: u32,
+}
+
+struct TileCache {
+    /// Missing or broken tiles are remembered as None, so they're not tried again. Also tracks the
+    /// last frame each tile was drawn.
+    tiles: BTreeMap<TileID, (Option<Drawable>, usize)>,
+    frame: usize,
+}
+
+/// Decodes tiles off the UI thread, where possible
+struct TileLoader {
+    /// Requested, but not in the cache yet
+    pending: BTreeSet<TileID>,
+    #[cfg(not(target_arch = "wasm32"))]
+    requests: std::sync::mpsc::Sender<TileID>,
+    #[cfg(not(target_arch = "wasm32"))]
+    results: std::sync::mpsc::Receiver<(TileID, Option<image::RgbaImage>)>,
+    #[cfg(target_arch = "wasm32")]
+    dir: String,
+}
+
+impl TileLoader {
+    fn new(dir: String) -> TileLoader {
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            let (requests, rx) = std::sync::mpsc::channel();
+            let (tx, results) = std::sync::mpsc::channel();
+            // Stops when the Basemap is dropped
+            std::thread::spawn(move || {
+                for id in rx {
+                    if tx.send((id, decode_tile(&dir, id))).is_err() {
+                        break;
+                    }
+                }
+            });
+            TileLoader {
+                pending: BTreeSet::new(),
+                requests,
+                results,
+            }
+        }
+
+        #[cfg(target_arch = "wasm32")]
+        {
+            TileLoader {
+                pending: BTreeSet::new(),
+                dir,
+            }
+        }
+    }
+
+    fn request(&mut self, id: TileID) {
+        if self.pending.insert(id) {
+            #[cfg(not(target_arch = "wasm32"))]
+            {
+                // If the thread died, the tile just stays pending
+                let _ = self.requests.send(id);
+            }
+        }
+    }
+
+    /// Tiles decoded since last time
+    fn finished(&mut self) -> Vec<(TileID, Option<image::RgbaImage>)> {
+        #[cfg(not(target_arch = "wasm32"))]
+        let done: Vec<_> = self
+            .results
+            .try_iter()
+            .take(MAX_UPLOADS_PER_EVENT)
+            .collect();
+
+        #[cfg(target_arch = "wasm32")]
+        let done: Vec<_> = self
+            .pending
+            .iter()
+            .take(MAX_DECODES_PER_EVENT)
+            .map(|id| (*id, decode_tile(&self.dir, *id)))
+            .collect();
+
+        for (id, _) in &done {
+            self.pending.remove(id);
+        }
+        done
+    }
+}
+
+impl Basemap {
+    pub fn load(
+        ctx: &mut EventCtx,
+        gps_bounds: &GPSBounds,
+        opts: &BasemapOptions,
+    ) -> Result<Basemap> {
+        let source = opts.source.trim_end_matches('/').to_string();
+        if source.ends_with(".geojson") || source.ends_with(".json") {
+            let batch = load_vector(&source, gps_bounds, opts.opacity)?;
+            return Ok(Basemap {
+                source: Source::Vector(ctx.upload(batch)),
+            });
+        }
+        if source.ends_with(".mbtiles") {
+            bail!(
+                "{} is an MBTiles file, which can't be read directly. Extract it to a directory \
+                 of {{z}}/{{x}}/{{y}}.png tiles first, with a tool like mb-util.",
+                source
+            );
+        }
+        if !std::path::Path::new(&source).is_dir() {
+            bail!("{} isn't a tile directory or a GeoJSON file", source);
+        }
+
+        let mut zooms: Vec<u32> = abstio::list_dir(source.clone())
+            .into_iter()
+            .filter_map(|path| path.rsplit('/').next().and_then(|z| z.parse().ok()))
+            .collect();
+        if zooms.is_empty() {
+            bail!("{} doesn't have any {{z}}/{{x}}/{{y}}.png tiles", source);
+        }
+        zooms.sort();
+        Ok(Basemap {
+            source: Source::Tiles {
+                loader: TileLoader::new(source),
+                zooms,
+                opacity: opts.opacity,
+                cache: RefCell::new(TileCache {
+                    tiles: BTreeMap::new(),
+                    frame: 0,
+                }),
+            },
+        })
+    }
+
+    /// Call this every event, before anything else can move the camera. Starts loading the raster
+    /// tiles newly on screen, and uploads the ones that are ready.
+    pub fn event(&mut self, ctx: &mut EventCtx, gps_bounds: &GPSBounds) {
+        let (zooms, opacity, cache, loader) = match self.source {
+            Source::Vector(_) => {
+                return;
+            }
+            Source::Tiles {
+                ref zooms,
+                opacity,
+                ref mut cache,
+                ref mut loader,
+            } => (zooms, opacity, cache.get_mut(), loader),
+        };
+
+        for id in visible_tiles(
+            zooms,
+            &ctx.canvas.get_screen_bounds(),
+            ctx.canvas.cam_zoom,
+            gps_bounds,
+        ) {
+            if !cache.tiles.contains_key(&id) {
+                loader.request(id);
+            }
+        }
+
+        for (id, img) in loader.finished() {
+            let draw = img.map(|img| upload_tile(ctx, &img, id, gps_bounds, opacity));
+            cache.tiles.insert(id, (draw, cache.frame));
+        }
+        if !loader.pending.is_empty() {
+            ctx.request_update(UpdateType::Game);
+        }
+
+        if cache.tiles.len() > MAX_CACHED_TILES {
+            let mut by_age: Vec<(usize, TileID)> = cache
+                .tiles
+                .iter()
+                .map(|(id, (_, last_drawn))| (*last_drawn, *id))
+                .collect();
+            by_age.sort();
+            let excess = cache.tiles.len() - MAX_CACHED_TILES;
+            for (_, id) in by_age.into_iter().take(excess) {
+                cache.tiles.remove(&id);
+            }
+        }
+    }
+
+    /// Call this before drawing anything else. Raster tiles that are still loading are covered by
+    /// a coarser tile, if one is loaded.
+    pub fn draw(&self, g: &mut GfxCtx, gps_bounds: &GPSBounds) {
+        let (zooms, cache) = match self.source {
+            Source::Vector(ref draw) => {
+                g.redraw(draw);
+                return;
+            }
+            Source::Tiles {
+                ref zooms,
+                ref cache,
+                ..
+            } => (zooms, cache),
+        };
+
+        let mut cache = cache.borrow_mut();
+        cache.frame += 1;
+        let frame = cache.frame;
+        let mut draw_ids = BTreeSet::new();
+        for id in visible_tiles(zooms, &g.get_screen_bounds(), g.canvas.cam_zoom, gps_bounds) {
+            if let Some(id) = std::iter::successors(Some(id), |id| id.parent())
+                .find(|id| matches!(cache.tiles.get(id), Some((Some(_), _))))
+            {
+                draw_ids.insert(id);
+            }
+        }
+        // Coarser tiles go underneath
+        for id in draw_ids {
+            let tile = cache.tiles.get_mut(&id).unwrap();
+            tile.1 = frame;
+            g.redraw(tile.0.as_ref().unwrap());
+        }
+    }
+}
+
+impl TileID {
+    fn parent(&self) -> Option<TileID> {
+        if self.z == 0 {
+            return None;
+        }
+        Some(TileID {
+            z: self.z - 1,
+            x: self.x / 2,
+            y: self.y / 2,
+        })
+    }
+}
+
+/// The tiles covering part of the map, at the zoom level best matching the camera
+fn visible_tiles(
+    zooms: &[u32],
+    bounds: &Bounds,
+    cam_zoom: f64,
+    gps_bounds: &GPSBounds,
+) -> Vec<TileID> {
+    let top_left = Pt2D::new(bounds.min_x, bounds.min_y).to_gps(gps_bounds);
+    let bottom_right = Pt2D::new(bounds.max_x, bounds.max_y).to_gps(gps_bounds);
+    let z = pick_zoom(zooms, top_left, bottom_right, cam_zoom);
+    let (x1, y1) = tile_containing(top_left, z);
+    let (x2, y2) = tile_containing(bottom_right, z);
+    let mut ids = Vec::new();
+    for x in x1..=x2 {
+        for y in y1..=y2 {
+            ids.push(TileID { z, x, y });
+        }
+    }
+    ids
+}
+
+/// The zoom level whose pixels are closest to the screen's, but not so detailed that covering the
+/// screen takes too many tiles. Only zoom levels in the tile directory are considered.
+fn pick_zoom(zooms: &[u32], top_left: LonLat, bottom_right: LonLat, cam_zoom: f64) -> u32 {
+    let lat = ((top_left.y() + bottom_right.y()) / 2.0).to_radians();
+    let ideal = (METERS_PER_PIXEL_AT_Z0 * lat.cos() * cam_zoom)
+        .log2()
+        .round();
+    let mut idx = zooms
+        .iter()
+        .rposition(|z| (*z as f64) <= ideal)
+        .unwrap_or(0);
+    while idx > 0 {
+        let z = zooms[idx];
+        let (x1, y1) = tile_containing(top_left, z);
+        let (x2, y2) = tile_containing(bottom_right, z);
+        if (x2 - x1 + 1) * (y2 - y1 + 1) <= MAX_VISIBLE_TILES {
+            break;
+        }
+        idx -= 1;
+    }
+    zooms[idx]
+}
+
+fn tile_containing(pt: LonLat, z: u32) -> (u32, u32) {
+    let n = 2.0_f64.powi(z as i32);
+    let lat = pt.y().max(-MAX_LATITUDE).min(MAX_LATITUDE).to_radians();
+    let x = (pt.x() + 180.0) / 360.0 * n;
+    let y = (1.0 - (lat.tan() + 1.0 / lat.cos()).ln() / PI) / 2.0 * n;
+    let clamp = |v: f64| v.max(0.0).min(n - 1.0) as u32;
+    (clamp(x), clamp(y))
+}
+
+/// The northwest corner of a fractional tile position
+fn tile_corner(x: f64, y: f64, z: u32) -> LonLat {
+    let n = 2.0_f64.powi(z as i32);
+    let lon = x / n * 360.0 - 180.0;
+    let lat = (PI * (1.0 - 2.0 * y / n)).sinh().atan().to_degrees();
+    LonLat::new(lon, lat)
+}
+
+fn decode_tile(dir: &str, id: TileID) -> Option<image::RgbaImage> {
+    let path = format!("{}/{}/{}/{}.png", dir, id.z, id.x, id.y);
+    if !abstio::file_exists(path.clone()) {
+        return None;
+    }
+    let decoded =
+        abstio::slurp_file(path.clone()).and_then(|bytes| Ok(image::load_from_memory(&bytes)?));
+    match decoded {
+        Ok(img) => Some(img.to_rgba8()),
+        Err(err) => {
+            warn!("Skipping basemap tile {}: {}", path, err);
+            None
+        }
+    }
+}
+
+/// Stretches the image over the tile's area. Web Mercator isn't linear in latitude, but the
+/// difference across a single tile is negligible.
+fn upload_tile(
+    ctx: &EventCtx,
+    img: &image::RgbaImage,
+    id: TileID,
+    gps_bounds: &GPSBounds,
+    opacity: f64,
+) -> Drawable {
+    let nw = tile_corner(id.x as f64, id.y as f64, id.z).to_pt(gps_bounds);
+    let se = tile_corner((id.x + 1) as f64, (id.y + 1) as f64, id.z).to_pt(gps_bounds);
+    match ctx
+        .prerender
+        .upload_raster(img, &Bounds::from(&vec![nw, se]), opacity)
+    {
+        Ok(draw) => draw,
+        Err(_) => ctx.upload(mosaic(img, id, gps_bounds, opacity)),
+    }
+}
+
+/// Approximates a tile with solid-colored cells, for when images can't be drawn directly
+fn mosaic(img: &image::RgbaImage, id: TileID, gps_bounds: &GPSBounds, opacity: f64) -> GeomBatch {
+    let cells = CELLS_PER_TILE.min(img.width()).min(img.height());
+    let mut batch = GeomBatch::new();
+    for cy in 0..cells {
+        for cx in 0..cells {
+            // Average the pixels falling in this cell
+            let (px1, px2) = (cx * img.width() / cells, (cx + 1) * img.width() / cells);
+            let (py1, py2) = (cy * img.height() / cells, (cy + 1) * img.height() / cells);
+            let mut sum = [0.0; 4];
+            for py in py1..py2 {
+                for px in px1..px2 {
+                    for (total, channel) in sum.iter_mut().zip(img.get_pixel(px, py).0.iter()) {
+                        *total += *channel as f64;
+                    }
+                }
+            }
+            let count = ((px2 - px1) * (py2 - py1)) as f64;
+            let alpha = sum[3] / count / 255.0 * opacity;
+            if alpha <= 0.0 {
+                continue;
+            }
+
+            let step = 1.0 / (cells as f64);
+            let nw = tile_corner(
+                id.x as f64 + (cx as f64) * step,
+                id.y as f64 + (cy as f64) * step,
+                id.z,
+            );
+            let se = tile_corner(
+                id.x as f64 + ((cx + 1) as f64) * step,
+                id.y as f64 + ((cy + 1) as f64) * step,
+                id.z,
+            );
+            if let Some(rect) =
+                Polygon::rectangle_two_corners(nw.to_pt(gps_bounds), se.to_pt(gps_bounds))
+            {
+                batch.push(
+                    Color::rgba(
+                        (sum[0] / count) as usize,
+                        (sum[1] / count) as usize,
+                        (sum[2] / count) as usize,
+                        alpha as f32,
+                    ),
+                    rect,
+                );
+            }
+        }
+    }
+    batch
+}
+
+/// Draws lines and polygons from a GeoJSON file. Points and anything else are skipped. Unlike the
+/// map itself, nothing is clipped to the boundary.
+fn load_vector(path: &str, gps_bounds: &GPSBounds, opacity: f64) -> Result<GeomBatch> {
+    let bytes = abstio::slurp_file(path)?;
+    let geojson = std::str::from_utf8(&bytes)?.parse::<geojson::GeoJson>()?;
+    let collection = match geojson {
+        geojson::GeoJson::FeatureCollection(collection) => collection,
+        _ => bail!("{} isn't a GeoJSON FeatureCollection", path),
+    };
+
+    let to_pts = |raw: &Vec<Vec<f64>>| -> Vec<Pt2D> {
+        let gps_pts: Vec<LonLat> = raw.iter().map(|pt| LonLat::new(pt[0], pt[1])).collect();
+        gps_bounds.convert(&gps_pts)
+    };
+    let mut areas = Vec::new();
+    let mut lines = Vec::new();
+    for feature in collection.features {
+        let value = match feature.geometry {
+            Some(geom) => geom.value,
+            None => continue,
+        };
+        match value {
+            geojson::Value::LineString(pts) => {
+                lines.push(to_pts(&pts));
+            }
+            geojson::Value::MultiLineString(list) => {
+                lines.extend(list.iter().map(to_pts));
+            }
+            // Holes are ignored
+            geojson::Value::Polygon(rings) => {
+                areas.extend(rings.get(0).map(to_pts));
+            }
+            geojson::Value::MultiPolygon(polygons) => {
+                areas.extend(polygons.iter().filter_map(|rings| rings.get(0)).map(to_pts));
+            }
+            _ => {}
+        }
+    }
+
+    // Lines go on top of areas
+    let mut batch = GeomBatch::new();
+    for pts in areas {
+        if let Ok(ring) = Ring::new(pts) {
+            batch.push(VECTOR_AREA.alpha(opacity as f32), ring.to_polygon());
+        }
+    }
+    for pts in lines {
+        if let Ok(pl) = PolyLine::deduping_new(pts) {
+            batch.push(
+                VECTOR_LINE.alpha(opacity as f32),
+                pl.make_polygons(VECTOR_LINE_WIDTH),
+            );
+        }
+    }
+    if batch.is_empty() {
+        bail!("{} doesn't have any lines or polygons", path);
+    }
+    Ok(batch)
+}
+
+/// Picks the basemap source and how it's drawn. The choice is remembered between sessions.
+pub struct BasemapSettings {
+    panel: Panel,
+}
+
+impl BasemapSettings {
+    pub fn new(ctx: &mut EventCtx, app: &App) -> Box<dyn State<App>> {
+        let current = app.opts.basemap.clone().unwrap_or(BasemapOptions {
+            source: String::new(),
+            opacity: 0.5,
+            enabled: true,
+        });
+        Box::new(BasemapSettings {
+            panel: Panel::new(Widget::col(vec![
+                Widget::row(vec![
+                    Line("Basemap underlay").small_heading().draw(ctx),
+                    ctx.style().btn_close_widget(ctx),
+                ]),
+                "Draw familiar context beneath the map, from files on this computer."
+                    .draw_text(ctx),
+                Line(
+                    "Use a directory of {z}/{x}/{y}.png raster tiles, or a GeoJSON file with \
+                     lines and polygons, like coastlines and major roads.",
+                )
+                .secondary()
+                .draw(ctx),
+                Widget::row(vec![
+                    "Source:".draw_text(ctx).centered_vert(),
+                    Widget::text_entry(ctx, current.source, true).named("source"),
+                ]),
+                Widget::row(vec![
+                    "Opacity:".draw_text(ctx).centered_vert(),
+                    Slider::area(ctx, 0.15 * ctx.canvas.window_width, current.opacity)
+                        .named("opacity"),
+                ]),
+                Checkbox::checkbox(ctx, "Show the basemap", None, current.enabled),
+                ctx.style().btn_solid_dark_text("Apply").build_def(ctx),
+            ]))
+            .aligned(HorizontalAlignment::Center, VerticalAlignment::Center)
+            .build(ctx),
+        })
+    }
+}
+
+impl State<App> for BasemapSettings {
+    fn event(&mut self, ctx: &mut EventCtx, app: &mut App) -> Transition {
+        if let Outcome::Clicked(x) = self.panel.event(ctx) {
+            match x.as_ref() {
+                "close" => {
+                    return Transition::Pop;
+                }
+                "Apply" => {
+                    let source = self.panel.text_box("source").trim().to_string();
+                    if source.is_empty() {
+                        app.opts.basemap = None;
+                        app.primary.basemap = None;
+                        app.opts.save_persisted();
+                        return Transition::Pop;
+                    }
+                    let opts = BasemapOptions {
+                        source,
+                        opacity: self.panel.slider("opacity").get_percent(),
+                        enabled: self.panel.is_checked("Show the basemap"),
+                    };
+                    let basemap = if opts.enabled {
+                        match Basemap::load(ctx, app.primary.map.get_gps_bounds(), &opts) {
+                            Ok(b) => Some(b),
+                            Err(err) => {
+                                return Transition::Push(PopupMsg::new(
+                                    ctx,
+                                    "Error",
+                                    vec![err.to_string()],
+                                ));
+                            }
+                        }
+                    } else {
+                        None
+                    };
+                    app.primary.basemap = basemap;
+                    app.opts.basemap = Some(opts);
+                    app.opts.save_persisted();
+                    return Transition::Pop;
+                }
+                _ => unreachable!(),
+            }
+        }
+        Transition::Keep
+    }
+
+    fn draw(&self, g: &mut GfxCtx, _: &App) {
+        self.panel.draw(g);
+    }
+}
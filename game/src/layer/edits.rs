@@ -0,0 +1,346 @@
+//! Compares the current map against the unedited one. The diff is worked out from the edits
+//! themselves -- the roads and intersections they touch, and what those looked like originally --
+//! so it's cheap enough to recalculate every time the edits change.
+
+use geom::{Duration, Percent};
+use map_gui::tools::ColorDiscrete;
+use map_gui::ID;
+use map_model::{Direction, EditIntersection, EditRoad, IntersectionID, LaneType, RoadID};
+use widgetry::{
+    Color, Drawable, EventCtx, GfxCtx, HorizontalAlignment, Line, Outcome, Panel, StyledButtons,
+    Text, VerticalAlignment, Widget,
+};
+
+use crate::app::{App, Transition};
+use crate::common::Warping;
+use crate::layer::{header, Layer, LayerOutcome};
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum EditKind {
+    Lanes,
+    SpeedLimit,
+    Access,
+    SignalTiming,
+    IntersectionControl,
+}
+
+impl EditKind {
+    fn all() -> Vec<EditKind> {
+        vec![
+            EditKind::Lanes,
+            EditKind::SpeedLimit,
+            EditKind::Access,
+            EditKind::SignalTiming,
+            EditKind::IntersectionControl,
+        ]
+    }
+
+    pub fn describe(self) -> &'static str {
+        match self {
+            EditKind::Lanes => "lane change",
+            EditKind::SpeedLimit => "speed limit",
+            EditKind::Access => "access restrictions",
+            EditKind::SignalTiming => "signal timing",
+            EditKind::IntersectionControl => "intersection control",
+        }
+    }
+
+    fn color(self) -> Color {
+        match self {
+            EditKind::Lanes => Color::hex("#12409D"),
+            EditKind::SpeedLimit => Color::ORANGE,
+            EditKind::Access => Color::PURPLE,
+            EditKind::SignalTiming => Color::GREEN,
+            EditKind::IntersectionControl => Color::RED,
+        }
+    }
+}
+
+/// One attribute of one object that differs from the unedited map
+pub struct EditDiff {
+    /// The lane or intersection to show. Roads don't have an info panel, so changes to a whole road
+    /// point at one of its lanes.
+    pub id: ID,
+    pub kind: EditKind,
+    pub before: String,
+    pub after: String,
+}
+
+impl EditDiff {
+    pub fn before_after(&self) -> String {
+        format!("{} → {}", self.before, self.after)
+    }
+}
+
+/// Everything that differs from the unedited map, roads first
+pub fn all_diffs(app: &App) -> Vec<EditDiff> {
+    let edits = app.primary.map.get_edits();
+    let mut diffs = Vec::new();
+    for r in &edits.changed_roads {
+        diffs.extend(road_diffs(app, *r));
+    }
+    for i in edits.original_intersections.keys() {
+        diffs.extend(intersection_diff(app, *i));
+    }
+    diffs
+}
+
+pub fn road_diffs(app: &App, r: RoadID) -> Vec<EditDiff> {
+    let map = &app.primary.map;
+    let road = map.get_r(r);
+    let orig = EditRoad::get_orig_from_osm(road, map.get_config());
+    let current = map.get_r_edit(r);
+    if orig == current {
+        return Vec::new();
+    }
+    let lanes = road.lanes_ltr();
+    let mut diffs = Vec::new();
+
+    if orig.lanes_ltr.len() != current.lanes_ltr.len() {
+        diffs.push(EditDiff {
+            id: ID::Lane(lanes[0].0),
+            kind: EditKind::Lanes,
+            before: format!("{} lanes", orig.lanes_ltr.len()),
+            after: format!("{} lanes", current.lanes_ltr.len()),
+        });
+    } else {
+        for (idx, (before, after)) in orig
+            .lanes_ltr
+            .iter()
+            .zip(current.lanes_ltr.iter())
+            .enumerate()
+        {
+            if before != after {
+                diffs.push(EditDiff {
+                    id: ID::Lane(lanes[idx].0),
+                    kind: EditKind::Lanes,
+                    before: describe_lane(*before),
+                    after: describe_lane(*after),
+                });
+            }
+        }
+    }
+
+    if orig.speed_limit != current.speed_limit {
+        diffs.push(EditDiff {
+            id: ID::Lane(lanes[0].0),
+            kind: EditKind::SpeedLimit,
+            before: orig.speed_limit.to_string(&app.opts.units),
+            after: current.speed_limit.to_string(&app.opts.units),
+        });
+    }
+    if orig.access_restrictions != current.access_restrictions {
+        diffs.push(EditDiff {
+            id: ID::Lane(lanes[0].0),
+            kind: EditKind::Access,
+            before: describe_access(&orig),
+            after: describe_access(&current),
+        });
+    }
+    diffs
+}
+
+/// None if the intersection wasn't edited, or was edited back to the way it started
+pub fn intersection_diff(app: &App, i: IntersectionID) -> Option<EditDiff> {
+    let map = &app.primary.map;
+    let orig = map.get_edits().original_intersections.get(&i)?;
+    let current = map.get_i_edit(i);
+    if orig == &current {
+        return None;
+    }
+    let kind = match (orig, &current) {
+        (EditIntersection::TrafficSignal(_), EditIntersection::TrafficSignal(_)) => {
+            EditKind::SignalTiming
+        }
+        _ => EditKind::IntersectionControl,
+    };
+    Some(EditDiff {
+        id: ID::Intersection(i),
+        kind,
+        before: describe_intersection(orig),
+        after: describe_intersection(&current),
+    })
+}
+
+fn describe_lane((lt, dir): (LaneType, Direction)) -> String {
+    format!("{}, {}", lt.short_name(), dir)
+}
+
+fn describe_access(road: &EditRoad) -> String {
+    let restrictions = &road.access_restrictions;
+    let mut parts = Vec::new();
+    let banned = restrictions.allow_through_traffic.complement().len();
+    if banned > 0 {
+        parts.push(format!("{} modes can't pass through", banned));
+    }
+    if let Some(cap) = restrictions.cap_vehicles_per_hour {
+        parts.push(format!("cap of {} vehicles per hour", cap));
+    }
+    if parts.is_empty() {
+        "unrestricted".to_string()
+    } else {
+        parts.join(", ")
+    }
+}
+
+fn describe_intersection(edit: &EditIntersection) -> String {
+    match edit {
+        EditIntersection::StopSign(ss) => {
+            let stops = ss.roads.values().filter(|r| r.must_stop).count();
+            format!(
+                "stop sign ({} of {} approaches stop)",
+                stops,
+                ss.roads.len()
+            )
+        }
+        EditIntersection::TrafficSignal(ts) => {
+            // Only the first plan is ever edited in the game
+            let stages = &ts.plans[0].stages;
+            let cycle: usize = stages
+                .iter()
+                .map(|s| match s.stage_type {
+                    traffic_signal_data::StageType::Fixed(x) => x,
+                    traffic_signal_data::StageType::Variable(min, _, _) => min,
+                })
+                .sum();
+            format!(
+                "signal ({} stages, {} cycle)",
+                stages.len(),
+                Duration::seconds(cycle as f64)
+            )
+        }
+        EditIntersection::Closed => "closed".to_string(),
+    }
+}
+
+/// Highlights everything edited, colored by the kind of change, with a list of the changes that
+/// jumps to each one.
+pub struct EditsDiff {
+    panel: Panel,
+    unzoomed: Drawable,
+    zoomed: Drawable,
+    ids: Vec<ID>,
+    // Edits name, number of commands
+    key: (String, usize),
+}
+
+impl Layer for EditsDiff {
+    fn name(&self) -> Option<&'static str> {
+        Some("edits diff")
+    }
+    fn event(
+        &mut self,
+        ctx: &mut EventCtx,
+        app: &mut App,
+        minimap: &Panel,
+    ) -> Option<LayerOutcome> {
+        if self.key != edits_key(app) {
+            *self = EditsDiff::new(ctx, app);
+        }
+
+        self.panel.align_above(ctx, minimap);
+        if let Outcome::Clicked(x) = self.panel.event(ctx) {
+            if x == "close" {
+                return Some(LayerOutcome::Close);
+            }
+            let idx = x.strip_prefix("edit #").unwrap().parse::<usize>().unwrap();
+            let id = self.ids[idx].clone();
+            if let Some(pt) = app.primary.canonical_point(id.clone()) {
+                return Some(LayerOutcome::Transition(Transition::Push(Warping::new(
+                    ctx,
+                    pt,
+                    Some(10.0),
+                    Some(id),
+                    &mut app.primary,
+                ))));
+            }
+        }
+        None
+    }
+    fn draw(&self, g: &mut GfxCtx, app: &App) {
+        self.panel.draw(g);
+        if g.canvas.cam_zoom < app.opts.min_zoom_for_detail {
+            g.redraw(&self.unzoomed);
+        } else {
+            g.redraw(&self.zoomed);
+        }
+    }
+    fn draw_minimap(&self, g: &mut GfxCtx) {
+        g.redraw(&self.unzoomed);
+    }
+}
+
+impl EditsDiff {
+    pub fn new(ctx: &mut EventCtx, app: &App) -> EditsDiff {
+        let map = &app.primary.map;
+        let diffs = all_diffs(app);
+
+        let mut colorer = ColorDiscrete::new(
+            app,
+            EditKind::all()
+                .into_iter()
+                .map(|k| (k.describe(), k.color()))
+                .collect(),
+        );
+        let mut col = vec![
+            header(ctx, &format!("Edits diff ({})", map.get_edits().edits_name)),
+            Text::from(Line(format!(
+                "{} changes from the original map",
+                diffs.len()
+            )))
+            .draw(ctx),
+        ];
+        let mut ids = Vec::new();
+        for (idx, diff) in diffs.iter().enumerate() {
+            let label = match diff.id {
+                ID::Lane(l) => {
+                    if diff.kind == EditKind::Lanes {
+                        colorer.add_l(l, diff.kind.describe());
+                    } else {
+                        colorer.add_r(map.get_l(l).parent, diff.kind.describe());
+                    }
+                    map.get_parent(l).get_name(app.opts.language.as_ref())
+                }
+                ID::Intersection(i) => {
+                    colorer.add_i(i, diff.kind.describe());
+                    i.to_string()
+                }
+                _ => unreachable!(),
+            };
+            col.push(Widget::row(vec![
+                Line(diff.kind.describe())
+                    .fg(diff.kind.color())
+                    .draw(ctx)
+                    .centered_vert(),
+                ctx.style()
+                    .btn_plain_light_text(&label)
+                    .build_widget(ctx, &format!("edit #{}", idx)),
+            ]));
+            col.push(
+                Line(diff.before_after())
+                    .secondary()
+                    .draw(ctx)
+                    .margin_below(5),
+            );
+            ids.push(diff.id.clone());
+        }
+
+        let (unzoomed, zoomed, legend) = colorer.build(ctx);
+        col.insert(2, legend);
+        EditsDiff {
+            panel: Panel::new(Widget::col(col))
+                .aligned(HorizontalAlignment::Right, VerticalAlignment::Center)
+                .max_size(Percent::int(30), Percent::int(60))
+                .build(ctx),
+            unzoomed,
+            zoomed,
+            ids,
+            key: edits_key(app),
+        }
+    }
+}
+
+fn edits_key(app: &App) -> (String, usize) {
+    let edits = app.primary.map.get_edits();
+    (edits.edits_name.clone(), edits.commands.len())
+}
@@ -0,0 +1,277 @@
+use std::collections::HashSet;
+
+use aabb_quadtree::QuadTree;
+
+use geom::{Distance, PolyLine, Pt2D};
+use map_model::{osm, IntersectionID, Map, RoadID};
+use widgetry::{
+    Choice, Color, Drawable, EventCtx, GeomBatch, GfxCtx, HorizontalAlignment, Line, Outcome,
+    Panel, Text, TextExt, VerticalAlignment, Widget,
+};
+
+use crate::app::App;
+use crate::layer::{header, Layer, LayerOutcome};
+
+/// Labels aren't drawn when zoomed out further than this
+const MIN_ZOOM: f64 = 0.5;
+/// Zooming in past this many doublings of MIN_ZOOM doesn't place the labels again
+const MAX_ZOOM_LEVEL: i32 = 7;
+/// The space between repeated labels of one long street, in pixels
+const SPACING: f64 = 600.0;
+
+/// Draws street names along roads, for readable screenshots.
+pub struct StreetLabels {
+    panel: Panel,
+    /// Text height in pixels
+    size: f64,
+    /// None when zoomed out too far. Otherwise, how many times the zoom has doubled past
+    /// MIN_ZOOM.
+    zoom_level: Option<i32>,
+    draw: Drawable,
+}
+
+impl Layer for StreetLabels {
+    fn name(&self) -> Option<&'static str> {
+        Some("street names")
+    }
+    fn event(
+        &mut self,
+        ctx: &mut EventCtx,
+        app: &mut App,
+        minimap: &Panel,
+    ) -> Option<LayerOutcome> {
+        self.panel.align_above(ctx, minimap);
+        match self.panel.event(ctx) {
+            Outcome::Clicked(x) => match x.as_ref() {
+                "close" => {
+                    return Some(LayerOutcome::Close);
+                }
+                _ => unreachable!(),
+            },
+            Outcome::Changed => {
+                self.size = self.panel.dropdown_value("size");
+                self.place_labels(ctx, app);
+            }
+            _ => {}
+        }
+
+        if zoom_level(ctx.canvas.cam_zoom) != self.zoom_level {
+            self.place_labels(ctx, app);
+        }
+        None
+    }
+    fn draw(&self, g: &mut GfxCtx, _: &App) {
+        self.panel.draw(g);
+        g.redraw(&self.draw);
+    }
+    fn draw_minimap(&self, _: &mut GfxCtx) {}
+}
+
+impl StreetLabels {
+    pub fn new(ctx: &mut EventCtx, app: &App) -> StreetLabels {
+        let size = 16.0;
+        let panel = Panel::new(Widget::col(vec![
+            header(ctx, "Street names"),
+            Widget::row(vec![
+                "Text size:".draw_text(ctx).centered_vert(),
+                Widget::dropdown(
+                    ctx,
+                    "size",
+                    size,
+                    vec![
+                        Choice::new("small", 12.0),
+                        Choice::new("medium", 16.0),
+                        Choice::new("large", 22.0),
+                        Choice::new("huge", 30.0),
+                    ],
+                ),
+            ]),
+        ]))
+        .aligned(HorizontalAlignment::Right, VerticalAlignment::Center)
+        .build(ctx);
+
+        let mut layer = StreetLabels {
+            panel,
+            size,
+            zoom_level: None,
+            draw: Drawable::empty(ctx),
+        };
+        layer.place_labels(ctx, app);
+        layer
+    }
+
+    fn place_labels(&mut self, ctx: &mut EventCtx, app: &App) {
+        let map = &app.primary.map;
+        self.zoom_level = zoom_level(ctx.canvas.cam_zoom);
+        let zoom = match self.zoom_level {
+            Some(level) => MIN_ZOOM * 2.0_f64.powi(level),
+            None => {
+                self.draw = Drawable::empty(ctx);
+                return;
+            }
+        };
+        // Small streets would just be clutter when zoomed out
+        let min_rank = if zoom < app.opts.min_zoom_for_detail {
+            osm::RoadRank::Arterial
+        } else {
+            osm::RoadRank::Local
+        };
+
+        let mut streets = find_streets(map, app.opts.language.as_ref(), min_rank);
+        // Bridges win over the roads below them, then bigger roads win over smaller
+        streets.sort_by(|a, b| {
+            (b.zorder, b.rank)
+                .cmp(&(a.zorder, a.rank))
+                .then_with(|| b.pl.length().partial_cmp(&a.pl.length()).unwrap())
+        });
+
+        let mut batch = GeomBatch::new();
+        let mut placed: QuadTree<()> = QuadTree::default(map.get_bounds().as_bbox());
+        for street in streets {
+            // Measure the text at the default size, then scale it to the requested size on screen
+            let dims = Text::from(Line(&street.name)).render(ctx).get_dims();
+            let scale = self.size / dims.height / zoom;
+            let text_length = Distance::meters(dims.width * scale);
+            let text_height = Distance::meters(dims.height * scale);
+            let padding = text_height / 2.0;
+            let length = street.pl.length();
+            if length < text_length + padding * 2.0 {
+                continue;
+            }
+
+            // Short streets get one label in the middle. Longer ones get one label every so often.
+            let spacing = Distance::meters(SPACING / zoom);
+            let num_labels = (length / spacing).floor().max(1.0) as usize;
+            let step = length / (num_labels as f64);
+            for idx in 0..num_labels {
+                let middle = step * (idx as f64) + step / 2.0;
+                let start = middle - text_length / 2.0 - padding;
+                let end = middle + text_length / 2.0 + padding;
+                if start < Distance::ZERO || end > length {
+                    continue;
+                }
+                let slice = match street.pl.maybe_exact_slice(start, end) {
+                    Ok(pl) => pl,
+                    Err(_) => continue,
+                };
+                let background = slice.make_polygons(text_height * 1.4);
+                let bounds = background.get_bounds().as_bbox();
+                if !placed.query(bounds).is_empty() {
+                    continue;
+                }
+                placed.insert_with_box((), bounds);
+
+                // Keep the text from being upside down
+                let slice = if slice.last_pt().x() < slice.first_pt().x() {
+                    slice.reversed()
+                } else {
+                    slice
+                };
+                batch.push(Color::WHITE.alpha(0.8), background);
+                batch.append(Line(&street.name).fg(Color::BLACK).render_curvey(
+                    ctx.prerender,
+                    &slice,
+                    scale,
+                ));
+            }
+        }
+        self.draw = ctx.upload(batch);
+    }
+}
+
+/// Labels are placed again each time the zoom doubles or halves. Past that, the text would look
+/// noticeably bigger or smaller than the chosen size.
+fn zoom_level(zoom: f64) -> Option<i32> {
+    if zoom < MIN_ZOOM {
+        return None;
+    }
+    Some(((zoom / MIN_ZOOM).log2().floor() as i32).min(MAX_ZOOM_LEVEL))
+}
+
+/// A named street, possibly spanning many roads
+struct Street {
+    name: String,
+    rank: osm::RoadRank,
+    zorder: isize,
+    pl: PolyLine,
+}
+
+/// Joins consecutive roads with the same name, so a long street isn't labeled on every block. A
+/// street only continues through an intersection if exactly one other road there has the same name
+/// and height.
+fn find_streets(map: &Map, lang: Option<&String>, min_rank: osm::RoadRank) -> Vec<Street> {
+    let name = |r: RoadID| map.get_r(r).get_name(lang);
+    let mut visited: HashSet<RoadID> = HashSet::new();
+    let mut streets = Vec::new();
+    for r in map.all_roads() {
+        if visited.contains(&r.id) || r.is_light_rail() || r.get_rank() < min_rank {
+            continue;
+        }
+        visited.insert(r.id);
+        let street_name = name(r.id);
+        if street_name == "???" {
+            continue;
+        }
+
+        let next = |from: RoadID, i: IntersectionID, visited: &HashSet<RoadID>| {
+            let candidates: Vec<RoadID> = map
+                .get_i(i)
+                .roads
+                .iter()
+                .cloned()
+                .filter(|other| {
+                    *other != from
+                        && !visited.contains(other)
+                        && map.get_r(*other).zorder == r.zorder
+                        && name(*other) == street_name
+                })
+                .collect();
+            if candidates.len() == 1 {
+                Some(candidates[0])
+            } else {
+                None
+            }
+        };
+
+        // Walk forwards from the end of the road, then backwards from the start
+        let mut pts: Vec<Pt2D> = r.center_pts.points().clone();
+        let (mut from, mut i) = (r.id, r.dst_i);
+        while let Some(other) = next(from, i, &visited) {
+            visited.insert(other);
+            let road = map.get_r(other);
+            if road.src_i == i {
+                pts.extend(road.center_pts.points().clone());
+                i = road.dst_i;
+            } else {
+                pts.extend(road.center_pts.reversed().points().clone());
+                i = road.src_i;
+            }
+            from = other;
+        }
+        let (mut from, mut i) = (r.id, r.src_i);
+        while let Some(other) = next(from, i, &visited) {
+            visited.insert(other);
+            let road = map.get_r(other);
+            let mut before = if road.dst_i == i {
+                i = road.src_i;
+                road.center_pts.points().clone()
+            } else {
+                i = road.dst_i;
+                road.center_pts.reversed().points().clone()
+            };
+            before.extend(pts);
+            pts = before;
+            from = other;
+        }
+
+        if let Ok(pl) = PolyLine::deduping_new(pts) {
+            streets.push(Street {
+                name: street_name,
+                rank: r.get_rank(),
+                zorder: r.zorder,
+                pl,
+            });
+        }
+    }
+    streets
+}
@@ -8,11 +8,15 @@ use widgetry::{
 use crate::app::{App, Transition};
 use crate::sandbox::dashboards;
 
+pub mod basemap;
+pub mod edits;
 mod elevation;
 pub mod favorites;
+mod labels;
 pub mod map;
 mod pandemic;
 mod parking;
+pub mod pedestrians;
 mod population;
 pub mod traffic;
 pub mod transit;
@@ -51,6 +55,8 @@ impl dyn Layer {
 pub enum LayerOutcome {
     Close,
     Replace(Box<dyn Layer>),
+    /// Keep the layer, but do something outside of it
+    Transition(Transition),
 }
 
 // TODO Maybe overkill, but could embed a minimap and preview the layer on hover
@@ -75,6 +81,10 @@ impl PickLayer {
                 app.primary.layer = Some(l);
                 return None;
             }
+            Some(LayerOutcome::Transition(t)) => {
+                app.primary.layer = Some(layer);
+                return Some(t);
+            }
             None => {}
         }
         app.primary.layer = Some(layer);
@@ -108,17 +118,22 @@ impl PickLayer {
                     "Traffic".draw_text(ctx),
                     btn("delay", Key::D),
                     btn("throughput", Key::T),
+                    btn("recent throughput", Key::H),
                     btn("traffic jams", Key::J),
+                    btn("pedestrian crowding", Key::W),
                 ]),
                 Widget::col(vec![
                     "Map".draw_text(ctx),
                     btn("map edits", Key::E),
+                    btn("edits diff", Key::G),
                     btn("parking occupancy", Key::P),
                     btn("bike network", Key::B),
                     btn("transit network", Key::U),
                     btn("population map", Key::X),
                     btn("no sidewalks", Key::S),
                     btn("favorite buildings", Key::F),
+                    btn("street names", Key::K),
+                    btn("basemap underlay", Key::I),
                 ]),
             ])
             .evenly_spaced(),
@@ -183,12 +198,18 @@ impl State<App> for PickLayer {
                 "map edits" => {
                     app.primary.layer = Some(Box::new(map::Static::edits(ctx, app)));
                 }
+                "edits diff" => {
+                    app.primary.layer = Some(Box::new(edits::EditsDiff::new(ctx, app)));
+                }
                 "no sidewalks" => {
                     app.primary.layer = Some(Box::new(map::Static::no_sidewalks(ctx, app)));
                 }
                 "favorite buildings" => {
                     app.primary.layer = Some(Box::new(favorites::ShowFavorites::new(ctx, app)));
                 }
+                "street names" => {
+                    app.primary.layer = Some(Box::new(labels::StreetLabels::new(ctx, app)));
+                }
                 "pandemic model" => {
                     app.primary.layer = Some(Box::new(pandemic::Pandemic::new(
                         ctx,
@@ -213,6 +234,10 @@ impl State<App> for PickLayer {
                 "parking efficiency" => {
                     app.primary.layer = Some(Box::new(parking::Efficiency::new(ctx, app)));
                 }
+                "pedestrian crowding" => {
+                    app.primary.layer =
+                        Some(Box::new(pedestrians::PedestrianCrowding::new(ctx, app)));
+                }
                 "population map" => {
                     app.primary.layer = Some(Box::new(population::PopulationMap::new(
                         ctx,
@@ -229,6 +254,9 @@ impl State<App> for PickLayer {
                         AgentType::all().into_iter().collect(),
                     )));
                 }
+                "recent throughput" => {
+                    app.primary.layer = Some(Box::new(traffic::RecentThroughput::new(ctx, app)));
+                }
                 "traffic jams" => {
                     app.primary.layer = Some(Box::new(traffic::TrafficJams::new(ctx, app)));
                 }
@@ -243,6 +271,9 @@ impl State<App> for PickLayer {
                 "commuter patterns" => {
                     return Transition::Replace(dashboards::CommuterPatterns::new(ctx, app));
                 }
+                "basemap underlay" => {
+                    return Transition::Replace(basemap::BasemapSettings::new(ctx, app));
+                }
                 _ => unreachable!(),
             },
             _ => {
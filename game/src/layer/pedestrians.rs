@@ -0,0 +1,318 @@
+use std::collections::BTreeMap;
+
+use abstutil::prettyprint_usize;
+use geom::{Duration, Time};
+use map_gui::tools::{ColorLegend, ColorNetwork};
+use map_gui::ID;
+use map_model::{IntersectionID, LaneID, RoadID, SIDEWALK_THICKNESS};
+use sim::{AgentType, RECENT_SIDEWALK_WINDOW};
+use widgetry::{
+    Color, Drawable, EventCtx, GfxCtx, HorizontalAlignment, Line, Outcome, Panel, Text,
+    VerticalAlignment, Widget,
+};
+
+use crate::app::App;
+use crate::common::launch_info_panel;
+use crate::info::{DataOptions, Tab};
+use crate::layer::{header, Layer, LayerOutcome};
+
+/// Sidewalks are colored by how many people stepped onto them during the last
+/// RECENT_SIDEWALK_WINDOW, per meter of sidewalk. These are the upper limits of every band but the
+/// last.
+const SIDEWALK_DENSITY_BANDS: [f64; 3] = [0.1, 0.3, 0.6];
+/// Crosswalks are colored by how long people waited to start crossing, on average. These are the
+/// upper limits of every band but the last.
+const CROSSWALK_WAIT_BANDS: [Duration; 3] = [
+    Duration::const_seconds(10.0),
+    Duration::const_seconds(30.0),
+    Duration::const_seconds(60.0),
+];
+
+/// Pedestrian level of service: sidewalks colored by how crowded they've recently been, and
+/// crosswalks at traffic signals by how long people recently waited to cross.
+pub struct PedestrianCrowding {
+    time: Time,
+    /// For sidewalks that anybody recently stepped onto, how many people and how many per meter
+    sidewalks: BTreeMap<LaneID, (usize, f64)>,
+    /// The sidewalk under the cursor
+    hovered: Option<LaneID>,
+    tooltip: Option<Text>,
+    unzoomed: Drawable,
+    zoomed: Drawable,
+    panel: Panel,
+}
+
+impl Layer for PedestrianCrowding {
+    fn name(&self) -> Option<&'static str> {
+        Some("pedestrian crowding")
+    }
+    fn event(
+        &mut self,
+        ctx: &mut EventCtx,
+        app: &mut App,
+        minimap: &Panel,
+    ) -> Option<LayerOutcome> {
+        let now = app.primary.sim.time();
+        if now < self.time || now - self.time >= Duration::minutes(1) {
+            *self = PedestrianCrowding::new(ctx, app);
+        }
+
+        if ctx.redo_mouseover() {
+            self.hovered = if ctx.canvas.cam_zoom < app.opts.min_zoom_for_detail {
+                // Sidewalks can't be picked out when unzoomed, so use the busiest one on the road
+                match app.mouseover_unzoomed_roads_and_intersections(ctx) {
+                    Some(ID::Road(r)) => self.busiest_sidewalk(app, r),
+                    _ => None,
+                }
+            } else {
+                match app.primary.current_selection {
+                    Some(ID::Lane(l)) if self.sidewalks.contains_key(&l) => Some(l),
+                    _ => None,
+                }
+            };
+            self.tooltip = self.hovered.map(|l| {
+                let (count, density) = self.sidewalks[&l];
+                Text::from_multiline(vec![
+                    Line(format!("{:.2} people per meter", density)),
+                    Line(format!(
+                        "{} people in the last {}",
+                        prettyprint_usize(count),
+                        RECENT_SIDEWALK_WINDOW.to_string(&app.opts.units)
+                    ))
+                    .secondary(),
+                ])
+            });
+        }
+        if let Some(l) = self.hovered {
+            if ctx.normal_left_click() {
+                return Some(LayerOutcome::Transition(launch_info_panel(
+                    Tab::LaneTraffic(l, DataOptions::new()),
+                )));
+            }
+        }
+
+        self.panel.align_above(ctx, minimap);
+        if let Outcome::Clicked(x) = self.panel.event(ctx) {
+            match x.as_ref() {
+                "close" => {
+                    return Some(LayerOutcome::Close);
+                }
+                _ => unreachable!(),
+            }
+        }
+        None
+    }
+    fn draw(&self, g: &mut GfxCtx, app: &App) {
+        self.panel.draw(g);
+        if g.canvas.cam_zoom < app.opts.min_zoom_for_detail {
+            g.redraw(&self.unzoomed);
+        } else {
+            g.redraw(&self.zoomed);
+        }
+        if let Some(ref txt) = self.tooltip {
+            g.draw_mouse_tooltip(txt.clone());
+        }
+    }
+    fn draw_minimap(&self, g: &mut GfxCtx) {
+        g.redraw(&self.unzoomed);
+    }
+}
+
+impl PedestrianCrowding {
+    pub fn new(ctx: &mut EventCtx, app: &App) -> PedestrianCrowding {
+        let map = &app.primary.map;
+        let now = app.primary.sim.time();
+        let mut colorer = ColorNetwork::new(app);
+
+        let mut counts: BTreeMap<LaneID, usize> = BTreeMap::new();
+        for (t, l) in &app.primary.sim.get_analytics().recent_sidewalk_entries {
+            if now - *t <= RECENT_SIDEWALK_WINDOW {
+                *counts.entry(*l).or_insert(0) += 1;
+            }
+        }
+        let mut sidewalks = BTreeMap::new();
+        for (l, count) in counts {
+            let lane = map.get_l(l);
+            let density = (count as f64) / lane.length().inner_meters();
+            let color = band(app, band_idx(density, &SIDEWALK_DENSITY_BANDS));
+            colorer
+                .unzoomed
+                .push(color, lane.lane_center_pts.make_polygons(lane.width * 3.0));
+            colorer.zoomed.push(
+                color.alpha(0.6),
+                lane.lane_center_pts.make_polygons(lane.width),
+            );
+            sidewalks.insert(l, (count, density));
+        }
+
+        let waits = {
+            let mut cache = app.primary.caches.crosswalk_waits.borrow_mut();
+            let cache = cache.get_or_insert_with(CrosswalkWaits::new);
+            cache.update(app);
+            cache.average_by_crossing(app)
+        };
+        for ((i, idx), wait) in waits {
+            let movement = match map
+                .maybe_get_traffic_signal(i)
+                .and_then(|signal| signal.movements.values().nth(idx))
+            {
+                Some(m) => m,
+                None => continue,
+            };
+            let color = band(app, band_idx(wait, &CROSSWALK_WAIT_BANDS));
+            colorer
+                .unzoomed
+                .push(color, movement.geom.make_polygons(SIDEWALK_THICKNESS * 3.0));
+            colorer.zoomed.push(
+                color.alpha(0.8),
+                movement.geom.make_polygons(SIDEWALK_THICKNESS),
+            );
+        }
+        let (unzoomed, zoomed) = colorer.build(ctx);
+
+        let units = &app.opts.units;
+        let mut sidewalk_legend = vec![format!("under {}", SIDEWALK_DENSITY_BANDS[0])];
+        for pair in SIDEWALK_DENSITY_BANDS.windows(2) {
+            sidewalk_legend.push(format!("{} - {}", pair[0], pair[1]));
+        }
+        sidewalk_legend.push(format!("over {}", SIDEWALK_DENSITY_BANDS[2]));
+        let mut wait_legend = vec![format!(
+            "under {}",
+            CROSSWALK_WAIT_BANDS[0].to_string(units)
+        )];
+        for pair in CROSSWALK_WAIT_BANDS.windows(2) {
+            wait_legend.push(format!(
+                "{} - {}",
+                pair[0].to_string(units),
+                pair[1].to_string(units)
+            ));
+        }
+        wait_legend.push(format!("over {}", CROSSWALK_WAIT_BANDS[2].to_string(units)));
+
+        let mut col = vec![
+            header(ctx, "Pedestrian crowding"),
+            Text::from(
+                Line(format!(
+                    "Over the {} before {}",
+                    RECENT_SIDEWALK_WINDOW.to_string(units),
+                    now.ampm_tostring()
+                ))
+                .secondary(),
+            )
+            .draw(ctx),
+            Line("People per meter of sidewalk").draw(ctx),
+        ];
+        for (idx, label) in sidewalk_legend.into_iter().enumerate() {
+            col.push(ColorLegend::row(ctx, band(app, idx), label));
+        }
+        col.push(Line("Average wait at crosswalks").draw(ctx));
+        for (idx, label) in wait_legend.into_iter().enumerate() {
+            col.push(ColorLegend::row(ctx, band(app, idx), label));
+        }
+
+        PedestrianCrowding {
+            time: now,
+            sidewalks,
+            hovered: None,
+            tooltip: None,
+            unzoomed,
+            zoomed,
+            panel: Panel::new(Widget::col(col))
+                .aligned(HorizontalAlignment::Right, VerticalAlignment::Center)
+                .build(ctx),
+        }
+    }
+
+    fn busiest_sidewalk(&self, app: &App, r: RoadID) -> Option<LaneID> {
+        app.primary
+            .map
+            .get_r(r)
+            .lanes_ltr()
+            .into_iter()
+            .filter_map(|(l, _, _)| self.sidewalks.get(&l).map(|(count, _)| (*count, l)))
+            .max()
+            .map(|(_, l)| l)
+    }
+}
+
+/// The color of one of the four bands, from best to worst
+fn band(app: &App, idx: usize) -> Color {
+    app.cs.good_to_bad_red.eval((idx as f64) / 3.0)
+}
+
+/// Which of the four bands a value belongs in
+fn band_idx<T: PartialOrd>(value: T, limits: &[T]) -> usize {
+    limits
+        .iter()
+        .position(|limit| value <= *limit)
+        .unwrap_or(limits.len())
+}
+
+/// How long pedestrians recently waited at the crosswalks of traffic signals. Only the delays
+/// recorded since the last update are looked at, and this lives in `PerMap`, so toggling the layer
+/// doesn't start over.
+pub struct CrosswalkWaits {
+    time: Time,
+    /// For every traffic signal, how many of its delays have been looked at
+    seen: BTreeMap<IntersectionID, usize>,
+    /// Pedestrian delays in the last RECENT_SIDEWALK_WINDOW, as (when, signal, movement index,
+    /// delay)
+    recent: Vec<(Time, IntersectionID, u8, Duration)>,
+}
+
+impl CrosswalkWaits {
+    fn new() -> CrosswalkWaits {
+        CrosswalkWaits {
+            time: Time::START_OF_DAY,
+            seen: BTreeMap::new(),
+            recent: Vec::new(),
+        }
+    }
+
+    fn update(&mut self, app: &App) {
+        let now = app.primary.sim.time();
+        let delays = &app.primary.sim.get_analytics().intersection_delays;
+        // The simulation was reset or a savestate loaded
+        if now < self.time
+            || self
+                .seen
+                .iter()
+                .any(|(i, n)| delays.get(i).map(|list| list.len()).unwrap_or(0) < *n)
+        {
+            *self = CrosswalkWaits::new();
+        }
+
+        for (i, list) in delays {
+            let seen = self.seen.entry(*i).or_insert(0);
+            for (idx, t, dt, agent_type) in &list[*seen..] {
+                if *agent_type == AgentType::Pedestrian {
+                    self.recent.push((*t, *i, *idx, *dt));
+                }
+            }
+            *seen = list.len();
+        }
+        self.recent
+            .retain(|(t, _, _, _)| now - *t <= RECENT_SIDEWALK_WINDOW);
+        self.time = now;
+    }
+
+    /// The average wait per crosswalk, keyed by signal and movement index. Crosswalk movements
+    /// only ever have pedestrians.
+    fn average_by_crossing(&self, app: &App) -> BTreeMap<(IntersectionID, usize), Duration> {
+        let now = app.primary.sim.time();
+        let mut totals: BTreeMap<(IntersectionID, usize), (Duration, usize)> = BTreeMap::new();
+        for (t, i, idx, dt) in &self.recent {
+            if now - *t <= RECENT_SIDEWALK_WINDOW {
+                let total = totals
+                    .entry((*i, *idx as usize))
+                    .or_insert((Duration::ZERO, 0));
+                total.0 += *dt;
+                total.1 += 1;
+            }
+        }
+        totals
+            .into_iter()
+            .map(|(key, (total, count))| (key, total / (count as f64)))
+            .collect()
+    }
+}
@@ -7,14 +7,16 @@ use geom::{Circle, Distance, Duration, Percent, Polygon, Pt2D, Time};
 use map_gui::render::unzoomed_agent_radius;
 use map_gui::tools::{ColorLegend, ColorNetwork, DivergingScale};
 use map_gui::ID;
-use map_model::{IntersectionID, Map, Traversable};
+use map_model::{IntersectionID, Map, RoadID, Traversable};
 use sim::{AgentType, VehicleType};
 use widgetry::{
     Checkbox, Color, Drawable, EventCtx, GeomBatch, GfxCtx, HorizontalAlignment, Line, Outcome,
-    Panel, Text, TextExt, VerticalAlignment, Widget,
+    Panel, StyledButtons, Text, TextExt, VerticalAlignment, Widget,
 };
 
 use crate::app::App;
+use crate::common::launch_info_panel;
+use crate::info::Tab;
 use crate::layer::{header, Layer, LayerOutcome};
 
 pub struct Backpressure {
@@ -262,6 +264,139 @@ impl Throughput {
     }
 }
 
+/// Roads colored by how many people crossed them in roughly the last simulated hour, to spot
+/// what's busy right now, rather than over the whole day.
+pub struct RecentThroughput {
+    time: Time,
+    counts: Counter<RoadID>,
+    tooltip: Option<Text>,
+    unzoomed: Drawable,
+    zoomed: Drawable,
+    panel: Panel,
+}
+
+impl Layer for RecentThroughput {
+    fn name(&self) -> Option<&'static str> {
+        Some("recent throughput")
+    }
+    fn event(
+        &mut self,
+        ctx: &mut EventCtx,
+        app: &mut App,
+        minimap: &Panel,
+    ) -> Option<LayerOutcome> {
+        // Recalculating constantly while the simulation runs quickly is wasteful, and the counts
+        // barely change.
+        let now = app.primary.sim.time();
+        if now < self.time || now - self.time >= Duration::minutes(1) {
+            *self = RecentThroughput::new(ctx, app);
+        }
+
+        if ctx.canvas.cam_zoom < app.opts.min_zoom_for_detail {
+            if ctx.redo_mouseover() {
+                self.tooltip = None;
+                if let Some(ID::Road(r)) = app.mouseover_unzoomed_roads_and_intersections(ctx) {
+                    self.tooltip = Some(Text::from(Line(format!(
+                        "{} in the last hour",
+                        prettyprint_usize(self.counts.get(r))
+                    ))));
+                }
+            }
+            // Nothing is normally clickable while unzoomed, so open the road's info panel here.
+            // When zoomed in, clicking lanes already works.
+            if ctx.normal_left_click() {
+                if let Some(ID::Road(r)) = app.mouseover_unzoomed_roads_and_intersections(ctx) {
+                    let tab =
+                        Tab::from_id(app, ID::Lane(app.primary.map.get_r(r).lanes_ltr()[0].0));
+                    return Some(LayerOutcome::Transition(launch_info_panel(tab)));
+                }
+            }
+        } else {
+            self.tooltip = None;
+        }
+
+        self.panel.align_above(ctx, minimap);
+        if let Outcome::Clicked(x) = self.panel.event(ctx) {
+            match x.as_ref() {
+                "close" => {
+                    return Some(LayerOutcome::Close);
+                }
+                "refresh" => {
+                    app.primary.caches.recent_road_thruput.borrow_mut().clear();
+                    *self = RecentThroughput::new(ctx, app);
+                }
+                _ => unreachable!(),
+            }
+        }
+        None
+    }
+    fn draw(&self, g: &mut GfxCtx, app: &App) {
+        self.panel.draw(g);
+        if g.canvas.cam_zoom < app.opts.min_zoom_for_detail {
+            g.redraw(&self.unzoomed);
+        } else {
+            g.redraw(&self.zoomed);
+        }
+        if let Some(ref txt) = self.tooltip {
+            g.draw_mouse_tooltip(txt.clone());
+        }
+    }
+    fn draw_minimap(&self, g: &mut GfxCtx) {
+        g.redraw(&self.unzoomed);
+    }
+}
+
+impl RecentThroughput {
+    pub fn new(ctx: &mut EventCtx, app: &App) -> RecentThroughput {
+        let now = app.primary.sim.time();
+        let counts = {
+            let mut cache = app.primary.caches.recent_road_thruput.borrow_mut();
+            cache.update(Some(now), |now| {
+                app.primary
+                    .sim
+                    .get_analytics()
+                    .road_thruput
+                    .all_counts_in_last_hour(now)
+            });
+            cache.value().unwrap().clone()
+        };
+
+        let panel = Panel::new(Widget::col(vec![
+            header(ctx, "Recent throughput"),
+            Text::from(
+                Line(format!(
+                    "This counts all people crossing in the hour before {}",
+                    now.ampm_tostring()
+                ))
+                .secondary(),
+            )
+            .wrap_to_pct(ctx, 15)
+            .draw(ctx),
+            ctx.style().btn_outline_light_text("refresh").build_def(ctx),
+            ColorLegend::gradient(
+                ctx,
+                &app.cs.good_to_bad_red,
+                vec!["0".to_string(), prettyprint_usize(counts.max())],
+            ),
+        ]))
+        .aligned(HorizontalAlignment::Right, VerticalAlignment::Center)
+        .build(ctx);
+
+        let mut colorer = ColorNetwork::new(app);
+        colorer.pct_roads(counts.clone(), &app.cs.good_to_bad_red);
+        let (unzoomed, zoomed) = colorer.build(ctx);
+
+        RecentThroughput {
+            time: now,
+            counts,
+            tooltip: None,
+            unzoomed,
+            zoomed,
+            panel,
+        }
+    }
+}
+
 pub struct CompareThroughput {
     time: Time,
     unzoomed: Drawable,
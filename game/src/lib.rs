@@ -24,6 +24,7 @@ mod edit;
 mod info;
 mod layer;
 mod pregame;
+mod query_api;
 mod sandbox;
 
 pub fn main() {
@@ -36,9 +37,20 @@ pub fn main() {
         sim_flags: SimFlags::from_args(&mut args),
         live_map_edits: args.enabled("--live_map_edits"),
         study_area: args.optional("--study_area"),
+        query_api: args
+            .optional_parse("--query_api_port", |s| s.parse::<u16>())
+            .map(|port| query_api::QueryApiConfig {
+                port,
+                snapshot_every: std::time::Duration::from_secs_f64(
+                    args.optional_parse("--query_api_snapshot_secs", |s| s.parse::<f64>())
+                        .unwrap_or(1.0),
+                ),
+            }),
     };
     let mut opts = Options::default();
     opts.toggle_day_night_colors = true;
+    // Flags take precedence over anything remembered
+    opts.load_persisted();
     opts.update_from_args(&mut args);
     let mut settings = widgetry::Settings::new("A/B Street")
         .read_svg(Box::new(abstio::slurp_bytes))
@@ -90,6 +102,8 @@ pub fn main() {
     }
     let start_with_edits = args.optional("--edits");
     let center_camera = args.optional("--cam");
+    // Something like i/421 or w123, to warp to and open an info panel for
+    let start_at = args.optional("--at");
 
     if let Some(site) = args.optional("--actdev") {
         let city = site.replace("-", "_");
@@ -116,6 +130,7 @@ pub fn main() {
             mode,
             initialize_tutorial,
             center_camera,
+            start_at,
         )
     });
 }
@@ -128,11 +143,13 @@ fn setup_app(
     maybe_mode: Option<GameplayMode>,
     initialize_tutorial: bool,
     center_camera: Option<String>,
+    start_at: Option<String>,
 ) -> (App, Vec<Box<dyn State<App>>>) {
     let title = !opts.dev
         && !flags.sim_flags.load.contains("player/save")
         && !flags.sim_flags.load.contains("/scenarios/")
-        && maybe_mode.is_none();
+        && maybe_mode.is_none()
+        && start_at.is_none();
     // If we're starting directly in a challenge mode, the tutorial, or by playing a scenario,
     // usually time is midnight, so save some effort and start with the correct color scheme. If
     // we're loading a savestate and it's actually daytime, we'll pay a small penalty to switch
@@ -144,14 +161,15 @@ fn setup_app(
     {
         opts.color_scheme = map_gui::colors::ColorSchemeChoice::NightMode;
     }
-    let cs = map_gui::colors::ColorScheme::new(ctx, opts.color_scheme);
+    let cs = map_gui::colors::ColorScheme::new(ctx, opts.color_scheme, opts.agent_color_scheme);
+    let query_api = flags.query_api.clone().map(query_api::QueryApi::start);
 
     // SimFlags::load doesn't know how to do async IO, which we need on the web. But in the common
     // case, all we're creating there is a map. If so, use the proper async interface.
     //
     // Note if we started with a scenario, main() rewrote it to be the appropriate map, along with
     // maybe_mode.
-    if flags.sim_flags.load.contains("/maps/") {
+    let (mut app, states) = if flags.sim_flags.load.contains("/maps/") {
         // Get App created with a dummy blank map
         let map = Map::blank();
         let sim = Sim::new(&map, flags.sim_flags.opts.clone());
@@ -166,6 +184,7 @@ fn setup_app(
         );
         let app = App {
             primary,
+            secondary: None,
             cs,
             opts,
             per_obj: crate::app::PerObjectActions::new(),
@@ -185,6 +204,7 @@ fn setup_app(
                     maybe_mode,
                     initialize_tutorial,
                     center_camera,
+                    start_at,
                 ))
             }),
         )];
@@ -199,6 +219,7 @@ fn setup_app(
         });
         let mut app = App {
             primary,
+            secondary: None,
             cs,
             opts,
             per_obj: crate::app::PerObjectActions::new(),
@@ -212,9 +233,12 @@ fn setup_app(
             maybe_mode,
             initialize_tutorial,
             center_camera,
+            start_at,
         );
         (app, states)
-    }
+    };
+    app.session.query_api = query_api;
+    (app, states)
 }
 
 fn finish_app_setup(
@@ -225,6 +249,7 @@ fn finish_app_setup(
     maybe_mode: Option<GameplayMode>,
     initialize_tutorial: bool,
     center_camera: Option<String>,
+    start_at: Option<String>,
 ) -> Vec<Box<dyn State<App>>> {
     if let Some((pt, zoom)) =
         center_camera.and_then(|cam| parse_center_camera(ctx, &app.primary.map, cam))
@@ -271,30 +296,42 @@ fn finish_app_setup(
         crate::sandbox::gameplay::Tutorial::initialize(ctx, app);
     }
 
-    let start_daytime = Box::new(|ctx: &mut EventCtx, app: &mut App| {
-        ctx.loading_screen("start in the daytime", |_, mut timer| {
-            app.primary
-                .sim
-                .timed_step(&app.primary.map, Duration::hours(6), &mut None, &mut timer);
-        });
-        vec![Transition::Keep]
+    // Blog posts and just looking at an empty map start in the daytime
+    let start_daytime = match maybe_mode {
+        Some(GameplayMode::Blog(_, _)) | None => true,
+        Some(_) => false,
+    };
+    let finalize = Box::new(move |ctx: &mut EventCtx, app: &mut App| {
+        if start_daytime {
+            ctx.loading_screen("start in the daytime", |_, mut timer| {
+                app.primary.sim.timed_step(
+                    &app.primary.map,
+                    Duration::hours(6),
+                    &mut None,
+                    &mut timer,
+                );
+            });
+        }
+        // Only warp once the sandbox has finished loading, since the info panel lives there.
+        match start_at {
+            Some(input) => vec![Transition::Push(crate::common::DebugWarp::go_to(
+                ctx, input,
+            ))],
+            None => vec![Transition::Keep],
+        }
     });
 
     let states: Vec<Box<dyn State<App>>> = if title {
         vec![Box::new(TitleScreen::new(ctx, app))]
     } else if let Some(mode) = maybe_mode {
-        if let GameplayMode::Blog(_, _) = mode {
-            vec![SandboxMode::async_new(app, mode, start_daytime)]
-        } else {
-            vec![SandboxMode::simple_new(app, mode)]
-        }
+        vec![SandboxMode::async_new(app, mode, finalize)]
     } else {
         // We got here by just passing --dev and a map as flags; we're just looking at an empty
-        // map. Start in the daytime.
+        // map.
         vec![SandboxMode::async_new(
             app,
             GameplayMode::Freeform(app.primary.map.get_name().clone()),
-            start_daytime,
+            finalize,
         )]
     };
     if let Some(ss) = savestate {
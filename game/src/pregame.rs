@@ -19,7 +19,7 @@ use crate::app::{App, Transition};
 use crate::challenges::ChallengesPicker;
 use crate::devtools::DevToolsMode;
 use crate::edit::apply_map_edits;
-use crate::sandbox::gameplay::Tutorial;
+use crate::sandbox::gameplay::{Tour, Tutorial};
 use crate::sandbox::{GameplayMode, SandboxMode};
 
 pub struct TitleScreen {
@@ -68,6 +68,14 @@ impl State<App> for TitleScreen {
             Outcome::Clicked(x) => match x.as_ref() {
                 "start game" => {
                     app.primary.clear_sim();
+                    if !app.opts.offered_tour {
+                        app.opts.offered_tour = true;
+                        app.opts.save_persisted();
+                        return Transition::Multi(vec![
+                            Transition::Replace(MainMenu::new(ctx)),
+                            Transition::Push(Tour::offer(ctx)),
+                        ]);
+                    }
                     return Transition::Replace(MainMenu::new(ctx));
                 }
                 _ => unreachable!(),
@@ -153,6 +161,15 @@ impl MainMenu {
             })
             .centered(),
             Widget::row(vec![
+                ctx.style()
+                    .btn_outline_light_text("Quick tour")
+                    .tooltip({
+                        let mut txt = Text::tooltip(ctx, Key::Q, "Quick tour");
+                        txt.add(Line("A few minutes on the basic controls").small());
+                        txt
+                    })
+                    .hotkey(Key::Q)
+                    .build_widget(ctx, "Quick tour"),
                 ctx.style()
                     .btn_outline_light_text("Community Proposals")
                     .tooltip({
@@ -195,6 +212,9 @@ impl State<App> for MainMenu {
                 "Tutorial" => {
                     return Tutorial::start(ctx, app);
                 }
+                "Quick tour" => {
+                    return Transition::Push(Tour::start(app));
+                }
                 "Sandbox mode" => {
                     let scenario = if abstio::file_exists(abstio::path_scenario(
                         app.primary.map.get_name(),
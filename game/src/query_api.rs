@@ -0,0 +1,623 @@
+//! An optional, read-only HTTP API for querying the simulation while the game runs, for driving
+//! analysis from something like a notebook. It only exists when the game is built with the
+//! `query_api` feature and `--query_api_port` is passed.
+//!
+//! The server runs on a background thread and never touches the live simulation. Instead, the UI
+//! thread periodically publishes a `Snapshot`, and requests are answered from the latest one.
+//!
+//! > cargo run --bin game --features query_api -- --query_api_port=1234
+//! > curl http://localhost:1234/
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::{Arc, Mutex, RwLock};
+
+use instant::Instant;
+use serde::{Serialize, Serializer};
+use serde_json::{json, Value};
+
+use abstio::MapName;
+use geom::{LonLat, Time};
+use sim::{AgentID, AgentType, TripID, TripMode, TripResult};
+
+use crate::app::PerMap;
+
+/// Bump this and add new routes under the new prefix when anything about the responses changes
+/// incompatibly.
+const VERSION: &str = "v1";
+
+/// How to run the query API, from command-line flags
+#[derive(Clone)]
+pub struct QueryApiConfig {
+    pub port: u16,
+    /// How often to publish a new snapshot, in real time. Building one isn't free on big maps.
+    pub snapshot_every: std::time::Duration,
+}
+
+/// The running server, plus what's needed to feed it snapshots
+pub struct QueryApi {
+    config: QueryApiConfig,
+    latest: Arc<RwLock<Option<Arc<Snapshot>>>>,
+    /// When the last snapshot was published, and the map and sim time it captured
+    last_published: Option<(Instant, MapName, Time)>,
+    trips: TripTracker,
+    // Only needed to shut down, which happens through &self
+    server: Mutex<Option<server::Handle>>,
+}
+
+/// Everything the API can answer, captured at one moment
+struct Snapshot {
+    map: String,
+    time: Time,
+    /// Keyed by IntersectionID
+    intersection_delays: BTreeMap<usize, DelaySummary>,
+    /// Keyed by LaneID. How many agents have entered each lane so far today.
+    lane_throughput: BTreeMap<usize, usize>,
+    /// Keyed by TripID. Shared between snapshots until a trip changes.
+    trips: Arc<BTreeMap<usize, TripStatus>>,
+    agents: Vec<AgentPosition>,
+}
+
+#[derive(Serialize)]
+struct DelaySummary {
+    /// How many agents finished waiting here
+    count: usize,
+    mean_seconds: f64,
+    max_seconds: f64,
+}
+
+#[derive(Clone, Serialize)]
+struct TripStatus {
+    mode: TripMode,
+    #[serde(serialize_with = "serialize_time")]
+    departure: Time,
+    /// One of "not started", "ongoing", "finished", or "cancelled"
+    status: &'static str,
+    cancellation_reason: Option<String>,
+}
+
+#[derive(Serialize)]
+struct AgentPosition {
+    agent_type: AgentType,
+    /// The car or pedestrian number, depending on the type
+    id: usize,
+    /// None for buses and trains
+    person: Option<usize>,
+    trip: Option<usize>,
+    lon: f64,
+    lat: f64,
+}
+
+impl QueryApi {
+    /// Starts serving on a background thread. If the server can't start, the error is logged and
+    /// the game keeps going without it.
+    pub fn start(config: QueryApiConfig) -> QueryApi {
+        let latest = Arc::new(RwLock::new(None));
+        let server = match server::start(config.port, latest.clone()) {
+            Ok(handle) => {
+                info!("Query API listening on http://127.0.0.1:{}/", config.port);
+                Some(handle)
+            }
+            Err(err) => {
+                error!("Couldn't start the query API: {}", err);
+                None
+            }
+        };
+        QueryApi {
+            config,
+            latest,
+            last_published: None,
+            trips: TripTracker::new(),
+            server: Mutex::new(server),
+        }
+    }
+
+    /// Called every event. Publishes a new snapshot if enough real time has passed and the
+    /// simulation actually changed.
+    pub fn maybe_publish(&mut self, primary: &PerMap) {
+        let map_name = primary.map.get_name();
+        let time = primary.sim.time();
+        if let Some((at, ref name, t)) = self.last_published {
+            if at.elapsed() < self.config.snapshot_every || (name == map_name && t == time) {
+                return;
+            }
+        }
+        let trips = self.trips.update(primary);
+        let snapshot = Snapshot::new(primary, trips);
+        *self.latest.write().unwrap() = Some(Arc::new(snapshot));
+        self.last_published = Some((Instant::now(), map_name.clone(), time));
+    }
+
+    /// Stops the server and waits for it to finish.
+    pub fn shutdown(&self) {
+        if let Some(handle) = self.server.lock().unwrap().take() {
+            handle.shutdown();
+        }
+    }
+}
+
+impl Snapshot {
+    fn new(primary: &PerMap, trips: Arc<BTreeMap<usize, TripStatus>>) -> Snapshot {
+        let map = &primary.map;
+        let sim = &primary.sim;
+        let analytics = sim.get_analytics();
+
+        let mut intersection_delays = BTreeMap::new();
+        for (i, delays) in &analytics.intersection_delays {
+            if delays.is_empty() {
+                continue;
+            }
+            let total: f64 = delays.iter().map(|(_, _, dt, _)| dt.inner_seconds()).sum();
+            intersection_delays.insert(
+                i.0,
+                DelaySummary {
+                    count: delays.len(),
+                    mean_seconds: total / (delays.len() as f64),
+                    max_seconds: delays
+                        .iter()
+                        .map(|(_, _, dt, _)| dt.inner_seconds())
+                        .fold(0.0, f64::max),
+                },
+            );
+        }
+
+        let lane_throughput = analytics
+            .lane_thruput
+            .all_total_counts(&AgentType::all().into_iter().collect())
+            .consume()
+            .into_iter()
+            .map(|(l, cnt)| (l.0, cnt))
+            .collect();
+
+        let agents = sim
+            .get_unzoomed_agents(map)
+            .into_iter()
+            .map(|a| {
+                let gps = a.pos.to_gps(map.get_gps_bounds());
+                AgentPosition {
+                    agent_type: a.id.to_type(),
+                    id: match a.id {
+                        AgentID::Car(c) => c.0,
+                        AgentID::Pedestrian(p) => p.0,
+                        AgentID::BusPassenger(p, _) => p.0,
+                    },
+                    person: a.person.map(|p| p.0),
+                    trip: sim.agent_to_trip(a.id).map(|t| t.0),
+                    lon: gps.x(),
+                    lat: gps.y(),
+                }
+            })
+            .collect();
+
+        Snapshot {
+            map: map.get_name().describe(),
+            time: sim.time(),
+            intersection_delays,
+            lane_throughput,
+            trips,
+            agents,
+        }
+    }
+}
+
+/// Keeps trip statuses up-to-date between snapshots. Checking every trip from scratch is slow on
+/// big maps, but only a few trips start or finish between two snapshots.
+struct TripTracker {
+    /// The map and time last updated. Switching maps or rewinding starts over.
+    map: Option<MapName>,
+    time: Time,
+    statuses: Arc<BTreeMap<usize, TripStatus>>,
+    /// Trips that haven't started yet, by scheduled departure
+    not_started: BTreeSet<(Time, TripID)>,
+    /// How many of the analytics' finished trips have been handled
+    num_finished: usize,
+}
+
+impl TripTracker {
+    fn new() -> TripTracker {
+        TripTracker {
+            map: None,
+            time: Time::START_OF_DAY,
+            statuses: Arc::new(BTreeMap::new()),
+            not_started: BTreeSet::new(),
+            num_finished: 0,
+        }
+    }
+
+    fn update(&mut self, primary: &PerMap) -> Arc<BTreeMap<usize, TripStatus>> {
+        let sim = &primary.sim;
+        let now = sim.time();
+        let finished_trips = &sim.get_analytics().finished_trips;
+        if self.map.as_ref() != Some(primary.map.get_name())
+            || now < self.time
+            || finished_trips.len() < self.num_finished
+        {
+            *self = TripTracker::new();
+            self.map = Some(primary.map.get_name().clone());
+        }
+        self.time = now;
+
+        // The last snapshot may still be using the statuses, so collect changes and only copy
+        // them if there are any
+        let mut changed: BTreeMap<usize, TripStatus> = BTreeMap::new();
+
+        // Trips added since the last update, like ones spawned from the UI, have the next IDs
+        let (num_done, num_unfinished) = sim.num_trips();
+        for id in (self.statuses.len()..num_done + num_unfinished).map(TripID) {
+            let info = sim.trip_info(id);
+            self.not_started.insert((info.departure, id));
+            changed.insert(
+                id.0,
+                TripStatus {
+                    mode: info.mode,
+                    departure: info.departure,
+                    status: "not started",
+                    cancellation_reason: None,
+                },
+            );
+        }
+
+        // Starting can be delayed past the scheduled departure, so keep checking those
+        let departed: Vec<(Time, TripID)> = self
+            .not_started
+            .range(..(now, TripID(usize::MAX)))
+            .cloned()
+            .collect();
+        for (departure, id) in departed {
+            if let TripResult::TripNotStarted = sim.trip_to_agent(id) {
+                continue;
+            }
+            self.not_started.remove(&(departure, id));
+            if let Some(status) = self.get(&changed, id) {
+                changed.insert(
+                    id.0,
+                    TripStatus {
+                        status: "ongoing",
+                        ..status
+                    },
+                );
+            }
+        }
+
+        for (_, id, _, duration) in &finished_trips[self.num_finished..] {
+            let status = match self.get(&changed, *id) {
+                Some(status) => status,
+                None => continue,
+            };
+            // A trip can be cancelled before it ever starts
+            self.not_started.remove(&(status.departure, *id));
+            changed.insert(
+                id.0,
+                if duration.is_some() {
+                    TripStatus {
+                        status: "finished",
+                        ..status
+                    }
+                } else {
+                    TripStatus {
+                        status: "cancelled",
+                        cancellation_reason: sim.trip_info(*id).cancellation_reason,
+                        ..status
+                    }
+                },
+            );
+        }
+        self.num_finished = finished_trips.len();
+
+        if !changed.is_empty() {
+            Arc::make_mut(&mut self.statuses).extend(changed);
+        }
+        self.statuses.clone()
+    }
+
+    /// The latest status of a trip, including changes not applied yet
+    fn get(&self, changed: &BTreeMap<usize, TripStatus>, id: TripID) -> Option<TripStatus> {
+        changed
+            .get(&id.0)
+            .or_else(|| self.statuses.get(&id.0))
+            .cloned()
+    }
+}
+
+fn serialize_time<S: Serializer>(t: &Time, s: S) -> Result<S::Ok, S::Error> {
+    s.serialize_str(&t.to_string())
+}
+
+/// (path, parameters, description) of every route
+fn routes() -> Vec<(String, &'static str, &'static str)> {
+    vec![
+        (
+            "/".to_string(),
+            "",
+            "This index of every route. Every response also says which route and version it came \
+             from.",
+        ),
+        (
+            format!("/{}/time", VERSION),
+            "",
+            "The simulation time and map of the latest snapshot.",
+        ),
+        (
+            format!("/{}/intersections/delay", VERSION),
+            "id (optional IntersectionID)",
+            "How long agents have waited at intersections so far: the count, mean, and max delay \
+             in seconds. Without an id, lists every intersection with any delay.",
+        ),
+        (
+            format!("/{}/lanes/throughput", VERSION),
+            "id (optional LaneID)",
+            "How many agents have entered lanes so far today. Without an id, lists every lane \
+             used so far.",
+        ),
+        (
+            format!("/{}/trips/status", VERSION),
+            "id (required TripID)",
+            "The mode, scheduled departure, and status of one trip: not started, ongoing, \
+             finished, or cancelled.",
+        ),
+        (
+            format!("/{}/agents", VERSION),
+            "bbox (required, as min_lon,min_lat,max_lon,max_lat)",
+            "The position of every agent inside the bounding box. Transit riders are inside their \
+             bus or train, so they aren't listed.",
+        ),
+    ]
+}
+
+fn index() -> Value {
+    json!({
+        "version": VERSION,
+        "description": "A read-only view of the running A/B Street simulation. Data comes from \
+                        snapshots published periodically while the game runs, so it may lag the \
+                        game slightly.",
+        "routes": routes()
+            .into_iter()
+            .map(|(path, params, description)| {
+                json!({
+                    "path": path,
+                    "parameters": params,
+                    "description": description,
+                })
+            })
+            .collect::<Vec<_>>(),
+    })
+}
+
+/// The outcome of a request, as an HTTP status and a JSON body
+struct Reply {
+    status: u16,
+    body: Value,
+}
+
+impl Reply {
+    fn error(status: u16, msg: String) -> Reply {
+        Reply {
+            status,
+            body: json!({
+                "error": msg,
+                "index": "/",
+            }),
+        }
+    }
+}
+
+/// Answers one GET request from a snapshot. This is plain so it doesn't depend on the HTTP
+/// library.
+fn handle(path: &str, params: &BTreeMap<String, String>, latest: Option<&Snapshot>) -> Reply {
+    let path = path.trim_end_matches('/');
+    if path.is_empty() {
+        return Reply {
+            status: 200,
+            body: index(),
+        };
+    }
+    let description = match routes().into_iter().find(|(p, _, _)| p == path) {
+        Some((_, _, description)) => description,
+        None => return Reply::error(404, format!("Unknown route {}", path)),
+    };
+    let snapshot = match latest {
+        Some(snapshot) => snapshot,
+        None => {
+            return Reply::error(
+                503,
+                "The simulation hasn't published a snapshot yet".to_string(),
+            )
+        }
+    };
+
+    let data = match query(path, params, snapshot) {
+        Ok(data) => data,
+        Err(err) => return Reply::error(400, err.to_string()),
+    };
+    Reply {
+        status: 200,
+        body: json!({
+            "version": VERSION,
+            "route": path,
+            "description": description,
+            "map": snapshot.map,
+            "sim_time": snapshot.time.to_string(),
+            "data": data,
+        }),
+    }
+}
+
+fn query(
+    path: &str,
+    params: &BTreeMap<String, String>,
+    snapshot: &Snapshot,
+) -> anyhow::Result<Value> {
+    let id = || -> anyhow::Result<Option<usize>> {
+        match params.get("id") {
+            Some(x) => Ok(Some(x.parse::<usize>()?)),
+            None => Ok(None),
+        }
+    };
+    match path.trim_start_matches(&format!("/{}", VERSION)) {
+        "/time" => Ok(json!({
+            "time": snapshot.time.to_string(),
+            "seconds_since_midnight": (snapshot.time - Time::START_OF_DAY).inner_seconds(),
+        })),
+        "/intersections/delay" => match id()? {
+            Some(i) => Ok(snapshot
+                .intersection_delays
+                .get(&i)
+                .map(to_json)
+                .unwrap_or_else(|| {
+                    to_json(&DelaySummary {
+                        count: 0,
+                        mean_seconds: 0.0,
+                        max_seconds: 0.0,
+                    })
+                })),
+            None => Ok(to_json(&snapshot.intersection_delays)),
+        },
+        "/lanes/throughput" => match id()? {
+            Some(l) => Ok(json!(snapshot
+                .lane_throughput
+                .get(&l)
+                .cloned()
+                .unwrap_or(0))),
+            None => Ok(to_json(&snapshot.lane_throughput)),
+        },
+        "/trips/status" => {
+            let trip = id()?.ok_or_else(|| anyhow!("missing parameter id"))?;
+            match snapshot.trips.get(&trip) {
+                Some(status) => Ok(to_json(status)),
+                None => bail!("Trip #{} doesn't exist", trip),
+            }
+        }
+        "/agents" => {
+            let bbox = params
+                .get("bbox")
+                .ok_or_else(|| anyhow!("missing parameter bbox"))?;
+            let nums = bbox
+                .split(',')
+                .map(|x| x.parse::<f64>())
+                .collect::<Result<Vec<f64>, _>>()?;
+            if nums.len() != 4 {
+                bail!("bbox should be min_lon,min_lat,max_lon,max_lat");
+            }
+            let (min, max) = (LonLat::new(nums[0], nums[1]), LonLat::new(nums[2], nums[3]));
+            let agents: Vec<&AgentPosition> = snapshot
+                .agents
+                .iter()
+                .filter(|a| {
+                    a.lon >= min.x() && a.lon <= max.x() && a.lat >= min.y() && a.lat <= max.y()
+                })
+                .collect();
+            Ok(to_json(&agents))
+        }
+        _ => unreachable!(),
+    }
+}
+
+fn to_json<T: Serialize>(x: &T) -> Value {
+    // Everything in a snapshot is plain data, so this can't fail
+    serde_json::to_value(x).unwrap()
+}
+
+#[cfg(all(feature = "query_api", not(target_arch = "wasm32")))]
+mod server {
+    use std::collections::BTreeMap;
+    use std::sync::{Arc, RwLock};
+    use std::thread::JoinHandle;
+
+    use anyhow::Result;
+    use hyper::service::{make_service_fn, service_fn};
+    use hyper::{Body, Method, Request, Response, Server};
+    use tokio::sync::oneshot;
+
+    use super::{handle, Reply, Snapshot};
+
+    pub(super) struct Handle {
+        stop: oneshot::Sender<()>,
+        thread: JoinHandle<()>,
+    }
+
+    impl Handle {
+        pub(super) fn shutdown(self) {
+            // If the server already died, there's nothing to stop
+            let _ = self.stop.send(());
+            if self.thread.join().is_err() {
+                error!("The query API thread panicked");
+            }
+        }
+    }
+
+    pub(super) fn start(port: u16, latest: Arc<RwLock<Option<Arc<Snapshot>>>>) -> Result<Handle> {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()?;
+        // Bind before starting the thread, so problems like the port being taken are reported
+        let addr = std::net::SocketAddr::from(([127, 0, 0, 1], port));
+        let builder = {
+            let _guard = runtime.enter();
+            Server::try_bind(&addr)?
+        };
+
+        let (stop, stopped) = oneshot::channel::<()>();
+        let thread = std::thread::spawn(move || {
+            runtime.block_on(async move {
+                let make_service = make_service_fn(move |_| {
+                    let latest = latest.clone();
+                    async move {
+                        Ok::<_, hyper::Error>(service_fn(move |req| serve_req(req, latest.clone())))
+                    }
+                });
+                let server = builder.serve(make_service).with_graceful_shutdown(async {
+                    stopped.await.ok();
+                });
+                if let Err(err) = server.await {
+                    error!("Query API server error: {}", err);
+                }
+            });
+        });
+        Ok(Handle { stop, thread })
+    }
+
+    async fn serve_req(
+        req: Request<Body>,
+        latest: Arc<RwLock<Option<Arc<Snapshot>>>>,
+    ) -> Result<Response<Body>, hyper::Error> {
+        let reply = if req.method() != Method::GET {
+            Reply::error(
+                405,
+                "The query API is read-only, so only GET is supported".to_string(),
+            )
+        } else {
+            let params: BTreeMap<String, String> =
+                url::form_urlencoded::parse(req.uri().query().unwrap_or("").as_bytes())
+                    .map(|(k, v)| (k.to_string(), v.to_string()))
+                    .collect();
+            // Only hold the lock long enough to grab the latest snapshot
+            let snapshot = latest.read().unwrap().clone();
+            handle(req.uri().path(), &params, snapshot.as_deref())
+        };
+        Ok(Response::builder()
+            .status(reply.status)
+            .header("Content-Type", "application/json")
+            .body(Body::from(reply.body.to_string()))
+            .unwrap())
+    }
+}
+
+// The HTTP server is too heavy a dependency to always build, and there's no way to listen for
+// requests from the browser anyway
+#[cfg(not(all(feature = "query_api", not(target_arch = "wasm32"))))]
+mod server {
+    use std::sync::{Arc, RwLock};
+
+    use anyhow::Result;
+
+    use super::Snapshot;
+
+    pub(super) struct Handle;
+
+    impl Handle {
+        pub(super) fn shutdown(self) {}
+    }
+
+    pub(super) fn start(_: u16, _: Arc<RwLock<Option<Arc<Snapshot>>>>) -> Result<Handle> {
+        bail!("The query API isn't supported on the web, or without the query_api feature")
+    }
+}
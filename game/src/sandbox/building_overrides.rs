@@ -0,0 +1,198 @@
+use serde::{Deserialize, Serialize};
+
+use abstio::MapName;
+use abstutil::Timer;
+use map_model::{BuildingID, Map};
+use sim::{BuildingOverride, Scenario};
+use widgetry::{
+    Color, CornerRounding, EventCtx, GfxCtx, HorizontalAlignment, Line, Outcome, Panel, Spinner,
+    State, StyledButtons, TextExt, VerticalAlignment, Widget,
+};
+
+use crate::app::{App, Transition};
+use crate::sandbox::{GameplayMode, SandboxMode};
+
+/// Land-use experiments for one map, saved as player data. They're applied every time a scenario
+/// is instantiated on the map, so nothing has to be re-imported.
+#[derive(Serialize, Deserialize)]
+pub struct MapExperiments {
+    pub building_overrides: Vec<BuildingOverride>,
+}
+
+impl MapExperiments {
+    pub fn load(name: &MapName) -> MapExperiments {
+        abstio::maybe_read_json::<MapExperiments>(
+            MapExperiments::path(name),
+            &mut Timer::throwaway(),
+        )
+        .unwrap_or_else(|_| MapExperiments {
+            building_overrides: Vec::new(),
+        })
+    }
+
+    fn save(&self, name: &MapName) {
+        abstio::write_json(MapExperiments::path(name), self);
+    }
+
+    fn path(name: &MapName) -> String {
+        abstio::path_player(format!(
+            "experiments/{}/{}/{}.json",
+            name.city.country, name.city.city, name.map
+        ))
+    }
+
+    pub fn get(&self, map: &Map, b: BuildingID) -> Option<&BuildingOverride> {
+        let orig_id = map.get_b(b).orig_id;
+        self.building_overrides
+            .iter()
+            .find(|o| o.building == orig_id)
+    }
+
+    /// Adds synthetic people to the scenario for every building override. Overrides for buildings
+    /// that don't exist on this map anymore are skipped. Returns the number of people added.
+    pub fn apply(&self, map: &Map, scenario: &mut Scenario) -> usize {
+        self.building_overrides
+            .iter()
+            .map(|o| o.apply(map, scenario))
+            .sum()
+    }
+}
+
+/// A badge for the building's info panel, if it has an override
+pub fn override_badge(ctx: &EventCtx, app: &App, b: BuildingID) -> Option<Widget> {
+    let o = app.primary.experiments.get(&app.primary.map, b)?;
+    let color = Color::hex("#F4DA22");
+    Some(
+        Line(format!(
+            "synthetic override: {} households, {} jobs",
+            o.households, o.jobs
+        ))
+        .small()
+        .fg(color)
+        .batch(ctx)
+        .container()
+        .padding(5)
+        .corner_rounding(CornerRounding::FullyRounded)
+        .outline(1.0, color)
+        .bg(color.alpha(0.2)),
+    )
+}
+
+/// Sets or removes the extra households and jobs at one building. The simulation restarts from
+/// midnight to regenerate the building's people.
+pub struct BuildingOverrideEditor {
+    panel: Panel,
+    building: BuildingID,
+    gameplay: GameplayMode,
+}
+
+impl BuildingOverrideEditor {
+    pub fn new(
+        ctx: &mut EventCtx,
+        app: &App,
+        building: BuildingID,
+        gameplay: GameplayMode,
+    ) -> Box<dyn State<App>> {
+        let current = app.primary.experiments.get(&app.primary.map, building);
+        let panel = Panel::new(Widget::col(vec![
+            Widget::row(vec![
+                Line("Override trip generation").small_heading().draw(ctx),
+                ctx.style().btn_close_widget(ctx),
+            ]),
+            format!("At {}", app.primary.map.get_b(building).address).draw_text(ctx),
+            "Each household or job adds one synthetic person commuting to or from here."
+                .draw_text(ctx),
+            Widget::row(vec![
+                "Extra households:".draw_text(ctx),
+                Spinner::new(
+                    ctx,
+                    (0, 1000),
+                    current.map(|o| o.households as isize).unwrap_or(0),
+                )
+                .named("households"),
+            ]),
+            Widget::row(vec![
+                "Extra jobs:".draw_text(ctx),
+                Spinner::new(
+                    ctx,
+                    (0, 1000),
+                    current.map(|o| o.jobs as isize).unwrap_or(0),
+                )
+                .named("jobs"),
+            ]),
+            "Changes restart the simulation from midnight.".draw_text(ctx),
+            Widget::row(vec![
+                ctx.style().btn_solid_dark_text("Apply").build_def(ctx),
+                ctx.style()
+                    .btn_outline_light_text("Remove override")
+                    .disabled(current.is_none())
+                    .build_def(ctx),
+            ]),
+        ]))
+        .aligned(HorizontalAlignment::Right, VerticalAlignment::Top)
+        .build(ctx);
+        Box::new(BuildingOverrideEditor {
+            panel,
+            building,
+            gameplay,
+        })
+    }
+
+    fn save_and_reset(&self, app: &mut App, new_override: Option<BuildingOverride>) -> Transition {
+        let orig_id = app.primary.map.get_b(self.building).orig_id;
+        let experiments = &mut app.primary.experiments;
+        experiments
+            .building_overrides
+            .retain(|o| o.building != orig_id);
+        experiments.building_overrides.extend(new_override);
+        experiments.save(app.primary.map.get_name());
+
+        Transition::Multi(vec![
+            Transition::Pop,
+            Transition::Replace(SandboxMode::simple_new(app, self.gameplay.clone())),
+        ])
+    }
+}
+
+impl State<App> for BuildingOverrideEditor {
+    fn event(&mut self, ctx: &mut EventCtx, app: &mut App) -> Transition {
+        ctx.canvas_movement();
+
+        if let Outcome::Clicked(x) = self.panel.event(ctx) {
+            match x.as_ref() {
+                "close" => {
+                    return Transition::Pop;
+                }
+                "Apply" => {
+                    let households = self.panel.spinner("households") as usize;
+                    let jobs = self.panel.spinner("jobs") as usize;
+                    // Nothing extra is the same as no override
+                    let new_override = if households == 0 && jobs == 0 {
+                        None
+                    } else {
+                        Some(BuildingOverride {
+                            building: app.primary.map.get_b(self.building).orig_id,
+                            households,
+                            jobs,
+                        })
+                    };
+                    return self.save_and_reset(app, new_override);
+                }
+                "Remove override" => {
+                    return self.save_and_reset(app, None);
+                }
+                _ => unreachable!(),
+            }
+        }
+
+        Transition::Keep
+    }
+
+    fn draw(&self, g: &mut GfxCtx, app: &App) {
+        self.panel.draw(g);
+        g.draw_polygon(
+            Color::YELLOW.alpha(0.5),
+            app.primary.map.get_b(self.building).polygon.clone(),
+        );
+    }
+}
@@ -0,0 +1,407 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use abstutil::Timer;
+use geom::{Duration, Polygon, Time};
+use map_gui::tools::PopupMsg;
+use map_gui::ID;
+use map_model::osm::OsmID;
+use map_model::{BuildingID, Map, NORMAL_LANE_THICKNESS};
+use sim::{IndividTrip, PersonSpec, Scenario, TripEndpoint, TripID, TripMode, TripPurpose};
+use widgetry::{
+    Checkbox, Choice, Color, EventCtx, GfxCtx, HorizontalAlignment, Line, Outcome, Panel, Spinner,
+    State, StyledButtons, TextExt, VerticalAlignment, Widget,
+};
+
+use crate::app::{App, Transition};
+use crate::common::CommonState;
+
+/// Trips added to the running simulation by hand, for quick experiments without editing a
+/// scenario file. Each trip is made by a new person who exists just for it.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct CustomTrips {
+    /// Buildings are referred to by OSM ID, so saved trips still work after the map is regenerated
+    pub from: OsmID,
+    pub to: OsmID,
+    pub mode: TripMode,
+    pub purpose: TripPurpose,
+    /// When the first person leaves
+    pub departure: Time,
+    /// How many people make the same trip
+    pub count: usize,
+    /// Between each person leaving
+    pub spacing: Duration,
+}
+
+/// Custom trips saved per map as player data, to spawn again in a later session.
+#[derive(Serialize, Deserialize)]
+struct SavedCustomTrips {
+    trips: Vec<CustomTrips>,
+}
+
+impl CustomTrips {
+    /// Adds the trips to the current simulation and remembers them. Departures in the past start
+    /// immediately instead. Returns the number of trips spawned.
+    pub fn spawn(self, app: &mut App) -> usize {
+        let map = &app.primary.map;
+        let (from, to) = match self.buildings(map) {
+            Some(pair) => pair,
+            None => {
+                return 0;
+            }
+        };
+        let now = app.primary.sim.time();
+        // Instantiating a scenario renames the simulation, so keep the current name
+        let mut scenario = Scenario::empty(map, &app.primary.sim.get_run_name().clone());
+        for idx in 0..self.count {
+            let departure = self.departure + self.spacing * (idx as f64);
+            scenario.people.push(PersonSpec {
+                orig_id: None,
+                origin: TripEndpoint::Bldg(from),
+                trips: vec![IndividTrip::new(
+                    if departure < now { now } else { departure },
+                    self.purpose,
+                    TripEndpoint::Bldg(to),
+                    self.mode,
+                )],
+            });
+        }
+
+        let first_new_person = app.primary.sim.get_all_people().len();
+        let mut rng = app.primary.current_flags.sim_flags.make_rng();
+        scenario.instantiate(
+            &mut app.primary.sim,
+            map,
+            &mut rng,
+            &mut Timer::new("spawn custom trips"),
+        );
+        // Start anybody leaving right now
+        app.primary.sim.tiny_step(map, &mut app.primary.sim_cb);
+
+        let trips: Vec<TripID> = app.primary.sim.get_all_people()[first_new_person..]
+            .iter()
+            .flat_map(|person| person.trips.clone())
+            .collect();
+        let num_trips = trips.len();
+        app.primary.custom_trips.push((self, trips));
+        num_trips
+    }
+
+    /// Forgets about one group of custom trips, cancelling the ones that haven't started yet.
+    /// Trips already underway continue. Returns the number cancelled.
+    pub fn remove(app: &mut App, idx: usize) -> usize {
+        let (_, trips) = app.primary.custom_trips.remove(idx);
+        trips
+            .into_iter()
+            .filter(|t| app.primary.sim.cancel_unstarted_trip(*t).is_ok())
+            .count()
+    }
+
+    /// None if either building doesn't exist on this map anymore
+    fn buildings(&self, map: &Map) -> Option<(BuildingID, BuildingID)> {
+        Some((
+            map.find_b_by_osm_id(self.from)?,
+            map.find_b_by_osm_id(self.to)?,
+        ))
+    }
+
+    pub fn describe(&self, app: &App) -> String {
+        let map = &app.primary.map;
+        // Only trips that were spawned get described, so the buildings exist
+        let (from, to) = self.buildings(map).unwrap();
+        format!(
+            "{} {} {} from {} to {} ({})",
+            self.count,
+            if self.count == 1 { "person" } else { "people" },
+            self.mode.ongoing_verb(),
+            map.get_b(from).address,
+            map.get_b(to).address,
+            self.purpose
+        )
+    }
+
+    pub fn describe_schedule(&self) -> String {
+        if self.count == 1 {
+            format!("Leaving at {}", self.departure.ampm_tostring())
+        } else {
+            format!(
+                "Leaving from {}, every {}",
+                self.departure.ampm_tostring(),
+                self.spacing
+            )
+        }
+    }
+
+    pub fn path(app: &App) -> String {
+        let name = app.primary.map.get_name();
+        abstio::path_player(format!(
+            "custom_trips/{}/{}/{}.json",
+            name.city.country, name.city.city, name.map
+        ))
+    }
+
+    /// Saves every group of custom trips spawned in this simulation, replacing anything saved
+    /// before.
+    pub fn save_all(app: &App) {
+        let saved = SavedCustomTrips {
+            trips: app
+                .primary
+                .custom_trips
+                .iter()
+                .map(|(spec, _)| spec.clone())
+                .collect(),
+        };
+        abstio::write_json(CustomTrips::path(app), &saved);
+    }
+
+    /// Spawns every saved group of custom trips. Returns the number of trips spawned, and how many
+    /// saved groups were skipped because their buildings don't exist on this map anymore.
+    pub fn spawn_saved(app: &mut App) -> Result<(usize, usize)> {
+        let saved = abstio::maybe_read_json::<SavedCustomTrips>(
+            CustomTrips::path(app),
+            &mut Timer::throwaway(),
+        )?;
+        let mut num_trips = 0;
+        let mut skipped = 0;
+        for spec in saved.trips {
+            if spec.buildings(&app.primary.map).is_none() {
+                skipped += 1;
+                continue;
+            }
+            num_trips += spec.spawn(app);
+        }
+        Ok((num_trips, skipped))
+    }
+}
+
+/// Spawns custom trips from a building. Click another building to pick the destination, then
+/// choose how and when to go.
+pub struct CustomTripSpawner {
+    panel: Panel,
+    from: BuildingID,
+    // The destination and the route there for the chosen mode. No route means the trip is
+    // impossible.
+    to: Option<(BuildingID, Option<Polygon>)>,
+    // Once the destination is clicked, stop following the cursor
+    confirmed: bool,
+}
+
+impl CustomTripSpawner {
+    pub fn new(ctx: &mut EventCtx, app: &App, from: BuildingID) -> Box<dyn State<App>> {
+        let (hours, minutes, _, _) = app.primary.sim.time().get_parts();
+        let panel = Panel::new(Widget::col(vec![
+            Widget::row(vec![
+                Line("Spawn trips").small_heading().draw(ctx),
+                ctx.style().btn_close_widget(ctx),
+            ]),
+            format!("From {}", app.primary.map.get_b(from).address).draw_text(ctx),
+            "Click another building to go there"
+                .draw_text(ctx)
+                .named("instructions"),
+            Widget::row(vec![
+                "Type of trip:".draw_text(ctx),
+                Widget::dropdown(
+                    ctx,
+                    "mode",
+                    TripMode::Drive,
+                    TripMode::all()
+                        .into_iter()
+                        .map(|m| Choice::new(m.ongoing_verb(), m))
+                        .collect(),
+                ),
+            ]),
+            Widget::row(vec![
+                "Purpose:".draw_text(ctx),
+                Widget::dropdown(
+                    ctx,
+                    "purpose",
+                    TripPurpose::Shopping,
+                    vec![
+                        TripPurpose::Home,
+                        TripPurpose::Work,
+                        TripPurpose::School,
+                        TripPurpose::Escort,
+                        TripPurpose::PersonalBusiness,
+                        TripPurpose::Shopping,
+                        TripPurpose::Meal,
+                        TripPurpose::Social,
+                        TripPurpose::Recreation,
+                        TripPurpose::Medical,
+                    ]
+                    .into_iter()
+                    .map(|p| Choice::new(p.to_string(), p))
+                    .collect(),
+                ),
+            ]),
+            Checkbox::switch(ctx, "leave now", None, true),
+            Widget::row(vec![
+                "Otherwise leave at".draw_text(ctx),
+                Spinner::new(ctx, (0, 23), hours as isize).named("hours"),
+                ":".draw_text(ctx),
+                Spinner::new(ctx, (0, 59), minutes as isize).named("minutes"),
+            ]),
+            Widget::row(vec![
+                "Number of people:".draw_text(ctx),
+                Spinner::new(ctx, (1, 100), 1).named("count"),
+            ]),
+            Widget::row(vec![
+                "Minutes between each person leaving:".draw_text(ctx),
+                Spinner::new(ctx, (0, 60), 0).named("spacing"),
+            ]),
+            ctx.style()
+                .btn_solid_dark_text("Spawn")
+                .disabled(true)
+                .build_def(ctx),
+        ]))
+        .aligned(HorizontalAlignment::Right, VerticalAlignment::Top)
+        .build(ctx);
+        Box::new(CustomTripSpawner {
+            panel,
+            from,
+            to: None,
+            confirmed: false,
+        })
+    }
+
+    fn route(&self, app: &App, to: BuildingID) -> Option<Polygon> {
+        let map = &app.primary.map;
+        let req = TripEndpoint::path_req(
+            TripEndpoint::Bldg(self.from),
+            TripEndpoint::Bldg(to),
+            self.panel.dropdown_value("mode"),
+            map,
+        )?;
+        let path = map.pathfind(req).ok()?;
+        path.trace(map)
+            .map(|pl| pl.make_polygons(NORMAL_LANE_THICKNESS))
+    }
+
+    fn set_confirmed(&mut self, ctx: &mut EventCtx, app: &App, confirmed: bool) {
+        self.confirmed = confirmed;
+        let instructions = match self.to {
+            Some((to, _)) if confirmed => {
+                format!("Going to {}", app.primary.map.get_b(to).address)
+            }
+            _ => "Click another building to go there".to_string(),
+        };
+        self.panel
+            .replace(ctx, "instructions", instructions.draw_text(ctx));
+        self.panel.replace(
+            ctx,
+            "Spawn",
+            ctx.style()
+                .btn_solid_dark_text("Spawn")
+                .disabled(!confirmed)
+                .build_def(ctx),
+        );
+    }
+
+    fn spawn(&mut self, ctx: &mut EventCtx, app: &mut App) -> Transition {
+        let now = app.primary.sim.time();
+        let departure = if self.panel.is_checked("leave now") {
+            now
+        } else {
+            Time::START_OF_DAY
+                + Duration::hours(self.panel.spinner("hours") as usize)
+                + Duration::minutes(self.panel.spinner("minutes") as usize)
+        };
+        if departure < now {
+            return Transition::Push(PopupMsg::new(
+                ctx,
+                "Error",
+                vec![format!(
+                    "It's already {}; pick a later time to leave",
+                    now.ampm_tostring()
+                )],
+            ));
+        }
+
+        let map = &app.primary.map;
+        let spec = CustomTrips {
+            from: map.get_b(self.from).orig_id,
+            to: map.get_b(self.to.as_ref().unwrap().0).orig_id,
+            mode: self.panel.dropdown_value("mode"),
+            purpose: self.panel.dropdown_value("purpose"),
+            departure,
+            count: self.panel.spinner("count") as usize,
+            spacing: Duration::minutes(self.panel.spinner("spacing") as usize),
+        };
+        let num_trips = spec.spawn(app);
+        app.recalculate_current_selection(ctx);
+        Transition::Replace(PopupMsg::new(
+            ctx,
+            "Trips spawned",
+            vec![format!(
+                "Spawned {} trips. Follow them from the Custom Trips dashboard.",
+                num_trips
+            )],
+        ))
+    }
+}
+
+impl State<App> for CustomTripSpawner {
+    fn event(&mut self, ctx: &mut EventCtx, app: &mut App) -> Transition {
+        match self.panel.event(ctx) {
+            Outcome::Clicked(x) => match x.as_ref() {
+                "close" => {
+                    return Transition::Pop;
+                }
+                "Spawn" => {
+                    return self.spawn(ctx, app);
+                }
+                _ => unreachable!(),
+            },
+            Outcome::Changed => {
+                // The mode might've changed, so check the destination is still reachable
+                if let Some((to, _)) = self.to {
+                    let route = self.route(app, to);
+                    let reachable = route.is_some();
+                    self.to = Some((to, route));
+                    if self.confirmed && !reachable {
+                        self.set_confirmed(ctx, app, false);
+                    }
+                }
+            }
+            _ => {}
+        }
+
+        ctx.canvas_movement();
+
+        if self.confirmed {
+            return Transition::Keep;
+        }
+
+        if ctx.redo_mouseover() {
+            app.primary.current_selection = app
+                .mouseover_unzoomed_buildings(ctx)
+                .filter(|id| matches!(id, ID::Building(b) if *b != self.from));
+        }
+        if let Some(ID::Building(b)) = app.primary.current_selection {
+            if self.to.as_ref().map(|(to, _)| *to != b).unwrap_or(true) {
+                self.to = Some((b, self.route(app, b)));
+            }
+            if self.to.as_ref().unwrap().1.is_some() && app.per_obj.left_click(ctx, "go here") {
+                app.primary.current_selection = None;
+                self.set_confirmed(ctx, app, true);
+            }
+        } else {
+            self.to = None;
+        }
+
+        Transition::Keep
+    }
+
+    fn draw(&self, g: &mut GfxCtx, app: &App) {
+        self.panel.draw(g);
+        CommonState::draw_osd(g, app);
+
+        let map = &app.primary.map;
+        g.draw_polygon(Color::BLUE.alpha(0.8), map.get_b(self.from).polygon.clone());
+        if let Some((to, ref route)) = self.to {
+            g.draw_polygon(Color::GREEN.alpha(0.8), map.get_b(to).polygon.clone());
+            if let Some(p) = route {
+                g.draw_polygon(Color::PURPLE, p.clone());
+            }
+        }
+    }
+}
@@ -0,0 +1,168 @@
+use geom::Percent;
+use map_gui::tools::PopupMsg;
+use sim::{TripID, TripResult};
+use widgetry::{
+    DrawBaselayer, EventCtx, GfxCtx, Line, Outcome, Panel, State, StyledButtons, Text, TextExt,
+    Widget,
+};
+
+use crate::app::{App, Transition};
+use crate::common::launch_info_panel;
+use crate::info::{OpenTrip, Tab};
+use crate::sandbox::dashboards::DashTab;
+use crate::sandbox::CustomTrips;
+
+/// Lists the trips spawned by hand into this simulation, so they can be inspected, removed, or
+/// saved to spawn again later.
+pub struct CustomTripsViewer {
+    panel: Panel,
+}
+
+impl CustomTripsViewer {
+    pub fn new(ctx: &mut EventCtx, app: &App) -> Box<dyn State<App>> {
+        Box::new(CustomTripsViewer {
+            panel: make_panel(ctx, app),
+        })
+    }
+}
+
+impl State<App> for CustomTripsViewer {
+    fn event(&mut self, ctx: &mut EventCtx, app: &mut App) -> Transition {
+        match self.panel.event(ctx) {
+            Outcome::Clicked(x) => {
+                if x == "close" {
+                    return Transition::Pop;
+                }
+                if x == "save to file" {
+                    CustomTrips::save_all(app);
+                    self.panel = make_panel(ctx, app);
+                    return Transition::Keep;
+                }
+                if x == "spawn saved trips" {
+                    let msg = match CustomTrips::spawn_saved(app) {
+                        Ok((num_trips, 0)) => format!("Spawned {} trips", num_trips),
+                        Ok((num_trips, skipped)) => format!(
+                            "Spawned {} trips. {} saved groups were skipped, because their \
+                             buildings don't exist on this map anymore.",
+                            num_trips, skipped
+                        ),
+                        Err(err) => format!("Couldn't load saved trips: {}", err),
+                    };
+                    self.panel = make_panel(ctx, app);
+                    return Transition::Push(PopupMsg::new(ctx, "Saved trips", vec![msg]));
+                }
+                if let Some(idx) = x.strip_prefix("remove #") {
+                    let cancelled = CustomTrips::remove(app, idx.parse::<usize>().unwrap());
+                    self.panel = make_panel(ctx, app);
+                    return Transition::Push(PopupMsg::new(
+                        ctx,
+                        "Custom trips removed",
+                        vec![format!(
+                            "{} trips that hadn't started yet were cancelled",
+                            cancelled
+                        )],
+                    ));
+                }
+
+                let trip = TripID(x.parse::<usize>().unwrap());
+                let person = app.primary.sim.trip_to_person(trip).unwrap();
+                Transition::Multi(vec![
+                    Transition::Pop,
+                    launch_info_panel(Tab::PersonTrips(person, OpenTrip::single(trip))),
+                ])
+            }
+            Outcome::Changed => DashTab::CustomTrips
+                .transition(ctx, app, &self.panel)
+                .unwrap(),
+            _ => Transition::Keep,
+        }
+    }
+
+    fn draw_baselayer(&self) -> DrawBaselayer {
+        DrawBaselayer::Custom
+    }
+
+    fn draw(&self, g: &mut GfxCtx, app: &App) {
+        g.clear(app.cs.dialog_bg);
+        self.panel.draw(g);
+    }
+}
+
+fn make_panel(ctx: &mut EventCtx, app: &App) -> Panel {
+    let sim = &app.primary.sim;
+    let mut col = vec![DashTab::CustomTrips.picker(ctx, app)];
+    if app.primary.custom_trips.is_empty() {
+        col.push("No custom trips yet. Open a building and spawn trips from there.".draw_text(ctx));
+    }
+    for (idx, (spec, trips)) in app.primary.custom_trips.iter().enumerate() {
+        // Rewinding the simulation can go back to before the trips existed
+        let trips: Vec<TripID> = trips
+            .iter()
+            .cloned()
+            .filter(|t| !matches!(sim.trip_to_agent(*t), TripResult::TripDoesntExist))
+            .collect();
+        let (mut not_started, mut ongoing, mut finished, mut cancelled) = (0, 0, 0, 0);
+        for t in &trips {
+            match sim.trip_to_agent(*t) {
+                TripResult::TripNotStarted => not_started += 1,
+                TripResult::TripDone => finished += 1,
+                TripResult::TripCancelled => cancelled += 1,
+                _ => ongoing += 1,
+            }
+        }
+
+        col.push(Widget::row(vec![
+            Text::from_multiline(vec![
+                Line(spec.describe(app)),
+                Line(spec.describe_schedule()).secondary(),
+                Line(format!(
+                    "{} not started, {} ongoing, {} finished, {} cancelled",
+                    not_started, ongoing, finished, cancelled
+                ))
+                .secondary(),
+            ])
+            .draw(ctx)
+            .centered_vert(),
+            ctx.style()
+                .btn_close()
+                .build_widget(ctx, &format!("remove #{}", idx))
+                .centered_vert()
+                .align_right(),
+        ]));
+        col.push(
+            Widget::custom_row(
+                trips
+                    .into_iter()
+                    .map(|t| {
+                        ctx.style()
+                            .btn_outline_light_text(&t.to_string())
+                            .build_widget(ctx, &t.0.to_string())
+                            .margin_right(8)
+                            .margin_below(8)
+                    })
+                    .collect(),
+            )
+            .flex_wrap(ctx, Percent::int(80)),
+        );
+    }
+
+    col.push(Widget::row(vec![
+        ctx.style()
+            .btn_outline_light_text("save to file")
+            .disabled(app.primary.custom_trips.is_empty())
+            .build_def(ctx),
+        ctx.style()
+            .btn_outline_light_text("spawn saved trips")
+            .disabled(!abstio::file_exists(CustomTrips::path(app)))
+            .build_def(ctx),
+    ]));
+    col.push(
+        Line(format!("Saved to {}", CustomTrips::path(app)))
+            .secondary()
+            .draw(ctx),
+    );
+
+    Panel::new(Widget::col(col))
+        .exact_size_percent(90, 90)
+        .build(ctx)
+}
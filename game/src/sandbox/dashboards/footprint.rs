@@ -0,0 +1,92 @@
+use std::collections::BTreeMap;
+
+use sim::TripMode;
+use widgetry::{
+    DrawBaselayer, EventCtx, GfxCtx, Line, Outcome, Panel, State, Text, TextExt, Widget,
+};
+
+use crate::app::{App, Transition};
+use crate::common::{color_for_mode, Externalities};
+use crate::sandbox::dashboards::DashTab;
+
+/// The carbon emitted and calories burned by everybody so far. Pathfinding for every trip would
+/// take too long, so each phase is estimated from the straight line between its endpoints.
+pub struct Footprint {
+    panel: Panel,
+}
+
+impl Footprint {
+    pub fn new(ctx: &mut EventCtx, app: &App) -> Box<dyn State<App>> {
+        let map = &app.primary.map;
+        let sim = &app.primary.sim;
+        let mut per_mode: BTreeMap<TripMode, Externalities> = BTreeMap::new();
+        for (_, trip, maybe_req, phase_type) in &sim.get_analytics().trip_log {
+            if let Some(req) = maybe_req {
+                *per_mode.entry(sim.trip_info(*trip).mode).or_default() +=
+                    Externalities::estimate(map, *phase_type, req);
+            }
+        }
+
+        let units = &app.opts.units;
+        let mut total = Externalities::default();
+        let mut col = vec![
+            DashTab::Footprint.picker(ctx, app),
+            "Estimated from the straight-line distance of every trip so far, ignoring hills"
+                .draw_text(ctx),
+        ];
+        for mode in TripMode::all() {
+            let e = per_mode.get(&mode).copied().unwrap_or_default();
+            total += e;
+            col.push(
+                Text::from_all(vec![
+                    Line(format!("{} trips: ", mode.noun())).fg(color_for_mode(app, mode)),
+                    Line(format!(
+                        "{}, {} burned",
+                        e.describe_co2(units),
+                        e.describe_kcal(units)
+                    )),
+                ])
+                .draw(ctx),
+            );
+        }
+        col.push(
+            Line(format!(
+                "Altogether: {}, {} burned",
+                total.describe_co2(units),
+                total.describe_kcal(units)
+            ))
+            .small_heading()
+            .draw(ctx),
+        );
+
+        Box::new(Footprint {
+            panel: Panel::new(Widget::col(col))
+                .exact_size_percent(90, 90)
+                .build(ctx),
+        })
+    }
+}
+
+impl State<App> for Footprint {
+    fn event(&mut self, ctx: &mut EventCtx, app: &mut App) -> Transition {
+        match self.panel.event(ctx) {
+            Outcome::Clicked(x) => match x.as_ref() {
+                "close" => Transition::Pop,
+                _ => unreachable!(),
+            },
+            Outcome::Changed => DashTab::Footprint
+                .transition(ctx, app, &self.panel)
+                .unwrap(),
+            _ => Transition::Keep,
+        }
+    }
+
+    fn draw_baselayer(&self) -> DrawBaselayer {
+        DrawBaselayer::Custom
+    }
+
+    fn draw(&self, g: &mut GfxCtx, app: &App) {
+        g.clear(app.cs.dialog_bg);
+        self.panel.draw(g);
+    }
+}
@@ -122,11 +122,9 @@ fn preview_trip(g: &mut GfxCtx, app: &App, panel: &Panel) {
 
     g.redraw(&app.primary.draw_map.boundary_polygon);
     g.redraw(&app.primary.draw_map.draw_all_areas);
-    g.redraw(
-        &app.primary
-            .draw_map
-            .draw_all_unzoomed_roads_and_intersections,
-    );
+    app.primary
+        .draw_map
+        .draw_all_unzoomed_roads_and_intersections(g, app);
 
     if let Some(x) = panel.currently_hovering() {
         if let Ok(idx) = x.parse::<usize>() {
@@ -1,19 +1,27 @@
 pub use commuter::CommuterPatterns;
 pub use traffic_signals::TrafficSignalDemand;
+pub use trip_durations::FinishedTripTimes;
 pub use trip_table::FinishedTripTable;
+pub use watch_list::WatchList;
 
-use widgetry::{Choice, EventCtx, Line, Panel, StyledButtons, TextExt, Widget};
+use widgetry::{Choice, Color, EventCtx, Line, Panel, StyledButtons, Text, TextExt, Widget};
 
 use crate::app::App;
 use crate::app::Transition;
 
 mod commuter;
+mod custom_trips;
+mod footprint;
 mod generic_trip_table;
 mod misc;
 mod parking_overhead;
+mod screenlines;
 mod summaries;
 mod traffic_signals;
+mod trip_durations;
+mod trip_phases;
 mod trip_table;
+mod watch_list;
 
 // Oh the dashboards melted, but we still had the radio
 #[derive(Clone, Copy, PartialEq, Debug)]
@@ -22,11 +30,17 @@ pub enum DashTab {
     CancelledTripTable,
     UnfinishedTripTable,
     TripSummaries,
+    TripDurations,
     ParkingOverhead,
+    DrivingTripPhases,
     ActiveTraffic,
     TransitRoutes,
     CommuterPatterns,
     TrafficSignals,
+    Screenlines,
+    Footprint,
+    WatchList,
+    CustomTrips,
 }
 
 impl DashTab {
@@ -34,23 +48,32 @@ impl DashTab {
         let mut choices = vec![
             Choice::new("Trip Table", DashTab::FinishedTripTable),
             Choice::new("Trip Summaries", DashTab::TripSummaries),
+            Choice::new("Trip Durations", DashTab::TripDurations),
             Choice::new("Parking Overhead", DashTab::ParkingOverhead),
+            Choice::new("Driving Trip Phases", DashTab::DrivingTripPhases),
             Choice::new("Active Traffic", DashTab::ActiveTraffic),
             Choice::new("Transit Routes", DashTab::TransitRoutes),
             Choice::new("Commuter Patterns", DashTab::CommuterPatterns),
             Choice::new("Traffic Signal Demand", DashTab::TrafficSignals),
+            Choice::new("Screenlines", DashTab::Screenlines),
+            Choice::new("Footprint", DashTab::Footprint),
+            Choice::new("Watch List", DashTab::WatchList),
+            Choice::new("Custom Trips", DashTab::CustomTrips),
         ];
         if app.has_prebaked().is_none() {
             choices.remove(1);
         }
-        Widget::row(vec![
-            Widget::draw_svg(ctx, "system/assets/meters/trip_histogram.svg"),
-            Line("Data").big_heading_plain().draw(ctx),
-            Widget::dropdown(ctx, "tab", self, choices),
-            format!("By {}", app.primary.sim.time().ampm_tostring())
-                .draw_text(ctx)
-                .centered_vert(),
-            ctx.style().btn_close_widget(ctx),
+        Widget::col(vec![
+            Widget::row(vec![
+                Widget::draw_svg(ctx, "system/assets/meters/trip_histogram.svg"),
+                Line("Data").big_heading_plain().draw(ctx),
+                Widget::dropdown(ctx, "tab", self, choices),
+                format!("By {}", app.primary.sim.time().ampm_tostring())
+                    .draw_text(ctx)
+                    .centered_vert(),
+                ctx.style().btn_close_widget(ctx),
+            ]),
+            baseline_indicator(ctx, app),
         ])
     }
 
@@ -76,12 +99,43 @@ impl DashTab {
             DashTab::TripSummaries => {
                 summaries::TripSummaries::new(ctx, app, summaries::Filter::new())
             }
+            DashTab::TripDurations => trip_durations::TripDurations::new(ctx, app),
             DashTab::ParkingOverhead => parking_overhead::ParkingOverhead::new(ctx, app),
+            DashTab::DrivingTripPhases => trip_phases::DrivingTripPhases::new(ctx, app),
             DashTab::ActiveTraffic => misc::ActiveTraffic::new(ctx, app),
             DashTab::TransitRoutes => misc::TransitRoutes::new(ctx, app),
             DashTab::CommuterPatterns => CommuterPatterns::new(ctx, app),
             DashTab::TrafficSignals => TrafficSignalDemand::new(ctx, app),
+            DashTab::Screenlines => screenlines::Screenlines::new(ctx, app),
+            DashTab::Footprint => footprint::Footprint::new(ctx, app),
+            DashTab::WatchList => watch_list::WatchListViewer::new(ctx, app),
+            DashTab::CustomTrips => custom_trips::CustomTripsViewer::new(ctx, app),
             DashTab::CancelledTripTable | DashTab::UnfinishedTripTable => unreachable!(),
         }))
     }
 }
+
+/// Says which prebaked results the dashboards compare against, and warns when they were simulated
+/// with different edits than the current ones.
+fn baseline_indicator(ctx: &EventCtx, app: &App) -> Widget {
+    let scenario = match app.has_prebaked() {
+        Some((_, scenario)) => scenario,
+        None => {
+            return Widget::nothing();
+        }
+    };
+    let mut txt = Text::from(Line(format!("Baseline: {} scenario", scenario)).secondary());
+    if let Some(meta) = app.prebaked_metadata() {
+        txt.append(Line(format!(", generated {}", meta.generated)).secondary());
+        if meta.edits_checksum != app.primary.map.get_edits().checksum(&app.primary.map) {
+            txt.add(
+                Line(format!(
+                    "The baseline was simulated with edits \"{}\", not the current ones",
+                    meta.edits_name
+                ))
+                .fg(Color::RED),
+            );
+        }
+    }
+    txt.draw(ctx)
+}
@@ -0,0 +1,378 @@
+use serde::{Deserialize, Serialize};
+
+use abstutil::{prettyprint_usize, Timer};
+use geom::{Angle, Distance, LonLat, PolyLine, Pt2D, Time};
+use map_model::{Direction, Map, RoadID};
+use sim::{AgentType, Analytics};
+use widgetry::{
+    Choice, Color, Drawable, EventCtx, GeomBatch, GfxCtx, HorizontalAlignment, Line, Outcome,
+    Panel, PlotOptions, State, StyledButtons, Text, TextExt, VerticalAlignment, Widget,
+};
+
+use crate::app::{App, Transition};
+use crate::common::{agent_type_plot, sum_counts_per_hour};
+use crate::sandbox::dashboards::DashTab;
+
+/// A line drawn across the map to count everything crossing it, the way transportation planners
+/// do. Stored in GPS coordinates, so it survives the map being regenerated.
+#[derive(Serialize, Deserialize)]
+struct Screenline {
+    name: String,
+    pt1: LonLat,
+    pt2: LonLat,
+}
+
+/// The screenlines drawn on one map, persisted as player data.
+#[derive(Serialize, Deserialize)]
+struct SavedScreenlines {
+    screenlines: Vec<Screenline>,
+}
+
+impl SavedScreenlines {
+    fn load(app: &App) -> SavedScreenlines {
+        abstio::maybe_read_json::<SavedScreenlines>(
+            SavedScreenlines::path(app),
+            &mut Timer::throwaway(),
+        )
+        .unwrap_or_else(|_| SavedScreenlines {
+            screenlines: Vec::new(),
+        })
+    }
+
+    fn save(&self, app: &App) {
+        abstio::write_json(SavedScreenlines::path(app), self);
+    }
+
+    fn path(app: &App) -> String {
+        let name = app.primary.map.get_name();
+        abstio::path_player(format!(
+            "screenlines/{}/{}/{}.json",
+            name.city.country, name.city.city, name.map
+        ))
+    }
+}
+
+pub struct Screenlines {
+    panel: Panel,
+    saved: SavedScreenlines,
+    // None when not drawing. Otherwise, the first point, once it's been clicked.
+    drawing: Option<Option<Pt2D>>,
+    // Index into saved
+    current: Option<usize>,
+    // None counts both directions
+    direction: Option<Crossing>,
+    draw: Drawable,
+}
+
+/// One of the two ways across a screenline, perpendicular to it
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum Crossing {
+    // Towards the left of the line, going from its first point to its second
+    Left,
+    Right,
+}
+
+impl Screenlines {
+    pub fn new(ctx: &mut EventCtx, app: &App) -> Box<dyn State<App>> {
+        let mut state = Screenlines {
+            panel: Panel::empty(ctx),
+            saved: SavedScreenlines::load(app),
+            drawing: None,
+            current: None,
+            direction: None,
+            draw: Drawable::empty(ctx),
+        };
+        state.recreate(ctx, app);
+        Box::new(state)
+    }
+
+    fn recreate(&mut self, ctx: &mut EventCtx, app: &App) {
+        let mut col = vec![
+            DashTab::Screenlines.picker(ctx, app),
+            Line("Screenlines").small_heading().draw(ctx),
+        ];
+
+        if self.drawing.is_some() {
+            col.push("Click two points on the map to draw a screenline".draw_text(ctx));
+            col.push(
+                ctx.style()
+                    .btn_outline_light_text("cancel drawing")
+                    .build_def(ctx),
+            );
+        } else {
+            for (idx, screenline) in self.saved.screenlines.iter().enumerate() {
+                col.push(
+                    ctx.style()
+                        .btn_outline_light_text(&screenline.name)
+                        .disabled(self.current == Some(idx))
+                        .build_widget(ctx, &format!("open screenline {}", idx)),
+                );
+            }
+            col.push(
+                ctx.style()
+                    .btn_solid_dark_text("draw a new screenline")
+                    .build_def(ctx),
+            );
+        }
+
+        let mut batch = GeomBatch::new();
+        if let Some(idx) = self.current {
+            let map = &app.primary.map;
+            let line = self.get_line(app, idx);
+            let roads = crossed_roads(app, &line);
+            for (r, _) in &roads {
+                batch.push(
+                    app.cs.perma_selected_object,
+                    map.get_r(*r).get_thick_polygon(map),
+                );
+            }
+            batch.push(Color::RED, line.make_polygons(Distance::meters(3.0)));
+
+            col.push(Widget::horiz_separator(ctx, 1.0));
+            col.push(Widget::row(vec![
+                Widget::text_entry(ctx, self.saved.screenlines[idx].name.clone(), false)
+                    .named("name"),
+                ctx.style().btn_outline_light_text("rename").build_def(ctx),
+                ctx.style()
+                    .btn_plain_destructive_text("delete")
+                    .build_def(ctx),
+            ]));
+            col.push(format!("Crosses {} roads", prettyprint_usize(roads.len())).draw_text(ctx));
+            col.push(Widget::row(vec![
+                "Direction:".draw_text(ctx).centered_vert(),
+                Widget::dropdown(
+                    ctx,
+                    "direction",
+                    self.direction,
+                    vec![
+                        Choice::new("both", None),
+                        Choice::new(Crossing::Left.describe(&line), Some(Crossing::Left)),
+                        Choice::new(Crossing::Right.describe(&line), Some(Crossing::Right)),
+                    ],
+                ),
+            ]));
+            if self.direction.is_some() {
+                col.push(
+                    Line("Pedestrians and transit riders are only counted in both directions")
+                        .secondary()
+                        .draw(ctx),
+                );
+            }
+            col.push(plot(ctx, app, &line, &roads, self.direction));
+        }
+        self.draw = ctx.upload(batch);
+
+        self.panel = Panel::new(Widget::col(col))
+            .aligned(HorizontalAlignment::Right, VerticalAlignment::Top)
+            .build(ctx);
+    }
+
+    fn get_line(&self, app: &App, idx: usize) -> PolyLine {
+        let gps_bounds = app.primary.map.get_gps_bounds();
+        let screenline = &self.saved.screenlines[idx];
+        PolyLine::must_new(vec![
+            screenline.pt1.to_pt(gps_bounds),
+            screenline.pt2.to_pt(gps_bounds),
+        ])
+    }
+}
+
+impl State<App> for Screenlines {
+    fn event(&mut self, ctx: &mut EventCtx, app: &mut App) -> Transition {
+        ctx.canvas_movement();
+
+        if let Some(first) = self.drawing {
+            if let Some(pt) = ctx.canvas.get_cursor_in_map_space() {
+                if ctx.normal_left_click() {
+                    match first {
+                        None => {
+                            self.drawing = Some(Some(pt));
+                        }
+                        Some(first) => {
+                            // Two clicks in the same place don't make a line
+                            if first.dist_to(pt) > Distance::meters(1.0) {
+                                let gps_bounds = app.primary.map.get_gps_bounds();
+                                self.saved.screenlines.push(Screenline {
+                                    name: format!(
+                                        "Screenline {}",
+                                        self.saved.screenlines.len() + 1
+                                    ),
+                                    pt1: first.to_gps(gps_bounds),
+                                    pt2: pt.to_gps(gps_bounds),
+                                });
+                                self.saved.save(app);
+                                self.current = Some(self.saved.screenlines.len() - 1);
+                            }
+                            self.drawing = None;
+                            self.recreate(ctx, app);
+                        }
+                    }
+                }
+            }
+        }
+
+        match self.panel.event(ctx) {
+            Outcome::Clicked(x) => match x.as_ref() {
+                "close" => {
+                    return Transition::Pop;
+                }
+                "draw a new screenline" => {
+                    self.drawing = Some(None);
+                    self.current = None;
+                    self.direction = None;
+                    self.recreate(ctx, app);
+                }
+                "cancel drawing" => {
+                    self.drawing = None;
+                    self.recreate(ctx, app);
+                }
+                "rename" => {
+                    let idx = self.current.unwrap();
+                    self.saved.screenlines[idx].name = self.panel.text_box("name");
+                    self.saved.save(app);
+                    self.recreate(ctx, app);
+                }
+                "delete" => {
+                    self.saved.screenlines.remove(self.current.take().unwrap());
+                    self.saved.save(app);
+                    self.recreate(ctx, app);
+                }
+                x => {
+                    let idx = x
+                        .strip_prefix("open screenline ")
+                        .unwrap()
+                        .parse::<usize>()
+                        .unwrap();
+                    self.current = Some(idx);
+                    self.direction = None;
+                    self.recreate(ctx, app);
+                }
+            },
+            Outcome::Changed => {
+                if let Some(t) = DashTab::Screenlines.transition(ctx, app, &self.panel) {
+                    return t;
+                }
+                // Typing a new name also lands here, so only rebuild when the direction changes
+                if self.current.is_some() {
+                    let direction = self.panel.dropdown_value("direction");
+                    if direction != self.direction {
+                        self.direction = direction;
+                        self.recreate(ctx, app);
+                    }
+                }
+            }
+            _ => {}
+        }
+
+        Transition::Keep
+    }
+
+    fn draw(&self, g: &mut GfxCtx, _: &App) {
+        g.redraw(&self.draw);
+        if let Some(Some(first)) = self.drawing {
+            if let Some(pt) = g.get_cursor_in_map_space() {
+                if let Ok(pl) = PolyLine::new(vec![first, pt]) {
+                    g.draw_polygon(
+                        Color::RED.alpha(0.8),
+                        pl.make_polygons(Distance::meters(3.0)),
+                    );
+                }
+            }
+        }
+        self.panel.draw(g);
+    }
+}
+
+impl Crossing {
+    /// The direction of travel across the line
+    fn angle(self, line: &PolyLine) -> Angle {
+        let angle = line.first_line().angle();
+        match self {
+            // Y points south in map-space, so rotating counter-clockwise goes left
+            Crossing::Left => angle.rotate_degs(-90.0),
+            Crossing::Right => angle.rotate_degs(90.0),
+        }
+    }
+
+    /// Names the crossing by the nearest compass direction, like "northbound"
+    fn describe(self, line: &PolyLine) -> &'static str {
+        // Map-space angles start pointing east and increase clockwise
+        let bearing = (self.angle(line).normalized_degrees() + 90.0).rem_euclid(360.0);
+        ["northbound", "eastbound", "southbound", "westbound"]
+            [((bearing + 45.0) / 90.0) as usize % 4]
+    }
+}
+
+/// Each road crossing the line, with the angle its forwards lanes travel where it crosses
+fn crossed_roads(app: &App, line: &PolyLine) -> Vec<(RoadID, Angle)> {
+    app.primary
+        .map
+        .all_roads()
+        .iter()
+        .filter_map(|r| {
+            r.center_pts
+                .intersection(line)
+                .map(|(_, angle)| (r.id, angle))
+        })
+        .collect()
+}
+
+// Hourly counts summed over all of the roads, compared against the baseline if there is one
+fn plot(
+    ctx: &mut EventCtx,
+    app: &App,
+    line: &PolyLine,
+    roads: &[(RoadID, Angle)],
+    direction: Option<Crossing>,
+) -> Widget {
+    let now = app.primary.sim.time();
+    let map = &app.primary.map;
+    let count = |analytics: &Analytics| match direction {
+        None => sum_counts_per_hour(
+            roads
+                .iter()
+                .map(|(r, _)| analytics.road_thruput.count_per_hour(*r, now))
+                .collect(),
+        ),
+        Some(crossing) => sum_directional_counts(analytics, map, roads, crossing.angle(line), now),
+    };
+    let current = count(app.primary.sim.get_analytics());
+    let baseline = if app.has_prebaked().is_some() {
+        count(app.prebaked())
+    } else {
+        Vec::new()
+    };
+
+    Widget::col(vec![
+        "Number crossing per hour".draw_text(ctx),
+        agent_type_plot(ctx, app, current, baseline, PlotOptions::filterable()),
+    ])
+}
+
+// Only lanes for vehicles have a direction; pedestrians can walk either way along a sidewalk, and
+// transit riders aren't counted per lane.
+fn sum_directional_counts(
+    analytics: &Analytics,
+    map: &Map,
+    roads: &[(RoadID, Angle)],
+    towards: Angle,
+    now: Time,
+) -> Vec<(AgentType, Vec<(Time, usize)>)> {
+    let mut per_lane = Vec::new();
+    for (r, fwd_angle) in roads {
+        for (l, dir, lt) in map.get_r(*r).lanes_ltr() {
+            if !lt.is_for_moving_vehicles() {
+                continue;
+            }
+            let angle = if dir == Direction::Fwd {
+                *fwd_angle
+            } else {
+                fwd_angle.opposite()
+            };
+            if angle.approx_eq(towards, 90.0) {
+                per_lane.push(analytics.lane_thruput.count_per_hour(l, now));
+            }
+        }
+    }
+    sum_counts_per_hour(per_lane)
+}
@@ -0,0 +1,377 @@
+use std::cell::Ref;
+
+use abstutil::{prettyprint_usize, Counter};
+use geom::{Distance, Duration, Percent, Polygon, Time};
+use map_gui::tools::ColorLegend;
+use sim::{TripID, TripMode};
+use widgetry::{
+    Color, ControlState, DrawBaselayer, EventCtx, GeomBatch, GfxCtx, Line, Outcome, Panel, State,
+    StyledButtons, Text, Widget,
+};
+
+use crate::app::{App, Transition};
+use crate::common::{color_for_mode, launch_info_panel};
+use crate::info::{OpenTrip, Tab};
+use crate::sandbox::dashboards::DashTab;
+
+/// Pairing up every finished trip with the baseline is slow on big maps, so only redo it after
+/// this much simulated time passes.
+const REFRESH_EVERY: Duration = Duration::const_seconds(5.0 * 60.0);
+/// Roughly how many bars each histogram has
+const NUM_BUCKETS: usize = 20;
+const MAX_EXAMPLES: usize = 20;
+
+/// Histograms of how long finished trips took, and how much that changed from the baseline.
+pub struct TripDurations {
+    panel: Panel,
+    selected: Option<(Histogram, usize)>,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Histogram {
+    Durations,
+    Changes,
+}
+
+/// The finished trips as of some time, cached in `PerMap`.
+pub struct FinishedTripTimes {
+    /// How long every successful trip took
+    trips: Vec<(TripID, TripMode, Duration)>,
+    /// For trips finished in both the baseline and current simulation, the time after minus the
+    /// time before. Sorted by that change.
+    changes: Vec<(TripID, TripMode, Duration)>,
+}
+
+impl FinishedTripTimes {
+    fn new(app: &App) -> FinishedTripTimes {
+        let analytics = app.primary.sim.get_analytics();
+        let trips = analytics
+            .finished_trips
+            .iter()
+            .filter_map(|(_, id, mode, maybe_dt)| maybe_dt.map(|dt| (*id, *mode, dt)))
+            .collect();
+        let mut changes = Vec::new();
+        if app.has_prebaked().is_some() {
+            for (id, before, after, mode) in
+                analytics.both_finished_trips(app.primary.sim.time(), app.prebaked())
+            {
+                changes.push((id, mode, after - before));
+            }
+            changes.sort_by_key(|(_, _, dt)| *dt);
+        }
+        FinishedTripTimes { trips, changes }
+    }
+
+    /// Recalculates if the cached copy is too old. Also returns when it was calculated.
+    fn get(app: &App) -> (Time, Ref<FinishedTripTimes>) {
+        let now = app.primary.sim.time();
+        let mut cache = app.primary.caches.finished_trip_times.borrow_mut();
+        let key = match cache.key() {
+            // Time goes backwards after resetting or rewinding
+            Some(t) if t <= now && now - t < REFRESH_EVERY => t,
+            _ => now,
+        };
+        cache.update(Some(key), |_| FinishedTripTimes::new(app));
+        drop(cache);
+        (
+            key,
+            Ref::map(app.primary.caches.finished_trip_times.borrow(), |cache| {
+                cache.value().unwrap()
+            }),
+        )
+    }
+}
+
+impl TripDurations {
+    pub fn new(ctx: &mut EventCtx, app: &App) -> Box<dyn State<App>> {
+        Box::new(TripDurations {
+            panel: make_panel(ctx, app, None),
+            selected: None,
+        })
+    }
+}
+
+impl State<App> for TripDurations {
+    fn event(&mut self, ctx: &mut EventCtx, app: &mut App) -> Transition {
+        match self.panel.event(ctx) {
+            Outcome::Clicked(x) => {
+                if x == "close" {
+                    return Transition::Pop;
+                }
+                if let Some(id) = x.strip_prefix("examine Trip #") {
+                    let trip = TripID(id.parse::<usize>().unwrap());
+                    let person = app.primary.sim.trip_to_person(trip).unwrap();
+                    return Transition::Multi(vec![
+                        Transition::Pop,
+                        launch_info_panel(Tab::PersonTrips(person, OpenTrip::single(trip))),
+                    ]);
+                }
+
+                let selected = if let Some(idx) = x.strip_prefix("durations bucket ") {
+                    (Histogram::Durations, idx.parse::<usize>().unwrap())
+                } else if let Some(idx) = x.strip_prefix("changes bucket ") {
+                    (Histogram::Changes, idx.parse::<usize>().unwrap())
+                } else {
+                    unreachable!()
+                };
+                // Clicking the selected bucket again deselects it
+                self.selected = if self.selected == Some(selected) {
+                    None
+                } else {
+                    Some(selected)
+                };
+                self.panel = make_panel(ctx, app, self.selected);
+                Transition::Keep
+            }
+            Outcome::Changed => DashTab::TripDurations
+                .transition(ctx, app, &self.panel)
+                .unwrap(),
+            _ => Transition::Keep,
+        }
+    }
+
+    fn draw_baselayer(&self) -> DrawBaselayer {
+        DrawBaselayer::Custom
+    }
+
+    fn draw(&self, g: &mut GfxCtx, app: &App) {
+        g.clear(app.cs.dialog_bg);
+        self.panel.draw(g);
+    }
+}
+
+fn make_panel(ctx: &mut EventCtx, app: &App, selected: Option<(Histogram, usize)>) -> Panel {
+    let (as_of, data) = FinishedTripTimes::get(app);
+
+    let mut col = vec![DashTab::TripDurations.picker(ctx, app)];
+    col.push(
+        Text::from_multiline(vec![
+            Line(format!(
+                "{} trips finished by {}",
+                prettyprint_usize(data.trips.len()),
+                as_of.ampm_tostring()
+            ))
+            .small_heading(),
+            Line("Click a bar to see some of the trips in it").secondary(),
+        ])
+        .draw(ctx),
+    );
+    col.push(ColorLegend::entries(
+        ctx,
+        TripMode::all()
+            .into_iter()
+            .map(|m| (color_for_mode(app, m), m.ongoing_verb().to_string()))
+            .collect(),
+        true,
+    ));
+
+    let durations = Buckets::new(&data.trips);
+    col.push(Line("How long trips took").small_heading().draw(ctx));
+    col.push(durations.draw(
+        ctx,
+        app,
+        "durations",
+        match selected {
+            Some((Histogram::Durations, idx)) => Some(idx),
+            _ => None,
+        },
+    ));
+
+    let changes = Buckets::new(&data.changes);
+    if !data.changes.is_empty() {
+        let faster = data
+            .changes
+            .iter()
+            .filter(|(_, _, dt)| *dt < Duration::ZERO)
+            .count();
+        let slower = data
+            .changes
+            .iter()
+            .filter(|(_, _, dt)| *dt > Duration::ZERO)
+            .count();
+        col.push(
+            Text::from_multiline(vec![
+                Line("Changes from the baseline, for trips finished in both").small_heading(),
+                Line(format!(
+                    "{} faster, {} slower, {} unchanged",
+                    prettyprint_usize(faster),
+                    prettyprint_usize(slower),
+                    prettyprint_usize(data.changes.len() - faster - slower)
+                )),
+                Line(format!(
+                    "Median change: {}",
+                    data.changes[data.changes.len() / 2].2
+                )),
+            ])
+            .draw(ctx),
+        );
+        col.push(changes.draw(
+            ctx,
+            app,
+            "changes",
+            match selected {
+                Some((Histogram::Changes, idx)) => Some(idx),
+                _ => None,
+            },
+        ));
+    }
+
+    if let Some((histogram, idx)) = selected {
+        let buckets = match histogram {
+            Histogram::Durations => &durations,
+            Histogram::Changes => &changes,
+        };
+        let trips = &buckets.trips[idx];
+        col.push(
+            Line(format!(
+                "{} of {} trips between {} and {}",
+                trips.len().min(MAX_EXAMPLES),
+                prettyprint_usize(trips.len()),
+                buckets.bucket_start(idx),
+                buckets.bucket_start(idx + 1)
+            ))
+            .small_heading()
+            .draw(ctx),
+        );
+        col.push(
+            Widget::custom_row(
+                trips
+                    .iter()
+                    .take(MAX_EXAMPLES)
+                    .map(|(t, _)| {
+                        ctx.style()
+                            .btn_outline_light_text(&format!("examine {}", t))
+                            .build_def(ctx)
+                            .margin_right(8)
+                            .margin_below(8)
+                    })
+                    .collect(),
+            )
+            .flex_wrap(ctx, Percent::int(80)),
+        );
+    }
+
+    Panel::new(Widget::col(col))
+        .exact_size_percent(90, 90)
+        .build(ctx)
+}
+
+/// Trips grouped into evenly sized ranges of some duration
+struct Buckets {
+    start: Duration,
+    width: Duration,
+    trips: Vec<Vec<(TripID, TripMode)>>,
+}
+
+impl Buckets {
+    fn new(values: &[(TripID, TripMode, Duration)]) -> Buckets {
+        let min = values
+            .iter()
+            .map(|(_, _, dt)| *dt)
+            .min()
+            .unwrap_or(Duration::ZERO)
+            .min(Duration::ZERO);
+        let max = values
+            .iter()
+            .map(|(_, _, dt)| *dt)
+            .max()
+            .unwrap_or(Duration::ZERO);
+        // Round to whole seconds, and line up one edge with zero
+        let width = Duration::seconds(
+            ((max - min) / (NUM_BUCKETS as f64))
+                .inner_seconds()
+                .ceil()
+                .max(1.0),
+        );
+        let start = width * (min / width).floor();
+
+        let mut trips = vec![Vec::new(); ((max - start) / width).floor() as usize + 1];
+        for (id, mode, dt) in values {
+            trips[((*dt - start) / width).floor() as usize].push((*id, *mode));
+        }
+        Buckets {
+            start,
+            width,
+            trips,
+        }
+    }
+
+    fn bucket_start(&self, idx: usize) -> Duration {
+        self.start + self.width * (idx as f64)
+    }
+
+    fn draw(&self, ctx: &mut EventCtx, app: &App, name: &str, selected: Option<usize>) -> Widget {
+        let bar_width = 30.0;
+        let max_height = 200.0;
+        let max_count = self.trips.iter().map(|t| t.len()).max().unwrap_or(0).max(1);
+        let background = Polygon::rectangle(bar_width, max_height);
+
+        let mut bars = Vec::new();
+        for (idx, trips) in self.trips.iter().enumerate() {
+            let mut per_mode = Counter::new();
+            for (_, mode) in trips {
+                per_mode.inc(*mode);
+            }
+
+            // The invisible background keeps the bottoms of the bars lined up
+            let mut batch = GeomBatch::from(vec![(Color::CLEAR, background.clone())]);
+            let mut tooltip = Text::from(Line(format!(
+                "{} trips between {} and {}",
+                prettyprint_usize(trips.len()),
+                self.bucket_start(idx),
+                self.bucket_start(idx + 1)
+            )));
+            let mut y = max_height;
+            for mode in TripMode::all() {
+                let cnt = per_mode.get(mode);
+                if cnt == 0 {
+                    continue;
+                }
+                let height = max_height * (cnt as f64) / (max_count as f64);
+                y -= height;
+                batch.push(
+                    color_for_mode(app, mode),
+                    Polygon::rectangle(bar_width, height).translate(0.0, y),
+                );
+                tooltip.add(
+                    Line(format!(
+                        "{}: {}",
+                        mode.ongoing_verb(),
+                        prettyprint_usize(cnt)
+                    ))
+                    .secondary(),
+                );
+            }
+
+            let mut hovered = batch.clone();
+            if let Ok(outline) = background.to_outline(Distance::meters(2.0)) {
+                if selected == Some(idx) {
+                    batch.push(Color::WHITE, outline.clone());
+                }
+                hovered.push(Color::WHITE, outline);
+            }
+            bars.push(
+                ctx.style()
+                    .btn_plain_light()
+                    .custom_batch(batch, ControlState::Default)
+                    .custom_batch(hovered, ControlState::Hovered)
+                    .tooltip(tooltip)
+                    .build_widget(ctx, &format!("{} bucket {}", name, idx)),
+            );
+        }
+
+        Widget::col(vec![
+            Widget::custom_row(bars),
+            Line(format!(
+                "From {} to {}, in steps of {}",
+                self.start,
+                self.bucket_start(self.trips.len()),
+                self.width
+            ))
+            .secondary()
+            .draw(ctx),
+        ])
+        .padding(16)
+        .outline(2.0, Color::WHITE)
+    }
+}
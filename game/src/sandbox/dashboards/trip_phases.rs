@@ -0,0 +1,163 @@
+use abstutil::prettyprint_usize;
+use geom::{Duration, Time};
+use sim::{TripMode, TripPhaseDurations, TripPhaseType};
+use widgetry::{
+    DrawBaselayer, EventCtx, GfxCtx, Line, LinePlot, Outcome, Panel, PlotOptions, Series, State,
+    Text, Widget,
+};
+
+use crate::app::{App, Transition};
+use crate::common::{cmp_duration_shorter, color_for_trip_phase};
+use crate::sandbox::dashboards::DashTab;
+
+// Trips are grouped by the hour they finish
+const WINDOW: Duration = Duration::const_seconds(3600.0);
+
+/// For finished trips taken by car, how is the time split between driving, looking for parking,
+/// and walking between the car and the building? Parking search time is the main thing to watch
+/// when experimenting with parking policy.
+pub struct DrivingTripPhases {
+    panel: Panel,
+}
+
+impl DrivingTripPhases {
+    pub fn new(ctx: &mut EventCtx, app: &App) -> Box<dyn State<App>> {
+        let now = app.primary.sim.time();
+        let after = app
+            .primary
+            .sim
+            .get_analytics()
+            .trip_phase_durations_by_mode(TripMode::Drive, now, WINDOW);
+        // Prebaked results don't keep per-phase durations
+        let before =
+            if app.has_prebaked().is_some() && !app.prebaked().trip_phase_durations.is_empty() {
+                Some(
+                    app.prebaked()
+                        .trip_phase_durations_by_mode(TripMode::Drive, now, WINDOW),
+                )
+            } else {
+                None
+            };
+
+        let mut col = vec![DashTab::DrivingTripPhases.picker(ctx, app)];
+        col.push(summary(app, &after, before.as_deref()).draw(ctx));
+
+        let mut series = make_series(app, &after, "");
+        if let Some(ref before) = before {
+            series.extend(make_series(app, before, " (before changes)"));
+        }
+        col.push(
+            Line("Average time per trip, by hour finished")
+                .small_heading()
+                .draw(ctx),
+        );
+        col.push(LinePlot::new(ctx, series, PlotOptions::fixed()));
+
+        Box::new(DrivingTripPhases {
+            panel: Panel::new(Widget::col(col))
+                .exact_size_percent(90, 90)
+                .build(ctx),
+        })
+    }
+}
+
+impl State<App> for DrivingTripPhases {
+    fn event(&mut self, ctx: &mut EventCtx, app: &mut App) -> Transition {
+        match self.panel.event(ctx) {
+            Outcome::Clicked(x) => match x.as_ref() {
+                "close" => Transition::Pop,
+                _ => unreachable!(),
+            },
+            Outcome::Changed => DashTab::DrivingTripPhases
+                .transition(ctx, app, &self.panel)
+                .unwrap(),
+            _ => Transition::Keep,
+        }
+    }
+
+    fn draw_baselayer(&self) -> DrawBaselayer {
+        DrawBaselayer::Custom
+    }
+
+    fn draw(&self, g: &mut GfxCtx, app: &App) {
+        g.clear(app.cs.dialog_bg);
+        self.panel.draw(g);
+    }
+}
+
+// Averages over all of the windows
+fn overall(windows: &[(Time, usize, TripPhaseDurations)]) -> (usize, TripPhaseDurations) {
+    let mut cnt = 0;
+    let mut sum = TripPhaseDurations::default();
+    for (_, n, durations) in windows {
+        cnt += n;
+        sum.add_all(durations);
+    }
+    if cnt == 0 {
+        return (0, sum);
+    }
+    (cnt, sum.divide(cnt))
+}
+
+fn summary(
+    app: &App,
+    after: &[(Time, usize, TripPhaseDurations)],
+    before: Option<&[(Time, usize, TripPhaseDurations)]>,
+) -> Text {
+    let (cnt, avg) = overall(after);
+    let mut txt = Text::from(Line(format!(
+        "{} driving trips finished",
+        prettyprint_usize(cnt)
+    )));
+    if cnt == 0 {
+        return txt;
+    }
+    let before = before.map(overall).filter(|(n, _)| *n > 0);
+
+    let rows: Vec<(&str, fn(&TripPhaseDurations) -> Duration)> = vec![
+        ("looking for parking", |x| x.parking),
+        ("driving", |x| x.driving),
+        ("walking to and from parking", |x| x.walking),
+    ];
+    for (idx, (label, get)) in rows.into_iter().enumerate() {
+        let mut line = vec![
+            Line(format!("Average time {}: ", label)),
+            Line(get(&avg).to_string(&app.opts.units)),
+        ];
+        if idx == 0 {
+            line = line.into_iter().map(|l| l.small_heading()).collect();
+        }
+        if let Some((_, ref before_avg)) = before {
+            line.push(Line(" ("));
+            line.extend(cmp_duration_shorter(app, get(&avg), get(before_avg)));
+            line.push(Line(")"));
+        }
+        txt.add_appended(line);
+    }
+    txt
+}
+
+fn make_series(
+    app: &App,
+    windows: &[(Time, usize, TripPhaseDurations)],
+    suffix: &str,
+) -> Vec<Series<Duration>> {
+    let alpha = if suffix.is_empty() { 1.0 } else { 0.5 };
+    let rows: Vec<(&str, TripPhaseType, fn(&TripPhaseDurations) -> Duration)> = vec![
+        ("Looking for parking", TripPhaseType::Parking, |x| x.parking),
+        ("Driving", TripPhaseType::Driving, |x| x.driving),
+        ("Walking to/from parking", TripPhaseType::Walking, |x| {
+            x.walking
+        }),
+    ];
+    rows.into_iter()
+        .map(|(label, phase_type, get)| Series {
+            label: format!("{}{}", label, suffix),
+            color: color_for_trip_phase(app, phase_type).alpha(alpha),
+            pts: windows
+                .iter()
+                .map(|(t, cnt, sum)| (*t, get(sum) / (*cnt as f64)))
+                .collect(),
+        })
+        .collect()
+}
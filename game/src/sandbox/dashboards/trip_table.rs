@@ -60,6 +60,7 @@ struct FinishedTrip {
     mode: TripMode,
     modified: bool,
     capped: bool,
+    synthetic: bool,
     starts_off_map: bool,
     ends_off_map: bool,
     departure: Time,
@@ -95,6 +96,8 @@ struct Filters {
     modified_trips: bool,
     uncapped_trips: bool,
     capped_trips: bool,
+    scenario_trips: bool,
+    synthetic_trips: bool,
 }
 
 fn produce_raw_data(app: &App) -> (Vec<FinishedTrip>, Vec<CancelledTrip>) {
@@ -156,6 +159,7 @@ fn produce_raw_data(app: &App) -> (Vec<FinishedTrip>, Vec<CancelledTrip>) {
             departure: trip.departure,
             modified: trip.modified,
             capped: trip.capped,
+            synthetic: trip.synthetic,
             starts_off_map,
             ends_off_map,
             duration_after,
@@ -185,6 +189,8 @@ fn make_table_finished_trips(app: &App) -> Table<App, FinishedTrip, Filters> {
             modified_trips: true,
             uncapped_trips: true,
             capped_trips: true,
+            scenario_trips: true,
+            synthetic_trips: true,
         },
         to_controls: Box::new(move |ctx, app, state| {
             Widget::col(vec![
@@ -232,6 +238,21 @@ fn make_table_finished_trips(app: &App) -> Table<App, FinishedTrip, Filters> {
                     } else {
                         Widget::nothing()
                     },
+                    if app.primary.has_synthetic_trips {
+                        Checkbox::switch(ctx, "trips from the scenario", None, state.scenario_trips)
+                    } else {
+                        Widget::nothing()
+                    },
+                    if app.primary.has_synthetic_trips {
+                        Checkbox::switch(
+                            ctx,
+                            "synthetic override trips",
+                            None,
+                            state.synthetic_trips,
+                        )
+                    } else {
+                        Widget::nothing()
+                    },
                 ]),
             ])
         }),
@@ -258,6 +279,12 @@ fn make_table_finished_trips(app: &App) -> Table<App, FinishedTrip, Filters> {
                 capped_trips: panel
                     .maybe_is_checked("trips affected by congestion caps")
                     .unwrap_or(true),
+                scenario_trips: panel
+                    .maybe_is_checked("trips from the scenario")
+                    .unwrap_or(true),
+                synthetic_trips: panel
+                    .maybe_is_checked("synthetic override trips")
+                    .unwrap_or(true),
             }
         }),
         apply: Box::new(|state, x| {
@@ -282,6 +309,12 @@ fn make_table_finished_trips(app: &App) -> Table<App, FinishedTrip, Filters> {
             if !state.capped_trips && x.capped {
                 return false;
             }
+            if !state.scenario_trips && !x.synthetic {
+                return false;
+            }
+            if !state.synthetic_trips && x.synthetic {
+                return false;
+            }
             true
         }),
     };
@@ -317,6 +350,18 @@ fn make_table_finished_trips(app: &App) -> Table<App, FinishedTrip, Filters> {
             }),
         );
     }
+    if app.primary.has_synthetic_trips {
+        table.static_col(
+            "Synthetic",
+            Box::new(|x| {
+                if x.synthetic {
+                    "Yes".to_string()
+                } else {
+                    "No".to_string()
+                }
+            }),
+        );
+    }
     table.column(
         "Type",
         Box::new(|ctx, app, x| {
@@ -392,7 +437,7 @@ fn make_table_finished_trips(app: &App) -> Table<App, FinishedTrip, Filters> {
 
 fn make_table_cancelled_trips(app: &App) -> Table<App, CancelledTrip, Filters> {
     let (_, cancelled) = produce_raw_data(app);
-    // Reuse the same filters, but ignore modified and capped trips
+    // Reuse the same filters, but ignore modified, capped, and synthetic trips
     let filter: Filter<App, CancelledTrip, Filters> = Filter {
         state: Filters {
             modes: TripMode::all().into_iter().collect(),
@@ -402,6 +447,8 @@ fn make_table_cancelled_trips(app: &App) -> Table<App, CancelledTrip, Filters> {
             modified_trips: true,
             uncapped_trips: true,
             capped_trips: true,
+            scenario_trips: true,
+            synthetic_trips: true,
         },
         to_controls: Box::new(move |ctx, app, state| {
             Widget::col(vec![
@@ -427,6 +474,8 @@ fn make_table_cancelled_trips(app: &App) -> Table<App, CancelledTrip, Filters> {
                 modified_trips: true,
                 uncapped_trips: true,
                 capped_trips: true,
+                scenario_trips: true,
+                synthetic_trips: true,
             }
         }),
         apply: Box::new(|state, x| {
@@ -506,7 +555,7 @@ fn make_table_unfinished_trips(app: &App) -> Table<App, UnfinishedTrip, Filters>
         }
     }
 
-    // Reuse the same filters, but ignore modified and capped trips
+    // Reuse the same filters, but ignore modified, capped, and synthetic trips
     let filter: Filter<App, UnfinishedTrip, Filters> = Filter {
         state: Filters {
             modes: TripMode::all().into_iter().collect(),
@@ -516,6 +565,8 @@ fn make_table_unfinished_trips(app: &App) -> Table<App, UnfinishedTrip, Filters>
             modified_trips: true,
             uncapped_trips: true,
             capped_trips: true,
+            scenario_trips: true,
+            synthetic_trips: true,
         },
         to_controls: Box::new(move |ctx, app, state| checkbox_per_mode(ctx, app, &state.modes)),
         from_controls: Box::new(|panel| {
@@ -533,6 +584,8 @@ fn make_table_unfinished_trips(app: &App) -> Table<App, UnfinishedTrip, Filters>
                 modified_trips: true,
                 uncapped_trips: true,
                 capped_trips: true,
+                scenario_trips: true,
+                synthetic_trips: true,
             }
         }),
         apply: Box::new(|state, x| {
@@ -0,0 +1,374 @@
+use serde::{Deserialize, Serialize};
+
+use abstutil::{prettyprint_usize, Timer};
+use geom::Duration;
+use map_gui::ID;
+use map_model::osm::{self, OsmID};
+use map_model::raw::OriginalRoad;
+use map_model::{LaneID, Map};
+use widgetry::{
+    Color, DrawBaselayer, EventCtx, GfxCtx, Line, Outcome, Panel, State, StyledButtons, Text,
+    TextExt, Widget,
+};
+
+use crate::app::{App, Transition};
+use crate::common::Warping;
+use crate::sandbox::dashboards::DashTab;
+
+/// Objects the player wants to keep an eye on during a long session, with a note about each.
+/// Persisted per map as player data, so the JSON file can be shared with somebody else.
+#[derive(Serialize, Deserialize)]
+pub struct WatchList {
+    entries: Vec<WatchEntry>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct WatchEntry {
+    id: WatchedID,
+    note: String,
+}
+
+/// Agents come and go, so only parts of the map can be watched. IDs like LaneID change whenever
+/// the map is regenerated, so refer to things by OSM IDs instead, the same way favorites do.
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+enum WatchedID {
+    /// The lane's position on the road, from the left
+    Lane(OriginalRoad, usize),
+    Intersection(osm::NodeID),
+    Building(OsmID),
+    ParkingLot(OsmID),
+    /// The sidewalk and the stop's name, since a sidewalk may have several stops
+    BusStop(OriginalRoad, usize, String),
+}
+
+impl WatchedID {
+    fn from_id(id: ID, map: &Map) -> Option<WatchedID> {
+        match id {
+            ID::Lane(l) => {
+                let (r, idx) = lane_position(l, map);
+                Some(WatchedID::Lane(r, idx))
+            }
+            ID::Intersection(i) => Some(WatchedID::Intersection(map.get_i(i).orig_id)),
+            ID::Building(b) => Some(WatchedID::Building(map.get_b(b).orig_id)),
+            ID::ParkingLot(pl) => Some(WatchedID::ParkingLot(map.get_pl(pl).osm_id)),
+            ID::BusStop(bs) => {
+                let (r, idx) = lane_position(bs.sidewalk, map);
+                Some(WatchedID::BusStop(r, idx, map.get_bs(bs).name.clone()))
+            }
+            _ => None,
+        }
+    }
+
+    /// None if the map has changed since the list was saved, and the object is gone
+    fn to_id(&self, map: &Map) -> Option<ID> {
+        match self {
+            WatchedID::Lane(r, idx) => find_lane(*r, *idx, map).map(ID::Lane),
+            WatchedID::Intersection(i) => map.find_i_by_osm_id(*i).ok().map(ID::Intersection),
+            WatchedID::Building(b) => map.find_b_by_osm_id(*b).map(ID::Building),
+            WatchedID::ParkingLot(osm_id) => map
+                .all_parking_lots()
+                .iter()
+                .find(|pl| pl.osm_id == *osm_id)
+                .map(|pl| ID::ParkingLot(pl.id)),
+            WatchedID::BusStop(r, idx, name) => {
+                let sidewalk = find_lane(*r, *idx, map)?;
+                map.get_l(sidewalk)
+                    .bus_stops
+                    .iter()
+                    .find(|bs| &map.get_bs(**bs).name == name)
+                    .map(|bs| ID::BusStop(*bs))
+            }
+        }
+    }
+}
+
+fn lane_position(l: LaneID, map: &Map) -> (OriginalRoad, usize) {
+    let road = map.get_parent(l);
+    let idx = road
+        .lanes_ltr()
+        .into_iter()
+        .position(|(id, _, _)| id == l)
+        .unwrap();
+    (road.orig_id, idx)
+}
+
+fn find_lane(r: OriginalRoad, idx: usize, map: &Map) -> Option<LaneID> {
+    let r = map.find_r_by_osm_id(r).ok()?;
+    map.get_r(r).lanes_ltr().get(idx).map(|(l, _, _)| *l)
+}
+
+fn describe(id: &ID, app: &App) -> String {
+    let map = &app.primary.map;
+    let lang = app.opts.language.as_ref();
+    match id {
+        ID::Lane(l) => format!("Lane #{} on {}", l.0, map.get_parent(*l).get_name(lang)),
+        ID::Intersection(i) => map.get_i(*i).name(lang, map),
+        ID::Building(b) => map.get_b(*b).address.clone(),
+        ID::ParkingLot(pl) => format!("Parking lot #{}", pl.0),
+        ID::BusStop(bs) => map.get_bs(*bs).name.clone(),
+        _ => format!("{:?}", id),
+    }
+}
+
+/// The most telling number about the object, so far today
+fn headline(id: &ID, app: &App) -> String {
+    let map = &app.primary.map;
+    let sim = &app.primary.sim;
+    let analytics = sim.get_analytics();
+    match id {
+        ID::Lane(l) => format!(
+            "{} crossed the road",
+            prettyprint_usize(analytics.road_thruput.total_for(map.get_l(*l).parent))
+        ),
+        ID::Intersection(i) => {
+            let delays = analytics
+                .intersection_delays
+                .get(i)
+                .cloned()
+                .unwrap_or_else(Vec::new);
+            if delays.is_empty() {
+                format!(
+                    "{} crossed",
+                    prettyprint_usize(analytics.intersection_thruput.total_for(*i))
+                )
+            } else {
+                let total = delays
+                    .iter()
+                    .fold(Duration::ZERO, |sum, (_, _, dt, _)| sum + *dt);
+                format!("{} average delay", total / (delays.len() as f64))
+            }
+        }
+        ID::Building(b) => format!(
+            "{} people inside",
+            prettyprint_usize(sim.bldg_to_people(*b).len())
+        ),
+        ID::ParkingLot(pl) => format!(
+            "{} / {} spots free",
+            sim.get_free_lot_spots(*pl).len(),
+            map.get_pl(*pl).capacity()
+        ),
+        ID::BusStop(bs) => format!(
+            "{} boardings",
+            prettyprint_usize(
+                analytics
+                    .passengers_boarding
+                    .get(bs)
+                    .map(|list| list.len())
+                    .unwrap_or(0)
+            )
+        ),
+        _ => String::new(),
+    }
+}
+
+impl WatchList {
+    /// Also returns how many saved entries refer to objects that don't exist anymore. Those are
+    /// dropped.
+    fn load(app: &App) -> (WatchList, usize) {
+        let mut list =
+            abstio::maybe_read_json::<WatchList>(WatchList::path(app), &mut Timer::throwaway())
+                .unwrap_or_else(|_| WatchList {
+                    entries: Vec::new(),
+                });
+        let before = list.entries.len();
+        list.entries
+            .retain(|entry| entry.id.to_id(&app.primary.map).is_some());
+        let dropped = before - list.entries.len();
+        (list, dropped)
+    }
+
+    fn save(&self, app: &App) {
+        abstio::write_json(WatchList::path(app), self);
+    }
+
+    fn path(app: &App) -> String {
+        let name = app.primary.map.get_name();
+        abstio::path_player(format!(
+            "watch_lists/{}/{}/{}.json",
+            name.city.country, name.city.city, name.map
+        ))
+    }
+
+    /// Only some objects can be watched at all
+    pub fn can_watch(id: &ID) -> bool {
+        matches!(
+            id,
+            ID::Lane(_)
+                | ID::Intersection(_)
+                | ID::Building(_)
+                | ID::ParkingLot(_)
+                | ID::BusStop(_)
+        )
+    }
+
+    pub fn contains(app: &App, id: ID) -> bool {
+        let id = WatchedID::from_id(id, &app.primary.map).unwrap();
+        WatchList::load(app).0.entries.iter().any(|e| e.id == id)
+    }
+
+    pub fn add(app: &App, id: ID) {
+        let mut list = WatchList::load(app).0;
+        list.entries.push(WatchEntry {
+            id: WatchedID::from_id(id, &app.primary.map).unwrap(),
+            note: String::new(),
+        });
+        list.save(app);
+    }
+
+    pub fn remove(app: &App, id: ID) {
+        let id = WatchedID::from_id(id, &app.primary.map).unwrap();
+        let mut list = WatchList::load(app).0;
+        list.entries.retain(|e| e.id != id);
+        list.save(app);
+    }
+}
+
+pub struct WatchListViewer {
+    panel: Panel,
+    list: WatchList,
+    dropped: usize,
+    // Notes are saved when leaving, not after every keystroke
+    notes_changed: bool,
+}
+
+impl WatchListViewer {
+    pub fn new(ctx: &mut EventCtx, app: &App) -> Box<dyn State<App>> {
+        let (list, dropped) = WatchList::load(app);
+        if dropped > 0 {
+            warn!(
+                "Dropping {} entries from {} that no longer exist on this map",
+                dropped,
+                WatchList::path(app)
+            );
+        }
+        let mut viewer = WatchListViewer {
+            panel: Panel::empty(ctx),
+            list,
+            dropped,
+            notes_changed: false,
+        };
+        viewer.recreate(ctx, app);
+        Box::new(viewer)
+    }
+
+    fn recreate(&mut self, ctx: &mut EventCtx, app: &App) {
+        let mut col = vec![DashTab::WatchList.picker(ctx, app)];
+        if self.dropped > 0 {
+            col.push(
+                Line(format!(
+                    "{} saved objects no longer exist on this map, so they were dropped",
+                    self.dropped
+                ))
+                .fg(Color::RED)
+                .draw(ctx),
+            );
+        }
+        if self.list.entries.is_empty() {
+            col.push(
+                "Nothing watched yet. Open something on the map and add it to the watch list."
+                    .draw_text(ctx),
+            );
+        }
+        for (idx, entry) in self.list.entries.iter().enumerate() {
+            // Loading the list dropped everything that doesn't exist anymore
+            let id = entry.id.to_id(&app.primary.map).unwrap();
+            col.push(Widget::row(vec![
+                ctx.style()
+                    .btn_plain_light_icon("system/assets/tools/location.svg")
+                    .build_widget(ctx, &format!("jump to #{}", idx))
+                    .centered_vert(),
+                Text::from_multiline(vec![
+                    Line(describe(&id, app)),
+                    Line(headline(&id, app)).secondary(),
+                ])
+                .draw(ctx)
+                .centered_vert(),
+                Widget::text_entry(ctx, entry.note.clone(), false)
+                    .named(format!("note #{}", idx))
+                    .centered_vert()
+                    .align_right(),
+                ctx.style()
+                    .btn_close()
+                    .build_widget(ctx, &format!("stop watching #{}", idx))
+                    .centered_vert(),
+            ]));
+        }
+        col.push(
+            Line(format!("Saved to {}", WatchList::path(app)))
+                .secondary()
+                .draw(ctx),
+        );
+        self.panel = Panel::new(Widget::col(col))
+            .exact_size_percent(90, 90)
+            .build(ctx);
+    }
+
+    fn save_notes(&mut self, app: &App) {
+        if self.notes_changed {
+            self.list.save(app);
+            self.notes_changed = false;
+        }
+    }
+}
+
+impl State<App> for WatchListViewer {
+    fn event(&mut self, ctx: &mut EventCtx, app: &mut App) -> Transition {
+        match self.panel.event(ctx) {
+            Outcome::Clicked(x) => {
+                if x == "close" {
+                    self.save_notes(app);
+                    return Transition::Pop;
+                }
+                if let Some(idx) = x.strip_prefix("stop watching #") {
+                    self.list.entries.remove(idx.parse::<usize>().unwrap());
+                    self.notes_changed = true;
+                    self.save_notes(app);
+                    self.recreate(ctx, app);
+                    return Transition::Keep;
+                }
+                let idx = x
+                    .strip_prefix("jump to #")
+                    .unwrap()
+                    .parse::<usize>()
+                    .unwrap();
+                self.save_notes(app);
+                let id = self.list.entries[idx].id.to_id(&app.primary.map).unwrap();
+                let pt = app.primary.canonical_point(id.clone()).unwrap();
+                Transition::Multi(vec![
+                    Transition::Pop,
+                    Transition::Push(Warping::new(
+                        ctx,
+                        pt,
+                        Some(10.0),
+                        Some(id),
+                        &mut app.primary,
+                    )),
+                ])
+            }
+            Outcome::Changed => {
+                if let Some(t) = DashTab::WatchList.transition(ctx, app, &self.panel) {
+                    self.save_notes(app);
+                    return t;
+                }
+                for (idx, entry) in self.list.entries.iter_mut().enumerate() {
+                    let note = self.panel.text_box(&format!("note #{}", idx));
+                    if note != entry.note {
+                        entry.note = note;
+                        self.notes_changed = true;
+                    }
+                }
+                Transition::Keep
+            }
+            _ => Transition::Keep,
+        }
+    }
+
+    fn draw_baselayer(&self) -> DrawBaselayer {
+        DrawBaselayer::Custom
+    }
+
+    fn draw(&self, g: &mut GfxCtx, app: &App) {
+        g.clear(app.cs.dialog_bg);
+        self.panel.draw(g);
+    }
+}
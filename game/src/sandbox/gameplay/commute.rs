@@ -121,22 +121,16 @@ impl GameplayState for OptimizeCommute {
             );
         }
 
-        self.meter.align_below(
-            ctx,
-            &controls.agent_meter.as_ref().unwrap().panel,
-            METER_HACK,
-        );
+        self.meter
+            .align_below(ctx, controls.lowest_right_panel(app).unwrap(), METER_HACK);
 
         if self.time != app.primary.sim.time() && !self.done {
             self.time = app.primary.sim.time();
 
             let (before, after, done) = get_score(app, &self.trips);
             self.meter = make_meter(ctx, app, before, after, done, self.trips.len());
-            self.meter.align_below(
-                ctx,
-                &controls.agent_meter.as_ref().unwrap().panel,
-                METER_HACK,
-            );
+            self.meter
+                .align_below(ctx, controls.lowest_right_panel(app).unwrap(), METER_HACK);
 
             if done == self.trips.len() {
                 self.done = true;
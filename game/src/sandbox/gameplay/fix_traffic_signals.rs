@@ -91,11 +91,8 @@ impl GameplayState for FixTrafficSignals {
         controls: &mut SandboxControls,
         _: &mut Actions,
     ) -> Option<Transition> {
-        self.meter.align_below(
-            ctx,
-            &controls.agent_meter.as_ref().unwrap().panel,
-            METER_HACK,
-        );
+        self.meter
+            .align_below(ctx, controls.lowest_right_panel(app).unwrap(), METER_HACK);
 
         // Normally we just do this once at the beginning, but because there are other paths to
         // reseting (like jump-to-time), it's safest just to do this.
@@ -142,11 +139,8 @@ impl GameplayState for FixTrafficSignals {
             } else {
                 self.meter = make_meter(ctx, app, None);
             }
-            self.meter.align_below(
-                ctx,
-                &controls.agent_meter.as_ref().unwrap().panel,
-                METER_HACK,
-            );
+            self.meter
+                .align_below(ctx, controls.lowest_right_panel(app).unwrap(), METER_HACK);
 
             if app.primary.sim.is_done() {
                 self.done_at = Some(app.primary.sim.time());
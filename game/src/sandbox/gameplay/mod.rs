@@ -15,6 +15,7 @@ use widgetry::{
 };
 
 pub use self::freeform::spawn_agents_around;
+pub use self::tour::{Tour, TourStep};
 pub use self::tutorial::{Tutorial, TutorialPointer, TutorialState};
 use crate::app::App;
 use crate::app::Transition;
@@ -29,6 +30,7 @@ pub mod commute;
 pub mod fix_traffic_signals;
 pub mod freeform;
 pub mod play_scenario;
+mod tour;
 pub mod tutorial;
 
 #[derive(PartialEq, Eq, PartialOrd, Ord, Clone)]
@@ -41,6 +43,8 @@ pub enum GameplayMode {
     OptimizeCommute(OrigPersonID, Duration),
     // Map name, scenario name
     Blog(MapName, Option<String>),
+    // A short walk through the basic controls
+    Tour(MapName),
 
     // current
     Tutorial(TutorialPointer),
@@ -106,6 +110,7 @@ impl GameplayMode {
             GameplayMode::OptimizeCommute(_, _) => MapName::seattle("montlake"),
             GameplayMode::Tutorial(_) => MapName::seattle("montlake"),
             GameplayMode::Blog(ref name, _) => name.clone(),
+            GameplayMode::Tour(ref name) => name.clone(),
         }
     }
 
@@ -138,6 +143,14 @@ impl GameplayMode {
             GameplayMode::FixTrafficSignals | GameplayMode::OptimizeCommute(_, _) => {
                 "weekday".to_string()
             }
+            // Like the sandbox on the main menu
+            GameplayMode::Tour(ref name) => {
+                if abstio::file_exists(abstio::path_scenario(name, "weekday")) {
+                    "weekday".to_string()
+                } else {
+                    "home_to_work".to_string()
+                }
+            }
         };
         if name == "random" {
             LoadScenario::Scenario(ScenarioGenerator::small_run(map).generate(map, &mut rng, timer))
@@ -232,6 +245,7 @@ impl GameplayMode {
             GameplayMode::Blog(_, ref maybe_scenario) => {
                 blog::Blog::new(ctx, maybe_scenario.clone())
             }
+            GameplayMode::Tour(_) => Tour::new(ctx, app),
         }
     }
 }
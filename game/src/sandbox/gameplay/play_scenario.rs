@@ -2,6 +2,7 @@ use std::collections::BTreeSet;
 
 use maplit::btreeset;
 
+use geom::Distance;
 use map_gui::tools::{grey_out_map, nice_map_name, ChooseSomething, CityPicker, PopupMsg};
 use sim::{ScenarioModifier, TripMode};
 use widgetry::{
@@ -14,7 +15,7 @@ use crate::common::{checkbox_per_mode, update_url};
 use crate::edit::EditMode;
 use crate::sandbox::gameplay::freeform::ChangeScenario;
 use crate::sandbox::gameplay::{GameplayMode, GameplayState};
-use crate::sandbox::{Actions, SandboxControls, SandboxMode};
+use crate::sandbox::{Actions, PickEditsToCompare, SandboxControls, SandboxMode};
 
 pub struct PlayScenario {
     top_center: Panel,
@@ -102,6 +103,15 @@ impl GameplayState for PlayScenario {
                         self.modifiers.clone(),
                     ),
                 ))),
+                "compare proposals" => Some(Transition::Push(PickEditsToCompare::new(
+                    ctx,
+                    app,
+                    GameplayMode::PlayScenario(
+                        app.primary.map.get_name().clone(),
+                        self.scenario_name.clone(),
+                        self.modifiers.clone(),
+                    ),
+                ))),
                 "edit traffic patterns" => Some(Transition::Push(EditScenarioModifiers::new(
                     ctx,
                     self.scenario_name.clone(),
@@ -144,6 +154,9 @@ impl GameplayState for PlayScenario {
                     .btn_outline_light_icon_text("system/assets/tools/pencil.svg", "Edit map")
                     .hotkey(lctrl(Key::E))
                     .build_widget(ctx, "edit map"),
+                ctx.style()
+                    .btn_outline_light_text("Compare proposals")
+                    .build_widget(ctx, "compare proposals"),
             ])
             .centered(),
             if self.scenario_name != "empty" {
@@ -212,6 +225,11 @@ impl EditScenarioModifiers {
                 .btn_solid_dark_text("Change trip mode")
                 .build_def(ctx),
         );
+        rows.push(
+            ctx.style()
+                .btn_solid_dark_text("Shift short driving trips")
+                .build_def(ctx),
+        );
         rows.push(
             ctx.style()
                 .btn_solid_dark_text("Add extra new trips")
@@ -282,6 +300,14 @@ impl State<App> for EditScenarioModifiers {
                         self.modifiers.clone(),
                     ));
                 }
+                "Shift short driving trips" => {
+                    return Transition::Push(ShiftDriveTrips::new(
+                        ctx,
+                        app,
+                        self.scenario_name.clone(),
+                        self.modifiers.clone(),
+                    ));
+                }
                 "Add extra new trips" => {
                     return Transition::Push(ChooseSomething::new(
                         ctx,
@@ -471,3 +497,138 @@ impl State<App> for ChangeMode {
         self.panel.draw(g);
     }
 }
+
+struct ShiftDriveTrips {
+    panel: Panel,
+    scenario_name: String,
+    modifiers: Vec<ScenarioModifier>,
+}
+
+impl ShiftDriveTrips {
+    fn new(
+        ctx: &mut EventCtx,
+        app: &App,
+        scenario_name: String,
+        modifiers: Vec<ScenarioModifier>,
+    ) -> Box<dyn State<App>> {
+        Box::new(ShiftDriveTrips {
+            scenario_name,
+            modifiers,
+            panel: Panel::new(Widget::col(vec![
+                Line("Shift short driving trips").small_heading().draw(ctx),
+                Widget::row(vec![
+                    "Percent of matching trips to shift:"
+                        .draw_text(ctx)
+                        .centered_vert(),
+                    Spinner::new(ctx, (1, 100), 10).named("pct_trips"),
+                ]),
+                Widget::row(vec![
+                    format!(
+                        "Only trips shorter than ({}):",
+                        if app.opts.units.metric {
+                            "kilometers"
+                        } else {
+                            "miles"
+                        }
+                    )
+                    .draw_text(ctx)
+                    .centered_vert(),
+                    Spinner::new(ctx, (1, 50), 3).named("max_distance"),
+                ]),
+                Widget::row(vec![
+                    "Departing from:".draw_text(ctx),
+                    Slider::area(ctx, 0.25 * ctx.canvas.window_width, 0.0).named("depart from"),
+                ]),
+                Widget::row(vec![
+                    "Departing until:".draw_text(ctx),
+                    Slider::area(ctx, 0.25 * ctx.canvas.window_width, 1.0).named("depart to"),
+                ]),
+                Widget::horiz_separator(ctx, 0.5),
+                Widget::row(vec![
+                    "Change to trip type:".draw_text(ctx),
+                    Widget::dropdown(
+                        ctx,
+                        "to_mode",
+                        TripMode::Bike,
+                        [TripMode::Bike, TripMode::Transit, TripMode::Walk]
+                            .iter()
+                            .map(|m| Choice::new(m.ongoing_verb(), *m))
+                            .collect(),
+                    ),
+                ]),
+                Widget::row(vec![
+                    "Random seed:".draw_text(ctx).centered_vert(),
+                    Spinner::new(ctx, (0, 1000), 42).named("rng_seed"),
+                ]),
+                Widget::row(vec![
+                    ctx.style()
+                        .btn_solid_dark_text("Apply")
+                        .hotkey(Key::Enter)
+                        .build_def(ctx),
+                    ctx.style()
+                        .btn_solid_dark_text("Discard changes")
+                        .hotkey(Key::Escape)
+                        .build_def(ctx),
+                ])
+                .centered(),
+            ]))
+            .exact_size_percent(80, 80)
+            .build(ctx),
+        })
+    }
+}
+
+impl State<App> for ShiftDriveTrips {
+    fn event(&mut self, ctx: &mut EventCtx, app: &mut App) -> Transition {
+        match self.panel.event(ctx) {
+            Outcome::Clicked(x) => match x.as_ref() {
+                "Discard changes" => Transition::Pop,
+                "Apply" => {
+                    let (p1, p2) = (
+                        self.panel.slider("depart from").get_percent(),
+                        self.panel.slider("depart to").get_percent(),
+                    );
+                    if p1 >= p2 {
+                        return Transition::Push(PopupMsg::new(
+                            ctx,
+                            "Error",
+                            vec!["Your time range is backwards"],
+                        ));
+                    }
+                    let max_distance = self.panel.spinner("max_distance") as f64;
+
+                    let mut mods = self.modifiers.clone();
+                    mods.push(ScenarioModifier::ShiftDriveTrips {
+                        pct_trips: self.panel.spinner("pct_trips") as usize,
+                        max_distance: if app.opts.units.metric {
+                            Distance::meters(1000.0 * max_distance)
+                        } else {
+                            Distance::miles(max_distance)
+                        },
+                        departure_filter: (
+                            app.primary.sim.get_end_of_day().percent_of(p1),
+                            app.primary.sim.get_end_of_day().percent_of(p2),
+                        ),
+                        to_mode: self.panel.dropdown_value("to_mode"),
+                        rng_seed: self.panel.spinner("rng_seed") as u64,
+                    });
+                    Transition::Multi(vec![
+                        Transition::Pop,
+                        Transition::Replace(EditScenarioModifiers::new(
+                            ctx,
+                            self.scenario_name.clone(),
+                            mods,
+                        )),
+                    ])
+                }
+                _ => unreachable!(),
+            },
+            _ => Transition::Keep,
+        }
+    }
+
+    fn draw(&self, g: &mut GfxCtx, app: &App) {
+        grey_out_map(g, app);
+        self.panel.draw(g);
+    }
+}
@@ -0,0 +1,346 @@
+use abstutil::Counter;
+use geom::{Distance, Duration};
+use map_gui::tools::ChooseSomething;
+use map_gui::ID;
+use map_model::{BuildingID, EditCmd, IntersectionID};
+use sim::PersonState;
+use widgetry::{
+    lctrl, Choice, Color, EventCtx, GfxCtx, HorizontalAlignment, Key, Line, Outcome, Panel,
+    ScreenRectangle, State, StyledButtons, Text, VerticalAlignment, Widget,
+};
+
+use crate::app::{App, Transition, UiEvent};
+use crate::common::Warping;
+use crate::edit::EditMode;
+use crate::info::Tab;
+use crate::sandbox::gameplay::{GameplayMode, GameplayState};
+use crate::sandbox::{Actions, SandboxControls, SandboxMode};
+
+/// A few minutes walking through the basic controls. Unlike the full tutorial, this plays on the
+/// normal map and scenario, and each step just waits for the player to actually do something.
+pub struct Tour {
+    top_center: Panel,
+    // Suggestions of what to click, so the player doesn't have to hunt around
+    bldg: Option<BuildingID>,
+    intersection: Option<IntersectionID>,
+    // The button to press next. Panels move around, so this is updated every event.
+    highlight: Option<ScreenRectangle>,
+}
+
+/// The steps of the tour, in order.
+#[derive(Clone, Copy, PartialEq)]
+pub enum TourStep {
+    ClickBuilding,
+    OpenOccupant,
+    FollowTrip,
+    IntersectionDelay,
+    EditLane,
+    Done,
+}
+
+impl TourStep {
+    fn all() -> Vec<TourStep> {
+        vec![
+            TourStep::ClickBuilding,
+            TourStep::OpenOccupant,
+            TourStep::FollowTrip,
+            TourStep::IntersectionDelay,
+            TourStep::EditLane,
+        ]
+    }
+
+    fn next(self) -> TourStep {
+        let all = TourStep::all();
+        match all.iter().position(|step| *step == self) {
+            Some(idx) => all.get(idx + 1).cloned().unwrap_or(TourStep::Done),
+            None => TourStep::Done,
+        }
+    }
+
+    fn instructions(self) -> &'static str {
+        match self {
+            TourStep::ClickBuilding => {
+                "Click on any building to see what's inside. The highlighted one is busy right now."
+            }
+            TourStep::OpenOccupant => "Open the People tab, then click on somebody inside.",
+            TourStep::FollowTrip => {
+                "Press the highlighted button (or F) to run the simulation and follow them."
+            }
+            TourStep::IntersectionDelay => {
+                "Now click on a traffic signal, like the highlighted one, and open its Delay tab."
+            }
+            TourStep::EditLane => {
+                "Finally, change something. Edit the map, click any lane, change its type, and \
+                 finish editing."
+            }
+            TourStep::Done => {
+                "That's the basics! Keep exploring, or try the tutorial and challenges from the \
+                 main menu."
+            }
+        }
+    }
+
+    fn finished_by(self, event: &UiEvent) -> bool {
+        match (self, event) {
+            (TourStep::ClickBuilding, UiEvent::InfoPanelOpened(tab)) => {
+                matches!(tab, Tab::BldgInfo(_, _, _) | Tab::BldgPeople(_))
+            }
+            (TourStep::OpenOccupant, UiEvent::InfoPanelOpened(tab)) => {
+                matches!(tab, Tab::PersonTrips(_, _))
+            }
+            (TourStep::FollowTrip, UiEvent::FollowedAgent) => true,
+            (TourStep::IntersectionDelay, UiEvent::InfoPanelOpened(tab)) => {
+                matches!(tab, Tab::IntersectionDelay(_, _, _))
+            }
+            _ => false,
+        }
+    }
+}
+
+impl Tour {
+    pub fn start(app: &mut App) -> Box<dyn State<App>> {
+        app.session.tour_step = TourStep::ClickBuilding;
+        SandboxMode::async_new(
+            app,
+            GameplayMode::Tour(app.primary.map.get_name().clone()),
+            Box::new(|ctx, app| {
+                // Nothing happens at midnight
+                ctx.loading_screen("start the tour in the morning", |_, mut timer| {
+                    app.primary.sim.timed_step(
+                        &app.primary.map,
+                        Duration::hours(7),
+                        &mut None,
+                        &mut timer,
+                    );
+                });
+                vec![Transition::Keep]
+            }),
+        )
+    }
+
+    /// Asks a new player if they'd like the tour.
+    pub fn offer(ctx: &mut EventCtx) -> Box<dyn State<App>> {
+        ChooseSomething::new(
+            ctx,
+            "New here? Take a quick tour of the basics",
+            vec![Choice::string("Take the tour"), Choice::string("Not now")],
+            Box::new(|resp, _, app| {
+                if resp == "Take the tour" {
+                    Transition::Replace(Tour::start(app))
+                } else {
+                    Transition::Pop
+                }
+            }),
+        )
+    }
+
+    pub fn new(ctx: &mut EventCtx, app: &mut App) -> Box<dyn GameplayState> {
+        if app.session.tour_step != TourStep::Done {
+            app.session.ui_events = Some(Vec::new());
+        }
+
+        let map = &app.primary.map;
+        let mut inside = Counter::new();
+        for person in app.primary.sim.get_all_people() {
+            if let PersonState::Inside(b) = person.state {
+                inside.inc(b);
+            }
+        }
+        let bldg = inside.highest_n(1).into_iter().next().map(|(b, _)| b);
+        let intersection = bldg.and_then(|b| {
+            let pt = map.get_b(b).polygon.center();
+            map.all_intersections()
+                .iter()
+                .filter(|i| i.is_traffic_signal())
+                .map(|i| (i.polygon.center().dist_to(pt), i.id))
+                .min_by(|(d1, _), (d2, _)| d1.partial_cmp(d2).unwrap())
+                .map(|(_, i)| i)
+        });
+
+        Box::new(Tour {
+            top_center: Panel::empty(ctx),
+            bldg,
+            intersection,
+            highlight: None,
+        })
+    }
+
+    fn advance(&mut self, ctx: &mut EventCtx, app: &mut App, controls: &mut SandboxControls) {
+        app.session.tour_step = app.session.tour_step.next();
+        match app.session.tour_step {
+            TourStep::FollowTrip => {
+                // The follow button only shows up while paused
+                if let Some(ref mut speed) = controls.speed {
+                    speed.pause(ctx, app);
+                }
+            }
+            TourStep::Done => {
+                app.session.ui_events = None;
+            }
+            _ => {}
+        }
+        self.recreate_panels(ctx, app);
+    }
+
+    fn suggestion(&self, app: &App) -> Option<ID> {
+        match app.session.tour_step {
+            TourStep::ClickBuilding => self.bldg.map(ID::Building),
+            TourStep::IntersectionDelay => self.intersection.map(ID::Intersection),
+            _ => None,
+        }
+    }
+
+    fn find_highlight(&self, app: &App, controls: &SandboxControls) -> Option<ScreenRectangle> {
+        let info_panel = |action: &str| {
+            controls
+                .common
+                .as_ref()
+                .and_then(|c| c.info_panel_rect_of(action))
+        };
+        match app.session.tour_step {
+            TourStep::OpenOccupant => {
+                // Point at somebody inside, or otherwise the tab listing them
+                let person = match controls
+                    .common
+                    .as_ref()
+                    .and_then(|c| c.info_panel_open(app))
+                {
+                    Some(ID::Building(b)) => app.primary.sim.bldg_to_people(b).into_iter().next(),
+                    _ => None,
+                };
+                person
+                    .and_then(|p| info_panel(&p.to_string()))
+                    .or_else(|| info_panel("People"))
+            }
+            TourStep::FollowTrip => info_panel("follow (run the simulation)"),
+            TourStep::IntersectionDelay => info_panel("Delay"),
+            TourStep::EditLane => Some(self.top_center.rect_of("edit map").clone()),
+            _ => None,
+        }
+    }
+}
+
+impl GameplayState for Tour {
+    fn event(
+        &mut self,
+        ctx: &mut EventCtx,
+        app: &mut App,
+        controls: &mut SandboxControls,
+        _: &mut Actions,
+    ) -> Option<Transition> {
+        if let Outcome::Clicked(x) = self.top_center.event(ctx) {
+            match x.as_ref() {
+                "edit map" => {
+                    return Some(Transition::Push(EditMode::new(
+                        ctx,
+                        app,
+                        GameplayMode::Tour(app.primary.map.get_name().clone()),
+                    )));
+                }
+                "show me" => {
+                    if let Some(id) = self.suggestion(app) {
+                        return Some(Transition::Push(Warping::new(
+                            ctx,
+                            app.primary.canonical_point(id).unwrap(),
+                            Some(10.0),
+                            None,
+                            &mut app.primary,
+                        )));
+                    }
+                }
+                "skip this step" => {
+                    self.advance(ctx, app, controls);
+                }
+                _ => unreachable!(),
+            }
+        }
+
+        let events = app
+            .session
+            .ui_events
+            .as_mut()
+            .map(std::mem::take)
+            .unwrap_or_default();
+        for event in events {
+            if app.session.tour_step.finished_by(&event) {
+                self.advance(ctx, app, controls);
+            }
+        }
+        // Editing the map happens in a different mode, so just check the result
+        if app.session.tour_step == TourStep::EditLane
+            && app
+                .primary
+                .map
+                .get_edits()
+                .commands
+                .iter()
+                .any(|cmd| matches!(cmd, EditCmd::ChangeRoad { .. }))
+        {
+            self.advance(ctx, app, controls);
+        }
+
+        self.highlight = self.find_highlight(app, controls);
+        None
+    }
+
+    fn draw(&self, g: &mut GfxCtx, app: &App) {
+        let color = Color::hex("#e25822");
+        match self.suggestion(app) {
+            Some(ID::Building(b)) => {
+                g.draw_polygon(color, app.primary.map.get_b(b).polygon.clone());
+            }
+            Some(ID::Intersection(i)) => {
+                g.draw_polygon(color, app.primary.map.get_i(i).polygon.clone());
+            }
+            _ => {}
+        }
+        if let Some(ref rect) = self.highlight {
+            if let Ok(outline) = rect.to_polygon().to_outline(Distance::meters(5.0)) {
+                g.fork_screenspace();
+                g.draw_polygon(color, outline);
+                g.unfork();
+            }
+        }
+
+        self.top_center.draw(g);
+    }
+
+    fn on_destroy(&self, app: &mut App) {
+        app.session.ui_events = None;
+    }
+
+    fn recreate_panels(&mut self, ctx: &mut EventCtx, app: &App) {
+        let step = app.session.tour_step;
+        let mut col = vec![Widget::row(vec![
+            Line("Quick tour").small_heading().draw(ctx).centered_vert(),
+            Widget::vert_separator(ctx, 50.0),
+            ctx.style()
+                .btn_outline_light_icon_text("system/assets/tools/pencil.svg", "Edit map")
+                .hotkey(lctrl(Key::E))
+                .build_widget(ctx, "edit map")
+                .centered_vert(),
+        ])];
+        let mut txt = Text::new();
+        if let Some(idx) = TourStep::all().iter().position(|s| *s == step) {
+            txt.add(Line(format!("Step {}/{}", idx + 1, TourStep::all().len())).secondary());
+        }
+        txt.add(Line(step.instructions()));
+        col.push(txt.wrap_to_pct(ctx, 30).draw(ctx));
+        if step != TourStep::Done {
+            col.push(Widget::row(vec![
+                if self.suggestion(app).is_some() {
+                    ctx.style().btn_outline_light_text("show me").build_def(ctx)
+                } else {
+                    Widget::nothing()
+                },
+                ctx.style()
+                    .btn_plain_light_text("skip this step")
+                    .build_def(ctx),
+            ]));
+        }
+
+        self.top_center = Panel::new(Widget::col(col))
+            .aligned(HorizontalAlignment::Center, VerticalAlignment::Top)
+            .build(ctx);
+    }
+}
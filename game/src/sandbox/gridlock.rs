@@ -0,0 +1,356 @@
+use std::collections::HashMap;
+
+use serde::Serialize;
+
+use geom::{ArrowCap, Circle, Distance, Duration, PolyLine, Time};
+use map_gui::ID;
+use sim::{AgentID, Gridlock};
+use widgetry::{
+    Color, Drawable, EventCtx, GeomBatch, GfxCtx, HorizontalAlignment, Line, Outcome, Panel, State,
+    StyledButtons, Text, TextExt, UpdateType, VerticalAlignment, Widget,
+};
+
+use crate::app::{App, Transition};
+use crate::common::Warping;
+
+// How often to look for gridlock. Finding blocked agents touches every vehicle on the map, so
+// don't do it every step.
+const CHECK_EVERY: Duration = Duration::const_seconds(60.0);
+// Anybody waiting this long is considered stuck
+const STUCK_THRESHOLD: Duration = Duration::const_seconds(5.0 * 60.0);
+// Lots of long cycles would make the diagnosis panel enormous. Everything is still drawn.
+const MAX_INTERSECTIONS_LISTED: usize = 10;
+const MAX_CYCLES_LISTED: usize = 5;
+const MAX_AGENTS_LISTED: usize = 10;
+
+/// Periodically checks the simulation for gridlock, and offers to diagnose it when found. Nothing
+/// is paused; the alert just sits there until dismissed.
+pub struct GridlockDetector {
+    last_check: Option<Time>,
+    found: Option<Gridlock>,
+    // Don't nag about the same gridlock again after the player dismisses it. Once it clears up,
+    // new gridlock is reported.
+    dismissed: bool,
+    panel: Option<Panel>,
+}
+
+impl GridlockDetector {
+    pub fn new() -> GridlockDetector {
+        GridlockDetector {
+            last_check: None,
+            found: None,
+            dismissed: false,
+            panel: None,
+        }
+    }
+
+    fn recreate_panel(&mut self, ctx: &mut EventCtx) {
+        let gridlock = match self.found {
+            Some(ref g) if !self.dismissed => g,
+            _ => {
+                self.panel = None;
+                return;
+            }
+        };
+
+        let mut txt = Text::from(Line("Gridlock detected").small_heading().fg(Color::RED));
+        if !gridlock.cycles.is_empty() {
+            txt.add(Line(format!(
+                "{} cycles of agents blocking each other",
+                gridlock.cycles.len()
+            )));
+        }
+        if !gridlock.stuck_intersections.is_empty() {
+            txt.add(Line(format!(
+                "{} intersections stuck for {}+",
+                gridlock.stuck_intersections.len(),
+                STUCK_THRESHOLD
+            )));
+        }
+        self.panel = Some(
+            Panel::new(Widget::col(vec![
+                txt.draw(ctx),
+                Widget::row(vec![
+                    ctx.style().btn_solid_dark_text("diagnose").build_def(ctx),
+                    ctx.style().btn_plain_light_text("dismiss").build_def(ctx),
+                ]),
+            ]))
+            .aligned(HorizontalAlignment::Left, VerticalAlignment::Center)
+            .build(ctx),
+        );
+    }
+
+    pub fn event(&mut self, ctx: &mut EventCtx, app: &App) -> Option<Transition> {
+        if let Some(ref mut panel) = self.panel {
+            if let Outcome::Clicked(x) = panel.event(ctx) {
+                match x.as_ref() {
+                    "diagnose" => {
+                        return Some(Transition::Push(GridlockDiagnosis::new(
+                            ctx,
+                            app,
+                            self.found.clone().unwrap(),
+                        )));
+                    }
+                    "dismiss" => {
+                        self.dismissed = true;
+                        self.recreate_panel(ctx);
+                        return None;
+                    }
+                    _ => unreachable!(),
+                }
+            }
+        }
+
+        let now = app.primary.sim.time();
+        // Time goes backwards after rewinding or resetting
+        if let Some(t) = self.last_check {
+            if now >= t && now - t < CHECK_EVERY {
+                return None;
+            }
+        }
+        self.last_check = Some(now);
+
+        self.found = app
+            .primary
+            .sim
+            .find_gridlock(&app.primary.map, STUCK_THRESHOLD);
+        if self.found.is_none() {
+            self.dismissed = false;
+        }
+        self.recreate_panel(ctx);
+        None
+    }
+
+    pub fn draw(&self, g: &mut GfxCtx) {
+        if let Some(ref panel) = self.panel {
+            panel.draw(g);
+        }
+    }
+}
+
+/// Lists everything involved in some gridlock, so it can be investigated or reported.
+struct GridlockDiagnosis {
+    panel: Panel,
+    gridlock: Gridlock,
+    draw: Drawable,
+    // Where each jump button goes
+    warpers: HashMap<String, ID>,
+}
+
+impl GridlockDiagnosis {
+    fn new(ctx: &mut EventCtx, app: &App, gridlock: Gridlock) -> Box<dyn State<App>> {
+        let map = &app.primary.map;
+        let lang = app.opts.language.as_ref();
+
+        let mut batch = GeomBatch::new();
+        let mut warpers = HashMap::new();
+        let mut col = vec![Widget::row(vec![
+            Line(format!("Gridlock at {}", gridlock.time.ampm_tostring()))
+                .small_heading()
+                .draw(ctx),
+            ctx.style().btn_close_widget(ctx),
+        ])];
+
+        if !gridlock.stuck_intersections.is_empty() {
+            col.push(Line("Stuck intersections").small_heading().draw(ctx));
+        }
+        for (idx, (i, since)) in gridlock.stuck_intersections.iter().enumerate() {
+            batch.push(Color::RED.alpha(0.5), map.get_i(*i).polygon.clone());
+            if idx == MAX_INTERSECTIONS_LISTED {
+                col.push(
+                    format!("... and {} more", gridlock.stuck_intersections.len() - idx)
+                        .draw_text(ctx),
+                );
+            }
+            if idx >= MAX_INTERSECTIONS_LISTED {
+                continue;
+            }
+            col.push(jump_row(
+                ctx,
+                &mut warpers,
+                ID::Intersection(*i),
+                Text::from_multiline(vec![
+                    Line(map.get_i(*i).name(lang, map)),
+                    Line(format!(
+                        "Somebody waiting since {} ({})",
+                        since.ampm_tostring(),
+                        gridlock.time - *since
+                    ))
+                    .secondary(),
+                ]),
+            ));
+        }
+
+        for i in &gridlock.cycle_intersections {
+            batch.push(
+                Color::YELLOW,
+                map.get_i(*i)
+                    .polygon
+                    .to_outline(Distance::meters(1.0))
+                    .unwrap_or_else(|_| map.get_i(*i).polygon.clone()),
+            );
+        }
+        for (idx, cycle) in gridlock.cycles.iter().enumerate() {
+            draw_cycle(&mut batch, app, cycle);
+            if idx == MAX_CYCLES_LISTED {
+                col.push(
+                    format!("... and {} more cycles", gridlock.cycles.len() - idx).draw_text(ctx),
+                );
+            }
+            if idx >= MAX_CYCLES_LISTED {
+                continue;
+            }
+            col.push(
+                Line(format!("Cycle #{}: {} agents", idx + 1, cycle.len()))
+                    .small_heading()
+                    .draw(ctx),
+            );
+            for (pos, agent) in cycle.iter().enumerate().take(MAX_AGENTS_LISTED) {
+                let blocker = cycle[(pos + 1) % cycle.len()];
+                col.push(jump_row(
+                    ctx,
+                    &mut warpers,
+                    ID::from_agent(*agent),
+                    Text::from(Line(format!("{} is blocked by {}", agent, blocker))),
+                ));
+            }
+            if cycle.len() > MAX_AGENTS_LISTED {
+                col.push(
+                    format!("... and {} more", cycle.len() - MAX_AGENTS_LISTED).draw_text(ctx),
+                );
+            }
+        }
+
+        col.push(
+            ctx.style()
+                .btn_solid_dark_text("screenshot and dump state")
+                .build_def(ctx),
+        );
+        col.push(Text::new().draw(ctx).named("saved"));
+
+        Box::new(GridlockDiagnosis {
+            panel: Panel::new(Widget::col(col))
+                .aligned(HorizontalAlignment::Right, VerticalAlignment::Center)
+                .build(ctx),
+            gridlock,
+            draw: ctx.upload(batch),
+            warpers,
+        })
+    }
+
+    // Everything needed to reproduce this in a bug report. Returns the directory.
+    fn dump(&self, ctx: &mut EventCtx, app: &mut App) -> String {
+        let dir = abstio::path_player(format!(
+            "gridlock/{}_{}",
+            app.primary.map.get_name().as_filename(),
+            self.gridlock.time.as_filename()
+        ));
+        let savestate = ctx.loading_screen("savestate", |_, _| app.primary.sim.save());
+        abstio::write_json(
+            format!("{}/report.json", dir),
+            &GridlockReport {
+                map: app.primary.map.get_name().describe(),
+                edits: app.primary.map.get_edits().edits_name.clone(),
+                savestate,
+                gridlock: self.gridlock.clone(),
+            },
+        );
+        abstio::write_json(
+            format!("{}/edits.json", dir),
+            &app.primary.map.get_edits().to_permanent(&app.primary.map),
+        );
+        // The panel is hidden during the capture, so the highlighted map is what gets saved
+        ctx.request_update(UpdateType::ScreenCaptureCurrentShot {
+            filename: format!("{}/screenshot.png", dir),
+            camera: None,
+        });
+        dir
+    }
+}
+
+impl State<App> for GridlockDiagnosis {
+    fn event(&mut self, ctx: &mut EventCtx, app: &mut App) -> Transition {
+        ctx.canvas_movement();
+        if ctx.redo_mouseover() {
+            app.recalculate_current_selection(ctx);
+        }
+
+        if let Outcome::Clicked(x) = self.panel.event(ctx) {
+            match x.as_ref() {
+                "close" => {
+                    return Transition::Pop;
+                }
+                "screenshot and dump state" => {
+                    let dir = self.dump(ctx, app);
+                    let txt = Text::from(Line(format!("Saved to {}", dir)).secondary());
+                    self.panel.replace(ctx, "saved", txt.draw(ctx));
+                }
+                x => {
+                    let id = self.warpers[x].clone();
+                    let pt = app.primary.canonical_point(id.clone()).unwrap();
+                    return Transition::Push(Warping::new(
+                        ctx,
+                        pt,
+                        Some(10.0),
+                        Some(id),
+                        &mut app.primary,
+                    ));
+                }
+            }
+        }
+
+        Transition::Keep
+    }
+
+    fn draw(&self, g: &mut GfxCtx, _: &App) {
+        g.redraw(&self.draw);
+        if !g.is_screencap() {
+            self.panel.draw(g);
+        }
+    }
+}
+
+// An arrow from each agent to whoever's blocking them
+fn draw_cycle(batch: &mut GeomBatch, app: &App, cycle: &[AgentID]) {
+    let map = &app.primary.map;
+    let sim = &app.primary.sim;
+    for (pos, agent) in cycle.iter().enumerate() {
+        let blocker = cycle[(pos + 1) % cycle.len()];
+        if let (Some(pt1), Some(pt2)) = (
+            sim.canonical_pt_for_agent(*agent, map),
+            sim.canonical_pt_for_agent(blocker, map),
+        ) {
+            if let Ok(pl) = PolyLine::new(vec![pt1, pt2]) {
+                batch.push(
+                    Color::CYAN,
+                    pl.make_arrow(Distance::meters(0.5), ArrowCap::Triangle),
+                );
+            }
+            batch.push(
+                Color::CYAN.alpha(0.5),
+                Circle::new(pt1, Distance::meters(3.0)).to_polygon(),
+            );
+        }
+    }
+}
+
+fn jump_row(ctx: &mut EventCtx, warpers: &mut HashMap<String, ID>, id: ID, txt: Text) -> Widget {
+    // Agents can show up in more than one cycle
+    let action = format!("jump to {:?} #{}", id, warpers.len());
+    warpers.insert(action.clone(), id);
+    Widget::row(vec![
+        ctx.style()
+            .btn_plain_light_icon("system/assets/tools/location.svg")
+            .build_widget(ctx, &action)
+            .centered_vert(),
+        txt.draw(ctx).centered_vert(),
+    ])
+}
+
+#[derive(Serialize)]
+struct GridlockReport {
+    map: String,
+    edits: String,
+    savestate: String,
+    gridlock: Gridlock,
+}
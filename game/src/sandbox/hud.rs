@@ -0,0 +1,286 @@
+use std::collections::BTreeSet;
+
+use instant::Instant;
+
+use abstutil::prettyprint_usize;
+use geom::{Circle, Distance, Duration, Time};
+use map_gui::render::unzoomed_agent_radius;
+use sim::{AgentType, TripMode};
+use widgetry::{
+    Checkbox, Drawable, EventCtx, GeomBatch, GfxCtx, HorizontalAlignment, Key, Line, Outcome,
+    Panel, StyledButtons, Text, TextExt, VerticalAlignment, Widget,
+};
+
+use crate::app::App;
+use crate::common::color_for_mode;
+
+// How often to check if the values shown changed
+const REFRESH_EVERY: Duration = Duration::const_seconds(0.25);
+
+/// A small, always-visible readout of the simulation's vital signs. Rebuilding a panel every frame
+/// is wasteful, so the values are only sampled a few times per second, and the panel is only
+/// rebuilt when something it shows actually changed.
+///
+/// It's placed on the right, below the agent meter, so it doesn't fight with info panels on the
+/// left. Press H to hide or show it.
+pub struct Hud {
+    panel: Option<Panel>,
+    values: Option<Values>,
+    last_check: Option<Instant>,
+
+    // Counted incrementally from the analytics, since the list of finished trips gets long
+    trips_seen: usize,
+    finished: usize,
+    cancelled: usize,
+
+    highlight: BTreeSet<TripMode>,
+    // Outlines around the highlighted agents, and when they were calculated
+    draw_highlight: Option<(Time, Drawable)>,
+}
+
+#[derive(PartialEq)]
+struct Values {
+    time: Time,
+    // In the order of TripMode::all()
+    active: Vec<usize>,
+    finished: usize,
+    cancelled: usize,
+    speed: String,
+}
+
+impl Hud {
+    pub fn new() -> Hud {
+        Hud {
+            panel: None,
+            values: None,
+            last_check: None,
+            trips_seen: 0,
+            finished: 0,
+            cancelled: 0,
+            highlight: BTreeSet::new(),
+            draw_highlight: None,
+        }
+    }
+
+    /// Rebuilds the panel on the next event, even if no values changed.
+    pub fn recreate_panel(&mut self) {
+        self.values = None;
+        self.last_check = None;
+    }
+
+    /// Only while it's shown
+    pub fn panel(&self, app: &App) -> Option<&Panel> {
+        if app.session.show_hud {
+            self.panel.as_ref()
+        } else {
+            None
+        }
+    }
+
+    /// `speed_ratio` is how quickly the simulation is actually running, if it's running at all.
+    /// `below` is the panel to stay under.
+    pub fn event(
+        &mut self,
+        ctx: &mut EventCtx,
+        app: &mut App,
+        speed_ratio: Option<f64>,
+        below: Option<&Panel>,
+    ) {
+        if !app.session.show_hud {
+            if ctx.input.pressed(Key::H) {
+                app.session.show_hud = true;
+                self.recreate_panel();
+            } else {
+                return;
+            }
+        }
+
+        if self
+            .last_check
+            .map(|t| Duration::realtime_elapsed(t) >= REFRESH_EVERY)
+            .unwrap_or(true)
+        {
+            self.last_check = Some(Instant::now());
+            let values = self.current_values(app, speed_ratio);
+            if self.values.as_ref() != Some(&values) {
+                self.panel = Some(make_panel(ctx, app, &values, &self.highlight, below));
+                self.values = Some(values);
+            }
+            self.update_highlight(ctx, app);
+        }
+
+        if let Some(ref mut panel) = self.panel {
+            match panel.event(ctx) {
+                Outcome::Clicked(x) => match x.as_ref() {
+                    "hide" => {
+                        app.session.show_hud = false;
+                        self.highlight.clear();
+                        self.draw_highlight = None;
+                    }
+                    _ => unreachable!(),
+                },
+                Outcome::Changed => {
+                    self.highlight = TripMode::all()
+                        .into_iter()
+                        .filter(|mode| panel.is_checked(mode.ongoing_verb()))
+                        .collect();
+                    self.draw_highlight = None;
+                }
+                _ => {}
+            }
+        }
+        if self.draw_highlight.is_none() {
+            self.update_highlight(ctx, app);
+        }
+    }
+
+    pub fn draw(&self, g: &mut GfxCtx, app: &App) {
+        if !app.session.show_hud {
+            return;
+        }
+        if let Some((_, ref draw)) = self.draw_highlight {
+            g.redraw(draw);
+        }
+        if let Some(ref panel) = self.panel {
+            panel.draw(g);
+        }
+    }
+
+    fn current_values(&mut self, app: &App, speed_ratio: Option<f64>) -> Values {
+        let finished_trips = &app.primary.sim.get_analytics().finished_trips;
+        // The simulation was reset or rewound
+        if finished_trips.len() < self.trips_seen {
+            self.trips_seen = 0;
+            self.finished = 0;
+            self.cancelled = 0;
+        }
+        for (_, _, _, maybe_dt) in &finished_trips[self.trips_seen..] {
+            if maybe_dt.is_some() {
+                self.finished += 1;
+            } else {
+                self.cancelled += 1;
+            }
+        }
+        self.trips_seen = finished_trips.len();
+
+        let agents = app.primary.sim.num_agents();
+        Values {
+            time: app.primary.sim.time(),
+            active: TripMode::all()
+                .into_iter()
+                .map(|mode| match mode {
+                    TripMode::Walk => agents.get(AgentType::Pedestrian),
+                    TripMode::Bike => agents.get(AgentType::Bike),
+                    TripMode::Transit => agents.get(AgentType::TransitRider),
+                    TripMode::Drive => agents.get(AgentType::Car),
+                })
+                .collect(),
+            finished: self.finished,
+            cancelled: self.cancelled,
+            // Round, so tiny fluctuations don't rebuild the panel
+            speed: match speed_ratio {
+                Some(ratio) if ratio < 10.0 => format!("{:.1}x", ratio),
+                Some(ratio) => format!("{}x", ratio.round() as usize),
+                None => "paused".to_string(),
+            },
+        }
+    }
+
+    fn update_highlight(&mut self, ctx: &mut EventCtx, app: &App) {
+        if self.highlight.is_empty() {
+            self.draw_highlight = None;
+            return;
+        }
+        let now = app.primary.sim.time();
+        if self
+            .draw_highlight
+            .as_ref()
+            .map(|(t, _)| *t == now)
+            .unwrap_or(false)
+        {
+            return;
+        }
+
+        let mut batch = GeomBatch::new();
+        for agent in app.primary.sim.get_unzoomed_agents(&app.primary.map) {
+            let mode = match agent_mode(agent.id.to_type()) {
+                Some(mode) if self.highlight.contains(&mode) => mode,
+                _ => continue,
+            };
+            let radius = 2.0 * unzoomed_agent_radius(agent.id.to_vehicle_type());
+            if let Ok(outline) = Circle::new(agent.pos, radius).to_outline(Distance::meters(2.0)) {
+                batch.push(color_for_mode(app, mode), outline);
+            }
+        }
+        self.draw_highlight = Some((now, ctx.upload(batch)));
+    }
+}
+
+// Passengers aren't drawn separately, so highlighting transit outlines buses and trains instead.
+fn agent_mode(agent_type: AgentType) -> Option<TripMode> {
+    match agent_type {
+        AgentType::Car => Some(TripMode::Drive),
+        AgentType::Bike => Some(TripMode::Bike),
+        AgentType::Bus | AgentType::Train => Some(TripMode::Transit),
+        AgentType::Pedestrian => Some(TripMode::Walk),
+        AgentType::TransitRider => None,
+    }
+}
+
+fn make_panel(
+    ctx: &mut EventCtx,
+    app: &App,
+    values: &Values,
+    highlight: &BTreeSet<TripMode>,
+    below: Option<&Panel>,
+) -> Panel {
+    let mut col = vec![Widget::row(vec![
+        Line(values.time.ampm_tostring())
+            .small_monospaced()
+            .draw(ctx)
+            .centered_vert(),
+        format!("Speed: {}", values.speed)
+            .draw_text(ctx)
+            .centered_vert(),
+        ctx.style()
+            .btn_close()
+            .hotkey(Key::H)
+            .build_widget(ctx, "hide")
+            .align_right(),
+    ])];
+    for (mode, count) in TripMode::all().into_iter().zip(values.active.iter()) {
+        col.push(Widget::row(vec![
+            Checkbox::colored(
+                ctx,
+                mode.ongoing_verb(),
+                color_for_mode(app, mode),
+                highlight.contains(&mode),
+            ),
+            prettyprint_usize(*count)
+                .draw_text(ctx)
+                .centered_vert()
+                .align_right(),
+        ]));
+    }
+    col.push(
+        Text::from_multiline(vec![
+            Line(format!(
+                "Finished trips: {}",
+                prettyprint_usize(values.finished)
+            )),
+            Line(format!(
+                "Cancelled trips: {}",
+                prettyprint_usize(values.cancelled)
+            )),
+        ])
+        .draw(ctx),
+    );
+
+    let mut panel = Panel::new(Widget::col(col))
+        .aligned(HorizontalAlignment::Right, VerticalAlignment::Top)
+        .build(ctx);
+    if let Some(other) = below {
+        panel.align_below(ctx, other, 10.0);
+    }
+    panel
+}
@@ -0,0 +1,181 @@
+use abstutil::prettyprint_usize;
+use geom::{Distance, Duration, Line, Time};
+use map_gui::tools::PopupMsg;
+use map_model::{EditCmd, LaneID, LaneType, MapEdits};
+use widgetry::{Color, Drawable, EventCtx, GeomBatch, GfxCtx};
+
+use crate::app::{App, Transition};
+use crate::edit::{apply_map_edits, try_change_lt};
+
+/// A lane closed from its info panel for a while, to watch how traffic copes. The closure is just
+/// a normal map edit applied to the live simulation, so reopening the lane removes that one
+/// command and leaves any other edits made in the meantime alone.
+pub struct LaneClosure {
+    pub lane: LaneID,
+    pub until: Time,
+    orig_lt: LaneType,
+    cmd: EditCmd,
+    draw: Drawable,
+}
+
+impl LaneClosure {
+    /// Closes the lane immediately, unless doing so would break something, like orphaning a bus
+    /// stop or closing a second lane at once.
+    pub fn start(ctx: &mut EventCtx, app: &mut App, l: LaneID, duration: Duration) -> Transition {
+        if let Some(ref closure) = app.primary.lane_closure {
+            return Transition::Push(PopupMsg::new(
+                ctx,
+                "Error",
+                vec![format!(
+                    "Another lane is already closed until {}",
+                    closure.until.ampm_tostring()
+                )],
+            ));
+        }
+        let orig_lt = app.primary.map.get_l(l).lane_type;
+        let cmd = match try_change_lt(ctx, &mut app.primary.map, l, LaneType::Construction) {
+            Ok(cmd) => cmd,
+            Err(err) => {
+                return Transition::Push(err);
+            }
+        };
+        let mut edits = app.primary.map.get_edits().clone();
+        edits.commands.push(cmd.clone());
+        let (rerouted, cancelled, parked_cars) = apply_live_edits(ctx, app, edits);
+
+        let until = app.primary.sim.time() + duration;
+        app.primary.lane_closure = Some(LaneClosure {
+            lane: l,
+            until,
+            orig_lt,
+            cmd,
+            draw: ctx.upload(hatching(app, l)),
+        });
+
+        Transition::Push(PopupMsg::new(
+            ctx,
+            "Lane closed",
+            vec![
+                format!(
+                    "{} is closed until {}",
+                    app.primary
+                        .map
+                        .get_parent(l)
+                        .get_name(app.opts.language.as_ref()),
+                    until.ampm_tostring()
+                ),
+                format!(
+                    "Closing it rerouted {} trips, cancelled {} that couldn't get around it, and \
+                     displaced {} parked cars. Trips that haven't started yet will route around \
+                     it.",
+                    prettyprint_usize(rerouted),
+                    prettyprint_usize(cancelled),
+                    prettyprint_usize(parked_cars)
+                ),
+            ],
+        ))
+    }
+
+    /// Reopens the lane once the closure expires. Call this after the simulation might've
+    /// advanced.
+    pub fn event(ctx: &mut EventCtx, app: &mut App) -> Option<Transition> {
+        if app
+            .primary
+            .lane_closure
+            .as_ref()
+            .map(|c| app.primary.sim.time() < c.until)
+            .unwrap_or(true)
+        {
+            return None;
+        }
+        let closure = app.primary.lane_closure.take().unwrap();
+
+        let map = &app.primary.map;
+        let mut edits = map.get_edits().clone();
+        // The player might've already undone the closure in edit mode
+        let idx = edits.commands.iter().rposition(|cmd| cmd == &closure.cmd)?;
+        let r = map.get_l(closure.lane).parent;
+        let edited_later = edits.commands[idx + 1..]
+            .iter()
+            .any(|cmd| matches!(cmd, EditCmd::ChangeRoad { r: other, .. } if *other == r));
+        if !edited_later {
+            edits.commands.remove(idx);
+        } else if map.get_l(closure.lane).lane_type == LaneType::Construction {
+            // Later edits to the same road were made on top of the closure, so removing it would
+            // undo them too. Just change the lane back instead.
+            edits.commands.push(map.edit_road_cmd(r, |new| {
+                new.lanes_ltr[map.get_r(r).offset(closure.lane)].0 = closure.orig_lt;
+            }));
+        } else {
+            // The lane was changed to something else since; leave it that way.
+            return None;
+        }
+        let (rerouted, cancelled, parked_cars) = apply_live_edits(ctx, app, edits);
+
+        Some(Transition::Push(PopupMsg::new(
+            ctx,
+            "Lane reopened",
+            vec![
+                format!(
+                    "{} reopened at {}",
+                    app.primary
+                        .map
+                        .get_parent(closure.lane)
+                        .get_name(app.opts.language.as_ref()),
+                    app.primary.sim.time().ampm_tostring()
+                ),
+                format!(
+                    "Reopening it rerouted {} trips, cancelled {}, and displaced {} parked cars",
+                    prettyprint_usize(rerouted),
+                    prettyprint_usize(cancelled),
+                    prettyprint_usize(parked_cars)
+                ),
+            ],
+        )))
+    }
+
+    pub fn draw(&self, g: &mut GfxCtx) {
+        g.redraw(&self.draw);
+    }
+}
+
+/// Diagonal stripes across a closed lane.
+pub fn hatching(app: &App, l: LaneID) -> GeomBatch {
+    let lane = app.primary.map.get_l(l);
+    let mut batch = GeomBatch::new();
+    let step = Distance::meters(2.0);
+    let mut dist = step / 2.0;
+    while dist < lane.length() {
+        let (pt, angle) = lane.lane_center_pts.must_dist_along(dist);
+        let stripe = Line::must_new(
+            pt.project_away(lane.width / 2.0, angle.rotate_degs(45.0)),
+            pt.project_away(lane.width / 2.0, angle.rotate_degs(225.0)),
+        );
+        batch.push(Color::RED, stripe.make_polygons(Distance::meters(0.3)));
+        dist += step;
+    }
+    batch
+}
+
+// Applies edits to the live simulation, like leaving edit mode does, except trips currently
+// crossing something that changed are rerouted around it when possible. Returns the number of
+// (trips rerouted, trips cancelled, parked cars displaced).
+pub fn apply_live_edits(
+    ctx: &mut EventCtx,
+    app: &mut App,
+    edits: MapEdits,
+) -> (usize, usize, usize) {
+    apply_map_edits(ctx, app, edits);
+    ctx.loading_screen("update pathfinding", |_, mut timer| {
+        app.primary
+            .map
+            .recalculate_pathfinding_after_edits(&mut timer);
+    });
+    app.primary.dirty_from_edits = true;
+    app.primary
+        .sim
+        .handle_live_edited_traffic_signals(&app.primary.map);
+    app.primary
+        .sim
+        .handle_live_edits_by_rerouting(&app.primary.map)
+}
@@ -0,0 +1,148 @@
+use std::time::SystemTime;
+
+use instant::Instant;
+
+use map_gui::load::MapLoader;
+use map_gui::ID;
+use widgetry::{
+    EventCtx, GfxCtx, HorizontalAlignment, Line, Outcome, Panel, StyledButtons, Text,
+    VerticalAlignment, Widget,
+};
+
+use crate::app::{App, Transition};
+use crate::common::launch_info_panel;
+use crate::info::Tab;
+use crate::sandbox::{GameplayMode, SandboxMode};
+
+/// Notices when the current map's file changes on disk, like after re-running the importer, and
+/// offers to reload it. Nothing is paused; the prompt just sits there until answered.
+pub struct MapFileWatcher {
+    last_modified: Option<SystemTime>,
+    last_check: Instant,
+    panel: Option<Panel>,
+}
+
+impl MapFileWatcher {
+    pub fn new(app: &App) -> MapFileWatcher {
+        MapFileWatcher {
+            last_modified: modified_time(app),
+            last_check: Instant::now(),
+            panel: None,
+        }
+    }
+
+    /// `info_panel` is the object whose info panel is open, to re-open after reloading.
+    pub fn event(
+        &mut self,
+        ctx: &mut EventCtx,
+        app: &mut App,
+        mode: &GameplayMode,
+        info_panel: Option<ID>,
+    ) -> Option<Transition> {
+        if let Some(ref mut panel) = self.panel {
+            if let Outcome::Clicked(x) = panel.event(ctx) {
+                self.panel = None;
+                match x.as_ref() {
+                    "reload" => {
+                        return Some(reload(ctx, app, mode.clone(), info_panel));
+                    }
+                    // Only ask again if the file changes again
+                    "not now" => {}
+                    _ => unreachable!(),
+                }
+            }
+        }
+
+        // Polling the filesystem is cheap, but not free
+        if self.last_check.elapsed() < std::time::Duration::from_secs(2) {
+            return None;
+        }
+        self.last_check = Instant::now();
+
+        let modified = modified_time(app);
+        if modified == self.last_modified {
+            return None;
+        }
+        self.last_modified = modified;
+        // If the file is gone, there's nothing to reload
+        if modified.is_some() {
+            self.panel = Some(make_panel(ctx, app));
+        }
+        None
+    }
+
+    pub fn draw(&self, g: &mut GfxCtx) {
+        if let Some(ref panel) = self.panel {
+            panel.draw(g);
+        }
+    }
+}
+
+fn make_panel(ctx: &mut EventCtx, app: &App) -> Panel {
+    let mut txt = Text::from(Line("The map changed on disk").small_heading());
+    txt.add(Line(app.primary.map.get_name().path()).secondary());
+    txt.add(Line(
+        "Reloading restarts the simulation from the beginning, without any map edits. Edits \
+         are still saved, and can be loaded again from edit mode.",
+    ));
+    Panel::new(Widget::col(vec![
+        txt.wrap_to_pct(ctx, 30).draw(ctx),
+        Widget::row(vec![
+            ctx.style().btn_solid_dark_text("reload").build_def(ctx),
+            ctx.style().btn_plain_light_text("not now").build_def(ctx),
+        ]),
+    ]))
+    .aligned(HorizontalAlignment::Left, VerticalAlignment::Center)
+    .build(ctx)
+}
+
+fn reload(ctx: &mut EventCtx, app: &App, mode: GameplayMode, info_panel: Option<ID>) -> Transition {
+    let camera = (ctx.canvas.center_to_map_pt(), ctx.canvas.cam_zoom);
+    Transition::Push(MapLoader::force_reload(
+        ctx,
+        app.primary.map.get_name().clone(),
+        Box::new(move |_, app| {
+            Transition::Multi(vec![
+                Transition::Pop,
+                Transition::Replace(SandboxMode::async_new(
+                    app,
+                    mode,
+                    Box::new(move |ctx, app| {
+                        ctx.canvas.cam_zoom = camera.1;
+                        ctx.canvas.center_on_map_pt(camera.0);
+
+                        // Agents are gone after restarting the simulation, and the object might
+                        // not exist in the new map
+                        match info_panel {
+                            Some(id) if still_exists(app, &id) => {
+                                vec![launch_info_panel(Tab::from_id(app, id))]
+                            }
+                            _ => Vec::new(),
+                        }
+                    }),
+                )),
+            ])
+        }),
+    ))
+}
+
+fn still_exists(app: &App, id: &ID) -> bool {
+    let map = &app.primary.map;
+    match id {
+        ID::Road(r) => map.maybe_get_r(*r).is_some(),
+        ID::Lane(l) => map.maybe_get_l(*l).is_some(),
+        ID::Intersection(i) => map.maybe_get_i(*i).is_some(),
+        ID::Building(b) => map.maybe_get_b(*b).is_some(),
+        ID::ParkingLot(pl) => map.maybe_get_pl(*pl).is_some(),
+        ID::BusStop(bs) => map.maybe_get_bs(*bs).is_some(),
+        ID::Area(a) => map.maybe_get_a(*a).is_some(),
+        ID::Car(_) | ID::Pedestrian(_) | ID::PedCrowd(_) => false,
+    }
+}
+
+// Doesn't work on the web, which just means the watcher never notices anything
+fn modified_time(app: &App) -> Option<SystemTime> {
+    std::fs::metadata(app.primary.map.get_name().path())
+        .and_then(|m| m.modified())
+        .ok()
+}
@@ -0,0 +1,202 @@
+use std::collections::BTreeMap;
+
+use abstutil::prettyprint_usize;
+use geom::{Circle, Distance, Duration, Pt2D, Time};
+use map_gui::tools::ColorLegend;
+use map_model::{RoadID, TurnID};
+use sim::{MergeEvent, MergeManeuver};
+use widgetry::{
+    Color, Drawable, EventCtx, GeomBatch, GfxCtx, HorizontalAlignment, Line, Outcome, Panel,
+    StyledButtons, Text, VerticalAlignment, Widget,
+};
+
+use crate::app::App;
+
+// Markers fade out over this long
+const MARKER_LIFETIME: Duration = Duration::const_seconds(2.0);
+const MAX_LOCATIONS_LISTED: usize = 8;
+const FORCED_BRAKE: Color = Color::RED;
+const SMOOTH: Color = Color::CYAN;
+
+/// Live lane changes and yields onto or off of one road. Each maneuver briefly flashes on the map,
+/// and the panel counts them per turn. The simulation only records anything while this exists, so
+/// closing it stops the watch.
+pub struct MergeWatcher {
+    road: RoadID,
+    since: Time,
+    last_update: Time,
+    /// Recent maneuvers still being drawn: when, where, and if somebody had to brake
+    markers: Vec<(Time, Pt2D, bool)>,
+    /// Per maneuver and turn, how many happened and how many forced somebody to brake
+    counts: BTreeMap<(MergeManeuver, TurnID), (usize, usize)>,
+    panel: Panel,
+    draw_road: Drawable,
+}
+
+impl MergeWatcher {
+    pub fn new(ctx: &mut EventCtx, app: &mut App, road: RoadID) -> MergeWatcher {
+        app.primary.sim.watch_merges(road);
+        let now = app.primary.sim.time();
+        let map = &app.primary.map;
+        let polygon = map.get_r(road).get_thick_polygon(map);
+        let mut batch = GeomBatch::new();
+        batch.push(
+            Color::YELLOW,
+            polygon
+                .to_outline(Distance::meters(1.0))
+                .unwrap_or_else(|_| polygon.clone()),
+        );
+        let mut watcher = MergeWatcher {
+            road,
+            since: now,
+            last_update: now,
+            markers: Vec::new(),
+            counts: BTreeMap::new(),
+            panel: Panel::empty(ctx),
+            draw_road: ctx.upload(batch),
+        };
+        watcher.recreate_panel(ctx, app);
+        watcher
+    }
+
+    /// Returns true when the watch should be closed. The simulation has already stopped watching.
+    pub fn event(&mut self, ctx: &mut EventCtx, app: &mut App) -> bool {
+        if let Outcome::Clicked(x) = self.panel.event(ctx) {
+            match x.as_ref() {
+                "close" => {
+                    app.primary.sim.stop_watching_merges();
+                    return true;
+                }
+                _ => unreachable!(),
+            }
+        }
+
+        let now = app.primary.sim.time();
+        if now == self.last_update {
+            return false;
+        }
+        // Time goes backwards after rewinding or resetting, and the new simulation won't be
+        // watching anything yet
+        if now < self.last_update {
+            self.since = now;
+            self.markers.clear();
+            self.counts.clear();
+        }
+        self.last_update = now;
+        if app
+            .primary
+            .sim
+            .get_mut_merge_watch()
+            .map(|w| w.road() != self.road)
+            .unwrap_or(true)
+        {
+            app.primary.sim.watch_merges(self.road);
+        }
+
+        let events = app.primary.sim.get_mut_merge_watch().unwrap().take_events();
+        let changed = !events.is_empty();
+        for ev in events {
+            self.markers
+                .push((ev.time, marker_pos(app, &ev), ev.forced_brake));
+            let count = self.counts.entry((ev.maneuver, ev.turn)).or_insert((0, 0));
+            count.0 += 1;
+            if ev.forced_brake {
+                count.1 += 1;
+            }
+        }
+        self.markers.retain(|(t, _, _)| now - *t < MARKER_LIFETIME);
+        if changed {
+            self.recreate_panel(ctx, app);
+        }
+        false
+    }
+
+    fn recreate_panel(&mut self, ctx: &mut EventCtx, app: &App) {
+        let mut col = vec![
+            Widget::row(vec![
+                Line(format!(
+                    "Merges on {}",
+                    app.primary
+                        .map
+                        .get_r(self.road)
+                        .get_name(app.opts.language.as_ref())
+                ))
+                .small_heading()
+                .draw(ctx),
+                ctx.style().btn_close_widget(ctx),
+            ]),
+            Text::from(Line(format!("Since {}", self.since.ampm_tostring())).secondary()).draw(ctx),
+            ColorLegend::row(ctx, FORCED_BRAKE, "somebody had to brake"),
+            ColorLegend::row(ctx, SMOOTH, "nobody had to brake"),
+        ];
+
+        let mut txt = Text::new();
+        for (maneuver, label) in vec![
+            (MergeManeuver::LaneChange, "lane changes"),
+            (MergeManeuver::Yield, "yields"),
+        ] {
+            let (total, braked) = self
+                .counts
+                .iter()
+                .filter(|((m, _), _)| *m == maneuver)
+                .fold((0, 0), |(a, b), (_, (total, braked))| {
+                    (a + total, b + braked)
+                });
+            txt.add(Line(format!(
+                "{} {}, {} with braking",
+                prettyprint_usize(total),
+                label,
+                prettyprint_usize(braked)
+            )));
+        }
+
+        let mut busiest: Vec<_> = self.counts.iter().collect();
+        busiest.sort_by_key(|(_, (total, _))| std::cmp::Reverse(*total));
+        if !busiest.is_empty() {
+            txt.add(Line("Busiest places").small_heading());
+        }
+        for ((maneuver, turn), (total, braked)) in busiest.into_iter().take(MAX_LOCATIONS_LISTED) {
+            txt.add(Line(format!(
+                "{}x {} from {} to {} ({} with braking)",
+                prettyprint_usize(*total),
+                match maneuver {
+                    MergeManeuver::LaneChange => "lane change",
+                    MergeManeuver::Yield => "yield",
+                },
+                turn.src,
+                turn.dst,
+                prettyprint_usize(*braked)
+            )));
+        }
+        col.push(txt.draw(ctx));
+
+        self.panel = Panel::new(Widget::col(col))
+            .aligned(HorizontalAlignment::Right, VerticalAlignment::Center)
+            .build(ctx);
+    }
+
+    pub fn draw(&self, g: &mut GfxCtx, app: &App) {
+        self.panel.draw(g);
+        g.redraw(&self.draw_road);
+
+        let now = app.primary.sim.time();
+        for (t, pt, forced_brake) in &self.markers {
+            // Each marker grows and fades out
+            let pct = ((now - *t) / MARKER_LIFETIME).min(1.0);
+            let color = if *forced_brake { FORCED_BRAKE } else { SMOOTH };
+            g.draw_polygon(
+                color.alpha(1.0 - pct as f32),
+                Circle::new(*pt, Distance::meters(1.0 + 3.0 * pct)).to_polygon(),
+            );
+        }
+    }
+}
+
+/// Lane changes happen partway through the turn, and vehicles yield right before starting it
+fn marker_pos(app: &App, ev: &MergeEvent) -> Pt2D {
+    let geom = &app.primary.map.get_t(ev.turn).geom;
+    match ev.maneuver {
+        MergeManeuver::LaneChange => geom.middle(),
+        MergeManeuver::Yield => geom.first_pt(),
+    }
+}
@@ -2,39 +2,71 @@ use anyhow::Result;
 use maplit::btreeset;
 
 use abstutil::prettyprint_usize;
-use geom::{Circle, Distance, Pt2D, Time};
+use geom::{Circle, Distance, Duration, Pt2D, Time};
 use map_gui::colors::ColorSchemeChoice;
 use map_gui::load::{FileLoader, MapLoader};
 use map_gui::options::OptionsPanel;
 use map_gui::render::{unzoomed_agent_radius, UnzoomedAgents};
 use map_gui::tools::{ChooseSomething, Minimap, PopupMsg, TurnExplorer};
 use map_gui::{AppLike, ID};
+use map_model::LaneType;
 use sim::{Analytics, Scenario};
 use widgetry::{
     lctrl, Choice, Color, EventCtx, GeomBatch, GfxCtx, HorizontalAlignment, Key, Line, Outcome,
     Panel, State, StyledButtons, Text, TextExt, UpdateType, VerticalAlignment, Widget,
 };
 
-pub use self::gameplay::{spawn_agents_around, GameplayMode, TutorialPointer, TutorialState};
+use self::building_overrides::BuildingOverrideEditor;
+pub use self::building_overrides::{override_badge, MapExperiments};
+pub use self::custom_trips::{CustomTripSpawner, CustomTrips};
+pub use self::gameplay::{
+    spawn_agents_around, GameplayMode, TourStep, TutorialPointer, TutorialState,
+};
+use self::gridlock::GridlockDetector;
+use self::hud::Hud;
+pub use self::lane_closure::{apply_live_edits, hatching, LaneClosure};
+use self::map_watcher::MapFileWatcher;
+use self::merge_watcher::MergeWatcher;
 use self::misc_tools::{RoutePreview, TrafficRecorder};
+use self::multi_select::MultiSelect;
+pub use self::quick_edit::QuickEdit;
+use self::replay::{Recorder, ReplaySetup};
 pub use self::speed::{SpeedControls, TimePanel};
+pub use self::split_screen::PickEditsToCompare;
+use self::time_lapse::{TimeLapse, TimeLapseSetup};
 pub use self::time_warp::TimeWarpScreen;
+pub use self::trip_watcher::{TripWatcher, WatchFor};
 use crate::app::{App, Transition};
-use crate::common::{tool_panel, CommonState, MinimapController};
+use crate::common::{tool_panel, ColorSchemeWatcher, CommonState, MinimapController};
 use crate::debug::DebugMode;
 use crate::edit::{
-    can_edit_lane, EditMode, LaneEditor, SaveEdits, StopSignEditor, TrafficSignalEditor,
+    is_editable_lane, EditMode, LaneEditor, SaveEdits, StopSignEditor, TrafficSignalEditor,
 };
-use crate::info::ContextualActions;
+use crate::info::{ContextualActions, OpenTrip, Tab};
 use crate::layer::favorites::{Favorites, ShowFavorites};
 use crate::layer::PickLayer;
 use crate::pregame::MainMenu;
+use crate::sandbox::dashboards::WatchList;
 
+mod building_overrides;
+mod custom_trips;
 pub mod dashboards;
 pub mod gameplay;
+mod gridlock;
+mod hud;
+mod lane_closure;
+mod map_watcher;
+mod merge_watcher;
 mod misc_tools;
+mod multi_select;
+mod quick_edit;
+mod replay;
+mod rewind;
 mod speed;
+mod split_screen;
+mod time_lapse;
 mod time_warp;
+mod trip_watcher;
 mod uber_turns;
 
 pub struct SandboxMode {
@@ -45,6 +77,8 @@ pub struct SandboxMode {
 
     recalc_unzoomed_agent: Option<Time>,
     last_cs: ColorSchemeChoice,
+    color_watcher: ColorSchemeWatcher,
+    map_watcher: MapFileWatcher,
 }
 
 pub struct SandboxControls {
@@ -54,7 +88,13 @@ pub struct SandboxControls {
     time_panel: Option<TimePanel>,
     speed: Option<SpeedControls>,
     pub agent_meter: Option<AgentMeter>,
+    hud: Option<Hud>,
     minimap: Option<Minimap<App, MinimapController>>,
+    time_lapse: Option<TimeLapse>,
+    recorder: Option<Recorder>,
+    pub trip_watcher: TripWatcher,
+    gridlock: Option<GridlockDetector>,
+    merge_watcher: Option<MergeWatcher>,
 }
 
 impl SandboxMode {
@@ -105,6 +145,20 @@ impl State<App> for SandboxMode {
             };
         }
 
+        if let Some(t) = self.color_watcher.event(ctx, app) {
+            return t;
+        }
+        let info_panel = self
+            .controls
+            .common
+            .as_ref()
+            .and_then(|c| c.info_panel_open(app));
+        if let Some(t) = self
+            .map_watcher
+            .event(ctx, app, &self.gameplay_mode, info_panel)
+        {
+            return t;
+        }
         if app.opts.color_scheme != self.last_cs {
             self.last_cs = app.opts.color_scheme;
             self.controls.recreate_panels(ctx, app);
@@ -132,6 +186,12 @@ impl State<App> for SandboxMode {
         if app.opts.dev && ctx.input.pressed(lctrl(Key::D)) {
             return Transition::Push(DebugMode::new(ctx));
         }
+        if app.opts.dev && self.controls.time_lapse.is_none() && ctx.input.pressed(lctrl(Key::T)) {
+            return Transition::Push(TimeLapseSetup::new(ctx));
+        }
+        if app.opts.dev && self.controls.recorder.is_none() && ctx.input.pressed(lctrl(Key::R)) {
+            return Transition::Push(ReplaySetup::new(ctx));
+        }
 
         if let Some(ref mut m) = self.controls.minimap {
             if let Some(t) = m.event(ctx, app) {
@@ -147,6 +207,60 @@ impl State<App> for SandboxMode {
                 return t;
             }
         }
+        // After the speed controls, so we capture the latest time
+        if let Some(ref mut tl) = self.controls.time_lapse {
+            if tl.event(ctx, app) {
+                self.controls.time_lapse = None;
+            }
+        }
+        if let Some(ref mut recorder) = self.controls.recorder {
+            if recorder.event(ctx, app) {
+                let path = self.controls.recorder.take().unwrap().save(app);
+                return Transition::Push(PopupMsg::new(
+                    ctx,
+                    "Recording saved",
+                    vec![format!("Wrote {}", path)],
+                ));
+            }
+        }
+        let triggered = self.controls.trip_watcher.event(ctx, app);
+        if !triggered.is_empty() {
+            if let Some(ref mut s) = self.controls.speed {
+                s.pause(ctx, app);
+            }
+            // Show the first trip that triggered
+            let trip = triggered[0].0;
+            let mut actions = self.contextual_actions();
+            if let (Some(ref mut c), Some(person)) = (
+                self.controls.common.as_mut(),
+                app.primary.sim.trip_to_person(trip),
+            ) {
+                c.launch_info_panel(
+                    ctx,
+                    app,
+                    Tab::PersonTrips(person, OpenTrip::single(trip)),
+                    &mut actions,
+                );
+            }
+            return Transition::Push(PopupMsg::new(
+                ctx,
+                "Paused",
+                triggered.into_iter().map(|(_, msg)| msg).collect(),
+            ));
+        }
+        if let Some(ref mut gridlock) = self.controls.gridlock {
+            if let Some(t) = gridlock.event(ctx, app) {
+                return t;
+            }
+        }
+        if let Some(ref mut watcher) = self.controls.merge_watcher {
+            if watcher.event(ctx, app) {
+                self.controls.merge_watcher = None;
+            }
+        }
+        if let Some(t) = LaneClosure::event(ctx, app) {
+            return t;
+        }
 
         // We need to recalculate unzoomed agent mouseover when the mouse is still and time passes
         // (since something could move beneath the cursor), or when the mouse moves.
@@ -169,6 +283,15 @@ impl State<App> for SandboxMode {
             }
         }
 
+        // Shift-clicking starts selecting several objects, instead of opening the info panel
+        if self.controls.common.is_some() && ctx.is_key_down(Key::LeftShift) {
+            if let Some(id) = app.primary.current_selection.clone() {
+                if MultiSelect::can_select(&id) && ctx.normal_left_click() {
+                    return Transition::Push(MultiSelect::new(ctx, app, id, actions));
+                }
+            }
+        }
+
         // Fragile ordering. Let this work before tool_panel, so Key::Escape from the info panel
         // beats the one to quit. And let speed update the sim before we update the info panel.
         if let Some(ref mut c) = self.controls.common {
@@ -200,6 +323,20 @@ impl State<App> for SandboxMode {
                 return t;
             }
         }
+        if let Some(ref mut hud) = self.controls.hud {
+            let speed_ratio = self
+                .controls
+                .speed
+                .as_ref()
+                .filter(|s| !s.is_paused())
+                .and_then(|s| s.achieved_speed_ratio());
+            hud.event(
+                ctx,
+                app,
+                speed_ratio,
+                self.controls.agent_meter.as_ref().map(|am| &am.panel),
+            );
+        }
 
         if self
             .controls
@@ -219,6 +356,13 @@ impl State<App> for SandboxMode {
         if let Some(ref l) = app.primary.layer {
             l.draw(g, app);
         }
+        if let Some(ref closure) = app.primary.lane_closure {
+            closure.draw(g);
+        }
+        // Time-lapse screenshots should just show the map
+        if g.is_screencap() {
+            return;
+        }
 
         if let Some(ref c) = self.controls.common {
             c.draw(g, app);
@@ -237,9 +381,26 @@ impl State<App> for SandboxMode {
         if let Some(ref am) = self.controls.agent_meter {
             am.draw(g);
         }
+        if let Some(ref hud) = self.controls.hud {
+            hud.draw(g, app);
+        }
         if let Some(ref m) = self.controls.minimap {
             m.draw(g, app);
         }
+        if let Some(ref tl) = self.controls.time_lapse {
+            tl.draw(g);
+        }
+        if let Some(ref recorder) = self.controls.recorder {
+            recorder.draw(g);
+        }
+        self.controls.trip_watcher.draw(g);
+        if let Some(ref gridlock) = self.controls.gridlock {
+            gridlock.draw(g);
+        }
+        if let Some(ref watcher) = self.controls.merge_watcher {
+            watcher.draw(g, app);
+        }
+        self.map_watcher.draw(g);
         if let Some(ref r) = self.controls.route_preview {
             r.draw(g);
         }
@@ -248,6 +409,13 @@ impl State<App> for SandboxMode {
     }
 
     fn on_destroy(&mut self, _: &mut EventCtx, app: &mut App) {
+        // Don't lose a recording in progress
+        if let Some(recorder) = self.controls.recorder.take() {
+            recorder.save(app);
+        }
+        if self.controls.merge_watcher.take().is_some() {
+            app.primary.sim.stop_watching_merges();
+        }
         app.primary.layer = None;
         app.primary.agents.borrow_mut().unzoomed_agents = UnzoomedAgents::new(&app.cs);
         self.gameplay.on_destroy(app);
@@ -520,24 +688,27 @@ impl ContextualActions for Actions {
                     if app.primary.map.get_i(i).is_traffic_signal() {
                         actions.push((Key::E, "edit traffic signal".to_string()));
                     }
-                    if app.primary.map.get_i(i).is_stop_sign()
-                        && self.gameplay.can_edit_stop_signs()
-                    {
+                    if app.primary.map.get_i(i).is_stop_sign() {
                         actions.push((Key::E, "edit stop sign".to_string()));
                     }
                     if app.opts.dev {
                         actions.push((Key::U, "explore uber-turns".to_string()));
-                        if app.primary.sim.num_recorded_trips().is_none() {
-                            actions.push((Key::R, "record traffic here".to_string()));
-                        }
+                        actions.push((Key::R, "record traffic here".to_string()));
                     }
                 }
                 ID::Lane(l) => {
                     if !app.primary.map.get_turns_from_lane(l).is_empty() {
                         actions.push((Key::Z, "explore turns from this lane".to_string()));
                     }
-                    if can_edit_lane(&self.gameplay, l, app) {
+                    if is_editable_lane(l, app) {
                         actions.push((Key::E, "edit lane".to_string()));
+                        actions.push((Key::C, "close this lane for 1 hour".to_string()));
+                        if self.gameplay.can_edit_lanes() {
+                            actions.extend(QuickEdit::actions(app, l));
+                        }
+                    }
+                    if app.primary.map.get_l(l).lane_type.is_for_moving_vehicles() {
+                        actions.push((Key::M, "watch merges here".to_string()));
                     }
                 }
                 ID::Building(b) => {
@@ -546,10 +717,19 @@ impl ContextualActions for Actions {
                     } else {
                         actions.push((Key::F, "add this building to favorites".to_string()));
                     }
+                    actions.push((Key::T, "spawn trips from here".to_string()));
+                    actions.push((Key::G, "override trip generation".to_string()));
                 }
                 _ => {}
             }
         }
+        if WatchList::can_watch(&id) {
+            if WatchList::contains(app, id.clone()) {
+                actions.push((Key::W, "remove from watch list".to_string()));
+            } else {
+                actions.push((Key::W, "add to watch list".to_string()));
+            }
+        }
         actions.extend(match self.gameplay {
             GameplayMode::Freeform(_) => gameplay::freeform::actions(app, id),
             GameplayMode::Tutorial(_) => gameplay::tutorial::actions(app, id),
@@ -557,6 +737,45 @@ impl ContextualActions for Actions {
         });
         actions
     }
+    fn enabled(&self, app: &App, id: ID, action: &str) -> Result<(), String> {
+        match (id, action) {
+            (ID::Lane(_), "edit lane") if !self.gameplay.can_edit_lanes() => {
+                Err("Lanes can't be changed in this mode".to_string())
+            }
+            (ID::Lane(l), "close this lane for 1 hour") => {
+                if !self.gameplay.can_edit_lanes() {
+                    Err("Lanes can't be changed in this mode".to_string())
+                } else if app.primary.map.get_l(l).lane_type == LaneType::Construction {
+                    Err("This lane is already closed".to_string())
+                } else if app.primary.lane_closure.is_some() {
+                    Err("Only one lane can be closed at a time".to_string())
+                } else {
+                    Ok(())
+                }
+            }
+            (ID::Intersection(_), "edit stop sign") if !self.gameplay.can_edit_stop_signs() => {
+                Err("Stop signs can't be changed in this mode".to_string())
+            }
+            (ID::Building(_), "override trip generation")
+                if !matches!(self.gameplay, GameplayMode::PlayScenario(_, _, _)) =>
+            {
+                Err("Only scenarios can be changed".to_string())
+            }
+            (ID::Intersection(_), "record traffic here")
+                if app.primary.sim.num_recorded_trips().is_some() =>
+            {
+                Err("Already recording traffic somewhere".to_string())
+            }
+            (id, "follow (run the simulation)") => match id {
+                ID::Car(c) if app.primary.sim.lookup_parked_car(c).is_some() => {
+                    Err("Parked cars don't go anywhere".to_string())
+                }
+                ID::Car(_) | ID::Pedestrian(_) | ID::PedCrowd(_) => Ok(()),
+                _ => Err("There's nobody moving here to follow".to_string()),
+            },
+            _ => Ok(()),
+        }
+    }
     fn execute(
         &mut self,
         ctx: &mut EventCtx,
@@ -588,10 +807,31 @@ impl ContextualActions for Actions {
             (ID::Lane(l), "explore turns from this lane") => {
                 Transition::Push(TurnExplorer::new(ctx, app, l))
             }
+            (ID::Lane(l), "watch merges here") => {
+                let r = app.primary.map.get_l(l).parent;
+                Transition::ModifyState(Box::new(move |state, ctx, app| {
+                    let mode = state.downcast_mut::<SandboxMode>().unwrap();
+                    mode.controls.merge_watcher = Some(MergeWatcher::new(ctx, app, r));
+                }))
+            }
             (ID::Lane(l), "edit lane") => Transition::Multi(vec![
                 Transition::Push(EditMode::new(ctx, app, self.gameplay.clone())),
                 Transition::Push(LaneEditor::new(ctx, app, l, self.gameplay.clone())),
             ]),
+            (ID::Lane(l), "close this lane for 1 hour") => {
+                // Keep the panel open to show the countdown
+                *close_panel = false;
+                LaneClosure::start(ctx, app, l, Duration::hours(1))
+            }
+            (ID::Lane(l), "convert to a bike lane")
+            | (ID::Lane(l), "convert to a bus lane")
+            | (ID::Lane(l), "reverse direction")
+            | (ID::Lane(l), "raise the speed limit")
+            | (ID::Lane(l), "lower the speed limit") => {
+                // Keep the panel open to show what changed
+                *close_panel = false;
+                QuickEdit::apply(ctx, app, l, &action)
+            }
             (ID::Building(b), "add this building to favorites") => {
                 Favorites::add(app, b);
                 app.primary.layer = Some(Box::new(ShowFavorites::new(ctx, app)));
@@ -602,13 +842,27 @@ impl ContextualActions for Actions {
                 app.primary.layer = Some(Box::new(ShowFavorites::new(ctx, app)));
                 Transition::Keep
             }
+            (ID::Building(b), "spawn trips from here") => {
+                Transition::Push(CustomTripSpawner::new(ctx, app, b))
+            }
+            (ID::Building(b), "override trip generation") => Transition::Push(
+                BuildingOverrideEditor::new(ctx, app, b, self.gameplay.clone()),
+            ),
+            (id, "add to watch list") => {
+                WatchList::add(app, id);
+                Transition::Keep
+            }
+            (id, "remove from watch list") => {
+                WatchList::remove(app, id);
+                Transition::Keep
+            }
             (_, "follow (run the simulation)") => {
                 *close_panel = false;
                 Transition::ModifyState(Box::new(|state, ctx, app| {
                     let mode = state.downcast_mut::<SandboxMode>().unwrap();
                     let speed = mode.controls.speed.as_mut().unwrap();
                     assert!(speed.is_paused());
-                    speed.resume_realtime(ctx, app);
+                    speed.follow_agent(ctx, app);
                 }))
             }
             (_, "unfollow (pause the simulation)") => {
@@ -752,11 +1006,17 @@ impl State<App> for SandboxLoader {
                 LoadStage::GotScenario(mut scenario) => {
                     let scenario_name = scenario.scenario_name.clone();
                     ctx.loading_screen("instantiate scenario", |_, mut timer| {
+                        let mut num_synthetic = 0;
                         if let GameplayMode::PlayScenario(_, _, ref modifiers) = self.mode {
                             for m in modifiers {
                                 scenario = m.apply(&app.primary.map, scenario);
                             }
+                            num_synthetic = app
+                                .primary
+                                .experiments
+                                .apply(&app.primary.map, &mut scenario);
                         }
+                        app.primary.has_synthetic_trips = num_synthetic > 0;
 
                         scenario.instantiate(
                             &mut app.primary.sim,
@@ -831,6 +1091,8 @@ impl State<App> for SandboxLoader {
                         gameplay_mode: self.mode.clone(),
                         recalc_unzoomed_agent: None,
                         last_cs: app.opts.color_scheme,
+                        color_watcher: ColorSchemeWatcher::new(app),
+                        map_watcher: MapFileWatcher::new(app),
                     });
 
                     let mut transitions = vec![Transition::Replace(sandbox)];
@@ -916,11 +1178,25 @@ impl SandboxControls {
             } else {
                 None
             },
+            hud: if gameplay.has_speed() {
+                Some(Hud::new())
+            } else {
+                None
+            },
             minimap: if gameplay.has_minimap() {
                 Some(Minimap::new(ctx, app, MinimapController))
             } else {
                 None
             },
+            time_lapse: None,
+            recorder: None,
+            trip_watcher: TripWatcher::new(),
+            gridlock: if gameplay.has_speed() {
+                Some(GridlockDetector::new())
+            } else {
+                None
+            },
+            merge_watcher: None,
         }
     }
 
@@ -934,5 +1210,17 @@ impl SandboxControls {
         if let Some(ref mut minimap) = self.minimap {
             minimap.recreate_panel(ctx, app);
         }
+        if let Some(ref mut hud) = self.hud {
+            hud.recreate_panel();
+        }
+    }
+
+    /// The lowest of the panels stacked on the top right, for gameplay modes to put more panels
+    /// beneath.
+    pub fn lowest_right_panel(&self, app: &App) -> Option<&Panel> {
+        self.hud
+            .as_ref()
+            .and_then(|hud| hud.panel(app))
+            .or_else(|| self.agent_meter.as_ref().map(|am| &am.panel))
     }
 }
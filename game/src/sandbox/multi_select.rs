@@ -0,0 +1,327 @@
+use std::collections::BTreeSet;
+
+use geom::{Polygon, Pt2D};
+use map_gui::ID;
+use widgetry::{
+    Color, Drawable, EventCtx, GeomBatch, GfxCtx, HorizontalAlignment, Key, Line, Outcome, Panel,
+    PlotOptions, State, StyledButtons, TextExt, VerticalAlignment, Widget,
+};
+
+use crate::app::{App, Transition};
+use crate::common::{agent_type_plot, sum_counts_per_hour, CommonState};
+use crate::info::ContextualActions;
+use crate::sandbox::Actions;
+
+/// Accumulates a set of lanes, intersections, or buildings, then shows their combined traffic or
+/// applies one contextual action to each of them.
+pub struct MultiSelect {
+    // All of the same type
+    members: BTreeSet<ID>,
+    actions: Actions,
+    // Where a rectangle selection started, in map-space
+    drag_from: Option<Pt2D>,
+    // The rectangle being dragged, redrawn only when the cursor moves
+    drag_rect: Drawable,
+    panel: Panel,
+    highlight: Drawable,
+}
+
+impl MultiSelect {
+    /// Only some kinds of objects can be selected together.
+    pub fn can_select(id: &ID) -> bool {
+        matches!(id, ID::Lane(_) | ID::Intersection(_) | ID::Building(_))
+    }
+
+    pub fn new(ctx: &mut EventCtx, app: &App, first: ID, actions: Actions) -> Box<dyn State<App>> {
+        let mut state = MultiSelect {
+            members: BTreeSet::new(),
+            actions,
+            drag_from: None,
+            drag_rect: Drawable::empty(ctx),
+            panel: Panel::empty(ctx),
+            highlight: Drawable::empty(ctx),
+        };
+        state.members.insert(first);
+        state.update(ctx, app);
+        Box::new(state)
+    }
+
+    fn same_type(&self, id: &ID) -> bool {
+        self.members
+            .iter()
+            .next()
+            .map(|x| std::mem::discriminant(x) == std::mem::discriminant(id))
+            .unwrap_or_else(|| MultiSelect::can_select(id))
+    }
+
+    fn toggle(&mut self, id: ID) {
+        if !self.members.remove(&id) && self.same_type(&id) {
+            self.members.insert(id);
+        }
+    }
+
+    fn select_area(&mut self, app: &App, rect: &Polygon) {
+        let map = &app.primary.map;
+        let candidates: Vec<(ID, Pt2D)> = match self.members.iter().next() {
+            Some(ID::Lane(_)) => map
+                .all_lanes()
+                .iter()
+                .map(|l| (ID::Lane(l.id), l.lane_center_pts.middle()))
+                .collect(),
+            Some(ID::Intersection(_)) => map
+                .all_intersections()
+                .iter()
+                .map(|i| (ID::Intersection(i.id), i.polygon.center()))
+                .collect(),
+            Some(ID::Building(_)) => map
+                .all_buildings()
+                .iter()
+                .map(|b| (ID::Building(b.id), b.polygon.center()))
+                .collect(),
+            _ => Vec::new(),
+        };
+        for (id, pt) in candidates {
+            if rect.contains_pt(pt) {
+                self.members.insert(id);
+            }
+        }
+    }
+
+    fn update(&mut self, ctx: &mut EventCtx, app: &App) {
+        let map = &app.primary.map;
+        let mut batch = GeomBatch::new();
+        for id in &self.members {
+            let polygon = match id {
+                ID::Lane(l) => {
+                    let lane = map.get_l(*l);
+                    lane.lane_center_pts.make_polygons(lane.width)
+                }
+                ID::Intersection(i) => map.get_i(*i).polygon.clone(),
+                ID::Building(b) => map.get_b(*b).polygon.clone(),
+                _ => unreachable!(),
+            };
+            batch.push(app.cs.perma_selected_object, polygon);
+        }
+        self.highlight = ctx.upload(batch);
+
+        let noun = match self.members.iter().next() {
+            Some(ID::Lane(_)) => "lane",
+            Some(ID::Intersection(_)) => "intersection",
+            Some(ID::Building(_)) => "building",
+            _ => "object",
+        };
+        let mut col = vec![
+            Widget::row(vec![
+                Line(format!(
+                    "{} {}{} selected",
+                    self.members.len(),
+                    noun,
+                    if self.members.len() == 1 { "" } else { "s" }
+                ))
+                .small_heading()
+                .draw(ctx),
+                ctx.style().btn_close_widget(ctx),
+            ]),
+            "Click to add or remove, hold shift and drag to select an area".draw_text(ctx),
+        ];
+        if matches!(
+            self.members.iter().next(),
+            Some(ID::Lane(_)) | Some(ID::Intersection(_))
+        ) {
+            col.push(
+                ctx.style()
+                    .btn_outline_light_text("combined traffic plot")
+                    .disabled(self.members.is_empty())
+                    .build_def(ctx),
+            );
+        }
+
+        let common_actions = self.common_actions(app);
+        if !common_actions.is_empty() {
+            col.push("Apply to each:".draw_text(ctx));
+            for action in common_actions {
+                col.push(
+                    ctx.style()
+                        .btn_outline_light_text(&action)
+                        .build_widget(ctx, &action),
+                );
+            }
+        }
+
+        self.panel = Panel::new(Widget::col(col))
+            .aligned(HorizontalAlignment::Right, VerticalAlignment::Top)
+            .build(ctx);
+    }
+
+    // Only the actions available and enabled for every member
+    fn common_actions(&self, app: &App) -> Vec<String> {
+        let mut result: Option<Vec<String>> = None;
+        for id in &self.members {
+            let available: Vec<String> = self
+                .actions
+                .actions(app, id.clone())
+                .into_iter()
+                .map(|(_, action)| action)
+                .filter(|action| self.actions.enabled(app, id.clone(), action).is_ok())
+                .collect();
+            result = Some(match result {
+                Some(actions) => actions
+                    .into_iter()
+                    .filter(|a| available.contains(a))
+                    .collect(),
+                None => available,
+            });
+        }
+        result.unwrap_or_else(Vec::new)
+    }
+}
+
+impl State<App> for MultiSelect {
+    fn event(&mut self, ctx: &mut EventCtx, app: &mut App) -> Transition {
+        let shift = ctx.is_key_down(Key::LeftShift);
+        if !shift && self.drag_from.is_none() {
+            ctx.canvas_movement();
+        }
+        if ctx.redo_mouseover() {
+            app.recalculate_current_selection(ctx);
+        }
+
+        if let Some(from) = self.drag_from {
+            if ctx.redo_mouseover() {
+                let mut batch = GeomBatch::new();
+                if let Some(rect) = ctx
+                    .canvas
+                    .get_cursor_in_map_space()
+                    .and_then(|to| Polygon::rectangle_two_corners(from, to))
+                {
+                    batch.push(Color::BLUE.alpha(0.3), rect);
+                }
+                self.drag_rect = ctx.upload(batch);
+            }
+            if ctx.input.left_mouse_button_released() {
+                self.drag_from = None;
+                self.drag_rect = Drawable::empty(ctx);
+                match ctx
+                    .canvas
+                    .get_cursor_in_map_space()
+                    .and_then(|to| Polygon::rectangle_two_corners(from, to))
+                {
+                    Some(rect) => {
+                        self.select_area(app, &rect);
+                    }
+                    // Didn't drag anywhere, so treat it like a click
+                    None => {
+                        if let Some(id) = app.primary.current_selection.clone() {
+                            self.toggle(id);
+                        }
+                    }
+                }
+                self.update(ctx, app);
+            }
+        } else if shift && ctx.input.left_mouse_button_pressed() {
+            self.drag_from = ctx.canvas.get_cursor_in_map_space();
+        } else if let Some(id) = app.primary.current_selection.clone() {
+            if ctx.normal_left_click() {
+                self.toggle(id);
+                self.update(ctx, app);
+            }
+        }
+
+        match self.panel.event(ctx) {
+            Outcome::Clicked(x) => match x.as_ref() {
+                "close" => Transition::Pop,
+                "combined traffic plot" => {
+                    Transition::Push(CombinedThroughput::new(ctx, app, &self.members))
+                }
+                action => {
+                    // Actions that open an editor or fail with a popup only make sense once, so
+                    // stop at the first member that needs a new state, instead of stacking one
+                    // for every member.
+                    let mut result = Transition::Keep;
+                    for id in self.members.clone() {
+                        let t = self
+                            .actions
+                            .execute(ctx, app, id, action.to_string(), &mut false);
+                        if !matches!(t, Transition::Keep) {
+                            result = t;
+                            break;
+                        }
+                    }
+                    self.update(ctx, app);
+                    result
+                }
+            },
+            _ => Transition::Keep,
+        }
+    }
+
+    fn draw(&self, g: &mut GfxCtx, app: &App) {
+        g.redraw(&self.highlight);
+        g.redraw(&self.drag_rect);
+        self.panel.draw(g);
+        CommonState::draw_osd(g, app);
+    }
+}
+
+/// Throughput summed over several lanes or intersections. Lanes count traffic along their entire
+/// road, so each road is only counted once.
+struct CombinedThroughput {
+    panel: Panel,
+}
+
+impl CombinedThroughput {
+    fn new(ctx: &mut EventCtx, app: &App, members: &BTreeSet<ID>) -> Box<dyn State<App>> {
+        let analytics = app.primary.sim.get_analytics();
+        let now = app.primary.sim.time();
+
+        let mut roads = BTreeSet::new();
+        let mut per_member = Vec::new();
+        for id in members {
+            match id {
+                ID::Lane(l) => {
+                    let r = app.primary.map.get_l(*l).parent;
+                    if roads.insert(r) {
+                        per_member.push(analytics.road_thruput.count_per_hour(r, now));
+                    }
+                }
+                ID::Intersection(i) => {
+                    per_member.push(analytics.intersection_thruput.count_per_hour(*i, now));
+                }
+                _ => unreachable!(),
+            }
+        }
+
+        let total = sum_counts_per_hour(per_member);
+
+        Box::new(CombinedThroughput {
+            panel: Panel::new(Widget::col(vec![
+                Widget::row(vec![
+                    Line(format!("Combined traffic for {} objects", members.len()))
+                        .small_heading()
+                        .draw(ctx),
+                    ctx.style().btn_close_widget(ctx),
+                ]),
+                "Number of commuters and vehicles per hour".draw_text(ctx),
+                agent_type_plot(ctx, app, total, Vec::new(), PlotOptions::filterable()),
+            ]))
+            .aligned(HorizontalAlignment::Center, VerticalAlignment::Center)
+            .build(ctx),
+        })
+    }
+}
+
+impl State<App> for CombinedThroughput {
+    fn event(&mut self, ctx: &mut EventCtx, _: &mut App) -> Transition {
+        match self.panel.event(ctx) {
+            Outcome::Clicked(x) => match x.as_ref() {
+                "close" => Transition::Pop,
+                _ => unreachable!(),
+            },
+            _ => Transition::Keep,
+        }
+    }
+
+    fn draw(&self, g: &mut GfxCtx, _: &App) {
+        self.panel.draw(g);
+    }
+}
@@ -0,0 +1,135 @@
+use abstutil::prettyprint_usize;
+use geom::Speed;
+use map_gui::tools::PopupMsg;
+use map_model::{EditCmd, LaneID, LaneType};
+use widgetry::{EventCtx, Key};
+
+use super::lane_closure::apply_live_edits;
+use crate::app::{App, Transition};
+use crate::edit::{reverse_lane, speed_limit_choices, try_change_lt};
+
+/// The last common edit made straight from a lane's info panel, without entering edit mode. It's
+/// a normal map edit, so it shows up in the edits list and gets saved like any other; this just
+/// remembers enough to describe and undo it. Any other change to the edits forgets it.
+pub struct QuickEdit {
+    pub lane: LaneID,
+    pub summary: Vec<String>,
+    cmd: EditCmd,
+}
+
+impl QuickEdit {
+    /// The shortcuts that make sense for a lane.
+    pub fn actions(app: &App, l: LaneID) -> Vec<(Key, String)> {
+        let map = &app.primary.map;
+        let lane = map.get_l(l);
+        let mut actions = Vec::new();
+        if lane.is_walkable() {
+            return actions;
+        }
+        if lane.lane_type != LaneType::Biking {
+            actions.push((Key::K, "convert to a bike lane".to_string()));
+        }
+        if lane.lane_type != LaneType::Bus {
+            actions.push((Key::U, "convert to a bus lane".to_string()));
+        }
+        actions.push((Key::R, "reverse direction".to_string()));
+        let speed_limit = map.get_r(lane.parent).speed_limit;
+        if next_speed_limit(app, speed_limit, true).is_some() {
+            actions.push((Key::RightBracket, "raise the speed limit".to_string()));
+        }
+        if next_speed_limit(app, speed_limit, false).is_some() {
+            actions.push((Key::LeftBracket, "lower the speed limit".to_string()));
+        }
+        actions
+    }
+
+    /// Applies one of the shortcuts to the live simulation.
+    pub fn apply(ctx: &mut EventCtx, app: &mut App, l: LaneID, action: &str) -> Transition {
+        let r = app.primary.map.get_l(l).parent;
+        let old_speed_limit = app.primary.map.get_r(r).speed_limit;
+        let (result, description) = match action {
+            "convert to a bike lane" => (
+                try_change_lt(ctx, &mut app.primary.map, l, LaneType::Biking),
+                "Converted to a bike lane".to_string(),
+            ),
+            "convert to a bus lane" => (
+                try_change_lt(ctx, &mut app.primary.map, l, LaneType::Bus),
+                "Converted to a bus lane".to_string(),
+            ),
+            "reverse direction" => (
+                Ok(reverse_lane(&app.primary.map, l)),
+                "Reversed the direction of the lane".to_string(),
+            ),
+            "raise the speed limit" | "lower the speed limit" => {
+                let speed_limit =
+                    next_speed_limit(app, old_speed_limit, action == "raise the speed limit")
+                        .unwrap();
+                (
+                    Ok(app.primary.map.edit_road_cmd(r, |new| {
+                        new.speed_limit = speed_limit;
+                    })),
+                    format!(
+                        "Changed the speed limit from {} to {}",
+                        old_speed_limit.to_string(&app.opts.units),
+                        speed_limit.to_string(&app.opts.units)
+                    ),
+                )
+            }
+            _ => unreachable!(),
+        };
+        let cmd = match result {
+            Ok(cmd) => cmd,
+            Err(err) => {
+                return Transition::Push(err);
+            }
+        };
+
+        let mut edits = app.primary.map.get_edits().clone();
+        edits.commands.push(cmd.clone());
+        let (rerouted, cancelled, parked_cars) = apply_live_edits(ctx, app, edits);
+        app.primary.quick_edit = Some(QuickEdit {
+            lane: l,
+            summary: vec![
+                description,
+                format!(
+                    "{} trips were rerouted, {} cancelled, and {} parked cars displaced. Trips \
+                     that haven't started yet will plan around the change.",
+                    prettyprint_usize(rerouted),
+                    prettyprint_usize(cancelled),
+                    prettyprint_usize(parked_cars)
+                ),
+            ],
+            cmd,
+        });
+        Transition::Keep
+    }
+
+    /// Reverts the last shortcut.
+    pub fn undo(ctx: &mut EventCtx, app: &mut App) -> Transition {
+        let quick = app.primary.quick_edit.take().unwrap();
+        let mut edits = app.primary.map.get_edits().clone();
+        // Any other edit since should've forgotten the shortcut, but never undo something else
+        if edits.commands.last() != Some(&quick.cmd) {
+            return Transition::Push(PopupMsg::new(
+                ctx,
+                "Error",
+                vec!["The map was edited since, so undo this from edit mode instead"],
+            ));
+        }
+        edits.commands.pop();
+        apply_live_edits(ctx, app, edits);
+        Transition::Keep
+    }
+}
+
+// The next choice up or down from the speed limit edit mode offers, if there is one.
+fn next_speed_limit(app: &App, current: Speed, raise: bool) -> Option<Speed> {
+    // The current limit might come from OSM and not exactly match one of the choices
+    let epsilon = Speed::miles_per_hour(0.5);
+    let mut choices = speed_limit_choices(app).into_iter().map(|c| c.data);
+    if raise {
+        choices.find(|s| *s > current + epsilon)
+    } else {
+        choices.filter(|s| *s < current - epsilon).last()
+    }
+}
@@ -0,0 +1,381 @@
+use std::collections::HashMap;
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use abstio::MapName;
+use abstutil::Timer;
+use geom::{Angle, Duration, Pt2D, Time};
+use sim::{AgentID, AgentType, TripID};
+
+/// Bump this whenever the encoding of frames changes.
+const FORMAT_VERSION: usize = 1;
+/// Every this many frames, positions are written in full instead of relative to the previous
+/// frame. Seeking only has to decode from the nearest keyframe, not from the beginning.
+const KEYFRAME_EVERY: usize = 60;
+/// Positions are rounded to a tenth of a meter.
+const UNITS_PER_METER: f64 = 10.0;
+
+/// Agent positions sampled at a fixed interval through a simulation, compact enough to keep a
+/// full day.
+#[derive(Serialize, Deserialize)]
+pub struct Replay {
+    pub header: ReplayHeader,
+    /// Every agent appearing in at least one frame. Frames refer to these by index.
+    pub agents: Vec<RecordedAgent>,
+    frames: Vec<Frame>,
+}
+
+// Just the first field of a serialized Replay. The rest of the format may have changed shape, so
+// check this before trying to read everything.
+#[derive(Deserialize)]
+struct FormatVersion(usize);
+
+/// Describes what was recorded, so playback can load the right map and warn about differences.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ReplayHeader {
+    pub version: usize,
+    pub map_name: MapName,
+    pub edits_name: String,
+    /// Catches edits that changed after recording, even when the name is the same
+    pub edits_checksum: u64,
+    pub scenario: String,
+    pub interval: Duration,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct RecordedAgent {
+    pub id: AgentID,
+    pub mode: AgentType,
+    pub trip: Option<TripID>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Frame {
+    time: Time,
+    /// For every agent present, in increasing order of index: the index minus the previous one,
+    /// then x and y minus the agent's position in its last frame (or zero at a keyframe), all as
+    /// zigzag varints, then one byte of heading.
+    data: Vec<u8>,
+}
+
+/// Where one agent was in one frame
+#[derive(Clone, Copy)]
+pub struct Sample {
+    /// Into `Replay::agents`
+    pub agent: usize,
+    pub pos: Pt2D,
+    pub heading: Angle,
+}
+
+impl Replay {
+    /// Reads a recording, refusing one written with a different encoding.
+    pub fn load(path: String) -> Result<Replay> {
+        let version: FormatVersion =
+            abstio::maybe_read_binary(path.clone(), &mut Timer::throwaway())?;
+        if version.0 != FORMAT_VERSION {
+            bail!(
+                "it was recorded with version {} of the replay format, but this build only plays \
+                 version {}",
+                version.0,
+                FORMAT_VERSION
+            );
+        }
+        abstio::maybe_read_binary(path, &mut Timer::throwaway())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    pub fn start_time(&self) -> Time {
+        self.frames[0].time
+    }
+
+    pub fn end_time(&self) -> Time {
+        self.frames.last().unwrap().time
+    }
+
+    /// Returns the frames just before and after a time, and how far the time is between them.
+    /// Outside of the recorded range, both frames are the same.
+    pub fn bracket(&self, time: Time) -> (usize, usize, f64) {
+        let idx = match self.frames.binary_search_by_key(&time, |f| f.time) {
+            Ok(idx) => return (idx, idx, 0.0),
+            Err(idx) => idx,
+        };
+        if idx == 0 {
+            return (0, 0, 0.0);
+        }
+        if idx == self.frames.len() {
+            return (idx - 1, idx - 1, 0.0);
+        }
+        let (t1, t2) = (self.frames[idx - 1].time, self.frames[idx].time);
+        (idx - 1, idx, (time - t1) / (t2 - t1))
+    }
+
+    /// Decodes one frame, starting from the closest keyframe before it.
+    pub fn decode(&self, frame: usize) -> Vec<Sample> {
+        let mut last_pos = HashMap::new();
+        let mut samples = Vec::new();
+        for idx in (frame - frame % KEYFRAME_EVERY)..=frame {
+            samples = decode_frame(&self.frames[idx].data, &mut last_pos);
+        }
+        samples
+    }
+}
+
+/// Builds up a `Replay` one frame at a time.
+pub struct ReplayWriter {
+    replay: Replay,
+    agent_indices: HashMap<(AgentID, Option<TripID>), usize>,
+    last_pos: HashMap<usize, (i64, i64)>,
+}
+
+impl ReplayWriter {
+    pub fn new(
+        map_name: MapName,
+        edits_name: String,
+        edits_checksum: u64,
+        scenario: String,
+        interval: Duration,
+    ) -> ReplayWriter {
+        ReplayWriter {
+            replay: Replay {
+                header: ReplayHeader {
+                    version: FORMAT_VERSION,
+                    map_name,
+                    edits_name,
+                    edits_checksum,
+                    scenario,
+                    interval,
+                },
+                agents: Vec::new(),
+                frames: Vec::new(),
+            },
+            agent_indices: HashMap::new(),
+            last_pos: HashMap::new(),
+        }
+    }
+
+    pub fn interval(&self) -> Duration {
+        self.replay.header.interval
+    }
+
+    pub fn num_frames(&self) -> usize {
+        self.replay.frames.len()
+    }
+
+    pub fn num_agents(&self) -> usize {
+        self.replay.agents.len()
+    }
+
+    /// Just the encoded positions, ignoring the small per-agent table
+    pub fn approx_bytes(&self) -> usize {
+        self.replay.frames.iter().map(|f| f.data.len()).sum()
+    }
+
+    /// Each agent should appear at most once.
+    pub fn add_frame(&mut self, time: Time, agents: Vec<(AgentID, Option<TripID>, Pt2D, Angle)>) {
+        if self.replay.frames.len() % KEYFRAME_EVERY == 0 {
+            self.last_pos.clear();
+        }
+
+        let mut entries = Vec::new();
+        for (id, trip, pos, heading) in agents {
+            let agents = &mut self.replay.agents;
+            let idx = *self.agent_indices.entry((id, trip)).or_insert_with(|| {
+                agents.push(RecordedAgent {
+                    id,
+                    mode: id.to_type(),
+                    trip,
+                });
+                agents.len() - 1
+            });
+            entries.push((idx, quantize(pos), heading));
+        }
+        entries.sort_by_key(|(idx, _, _)| *idx);
+
+        let mut data = Vec::new();
+        let mut prev_idx = 0;
+        for (idx, (x, y), heading) in entries {
+            let (last_x, last_y) = self.last_pos.insert(idx, (x, y)).unwrap_or((0, 0));
+            write_varint(&mut data, (idx - prev_idx) as u64);
+            write_varint(&mut data, zigzag(x - last_x));
+            write_varint(&mut data, zigzag(y - last_y));
+            data.push((heading.normalized_degrees() / 360.0 * 256.0).round() as u64 as u8);
+            prev_idx = idx;
+        }
+        self.replay.frames.push(Frame { time, data });
+    }
+
+    pub fn finish(self) -> Replay {
+        self.replay
+    }
+}
+
+fn decode_frame(data: &[u8], last_pos: &mut HashMap<usize, (i64, i64)>) -> Vec<Sample> {
+    let mut samples = Vec::new();
+    let mut cursor = 0;
+    let mut idx = 0;
+    while cursor < data.len() {
+        idx += read_varint(data, &mut cursor) as usize;
+        let (last_x, last_y) = last_pos.get(&idx).cloned().unwrap_or((0, 0));
+        let x = last_x + unzigzag(read_varint(data, &mut cursor));
+        let y = last_y + unzigzag(read_varint(data, &mut cursor));
+        let heading = Angle::degrees(f64::from(data[cursor]) / 256.0 * 360.0);
+        cursor += 1;
+        last_pos.insert(idx, (x, y));
+        samples.push(Sample {
+            agent: idx,
+            pos: Pt2D::new(x as f64 / UNITS_PER_METER, y as f64 / UNITS_PER_METER),
+            heading,
+        });
+    }
+    samples
+}
+
+fn quantize(pt: Pt2D) -> (i64, i64) {
+    (
+        (pt.x() * UNITS_PER_METER).round() as i64,
+        (pt.y() * UNITS_PER_METER).round() as i64,
+    )
+}
+
+// Small deltas of either sign become small unsigned numbers
+fn zigzag(x: i64) -> u64 {
+    ((x << 1) ^ (x >> 63)) as u64
+}
+
+fn unzigzag(x: u64) -> i64 {
+    ((x >> 1) as i64) ^ -((x & 1) as i64)
+}
+
+// LEB128: seven bits per byte, with the high bit set on every byte but the last
+fn write_varint(data: &mut Vec<u8>, mut x: u64) {
+    while x >= 0x80 {
+        data.push((x as u8) | 0x80);
+        x >>= 7;
+    }
+    data.push(x as u8);
+}
+
+fn read_varint(data: &[u8], cursor: &mut usize) -> u64 {
+    let mut x = 0;
+    let mut shift = 0;
+    loop {
+        let byte = data[*cursor];
+        *cursor += 1;
+        x |= u64::from(byte & 0x7f) << shift;
+        if byte < 0x80 {
+            return x;
+        }
+        shift += 7;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use geom::Distance;
+    use sim::PedestrianID;
+
+    #[test]
+    fn zigzag_round_trip() {
+        assert_eq!(0, zigzag(0));
+        assert_eq!(1, zigzag(-1));
+        assert_eq!(2, zigzag(1));
+        assert_eq!(3, zigzag(-2));
+        for x in vec![0, 1, -1, 63, -64, 1000, -1000, i64::MAX, i64::MIN] {
+            assert_eq!(x, unzigzag(zigzag(x)));
+        }
+    }
+
+    #[test]
+    fn varint_round_trip() {
+        let values = vec![0, 1, 127, 128, 300, 16383, 16384, u64::MAX];
+        let mut data = Vec::new();
+        for x in &values {
+            write_varint(&mut data, *x);
+        }
+        // 1 + 1 + 1 + 2 + 2 + 2 + 3 + 10 bytes
+        assert_eq!(22, data.len());
+        let mut cursor = 0;
+        for x in values {
+            assert_eq!(x, read_varint(&data, &mut cursor));
+        }
+        assert_eq!(data.len(), cursor);
+    }
+
+    #[test]
+    fn frames_round_trip() {
+        let mut writer = ReplayWriter::new(
+            MapName::seattle("montlake"),
+            "untitled edits".to_string(),
+            0,
+            "none".to_string(),
+            Duration::seconds(1.0),
+        );
+        let ped = |id| AgentID::Pedestrian(PedestrianID(id));
+        // Cross two keyframes
+        let mut expected = Vec::new();
+        for i in 0..2 * KEYFRAME_EVERY + 5 {
+            let t = i as f64;
+            // One agent always walks east. The other disappears sometimes and jumps around.
+            let mut agents = vec![(
+                ped(0),
+                None,
+                Pt2D::new(1.0 + 0.3 * t, 50.0),
+                Angle::degrees(0.0),
+            )];
+            if i % 7 != 0 {
+                agents.push((
+                    ped(1),
+                    None,
+                    Pt2D::new(500.0 - 2.5 * t, 10.0 * (i % 3) as f64),
+                    Angle::degrees(10.0 * t),
+                ));
+            }
+            writer.add_frame(Time::START_OF_DAY + Duration::seconds(t), agents.clone());
+            expected.push(agents);
+        }
+        let replay = writer.finish();
+        assert_eq!(2, replay.agents.len());
+
+        for (frame, agents) in expected.into_iter().enumerate() {
+            let samples = replay.decode(frame);
+            assert_eq!(agents.len(), samples.len());
+            for ((id, _, pos, heading), sample) in agents.into_iter().zip(samples) {
+                assert_eq!(id, replay.agents[sample.agent].id);
+                assert!(pos.dist_to(sample.pos) < Distance::meters(0.1));
+                let diff =
+                    (heading.normalized_degrees() - sample.heading.normalized_degrees()).abs();
+                assert!(diff.min(360.0 - diff) <= 360.0 / 256.0);
+            }
+        }
+    }
+
+    #[test]
+    fn reject_unknown_versions() {
+        let path = std::env::temp_dir()
+            .join("replay_version_test.bin")
+            .to_string_lossy()
+            .to_string();
+        let mut replay = ReplayWriter::new(
+            MapName::seattle("montlake"),
+            "untitled edits".to_string(),
+            0,
+            "none".to_string(),
+            Duration::seconds(1.0),
+        )
+        .finish();
+
+        abstio::write_binary(path.clone(), &replay);
+        assert!(Replay::load(path.clone()).is_ok());
+
+        replay.header.version = FORMAT_VERSION + 1;
+        abstio::write_binary(path.clone(), &replay);
+        assert!(Replay::load(path.clone()).is_err());
+
+        abstio::delete_file(path);
+    }
+}
@@ -0,0 +1,251 @@
+//! Record agent positions while the simulation runs, then play them back later without simulating
+//! anything. Scrubbing through a recording is handy for presentations.
+
+use abstutil::prettyprint_usize;
+use geom::{Circle, Distance, Duration, Pt2D, Time};
+use map_gui::load::MapLoader;
+use map_gui::tools::{grey_out_map, PopupMsg};
+use sim::CarStatus;
+use widgetry::{
+    Color, EventCtx, GeomBatch, GfxCtx, HorizontalAlignment, Key, Line, Outcome, Panel, Spinner,
+    State, StyledButtons, TextExt, VerticalAlignment, Widget,
+};
+
+pub use self::format::Replay;
+use self::format::ReplayWriter;
+use self::playback::ReplayPlayer;
+use crate::app::{App, Transition};
+use crate::sandbox::{GameplayMode, SandboxMode};
+
+mod format;
+mod playback;
+
+/// While the simulation runs, samples the position of every moving agent. The recording is
+/// written to a file when stopped.
+pub struct Recorder {
+    writer: ReplayWriter,
+    next_capture: Time,
+    panel: Panel,
+}
+
+impl Recorder {
+    fn new(ctx: &mut EventCtx, app: &App, scenario: String, interval: Duration) -> Recorder {
+        let map = &app.primary.map;
+        let edits = map.get_edits();
+        let mut recorder = Recorder {
+            writer: ReplayWriter::new(
+                map.get_name().clone(),
+                edits.edits_name.clone(),
+                edits.checksum(map),
+                scenario,
+                interval,
+            ),
+            next_capture: app.primary.sim.time(),
+            panel: Panel::empty(ctx),
+        };
+        recorder.recreate_panel(ctx);
+        recorder
+    }
+
+    fn recreate_panel(&mut self, ctx: &mut EventCtx) {
+        self.panel = Panel::new(Widget::row(vec![
+            Widget::draw_batch(
+                ctx,
+                GeomBatch::from(vec![(
+                    Color::RED,
+                    Circle::new(Pt2D::new(0.0, 0.0), Distance::meters(10.0)).to_polygon(),
+                )]),
+            )
+            .centered_vert(),
+            Widget::col(vec![
+                Line("Recording agents").small_heading().draw(ctx),
+                format!(
+                    "{} frames of {} agents, about {} KB",
+                    prettyprint_usize(self.writer.num_frames()),
+                    prettyprint_usize(self.writer.num_agents()),
+                    prettyprint_usize(self.writer.approx_bytes() / 1024)
+                )
+                .draw_text(ctx),
+            ]),
+            ctx.style()
+                .btn_solid_dark_text("Stop and save")
+                .build_def(ctx)
+                .centered_vert(),
+        ]))
+        .aligned(HorizontalAlignment::Left, VerticalAlignment::Center)
+        .build(ctx);
+    }
+
+    /// Returns true when the user has stopped recording.
+    pub fn event(&mut self, ctx: &mut EventCtx, app: &App) -> bool {
+        if let Outcome::Clicked(x) = self.panel.event(ctx) {
+            match x.as_ref() {
+                "Stop and save" => {
+                    return true;
+                }
+                _ => unreachable!(),
+            }
+        }
+
+        // Like time-lapse screenshots, only the current state can be captured. If one step
+        // crosses several intervals, playback interpolates over the gap.
+        let now = app.primary.sim.time();
+        if now >= self.next_capture {
+            while self.next_capture <= now {
+                self.next_capture = self.next_capture + self.writer.interval();
+            }
+            self.capture(app);
+            self.recreate_panel(ctx);
+        }
+        false
+    }
+
+    fn capture(&mut self, app: &App) {
+        let map = &app.primary.map;
+        let sim = &app.primary.sim;
+        let mut agents = Vec::new();
+        for car in sim.get_all_draw_cars(map) {
+            // Parked cars don't move, and there are far too many of them
+            if car.status == CarStatus::Parked {
+                continue;
+            }
+            let id = sim::AgentID::Car(car.id);
+            agents.push((
+                id,
+                sim.agent_to_trip(id),
+                car.body.last_pt(),
+                car.body.last_line().angle(),
+            ));
+        }
+        for ped in sim.get_all_draw_peds(map) {
+            let id = sim::AgentID::Pedestrian(ped.id);
+            agents.push((id, sim.agent_to_trip(id), ped.pos, ped.facing));
+        }
+        self.writer.add_frame(sim.time(), agents);
+    }
+
+    /// Writes everything recorded so far, and returns the path.
+    pub fn save(self, app: &App) -> String {
+        let path = abstio::path_player(format!(
+            "replays/{}_{}.bin",
+            app.primary.map.get_name().as_filename(),
+            chrono::Local::now().format("%Y-%m-%d_%H-%M-%S")
+        ));
+        abstio::write_binary(path.clone(), &self.writer.finish());
+        path
+    }
+
+    pub fn draw(&self, g: &mut GfxCtx) {
+        self.panel.draw(g);
+    }
+}
+
+/// Start a `Recorder`, or pick a previous recording to play back.
+pub struct ReplaySetup {
+    panel: Panel,
+}
+
+impl ReplaySetup {
+    pub fn new(ctx: &mut EventCtx) -> Box<dyn State<App>> {
+        let mut recordings = Vec::new();
+        for path in abstio::list_dir(abstio::path_player("replays")) {
+            if path.ends_with(".bin") {
+                recordings.push(
+                    ctx.style()
+                        .btn_outline_light_text(&abstutil::basename(&path))
+                        .build_widget(ctx, &path),
+                );
+            }
+        }
+        if recordings.is_empty() {
+            recordings.push("No recordings yet".draw_text(ctx));
+        }
+
+        Box::new(ReplaySetup {
+            panel: Panel::new(Widget::col(vec![
+                Widget::row(vec![
+                    Line("Record agents for playback").small_heading().draw(ctx),
+                    ctx.style().btn_close_widget(ctx),
+                ]),
+                Widget::row(vec![
+                    "Sample every agent every".draw_text(ctx).centered_vert(),
+                    Spinner::new(ctx, (1, 300), 10).named("seconds"),
+                    "simulated seconds".draw_text(ctx).centered_vert(),
+                ]),
+                "Longer intervals make smaller files, but movement along curves gets less smooth."
+                    .draw_text(ctx),
+                ctx.style()
+                    .btn_solid_dark_text("Start recording")
+                    .hotkey(Key::Enter)
+                    .build_def(ctx)
+                    .centered_horiz(),
+                Widget::horiz_separator(ctx, 1.0),
+                Line("Play back a recording").small_heading().draw(ctx),
+                Widget::col(recordings),
+            ]))
+            .build(ctx),
+        })
+    }
+}
+
+impl State<App> for ReplaySetup {
+    fn event(&mut self, ctx: &mut EventCtx, app: &mut App) -> Transition {
+        match self.panel.event(ctx) {
+            Outcome::Clicked(x) => match x.as_ref() {
+                "close" => Transition::Pop,
+                "Start recording" => {
+                    let interval = Duration::seconds(self.panel.spinner("seconds") as f64);
+                    Transition::Multi(vec![
+                        Transition::Pop,
+                        Transition::ModifyState(Box::new(move |state, ctx, app| {
+                            let sandbox = state.downcast_mut::<SandboxMode>().unwrap();
+                            let scenario = match sandbox.gameplay_mode {
+                                GameplayMode::PlayScenario(_, ref scenario, _) => scenario.clone(),
+                                _ => "none".to_string(),
+                            };
+                            sandbox.controls.recorder =
+                                Some(Recorder::new(ctx, app, scenario, interval));
+                        })),
+                    ])
+                }
+                path => {
+                    let replay = match Replay::load(path.to_string()) {
+                        Ok(replay) => replay,
+                        Err(err) => {
+                            return Transition::Replace(PopupMsg::new(
+                                ctx,
+                                "Error",
+                                vec![format!("Couldn't load {}: {}", path, err)],
+                            ));
+                        }
+                    };
+                    if replay.is_empty() {
+                        return Transition::Replace(PopupMsg::new(
+                            ctx,
+                            "Error",
+                            vec![format!("{} didn't record anything", path)],
+                        ));
+                    }
+                    let map_name = replay.header.map_name.clone();
+                    Transition::Multi(vec![
+                        Transition::Pop,
+                        Transition::Replace(MapLoader::new(
+                            ctx,
+                            app,
+                            map_name,
+                            Box::new(move |ctx, app| {
+                                Transition::Replace(ReplayPlayer::new(ctx, app, replay))
+                            }),
+                        )),
+                    ])
+                }
+            },
+            _ => Transition::Keep,
+        }
+    }
+
+    fn draw(&self, g: &mut GfxCtx, app: &App) {
+        grey_out_map(g, app);
+        self.panel.draw(g);
+    }
+}
@@ -0,0 +1,353 @@
+use std::collections::HashMap;
+
+use geom::{Circle, Distance, Duration, Line as GeomLine, Pt2D, Time};
+use map_gui::render::unzoomed_agent_radius;
+use map_gui::tools::nice_map_name;
+use sim::{AgentID, AgentType, VehicleType};
+use widgetry::{
+    Choice, Color, Drawable, EventCtx, GeomBatch, GfxCtx, HorizontalAlignment, Key, Line, Outcome,
+    Panel, Slider, State, StyledButtons, TextExt, UpdateType, VerticalAlignment, Widget,
+};
+
+use super::format::{Replay, Sample};
+use crate::app::{App, Transition};
+use crate::sandbox::{GameplayMode, SandboxMode};
+
+const SCRUB_WIDTH: f64 = 400.0;
+
+/// Draws the agents from a `Replay` over the map, with nothing being simulated.
+pub struct ReplayPlayer {
+    replay: Replay,
+    time: Time,
+    paused: bool,
+    panel: Panel,
+    // Where the scrub bar was last put, to notice when the user drags it
+    scrub_percent: f64,
+
+    // The decoded frames on either side of the current time
+    before: (usize, Vec<Sample>),
+    after: (usize, HashMap<usize, Sample>),
+    // Interpolated between the two frames
+    current: Vec<Sample>,
+    draw_agents: Drawable,
+
+    hovering: Option<usize>,
+    selected: Option<(usize, Panel)>,
+}
+
+impl ReplayPlayer {
+    pub fn new(ctx: &mut EventCtx, app: &mut App, replay: Replay) -> Box<dyn State<App>> {
+        // Nothing live should be drawn underneath
+        app.primary.clear_sim();
+        app.primary.current_selection = None;
+
+        let header = &replay.header;
+        let map = &app.primary.map;
+        let edits_warning = if header.edits_checksum != map.get_edits().checksum(map) {
+            Line(format!(
+                "Recorded with edits \"{}\", which differ from the current ones",
+                header.edits_name
+            ))
+            .fg(Color::RED)
+            .draw(ctx)
+        } else {
+            Widget::nothing()
+        };
+
+        let time = replay.start_time();
+        let mut player = ReplayPlayer {
+            time,
+            paused: true,
+            panel: Panel::new(Widget::col(vec![
+                Widget::row(vec![
+                    Line("Recording").small_heading().draw(ctx),
+                    Widget::vert_separator(ctx, 50.0),
+                    format!(
+                        "{}, scenario {}",
+                        nice_map_name(&header.map_name),
+                        header.scenario
+                    )
+                    .draw_text(ctx)
+                    .centered_vert(),
+                    ctx.style()
+                        .btn_outline_light_text("Back to sandbox")
+                        .build_def(ctx)
+                        .align_right(),
+                ]),
+                edits_warning,
+                Widget::row(vec![
+                    play_button(ctx, true),
+                    Slider::horizontal(ctx, SCRUB_WIDTH, 25.0, 0.0)
+                        .named("scrub")
+                        .centered_vert(),
+                    time.to_string()
+                        .draw_text(ctx)
+                        .named("time")
+                        .centered_vert(),
+                    Widget::dropdown(
+                        ctx,
+                        "speed",
+                        60.0,
+                        vec![
+                            Choice::new("1x", 1.0),
+                            Choice::new("10x", 10.0),
+                            Choice::new("60x", 60.0),
+                            Choice::new("600x", 600.0),
+                        ],
+                    )
+                    .centered_vert(),
+                ]),
+            ]))
+            .aligned(HorizontalAlignment::Center, VerticalAlignment::Bottom)
+            .build(ctx),
+            scrub_percent: 0.0,
+            before: (0, Vec::new()),
+            after: (0, HashMap::new()),
+            current: Vec::new(),
+            draw_agents: Drawable::empty(ctx),
+            hovering: None,
+            selected: None,
+            replay,
+        };
+        player.before = (0, player.replay.decode(0));
+        player.after = (0, by_agent(player.before.1.clone()));
+        player.set_time(ctx, app, time);
+        Box::new(player)
+    }
+
+    fn set_time(&mut self, ctx: &mut EventCtx, app: &App, time: Time) {
+        self.time = time
+            .max(self.replay.start_time())
+            .min(self.replay.end_time());
+
+        let (idx1, idx2, pct) = self.replay.bracket(self.time);
+        if idx1 != self.before.0 {
+            // When playing forwards, the old "after" frame is usually the new "before"
+            self.before = if idx1 == self.after.0 {
+                (idx1, self.after.1.values().cloned().collect())
+            } else {
+                (idx1, self.replay.decode(idx1))
+            };
+        }
+        if idx2 != self.after.0 {
+            self.after = (idx2, by_agent(self.replay.decode(idx2)));
+        }
+
+        // Agents missing from the next frame stay where they were last seen; agents appearing in
+        // the next frame aren't drawn until they're recorded.
+        self.current = self
+            .before
+            .1
+            .iter()
+            .map(|s| match self.after.1.get(&s.agent) {
+                Some(next) => Sample {
+                    agent: s.agent,
+                    pos: Pt2D::new(
+                        s.pos.x() + pct * (next.pos.x() - s.pos.x()),
+                        s.pos.y() + pct * (next.pos.y() - s.pos.y()),
+                    ),
+                    heading: s.heading.rotate_degs(
+                        pct * next.heading.simple_shortest_rotation_towards(s.heading),
+                    ),
+                },
+                None => *s,
+            })
+            .collect();
+
+        let mut batch = GeomBatch::new();
+        for s in &self.current {
+            let mode = self.replay.agents[s.agent].mode;
+            let radius = radius(mode);
+            batch.push(color(app, mode), Circle::new(s.pos, radius).to_polygon());
+            batch.push(
+                Color::BLACK,
+                GeomLine::must_new(s.pos, s.pos.project_away(radius, s.heading))
+                    .make_polygons(0.3 * radius),
+            );
+        }
+        self.draw_agents = ctx.upload(batch);
+
+        self.scrub_percent = (self.time - self.replay.start_time())
+            / (self.replay.end_time() - self.replay.start_time()).max(Duration::seconds(1.0));
+        self.panel
+            .slider_mut("scrub")
+            .set_percent(ctx, self.scrub_percent.min(1.0));
+        self.panel.replace(
+            ctx,
+            "time",
+            self.time
+                .to_string()
+                .draw_text(ctx)
+                .named("time")
+                .centered_vert(),
+        );
+        self.recalc_hovering(ctx);
+    }
+
+    fn recalc_hovering(&mut self, ctx: &EventCtx) {
+        self.hovering = None;
+        if let Some(cursor) = ctx.canvas.get_cursor_in_map_space() {
+            for s in &self.current {
+                let mode = self.replay.agents[s.agent].mode;
+                if Circle::new(s.pos, radius(mode)).contains_pt(cursor) {
+                    self.hovering = Some(s.agent);
+                }
+            }
+        }
+    }
+
+    fn info_card(&self, ctx: &mut EventCtx, agent: usize) -> Panel {
+        let recorded = &self.replay.agents[agent];
+        let name = match recorded.id {
+            AgentID::Car(c) => c.to_string(),
+            AgentID::Pedestrian(p) => p.to_string(),
+            AgentID::BusPassenger(_, _) => recorded.id.to_string(),
+        };
+        Panel::new(Widget::col(vec![
+            Widget::row(vec![
+                Line(name).small_heading().draw(ctx),
+                ctx.style().btn_close_widget(ctx),
+            ]),
+            format!("Mode: {}", recorded.mode.noun()).draw_text(ctx),
+            match recorded.trip {
+                Some(trip) => format!("Recorded during {}", trip),
+                None => "Not on a trip".to_string(),
+            }
+            .draw_text(ctx),
+        ]))
+        .aligned(HorizontalAlignment::Right, VerticalAlignment::Top)
+        .build(ctx)
+    }
+}
+
+impl State<App> for ReplayPlayer {
+    fn event(&mut self, ctx: &mut EventCtx, app: &mut App) -> Transition {
+        ctx.canvas_movement();
+
+        if let Some((_, ref mut card)) = self.selected {
+            if let Outcome::Clicked(x) = card.event(ctx) {
+                match x.as_ref() {
+                    "close" => {
+                        self.selected = None;
+                    }
+                    _ => unreachable!(),
+                }
+            }
+        }
+
+        match self.panel.event(ctx) {
+            Outcome::Clicked(x) => match x.as_ref() {
+                "Back to sandbox" => {
+                    return Transition::Replace(SandboxMode::simple_new(
+                        app,
+                        GameplayMode::Freeform(app.primary.map.get_name().clone()),
+                    ));
+                }
+                "play" | "pause" => {
+                    self.paused = !self.paused;
+                    // Start over after reaching the end
+                    if !self.paused && self.time == self.replay.end_time() {
+                        self.set_time(ctx, app, self.replay.start_time());
+                    }
+                    self.panel
+                        .replace(ctx, "play controls", play_button(ctx, self.paused));
+                }
+                _ => unreachable!(),
+            },
+            _ => {}
+        }
+
+        let scrubbed = self.panel.slider("scrub").get_percent();
+        if scrubbed != self.scrub_percent {
+            let time = self.replay.start_time()
+                + scrubbed * (self.replay.end_time() - self.replay.start_time());
+            self.set_time(ctx, app, time);
+        }
+
+        if !self.paused {
+            if let Some(real_dt) = ctx.input.nonblocking_is_update_event() {
+                ctx.input.use_update_event();
+                let speed: f64 = self.panel.dropdown_value("speed");
+                self.set_time(ctx, app, self.time + speed * real_dt);
+                if self.time == self.replay.end_time() {
+                    self.paused = true;
+                    self.panel
+                        .replace(ctx, "play controls", play_button(ctx, true));
+                }
+            }
+            ctx.request_update(UpdateType::Game);
+        }
+
+        if ctx.redo_mouseover() {
+            self.recalc_hovering(ctx);
+        }
+        if let Some(agent) = self.hovering {
+            if ctx.normal_left_click() {
+                self.selected = Some((agent, self.info_card(ctx, agent)));
+            }
+        }
+
+        Transition::Keep
+    }
+
+    fn draw(&self, g: &mut GfxCtx, app: &App) {
+        g.redraw(&self.draw_agents);
+        for agent in self
+            .hovering
+            .iter()
+            .chain(self.selected.as_ref().map(|(agent, _)| agent))
+        {
+            if let Some(s) = self.current.iter().find(|s| s.agent == *agent) {
+                let radius = radius(self.replay.agents[*agent].mode);
+                g.draw_polygon(
+                    app.cs.selected,
+                    Circle::new(s.pos, radius * 1.5).to_polygon(),
+                );
+            }
+        }
+
+        self.panel.draw(g);
+        if let Some((_, ref card)) = self.selected {
+            card.draw(g);
+        }
+    }
+}
+
+fn play_button(ctx: &mut EventCtx, paused: bool) -> Widget {
+    let button = ctx
+        .style()
+        .btn_plain_light_icon("system/assets/speed/triangle.svg")
+        .hotkey(Key::Space);
+    Widget::custom_row(vec![if paused {
+        button.build_widget(ctx, "play")
+    } else {
+        button
+            .image_path("system/assets/speed/pause.svg")
+            .build_widget(ctx, "pause")
+    }])
+    .named("play controls")
+    .centered_vert()
+}
+
+fn by_agent(samples: Vec<Sample>) -> HashMap<usize, Sample> {
+    samples.into_iter().map(|s| (s.agent, s)).collect()
+}
+
+fn radius(mode: AgentType) -> Distance {
+    match mode {
+        AgentType::Pedestrian | AgentType::TransitRider => unzoomed_agent_radius(None),
+        AgentType::Car => unzoomed_agent_radius(Some(VehicleType::Car)),
+        AgentType::Bike => unzoomed_agent_radius(Some(VehicleType::Bike)),
+        AgentType::Bus => unzoomed_agent_radius(Some(VehicleType::Bus)),
+        AgentType::Train => unzoomed_agent_radius(Some(VehicleType::Train)),
+    }
+}
+
+fn color(app: &App, mode: AgentType) -> Color {
+    match mode {
+        AgentType::Car => app.cs.unzoomed_car,
+        AgentType::Bike => app.cs.unzoomed_bike,
+        AgentType::Bus | AgentType::Train => app.cs.unzoomed_bus,
+        AgentType::Pedestrian | AgentType::TransitRider => app.cs.unzoomed_pedestrian,
+    }
+}
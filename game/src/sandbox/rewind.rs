@@ -0,0 +1,163 @@
+use std::collections::VecDeque;
+
+use abstutil::{prettyprint_usize, serialized_size_bytes};
+use geom::Time;
+use map_gui::tools::PopupMsg;
+use map_model::MapEdits;
+use sim::Sim;
+use widgetry::EventCtx;
+
+use crate::app::{App, Transition};
+
+/// Measuring a snapshot walks through the entire simulation, so only do it this often, and assume
+/// the size changes slowly in between.
+const REMEASURE_EVERY: usize = 10;
+
+/// Periodically copies the entire simulation into memory, so the player can go back a bit after
+/// watching something interesting happen, instead of restarting from midnight. Controlled by
+/// `rewind_interval` and `rewind_memory_limit_mb` in the options.
+pub struct Snapshots {
+    // Oldest first
+    states: VecDeque<Snapshot>,
+    total_bytes: usize,
+    next_capture: Time,
+    // The size of the last snapshot measured, and how many captures have reused it since
+    last_measured: Option<(usize, usize)>,
+}
+
+struct Snapshot {
+    sim: Sim,
+    // The map edits in effect when the snapshot was taken
+    edits: MapEdits,
+    bytes: usize,
+}
+
+impl Snapshots {
+    pub fn new(app: &App) -> Snapshots {
+        Snapshots {
+            states: VecDeque::new(),
+            total_bytes: 0,
+            next_capture: app.primary.sim.time(),
+            last_measured: None,
+        }
+    }
+
+    /// Call this after the simulation might've advanced. Returns true if the available snapshots
+    /// changed.
+    pub fn event(&mut self, app: &App) -> bool {
+        let interval = match app.opts.rewind_interval {
+            Some(x) => x,
+            None => {
+                let changed = !self.states.is_empty();
+                self.states.clear();
+                self.total_bytes = 0;
+                return changed;
+            }
+        };
+        let now = app.primary.sim.time();
+        if now < self.next_capture {
+            return false;
+        }
+        self.next_capture = now + interval;
+
+        let bytes = self.estimate_bytes(&app.primary.sim);
+        let limit = app.opts.rewind_memory_limit_mb * 1024 * 1024;
+        // Making room for a snapshot bigger than the whole budget would evict everything, and then
+        // the snapshot itself
+        if bytes > limit {
+            return false;
+        }
+        while self.total_bytes + bytes > limit {
+            let dropped = self.states.pop_front().unwrap();
+            self.total_bytes -= dropped.bytes;
+        }
+        self.states.push_back(Snapshot {
+            sim: app.primary.sim.clone(),
+            edits: app.primary.map.get_edits().clone(),
+            bytes,
+        });
+        self.total_bytes += bytes;
+        true
+    }
+
+    fn estimate_bytes(&mut self, sim: &Sim) -> usize {
+        match self.last_measured {
+            Some((bytes, reused)) if reused < REMEASURE_EVERY => {
+                self.last_measured = Some((bytes, reused + 1));
+                bytes
+            }
+            _ => {
+                let bytes = serialized_size_bytes(sim);
+                self.last_measured = Some((bytes, 0));
+                bytes
+            }
+        }
+    }
+
+    /// The time that rewinding would restore, if there's any snapshot in the past.
+    pub fn rewind_target(&self, app: &App) -> Option<Time> {
+        self.pick(app).map(|idx| self.states[idx].sim.time())
+    }
+
+    // Prefer going back at least one full interval; just rewinding a few seconds to a snapshot
+    // that was taken recently isn't useful. If there's nothing that old, use the oldest one.
+    fn pick(&self, app: &App) -> Option<usize> {
+        let now = app.primary.sim.time();
+        let interval = app.opts.rewind_interval?;
+        let mut result = None;
+        for (idx, snapshot) in self.states.iter().enumerate() {
+            let time = snapshot.sim.time();
+            if time >= now {
+                break;
+            }
+            if result.is_none() || time + interval <= now {
+                result = Some(idx);
+            }
+        }
+        result
+    }
+
+    /// Restores the simulation to the most recent snapshot that's sufficiently in the past.
+    /// Analytics are part of the simulation, so they're restored too. Later snapshots are
+    /// discarded.
+    pub fn rewind(&mut self, ctx: &mut EventCtx, app: &mut App) -> Option<Transition> {
+        let idx = self.pick(app)?;
+        self.states.truncate(idx + 1);
+        self.total_bytes = self.states.iter().map(|s| s.bytes).sum();
+
+        let snapshot = &self.states[idx];
+        app.primary.sim = snapshot.sim.clone();
+        self.next_capture = app.primary.sim.time() + app.opts.rewind_interval.unwrap();
+        // The simulation just shrank
+        self.last_measured = None;
+
+        // If the map was edited since the snapshot, keep the edits and apply them to the restored
+        // simulation, the same way as editing in the middle of a simulation does.
+        let mut transition = None;
+        if &snapshot.edits != app.primary.map.get_edits() {
+            app.primary
+                .sim
+                .handle_live_edited_traffic_signals(&app.primary.map);
+            let (trips, parked_cars) = app.primary.sim.handle_live_edits(&app.primary.map);
+            transition = Some(Transition::Push(PopupMsg::new(
+                ctx,
+                "Map edits kept",
+                vec![
+                    format!(
+                        "The map was edited after {}. Your edits are still in place, but \
+                         interrupted {} trips and displaced {} parked cars",
+                        app.primary.sim.time().ampm_tostring(),
+                        prettyprint_usize(trips),
+                        prettyprint_usize(parked_cars)
+                    ),
+                    "Simulation results won't be finalized unless you restart from midnight with \
+                     your changes"
+                        .to_string(),
+                ],
+            )));
+        }
+
+        app.recalculate_current_selection(ctx);
+        transition.or(Some(Transition::KeepWithMouseover))
+    }
+}
@@ -1,3 +1,5 @@
+use std::collections::VecDeque;
+
 use geom::{Duration, Polygon, Time};
 use map_gui::tools::PopupMsg;
 use map_gui::ID;
@@ -8,8 +10,9 @@ use widgetry::{
     Widget,
 };
 
-use crate::app::{App, Transition};
+use crate::app::{App, Transition, UiEvent};
 use crate::common::Warping;
+use crate::sandbox::rewind::Snapshots;
 use crate::sandbox::time_warp::JumpToTime;
 use crate::sandbox::{GameplayMode, SandboxMode, TimeWarpScreen};
 
@@ -18,6 +21,8 @@ pub struct SpeedControls {
 
     paused: bool,
     setting: SpeedSetting,
+    snapshots: Snapshots,
+    meter: SpeedMeter,
 }
 
 #[derive(Clone, Copy, PartialEq, PartialOrd)]
@@ -32,12 +37,87 @@ enum SpeedSetting {
     Fastest,
 }
 
+impl SpeedSetting {
+    fn multiplier(self) -> f64 {
+        match self {
+            SpeedSetting::Realtime => 1.0,
+            SpeedSetting::Fast => 5.0,
+            SpeedSetting::Faster => 30.0,
+            SpeedSetting::Fastest => 3600.0,
+        }
+    }
+}
+
+/// Measures how quickly the simulation is actually advancing, since large maps can't always keep
+/// up with the requested speed. This only sums durations that the update loop already has, so it
+/// doesn't cost anything noticeable.
+struct SpeedMeter {
+    // Samples are only comparable at one setting
+    setting: Option<SpeedSetting>,
+    // (real time elapsed, sim time advanced) for each update over the last few seconds
+    samples: VecDeque<(Duration, Duration)>,
+    total_real: Duration,
+    total_sim: Duration,
+    // Real time since the readout was last refreshed
+    since_refresh: Duration,
+}
+
+impl SpeedMeter {
+    fn new() -> SpeedMeter {
+        SpeedMeter {
+            setting: None,
+            samples: VecDeque::new(),
+            total_real: Duration::ZERO,
+            total_sim: Duration::ZERO,
+            since_refresh: Duration::ZERO,
+        }
+    }
+
+    fn reset(&mut self) {
+        *self = SpeedMeter::new();
+    }
+
+    // Returns true when the readout should be refreshed.
+    fn add_sample(&mut self, setting: SpeedSetting, real_dt: Duration, sim_dt: Duration) -> bool {
+        if self.setting != Some(setting) {
+            self.reset();
+            self.setting = Some(setting);
+        }
+        self.samples.push_back((real_dt, sim_dt));
+        self.total_real += real_dt;
+        self.total_sim += sim_dt;
+        while self.total_real > Duration::seconds(2.0) && self.samples.len() > 1 {
+            let (real, sim) = self.samples.pop_front().unwrap();
+            self.total_real -= real;
+            self.total_sim -= sim;
+        }
+
+        self.since_refresh += real_dt;
+        if self.since_refresh >= Duration::seconds(0.25) {
+            self.since_refresh = Duration::ZERO;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn ratio(&self) -> Option<f64> {
+        if self.setting.is_none() || self.total_real == Duration::ZERO {
+            None
+        } else {
+            Some(self.total_sim / self.total_real)
+        }
+    }
+}
+
 impl SpeedControls {
     pub fn new(ctx: &mut EventCtx, app: &App) -> SpeedControls {
         let mut speed = SpeedControls {
             panel: Panel::empty(ctx),
             paused: false,
             setting: SpeedSetting::Realtime,
+            snapshots: Snapshots::new(app),
+            meter: SpeedMeter::new(),
         };
         speed.recreate_panel(ctx, app);
         speed
@@ -106,8 +186,9 @@ impl SpeedControls {
                 })
                 .collect(),
             )
-            .margin_right(16),
+            .margin_right(8),
         );
+        row.push(self.achieved_speed(ctx));
 
         row.push(
             PersistentSplit::widget(
@@ -132,6 +213,21 @@ impl SpeedControls {
                 .build_widget(ctx, "jump to specific time"),
         );
 
+        row.push({
+            let target = self.snapshots.rewind_target(app);
+            ctx.style()
+                .btn_plain_light_icon("system/assets/tools/undo.svg")
+                .tooltip(Text::from(Line(match target {
+                    Some(t) => format!("Rewind to {}", t.ampm_tostring()),
+                    None if app.opts.rewind_interval.is_none() => {
+                        "Turn on snapshots in the settings to rewind".to_string()
+                    }
+                    None => "No earlier snapshots to rewind to yet".to_string(),
+                })))
+                .disabled(target.is_none())
+                .build_widget(ctx, "rewind")
+        });
+
         row.push(
             ctx.style()
                 .btn_plain_light_icon("system/assets/speed/reset.svg")
@@ -147,6 +243,40 @@ impl SpeedControls {
             .build(ctx);
     }
 
+    fn achieved_speed(&self, ctx: &mut EventCtx) -> Widget {
+        let mut txt = Text::new();
+        if let Some(ratio) = self
+            .meter
+            .ratio()
+            .filter(|_| self.meter.setting == Some(self.setting))
+        {
+            let target = self.setting.multiplier();
+            let line = Line(if ratio < 10.0 {
+                format!("{:.1}x", ratio)
+            } else {
+                format!("{}x", ratio.round() as usize)
+            })
+            .small();
+            txt.add(if ratio < 0.5 * target {
+                line.fg(Color::RED)
+            } else if ratio < 0.75 * target {
+                line.fg(Color::ORANGE)
+            } else {
+                line
+            });
+        }
+        txt.draw(ctx)
+            .centered_vert()
+            .margin_right(16)
+            .named("achieved speed")
+    }
+
+    /// How many simulated seconds pass per real second, measured over the last few seconds.
+    /// `None` if the simulation hasn't run recently.
+    pub fn achieved_speed_ratio(&self) -> Option<f64> {
+        self.meter.ratio()
+    }
+
     pub fn event(
         &mut self,
         ctx: &mut EventCtx,
@@ -197,6 +327,11 @@ impl SpeedControls {
                         )));
                     }
                 }
+                "rewind" => {
+                    let t = self.snapshots.rewind(ctx, app);
+                    self.recreate_panel(ctx, app);
+                    return t;
+                }
                 "jump to specific time" => {
                     return Some(Transition::Push(JumpToTime::new(
                         ctx,
@@ -270,13 +405,8 @@ impl SpeedControls {
         if !self.paused {
             if let Some(real_dt) = ctx.input.nonblocking_is_update_event() {
                 ctx.input.use_update_event();
-                let multiplier = match self.setting {
-                    SpeedSetting::Realtime => 1.0,
-                    SpeedSetting::Fast => 5.0,
-                    SpeedSetting::Faster => 30.0,
-                    SpeedSetting::Fastest => 3600.0,
-                };
-                let dt = multiplier * real_dt;
+                let dt = self.setting.multiplier() * real_dt;
+                let before = app.primary.sim.time();
                 // TODO This should match the update frequency in widgetry. Plumb along the deadline
                 // or frequency to here.
                 app.primary.sim.time_limited_step(
@@ -286,8 +416,28 @@ impl SpeedControls {
                     &mut app.primary.sim_cb,
                 );
                 app.recalculate_current_selection(ctx);
+
+                if self
+                    .meter
+                    .add_sample(self.setting, real_dt, app.primary.sim.time() - before)
+                {
+                    if let Some(ratio) = self.achieved_speed_ratio() {
+                        if app.opts.dev && ratio < 0.75 * self.setting.multiplier() {
+                            info!(
+                                "Simulation falling behind: {:.1}x achieved, {}x requested",
+                                ratio,
+                                self.setting.multiplier()
+                            );
+                        }
+                    }
+                    let readout = self.achieved_speed(ctx);
+                    self.panel.replace(ctx, "achieved speed", readout);
+                }
             }
         }
+        if self.snapshots.event(app) {
+            self.recreate_panel(ctx, app);
+        }
 
         // TODO Need to do this anywhere that steps the sim, like TimeWarpScreen.
         let alerts = app.primary.sim.clear_alerts();
@@ -339,6 +489,7 @@ impl SpeedControls {
     pub fn pause(&mut self, ctx: &mut EventCtx, app: &App) {
         if !self.paused {
             self.paused = true;
+            self.meter.reset();
             self.recreate_panel(ctx, app);
         }
     }
@@ -351,6 +502,12 @@ impl SpeedControls {
         }
     }
 
+    /// Resumes in real-time, so the player can watch whatever agent they picked.
+    pub fn follow_agent(&mut self, ctx: &mut EventCtx, app: &mut App) {
+        self.resume_realtime(ctx, app);
+        app.session.record_ui_event(UiEvent::FollowedAgent);
+    }
+
     pub fn is_paused(&self) -> bool {
         self.paused
     }
@@ -0,0 +1,606 @@
+//! Runs the same scenario on two versions of the map at once, each with a different set of edits,
+//! and shows them side by side. Both sides share one camera and advance in lockstep. The sandbox's
+//! own map is reused for the left side, so only one more copy has to fit in memory.
+
+use anyhow::Result;
+
+use abstutil::Timer;
+use geom::{Distance, Duration, Line as GeomLine, Pt2D, Time};
+use map_gui::render::DrawOptions;
+use map_gui::tools::PopupMsg;
+use map_gui::ID;
+use map_model::{Map, MapEdits};
+use sim::{Scenario, Sim, TripID};
+use widgetry::{
+    Choice, Color, DrawBaselayer, EventCtx, GfxCtx, HorizontalAlignment, Key, Line, Outcome, Panel,
+    ScreenPt, ScreenRectangle, Spinner, State, StyledButtons, Text, TextExt, UpdateType,
+    VerticalAlignment, Widget,
+};
+
+use crate::app::{App, Flags, PerMap, ShowEverything, Transition};
+use crate::edit::apply_map_edits;
+use crate::info::{ContextualActions, InfoPanel, Tab};
+use crate::sandbox::{CustomTrips, GameplayMode, MapExperiments, QuickEdit};
+
+// Above this, loading another copy of the map is likely to run out of memory
+const LARGE_MAP_LANES: usize = 20_000;
+
+/// Choose which edits go on each side.
+pub struct PickEditsToCompare {
+    panel: Panel,
+    mode: GameplayMode,
+}
+
+impl PickEditsToCompare {
+    pub fn new(ctx: &mut EventCtx, app: &App, mode: GameplayMode) -> Box<dyn State<App>> {
+        let map = &app.primary.map;
+        let current = Some(map.get_edits().edits_name.clone());
+        let right = if edits_choices(map).iter().any(|c| c.data == current) {
+            current
+        } else {
+            None
+        };
+
+        let mut col = vec![
+            Widget::row(vec![
+                Line("Compare two proposals").small_heading().draw(ctx),
+                ctx.style().btn_close_widget(ctx),
+            ]),
+            "The current scenario runs on both at the same time.".draw_text(ctx),
+            Widget::row(vec![
+                "Left:".draw_text(ctx).centered_vert(),
+                Widget::dropdown(ctx, "left", None, edits_choices(map)),
+            ]),
+            Widget::row(vec![
+                "Right:".draw_text(ctx).centered_vert(),
+                Widget::dropdown(ctx, "right", right, edits_choices(map)),
+            ]),
+        ];
+        if map.all_lanes().len() > LARGE_MAP_LANES {
+            col.push(
+                Text::from(
+                    Line(
+                        "This is a very large map. Another copy of it and its simulation has to \
+                         fit in memory, which might not work.",
+                    )
+                    .fg(Color::RED),
+                )
+                .wrap_to_pct(ctx, 30)
+                .draw(ctx),
+            );
+        }
+        col.push(
+            ctx.style()
+                .btn_solid_dark_text("Compare")
+                .hotkey(Key::Enter)
+                .build_def(ctx),
+        );
+
+        Box::new(PickEditsToCompare {
+            panel: Panel::new(Widget::col(col)).build(ctx),
+            mode,
+        })
+    }
+}
+
+impl State<App> for PickEditsToCompare {
+    fn event(&mut self, ctx: &mut EventCtx, app: &mut App) -> Transition {
+        if let Outcome::Clicked(x) = self.panel.event(ctx) {
+            match x.as_ref() {
+                "close" => {
+                    return Transition::Pop;
+                }
+                "Compare" => {
+                    let scenario = match app.primary.scenario.clone() {
+                        Some(s) => s,
+                        None => {
+                            return Transition::Push(PopupMsg::new(
+                                ctx,
+                                "Can't compare",
+                                vec!["Only scenarios loaded from a file can be compared"],
+                            ));
+                        }
+                    };
+                    let left: Option<String> = self.panel.dropdown_value("left");
+                    let right: Option<String> = self.panel.dropdown_value("right");
+                    let mode = &self.mode;
+                    let result = ctx.loading_screen("load both sides", |ctx, timer| {
+                        // Load everything that can fail before touching the sandbox's map
+                        let right_side =
+                            load_side(ctx, app, right.as_ref(), &scenario, mode, timer)?;
+                        let left_edits = load_edits(&app.primary.map, left.as_ref(), timer)?;
+                        let sandbox =
+                            Sandbox::switch_to_side(ctx, app, left_edits, &scenario, mode, timer);
+                        Ok((sandbox, right_side))
+                    });
+                    return match result {
+                        Ok((sandbox, right_side)) => Transition::Replace(SplitScreen::new(
+                            ctx,
+                            app,
+                            [left, right],
+                            sandbox,
+                            right_side,
+                            self.mode.clone(),
+                        )),
+                        Err(err) => Transition::Push(PopupMsg::new(
+                            ctx,
+                            "Can't compare",
+                            vec![err.to_string()],
+                        )),
+                    };
+                }
+                _ => unreachable!(),
+            }
+        }
+        Transition::Keep
+    }
+
+    fn draw(&self, g: &mut GfxCtx, app: &App) {
+        map_gui::tools::grey_out_map(g, app);
+        self.panel.draw(g);
+    }
+}
+
+// None is the original map
+fn edits_choices(map: &Map) -> Vec<Choice<Option<String>>> {
+    let mut choices = vec![Choice::new("original map", None)];
+    for name in abstio::list_all_objects(abstio::path_all_edits(map.get_name())) {
+        choices.push(Choice::new(name.clone(), Some(name)));
+    }
+    choices
+}
+
+// Loads a fresh copy of the current map, with some edits, and starts the scenario on it.
+fn load_side(
+    ctx: &mut EventCtx,
+    app: &App,
+    edits_name: Option<&String>,
+    scenario: &Scenario,
+    mode: &GameplayMode,
+    timer: &mut Timer,
+) -> Result<PerMap> {
+    let mut map = Map::new(app.primary.map.get_name().path(), timer);
+    let edits = load_edits(&map, edits_name, timer)?;
+    map.must_apply_edits(edits);
+    map.recalculate_pathfinding_after_edits(timer);
+
+    let flags = app.primary.current_flags.clone();
+    let (sim, has_synthetic_trips) = start_scenario(&map, &flags, scenario, mode, timer);
+    let mut per_map = PerMap::map_loaded(map, sim, flags, &app.opts, &app.cs, ctx, timer);
+    per_map.has_synthetic_trips = has_synthetic_trips;
+    Ok(per_map)
+}
+
+// None is the original map
+fn load_edits(map: &Map, edits_name: Option<&String>, timer: &mut Timer) -> Result<MapEdits> {
+    match edits_name {
+        Some(name) => MapEdits::load(map, abstio::path_edits(map.get_name(), name), timer),
+        None => Ok(map.new_edits()),
+    }
+}
+
+// Also returns true if building overrides added any synthetic people
+fn start_scenario(
+    map: &Map,
+    flags: &Flags,
+    scenario: &Scenario,
+    mode: &GameplayMode,
+    timer: &mut Timer,
+) -> (Sim, bool) {
+    let mut scenario = scenario.clone();
+    let mut num_synthetic = 0;
+    if let GameplayMode::PlayScenario(_, _, ref modifiers) = mode {
+        for m in modifiers {
+            scenario = m.apply(map, scenario);
+        }
+        num_synthetic = MapExperiments::load(map.get_name()).apply(map, &mut scenario);
+    }
+    let mut sim = Sim::new(map, flags.sim_flags.opts.clone());
+    scenario.instantiate(&mut sim, map, &mut flags.sim_flags.make_rng(), timer);
+    sim.tiny_step(map, &mut None);
+    (sim, num_synthetic > 0)
+}
+
+/// What the sandbox had on its map before it was reused for the left side. The sandbox's simulation
+/// waits in `suspended_sim`.
+struct Sandbox {
+    edits: MapEdits,
+    has_synthetic_trips: bool,
+    custom_trips: Vec<(CustomTrips, Vec<TripID>)>,
+    quick_edit: Option<QuickEdit>,
+}
+
+impl Sandbox {
+    fn switch_to_side(
+        ctx: &mut EventCtx,
+        app: &mut App,
+        edits: MapEdits,
+        scenario: &Scenario,
+        mode: &GameplayMode,
+        timer: &mut Timer,
+    ) -> Sandbox {
+        let sandbox = Sandbox {
+            edits: app.primary.map.get_edits().clone(),
+            has_synthetic_trips: app.primary.has_synthetic_trips,
+            custom_trips: std::mem::take(&mut app.primary.custom_trips),
+            quick_edit: app.primary.quick_edit.take(),
+        };
+
+        apply_map_edits(ctx, app, edits);
+        app.primary.map.recalculate_pathfinding_after_edits(timer);
+        let (sim, has_synthetic_trips) = start_scenario(
+            &app.primary.map,
+            &app.primary.current_flags,
+            scenario,
+            mode,
+            timer,
+        );
+        assert!(app.primary.suspended_sim.is_none());
+        app.primary.suspended_sim = Some(std::mem::replace(&mut app.primary.sim, sim));
+        app.primary.has_synthetic_trips = has_synthetic_trips;
+        sandbox
+    }
+
+    // app.primary must be the left side
+    fn restore(self, ctx: &mut EventCtx, app: &mut App) {
+        app.primary.current_selection = None;
+        apply_map_edits(ctx, app, self.edits);
+        ctx.loading_screen("restore the sandbox", |_, mut timer| {
+            app.primary
+                .map
+                .recalculate_pathfinding_after_edits(&mut timer);
+        });
+        app.primary.sim = app.primary.suspended_sim.take().unwrap();
+        app.primary.has_synthetic_trips = self.has_synthetic_trips;
+        app.primary.custom_trips = self.custom_trips;
+        app.primary.quick_edit = self.quick_edit;
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Side {
+    Left,
+    Right,
+}
+
+impl Side {
+    fn other(self) -> Side {
+        match self {
+            Side::Left => Side::Right,
+            Side::Right => Side::Left,
+        }
+    }
+
+    // How far this side's half of the screen is shifted, so that the center of the camera is in
+    // the middle of the half.
+    fn shift(self, window_width: f64) -> f64 {
+        match self {
+            Side::Left => -window_width / 4.0,
+            Side::Right => window_width / 4.0,
+        }
+    }
+
+    fn viewport(self, window_width: f64, window_height: f64) -> ScreenRectangle {
+        let x1 = match self {
+            Side::Left => 0.0,
+            Side::Right => window_width / 2.0,
+        };
+        ScreenRectangle {
+            x1,
+            y1: 0.0,
+            x2: x1 + window_width / 2.0,
+            y2: window_height,
+        }
+    }
+}
+
+/// Two simulations running side by side. Whichever side was last clicked lives in app.primary, so
+/// info panels and everything else work on it as usual; the other side is in app.secondary.
+pub struct SplitScreen {
+    hud: Panel,
+    // The edits name on each side, None for the original map
+    names: [Option<String>; 2],
+    active: Side,
+    paused: bool,
+    // When the HUD's numbers were calculated
+    hud_time: Time,
+    info_panel: Option<InfoPanel>,
+    // Restored onto the left side afterwards
+    sandbox: Option<Sandbox>,
+    mode: GameplayMode,
+}
+
+impl SplitScreen {
+    fn new(
+        ctx: &mut EventCtx,
+        app: &mut App,
+        names: [Option<String>; 2],
+        sandbox: Sandbox,
+        right: PerMap,
+        mode: GameplayMode,
+    ) -> Box<dyn State<App>> {
+        app.secondary = Some(right);
+        let mut split = SplitScreen {
+            hud: Panel::empty(ctx),
+            names,
+            active: Side::Left,
+            paused: true,
+            hud_time: Time::START_OF_DAY,
+            info_panel: None,
+            sandbox: Some(sandbox),
+            mode,
+        };
+        split.recreate_hud(ctx, app, 10);
+        Box::new(split)
+    }
+
+    fn recreate_hud(&mut self, ctx: &mut EventCtx, app: &App, speed: isize) {
+        let (left, right) = match self.active {
+            Side::Left => (&app.primary, app.secondary.as_ref().unwrap()),
+            Side::Right => (app.secondary.as_ref().unwrap(), &app.primary),
+        };
+        let mut sides = Vec::new();
+        for (name, per_map) in self.names.iter().zip(vec![left, right]) {
+            let (finished, avg_delay) = headline_metrics(per_map);
+            sides.push(Widget::col(vec![
+                Line(name.as_deref().unwrap_or("original map"))
+                    .small_heading()
+                    .draw(ctx),
+                format!("Finished trips: {}", abstutil::prettyprint_usize(finished)).draw_text(ctx),
+                format!(
+                    "Average delay at intersections: {}",
+                    avg_delay
+                        .map(|d| d.to_string())
+                        .unwrap_or_else(|| "none yet".to_string())
+                )
+                .draw_text(ctx),
+            ]));
+        }
+
+        self.hud = Panel::new(Widget::col(vec![
+            Widget::row(vec![
+                Line("Comparing proposals").small_heading().draw(ctx),
+                ctx.style()
+                    .btn_plain_light_icon(if self.paused {
+                        "system/assets/speed/triangle.svg"
+                    } else {
+                        "system/assets/speed/pause.svg"
+                    })
+                    .hotkey(Key::Space)
+                    .build_widget(ctx, if self.paused { "play" } else { "pause" }),
+                Spinner::new(ctx, (1, 3600), speed)
+                    .named("speed")
+                    .centered_vert(),
+                "x speed".draw_text(ctx).centered_vert(),
+                Line(app.primary.sim.time().ampm_tostring())
+                    .draw(ctx)
+                    .centered_vert(),
+                ctx.style().btn_close_widget(ctx),
+            ]),
+            Widget::row(sides).evenly_spaced(),
+        ]))
+        .aligned(HorizontalAlignment::Center, VerticalAlignment::Top)
+        .build(ctx);
+        self.hud_time = app.primary.sim.time();
+    }
+
+    fn swap_sides(&mut self, app: &mut App) {
+        std::mem::swap(&mut app.primary, app.secondary.as_mut().unwrap());
+        app.primary.current_selection = None;
+        app.secondary.as_mut().unwrap().current_selection = None;
+        self.active = self.active.other();
+        self.info_panel = None;
+    }
+
+    // Which side the cursor is over, and the point on that side's map
+    fn cursor_on_map(&self, ctx: &EventCtx) -> Option<(Side, Pt2D)> {
+        // This is None when the cursor is over a panel
+        ctx.canvas.get_cursor_in_map_space()?;
+        let cursor = ctx.canvas.get_cursor();
+        let width = ctx.canvas.window_width;
+        let side = if cursor.x < width / 2.0 {
+            Side::Left
+        } else {
+            Side::Right
+        };
+        let pt = ctx
+            .canvas
+            .screen_to_map(ScreenPt::new(cursor.x - side.shift(width), cursor.y));
+        Some((side, pt))
+    }
+
+    fn actions(&self) -> Actions {
+        Actions {
+            paused: self.paused,
+            mode: self.mode.clone(),
+        }
+    }
+}
+
+impl State<App> for SplitScreen {
+    fn event(&mut self, ctx: &mut EventCtx, app: &mut App) -> Transition {
+        ctx.canvas_movement();
+
+        let cursor = self.cursor_on_map(ctx);
+        if ctx.redo_mouseover() {
+            app.primary.current_selection = None;
+            if let Some((side, pt)) = cursor {
+                if side == self.active {
+                    app.recalculate_current_selection_at(ctx, pt);
+                }
+            }
+        }
+
+        // Clicking anywhere on the other side makes it the one info panels work on
+        if let Some((side, pt)) = cursor {
+            if side != self.active && ctx.normal_left_click() {
+                self.swap_sides(app);
+                app.recalculate_current_selection_at(ctx, pt);
+                let speed = self.hud.spinner("speed");
+                self.recreate_hud(ctx, app, speed);
+                return Transition::Keep;
+            }
+        }
+
+        let mut actions = self.actions();
+        if let Some(ref mut info) = self.info_panel {
+            let (closed, maybe_transition) = info.event(ctx, app, &mut actions);
+            if closed {
+                self.info_panel = None;
+            }
+            if let Some(t) = maybe_transition {
+                return t;
+            }
+        }
+        if let Some(id) = app.primary.current_selection.clone() {
+            if app.per_obj.left_click(ctx, "show info") {
+                self.info_panel = Some(InfoPanel::new(
+                    ctx,
+                    app,
+                    Tab::from_id(app, id),
+                    &mut actions,
+                ));
+                return Transition::Keep;
+            }
+        }
+
+        let speed = self.hud.spinner("speed");
+        if let Outcome::Clicked(x) = self.hud.event(ctx) {
+            match x.as_ref() {
+                "close" => {
+                    if self.active == Side::Right {
+                        self.swap_sides(app);
+                    }
+                    app.secondary = None;
+                    self.sandbox.take().unwrap().restore(ctx, app);
+                    return Transition::Pop;
+                }
+                "play" => {
+                    self.paused = false;
+                    self.recreate_hud(ctx, app, speed);
+                }
+                "pause" => {
+                    self.paused = true;
+                    self.recreate_hud(ctx, app, speed);
+                }
+                _ => unreachable!(),
+            }
+        }
+
+        if !self.paused {
+            if let Some(real_dt) = ctx.input.nonblocking_is_update_event() {
+                ctx.input.use_update_event();
+                let dt = (speed as f64) * real_dt;
+                app.primary.sim.time_limited_step(
+                    &app.primary.map,
+                    dt,
+                    Duration::seconds(0.033),
+                    &mut None,
+                );
+                // The other side has to catch up exactly, however long that takes, so both are
+                // always showing the same time.
+                let other = app.secondary.as_mut().unwrap();
+                let behind = app.primary.sim.time() - other.sim.time();
+                other
+                    .sim
+                    .timed_step(&other.map, behind, &mut None, &mut Timer::throwaway());
+
+                if let Some((side, pt)) = cursor {
+                    if side == self.active {
+                        app.recalculate_current_selection_at(ctx, pt);
+                    }
+                }
+                // Summarizing every delay isn't free, so don't do it every frame
+                if app.primary.sim.time() - self.hud_time >= Duration::minutes(1) {
+                    self.recreate_hud(ctx, app, speed);
+                }
+            }
+            ctx.request_update(UpdateType::Game);
+        }
+
+        Transition::Keep
+    }
+
+    fn draw_baselayer(&self) -> DrawBaselayer {
+        DrawBaselayer::Custom
+    }
+
+    fn draw(&self, g: &mut GfxCtx, app: &App) {
+        let width = g.canvas.window_width;
+        let height = g.canvas.window_height;
+        for side in vec![Side::Left, Side::Right] {
+            g.enable_clipping(side.viewport(width, height));
+            g.fork_shifted(side.shift(width), 0.0);
+            if side == self.active {
+                app.draw(g, DrawOptions::new(), &ShowEverything::new());
+                if let Some(ref info) = self.info_panel {
+                    info.draw_highlights(g, app);
+                }
+            } else {
+                app.draw_secondary(g, DrawOptions::new(), &ShowEverything::new());
+            }
+            g.unfork();
+            g.disable_clipping();
+        }
+
+        g.fork_screenspace();
+        g.draw_polygon(
+            Color::WHITE,
+            GeomLine::must_new(Pt2D::new(width / 2.0, 0.0), Pt2D::new(width / 2.0, height))
+                .make_polygons(Distance::meters(3.0)),
+        );
+        g.unfork();
+
+        self.hud.draw(g);
+        if let Some(ref info) = self.info_panel {
+            info.draw_panel(g, app);
+        }
+    }
+}
+
+// (finished trips, average delay at intersections so far)
+fn headline_metrics(per_map: &PerMap) -> (usize, Option<Duration>) {
+    let finished = per_map.sim.num_trips().0;
+    let mut total = Duration::ZERO;
+    let mut count = 0;
+    for delays in per_map.sim.get_analytics().intersection_delays.values() {
+        for (_, _, delay, _) in delays {
+            total += *delay;
+            count += 1;
+        }
+    }
+    let avg = if count == 0 {
+        None
+    } else {
+        Some(total / (count as f64))
+    };
+    (finished, avg)
+}
+
+// Info panels here only show things; there's nothing extra to do with objects.
+struct Actions {
+    paused: bool,
+    mode: GameplayMode,
+}
+
+impl ContextualActions for Actions {
+    fn actions(&self, _: &App, _: ID) -> Vec<(Key, String)> {
+        Vec::new()
+    }
+    fn execute(
+        &mut self,
+        _: &mut EventCtx,
+        _: &mut App,
+        _: ID,
+        _: String,
+        _: &mut bool,
+    ) -> Transition {
+        unreachable!()
+    }
+    fn is_paused(&self) -> bool {
+        self.paused
+    }
+    fn gameplay_mode(&self) -> GameplayMode {
+        self.mode.clone()
+    }
+}
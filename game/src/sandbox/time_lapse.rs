@@ -0,0 +1,191 @@
+use geom::{Circle, Distance, Duration, Pt2D, Time};
+use map_gui::tools::grey_out_map;
+use widgetry::{
+    Checkbox, Color, EventCtx, GeomBatch, GfxCtx, HorizontalAlignment, Key, Line, Outcome, Panel,
+    Spinner, State, StyledButtons, TextExt, UpdateType, VerticalAlignment, Widget,
+};
+
+use crate::app::{App, Transition};
+use crate::sandbox::SandboxMode;
+
+/// While the simulation runs, periodically writes a numbered screenshot of the map to a
+/// directory. Useful for before/after comparisons and time-lapse videos.
+pub struct TimeLapse {
+    dir: String,
+    interval: Duration,
+    // (cam_x, cam_y, cam_zoom), if the framing shouldn't follow the user's camera
+    camera: Option<(f64, f64, f64)>,
+    next_capture: Time,
+    frames_written: usize,
+    // Intervals skipped because the simulation stepped past them
+    frames_dropped: usize,
+    // Screenshots that were requested, but not saved because writing them fell behind. The count
+    // is shared with any other screenshots taken since the app started.
+    writer_dropped_at_start: usize,
+    panel: Panel,
+}
+
+impl TimeLapse {
+    fn new(
+        ctx: &mut EventCtx,
+        app: &App,
+        interval: Duration,
+        camera: Option<(f64, f64, f64)>,
+    ) -> TimeLapse {
+        let dir = format!(
+            "screenshots/time_lapse/{}_{}",
+            app.primary.map.get_name().as_filename(),
+            chrono::Local::now().format("%Y-%m-%d_%H-%M-%S")
+        );
+        let mut time_lapse = TimeLapse {
+            dir,
+            interval,
+            camera,
+            // Capture the starting state too
+            next_capture: app.primary.sim.time(),
+            frames_written: 0,
+            frames_dropped: 0,
+            writer_dropped_at_start: ctx.prerender.get_num_screencaps_dropped(),
+            panel: Panel::empty(ctx),
+        };
+        time_lapse.recreate_panel(ctx);
+        time_lapse
+    }
+
+    fn recreate_panel(&mut self, ctx: &mut EventCtx) {
+        let writer_dropped =
+            ctx.prerender.get_num_screencaps_dropped() - self.writer_dropped_at_start;
+        let mut status = format!(
+            "{} frames written (every {})",
+            self.frames_written.saturating_sub(writer_dropped),
+            self.interval
+        );
+        let dropped = self.frames_dropped + writer_dropped;
+        if dropped > 0 {
+            status = format!("{}, {} dropped", status, dropped);
+        }
+        self.panel = Panel::new(Widget::row(vec![
+            Widget::draw_batch(
+                ctx,
+                GeomBatch::from(vec![(
+                    Color::RED,
+                    Circle::new(Pt2D::new(0.0, 0.0), Distance::meters(10.0)).to_polygon(),
+                )]),
+            )
+            .centered_vert(),
+            Widget::col(vec![
+                Line("Capturing time-lapse").small_heading().draw(ctx),
+                status.draw_text(ctx),
+            ]),
+            ctx.style()
+                .btn_solid_dark_text("Stop")
+                .build_def(ctx)
+                .centered_vert(),
+        ]))
+        .aligned(HorizontalAlignment::Left, VerticalAlignment::Center)
+        .build(ctx);
+    }
+
+    /// Returns true when the user has stopped capturing.
+    pub fn event(&mut self, ctx: &mut EventCtx, app: &App) -> bool {
+        if let Outcome::Clicked(x) = self.panel.event(ctx) {
+            match x.as_ref() {
+                "Stop" => {
+                    return true;
+                }
+                _ => unreachable!(),
+            }
+        }
+
+        let now = app.primary.sim.time();
+        if now >= self.next_capture {
+            // When the simulation runs quickly, one step might cross several intervals. We can
+            // only capture the current state, so the other frames are lost.
+            let crossed = 1 + ((now - self.next_capture) / self.interval).floor() as usize;
+            self.frames_dropped += crossed - 1;
+            self.next_capture = self.next_capture + self.interval * (crossed as f64);
+
+            ctx.request_update(UpdateType::ScreenCaptureCurrentShot {
+                filename: format!("{}/{:05}.png", self.dir, self.frames_written),
+                camera: self.camera,
+            });
+            // The frame's only counted as written when it's requested; if the writer drops it, the
+            // next update of the panel corrects that
+            self.frames_written += 1;
+            self.recreate_panel(ctx);
+        }
+        false
+    }
+
+    pub fn draw(&self, g: &mut GfxCtx) {
+        self.panel.draw(g);
+    }
+}
+
+/// Configure and start a `TimeLapse`.
+pub struct TimeLapseSetup {
+    panel: Panel,
+}
+
+impl TimeLapseSetup {
+    pub fn new(ctx: &mut EventCtx) -> Box<dyn State<App>> {
+        Box::new(TimeLapseSetup {
+            panel: Panel::new(Widget::col(vec![
+                Widget::row(vec![
+                    Line("Capture a time-lapse").small_heading().draw(ctx),
+                    ctx.style().btn_close_widget(ctx),
+                ]),
+                Widget::row(vec![
+                    "Take a screenshot every".draw_text(ctx).centered_vert(),
+                    Spinner::new(ctx, (1, 120), 10).named("minutes"),
+                    "simulated minutes".draw_text(ctx).centered_vert(),
+                ]),
+                Checkbox::switch(ctx, "keep the camera fixed on the current view", None, true),
+                "Screenshots will be written to the screenshots/time_lapse directory"
+                    .draw_text(ctx),
+                ctx.style()
+                    .btn_solid_dark_text("Start")
+                    .hotkey(Key::Enter)
+                    .build_def(ctx)
+                    .centered_horiz(),
+            ]))
+            .build(ctx),
+        })
+    }
+}
+
+impl State<App> for TimeLapseSetup {
+    fn event(&mut self, ctx: &mut EventCtx, _: &mut App) -> Transition {
+        match self.panel.event(ctx) {
+            Outcome::Clicked(x) => match x.as_ref() {
+                "close" => Transition::Pop,
+                "Start" => {
+                    let interval = Duration::minutes(self.panel.spinner("minutes") as usize);
+                    let camera = if self
+                        .panel
+                        .is_checked("keep the camera fixed on the current view")
+                    {
+                        Some((ctx.canvas.cam_x, ctx.canvas.cam_y, ctx.canvas.cam_zoom))
+                    } else {
+                        None
+                    };
+                    Transition::Multi(vec![
+                        Transition::Pop,
+                        Transition::ModifyState(Box::new(move |state, ctx, app| {
+                            let sandbox = state.downcast_mut::<SandboxMode>().unwrap();
+                            sandbox.controls.time_lapse =
+                                Some(TimeLapse::new(ctx, app, interval, camera));
+                        })),
+                    ])
+                }
+                _ => unreachable!(),
+            },
+            _ => Transition::Keep,
+        }
+    }
+
+    fn draw(&self, g: &mut GfxCtx, app: &App) {
+        grey_out_map(g, app);
+        self.panel.draw(g);
+    }
+}
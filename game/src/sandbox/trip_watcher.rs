@@ -0,0 +1,125 @@
+use sim::{TripID, TripResult};
+use widgetry::{
+    EventCtx, GfxCtx, HorizontalAlignment, Line, Outcome, Panel, StyledButtons, TextExt,
+    VerticalAlignment, Widget,
+};
+
+use crate::app::App;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum WatchFor {
+    Start,
+    Finish,
+}
+
+/// Remembers trips the player wants to catch as they happen, so the simulation can pause itself
+/// instead of overshooting while fast-forwarding. Watches live in the sandbox, not the info panel,
+/// so they survive the panel being closed.
+pub struct TripWatcher {
+    watches: Vec<(TripID, WatchFor)>,
+    panel: Option<Panel>,
+}
+
+impl TripWatcher {
+    pub fn new() -> TripWatcher {
+        TripWatcher {
+            watches: Vec::new(),
+            panel: None,
+        }
+    }
+
+    pub fn watch(&mut self, ctx: &mut EventCtx, trip: TripID, watch: WatchFor) {
+        if !self.watches.contains(&(trip, watch)) {
+            self.watches.push((trip, watch));
+            self.recreate_panel(ctx);
+        }
+    }
+
+    fn recreate_panel(&mut self, ctx: &mut EventCtx) {
+        if self.watches.is_empty() {
+            self.panel = None;
+            return;
+        }
+
+        let mut col = vec![Line("Pause when...").small_heading().draw(ctx)];
+        for (idx, (trip, watch)) in self.watches.iter().enumerate() {
+            col.push(Widget::row(vec![
+                describe(*trip, *watch).draw_text(ctx).centered_vert(),
+                ctx.style()
+                    .btn_close()
+                    .build_widget(ctx, &format!("stop watching #{}", idx))
+                    .align_right(),
+            ]));
+        }
+        self.panel = Some(
+            Panel::new(Widget::col(col))
+                .aligned(HorizontalAlignment::Right, VerticalAlignment::Center)
+                .build(ctx),
+        );
+    }
+
+    /// Returns the watches that just triggered, with a message explaining each one. They're no
+    /// longer watched afterwards.
+    pub fn event(&mut self, ctx: &mut EventCtx, app: &App) -> Vec<(TripID, String)> {
+        if let Some(ref mut panel) = self.panel {
+            if let Outcome::Clicked(x) = panel.event(ctx) {
+                let idx = x
+                    .strip_prefix("stop watching #")
+                    .unwrap()
+                    .parse::<usize>()
+                    .unwrap();
+                self.watches.remove(idx);
+                self.recreate_panel(ctx);
+                return Vec::new();
+            }
+        }
+
+        let mut triggered = Vec::new();
+        let mut changed = false;
+        self.watches.retain(|(trip, watch)| {
+            let msg = match (app.primary.sim.trip_to_agent(*trip), watch) {
+                // Nothing to wait for anymore
+                (TripResult::TripDoesntExist, _) => {
+                    changed = true;
+                    return false;
+                }
+                (TripResult::TripNotStarted, _) => {
+                    return true;
+                }
+                (TripResult::TripCancelled, _) => format!("{} was cancelled", trip),
+                (TripResult::TripDone, WatchFor::Finish) => format!("{} finished", trip),
+                // Short trips can start and finish in one step, or during a time warp
+                (TripResult::TripDone, WatchFor::Start) => {
+                    format!("{} started and already finished", trip)
+                }
+                (_, WatchFor::Finish) => {
+                    return true;
+                }
+                (_, WatchFor::Start) => format!("{} started", trip),
+            };
+            triggered.push((
+                *trip,
+                format!("{} at {}", msg, app.primary.sim.time().ampm_tostring()),
+            ));
+            changed = true;
+            false
+        });
+        if changed {
+            self.recreate_panel(ctx);
+        }
+        triggered
+    }
+
+    pub fn draw(&self, g: &mut GfxCtx) {
+        if let Some(ref panel) = self.panel {
+            panel.draw(g);
+        }
+    }
+}
+
+fn describe(trip: TripID, watch: WatchFor) -> String {
+    match watch {
+        WatchFor::Start => format!("{} starts", trip),
+        WatchFor::Finish => format!("{} finishes", trip),
+    }
+}
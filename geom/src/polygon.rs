@@ -9,7 +9,11 @@ use geo::algorithm::intersects::Intersects;
 use geo_booleanop::boolean::BooleanOp;
 use serde::{Deserialize, Serialize};
 
-use crate::{Angle, Bounds, CornerRadii, Distance, GPSBounds, HashablePt2D, PolyLine, Pt2D, Ring};
+use abstutil::prettyprint_usize;
+
+use crate::{
+    Angle, Bounds, CornerRadii, Distance, GPSBounds, HashablePt2D, PolyLine, Pt2D, Ring, UnitFmt,
+};
 
 #[derive(PartialEq, Serialize, Deserialize, Clone, Debug)]
 pub struct Polygon {
@@ -377,6 +381,32 @@ impl Polygon {
         to_geo(&self.points()).unsigned_area()
     }
 
+    /// Describes the area according to formatting rules.
+    pub fn area_to_string(&self, fmt: &UnitFmt) -> String {
+        let sq_meters = self.area();
+        if fmt.metric {
+            if sq_meters < 10_000.0 {
+                format!("{} m²", prettyprint_usize(sq_meters.round() as usize))
+            } else if sq_meters < 1_000_000.0 {
+                format!("{:.1} hectares", sq_meters / 10_000.0)
+            } else {
+                format!("{:.2} km²", sq_meters / 1_000_000.0)
+            }
+        } else {
+            let acres = sq_meters / 4046.86;
+            if acres < 1.0 {
+                format!(
+                    "{} sq ft",
+                    prettyprint_usize((sq_meters * 10.7639).round() as usize)
+                )
+            } else if acres < 640.0 {
+                format!("{:.1} acres", acres)
+            } else {
+                format!("{:.2} sq mi", acres / 640.0)
+            }
+        }
+    }
+
     /// Doesn't handle multiple crossings in and out.
     pub fn clip_polyline(&self, input: &PolyLine) -> Option<Vec<Pt2D>> {
         let ring = Ring::must_new(self.points.clone());
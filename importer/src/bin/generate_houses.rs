@@ -1,17 +1,55 @@
 //! Procedurally generates houses along empty residential roads of a map. Writes a GeoJSON file
 //! with the results if the number of houses is at least `--num_required`. This can be used to
 //! autodetect if a map probably already has houses filled out in OSM.
+//!
+//! By default, every empty residential road is filled. To model where demand actually is, pass
+//! `--target_households` to stop after that many houses, or `--quotas` with a GeoJSON file of
+//! neighborhood polygons, each with a `name` and a number of `households`. Either way, roads
+//! closest to bus stops and commercial buildings are filled first.
+//!
+//! Houses are spaced randomly by default. `--placement=regular_lots` instead divides each block
+//! into lots `--lot_width` meters wide (12 by default), like a real subdivision, and also writes
+//! the lot boundaries as LineStrings.
 
-use std::collections::HashSet;
+use std::collections::{BTreeMap, HashSet};
 
 use aabb_quadtree::QuadTree;
+use anyhow::{bail, Result};
 use geojson::{Feature, FeatureCollection, GeoJson};
 use rand::{Rng, SeedableRng};
 use rand_xorshift::XorShiftRng;
 
-use abstutil::{CmdArgs, Timer};
-use geom::{Distance, Polygon};
-use map_model::{osm, Map};
+use abstutil::{prettyprint_usize, CmdArgs, Timer};
+use geom::{Distance, GPSBounds, LonLat, PolyLine, Polygon, Pt2D, Ring};
+use map_model::{osm, BuildingType, DrivingSide, Lane, Map};
+
+/// A generated house
+struct House {
+    polygon: Polygon,
+    /// The straight-line distance from the house's sidewalk to the nearest bus stop or commercial
+    /// building. Lower is better. None if the map has neither.
+    score: Option<Distance>,
+    /// Only filled out when placing houses by neighborhood quotas
+    neighborhood: Option<String>,
+    /// The sides and back of the lot, when placing houses in regular lots
+    lot: Option<PolyLine>,
+}
+
+/// How to space houses along each block
+#[derive(Clone, Copy)]
+enum Placement {
+    /// Houses of random sizes, with random gaps between them
+    Random,
+    /// Divide each block face into lots of this width, with one house centered in each
+    RegularLots(Distance),
+}
+
+/// An area that should get some number of new households
+struct Neighborhood {
+    name: String,
+    polygon: Polygon,
+    households: usize,
+}
 
 fn main() {
     let mut timer = Timer::new("generate houses");
@@ -20,9 +58,23 @@ fn main() {
     let num_required = args.required("--num_required").parse::<usize>().unwrap();
     let out = args.required("--out");
     let mut rng = XorShiftRng::seed_from_u64(args.required("--rng_seed").parse::<u64>().unwrap());
+    let target_households = args.optional_parse("--target_households", |s| s.parse::<usize>());
+    let quotas = args.optional("--quotas");
+    let placement = match args.optional("--placement").as_deref() {
+        None | Some("random") => Placement::Random,
+        Some("regular_lots") => Placement::RegularLots(Distance::meters(
+            args.optional_parse("--lot_width", |s| s.parse::<f64>())
+                .unwrap_or(12.0),
+        )),
+        Some(x) => panic!("Unknown --placement {}; use random or regular_lots", x),
+    };
     args.done();
+    if target_households.is_some() && quotas.is_some() {
+        panic!("Pass --target_households or --quotas, not both");
+    }
 
-    let houses = generate_buildings_on_empty_residential_roads(&map, &mut rng, &mut timer);
+    let mut houses =
+        generate_buildings_on_empty_residential_roads(&map, &mut rng, placement, &mut timer);
     if houses.len() <= num_required {
         panic!(
             "Only generated {} houses, but wanted at least {}",
@@ -31,15 +83,51 @@ fn main() {
         );
     }
 
+    // The houses are already ordered best-first, so meeting a target just means keeping the
+    // first few
+    if let Some(target) = target_households {
+        houses.truncate(target);
+        println!(
+            "Generated {} of {} requested households",
+            prettyprint_usize(houses.len()),
+            prettyprint_usize(target)
+        );
+    }
+    if let Some(path) = quotas {
+        let neighborhoods = read_neighborhoods(&path, map.get_gps_bounds()).unwrap();
+        houses = fill_quotas(houses, &neighborhoods);
+    }
+
     let mut features = Vec::new();
-    for poly in houses {
+    for house in houses {
+        let mut properties = serde_json::Map::new();
+        if let Some(score) = house.score {
+            properties.insert(
+                "score".to_string(),
+                serde_json::Value::from(score.inner_meters()),
+            );
+        }
+        if let Some(name) = house.neighborhood {
+            properties.insert("neighborhood".to_string(), serde_json::Value::from(name));
+        }
         features.push(Feature {
             bbox: None,
-            geometry: Some(poly.to_geojson(Some(map.get_gps_bounds()))),
+            geometry: Some(house.polygon.to_geojson(Some(map.get_gps_bounds()))),
             id: None,
-            properties: None,
+            properties: Some(properties),
             foreign_members: None,
         });
+        if let Some(lot) = house.lot {
+            let mut properties = serde_json::Map::new();
+            properties.insert("lot".to_string(), serde_json::Value::from(true));
+            features.push(Feature {
+                bbox: None,
+                geometry: Some(lot.to_geojson(Some(map.get_gps_bounds()))),
+                id: None,
+                properties: Some(properties),
+                foreign_members: None,
+            });
+        }
     }
     let geojson = GeoJson::from(FeatureCollection {
         bbox: None,
@@ -49,11 +137,110 @@ fn main() {
     abstio::write_json(out, &geojson);
 }
 
+/// Keeps the best houses in each neighborhood, up to its quota. Houses outside every neighborhood
+/// are dropped. Prints how many households each neighborhood got.
+fn fill_quotas(houses: Vec<House>, neighborhoods: &[Neighborhood]) -> Vec<House> {
+    let mut achieved: BTreeMap<&str, usize> = BTreeMap::new();
+    let mut outside = 0;
+    let mut result = Vec::new();
+    for mut house in houses {
+        let center = house.polygon.center();
+        // If neighborhoods overlap, the first one listed wins
+        match neighborhoods.iter().find(|n| n.polygon.contains_pt(center)) {
+            Some(n) => {
+                let cnt = achieved.entry(&n.name).or_insert(0);
+                if *cnt < n.households {
+                    *cnt += 1;
+                    house.neighborhood = Some(n.name.clone());
+                    result.push(house);
+                }
+            }
+            None => {
+                outside += 1;
+            }
+        }
+    }
+
+    for n in neighborhoods {
+        let cnt = achieved.get(n.name.as_str()).cloned().unwrap_or(0);
+        println!(
+            "{}: generated {} of {} requested households{}",
+            n.name,
+            prettyprint_usize(cnt),
+            prettyprint_usize(n.households),
+            if cnt < n.households {
+                " (not enough room on empty residential roads)"
+            } else {
+                ""
+            }
+        );
+    }
+    println!(
+        "Skipped {} houses outside every neighborhood",
+        prettyprint_usize(outside)
+    );
+    result
+}
+
+fn read_neighborhoods(path: &str, gps_bounds: &GPSBounds) -> Result<Vec<Neighborhood>> {
+    let bytes = abstio::slurp_file(path)?;
+    let geojson = std::str::from_utf8(&bytes)?.parse::<geojson::GeoJson>()?;
+    let collection = match geojson {
+        geojson::GeoJson::FeatureCollection(collection) => collection,
+        _ => bail!("{} isn't a FeatureCollection", path),
+    };
+
+    let mut neighborhoods = Vec::new();
+    for feature in collection.features {
+        let name = match feature
+            .properties
+            .as_ref()
+            .and_then(|props| props.get("name"))
+        {
+            Some(serde_json::Value::String(s)) => s.clone(),
+            _ => bail!("a neighborhood in {} is missing a name", path),
+        };
+        // Some sources store numbers as strings
+        let households = match feature
+            .properties
+            .as_ref()
+            .and_then(|props| props.get("households"))
+        {
+            Some(serde_json::Value::Number(n)) => n.as_u64().map(|x| x as usize),
+            Some(serde_json::Value::String(s)) => s.parse::<usize>().ok(),
+            _ => None,
+        };
+        let households = match households {
+            Some(x) => x,
+            None => bail!("neighborhood {} in {} is missing households", name, path),
+        };
+        // Only the outer ring is used
+        let raw_pts = match feature.geometry.map(|g| g.value) {
+            Some(geojson::Value::Polygon(mut rings)) => rings.remove(0),
+            _ => bail!("neighborhood {} in {} isn't a polygon", name, path),
+        };
+        let gps_pts: Vec<LonLat> = raw_pts
+            .into_iter()
+            .map(|pt| LonLat::new(pt[0], pt[1]))
+            .collect();
+        // Neighborhoods partly outside the map are fine
+        let polygon = Ring::new(gps_bounds.convert(&gps_pts))?.to_polygon();
+        neighborhoods.push(Neighborhood {
+            name,
+            polygon,
+            households,
+        });
+    }
+    Ok(neighborhoods)
+}
+
+/// Returns houses ordered by their score, best first.
 fn generate_buildings_on_empty_residential_roads(
     map: &Map,
     rng: &mut XorShiftRng,
+    placement: Placement,
     timer: &mut Timer,
-) -> Vec<Polygon> {
+) -> Vec<House> {
     timer.start("initially place buildings");
     let mut lanes_with_buildings = HashSet::new();
     for b in map.all_buildings() {
@@ -71,49 +258,73 @@ fn generate_buildings_on_empty_residential_roads(
         }
     }
 
+    // Score each sidewalk by how close it is to places people would want to live near. This is
+    // straight-line distance from the middle of the sidewalk for now; walking distance would be
+    // more realistic.
+    timer.start("score sidewalks");
+    let mut destinations: Vec<Pt2D> = map
+        .all_bus_stops()
+        .values()
+        .map(|bs| bs.sidewalk_pos.pt(map))
+        .collect();
+    for b in map.all_buildings() {
+        if matches!(
+            b.bldg_type,
+            BuildingType::Commercial(_) | BuildingType::ResidentialCommercial(_, _)
+        ) {
+            destinations.push(b.polygon.center());
+        }
+    }
+    let mut scored_sidewalks: Vec<(Option<Distance>, _)> = empty_sidewalks
+        .into_iter()
+        .map(|l| {
+            let pt = map.get_l(l).lane_center_pts.middle();
+            let score = destinations.iter().map(|dst| pt.dist_to(*dst)).min();
+            (score, l)
+        })
+        .collect();
+    // Sidewalks with no score (because there's nothing to be near) go last
+    scored_sidewalks.sort_by_key(|(score, l)| (score.is_none(), *score, *l));
+    timer.stop("score sidewalks");
+
     // Walk along each sidewalk, trying to place some simple houses with a bit of setback from the
-    // road.
+    // road. Sidewalks point the same way as traffic on their side of the road, so the direction
+    // away from the road flips when driving on the left.
+    let away_from_road = if map.get_config().driving_side == DrivingSide::Right {
+        -90.0
+    } else {
+        90.0
+    };
     let mut houses = Vec::new();
-    for l in empty_sidewalks {
+    for (score, l) in scored_sidewalks {
         let lane = map.get_l(l);
-        let mut dist_along = rand_dist(rng, 1.0, 5.0);
-        while dist_along < lane.lane_center_pts.length() {
-            let (sidewalk_pt, angle) = lane.lane_center_pts.must_dist_along(dist_along);
-            let width = rng.gen_range(6.0..14.0);
-            let height = rng.gen_range(6.0..14.0);
-
-            // Make it so that the front of the house is always set back a fixed amount. So account
-            // for the chosen "height".
-            let setback = Distance::meters(10.0) + Distance::meters(height / 2.0);
-            let center = sidewalk_pt.project_away(setback, angle.rotate_degs(-90.0));
-
-            houses.push(
-                Polygon::rectangle(width, height)
-                    .rotate(angle)
-                    .translate(center.x() - width / 2.0, center.y() - height / 2.0),
-            );
-
-            dist_along += Distance::meters(width.max(height)) + rand_dist(rng, 2.0, 4.0);
+        match placement {
+            Placement::Random => {
+                place_randomly(lane, score, away_from_road, rng, &mut houses);
+            }
+            Placement::RegularLots(lot_width) => {
+                place_in_lots(lane, score, away_from_road, lot_width, rng, &mut houses);
+            }
         }
     }
     timer.stop("initially place buildings");
 
-    // Remove buildings that hit each other. Build up the quadtree of finalized houses as we go,
-    // using index as the ID.
+    // Remove buildings that hit each other, keeping the better scored ones. Build up the quadtree
+    // of finalized houses as we go, using index as the ID.
     let mut non_overlapping = Vec::new();
     let mut quadtree = QuadTree::default(map.get_bounds().as_bbox());
     timer.start_iter("prune buildings overlapping each other", houses.len());
-    'HOUSE: for poly in houses {
+    'HOUSE: for house in houses {
         timer.next();
-        let mut search = poly.get_bounds();
+        let mut search = house.polygon.get_bounds();
         search.add_buffer(Distance::meters(1.0));
         for (idx, _, _) in quadtree.query(search.as_bbox()) {
-            if poly.intersects(&non_overlapping[*idx]) {
+            if house.polygon.intersects(&non_overlapping[*idx].polygon) {
                 continue 'HOUSE;
             }
         }
-        quadtree.insert_with_box(non_overlapping.len(), poly.get_bounds().as_bbox());
-        non_overlapping.push(poly);
+        quadtree.insert_with_box(non_overlapping.len(), house.polygon.get_bounds().as_bbox());
+        non_overlapping.push(house);
     }
 
     // Create a different quadtree, just containing static things in the map that we don't want
@@ -147,18 +358,100 @@ fn generate_buildings_on_empty_residential_roads(
         "prune buildings overlapping the basemap",
         non_overlapping.len(),
     );
-    'NON_OVERLAP: for poly in non_overlapping {
+    'NON_OVERLAP: for house in non_overlapping {
         timer.next();
-        for (idx, _, _) in quadtree.query(poly.get_bounds().as_bbox()) {
-            if poly.intersects(&static_polygons[*idx]) {
+        for (idx, _, _) in quadtree.query(house.polygon.get_bounds().as_bbox()) {
+            if house.polygon.intersects(&static_polygons[*idx]) {
                 continue 'NON_OVERLAP;
             }
         }
-        survivors.push(poly);
+        survivors.push(house);
     }
     survivors
 }
 
+fn place_randomly(
+    lane: &Lane,
+    score: Option<Distance>,
+    away_from_road: f64,
+    rng: &mut XorShiftRng,
+    houses: &mut Vec<House>,
+) {
+    let mut dist_along = rand_dist(rng, 1.0, 5.0);
+    while dist_along < lane.lane_center_pts.length() {
+        let (sidewalk_pt, angle) = lane.lane_center_pts.must_dist_along(dist_along);
+        let width = rng.gen_range(6.0..14.0);
+        let height = rng.gen_range(6.0..14.0);
+
+        // Make it so that the front of the house is always set back a fixed amount. So account
+        // for the chosen "height".
+        let setback = Distance::meters(10.0) + Distance::meters(height / 2.0);
+        let center = sidewalk_pt.project_away(setback, angle.rotate_degs(away_from_road));
+
+        houses.push(House {
+            polygon: Polygon::rectangle(width, height)
+                .rotate(angle)
+                .translate(center.x() - width / 2.0, center.y() - height / 2.0),
+            score,
+            neighborhood: None,
+            lot: None,
+        });
+
+        dist_along += Distance::meters(width.max(height)) + rand_dist(rng, 2.0, 4.0);
+    }
+}
+
+/// Divides one block face into equal lots, with any remainder split between the two ends, and
+/// places a house in the middle of each lot. Lots blocked by something are pruned later, along
+/// with their house.
+fn place_in_lots(
+    lane: &Lane,
+    score: Option<Distance>,
+    away_from_road: f64,
+    lot_width: Distance,
+    rng: &mut XorShiftRng,
+    houses: &mut Vec<House>,
+) {
+    // Each sidewalk runs between two consecutive intersections, so it's one face of the block.
+    // Stay clear of the corners, where the lots would overlap the cross street's.
+    let corner = Distance::meters(5.0);
+    let length = lane.lane_center_pts.length();
+    if length <= corner * 2.0 {
+        return;
+    }
+    let face = lane.lane_center_pts.exact_slice(corner, length - corner);
+    let num_lots = (face.length() / lot_width).floor() as usize;
+    let start = (face.length() - lot_width * (num_lots as f64)) / 2.0;
+    let lot_depth = Distance::meters(30.0);
+
+    for idx in 0..num_lots {
+        let lot_start = start + lot_width * (idx as f64);
+        let lot_end = lot_start + lot_width;
+        let (sidewalk_pt, angle) = face.must_dist_along(lot_start + lot_width / 2.0);
+        let away = angle.rotate_degs(away_from_road);
+
+        // Leave a side yard of at least a meter
+        let width = (lot_width.inner_meters() - 2.0).min(14.0).max(4.0);
+        let height = rng.gen_range(8.0..14.0);
+        let setback = rand_dist(rng, 8.0, 12.0) + Distance::meters(height / 2.0);
+        let center = sidewalk_pt.project_away(setback, away);
+
+        let (front1, angle1) = face.must_dist_along(lot_start);
+        let (front2, angle2) = face.must_dist_along(lot_end);
+        let back1 = front1.project_away(lot_depth, angle1.rotate_degs(away_from_road));
+        let back2 = front2.project_away(lot_depth, angle2.rotate_degs(away_from_road));
+
+        houses.push(House {
+            polygon: Polygon::rectangle(width, height)
+                .rotate(angle)
+                .translate(center.x() - width / 2.0, center.y() - height / 2.0),
+            score,
+            neighborhood: None,
+            lot: PolyLine::new(vec![front1, back1, back2, front2]).ok(),
+        });
+    }
+}
+
 fn rand_dist(rng: &mut XorShiftRng, low: f64, high: f64) -> Distance {
     assert!(high > low);
     Distance::meters(rng.gen_range(low..high))
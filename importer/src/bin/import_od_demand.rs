@@ -0,0 +1,33 @@
+//! Synthesizes a scenario from an origin-destination table. The table is a CSV file with the
+//! columns origin_zone, destination_zone, hour, mode, and count. The zones are polygons in a
+//! GeoJSON file, each with an "id" property.
+
+use rand::SeedableRng;
+use rand_xorshift::XorShiftRng;
+
+use abstutil::{CmdArgs, Timer};
+use map_model::Map;
+
+fn main() {
+    let mut args = CmdArgs::new();
+    let map = args.required("--map");
+    let zones = args.required("--zones");
+    let demand = args.required("--demand");
+    let scenario_name = args.required("--scenario_name");
+    let seed: u64 = args
+        .optional_parse("--rng_seed", |s| s.parse())
+        .unwrap_or(42);
+    args.done();
+
+    let mut timer = Timer::new("import origin-destination demand");
+    let map = Map::new(map, &mut timer);
+    let zones = popdat::read_zones(&zones, &map).unwrap();
+    let demand = popdat::read_demand(&demand).unwrap();
+    let mut rng = XorShiftRng::seed_from_u64(seed);
+    let (scenario, report) =
+        popdat::synthesize_od_demand(&scenario_name, &zones, &demand, &map, &mut rng).unwrap();
+    for line in report.describe() {
+        println!("{}", line);
+    }
+    scenario.save();
+}
@@ -0,0 +1,34 @@
+use rand::SeedableRng;
+use rand_xorshift::XorShiftRng;
+
+use abstutil::{CmdArgs, Timer};
+use geom::Duration;
+use sim::Scenario;
+
+fn main() {
+    let mut args = CmdArgs::new();
+    let input = args.required("--input");
+    let stddev = Duration::minutes(args.required("--stddev_minutes").parse().unwrap());
+    let seed: u64 = args.required("--rng").parse().unwrap();
+    let scenario_name = args.optional("--scenario_name");
+    args.done();
+
+    let mut rng = XorShiftRng::seed_from_u64(seed);
+    let orig: Scenario = abstio::read_binary(input, &mut Timer::throwaway());
+    // Use the original name as a prefix, so the scenario picker lists them together
+    let name = scenario_name.unwrap_or_else(|| {
+        format!(
+            "{}_jittered_{}m_seed{}",
+            orig.scenario_name,
+            stddev.inner_seconds() / 60.0,
+            seed
+        )
+    });
+    let mut scenario = orig.jitter_departures(stddev, &mut rng);
+    scenario.scenario_name = name;
+    scenario.save();
+    println!(
+        "Wrote {}",
+        abstio::path_scenario(&scenario.map_name, &scenario.scenario_name)
+    );
+}
@@ -0,0 +1,131 @@
+use anyhow::Result;
+
+use abstutil::{prettyprint_usize, Timer};
+use geom::{GPSBounds, LonLat, Polygon, Ring};
+use map_model::{BuildingType, Map};
+
+/// Reads census blocks from a GeoJSON file, with the number of people living in each as a
+/// `population` property. Each block's population is split between the residential buildings
+/// inside of it, proportional to their footprint. Blocks partly outside the map only contribute the
+/// share of their population matching the share of their area inside. Residential buildings
+/// outside of every block keep the number of residents guessed from OSM.
+pub fn distribute_residents(map: &mut Map, path: &str, timer: &mut Timer) {
+    let blocks = match read_blocks(path, map.get_gps_bounds()) {
+        Ok(blocks) => blocks,
+        Err(err) => {
+            panic!("Couldn't read census blocks from {}: {}", path, err);
+        }
+    };
+
+    let mut census_population = 0;
+    let mut assigned = 0;
+    let mut blocks_without_homes = 0;
+    timer.start_iter("distribute census blocks", blocks.len());
+    for (polygon, population) in blocks {
+        timer.next();
+        if !polygon.intersects(map.get_boundary_polygon()) {
+            continue;
+        }
+        let (inside_map, homes) = popdat::distribute_population_by_footprint(
+            geo::Polygon::from(polygon),
+            population,
+            map,
+        );
+        census_population += inside_map;
+        if homes.is_empty() && inside_map > 0 {
+            blocks_without_homes += 1;
+        }
+        for (home, n) in homes {
+            assigned += n;
+            let bldg_type = match map.get_b(home).bldg_type {
+                BuildingType::Residential {
+                    num_housing_units, ..
+                } => BuildingType::Residential {
+                    num_housing_units,
+                    num_residents: n,
+                },
+                BuildingType::ResidentialCommercial(_, worker_cap) => {
+                    BuildingType::ResidentialCommercial(n, worker_cap)
+                }
+                _ => unreachable!(),
+            };
+            map.hack_override_bldg_type(home, bldg_type);
+        }
+    }
+
+    info!(
+        "Assigned {} residents to buildings, out of a census population of {} inside the map",
+        prettyprint_usize(assigned),
+        prettyprint_usize(census_population)
+    );
+    if blocks_without_homes > 0 {
+        warn!(
+            "{} census blocks with people have no residential buildings, so nobody was assigned \
+             from them",
+            prettyprint_usize(blocks_without_homes)
+        );
+    }
+
+    map.save();
+}
+
+fn read_blocks(path: &str, gps_bounds: &GPSBounds) -> Result<Vec<(Polygon, usize)>> {
+    let bytes = abstio::slurp_file(path)?;
+    let geojson = std::str::from_utf8(&bytes)?.parse::<geojson::GeoJson>()?;
+    let collection = match geojson {
+        geojson::GeoJson::FeatureCollection(collection) => collection,
+        _ => bail!("{} isn't a FeatureCollection", path),
+    };
+
+    let mut blocks = Vec::new();
+    for feature in collection.features {
+        // Some sources store numbers as strings
+        let population = match feature
+            .properties
+            .as_ref()
+            .and_then(|props| props.get("population"))
+        {
+            Some(serde_json::Value::Number(n)) => n.as_f64().map(|x| x as usize),
+            Some(serde_json::Value::String(s)) => s.parse::<usize>().ok(),
+            _ => None,
+        }
+        .ok_or_else(|| anyhow!("a block in {} is missing a population", path))?;
+
+        // Only the outer ring of each polygon is used
+        let rings: Vec<Vec<Vec<f64>>> = match feature.geometry.map(|g| g.value) {
+            Some(geojson::Value::Polygon(mut rings)) => vec![rings.remove(0)],
+            Some(geojson::Value::MultiPolygon(polygons)) => polygons
+                .into_iter()
+                .map(|mut rings| rings.remove(0))
+                .collect(),
+            _ => {
+                warn!("Skipping a census block in {} that isn't a polygon", path);
+                continue;
+            }
+        };
+        // Split the population between the pieces of a MultiPolygon by area
+        let pieces: Vec<Polygon> = rings
+            .into_iter()
+            .filter_map(|raw_pts| {
+                let gps_pts: Vec<LonLat> = raw_pts
+                    .into_iter()
+                    .map(|pt| LonLat::new(pt[0], pt[1]))
+                    .collect();
+                // Blocks partly outside the map are still needed, so don't use try_convert
+                Ring::new(gps_bounds.convert(&gps_pts))
+                    .ok()
+                    .map(|ring| ring.to_polygon())
+            })
+            .collect();
+        let total_area: f64 = pieces.iter().map(|p| p.area()).sum();
+        for polygon in pieces {
+            let share = if total_area == 0.0 {
+                0.0
+            } else {
+                polygon.area() / total_area
+            };
+            blocks.push((polygon, (share * population as f64).round() as usize));
+        }
+    }
+    Ok(blocks)
+}
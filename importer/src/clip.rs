@@ -0,0 +1,360 @@
+use std::collections::{BTreeSet, HashMap};
+use std::fs::File;
+use std::hash::Hasher;
+use std::io::{BufRead, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use geo::algorithm::contains::Contains;
+
+use abstio::CityName;
+use abstutil::{prettyprint_usize, Fnv};
+use geom::LonLat;
+
+use crate::configuration::ImporterConfiguration;
+
+/// Clips one large .osm.pbf to the boundary of every map in a city, producing the same
+/// `osm/$map.osm` files that the rest of the pipeline reads.
+///
+/// Reading the huge extract is the slow part, so it's only done once, no matter how many maps
+/// there are. osmconvert decodes it into one stream of elements, and each element is copied into
+/// every map it belongs to:
+///
+/// - a node belongs to every map whose boundary contains it
+/// - a way belongs to every map with at least one of its nodes. Its nodes outside that boundary
+///   are added too, without their tags, so the way is complete.
+/// - a relation belongs to every map with at least one of its members
+///
+/// Each clipped file remembers the checksum of the extract and its boundary. If neither changed,
+/// the file is reused.
+pub fn clip_all(input: String, city: &CityName, maps: &[String], config: &ImporterConfiguration) {
+    let input_checksum = checksum_file(&input);
+    let mut stale = Vec::new();
+    for map in maps {
+        let output = city.input_path(format!("osm/{}.osm", map));
+        let key = format!(
+            "{:016x}_{:016x}",
+            input_checksum,
+            checksum_file(&poly_path(city, map))
+        );
+        let key_path = format!("{}.clip_key", output);
+        if Path::new(&output).exists()
+            && std::fs::read_to_string(&key_path).ok() == Some(key.clone())
+        {
+            continue;
+        }
+        stale.push((map, output, key_path, key));
+    }
+    println!(
+        "- {} of {} maps in {} need to be clipped from {}",
+        stale.len(),
+        maps.len(),
+        city.describe(),
+        input
+    );
+
+    if !stale.is_empty() {
+        let boundaries: Vec<Boundary> = stale
+            .iter()
+            .map(|(map, _, _, _)| Boundary::load(&poly_path(city, map)))
+            .collect();
+        let outputs: Vec<String> = stale
+            .iter()
+            .map(|(_, output, _, _)| output.clone())
+            .collect();
+        split_extract(&input, &boundaries, &outputs, config);
+        for (_, _, key_path, key) in stale {
+            std::fs::write(key_path, key).unwrap();
+        }
+    }
+
+    for map in maps {
+        let output = city.input_path(format!("osm/{}.osm", map));
+        let (nodes, ways, relations) = count_elements(&output);
+        println!(
+            "- {} has {} nodes, {} ways, and {} relations",
+            output,
+            prettyprint_usize(nodes),
+            prettyprint_usize(ways),
+            prettyprint_usize(relations)
+        );
+    }
+}
+
+/// One map's clipped .osm file, while it's being written.
+struct Clipped {
+    file: BufWriter<File>,
+    // Nodes are written as soon as they're read. Everything else has to wait until the nodes that
+    // finish the ways are known.
+    ways: String,
+    relations: String,
+    /// Nodes outside the boundary that ways inside it use
+    extra_nodes: BTreeSet<i64>,
+}
+
+fn split_extract(
+    input: &str,
+    boundaries: &[Boundary],
+    outputs: &[String],
+    config: &ImporterConfiguration,
+) {
+    let mut clipped: Vec<Clipped> = outputs
+        .iter()
+        .map(|path| {
+            std::fs::create_dir_all(Path::new(path).parent().unwrap())
+                .expect("Creating parent dir failed");
+            let mut file = BufWriter::new(File::create(path).unwrap());
+            writeln!(file, "<?xml version='1.0' encoding='UTF-8'?>").unwrap();
+            writeln!(file, "<osm version=\"0.6\" generator=\"importer\">").unwrap();
+            Clipped {
+                file,
+                ways: String::new(),
+                relations: String::new(),
+                extra_nodes: BTreeSet::new(),
+            }
+        })
+        .collect();
+
+    // The position of every node, even ones outside all of the boundaries, since a way might need
+    // them. They're stored in fixed-point like OSM does, to keep this small.
+    let mut positions: Vec<(i64, i32, i32)> = Vec::new();
+    // Which maps each object belongs to. Objects in no map are left out.
+    let mut node_maps: HashMap<i64, Vec<usize>> = HashMap::new();
+    let mut way_maps: HashMap<i64, Vec<usize>> = HashMap::new();
+    let mut relation_maps: HashMap<i64, Vec<usize>> = HashMap::new();
+
+    println!("- Reading {} once for {} maps", input, outputs.len());
+    let mut cmd = Command::new(&config.osmconvert);
+    cmd.arg(input).arg("--out-osm").stdout(Stdio::piped());
+    let mut child = cmd
+        .spawn()
+        .unwrap_or_else(|err| panic!("Failed to run {:?}: {:?}", cmd, err));
+    let mut lines = BufReader::new(child.stdout.take().unwrap()).lines();
+    while let Some(line) = lines.next() {
+        let line = line.unwrap();
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("<node") {
+            let element = read_element(line.clone(), &mut lines, "</node>");
+            let id = attr(&line, "id").parse::<i64>().unwrap();
+            let lon = attr(&line, "lon").parse::<f64>().unwrap();
+            let lat = attr(&line, "lat").parse::<f64>().unwrap();
+            positions.push((id, to_fixed(lon), to_fixed(lat)));
+
+            let maps: Vec<usize> = (0..boundaries.len())
+                .filter(|idx| boundaries[*idx].contains(lon, lat))
+                .collect();
+            for idx in &maps {
+                clipped[*idx].file.write_all(element.as_bytes()).unwrap();
+            }
+            if !maps.is_empty() {
+                node_maps.insert(id, maps);
+            }
+        } else if trimmed.starts_with("<way") {
+            let element = read_element(line.clone(), &mut lines, "</way>");
+            let nodes: Vec<i64> = element
+                .lines()
+                .filter(|l| l.trim_start().starts_with("<nd "))
+                .map(|l| attr(l, "ref").parse::<i64>().unwrap())
+                .collect();
+            let mut maps = Vec::new();
+            for n in &nodes {
+                add_maps(&mut maps, node_maps.get(n));
+            }
+            for idx in &maps {
+                clipped[*idx].ways.push_str(&element);
+                for n in &nodes {
+                    if !node_maps.get(n).map(|m| m.contains(idx)).unwrap_or(false) {
+                        clipped[*idx].extra_nodes.insert(*n);
+                    }
+                }
+            }
+            if !maps.is_empty() {
+                way_maps.insert(attr(&line, "id").parse::<i64>().unwrap(), maps);
+            }
+        } else if trimmed.starts_with("<relation") {
+            let element = read_element(line.clone(), &mut lines, "</relation>");
+            let mut maps = Vec::new();
+            for l in element.lines() {
+                if !l.trim_start().starts_with("<member ") {
+                    continue;
+                }
+                let member = attr(l, "ref").parse::<i64>().unwrap();
+                add_maps(
+                    &mut maps,
+                    match attr(l, "type") {
+                        "node" => node_maps.get(&member),
+                        "way" => way_maps.get(&member),
+                        // Relations usually come after the ones they contain
+                        _ => relation_maps.get(&member),
+                    },
+                );
+            }
+            for idx in &maps {
+                clipped[*idx].relations.push_str(&element);
+            }
+            if !maps.is_empty() {
+                relation_maps.insert(attr(&line, "id").parse::<i64>().unwrap(), maps);
+            }
+        }
+    }
+    let status = child.wait().unwrap();
+    if !status.success() {
+        panic!("{:?} failed", cmd);
+    }
+
+    // Extracts are normally sorted by ID already
+    positions.sort_unstable();
+    for c in clipped {
+        let mut file = c.file;
+        for id in c.extra_nodes {
+            // Skip missing nodes, like the rest of the pipeline
+            if let Ok(idx) = positions.binary_search_by_key(&id, |(node, _, _)| *node) {
+                let (_, lon, lat) = positions[idx];
+                writeln!(
+                    file,
+                    "\t<node id=\"{}\" lat=\"{}\" lon=\"{}\"/>",
+                    id,
+                    from_fixed(lat),
+                    from_fixed(lon)
+                )
+                .unwrap();
+            }
+        }
+        file.write_all(c.ways.as_bytes()).unwrap();
+        file.write_all(c.relations.as_bytes()).unwrap();
+        writeln!(file, "</osm>").unwrap();
+        file.flush().unwrap();
+    }
+}
+
+// Returns the lines of an element starting on this line, each ending with a newline
+fn read_element<I: Iterator<Item = std::io::Result<String>>>(
+    first: String,
+    lines: &mut I,
+    closing: &str,
+) -> String {
+    let mut element = first;
+    element.push('\n');
+    if element.trim_end().ends_with("/>") {
+        return element;
+    }
+    for line in lines {
+        let line = line.unwrap();
+        element.push_str(&line);
+        element.push('\n');
+        if line.trim_start().starts_with(closing) {
+            break;
+        }
+    }
+    element
+}
+
+// Finds the value of an attribute in one line of osmconvert's output
+fn attr<'a>(line: &'a str, key: &str) -> &'a str {
+    let prefix = format!(" {}=\"", key);
+    let start = line
+        .find(&prefix)
+        .unwrap_or_else(|| panic!("No {} in {}", key, line))
+        + prefix.len();
+    let len = line[start..].find('"').unwrap();
+    &line[start..start + len]
+}
+
+fn add_maps(all: &mut Vec<usize>, maps: Option<&Vec<usize>>) {
+    for idx in maps.into_iter().flatten() {
+        if !all.contains(idx) {
+            all.push(*idx);
+        }
+    }
+}
+
+// OSM stores coordinates with 7 decimal places
+fn to_fixed(x: f64) -> i32 {
+    (x * 1e7).round() as i32
+}
+
+fn from_fixed(x: i32) -> String {
+    format!("{:.7}", (x as f64) / 1e7)
+}
+
+struct Boundary {
+    polygon: geo::Polygon<f64>,
+    // Most of the extract is far from every boundary, so check this first
+    min: LonLat,
+    max: LonLat,
+}
+
+impl Boundary {
+    fn load(path: &str) -> Boundary {
+        let pts = LonLat::read_osmosis_polygon(path).unwrap();
+        let mut min_lon = f64::MAX;
+        let mut min_lat = f64::MAX;
+        let mut max_lon = f64::MIN;
+        let mut max_lat = f64::MIN;
+        for pt in &pts {
+            min_lon = min_lon.min(pt.x());
+            min_lat = min_lat.min(pt.y());
+            max_lon = max_lon.max(pt.x());
+            max_lat = max_lat.max(pt.y());
+        }
+        Boundary {
+            polygon: geo::Polygon::new(
+                pts.iter()
+                    .map(|pt| (pt.x(), pt.y()))
+                    .collect::<Vec<_>>()
+                    .into(),
+                Vec::new(),
+            ),
+            min: LonLat::new(min_lon, min_lat),
+            max: LonLat::new(max_lon, max_lat),
+        }
+    }
+
+    fn contains(&self, lon: f64, lat: f64) -> bool {
+        lon >= self.min.x()
+            && lon <= self.max.x()
+            && lat >= self.min.y()
+            && lat <= self.max.y()
+            && self.polygon.contains(&geo::Point::new(lon, lat))
+    }
+}
+
+fn poly_path(city: &CityName, map: &str) -> String {
+    format!(
+        "importer/config/{}/{}/{}.poly",
+        city.country, city.city, map
+    )
+}
+
+// Counts the top-level elements of a .osm file written by osmconvert, which puts each on its own
+// line.
+fn count_elements(path: &str) -> (usize, usize, usize) {
+    let mut nodes = 0;
+    let mut ways = 0;
+    let mut relations = 0;
+    for line in BufReader::new(File::open(path).unwrap()).lines() {
+        let line = line.unwrap();
+        let line = line.trim_start();
+        if line.starts_with("<node") {
+            nodes += 1;
+        } else if line.starts_with("<way") {
+            ways += 1;
+        } else if line.starts_with("<relation") {
+            relations += 1;
+        }
+    }
+    (nodes, ways, relations)
+}
+
+fn checksum_file(path: &str) -> u64 {
+    let mut f = BufReader::new(File::open(path).unwrap());
+    let mut buffer = vec![0; 1 << 20];
+    let mut hasher = Fnv::new();
+    loop {
+        let n = f.read(&mut buffer).unwrap();
+        if n == 0 {
+            return hasher.finish();
+        }
+        hasher.write(&buffer[..n]);
+    }
+}
@@ -1,6 +1,6 @@
 use serde::{Deserialize, Serialize};
 
-use abstio::MapName;
+use abstio::{CityName, MapName};
 use map_model::raw::RawMap;
 
 use crate::configuration::ImporterConfiguration;
@@ -9,8 +9,7 @@ use crate::utils::{download, osmconvert};
 /// Importing a new city can be done just by filling out this config file and specifying some
 /// polygon boundaries. Most fields are directly from `convert_osm::Options`.
 ///
-/// If any extra data is imported for a city (like collisions or population), then for now, don't
-/// use this.
+/// If any extra data is imported for a city (like collisions), then for now, don't use this.
 #[derive(Serialize, Deserialize)]
 pub struct GenericCityImporter {
     /// The URL to a .osm or .osm.pbf file containing the entire city.
@@ -25,13 +24,18 @@ pub struct GenericCityImporter {
     pub onstreet_parking: convert_osm::OnstreetParking,
     pub public_offstreet_parking: convert_osm::PublicOffstreetParking,
     pub private_offstreet_parking: convert_osm::PrivateOffstreetParking,
-    /// If provided, pull elevation data from this SRTM file. The SRTM parser is incorrect, so the
-    /// results will be nonsense.
+    /// If provided, pull elevation data from this SRTM .hgt file, or a directory of them.
     pub elevation: Option<String>,
     /// OSM railway=rail will be included as light rail if so. Cosmetic only.
     pub include_railroads: bool,
     /// If provided, read polygons from this GeoJSON file and add them to the RawMap as buildings.
     pub extra_buildings: Option<String>,
+    /// The URL to a GTFS .zip feed covering the city. If provided, bus routes and their schedules
+    /// come from here instead of OSM.
+    pub gtfs_url: Option<String>,
+    /// If provided, read census blocks from this GeoJSON file and split the `population` of each
+    /// one between the residential buildings inside it.
+    pub census_blocks: Option<String>,
 }
 
 impl GenericCityImporter {
@@ -41,8 +45,27 @@ impl GenericCityImporter {
         timer: &mut abstutil::Timer,
         config: &ImporterConfiguration,
     ) -> RawMap {
-        let local_osm_file = if self.osm_url.starts_with("http") {
-            let file = name.city.input_path(format!(
+        osmconvert(
+            self.download_osm(&name.city, config),
+            format!(
+                "importer/config/{}/{}/{}.poly",
+                name.city.country, name.city.city, name.map
+            ),
+            name.city.input_path(format!("osm/{}.osm", name.map)),
+            config,
+        );
+
+        self.download_gtfs(&name, config);
+        let map = convert_osm::convert(self.options(&name), timer);
+        map.save();
+        map
+    }
+
+    /// Returns the path to the .osm or .osm.pbf file for the entire city, downloading it if
+    /// needed.
+    pub fn download_osm(&self, city: &CityName, config: &ImporterConfiguration) -> String {
+        if self.osm_url.starts_with("http") {
+            let file = city.input_path(format!(
                 "osm/{}",
                 std::path::Path::new(&self.osm_url)
                     .file_name()
@@ -55,38 +78,34 @@ impl GenericCityImporter {
             file
         } else {
             self.osm_url.clone()
-        };
+        }
+    }
 
-        osmconvert(
-            local_osm_file,
-            format!(
+    /// The options for converting one map's clipped .osm file to a RawMap.
+    pub fn options(&self, name: &MapName) -> convert_osm::Options {
+        convert_osm::Options {
+            osm_input: name.city.input_path(format!("osm/{}.osm", name.map)),
+            name: name.clone(),
+
+            clip: Some(format!(
                 "importer/config/{}/{}/{}.poly",
                 name.city.country, name.city.city, name.map
-            ),
-            name.city.input_path(format!("osm/{}.osm", name.map)),
-            config,
-        );
-
-        let map = convert_osm::convert(
-            convert_osm::Options {
-                osm_input: name.city.input_path(format!("osm/{}.osm", name.map)),
-                name: name.clone(),
+            )),
+            map_config: self.map_config.clone(),
+            onstreet_parking: self.onstreet_parking.clone(),
+            public_offstreet_parking: self.public_offstreet_parking.clone(),
+            private_offstreet_parking: self.private_offstreet_parking.clone(),
+            elevation: self.elevation.clone(),
+            include_railroads: self.include_railroads,
+            extra_buildings: self.extra_buildings.clone(),
+            gtfs: self.gtfs_url.as_ref().map(|_| name.city.input_path("gtfs")),
+        }
+    }
 
-                clip: Some(format!(
-                    "importer/config/{}/{}/{}.poly",
-                    name.city.country, name.city.city, name.map
-                )),
-                map_config: self.map_config.clone(),
-                onstreet_parking: self.onstreet_parking.clone(),
-                public_offstreet_parking: self.public_offstreet_parking.clone(),
-                private_offstreet_parking: self.private_offstreet_parking.clone(),
-                elevation: self.elevation.clone(),
-                include_railroads: self.include_railroads,
-                extra_buildings: self.extra_buildings.clone(),
-            },
-            timer,
-        );
-        map.save();
-        map
+    /// If the city has a GTFS feed, downloads and unzips it, unless that's already happened.
+    pub fn download_gtfs(&self, name: &MapName, config: &ImporterConfiguration) {
+        if let Some(ref url) = self.gtfs_url {
+            download(config, name.city.input_path("gtfs/"), url);
+        }
     }
 }
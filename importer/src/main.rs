@@ -14,6 +14,8 @@ use configuration::{load_configuration, ImporterConfiguration};
 use dependencies::are_dependencies_callable;
 
 mod berlin;
+mod census;
+mod clip;
 mod configuration;
 mod dependencies;
 mod generic;
@@ -21,6 +23,7 @@ mod seattle;
 #[cfg(feature = "scenarios")]
 mod soundcast;
 mod uk;
+mod update;
 mod utils;
 
 // TODO Might be cleaner to express as a dependency graph?
@@ -56,7 +59,7 @@ fn main() {
     }
 
     // Otherwise, we're just operating on a single city.
-    let job = Job {
+    let mut job = Job {
         city: match args.optional("--city") {
             Some(x) => CityName::parse(&x).unwrap(),
             None => CityName::new("us", "seattle"),
@@ -73,9 +76,21 @@ fn main() {
         // Only process one map. If not specified, process all maps defined by clipping polygons in
         // importer/config/$city/.
         only_map: args.optional_free(),
+
+        // Re-import from a newer .osm.pbf for the whole city, only redoing what changed.
+        update_from: args.optional("--update"),
     };
     args.done();
 
+    if job.update_from.is_some() {
+        if job.city == CityName::new("us", "seattle") {
+            println!("--update doesn't work for Seattle yet; use --raw --map");
+            std::process::exit(1);
+        }
+        job.osm_to_raw = true;
+        job.raw_to_map = true;
+    }
+
     if !job.osm_to_raw && !job.raw_to_map && !job.scenario && !job.city_overview {
         println!(
             "Nothing to do! Pass some combination of --raw, --map, --scenario, --city_overview, \
@@ -105,6 +120,7 @@ fn regenerate_everything(config: ImporterConfiguration) {
             scenario: false,
             city_overview: false,
             only_map: None,
+            update_from: None,
         };
         // Only some maps run extra tasks
         if city == CityName::new("us", "seattle") {
@@ -134,6 +150,8 @@ struct Job {
     city_overview: bool,
 
     only_map: Option<String>,
+    /// A newer version of the city's .osm.pbf
+    update_from: Option<String>,
 }
 
 impl Job {
@@ -179,27 +197,59 @@ impl Job {
             (None, None)
         };
 
+        // Seattle is still special-cased; every other city is described by a config file
+        let city_cfg = if self.city == CityName::new("us", "seattle") {
+            None
+        } else {
+            match abstio::maybe_read_json::<generic::GenericCityImporter>(
+                format!(
+                    "importer/config/{}/{}/cfg.json",
+                    self.city.country, self.city.city
+                ),
+                timer,
+            ) {
+                Ok(city_cfg) => {
+                    let expected = map_model::DrivingSide::for_country(&self.city.country);
+                    if city_cfg.map_config.driving_side != expected {
+                        warn!(
+                            "{} is configured to drive on the {:?}, but {} usually drives on the \
+                             {:?}",
+                            self.city.describe(),
+                            city_cfg.map_config.driving_side,
+                            self.city.country,
+                            expected
+                        );
+                    }
+                    Some(city_cfg)
+                }
+                Err(err) => {
+                    panic!("Can't import city {}: {}", self.city.describe(), err);
+                }
+            }
+        };
+
+        // Read the city's huge OSM extract once for all of the maps, instead of once per map
+        if self.osm_to_raw && self.update_from.is_none() {
+            if let Some(ref city_cfg) = city_cfg {
+                let input = city_cfg.download_osm(&self.city, config);
+                timer.start(format!("clip OSM data for {}", self.city.describe()));
+                clip::clip_all(input, &self.city, &names, config);
+                timer.stop(format!("clip OSM data for {}", self.city.describe()));
+            }
+        }
+
         for name in names {
             if self.osm_to_raw {
-                // Still special-cased
-                if self.city == CityName::new("us", "seattle") {
-                    seattle::osm_to_raw(&name, timer, config);
-                } else {
-                    let raw = match abstio::maybe_read_json::<generic::GenericCityImporter>(
-                        format!(
-                            "importer/config/{}/{}/cfg.json",
-                            self.city.country, self.city.city
-                        ),
-                        timer,
-                    ) {
-                        Ok(city_cfg) => city_cfg.osm_to_raw(
-                            MapName::from_city(&self.city, &name),
-                            timer,
-                            config,
-                        ),
-                        Err(err) => {
-                            panic!("Can't import city {}: {}", self.city.describe(), err);
+                if let Some(ref city_cfg) = city_cfg {
+                    let map_name = MapName::from_city(&self.city, &name);
+                    let raw = if let Some(ref new_osm) = self.update_from {
+                        match update::osm_to_raw(city_cfg, map_name, new_osm, timer, config) {
+                            Some(raw) => raw,
+                            // Leave the existing map untouched
+                            None => continue,
                         }
+                    } else {
+                        city_cfg.osm_to_raw(map_name, timer, config)
                     };
 
                     if self.city == CityName::new("de", "berlin") {
@@ -209,6 +259,8 @@ impl Job {
                     } else if self.city == CityName::new("gb", "london") {
                         uk::import_extra_data(&raw, config, timer);
                     }
+                } else {
+                    seattle::osm_to_raw(&name, timer, config);
                 }
             }
             let name = MapName::from_city(&self.city, &name);
@@ -231,6 +283,17 @@ impl Job {
                     timer.start(format!("add GTFS schedules for {}", name.describe()));
                     seattle::add_gtfs_schedules(&mut map);
                     timer.stop(format!("add GTFS schedules for {}", name.describe()));
+                } else if let Some(path) = city_cfg.as_ref().and_then(|c| c.census_blocks.as_ref())
+                {
+                    timer.start(format!(
+                        "distribute census population for {}",
+                        name.describe()
+                    ));
+                    census::distribute_residents(&mut map, path, timer);
+                    timer.stop(format!(
+                        "distribute census population for {}",
+                        name.describe()
+                    ));
                 }
 
                 Some(map)
@@ -323,6 +386,7 @@ fn oneshot(
             elevation: None,
             include_railroads: true,
             extra_buildings: None,
+            gtfs: None,
         },
         &mut timer,
     );
@@ -144,6 +144,7 @@ pub fn osm_to_raw(name: &str, timer: &mut Timer, config: &ImporterConfiguration)
             // They mess up 16th and E Marginal badly enough to cause gridlock.
             include_railroads: false,
             extra_buildings: None,
+            gtfs: None,
         },
         timer,
     );
@@ -0,0 +1,87 @@
+use std::path::Path;
+
+use abstio::MapName;
+use abstutil::Timer;
+use map_model::raw::RawMap;
+
+use crate::configuration::ImporterConfiguration;
+use crate::generic::GenericCityImporter;
+use crate::utils::osmconvert;
+
+/// Imports one map from a newer OSM extract of the whole city, reusing the previous RawMap when
+/// only a few roads were retagged. Returns None if nothing relevant changed, leaving all of the
+/// existing files alone.
+pub fn osm_to_raw(
+    city_cfg: &GenericCityImporter,
+    name: MapName,
+    new_osm: &str,
+    timer: &mut Timer,
+    config: &ImporterConfiguration,
+) -> Option<RawMap> {
+    let clipping_polygon = format!(
+        "importer/config/{}/{}/{}.poly",
+        name.city.country, name.city.city, name.map
+    );
+    let old_clipped = name.city.input_path(format!("osm/{}.osm", name.map));
+    let new_clipped = name
+        .city
+        .input_path(format!("osm/{}.updated.osm", name.map));
+    // osmconvert skips existing files, and this might be left over from a failed run
+    if Path::new(&new_clipped).exists() {
+        std::fs::remove_file(&new_clipped).unwrap();
+    }
+    osmconvert(
+        new_osm.to_string(),
+        clipping_polygon,
+        new_clipped.clone(),
+        config,
+    );
+
+    let raw_path = abstio::path_raw_map(&name);
+    let reason = if !Path::new(&old_clipped).exists() || !Path::new(&raw_path).exists() {
+        "it hasn't been imported before".to_string()
+    } else {
+        // When the RawMap format changes (like when buildings gained elevation), the old file
+        // can't be read anymore, and everything has to be imported again.
+        match abstio::maybe_read_binary::<RawMap>(raw_path, timer) {
+            Err(err) => format!("the old raw map is in an outdated format ({})", err),
+            Ok(mut raw) => {
+                let mut opts = city_cfg.options(&name);
+                opts.osm_input = new_clipped.clone();
+                timer.start(format!("compare OSM data for {}", name.describe()));
+                let result = convert_osm::update(&mut raw, &old_clipped, &opts, timer);
+                timer.stop(format!("compare OSM data for {}", name.describe()));
+                match result {
+                    Ok(convert_osm::Update::Unchanged) => {
+                        println!("- {} hasn't changed", name.describe());
+                        std::fs::remove_file(&new_clipped).unwrap();
+                        return None;
+                    }
+                    Ok(convert_osm::Update::PatchedRoads(ways)) => {
+                        println!(
+                            "- Only the tags of {} roads in {} changed, patching them",
+                            ways.len(),
+                            name.describe()
+                        );
+                        std::fs::rename(&new_clipped, &old_clipped).unwrap();
+                        raw.save();
+                        return Some(raw);
+                    }
+                    Ok(convert_osm::Update::NeedsFullRebuild(reason)) => reason,
+                    Err(err) => format!("comparing failed: {}", err),
+                }
+            }
+        }
+    };
+
+    println!(
+        "- Regenerating all of {}, because {}",
+        name.describe(),
+        reason
+    );
+    std::fs::rename(&new_clipped, &old_clipped).unwrap();
+    city_cfg.download_gtfs(&name, config);
+    let map = convert_osm::convert(city_cfg.options(&name), timer);
+    map.save();
+    Some(map)
+}
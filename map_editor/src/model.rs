@@ -487,6 +487,7 @@ impl Model {
                 public_garage_name: None,
                 num_parking_spots: 0,
                 amenities: Vec::new(),
+                elevation: Distance::ZERO,
             },
         );
         self.bldg_added(id, ctx);
@@ -1,6 +1,11 @@
 //! A color scheme groups colors used for different map, dynamic, and UI elements in one place, to
 //! encourage deduplication. The player can also switch between different color schemes.
 
+use std::collections::BTreeMap;
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
 use map_model::osm::RoadRank;
 use map_model::LaneType;
 use widgetry::{Choice, Color, EventCtx, Fill, Style, Texture};
@@ -18,7 +23,7 @@ use crate::tools::{loading_tips, ColorScale};
 //
 // TODO There are plenty of colors left that aren't captured here. :(
 
-#[derive(Clone, Copy, PartialEq, Debug)]
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
 pub enum ColorSchemeChoice {
     DayMode,
     NightMode,
@@ -35,6 +40,17 @@ pub enum ColorSchemeChoice {
 }
 
 impl ColorSchemeChoice {
+    /// Individual colors of a scheme can be overridden by a JSON file here, mapping the name of a
+    /// color to "#RRGGBB" or "#RRGGBBAA".
+    pub fn overrides_path(self) -> String {
+        let label = ColorSchemeChoice::choices()
+            .into_iter()
+            .find(|c| c.data == self)
+            .unwrap()
+            .label;
+        abstio::path_player(format!("colors/{}.json", label.replace(' ', "_")))
+    }
+
     pub fn choices() -> Vec<Choice<ColorSchemeChoice>> {
         vec![
             Choice::new("day mode", ColorSchemeChoice::DayMode),
@@ -157,7 +173,66 @@ pub struct ColorScheme {
 }
 
 impl ColorScheme {
-    pub fn new(ctx: &mut EventCtx, scheme: ColorSchemeChoice) -> ColorScheme {
+    /// Colors for the map and UI come from one scheme. Agents and the overlays drawn on top of the
+    /// map can optionally come from another.
+    pub fn new(
+        ctx: &mut EventCtx,
+        scheme: ColorSchemeChoice,
+        agents: Option<ColorSchemeChoice>,
+    ) -> ColorScheme {
+        let load = |scheme| {
+            ColorScheme::load(scheme, None).unwrap_or_else(|err| {
+                warn!("Ignoring color overrides for {:?}: {}", scheme, err);
+                ColorScheme::builtin(scheme)
+            })
+        };
+        let mut cs = load(scheme);
+        if let Some(agents) = agents {
+            cs.take_agent_colors(&load(agents));
+        }
+        ctx.set_style(cs.gui_style.clone());
+        cs
+    }
+
+    /// Like `new`, but fails if there are overrides for either scheme that can't be parsed.
+    /// Doesn't change the UI style.
+    pub fn load(
+        scheme: ColorSchemeChoice,
+        agents: Option<ColorSchemeChoice>,
+    ) -> Result<ColorScheme> {
+        let mut cs = ColorScheme::builtin(scheme);
+        for (name, value) in ColorScheme::read_overrides(scheme)? {
+            let color = parse_hex(&value)?;
+            match cs.named_color(&name) {
+                Some(x) => {
+                    *x = color;
+                }
+                None => bail!("Unknown color {}", name),
+            }
+        }
+        if let Some(agents) = agents {
+            cs.take_agent_colors(&ColorScheme::load(agents, None)?);
+        }
+        Ok(cs)
+    }
+
+    /// Ask the UI to use this scheme's style.
+    pub fn set_style(&self, ctx: &mut EventCtx) {
+        ctx.set_style(self.gui_style.clone());
+    }
+
+    /// The raw contents of the overrides file for a scheme, without checking that the names or
+    /// colors are valid. Empty if there's no file.
+    pub fn read_overrides(scheme: ColorSchemeChoice) -> Result<BTreeMap<String, String>> {
+        let path = scheme.overrides_path();
+        if !abstio::file_exists(path.clone()) {
+            return Ok(BTreeMap::new());
+        }
+        abstutil::from_json(&abstio::slurp_file(path)?)
+    }
+
+    /// A scheme without any overrides applied.
+    pub fn builtin(scheme: ColorSchemeChoice) -> ColorScheme {
         let mut cs = match scheme {
             ColorSchemeChoice::DayMode => ColorScheme::day_mode(),
             ColorSchemeChoice::NightMode => ColorScheme::night_mode(),
@@ -173,10 +248,106 @@ impl ColorScheme {
             ColorSchemeChoice::NegativeSpace => ColorScheme::negative_space(),
         };
         cs.scheme = scheme;
-        ctx.set_style(cs.gui_style.clone());
         cs
     }
 
+    // The colors that can be overridden by name. Everything else is either derived from these or
+    // not a plain color.
+    fn named_colors(&mut self) -> Vec<(&'static str, &mut Color)> {
+        macro_rules! named {
+            ($($field:ident),*) => {
+                vec![$((stringify!($field), &mut self.$field)),*]
+            };
+        }
+        named!(
+            panel_bg,
+            section_bg,
+            inner_panel,
+            day_time_slider,
+            night_time_slider,
+            selected,
+            current_object,
+            perma_selected_object,
+            bottom_bar_id,
+            bottom_bar_name,
+            fade_map_dark,
+            dialog_bg,
+            minimap_cursor_border,
+            minimap_selected_zoom,
+            minimap_unselected_zoom,
+            driving_lane,
+            bus_lane,
+            parking_lane,
+            bike_lane,
+            sidewalk,
+            general_road_marking,
+            road_center_line,
+            light_rail_track,
+            private_road,
+            unzoomed_highway,
+            unzoomed_arterial,
+            unzoomed_residential,
+            unzoomed_trail,
+            normal_intersection,
+            stop_sign,
+            stop_sign_pole,
+            signal_protected_turn,
+            signal_permitted_turn,
+            signal_banned_turn,
+            signal_box,
+            signal_spinner,
+            signal_turn_block_bg,
+            very_slow_intersection,
+            slow_intersection,
+            normal_slow_intersection,
+            void_background,
+            unzoomed_interesting_intersection,
+            residential_building,
+            commercial_building,
+            building_outline,
+            parking_lot,
+            unzoomed_car,
+            unzoomed_bike,
+            unzoomed_bus,
+            unzoomed_pedestrian,
+            route,
+            turn_arrow,
+            brake_light,
+            bus_body,
+            bus_label,
+            train_body,
+            ped_head,
+            ped_foot,
+            ped_preparing_bike_body,
+            ped_crowd,
+            bike_frame,
+            parked_car,
+            bus_layer,
+            edits_layer,
+            parking_trip,
+            bike_trip,
+            bus_trip,
+            before_changes,
+            after_changes
+        )
+    }
+
+    fn named_color(&mut self, name: &str) -> Option<&mut Color> {
+        self.named_colors()
+            .into_iter()
+            .find(|(x, _)| *x == name)
+            .map(|(_, color)| color)
+    }
+
+    /// Every color that can be overridden, in the same format as the overrides file.
+    // Only mutable because named_colors hands out colors to change
+    pub fn overridable_colors(&mut self) -> BTreeMap<String, String> {
+        self.named_colors()
+            .into_iter()
+            .map(|(name, color)| (name.to_string(), format_hex(*color)))
+            .collect()
+    }
+
     fn day_mode() -> ColorScheme {
         let mut gui_style = Style::standard();
         gui_style.loading_tips = loading_tips();
@@ -377,6 +548,32 @@ impl ColorScheme {
     pub fn solid_road_center(&self) -> bool {
         self.scheme == ColorSchemeChoice::FadedZoom
     }
+
+    // Agents and overlays can use a different scheme than the map; these are the colors that
+    // switch over. Nothing rendered in DrawMap may depend on them.
+    fn take_agent_colors(&mut self, other: &ColorScheme) {
+        self.unzoomed_car = other.unzoomed_car;
+        self.unzoomed_bike = other.unzoomed_bike;
+        self.unzoomed_bus = other.unzoomed_bus;
+        self.unzoomed_pedestrian = other.unzoomed_pedestrian;
+
+        self.agent_colors = other.agent_colors.clone();
+        self.route = other.route;
+        self.turn_arrow = other.turn_arrow;
+        self.brake_light = other.brake_light;
+        self.bus_body = other.bus_body;
+        self.bus_label = other.bus_label;
+        self.train_body = other.train_body;
+        self.ped_head = other.ped_head;
+        self.ped_foot = other.ped_foot;
+        self.ped_preparing_bike_body = other.ped_preparing_bike_body;
+        self.ped_crowd = other.ped_crowd;
+        self.bike_frame = other.bike_frame;
+        self.parked_car = other.parked_car;
+
+        self.good_to_bad_red = other.good_to_bad_red.clone();
+        self.good_to_bad_green = other.good_to_bad_green.clone();
+    }
 }
 
 fn modulo_color(colors: &Vec<Color>, idx: usize) -> Color {
@@ -388,6 +585,39 @@ fn hex(x: &str) -> Color {
     Color::hex(x)
 }
 
+// Unlike Color::hex, doesn't crash on bad input
+fn parse_hex(raw: &str) -> Result<Color> {
+    if !raw.starts_with('#') || !(raw.len() == 7 || raw.len() == 9) || !raw.is_ascii() {
+        bail!("{} isn't #RRGGBB or #RRGGBBAA", raw);
+    }
+    let component = |idx: usize| -> Result<f32> {
+        Ok(u8::from_str_radix(&raw[idx..idx + 2], 16)? as f32 / 255.0)
+    };
+    let alpha = if raw.len() == 9 { component(7)? } else { 1.0 };
+    Ok(Color::rgba_f(
+        component(1)?,
+        component(3)?,
+        component(5)?,
+        alpha,
+    ))
+}
+
+// The inverse of parse_hex. Only includes alpha if the color isn't opaque.
+fn format_hex(c: Color) -> String {
+    let component = |x: f32| (x * 255.0).round() as u8;
+    let rgb = format!(
+        "#{:02X}{:02X}{:02X}",
+        component(c.r),
+        component(c.g),
+        component(c.b)
+    );
+    if c.a < 1.0 {
+        format!("{}{:02X}", rgb, component(c.a))
+    } else {
+        rgb
+    }
+}
+
 // Alternate, in-progress schemes
 impl ColorScheme {
     // Shamelessly adapted from https://github.com/Uriopass/Egregoria
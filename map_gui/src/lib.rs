@@ -5,10 +5,12 @@ extern crate anyhow;
 #[macro_use]
 extern crate log;
 
+use anyhow::Result;
+
 use abstutil::Timer;
 use geom::{Duration, Pt2D, Time};
 use map_model::{AreaID, BuildingID, BusStopID, IntersectionID, LaneID, Map, ParkingLotID, RoadID};
-use sim::{AgentID, CarID, PedestrianID, Sim};
+use sim::{AgentID, CarID, PedestrianID, Sim, TripID};
 use widgetry::{EventCtx, GfxCtx, State};
 
 pub use self::simple_app::SimpleApp;
@@ -24,17 +26,37 @@ pub mod render;
 mod simple_app;
 pub mod tools;
 
-/// An application wishing to use the tools in this crate has to implement this on the struct that
-/// implements `widgetry::SharedAppState`, so that the tools here can access the map. See
-/// `SimpleApp` for an example implementation.
-pub trait AppLike {
+/// Everything the renderables need to draw a map and simulation. Drawing never changes the app, so
+/// this can be implemented for read-only views, like the second map in a split screen.
+pub trait ReadOnlyAppLike {
     fn map(&self) -> &Map;
     fn sim(&self) -> &Sim;
     fn cs(&self) -> &ColorScheme;
-    fn mut_cs(&mut self) -> &mut ColorScheme;
     fn draw_map(&self) -> &DrawMap;
-    fn mut_draw_map(&mut self) -> &mut DrawMap;
     fn opts(&self) -> &Options;
+
+    // These two are needed to render traffic signals. Splitting them from sim() allows
+    // applications that don't run a traffic sim to work.
+    fn sim_time(&self) -> Time {
+        self.sim().time()
+    }
+    fn current_stage_and_remaining_time(&self, id: IntersectionID) -> (usize, Duration) {
+        self.sim().current_stage_and_remaining_time(id)
+    }
+
+    /// How long a trip took in some baseline simulation, to compare against the current one.
+    /// Applications without a baseline don't need to implement this.
+    fn baseline_trip_time(&self, _: TripID) -> Option<Duration> {
+        None
+    }
+}
+
+/// An application wishing to use the tools in this crate has to implement this on the struct that
+/// implements `widgetry::SharedAppState`, so that the tools here can access the map. See
+/// `SimpleApp` for an example implementation.
+pub trait AppLike: ReadOnlyAppLike {
+    fn mut_cs(&mut self) -> &mut ColorScheme;
+    fn mut_draw_map(&mut self) -> &mut DrawMap;
     fn mut_opts(&mut self) -> &mut Options;
     fn map_switched(&mut self, ctx: &mut EventCtx, map: Map, timer: &mut Timer);
     fn draw_with_opts(&self, g: &mut GfxCtx, opts: DrawOptions);
@@ -49,29 +71,56 @@ pub trait AppLike {
     where
         Self: Sized;
 
-    // These two are needed to render traffic signals. Splitting them from sim() allows
-    // applications that don't run a traffic sim to work.
-    fn sim_time(&self) -> Time {
-        self.sim().time()
-    }
-    fn current_stage_and_remaining_time(&self, id: IntersectionID) -> (usize, Duration) {
-        self.sim().current_stage_and_remaining_time(id)
-    }
-
     /// Change the color scheme. Idempotent. Return true if there was a change.
     fn change_color_scheme(&mut self, ctx: &mut EventCtx, cs: ColorSchemeChoice) -> bool {
         if self.opts().color_scheme == cs {
             return false;
         }
         self.mut_opts().color_scheme = cs;
-        *self.mut_cs() = ColorScheme::new(ctx, self.opts().color_scheme);
+        *self.mut_cs() = ColorScheme::new(ctx, cs, self.opts().agent_color_scheme);
 
         ctx.loading_screen("rerendering map colors", |ctx, timer| {
-            *self.mut_draw_map() = DrawMap::new(ctx, self.map(), self.opts(), self.cs(), timer);
+            *self.mut_draw_map() = DrawMap::new(ctx, self.map(), self.cs(), timer);
         });
+        // Agents might be using the map's colors
+        self.agent_colors_changed();
+
+        true
+    }
 
+    /// Change the color scheme used for agents and overlays, or use the map's scheme for them with
+    /// None. Idempotent. Return true if there was a change. The map itself isn't rerendered.
+    fn change_agent_color_scheme(
+        &mut self,
+        ctx: &mut EventCtx,
+        cs: Option<ColorSchemeChoice>,
+    ) -> bool {
+        if self.opts().agent_color_scheme == cs {
+            return false;
+        }
+        self.mut_opts().agent_color_scheme = cs;
+        *self.mut_cs() = ColorScheme::new(ctx, self.opts().color_scheme, cs);
+        self.agent_colors_changed();
         true
     }
+
+    /// Called after the colors used for agents change. Applications caching anything drawn with
+    /// those colors should forget it here.
+    fn agent_colors_changed(&mut self) {}
+
+    /// Reload the current color scheme, picking up any changes to its overrides file, and rerender
+    /// the map. If the overrides can't be parsed, nothing changes.
+    fn reload_color_scheme(&mut self, ctx: &mut EventCtx) -> Result<()> {
+        let cs = ColorScheme::load(self.opts().color_scheme, self.opts().agent_color_scheme)?;
+        cs.set_style(ctx);
+        *self.mut_cs() = cs;
+
+        ctx.loading_screen("rerendering map colors", |ctx, timer| {
+            *self.mut_draw_map() = DrawMap::new(ctx, self.map(), self.cs(), timer);
+        });
+        self.agent_colors_changed();
+        Ok(())
+    }
 }
 
 #[derive(Clone, Hash, PartialEq, Eq, Debug, PartialOrd, Ord)]
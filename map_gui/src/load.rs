@@ -40,6 +40,16 @@ impl MapLoader {
             });
         }
 
+        MapLoader::force_reload(ctx, name, on_load)
+    }
+
+    /// Like `new`, but loads the map from disk even if it's the current one, picking up any
+    /// changes to the file.
+    pub fn force_reload<A: AppLike + 'static>(
+        ctx: &mut EventCtx,
+        name: MapName,
+        on_load: Box<dyn FnOnce(&mut EventCtx, &mut A) -> Transition<A>>,
+    ) -> Box<dyn State<A>> {
         FileLoader::<A, map_model::Map>::new(
             ctx,
             name.path(),
@@ -1,12 +1,15 @@
-use abstutil::CmdArgs;
+use serde::{Deserialize, Serialize};
+
+use abstutil::{CmdArgs, Timer};
 use geom::{Duration, UnitFmt};
+use map_model::{DrivingSide, IntersectionID};
 use widgetry::{
-    Checkbox, Choice, EventCtx, GeomBatch, GfxCtx, Key, Line, Outcome, Panel, Spinner, State,
-    StyledButtons, TextExt, Widget,
+    Checkbox, Choice, Drawable, EventCtx, GeomBatch, GfxCtx, Key, Line, Outcome, Panel, Spinner,
+    State, StyledButtons, TextExt, Widget,
 };
 
 use crate::colors::ColorSchemeChoice;
-use crate::render::DrawBuilding;
+use crate::render::traffic_signal::draw_signal_stage;
 use crate::tools::grey_out_map;
 use crate::AppLike;
 
@@ -26,6 +29,8 @@ pub struct Options {
     pub traffic_signal_style: TrafficSignalStyle,
     /// The color scheme for map elements, agents, and the UI.
     pub color_scheme: ColorSchemeChoice,
+    /// If set, agents and overlays use this color scheme instead of color_scheme.
+    pub agent_color_scheme: Option<ColorSchemeChoice>,
     /// Automatically change color_scheme based on simulation time to reflect day/night
     pub toggle_day_night_colors: bool,
     /// Map elements are drawn differently when unzoomed and zoomed. This specifies the canvas zoom
@@ -40,12 +45,26 @@ pub struct Options {
     pub dont_draw_time_warp: bool,
     /// The delay threshold to halt on when jumping to the next delay
     pub jump_to_delay: Duration,
+    /// If present, keep a copy of the simulation state in memory this often, so it's possible to
+    /// rewind.
+    pub rewind_interval: Option<Duration>,
+    /// Discard the oldest copies of the simulation state once they use this many megabytes.
+    pub rewind_memory_limit_mb: usize,
 
     /// Display roads and buildings in an alternate language, if possible. None means to use the
     /// OSM native name.
     pub language: Option<String>,
     /// How to render geometric units
     pub units: UnitFmt,
+    /// Where the info panel goes and how big it is. The player can change this while it's open.
+    pub info_panel: InfoPanelLayout,
+
+    /// Has the player been offered a tour of the basic controls yet? Like the color schemes, this
+    /// is remembered between sessions.
+    pub offered_tour: bool,
+    /// Context drawn beneath the map, if the player has picked a source. Also remembered between
+    /// sessions.
+    pub basemap: Option<BasemapOptions>,
 }
 
 impl Options {
@@ -56,6 +75,7 @@ impl Options {
 
             traffic_signal_style: TrafficSignalStyle::BAP,
             color_scheme: ColorSchemeChoice::DayMode,
+            agent_color_scheme: None,
             toggle_day_night_colors: false,
             min_zoom_for_detail: 4.0,
             camera_angle: CameraAngle::TopDown,
@@ -63,6 +83,8 @@ impl Options {
             time_increment: Duration::minutes(10),
             dont_draw_time_warp: false,
             jump_to_delay: Duration::minutes(5),
+            rewind_interval: None,
+            rewind_memory_limit_mb: 512,
 
             language: None,
             units: UnitFmt {
@@ -70,9 +92,59 @@ impl Options {
                 // TODO Should default be based on the map?
                 metric: false,
             },
+            info_panel: InfoPanelLayout {
+                dock_right: false,
+                width_pct: 30,
+                height_pct: 60,
+            },
+
+            offered_tour: false,
+            basemap: None,
+        }
+    }
+
+    /// Restores the options remembered from previous sessions. Has no effect if the file is
+    /// missing or broken.
+    pub fn load_persisted(&mut self) {
+        if let Ok(persisted) = abstio::maybe_read_json::<PersistedOptions>(
+            abstio::path_player("options.json"),
+            &mut Timer::throwaway(),
+        ) {
+            self.offered_tour = persisted.offered_tour;
+            if let Some(cs) = persisted.color_scheme {
+                self.color_scheme = cs;
+                // The player picked this, so don't override it
+                self.toggle_day_night_colors = false;
+            }
+            self.agent_color_scheme = persisted.agent_color_scheme;
+            self.basemap = persisted.basemap;
         }
     }
 
+    /// Remembers the few options that last between sessions.
+    pub fn save_persisted(&self) {
+        let path = abstio::path_player("options.json");
+        // Apps that never load these can still save them from the options panel, so don't forget
+        // the tour was already offered
+        let offered_tour = self.offered_tour
+            || abstio::maybe_read_json::<PersistedOptions>(path.clone(), &mut Timer::throwaway())
+                .map(|persisted| persisted.offered_tour)
+                .unwrap_or(false);
+        abstio::write_json(
+            path,
+            &PersistedOptions {
+                offered_tour,
+                color_scheme: if self.toggle_day_night_colors {
+                    None
+                } else {
+                    Some(self.color_scheme)
+                },
+                agent_color_scheme: self.agent_color_scheme,
+                basemap: self.basemap.clone(),
+            },
+        );
+    }
+
     /// Update the options using command-line flags.
     pub fn update_from_args(&mut self, args: &mut CmdArgs) {
         self.dev = args.enabled("--dev");
@@ -101,6 +173,39 @@ impl Options {
     }
 }
 
+/// The subset of Options saved as player data.
+#[derive(Serialize, Deserialize)]
+struct PersistedOptions {
+    offered_tour: bool,
+    // None if the scheme automatically follows day and night
+    #[serde(default)]
+    color_scheme: Option<ColorSchemeChoice>,
+    #[serde(default)]
+    agent_color_scheme: Option<ColorSchemeChoice>,
+    #[serde(default)]
+    basemap: Option<BasemapOptions>,
+}
+
+/// Where to find the basemap drawn beneath the map, and how to draw it.
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct BasemapOptions {
+    /// Either a directory of raster tiles laid out as `{z}/{x}/{y}.png`, or a GeoJSON file with
+    /// simplified context, like coastlines and major roads. Nothing is fetched over the network.
+    pub source: String,
+    /// From 0 (invisible) to 1 (opaque)
+    pub opacity: f64,
+    /// The source is remembered even while the basemap is hidden
+    pub enabled: bool,
+}
+
+/// The info panel hugs the left or right edge of the window. Sizes are percentages of the window.
+#[derive(Clone, PartialEq, Debug)]
+pub struct InfoPanelLayout {
+    pub dock_right: bool,
+    pub width_pct: usize,
+    pub height_pct: usize,
+}
+
 /// Different ways of drawing traffic signals. The names of these aren't super meaningful...
 #[derive(Clone, PartialEq, Debug)]
 pub enum TrafficSignalStyle {
@@ -121,11 +226,14 @@ pub enum CameraAngle {
 
 pub struct OptionsPanel {
     panel: Panel,
+    // The nearest visible traffic signal, drawn in the chosen style before it's applied
+    preview_signal: Option<Drawable>,
 }
 
 impl OptionsPanel {
     pub fn new<A: AppLike>(ctx: &mut EventCtx, app: &A) -> Box<dyn State<A>> {
         Box::new(OptionsPanel {
+            preview_signal: None,
             panel: Panel::new(Widget::col(vec![
                 Widget::custom_row(vec![
                     Line("Settings").small_heading().draw(ctx),
@@ -174,16 +282,10 @@ impl OptionsPanel {
                             ctx,
                             "Traffic signal rendering",
                             app.opts().traffic_signal_style.clone(),
-                            vec![
-                                Choice::new("Default (Brian's style)", TrafficSignalStyle::BAP),
-                                Choice::new("Yuwen's style", TrafficSignalStyle::Yuwen),
-                                Choice::new(
-                                    "arrows showing individual turns (to debug)",
-                                    TrafficSignalStyle::IndividualTurnArrows,
-                                ),
-                            ],
+                            TrafficSignalStyle::choices(),
                         ),
                     ]),
+                    signal_style_previews(ctx, app),
                     Widget::row(vec![
                         "Camera angle:".draw_text(ctx),
                         Widget::dropdown(
@@ -201,14 +303,24 @@ impl OptionsPanel {
                         ),
                     ]),
                     Widget::row(vec![
-                        "Color scheme:".draw_text(ctx),
+                        "Map colors:".draw_text(ctx),
                         Widget::dropdown(
                             ctx,
-                            "Color scheme",
+                            "Map colors",
                             app.opts().color_scheme,
                             ColorSchemeChoice::choices(),
                         ),
                     ]),
+                    Widget::row(vec![
+                        "Agent colors:".draw_text(ctx),
+                        Widget::dropdown(ctx, "Agent colors", app.opts().agent_color_scheme, {
+                            let mut choices = vec![Choice::new("same as the map", None)];
+                            for c in ColorSchemeChoice::choices() {
+                                choices.push(Choice::new(c.label, Some(c.data)));
+                            }
+                            choices
+                        }),
+                    ]),
                     Widget::row(vec![
                         "Camera zoom to switch to unzoomed view".draw_text(ctx),
                         Widget::dropdown(
@@ -247,6 +359,50 @@ impl OptionsPanel {
                 ])
                 .bg(app.cs().section_bg)
                 .padding(8),
+                "Simulation".draw_text(ctx),
+                Widget::col(vec![
+                    Widget::row(vec![
+                        "Vehicles drive on the".draw_text(ctx),
+                        Line(match app.map().get_config().driving_side {
+                            DrivingSide::Right => "right",
+                            DrivingSide::Left => "left",
+                        })
+                        .draw(ctx),
+                        Line("(set by this map)").secondary().draw(ctx),
+                    ]),
+                    Widget::row(vec![
+                        "Save snapshots for rewinding".draw_text(ctx),
+                        Widget::dropdown(
+                            ctx,
+                            "rewind interval",
+                            app.opts().rewind_interval,
+                            vec![
+                                Choice::new("never", None),
+                                Choice::new("every 5 minutes", Some(Duration::minutes(5))),
+                                Choice::new("every 10 minutes", Some(Duration::minutes(10))),
+                                Choice::new("every 30 minutes", Some(Duration::minutes(30))),
+                                Choice::new("every hour", Some(Duration::hours(1))),
+                            ],
+                        ),
+                    ]),
+                    Widget::row(vec![
+                        "Memory for snapshots".draw_text(ctx),
+                        Widget::dropdown(
+                            ctx,
+                            "rewind memory",
+                            app.opts().rewind_memory_limit_mb,
+                            vec![
+                                Choice::new("256 MB", 256),
+                                Choice::new("512 MB", 512),
+                                Choice::new("1 GB", 1024),
+                                Choice::new("2 GB", 2048),
+                                Choice::new("4 GB", 4096),
+                            ],
+                        ),
+                    ]),
+                ])
+                .bg(app.cs().section_bg)
+                .padding(8),
                 "Debug".draw_text(ctx),
                 Widget::col(vec![
                     Checkbox::checkbox(ctx, "Enable developer mode", None, app.opts().dev),
@@ -308,44 +464,25 @@ impl<A: AppLike> State<A> for OptionsPanel {
                     let camera_angle = self.panel.dropdown_value("Camera angle");
                     if opts.camera_angle != camera_angle {
                         opts.camera_angle = camera_angle;
-                        ctx.loading_screen("rerendering buildings", |ctx, timer| {
-                            let mut all_buildings = GeomBatch::new();
-                            let mut all_building_paths = GeomBatch::new();
-                            let mut all_building_outlines = GeomBatch::new();
-                            timer
-                                .start_iter("rendering buildings", app.map().all_buildings().len());
-                            for b in app.map().all_buildings() {
-                                timer.next();
-                                DrawBuilding::new(
-                                    ctx,
-                                    b,
-                                    app.map(),
-                                    app.cs(),
-                                    &opts,
-                                    &mut all_buildings,
-                                    &mut all_building_paths,
-                                    &mut all_building_outlines,
-                                );
-                            }
-                            timer.start("upload geometry");
-                            app.mut_draw_map().draw_all_buildings = all_buildings.upload(ctx);
-                            app.mut_draw_map().draw_all_building_paths =
-                                all_building_paths.upload(ctx);
-                            app.mut_draw_map().draw_all_building_outlines =
-                                all_building_outlines.upload(ctx);
-                            timer.stop("upload geometry");
-                        });
+                        // Rebuilt with the new angle the next time they're drawn
+                        app.mut_draw_map().clear_buildings();
                     }
 
-                    if app.change_color_scheme(ctx, self.panel.dropdown_value("Color scheme")) {
+                    if app.change_color_scheme(ctx, self.panel.dropdown_value("Map colors")) {
                         // change_color_scheme doesn't modify our local copy of Options!
                         opts.color_scheme = app.opts().color_scheme;
                         // If the player picks a different scheme, don't undo it later.
                         opts.toggle_day_night_colors = false;
                     }
+                    if app.change_agent_color_scheme(ctx, self.panel.dropdown_value("Agent colors"))
+                    {
+                        opts.agent_color_scheme = app.opts().agent_color_scheme;
+                    }
 
                     opts.min_zoom_for_detail = self.panel.dropdown_value("min zoom");
                     opts.units.metric = self.panel.is_checked("metric / imperial units");
+                    opts.rewind_interval = self.panel.dropdown_value("rewind interval");
+                    opts.rewind_memory_limit_mb = self.panel.dropdown_value("rewind memory");
 
                     let language = self.panel.dropdown_value("language");
                     if language != opts.language {
@@ -355,12 +492,22 @@ impl<A: AppLike> State<A> for OptionsPanel {
                         }
                     }
 
+                    opts.save_persisted();
                     *app.mut_opts() = opts;
 
                     return widgetry::Transition::Pop;
                 }
                 _ => unreachable!(),
             },
+            Outcome::Changed => {
+                let style: TrafficSignalStyle =
+                    self.panel.dropdown_value("Traffic signal rendering");
+                self.preview_signal = if style == app.opts().traffic_signal_style {
+                    None
+                } else {
+                    preview_signal(ctx, app, style)
+                };
+            }
             _ => {}
         }
 
@@ -369,6 +516,109 @@ impl<A: AppLike> State<A> for OptionsPanel {
 
     fn draw(&self, g: &mut GfxCtx, app: &A) {
         grey_out_map(g, app);
+        if let Some(ref draw) = self.preview_signal {
+            g.redraw(draw);
+        }
         self.panel.draw(g);
     }
 }
+
+impl TrafficSignalStyle {
+    fn choices() -> Vec<Choice<TrafficSignalStyle>> {
+        vec![
+            Choice::new("Default (Brian's style)", TrafficSignalStyle::BAP),
+            Choice::new("Yuwen's style", TrafficSignalStyle::Yuwen),
+            Choice::new(
+                "arrows showing individual turns (to debug)",
+                TrafficSignalStyle::IndividualTurnArrows,
+            ),
+        ]
+    }
+}
+
+/// Draws the first stage of one sample traffic signal in every style, side by side. Rendering goes
+/// through draw_signal_stage, just like the map, so the previews always match. A four-way
+/// intersection shows off the most, so one is used if the map has any.
+fn signal_style_previews<A: AppLike>(ctx: &mut EventCtx, app: &A) -> Widget {
+    let map = app.map();
+    let signals: Vec<_> = map
+        .all_intersections()
+        .iter()
+        .filter(|i| i.is_traffic_signal())
+        .collect();
+    let i = match signals
+        .iter()
+        .find(|i| i.roads.len() == 4)
+        .or_else(|| signals.first())
+    {
+        Some(i) => i,
+        None => {
+            return Widget::nothing();
+        }
+    };
+
+    let bounds = i.polygon.get_bounds();
+    // Pick a zoom so that we fit a fixed size in pixels
+    let zoom = 100.0 / bounds.width().max(bounds.height());
+    let mut col = Vec::new();
+    for choice in TrafficSignalStyle::choices() {
+        let mut batch = GeomBatch::new();
+        batch.push(app.cs().normal_intersection, i.polygon.clone());
+        draw_signal_stage(
+            ctx.prerender,
+            &map.get_traffic_signal(i.id).stages[0],
+            0,
+            i.id,
+            None,
+            &mut batch,
+            app,
+            choice.data,
+        );
+        col.push(Widget::col(vec![
+            Widget::draw_batch(
+                ctx,
+                batch.translate(-bounds.min_x, -bounds.min_y).scale(zoom),
+            )
+            .centered_horiz(),
+            Line(choice.label).small().draw(ctx).centered_horiz(),
+        ]));
+    }
+    Widget::custom_row(col).evenly_spaced()
+}
+
+/// The traffic signal closest to the center of the screen, if any are visible
+fn nearest_visible_signal<A: AppLike>(ctx: &EventCtx, app: &A) -> Option<IntersectionID> {
+    let bounds = ctx.canvas.get_screen_bounds();
+    let center = ctx.canvas.center_to_map_pt();
+    app.map()
+        .all_intersections()
+        .iter()
+        .filter(|i| i.is_traffic_signal() && bounds.contains(i.polygon.center()))
+        .min_by_key(|i| i.polygon.center().dist_to(center))
+        .map(|i| i.id)
+}
+
+fn preview_signal<A: AppLike>(
+    ctx: &mut EventCtx,
+    app: &A,
+    style: TrafficSignalStyle,
+) -> Option<Drawable> {
+    let i = nearest_visible_signal(ctx, app)?;
+    let (idx, remaining) = app.current_stage_and_remaining_time(i);
+    let mut batch = GeomBatch::new();
+    batch.push(
+        app.cs().normal_intersection,
+        app.map().get_i(i).polygon.clone(),
+    );
+    draw_signal_stage(
+        ctx.prerender,
+        &app.map().get_traffic_signal(i).stages[idx],
+        idx,
+        i,
+        Some(remaining),
+        &mut batch,
+        app,
+        style,
+    );
+    Some(ctx.upload(batch))
+}
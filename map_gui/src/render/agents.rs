@@ -3,16 +3,19 @@ use std::collections::HashMap;
 
 use aabb_quadtree::QuadTree;
 
-use geom::{Circle, Pt2D, Time};
+use geom::{Circle, Duration, Pt2D, Time};
 use map_model::{Map, Traversable};
-use sim::{AgentID, Sim, UnzoomedAgent, VehicleType};
-use widgetry::{Checkbox, Color, Drawable, EventCtx, GeomBatch, GfxCtx, Panel, Prerender, Widget};
+use sim::{AgentID, Sim, TripPurpose, UnzoomedAgent, VehicleType};
+use widgetry::{
+    Checkbox, Choice, Color, Drawable, EventCtx, GeomBatch, GfxCtx, Panel, Prerender, Widget,
+};
 
 use crate::colors::ColorScheme;
 use crate::render::{
     draw_vehicle, unzoomed_agent_radius, DrawPedCrowd, DrawPedestrian, Renderable,
 };
-use crate::AppLike;
+use crate::tools::{ColorLegend, ColorScale};
+use crate::ReadOnlyAppLike;
 
 pub struct AgentCache {
     /// This is controlled almost entirely by the minimap panel. It has no meaning in edit mode.
@@ -36,6 +39,20 @@ impl AgentCache {
         }
     }
 
+    /// Forget everything already rendered, because the color scheme changed. The unzoomed agent
+    /// filters are kept.
+    pub fn colors_changed(&mut self, cs: &ColorScheme) {
+        let old = &self.unzoomed_agents;
+        let mut unzoomed_agents = UnzoomedAgents::new(cs);
+        unzoomed_agents.cars = old.cars;
+        unzoomed_agents.bikes = old.bikes;
+        unzoomed_agents.buses_and_trains = old.buses_and_trains;
+        unzoomed_agents.peds = old.peds;
+        unzoomed_agents.coloring = old.coloring;
+        *self = AgentCache::new(cs);
+        self.unzoomed_agents = unzoomed_agents;
+    }
+
     pub fn get(&self, on: Traversable) -> Vec<&dyn Renderable> {
         self.agents_per_on[&on]
             .iter()
@@ -84,7 +101,7 @@ impl AgentCache {
     pub fn calculate_unzoomed_agents<P: AsRef<Prerender>>(
         &mut self,
         prerender: &mut P,
-        app: &dyn AppLike,
+        app: &dyn ReadOnlyAppLike,
     ) -> &QuadTree<AgentID> {
         let now = app.sim().time();
         let mut recalc = true;
@@ -107,7 +124,7 @@ impl AgentCache {
                 Circle::new(Pt2D::new(0.0, 0.0), unzoomed_agent_radius(None)).to_polygon();
 
             for agent in app.sim().get_unzoomed_agents(app.map()) {
-                if let Some(color) = self.unzoomed_agents.color(&agent) {
+                if let Some(color) = self.unzoomed_agents.color(&agent, app) {
                     let circle = if agent.id.to_vehicle_type().is_some() {
                         car_circle.translate(agent.pos.x(), agent.pos.y())
                     } else {
@@ -126,7 +143,7 @@ impl AgentCache {
         &self.unzoomed.as_ref().unwrap().2
     }
 
-    pub fn draw_unzoomed_agents(&mut self, g: &mut GfxCtx, app: &dyn AppLike) {
+    pub fn draw_unzoomed_agents(&mut self, g: &mut GfxCtx, app: &dyn ReadOnlyAppLike) {
         self.calculate_unzoomed_agents(g, app);
         g.redraw(&self.unzoomed.as_ref().unwrap().3);
 
@@ -152,6 +169,7 @@ pub struct UnzoomedAgents {
     bikes: bool,
     buses_and_trains: bool,
     peds: bool,
+    coloring: AgentColoring,
 
     car_color: Color,
     bike_color: Color,
@@ -166,6 +184,7 @@ impl UnzoomedAgents {
             bikes: true,
             buses_and_trains: true,
             peds: true,
+            coloring: AgentColoring::Mode,
 
             car_color: cs.unzoomed_car.alpha(0.8),
             bike_color: cs.unzoomed_bike.alpha(0.8),
@@ -174,7 +193,44 @@ impl UnzoomedAgents {
         }
     }
 
-    fn color(&self, agent: &UnzoomedAgent) -> Option<Color> {
+    fn color(&self, agent: &UnzoomedAgent, app: &dyn ReadOnlyAppLike) -> Option<Color> {
+        let mode_color = self.mode_color(agent)?;
+        if self.coloring == AgentColoring::Mode {
+            return Some(mode_color);
+        }
+        // Buses and trains don't have a trip, so they always keep their usual color
+        let trip = match app.sim().agent_to_trip(agent.id) {
+            Some(t) => t,
+            None => {
+                return Some(mode_color);
+            }
+        };
+        let info = app.sim().trip_info(trip);
+        Some(match self.coloring {
+            AgentColoring::Mode => unreachable!(),
+            AgentColoring::TripPurpose => app
+                .cs()
+                .rotating_color_agents(purpose_category(info.purpose))
+                .alpha(0.8),
+            AgentColoring::DepartureTime => departure_scale()
+                .eval(
+                    (info.departure.inner_seconds() / Duration::hours(24).inner_seconds()).min(1.0),
+                )
+                .alpha(0.8),
+            AgentColoring::Delay => match app.baseline_trip_time(trip) {
+                Some(baseline) => {
+                    let delay = (app.sim().time() - info.departure) - baseline;
+                    app.cs()
+                        .good_to_bad_red
+                        .eval((delay / MAX_DELAY).max(0.0).min(1.0))
+                        .alpha(0.8)
+                }
+                None => Color::grey(0.5).alpha(0.8),
+            },
+        })
+    }
+
+    fn mode_color(&self, agent: &UnzoomedAgent) -> Option<Color> {
         match agent.id.to_vehicle_type() {
             Some(VehicleType::Car) => {
                 if self.cars {
@@ -207,21 +263,69 @@ impl UnzoomedAgents {
         }
     }
 
-    pub fn make_horiz_viz_panel(&self, ctx: &mut EventCtx) -> Widget {
-        Widget::custom_row(vec![
-            Checkbox::colored(ctx, "Car", self.car_color, self.cars).margin_right(24),
-            Checkbox::colored(ctx, "Bike", self.bike_color, self.bikes).margin_right(24),
-            Checkbox::colored(ctx, "Bus", self.bus_color, self.buses_and_trains).margin_right(24),
-            Checkbox::colored(ctx, "Walk", self.ped_color, self.peds).margin_right(8),
+    pub fn make_horiz_viz_panel(&self, ctx: &mut EventCtx, cs: &ColorScheme) -> Widget {
+        Widget::col(vec![
+            Widget::custom_row(vec![
+                Checkbox::colored(ctx, "Car", self.car_color, self.cars).margin_right(24),
+                Checkbox::colored(ctx, "Bike", self.bike_color, self.bikes).margin_right(24),
+                Checkbox::colored(ctx, "Bus", self.bus_color, self.buses_and_trains)
+                    .margin_right(24),
+                Checkbox::colored(ctx, "Walk", self.ped_color, self.peds).margin_right(8),
+            ]),
+            self.coloring_legend(ctx, cs),
         ])
     }
 
-    pub fn make_vert_viz_panel(&self, ctx: &mut EventCtx) -> Widget {
+    pub fn make_vert_viz_panel(&self, ctx: &mut EventCtx, cs: &ColorScheme) -> Widget {
         Widget::col(vec![
             Checkbox::colored(ctx, "Car", self.car_color, self.cars),
             Checkbox::colored(ctx, "Bike", self.bike_color, self.bikes),
             Checkbox::colored(ctx, "Bus", self.bus_color, self.buses_and_trains),
             Checkbox::colored(ctx, "Walk", self.ped_color, self.peds),
+            self.coloring_legend(ctx, cs),
+        ])
+    }
+
+    // Pick how to color agents, and explain the colors if they don't just mean the mode
+    fn coloring_legend(&self, ctx: &mut EventCtx, cs: &ColorScheme) -> Widget {
+        let legend = match self.coloring {
+            AgentColoring::Mode => Widget::nothing(),
+            AgentColoring::TripPurpose => Widget::col(
+                PURPOSE_CATEGORIES
+                    .iter()
+                    .enumerate()
+                    .map(|(idx, label)| {
+                        ColorLegend::row(ctx, cs.rotating_color_agents(idx), *label)
+                    })
+                    .collect(),
+            ),
+            AgentColoring::DepartureTime => ColorLegend::gradient(
+                ctx,
+                &departure_scale(),
+                vec!["midnight", "6am", "noon", "6pm", "midnight"],
+            ),
+            AgentColoring::Delay => Widget::col(vec![
+                ColorLegend::gradient(
+                    ctx,
+                    &cs.good_to_bad_red,
+                    vec!["on time".to_string(), format!("{} late", MAX_DELAY)],
+                ),
+                ColorLegend::row(ctx, Color::grey(0.5), "no baseline to compare"),
+            ]),
+        };
+        Widget::col(vec![
+            Widget::dropdown(
+                ctx,
+                "agent colors",
+                self.coloring,
+                vec![
+                    Choice::new("color by mode", AgentColoring::Mode),
+                    Choice::new("color by trip purpose", AgentColoring::TripPurpose),
+                    Choice::new("color by departure time", AgentColoring::DepartureTime),
+                    Choice::new("color by delay", AgentColoring::Delay),
+                ],
+            ),
+            legend,
         ])
     }
 
@@ -230,5 +334,41 @@ impl UnzoomedAgents {
         self.bikes = panel.is_checked("Bike");
         self.buses_and_trains = panel.is_checked("Bus");
         self.peds = panel.is_checked("Walk");
+        self.coloring = panel.dropdown_value("agent colors");
     }
 }
+
+/// What the color of an unzoomed agent means. Changing this only redraws the agents, not the map.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum AgentColoring {
+    Mode,
+    TripPurpose,
+    DepartureTime,
+    /// Compared to the same trip in the baseline simulation, if there is one
+    Delay,
+}
+
+// There are too many purposes to tell apart by color, so group them
+const PURPOSE_CATEGORIES: [&str; 6] = ["home", "work", "school", "errand", "leisure", "other"];
+
+fn purpose_category(purpose: TripPurpose) -> usize {
+    match purpose {
+        TripPurpose::Home => 0,
+        TripPurpose::Work => 1,
+        TripPurpose::School => 2,
+        TripPurpose::Escort
+        | TripPurpose::PersonalBusiness
+        | TripPurpose::Shopping
+        | TripPurpose::Meal
+        | TripPurpose::Medical => 3,
+        TripPurpose::Social | TripPurpose::Recreation => 4,
+        TripPurpose::ParkAndRideTransfer => 5,
+    }
+}
+
+// Trips delayed by this much or more are the worst color
+const MAX_DELAY: Duration = Duration::const_seconds(15.0 * 60.0);
+
+fn departure_scale() -> ColorScale {
+    ColorScale::from_colorous(colorous::TURBO)
+}
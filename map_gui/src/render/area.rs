@@ -4,7 +4,7 @@ use widgetry::{Color, EventCtx, Fill, GeomBatch, GfxCtx, Line, Text};
 
 use crate::colors::ColorScheme;
 use crate::render::{DrawOptions, Renderable};
-use crate::{AppLike, ID};
+use crate::{ReadOnlyAppLike, ID};
 
 pub struct DrawArea {
     pub id: AreaID,
@@ -52,7 +52,7 @@ impl Renderable for DrawArea {
         ID::Area(self.id)
     }
 
-    fn draw(&self, _: &mut GfxCtx, _: &dyn AppLike, _: &DrawOptions) {}
+    fn draw(&self, _: &mut GfxCtx, _: &dyn ReadOnlyAppLike, _: &DrawOptions) {}
 
     fn get_outline(&self, map: &Map) -> Polygon {
         // Since areas are so big, don't just draw the outline
@@ -5,7 +5,7 @@ use widgetry::{Drawable, GeomBatch, GfxCtx, Prerender};
 
 use crate::colors::ColorScheme;
 use crate::render::{DrawOptions, Renderable, OUTLINE_THICKNESS};
-use crate::{AppLike, ID};
+use crate::{ReadOnlyAppLike, ID};
 
 pub struct DrawBike {
     pub id: CarID,
@@ -115,7 +115,7 @@ impl Renderable for DrawBike {
         ID::Car(self.id)
     }
 
-    fn draw(&self, g: &mut GfxCtx, _: &dyn AppLike, _: &DrawOptions) {
+    fn draw(&self, g: &mut GfxCtx, _: &dyn ReadOnlyAppLike, _: &DrawOptions) {
         g.redraw(&self.draw_default);
     }
 
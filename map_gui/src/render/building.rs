@@ -2,12 +2,12 @@ use std::cell::RefCell;
 
 use geom::{Angle, Distance, Line, Polygon, Pt2D, Ring};
 use map_model::{Building, BuildingID, LaneType, Map, OffstreetParking, NORMAL_LANE_THICKNESS};
-use widgetry::{Color, Drawable, EventCtx, GeomBatch, GfxCtx, Line, Text};
+use widgetry::{Color, Drawable, GeomBatch, GfxCtx, Line, Prerender, Text};
 
 use crate::colors::{ColorScheme, ColorSchemeChoice};
 use crate::options::{CameraAngle, Options};
 use crate::render::{DrawOptions, Renderable, OUTLINE_THICKNESS};
-use crate::{AppLike, ID};
+use crate::{ReadOnlyAppLike, ID};
 
 pub struct DrawBuilding {
     pub id: BuildingID,
@@ -15,8 +15,16 @@ pub struct DrawBuilding {
 }
 
 impl DrawBuilding {
-    pub fn new(
-        ctx: &EventCtx,
+    pub fn new(bldg: &Building) -> DrawBuilding {
+        DrawBuilding {
+            id: bldg.id,
+            label: RefCell::new(None),
+        }
+    }
+
+    /// Adds this building to the batches covering every building in the map.
+    pub fn render<P: AsRef<Prerender>>(
+        prerender: &P,
         bldg: &Building,
         map: &Map,
         cs: &ColorScheme,
@@ -24,7 +32,7 @@ impl DrawBuilding {
         bldg_batch: &mut GeomBatch,
         paths_batch: &mut GeomBatch,
         outlines_batch: &mut GeomBatch,
-    ) -> DrawBuilding {
+    ) {
         // Trim the driveway away from the sidewalk's center line, so that it doesn't overlap. For
         // now, this cleanup is visual; it doesn't belong in the map_model layer.
         let orig_pl = &bldg.driveway_geom;
@@ -57,7 +65,7 @@ impl DrawBuilding {
                     // Might need to scale down more for some buildings, but so far, this works
                     // everywhere.
                     bldg_batch.append(
-                        GeomBatch::load_svg(ctx, "system/assets/map/parking.svg")
+                        GeomBatch::load_svg(prerender, "system/assets/map/parking.svg")
                             .scale(0.1)
                             .centered_on(bldg.label_center),
                     );
@@ -213,11 +221,6 @@ impl DrawBuilding {
                 driveway.make_polygons(NORMAL_LANE_THICKNESS),
             );
         }
-
-        DrawBuilding {
-            id: bldg.id,
-            label: RefCell::new(None),
-        }
     }
 }
 
@@ -226,7 +229,7 @@ impl Renderable for DrawBuilding {
         ID::Building(self.id)
     }
 
-    fn draw(&self, g: &mut GfxCtx, app: &dyn AppLike, opts: &DrawOptions) {
+    fn draw(&self, g: &mut GfxCtx, app: &dyn ReadOnlyAppLike, opts: &DrawOptions) {
         if opts.label_buildings {
             // Labels are expensive to compute up-front, so do it lazily, since we don't really
             // zoom in on all buildings in a single session anyway
@@ -1,10 +1,11 @@
+use std::cell::RefCell;
+
 use geom::{Angle, Circle, Distance, Line, Polygon, Pt2D};
-use map_model::{BusStop, BusStopID, Map};
-use widgetry::{Drawable, EventCtx, GeomBatch, GfxCtx};
+use map_model::{BusStop, BusStopID, DrivingSide, Map};
+use widgetry::{Drawable, GeomBatch, GfxCtx, Prerender};
 
-use crate::colors::ColorScheme;
 use crate::render::{DrawOptions, Renderable, OUTLINE_THICKNESS};
-use crate::{AppLike, ID};
+use crate::{ReadOnlyAppLike, ID};
 
 const RADIUS: Distance = Distance::const_meters(1.0);
 
@@ -13,22 +14,43 @@ pub struct DrawBusStop {
     center: Pt2D,
     zorder: isize,
 
-    draw_default: Drawable,
+    draw_default: RefCell<Option<Drawable>>,
 }
 
 impl DrawBusStop {
-    pub fn new(ctx: &EventCtx, stop: &BusStop, map: &Map, cs: &ColorScheme) -> DrawBusStop {
+    pub fn new(stop: &BusStop, map: &Map) -> DrawBusStop {
         let (pt, angle) = stop.sidewalk_pos.pt_and_angle(map);
+        // Sidewalks point the same way as traffic on their side of the road, so this puts the
+        // icon on the outer edge of the sidewalk
         let center = pt.project_away(
             map.get_l(stop.sidewalk_pos.lane()).width / 2.0,
-            angle.rotate_degs(90.0),
+            angle.rotate_degs(if map.get_config().driving_side == DrivingSide::Right {
+                90.0
+            } else {
+                -90.0
+            }),
         );
 
+        DrawBusStop {
+            id: stop.id,
+            center,
+            zorder: map.get_parent(stop.sidewalk_pos.lane()).zorder,
+            draw_default: RefCell::new(None),
+        }
+    }
+
+    pub fn render<P: AsRef<Prerender>>(
+        &self,
+        prerender: &P,
+        app: &dyn ReadOnlyAppLike,
+    ) -> GeomBatch {
+        let center = self.center;
+        let cs = app.cs();
         let mut icon = GeomBatch::new();
         icon.append(
             GeomBatch::load_svg(
-                ctx.prerender,
-                if stop.is_train_stop {
+                prerender,
+                if app.map().get_bs(self.id).is_train_stop {
                     "system/assets/map/light_rail.svg"
                 } else {
                     "system/assets/meters/bus.svg"
@@ -53,12 +75,17 @@ impl DrawBusStop {
             .make_polygons(Distance::meters(0.3)),
         );
 
-        DrawBusStop {
-            id: stop.id,
-            center,
-            zorder: map.get_parent(stop.sidewalk_pos.lane()).zorder,
-            draw_default: ctx.upload(batch),
+        batch
+    }
+
+    /// Builds the geometry now, if it hasn't been already. Returns true if there was work to do.
+    pub fn prepare(&self, g: &mut GfxCtx, app: &dyn ReadOnlyAppLike) -> bool {
+        let mut draw = self.draw_default.borrow_mut();
+        if draw.is_some() {
+            return false;
         }
+        *draw = Some(g.upload(self.render(g, app)));
+        true
     }
 }
 
@@ -67,8 +94,9 @@ impl Renderable for DrawBusStop {
         ID::BusStop(self.id)
     }
 
-    fn draw(&self, g: &mut GfxCtx, _: &dyn AppLike, _: &DrawOptions) {
-        g.redraw(&self.draw_default);
+    fn draw(&self, g: &mut GfxCtx, app: &dyn ReadOnlyAppLike, _: &DrawOptions) {
+        self.prepare(g, app);
+        g.redraw(self.draw_default.borrow().as_ref().unwrap());
     }
 
     fn get_outline(&self, _: &Map) -> Polygon {
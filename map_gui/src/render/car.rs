@@ -5,7 +5,7 @@ use widgetry::{Color, Drawable, GeomBatch, GfxCtx, Line, Prerender, Text};
 
 use crate::colors::ColorScheme;
 use crate::render::{DrawOptions, Renderable, OUTLINE_THICKNESS};
-use crate::{AppLike, ID};
+use crate::{ReadOnlyAppLike, ID};
 
 const CAR_WIDTH: Distance = Distance::const_meters(1.75);
 
@@ -204,7 +204,7 @@ impl Renderable for DrawCar {
         ID::Car(self.id)
     }
 
-    fn draw(&self, g: &mut GfxCtx, _: &dyn AppLike, _: &DrawOptions) {
+    fn draw(&self, g: &mut GfxCtx, _: &dyn ReadOnlyAppLike, _: &DrawOptions) {
         g.redraw(&self.draw_default);
     }
 
@@ -11,7 +11,7 @@ use crate::colors::ColorScheme;
 use crate::render::{
     traffic_signal, DrawOptions, Renderable, CROSSWALK_LINE_THICKNESS, OUTLINE_THICKNESS,
 };
-use crate::{AppLike, ID};
+use crate::{ReadOnlyAppLike, ID};
 
 pub struct DrawIntersection {
     pub id: IntersectionID,
@@ -36,7 +36,23 @@ impl DrawIntersection {
         *self.draw_traffic_signal.borrow_mut() = None;
     }
 
-    pub fn render<P: AsRef<Prerender>>(&self, prerender: &P, app: &dyn AppLike) -> GeomBatch {
+    /// Builds the geometry now, if it hasn't been already. Returns true if there was work to do.
+    pub fn prepare(&self, g: &mut GfxCtx, app: &dyn ReadOnlyAppLike) -> bool {
+        // Lazily calculate, because these are expensive to all do up-front, and most players won't
+        // exhaustively see every intersection during a single session
+        let mut draw = self.draw_default.borrow_mut();
+        if draw.is_some() {
+            return false;
+        }
+        *draw = Some(g.upload(self.render(g, app)));
+        true
+    }
+
+    pub fn render<P: AsRef<Prerender>>(
+        &self,
+        prerender: &P,
+        app: &dyn ReadOnlyAppLike,
+    ) -> GeomBatch {
         let map = app.map();
         let i = map.get_i(self.id);
 
@@ -163,14 +179,9 @@ impl Renderable for DrawIntersection {
         ID::Intersection(self.id)
     }
 
-    fn draw(&self, g: &mut GfxCtx, app: &dyn AppLike, opts: &DrawOptions) {
-        // Lazily calculate, because these are expensive to all do up-front, and most players won't
-        // exhaustively see every intersection during a single session
-        let mut draw = self.draw_default.borrow_mut();
-        if draw.is_none() {
-            *draw = Some(g.upload(self.render(g, app)));
-        }
-        g.redraw(draw.as_ref().unwrap());
+    fn draw(&self, g: &mut GfxCtx, app: &dyn ReadOnlyAppLike, opts: &DrawOptions) {
+        self.prepare(g, app);
+        g.redraw(self.draw_default.borrow().as_ref().unwrap());
 
         if let Some(signal) = app.map().maybe_get_traffic_signal(self.id) {
             if !opts.suppress_traffic_signal_details.contains(&self.id) {
@@ -268,7 +279,11 @@ pub fn calculate_corners(i: &Intersection, map: &Map) -> Vec<Polygon> {
 }
 
 // calculate_corners smooths edges, but we don't want to do that when drawing explicit borders.
-fn calculate_corners_with_borders(batch: &mut GeomBatch, app: &dyn AppLike, i: &Intersection) {
+fn calculate_corners_with_borders(
+    batch: &mut GeomBatch,
+    app: &dyn ReadOnlyAppLike,
+    i: &Intersection,
+) {
     let map = app.map();
     let rank = i.get_rank(map);
     let surface_color = app.cs().zoomed_road_surface(LaneType::Sidewalk, rank);
@@ -6,7 +6,7 @@ use map_model::{Direction, DrivingSide, Lane, LaneID, LaneType, Map, Road, RoadI
 use widgetry::{Drawable, GeomBatch, GfxCtx, Prerender, RewriteColor};
 
 use crate::render::{DrawOptions, Renderable, OUTLINE_THICKNESS};
-use crate::{AppLike, ID};
+use crate::{ReadOnlyAppLike, ID};
 
 pub struct DrawLane {
     pub id: LaneID,
@@ -30,7 +30,23 @@ impl DrawLane {
         *self.draw_default.borrow_mut() = None;
     }
 
-    pub fn render<P: AsRef<Prerender>>(&self, prerender: &P, app: &dyn AppLike) -> GeomBatch {
+    /// Builds the geometry now, if it hasn't been already. Returns true if there was work to do.
+    pub fn prepare(&self, g: &mut GfxCtx, app: &dyn ReadOnlyAppLike) -> bool {
+        // Lazily calculate, because these are expensive to all do up-front, and most players won't
+        // exhaustively see every lane during a single session
+        let mut draw = self.draw_default.borrow_mut();
+        if draw.is_some() {
+            return false;
+        }
+        *draw = Some(g.upload(self.render(g, app)));
+        true
+    }
+
+    pub fn render<P: AsRef<Prerender>>(
+        &self,
+        prerender: &P,
+        app: &dyn ReadOnlyAppLike,
+    ) -> GeomBatch {
         let map = app.map();
         let lane = map.get_l(self.id);
         let road = map.get_r(lane.parent);
@@ -190,14 +206,9 @@ impl Renderable for DrawLane {
         ID::Lane(self.id)
     }
 
-    fn draw(&self, g: &mut GfxCtx, app: &dyn AppLike, _: &DrawOptions) {
-        // Lazily calculate, because these are expensive to all do up-front, and most players won't
-        // exhaustively see every lane during a single session
-        let mut draw = self.draw_default.borrow_mut();
-        if draw.is_none() {
-            *draw = Some(g.upload(self.render(g, app)));
-        }
-        g.redraw(draw.as_ref().unwrap());
+    fn draw(&self, g: &mut GfxCtx, app: &dyn ReadOnlyAppLike, _: &DrawOptions) {
+        self.prepare(g, app);
+        g.redraw(self.draw_default.borrow().as_ref().unwrap());
     }
 
     fn get_outline(&self, map: &Map) -> Polygon {
@@ -1,22 +1,26 @@
+use std::cell::{Ref, RefCell};
 use std::collections::HashMap;
 
 use aabb_quadtree::QuadTree;
 
 use abstutil::Timer;
-use geom::{Bounds, Polygon};
+use geom::{Bounds, Distance, Polygon};
 use map_model::{AreaID, BuildingID, BusStopID, IntersectionID, LaneID, Map, ParkingLotID, RoadID};
-use widgetry::{Color, Drawable, EventCtx, GeomBatch};
+use widgetry::{Color, Drawable, EventCtx, GeomBatch, GfxCtx, Prerender};
 
 use crate::colors::ColorScheme;
-use crate::options::Options;
 use crate::render::building::DrawBuilding;
 use crate::render::bus_stop::DrawBusStop;
 use crate::render::intersection::DrawIntersection;
 use crate::render::lane::DrawLane;
 use crate::render::parking_lot::DrawParkingLot;
 use crate::render::road::DrawRoad;
-use crate::render::{AgentCache, DrawArea, Renderable};
-use crate::{AppLike, ID};
+use crate::render::{AgentCache, DrawArea, Renderable, OUTLINE_THICKNESS};
+use crate::{ReadOnlyAppLike, ID};
+
+/// When zoomed in, at most this many objects just off-screen get their detailed geometry built
+/// per frame, so panning into new areas doesn't stall on building everything at once.
+const PREPARE_AHEAD_PER_FRAME: usize = 10;
 
 pub struct DrawMap {
     pub roads: Vec<DrawRoad>,
@@ -28,27 +32,29 @@ pub struct DrawMap {
     pub areas: Vec<DrawArea>,
 
     pub boundary_polygon: Drawable,
-    pub draw_all_unzoomed_roads_and_intersections: Drawable,
-    pub draw_all_buildings: Drawable,
-    pub draw_all_building_paths: Drawable,
-    pub draw_all_building_outlines: Drawable,
-    pub draw_all_unzoomed_parking_lots: Drawable,
     pub draw_all_areas: Drawable,
+    // These cover the whole map and are slow to build, so they're only built the first time
+    // they're drawn.
+    draw_all_unzoomed_roads_and_intersections: RefCell<Option<Drawable>>,
+    draw_all_unzoomed_parking_lots: RefCell<Option<Drawable>>,
+    draw_all_buildings: RefCell<Option<BuildingLayers>>,
 
     pub zorder_range: (isize, isize),
     pub show_zorder: isize,
 
     quadtree: QuadTree<ID>,
+    // Everything near these screen bounds has its detailed geometry built already
+    prepared_nearby: RefCell<Option<Bounds>>,
+}
+
+struct BuildingLayers {
+    buildings: Drawable,
+    paths: Drawable,
+    outlines: Drawable,
 }
 
 impl DrawMap {
-    pub fn new(
-        ctx: &mut EventCtx,
-        map: &Map,
-        opts: &Options,
-        cs: &ColorScheme,
-        timer: &mut Timer,
-    ) -> DrawMap {
+    pub fn new(ctx: &mut EventCtx, map: &Map, cs: &ColorScheme, timer: &mut Timer) -> DrawMap {
         let mut roads: Vec<DrawRoad> = Vec::new();
         let mut low_z = 0;
         let mut high_z = 0;
@@ -74,52 +80,25 @@ impl DrawMap {
             intersections.push(DrawIntersection::new(i, map));
         }
 
-        let draw_all_unzoomed_roads_and_intersections =
-            DrawMap::regenerate_unzoomed_layer(map, cs, ctx, timer);
-
         let mut buildings: Vec<DrawBuilding> = Vec::new();
-        let mut all_buildings = GeomBatch::new();
-        let mut all_building_paths = GeomBatch::new();
-        let mut all_building_outlines = GeomBatch::new();
         timer.start_iter("make DrawBuildings", map.all_buildings().len());
         for b in map.all_buildings() {
             timer.next();
-            buildings.push(DrawBuilding::new(
-                ctx,
-                b,
-                map,
-                cs,
-                opts,
-                &mut all_buildings,
-                &mut all_building_paths,
-                &mut all_building_outlines,
-            ));
+            buildings.push(DrawBuilding::new(b));
         }
-        timer.start("upload all buildings");
-        let draw_all_buildings = all_buildings.upload(ctx);
-        let draw_all_building_paths = all_building_paths.upload(ctx);
-        let draw_all_building_outlines = all_building_outlines.upload(ctx);
-        timer.stop("upload all buildings");
 
-        timer.start("make DrawParkingLot");
+        timer.start_iter("make DrawParkingLots", map.all_parking_lots().len());
         let mut parking_lots: Vec<DrawParkingLot> = Vec::new();
-        let mut all_unzoomed_parking_lots = GeomBatch::new();
         for pl in map.all_parking_lots() {
-            parking_lots.push(DrawParkingLot::new(
-                ctx,
-                pl,
-                cs,
-                &mut all_unzoomed_parking_lots,
-            ));
+            timer.next();
+            parking_lots.push(DrawParkingLot::new(pl));
         }
-        let draw_all_unzoomed_parking_lots = all_unzoomed_parking_lots.upload(ctx);
-        timer.stop("make DrawParkingLot");
 
         timer.start_iter("make DrawBusStop", map.all_bus_stops().len());
         let mut bus_stops: HashMap<BusStopID, DrawBusStop> = HashMap::new();
         for s in map.all_bus_stops().values() {
             timer.next();
-            bus_stops.insert(s.id, DrawBusStop::new(ctx, s, map, cs));
+            bus_stops.insert(s.id, DrawBusStop::new(s, map));
         }
 
         let mut areas: Vec<DrawArea> = Vec::new();
@@ -140,25 +119,30 @@ impl DrawMap {
 
         timer.start("create quadtree");
         let mut quadtree = QuadTree::default(map.get_bounds().as_bbox());
-        // TODO use iter chain if everything was boxed as a renderable...
-        for obj in &roads {
-            quadtree.insert_with_box(obj.get_id(), obj.get_outline(map).get_bounds().as_bbox());
+        // Calculating every outline is slow, so use bounds that're cheap to find and contain the
+        // outline.
+        for r in map.all_roads() {
+            let mut bounds = r.center_pts.get_bounds();
+            bounds.add_buffer(r.get_half_width(map) + OUTLINE_THICKNESS);
+            quadtree.insert_with_box(ID::Road(r.id), bounds.as_bbox());
         }
-        for obj in &lanes {
-            quadtree.insert_with_box(obj.get_id(), obj.get_outline(map).get_bounds().as_bbox());
+        for l in map.all_lanes() {
+            let mut bounds = l.lane_center_pts.get_bounds();
+            bounds.add_buffer(l.width / 2.0 + OUTLINE_THICKNESS);
+            quadtree.insert_with_box(ID::Lane(l.id), bounds.as_bbox());
         }
-        for obj in &intersections {
-            quadtree.insert_with_box(obj.get_id(), obj.get_outline(map).get_bounds().as_bbox());
+        for i in map.all_intersections() {
+            quadtree.insert_with_box(ID::Intersection(i.id), i.polygon.get_bounds().as_bbox());
         }
-        for obj in &buildings {
-            quadtree.insert_with_box(obj.get_id(), obj.get_outline(map).get_bounds().as_bbox());
+        for b in map.all_buildings() {
+            quadtree.insert_with_box(ID::Building(b.id), b.polygon.get_bounds().as_bbox());
         }
-        for obj in &parking_lots {
-            quadtree.insert_with_box(obj.get_id(), obj.get_outline(map).get_bounds().as_bbox());
+        for pl in map.all_parking_lots() {
+            quadtree.insert_with_box(ID::ParkingLot(pl.id), pl.polygon.get_bounds().as_bbox());
         }
         // Don't put BusStops in the quadtree
-        for obj in &areas {
-            quadtree.insert_with_box(obj.get_id(), obj.get_outline(map).get_bounds().as_bbox());
+        for a in map.all_areas() {
+            quadtree.insert_with_box(ID::Area(a.id), a.polygon.get_bounds().as_bbox());
         }
         timer.stop("create quadtree");
 
@@ -179,14 +163,13 @@ impl DrawMap {
             bus_stops,
             areas,
             boundary_polygon,
-            draw_all_unzoomed_roads_and_intersections,
-            draw_all_buildings,
-            draw_all_building_paths,
-            draw_all_building_outlines,
-            draw_all_unzoomed_parking_lots,
             draw_all_areas,
+            draw_all_unzoomed_roads_and_intersections: RefCell::new(None),
+            draw_all_unzoomed_parking_lots: RefCell::new(None),
+            draw_all_buildings: RefCell::new(None),
 
             quadtree,
+            prepared_nearby: RefCell::new(None),
 
             zorder_range: (low_z, high_z),
             show_zorder: high_z,
@@ -196,7 +179,7 @@ impl DrawMap {
     pub fn regenerate_unzoomed_layer(
         map: &Map,
         cs: &ColorScheme,
-        ctx: &EventCtx,
+        prerender: &Prerender,
         timer: &mut Timer,
     ) -> Drawable {
         timer.start("generate unzoomed roads and intersections");
@@ -240,11 +223,103 @@ impl DrawMap {
         for (_, poly, color) in unzoomed_pieces {
             unzoomed_batch.push(color, poly);
         }
-        let draw_all_unzoomed_roads_and_intersections = unzoomed_batch.upload(ctx);
+        let draw_all_unzoomed_roads_and_intersections = prerender.upload(unzoomed_batch);
         timer.stop("generate unzoomed roads and intersections");
         draw_all_unzoomed_roads_and_intersections
     }
 
+    /// Replaces the unzoomed roads and intersections, after the map has been edited.
+    pub fn set_unzoomed_layer(&mut self, draw: Drawable) {
+        self.draw_all_unzoomed_roads_and_intersections = RefCell::new(Some(draw));
+    }
+
+    /// Throws away all buildings, so they're rebuilt with the current options the next time
+    /// they're drawn.
+    pub fn clear_buildings(&mut self) {
+        self.draw_all_buildings = RefCell::new(None);
+    }
+
+    pub fn draw_all_unzoomed_roads_and_intersections(
+        &self,
+        g: &mut GfxCtx,
+        app: &dyn ReadOnlyAppLike,
+    ) {
+        let mut draw = self.draw_all_unzoomed_roads_and_intersections.borrow_mut();
+        if draw.is_none() {
+            let mut timer = Timer::new("lazily render unzoomed roads and intersections");
+            *draw = Some(DrawMap::regenerate_unzoomed_layer(
+                app.map(),
+                app.cs(),
+                g.prerender,
+                &mut timer,
+            ));
+        }
+        g.redraw(draw.as_ref().unwrap());
+    }
+
+    pub fn draw_all_unzoomed_parking_lots(&self, g: &mut GfxCtx, app: &dyn ReadOnlyAppLike) {
+        let mut draw = self.draw_all_unzoomed_parking_lots.borrow_mut();
+        if draw.is_none() {
+            let mut timer = Timer::new("lazily render unzoomed parking lots");
+            timer.start_iter("render parking lots", app.map().all_parking_lots().len());
+            let mut batch = GeomBatch::new();
+            for pl in app.map().all_parking_lots() {
+                timer.next();
+                DrawParkingLot::render_unzoomed(g, pl, app.cs(), &mut batch);
+            }
+            *draw = Some(g.upload(batch));
+        }
+        g.redraw(draw.as_ref().unwrap());
+    }
+
+    pub fn draw_all_buildings(&self, g: &mut GfxCtx, app: &dyn ReadOnlyAppLike) {
+        let layers = self.building_layers(g, app);
+        g.redraw(&layers.buildings);
+    }
+
+    pub fn draw_all_building_paths(&self, g: &mut GfxCtx, app: &dyn ReadOnlyAppLike) {
+        let layers = self.building_layers(g, app);
+        g.redraw(&layers.paths);
+    }
+
+    pub fn draw_all_building_outlines(&self, g: &mut GfxCtx, app: &dyn ReadOnlyAppLike) {
+        let layers = self.building_layers(g, app);
+        g.redraw(&layers.outlines);
+    }
+
+    fn building_layers(&self, g: &mut GfxCtx, app: &dyn ReadOnlyAppLike) -> Ref<BuildingLayers> {
+        if self.draw_all_buildings.borrow().is_none() {
+            let mut timer = Timer::new("lazily render all buildings");
+            let map = app.map();
+            let mut all_buildings = GeomBatch::new();
+            let mut all_building_paths = GeomBatch::new();
+            let mut all_building_outlines = GeomBatch::new();
+            timer.start_iter("render buildings", map.all_buildings().len());
+            for b in map.all_buildings() {
+                timer.next();
+                DrawBuilding::render(
+                    g,
+                    b,
+                    map,
+                    app.cs(),
+                    app.opts(),
+                    &mut all_buildings,
+                    &mut all_building_paths,
+                    &mut all_building_outlines,
+                );
+            }
+            timer.start("upload all buildings");
+            let layers = BuildingLayers {
+                buildings: g.upload(all_buildings),
+                paths: g.upload(all_building_paths),
+                outlines: g.upload(all_building_outlines),
+            };
+            timer.stop("upload all buildings");
+            *self.draw_all_buildings.borrow_mut() = Some(layers);
+        }
+        Ref::map(self.draw_all_buildings.borrow(), |x| x.as_ref().unwrap())
+    }
+
     // The alt to these is implementing std::ops::Index, but that's way more verbose!
     pub fn get_r(&self, id: RoadID) -> &DrawRoad {
         &self.roads[id.0]
@@ -278,7 +353,7 @@ impl DrawMap {
         &'a self,
         ctx: &EventCtx,
         id: ID,
-        app: &dyn AppLike,
+        app: &dyn ReadOnlyAppLike,
         agents: &'a mut AgentCache,
     ) -> Option<&'a dyn Renderable> {
         let on = match id {
@@ -384,9 +459,48 @@ impl DrawMap {
         borrows
     }
 
+    /// Objects build their detailed geometry lazily the first time they're drawn. Call this every
+    /// frame while zoomed in to build a few objects just off-screen ahead of time, so that panning
+    /// into them doesn't stall. Once everything nearby is built, this does nothing until the camera
+    /// moves.
+    pub fn prepare_nearby(&self, g: &mut GfxCtx, app: &dyn ReadOnlyAppLike) {
+        let screen = g.get_screen_bounds();
+        if self.prepared_nearby.borrow().as_ref() == Some(&screen) {
+            return;
+        }
+        let mut bounds = screen.clone();
+        bounds.add_buffer(Distance::meters(bounds.width().max(bounds.height()) / 2.0));
+
+        let mut budget = PREPARE_AHEAD_PER_FRAME;
+        for id in self.get_matching_objects(bounds) {
+            if budget == 0 {
+                // Keep going next frame
+                return;
+            }
+            let did_work = match id {
+                ID::Road(id) => self.get_r(id).prepare(g, app),
+                ID::Lane(id) => {
+                    let mut did_work = self.get_l(id).prepare(g, app);
+                    for bs in &app.map().get_l(id).bus_stops {
+                        did_work |= self.get_bs(*bs).prepare(g, app);
+                    }
+                    did_work
+                }
+                ID::Intersection(id) => self.get_i(id).prepare(g, app),
+                ID::ParkingLot(id) => self.get_pl(id).prepare(g, app),
+                // Buildings and areas are built up-front
+                _ => false,
+            };
+            if did_work {
+                budget -= 1;
+            }
+        }
+        *self.prepared_nearby.borrow_mut() = Some(screen);
+    }
+
     /// Build a single gigantic `GeomBatch` to render the entire map when zoomed in. Likely messes
     /// up Z-ordering.
-    pub fn zoomed_batch(ctx: &EventCtx, app: &dyn AppLike) -> GeomBatch {
+    pub fn zoomed_batch(ctx: &EventCtx, app: &dyn ReadOnlyAppLike) -> GeomBatch {
         // TODO This repeats code. There are other approaches, like making EventCtx intercept
         // "uploads" and instead save the batches.
         let mut batch = GeomBatch::new();
@@ -403,7 +517,7 @@ impl DrawMap {
         }
 
         for pl in map.all_parking_lots() {
-            batch.append(DrawParkingLot::new(ctx, pl, cs, &mut GeomBatch::new()).render(app));
+            batch.append(DrawParkingLot::new(pl).render(app));
         }
 
         for l in map.all_lanes() {
@@ -422,7 +536,7 @@ impl DrawMap {
         let mut paths_batch = GeomBatch::new();
         let mut outlines_batch = GeomBatch::new();
         for b in map.all_buildings() {
-            DrawBuilding::new(
+            DrawBuilding::render(
                 ctx,
                 b,
                 map,
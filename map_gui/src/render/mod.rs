@@ -6,7 +6,7 @@ use sim::{DrawCarInput, VehicleType};
 use widgetry::{GfxCtx, Prerender};
 
 use crate::colors::ColorScheme;
-pub use crate::render::agents::{AgentCache, UnzoomedAgents};
+pub use crate::render::agents::{AgentCache, AgentColoring, UnzoomedAgents};
 pub use crate::render::area::DrawArea;
 use crate::render::bike::DrawBike;
 pub use crate::render::building::DrawBuilding;
@@ -15,7 +15,7 @@ pub use crate::render::intersection::{calculate_corners, DrawIntersection};
 pub use crate::render::map::DrawMap;
 pub use crate::render::pedestrian::{DrawPedCrowd, DrawPedestrian};
 pub use crate::render::turn::{DrawMovement, DrawUberTurnGroup};
-use crate::{AppLike, ID};
+use crate::{ReadOnlyAppLike, ID};
 
 mod agents;
 mod area;
@@ -45,7 +45,7 @@ pub trait Renderable {
     // Renderables are better off storing the inner ID directly.
     fn get_id(&self) -> ID;
     // Only traffic signals need UI. :\
-    fn draw(&self, g: &mut GfxCtx, app: &dyn AppLike, opts: &DrawOptions);
+    fn draw(&self, g: &mut GfxCtx, app: &dyn ReadOnlyAppLike, opts: &DrawOptions);
     // Higher z-ordered objects are drawn later. Default to low so roads at -1 don't vanish.
     fn get_zorder(&self) -> isize {
         -5
@@ -4,11 +4,11 @@ use geom::{Distance, PolyLine, Polygon, Pt2D};
 use map_model::{
     osm, LaneType, Map, ParkingLot, ParkingLotID, NORMAL_LANE_THICKNESS, PARKING_LOT_SPOT_LENGTH,
 };
-use widgetry::{Drawable, EventCtx, GeomBatch, GfxCtx};
+use widgetry::{Drawable, GeomBatch, GfxCtx, Prerender};
 
 use crate::colors::ColorScheme;
 use crate::render::{DrawOptions, Renderable, OUTLINE_THICKNESS};
-use crate::{AppLike, ID};
+use crate::{ReadOnlyAppLike, ID};
 
 pub struct DrawParkingLot {
     pub id: ParkingLotID,
@@ -16,12 +16,20 @@ pub struct DrawParkingLot {
 }
 
 impl DrawParkingLot {
-    pub fn new(
-        ctx: &EventCtx,
+    pub fn new(lot: &ParkingLot) -> DrawParkingLot {
+        DrawParkingLot {
+            id: lot.id,
+            draw: RefCell::new(None),
+        }
+    }
+
+    /// Adds this parking lot to the batch covering every parking lot when unzoomed.
+    pub fn render_unzoomed<P: AsRef<Prerender>>(
+        prerender: &P,
         lot: &ParkingLot,
         cs: &ColorScheme,
         unzoomed_batch: &mut GeomBatch,
-    ) -> DrawParkingLot {
+    ) {
         unzoomed_batch.push(cs.parking_lot, lot.polygon.clone());
         for aisle in &lot.aisles {
             let aisle_thickness = NORMAL_LANE_THICKNESS / 2.0;
@@ -31,18 +39,13 @@ impl DrawParkingLot {
             );
         }
         unzoomed_batch.append(
-            GeomBatch::load_svg(ctx, "system/assets/map/parking.svg")
+            GeomBatch::load_svg(prerender, "system/assets/map/parking.svg")
                 .scale(0.05)
                 .centered_on(lot.polygon.polylabel()),
         );
-
-        DrawParkingLot {
-            id: lot.id,
-            draw: RefCell::new(None),
-        }
     }
 
-    pub fn render(&self, app: &dyn AppLike) -> GeomBatch {
+    pub fn render(&self, app: &dyn ReadOnlyAppLike) -> GeomBatch {
         let lot = app.map().get_pl(self.id);
 
         // Trim the front path line away from the sidewalk's center line, so that it doesn't
@@ -92,6 +95,16 @@ impl DrawParkingLot {
 
         batch
     }
+
+    /// Builds the geometry now, if it hasn't been already. Returns true if there was work to do.
+    pub fn prepare(&self, g: &mut GfxCtx, app: &dyn ReadOnlyAppLike) -> bool {
+        let mut draw = self.draw.borrow_mut();
+        if draw.is_some() {
+            return false;
+        }
+        *draw = Some(g.upload(self.render(app)));
+        true
+    }
 }
 
 impl Renderable for DrawParkingLot {
@@ -99,12 +112,9 @@ impl Renderable for DrawParkingLot {
         ID::ParkingLot(self.id)
     }
 
-    fn draw(&self, g: &mut GfxCtx, app: &dyn AppLike, _: &DrawOptions) {
-        let mut draw = self.draw.borrow_mut();
-        if draw.is_none() {
-            *draw = Some(g.upload(self.render(app)));
-        }
-        g.redraw(draw.as_ref().unwrap());
+    fn draw(&self, g: &mut GfxCtx, app: &dyn ReadOnlyAppLike, _: &DrawOptions) {
+        self.prepare(g, app);
+        g.redraw(self.draw.borrow().as_ref().unwrap());
     }
 
     fn get_zorder(&self) -> isize {
@@ -5,7 +5,7 @@ use widgetry::{Color, Drawable, GeomBatch, GfxCtx, Line, Prerender, Text};
 
 use crate::colors::ColorScheme;
 use crate::render::{DrawOptions, Renderable, OUTLINE_THICKNESS};
-use crate::{AppLike, ID};
+use crate::{ReadOnlyAppLike, ID};
 
 pub struct DrawPedestrian {
     pub id: PedestrianID,
@@ -172,7 +172,7 @@ impl Renderable for DrawPedestrian {
         ID::Pedestrian(self.id)
     }
 
-    fn draw(&self, g: &mut GfxCtx, _: &dyn AppLike, _: &DrawOptions) {
+    fn draw(&self, g: &mut GfxCtx, _: &dyn ReadOnlyAppLike, _: &DrawOptions) {
         g.redraw(&self.draw_default);
     }
 
@@ -257,7 +257,7 @@ impl Renderable for DrawPedCrowd {
         ID::PedCrowd(self.members.clone())
     }
 
-    fn draw(&self, g: &mut GfxCtx, _: &dyn AppLike, _: &DrawOptions) {
+    fn draw(&self, g: &mut GfxCtx, _: &dyn ReadOnlyAppLike, _: &DrawOptions) {
         g.redraw(&self.draw_default);
     }
 
@@ -5,7 +5,7 @@ use map_model::{LaneType, Map, Road, RoadID};
 use widgetry::{Drawable, GeomBatch, GfxCtx, Line, Prerender, Text};
 
 use crate::render::{DrawOptions, Renderable};
-use crate::{AppLike, ID};
+use crate::{ReadOnlyAppLike, ID};
 
 pub struct DrawRoad {
     pub id: RoadID,
@@ -23,7 +23,11 @@ impl DrawRoad {
         }
     }
 
-    pub fn render<P: AsRef<Prerender>>(&self, prerender: &P, app: &dyn AppLike) -> GeomBatch {
+    pub fn render<P: AsRef<Prerender>>(
+        &self,
+        prerender: &P,
+        app: &dyn ReadOnlyAppLike,
+    ) -> GeomBatch {
         let prerender = prerender.as_ref();
 
         let mut batch = GeomBatch::new();
@@ -110,6 +114,16 @@ impl DrawRoad {
     pub fn clear_rendering(&mut self) {
         *self.draw.borrow_mut() = None;
     }
+
+    /// Builds the geometry now, if it hasn't been already. Returns true if there was work to do.
+    pub fn prepare(&self, g: &mut GfxCtx, app: &dyn ReadOnlyAppLike) -> bool {
+        let mut draw = self.draw.borrow_mut();
+        if draw.is_some() {
+            return false;
+        }
+        *draw = Some(g.upload(self.render(g, app)));
+        true
+    }
 }
 
 impl Renderable for DrawRoad {
@@ -117,12 +131,9 @@ impl Renderable for DrawRoad {
         ID::Road(self.id)
     }
 
-    fn draw(&self, g: &mut GfxCtx, app: &dyn AppLike, _: &DrawOptions) {
-        let mut draw = self.draw.borrow_mut();
-        if draw.is_none() {
-            *draw = Some(g.upload(self.render(g, app)));
-        }
-        g.redraw(draw.as_ref().unwrap());
+    fn draw(&self, g: &mut GfxCtx, app: &dyn ReadOnlyAppLike, _: &DrawOptions) {
+        self.prepare(g, app);
+        g.redraw(self.draw.borrow().as_ref().unwrap());
     }
 
     fn get_outline(&self, map: &Map) -> Polygon {
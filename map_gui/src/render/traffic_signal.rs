@@ -7,7 +7,7 @@ use widgetry::{Color, GeomBatch, Line, Prerender, RewriteColor, Text};
 use crate::options::TrafficSignalStyle;
 use crate::render::intersection::make_crosswalk;
 use crate::render::BIG_ARROW_THICKNESS;
-use crate::AppLike;
+use crate::ReadOnlyAppLike;
 
 pub fn draw_signal_stage(
     prerender: &Prerender,
@@ -16,7 +16,7 @@ pub fn draw_signal_stage(
     i: IntersectionID,
     time_left: Option<Duration>,
     batch: &mut GeomBatch,
-    app: &dyn AppLike,
+    app: &dyn ReadOnlyAppLike,
     signal_style: TrafficSignalStyle,
 ) {
     let signal = app.map().get_traffic_signal(i);
@@ -195,7 +195,7 @@ pub fn draw_signal_stage(
 }
 
 pub fn draw_stage_number(
-    app: &dyn AppLike,
+    app: &dyn ReadOnlyAppLike,
     prerender: &Prerender,
     i: IntersectionID,
     idx: usize,
@@ -216,7 +216,7 @@ pub fn draw_stage_number(
 }
 
 fn draw_time_left(
-    app: &dyn AppLike,
+    app: &dyn ReadOnlyAppLike,
     prerender: &Prerender,
     stage: &Stage,
     i: IntersectionID,
@@ -9,7 +9,7 @@ use widgetry::{Color, GeomBatch, Prerender};
 
 use crate::colors::ColorScheme;
 use crate::render::{traffic_signal, BIG_ARROW_THICKNESS};
-use crate::AppLike;
+use crate::ReadOnlyAppLike;
 
 const TURN_ICON_ARROW_LENGTH: Distance = Distance::const_meters(1.5);
 
@@ -115,7 +115,7 @@ impl DrawMovement {
 
     pub fn draw_selected_movement(
         &self,
-        app: &dyn AppLike,
+        app: &dyn ReadOnlyAppLike,
         batch: &mut GeomBatch,
         next_priority: Option<TurnPriority>,
     ) {
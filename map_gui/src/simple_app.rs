@@ -11,7 +11,7 @@ use crate::options::Options;
 use crate::render::DrawMap;
 use crate::render::{DrawOptions, Renderable};
 use crate::tools::CameraState;
-use crate::{AppLike, ID};
+use crate::{AppLike, ReadOnlyAppLike, ID};
 
 /// Simple app state that just renders a static map, without any dynamic agents on the map.
 pub struct SimpleApp<T> {
@@ -43,10 +43,10 @@ impl<T: 'static> SimpleApp<T> {
             .unwrap_or(MapName::seattle("montlake"));
         args.done();
 
-        let cs = ColorScheme::new(ctx, opts.color_scheme);
+        let cs = ColorScheme::new(ctx, opts.color_scheme, opts.agent_color_scheme);
         // Start with a blank map
         let map = Map::blank();
-        let draw_map = DrawMap::new(ctx, &map, &opts, &cs, &mut Timer::throwaway());
+        let draw_map = DrawMap::new(ctx, &map, &cs, &mut Timer::throwaway());
         let app = SimpleApp {
             map,
             draw_map,
@@ -70,9 +70,10 @@ impl<T: 'static> SimpleApp<T> {
         g.clear(self.cs.void_background);
         g.redraw(&self.draw_map.boundary_polygon);
         g.redraw(&self.draw_map.draw_all_areas);
-        g.redraw(&self.draw_map.draw_all_unzoomed_parking_lots);
-        g.redraw(&self.draw_map.draw_all_unzoomed_roads_and_intersections);
-        g.redraw(&self.draw_map.draw_all_buildings);
+        self.draw_map.draw_all_unzoomed_parking_lots(g, self);
+        self.draw_map
+            .draw_all_unzoomed_roads_and_intersections(g, self);
+        self.draw_map.draw_all_buildings(g, self);
         // Not the building paths
 
         // Still show some shape selection when zoomed out.
@@ -113,10 +114,10 @@ impl<T: 'static> SimpleApp<T> {
                 ID::Building(_) => {
                     if !drawn_all_buildings {
                         if opts.show_building_paths {
-                            g.redraw(&self.draw_map.draw_all_building_paths);
+                            self.draw_map.draw_all_building_paths(g, self);
                         }
-                        g.redraw(&self.draw_map.draw_all_buildings);
-                        g.redraw(&self.draw_map.draw_all_building_outlines);
+                        self.draw_map.draw_all_buildings(g, self);
+                        self.draw_map.draw_all_building_outlines(g, self);
                         drawn_all_buildings = true;
                     }
                 }
@@ -133,6 +134,8 @@ impl<T: 'static> SimpleApp<T> {
                 g.draw_polygon(self.cs.selected, obj.get_outline(&self.map));
             }
         }
+
+        self.draw_map.prepare_nearby(g, self);
     }
 
     /// Assumes some defaults.
@@ -209,7 +212,7 @@ impl<T: 'static> SimpleApp<T> {
     }
 }
 
-impl<T: 'static> AppLike for SimpleApp<T> {
+impl<T: 'static> ReadOnlyAppLike for SimpleApp<T> {
     #[inline]
     fn map(&self) -> &Map {
         &self.map
@@ -223,21 +226,40 @@ impl<T: 'static> AppLike for SimpleApp<T> {
         &self.cs
     }
     #[inline]
-    fn mut_cs(&mut self) -> &mut ColorScheme {
-        &mut self.cs
-    }
-    #[inline]
     fn draw_map(&self) -> &DrawMap {
         &self.draw_map
     }
     #[inline]
-    fn mut_draw_map(&mut self) -> &mut DrawMap {
-        &mut self.draw_map
-    }
-    #[inline]
     fn opts(&self) -> &Options {
         &self.opts
     }
+
+    fn sim_time(&self) -> Time {
+        self.time
+    }
+
+    fn current_stage_and_remaining_time(&self, id: IntersectionID) -> (usize, Duration) {
+        let signal = self.map.get_traffic_signal(id);
+        let mut time_left = (self.time - Time::START_OF_DAY) % signal.simple_cycle_duration();
+        for (idx, stage) in signal.stages.iter().enumerate() {
+            if time_left < stage.stage_type.simple_duration() {
+                return (idx, time_left);
+            }
+            time_left -= stage.stage_type.simple_duration();
+        }
+        unreachable!()
+    }
+}
+
+impl<T: 'static> AppLike for SimpleApp<T> {
+    #[inline]
+    fn mut_cs(&mut self) -> &mut ColorScheme {
+        &mut self.cs
+    }
+    #[inline]
+    fn mut_draw_map(&mut self) -> &mut DrawMap {
+        &mut self.draw_map
+    }
     #[inline]
     fn mut_opts(&mut self) -> &mut Options {
         &mut self.opts
@@ -246,7 +268,7 @@ impl<T: 'static> AppLike for SimpleApp<T> {
     fn map_switched(&mut self, ctx: &mut EventCtx, map: Map, timer: &mut Timer) {
         CameraState::save(ctx.canvas, self.map.get_name());
         self.map = map;
-        self.draw_map = DrawMap::new(ctx, &self.map, &self.opts, &self.cs, timer);
+        self.draw_map = DrawMap::new(ctx, &self.map, &self.cs, timer);
         CameraState::load(ctx, self.map.get_name());
     }
 
@@ -269,22 +291,6 @@ impl<T: 'static> AppLike for SimpleApp<T> {
             warper: Warper::new(ctx, pt, target_cam_zoom),
         })
     }
-
-    fn sim_time(&self) -> Time {
-        self.time
-    }
-
-    fn current_stage_and_remaining_time(&self, id: IntersectionID) -> (usize, Duration) {
-        let signal = self.map.get_traffic_signal(id);
-        let mut time_left = (self.time - Time::START_OF_DAY) % signal.simple_cycle_duration();
-        for (idx, stage) in signal.stages.iter().enumerate() {
-            if time_left < stage.stage_type.simple_duration() {
-                return (idx, time_left);
-            }
-            time_left -= stage.stage_type.simple_duration();
-        }
-        unreachable!()
-    }
 }
 
 impl<T: 'static> SharedAppState for SimpleApp<T> {
@@ -5,7 +5,7 @@ use geom::{Circle, Distance, Line, Polygon, Pt2D};
 use map_model::{BuildingID, BusStopID, IntersectionID, LaneID, Map, ParkingLotID, RoadID};
 use widgetry::{Color, Drawable, EventCtx, Fill, GeomBatch, Line, LinearGradient, Text, Widget};
 
-use crate::AppLike;
+use crate::ReadOnlyAppLike;
 
 pub struct ColorDiscrete<'a> {
     map: &'a Map,
@@ -18,7 +18,7 @@ pub struct ColorDiscrete<'a> {
 
 impl<'a> ColorDiscrete<'a> {
     pub fn new<I: Into<String>>(
-        app: &'a dyn AppLike,
+        app: &'a dyn ReadOnlyAppLike,
         categories: Vec<(I, Color)>,
     ) -> ColorDiscrete<'a> {
         let mut unzoomed = GeomBatch::new();
@@ -115,6 +115,19 @@ impl ColorLegend {
         ])
     }
 
+    /// Several colored swatches with labels, arranged in one row or column.
+    pub fn entries(ctx: &mut EventCtx, entries: Vec<(Color, String)>, horizontal: bool) -> Widget {
+        let rows = entries
+            .into_iter()
+            .map(|(color, label)| ColorLegend::row(ctx, color, label))
+            .collect::<Vec<_>>();
+        if horizontal {
+            Widget::custom_row(rows.into_iter().map(|w| w.margin_right(16)).collect())
+        } else {
+            Widget::col(rows)
+        }
+    }
+
     pub fn gradient<I: Into<String>>(
         ctx: &mut EventCtx,
         scale: &ColorScale,
@@ -260,7 +273,7 @@ pub struct ColorNetwork<'a> {
 }
 
 impl<'a> ColorNetwork<'a> {
-    pub fn new(app: &'a dyn AppLike) -> ColorNetwork {
+    pub fn new(app: &'a dyn ReadOnlyAppLike) -> ColorNetwork {
         let mut unzoomed = GeomBatch::new();
         unzoomed.push(
             app.cs().fade_map_dark,
@@ -353,6 +366,7 @@ impl<'a> ColorNetwork<'a> {
     }
 }
 
+#[derive(Clone)]
 pub struct ColorScale(pub Vec<Color>);
 
 impl ColorScale {
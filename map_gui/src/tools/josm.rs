@@ -0,0 +1,51 @@
+use anyhow::Result;
+
+use geom::LonLat;
+use map_model::osm::OsmID;
+
+/// Asks a running copy of JOSM to download the area covering `pts` and select one object in it,
+/// using the remote control API (https://josm.openstreetmap.de/wiki/Help/RemoteControlCommands).
+/// This only works if JOSM is running locally with remote control enabled.
+pub fn open_in_josm(id: OsmID, pts: Vec<LonLat>) -> Result<()> {
+    if pts.is_empty() {
+        bail!("no area to load around {}", id);
+    }
+    let left = pts.iter().map(|pt| pt.x()).fold(f64::MAX, f64::min);
+    let right = pts.iter().map(|pt| pt.x()).fold(f64::MIN, f64::max);
+    let bottom = pts.iter().map(|pt| pt.y()).fold(f64::MAX, f64::min);
+    let top = pts.iter().map(|pt| pt.y()).fold(f64::MIN, f64::max);
+    let select = match id {
+        OsmID::Node(n) => format!("node{}", n.0),
+        OsmID::Way(w) => format!("way{}", w.0),
+        OsmID::Relation(r) => format!("relation{}", r.0),
+    };
+    let url = format!(
+        "http://127.0.0.1:8111/load_and_zoom?left={}&right={}&top={}&bottom={}&select={}",
+        left, right, top, bottom, select
+    );
+    info!("Sending {} to JOSM", url);
+    send(&url)
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn send(url: &str) -> Result<()> {
+    // JOSM answers immediately if it's listening; don't hang the UI if something else is
+    let client = reqwest::blocking::Client::builder()
+        .timeout(std::time::Duration::from_secs(5))
+        .build()?;
+    let resp = client.get(url).send().map_err(|err| {
+        anyhow!(
+            "couldn't reach JOSM. Is it running, with remote control enabled? ({})",
+            err
+        )
+    })?;
+    if !resp.status().is_success() {
+        bail!("JOSM refused the request: {}", resp.text()?);
+    }
+    Ok(())
+}
+
+#[cfg(target_arch = "wasm32")]
+fn send(_: &str) -> Result<()> {
+    bail!("opening objects in JOSM isn't supported on the web")
+}
@@ -409,9 +409,9 @@ impl<A: AppLike + 'static, T: MinimapControls<A>> Minimap<A, T> {
         let draw_map = app.draw_map();
         g.redraw(&draw_map.boundary_polygon);
         g.redraw(&draw_map.draw_all_areas);
-        g.redraw(&draw_map.draw_all_unzoomed_parking_lots);
-        g.redraw(&draw_map.draw_all_unzoomed_roads_and_intersections);
-        g.redraw(&draw_map.draw_all_buildings);
+        draw_map.draw_all_unzoomed_parking_lots(g, app);
+        draw_map.draw_all_unzoomed_roads_and_intersections(g, app);
+        draw_map.draw_all_buildings(g, app);
         for draw in extra {
             g.redraw(draw);
         }
@@ -8,16 +8,18 @@ pub use self::camera::CameraState;
 pub use self::city_picker::CityPicker;
 pub use self::colors::{ColorDiscrete, ColorLegend, ColorNetwork, ColorScale, DivergingScale};
 pub use self::heatmap::{make_heatmap, Grid, HeatmapOptions};
+pub use self::josm::open_in_josm;
 pub use self::minimap::{Minimap, MinimapControls};
 pub use self::navigate::Navigator;
 pub use self::turn_explorer::TurnExplorer;
 pub use self::ui::{ChooseSomething, PopupMsg, PromptInput};
-use crate::AppLike;
+use crate::ReadOnlyAppLike;
 
 mod camera;
 mod city_picker;
 mod colors;
 mod heatmap;
+mod josm;
 mod minimap;
 mod navigate;
 mod turn_explorer;
@@ -42,7 +44,7 @@ pub fn loading_tips() -> Text {
 }
 
 /// Make it clear the map can't be interacted with right now.
-pub fn grey_out_map(g: &mut GfxCtx, app: &dyn AppLike) {
+pub fn grey_out_map(g: &mut GfxCtx, app: &dyn ReadOnlyAppLike) {
     g.fork_screenspace();
     // TODO - OSD height
     g.draw_polygon(
@@ -177,5 +179,10 @@ pub fn nice_country_name(code: &str) -> &str {
 }
 
 pub fn open_browser<I: Into<String>>(url: I) {
-    let _ = webbrowser::open(&url.into());
+    let url = url.into();
+    if let Err(err) = webbrowser::open(&url) {
+        // Some platforms have no way to launch a browser; at least leave the URL somewhere it can
+        // be copied from
+        warn!("Couldn't open a browser ({}). Visit {} manually", err, url);
+    }
 }
@@ -6,7 +6,9 @@ use petgraph::graphmap::DiGraphMap;
 
 use geom::{Distance, Duration, Speed};
 
-pub use self::walking::{all_walking_costs_from, WalkingOptions};
+pub use self::walking::{
+    all_walking_costs_from, all_walking_costs_from_bus_stop, walkshed_from, WalkingOptions,
+};
 use crate::pathfind::build_graph_for_vehicles;
 pub use crate::pathfind::{driving_cost, WalkingNode};
 use crate::{BuildingID, LaneID, Map, PathConstraints};
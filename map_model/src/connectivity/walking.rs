@@ -1,10 +1,10 @@
 use std::cmp::Ordering;
-use std::collections::{BinaryHeap, HashMap};
+use std::collections::{BTreeSet, BinaryHeap, HashMap};
 
 use geom::{Distance, Duration, Speed};
 
 use crate::pathfind::WalkingNode;
-use crate::{BuildingID, LaneType, Map, PathConstraints};
+use crate::{BuildingID, BusStopID, LaneID, LaneType, Map, PathConstraints, Position};
 
 #[derive(Clone)]
 pub struct WalkingOptions {
@@ -71,7 +71,121 @@ pub fn all_walking_costs_from(
     time_limit: Duration,
     opts: WalkingOptions,
 ) -> HashMap<BuildingID, Duration> {
-    let start_lane = map.get_l(map.get_b(start).sidewalk_pos.lane());
+    let cost_per_node =
+        walking_costs_per_node(map, map.get_b(start).sidewalk_pos, time_limit, &opts);
+    building_costs(map, &cost_per_node, &opts)
+}
+
+/// Starting from a bus stop, calculate the cost to all buildings and other bus stops. Like
+/// `all_walking_costs_from`, unreachable places and those more than the time_limit away aren't
+/// included.
+pub fn all_walking_costs_from_bus_stop(
+    map: &Map,
+    start: BusStopID,
+    time_limit: Duration,
+    opts: WalkingOptions,
+) -> (HashMap<BuildingID, Duration>, HashMap<BusStopID, Duration>) {
+    let cost_per_node =
+        walking_costs_per_node(map, map.get_bs(start).sidewalk_pos, time_limit, &opts);
+
+    let mut stops = HashMap::new();
+    for bs in map.all_bus_stops().values() {
+        if bs.id == start {
+            continue;
+        }
+        if let Some(cost) = cost_to(map, &cost_per_node, bs.sidewalk_pos, &opts) {
+            stops.insert(bs.id, cost);
+        }
+    }
+    (building_costs(map, &cost_per_node, &opts), stops)
+}
+
+fn building_costs(
+    map: &Map,
+    cost_per_node: &HashMap<WalkingNode, Duration>,
+    opts: &WalkingOptions,
+) -> HashMap<BuildingID, Duration> {
+    let mut results = HashMap::new();
+    for b in map.all_buildings() {
+        if let Some(cost) = cost_to(map, cost_per_node, b.sidewalk_pos, opts) {
+            results.insert(b.id, cost);
+        }
+    }
+    results
+}
+
+// Assign a cost based on which end of the sidewalk the position is closest to
+// TODO We could try to get a little more accurate by accounting for the distance from that end of
+// the sidewalk to the position
+fn cost_to(
+    map: &Map,
+    cost_per_node: &HashMap<WalkingNode, Duration>,
+    pos: Position,
+    opts: &WalkingOptions,
+) -> Option<Duration> {
+    let cost = cost_per_node.get(&WalkingNode::closest(pos, map))?;
+    let sidewalk_len = map.get_l(pos.lane()).length();
+    let dist = pos.dist_along();
+    let distance_from_closest_node = if sidewalk_len - dist <= dist {
+        dist
+    } else {
+        sidewalk_len - dist
+    };
+    Some(*cost + distance_from_closest_node / opts.walking_speed)
+}
+
+/// Starting from one building, find the parts of every sidewalk reachable within the time_limit.
+/// Returns (sidewalk, start distance, end distance) for each reachable piece. A sidewalk reached
+/// from both ends without enough time to cross all of it yields two pieces.
+pub fn walkshed_from(
+    map: &Map,
+    start: BuildingID,
+    time_limit: Duration,
+    opts: WalkingOptions,
+) -> Vec<(LaneID, Distance, Distance)> {
+    let cost_per_node =
+        walking_costs_per_node(map, map.get_b(start).sidewalk_pos, time_limit, &opts);
+
+    let mut lanes: BTreeSet<LaneID> = BTreeSet::new();
+    for node in cost_per_node.keys() {
+        if let WalkingNode::SidewalkEndpoint(l, _) = node {
+            lanes.insert(*l);
+        }
+    }
+
+    let mut results = Vec::new();
+    for l in lanes {
+        let len = map.get_l(l).length();
+        // How far along the sidewalk can we get from each end with the time left?
+        let from_start = cost_per_node
+            .get(&WalkingNode::SidewalkEndpoint(l, false))
+            .map(|cost| (opts.walking_speed * (time_limit - *cost)).min(len))
+            .unwrap_or(Distance::ZERO);
+        let from_end = cost_per_node
+            .get(&WalkingNode::SidewalkEndpoint(l, true))
+            .map(|cost| (opts.walking_speed * (time_limit - *cost)).min(len))
+            .unwrap_or(Distance::ZERO);
+        if from_start + from_end >= len {
+            results.push((l, Distance::ZERO, len));
+            continue;
+        }
+        if from_start > Distance::ZERO {
+            results.push((l, Distance::ZERO, from_start));
+        }
+        if from_end > Distance::ZERO {
+            results.push((l, len - from_end, len));
+        }
+    }
+    results
+}
+
+fn walking_costs_per_node(
+    map: &Map,
+    start: Position,
+    time_limit: Duration,
+    opts: &WalkingOptions,
+) -> HashMap<WalkingNode, Duration> {
+    let start_lane = map.get_l(start.lane());
     if start_lane.lane_type == LaneType::Shoulder && !opts.allow_shoulders {
         return HashMap::new();
     }
@@ -79,7 +193,7 @@ pub fn all_walking_costs_from(
     let mut queue: BinaryHeap<Item> = BinaryHeap::new();
     queue.push(Item {
         cost: Duration::ZERO,
-        node: WalkingNode::closest(map.get_b(start).sidewalk_pos, map),
+        node: WalkingNode::closest(start, map),
     });
 
     let mut cost_per_node: HashMap<WalkingNode, Duration> = HashMap::new();
@@ -119,23 +233,5 @@ pub fn all_walking_costs_from(
         }
     }
 
-    let mut results = HashMap::new();
-    // Assign every building a cost based on which end of the sidewalk it's closest to
-    // TODO We could try to get a little more accurate by accounting for the distance from that
-    // end of the sidewalk to the building
-    for b in map.all_buildings() {
-        if let Some(cost) = cost_per_node.get(&WalkingNode::closest(b.sidewalk_pos, map)) {
-            let sidewalk_len = map.get_l(b.sidewalk()).length();
-            let bldg_dist = b.sidewalk_pos.dist_along();
-            let distance_from_closest_node = if sidewalk_len - bldg_dist <= bldg_dist {
-                bldg_dist
-            } else {
-                sidewalk_len - bldg_dist
-            };
-            let total_cost = *cost + distance_from_closest_node / opts.walking_speed;
-            results.insert(b.id, total_cost);
-        }
-    }
-
-    results
+    cost_per_node
 }
@@ -3,11 +3,12 @@
 //! <https://a-b-street.github.io/docs/map/edits.html>.
 
 use std::collections::{BTreeMap, BTreeSet};
+use std::hash::Hasher;
 
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
 
-use abstutil::{retain_btreemap, retain_btreeset, Timer};
+use abstutil::{retain_btreemap, retain_btreeset, Fnv, Timer};
 use geom::{Speed, Time};
 
 pub use self::perma::PermanentMapEdits;
@@ -232,6 +233,18 @@ impl MapEdits {
         }
     }
 
+    /// Summarizes what these edits change, ignoring the name and description. Two sets of edits
+    /// with the same checksum almost certainly have the same effect, even across builds.
+    pub fn checksum(&self, map: &Map) -> u64 {
+        let mut perma = self.to_permanent(map);
+        perma.edits_name = String::new();
+        perma.proposal_description.clear();
+        perma.proposal_link = None;
+        let mut hasher = Fnv::new();
+        hasher.write(abstutil::to_json(&perma).as_bytes());
+        hasher.finish()
+    }
+
     /// Pick apart changed_roads and figure out if an entire road was edited, or just a few lanes.
     pub fn changed_lanes(&self, map: &Map) -> (BTreeSet<LaneID>, BTreeSet<RoadID>) {
         let mut lanes = BTreeSet::new();
@@ -48,7 +48,9 @@ pub use crate::objects::intersection::{Intersection, IntersectionID, Intersectio
 pub use crate::objects::lane::{Lane, LaneID, LaneType, PARKING_LOT_SPOT_LENGTH};
 pub use crate::objects::parking_lot::{ParkingLot, ParkingLotID};
 pub use crate::objects::road::{DirectedRoadID, Direction, Road, RoadID};
+pub use crate::objects::signal_timing::{Phase, SignalTiming, TurnGroup, VariableTiming};
 pub use crate::objects::stop_signs::{ControlStopSign, RoadWithStopSign};
+pub use crate::objects::tag_lints::TagFinding;
 pub use crate::objects::traffic_signals::{ControlTrafficSignal, Stage, StageType};
 pub use crate::objects::turn::{
     CompressedMovementID, Movement, MovementID, Turn, TurnID, TurnPriority, TurnType,
@@ -107,6 +107,7 @@ pub fn make_all_buildings(
                         b.osm_tags.is("building", "parking") || b.osm_tags.is("amenity", "parking"),
                     )
                 },
+                elevation: b.elevation,
                 osm_tags: if keep_bldg_tags {
                     b.osm_tags.clone()
                 } else {
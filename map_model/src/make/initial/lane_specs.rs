@@ -9,6 +9,9 @@ use crate::{
     SERVICE_ROAD_LANE_THICKNESS, SHOULDER_THICKNESS, SIDEWALK_THICKNESS,
 };
 
+const DIAGONAL_PARKING_THICKNESS: Distance = Distance::const_meters(4.5);
+const PERPENDICULAR_PARKING_THICKNESS: Distance = Distance::const_meters(5.0);
+
 #[derive(PartialEq)]
 pub struct LaneSpec {
     pub lt: LaneType,
@@ -266,16 +269,15 @@ pub fn get_lane_specs_ltr(tags: &Tags, cfg: &MapConfig) -> Vec<LaneSpec> {
     }
 
     if driving_lane == LaneType::Driving {
-        let has_parking = vec!["parallel", "diagonal", "perpendicular"];
-        let parking_lane_fwd = tags.is_any(osm::PARKING_RIGHT, has_parking.clone())
-            || tags.is_any(osm::PARKING_BOTH, has_parking.clone());
-        let parking_lane_back = tags.is_any(osm::PARKING_LEFT, has_parking.clone())
-            || tags.is_any(osm::PARKING_BOTH, has_parking);
-        if parking_lane_fwd {
-            fwd_side.push(fwd(LaneType::Parking));
+        if let Some(width) = parking_lane_width(tags, osm::PARKING_RIGHT) {
+            let mut spec = fwd(LaneType::Parking);
+            spec.width = width;
+            fwd_side.push(spec);
         }
-        if parking_lane_back {
-            back_side.push(back(LaneType::Parking));
+        if let Some(width) = parking_lane_width(tags, osm::PARKING_LEFT) {
+            let mut spec = back(LaneType::Parking);
+            spec.width = width;
+            back_side.push(spec);
         }
     }
 
@@ -347,6 +349,20 @@ pub fn get_lane_specs_ltr(tags: &Tags, cfg: &MapConfig) -> Vec<LaneSpec> {
     assemble_ltr(fwd_side, back_side, cfg.driving_side)
 }
 
+// If one side of the road has parking, how wide the lane is. Cars parked at an angle stick out
+// further than parallel parking. A tag for this side overrides one for both sides, even if it
+// says there's no parking.
+fn parking_lane_width(tags: &Tags, side: &str) -> Option<Distance> {
+    let value = tags.get(side).or_else(|| tags.get(osm::PARKING_BOTH))?;
+    match value.as_str() {
+        "parallel" | "marked" => Some(NORMAL_LANE_THICKNESS),
+        "diagonal" => Some(DIAGONAL_PARKING_THICKNESS),
+        "perpendicular" => Some(PERPENDICULAR_PARKING_THICKNESS),
+        // no_parking, no_stopping, fire_lane, separate, etc
+        _ => None,
+    }
+}
+
 fn assemble_ltr(
     mut fwd_side: Vec<LaneSpec>,
     mut back_side: Vec<LaneSpec>,
@@ -435,6 +451,19 @@ mod tests {
                 "spddddbbps",
                 "vvvv^^v^^^",
             ),
+            (
+                // I didn't look for a real example of this
+                "https://www.openstreetmap.org/way/353690151",
+                vec![
+                    "lanes=4",
+                    "sidewalk=both",
+                    "parking:lane:both=parallel",
+                    "parking:lane:right=no_parking",
+                ],
+                DrivingSide::Right,
+                "spdddds",
+                "vvvv^^^",
+            ),
             (
                 "https://www.openstreetmap.org/way/389654080",
                 vec![
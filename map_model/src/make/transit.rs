@@ -74,6 +74,7 @@ fn make_route(
                             driving_pos,
                             sidewalk_pos,
                             is_train_stop: !r.is_bus,
+                            osm_tags: stop.osm_tags.clone(),
                         },
                     );
                     id
@@ -129,6 +130,7 @@ fn make_route(
         }
     }
 
+    let spawn_times = r.spawn_times.clone().unwrap_or_else(default_spawn_times);
     let route = BusRoute {
         id: BusRouteID(map.bus_routes.len()),
         full_name: r.full_name.clone(),
@@ -139,8 +141,8 @@ fn make_route(
         route_type,
         start,
         end_border,
-        spawn_times: default_spawn_times(),
-        orig_spawn_times: default_spawn_times(),
+        spawn_times: spawn_times.clone(),
+        orig_spawn_times: spawn_times,
     };
 
     let mut debug_route = format!("All parts of the route:");
@@ -256,12 +258,11 @@ impl Matcher {
         // We already figured out what side of the road we're on
         let (r, dir) = stop.matched_road.unwrap();
         let r = map.get_r(map.find_r_by_osm_id(r)?);
-        // Prefer the rightmost match. DON'T use find_closest_lane here; we only want one side of
-        // the road.
+        // Prefer the match closest to the curb. DON'T use find_closest_lane here; we only want one
+        // side of the road.
         let l = map.get_l(
-            r.children(dir)
+            r.children_from_curb(dir, map)
                 .iter()
-                .rev()
                 .find(|(l, _)| route_type.can_use(map.get_l(*l), map))
                 .ok_or_else(|| anyhow!("{} {}, doesn't have a bus or driving lane", r.id, dir))?
                 .0,
@@ -44,6 +44,27 @@ pub enum DrivingSide {
     Left,
 }
 
+impl DrivingSide {
+    /// The side of the road people usually drive on in a country, identified by its lowercase
+    /// two-letter ISO 3166-1 code (the same codes used for `CityName::country`).
+    pub fn for_country(country: &str) -> DrivingSide {
+        if LEFT_HAND_TRAFFIC_COUNTRIES.contains(&country) {
+            DrivingSide::Left
+        } else {
+            DrivingSide::Right
+        }
+    }
+}
+
+// Everywhere else drives on the right
+const LEFT_HAND_TRAFFIC_COUNTRIES: [&str; 70] = [
+    "ag", "ai", "au", "bb", "bd", "bm", "bn", "bs", "bt", "bw", "ck", "cy", "dm", "fj", "fk", "gb",
+    "gd", "gg", "gy", "hk", "id", "ie", "im", "in", "je", "jm", "jp", "ke", "ki", "kn", "ky", "lc",
+    "lk", "ls", "mo", "ms", "mt", "mu", "mv", "mw", "my", "mz", "na", "np", "nr", "nu", "nz", "pk",
+    "pn", "sb", "sc", "sg", "sh", "sr", "sz", "tc", "th", "tl", "to", "tt", "tv", "tz", "ug", "vc",
+    "vg", "vi", "ws", "za", "zm", "zw",
+];
+
 impl Map {
     pub fn new(path: String, timer: &mut Timer) -> Map {
         if path.contains("/maps/") {
@@ -41,6 +41,8 @@ pub struct Building {
     pub amenities: Vec<Amenity>,
     pub bldg_type: BuildingType,
     pub parking: OffstreetParking,
+    /// Of the ground at the center. Zero when the map was imported without elevation data.
+    pub elevation: Distance,
     /// Depending on options while importing, these might be empty, to save file space.
     pub osm_tags: Tags,
 
@@ -5,7 +5,7 @@ use std::fmt;
 
 use serde::{Deserialize, Serialize};
 
-use abstutil::{deserialize_usize, serialize_usize};
+use abstutil::{deserialize_usize, serialize_usize, Tags};
 use geom::Time;
 
 use crate::{osm, LaneID, Map, PathConstraints, PathRequest, Position};
@@ -48,6 +48,8 @@ pub struct BusStop {
     pub sidewalk_pos: Position,
     /// If it's both, train overrides bus
     pub is_train_stop: bool,
+    /// From the stop and its platform, if they're mapped in OSM
+    pub osm_tags: Tags,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
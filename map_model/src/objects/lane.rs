@@ -229,6 +229,18 @@ impl Lane {
         }
     }
 
+    /// Returns [-1.0, 1.0]. 0 is flat, positive is uphill in the direction of travel, negative is
+    /// downhill.
+    pub fn percent_incline(&self, map: &Map) -> f64 {
+        let road = map.get_r(self.parent);
+        let grade = road.percent_grade(map);
+        if road.dir(self.id) == Direction::Fwd {
+            grade
+        } else {
+            -grade
+        }
+    }
+
     pub fn get_turn_restrictions(&self, road: &Road) -> Option<BTreeSet<TurnType>> {
         if !self.is_driving() {
             return None;
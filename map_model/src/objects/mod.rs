@@ -5,7 +5,9 @@ pub mod intersection;
 pub mod lane;
 pub mod parking_lot;
 pub mod road;
+pub mod signal_timing;
 pub mod stop_signs;
+pub mod tag_lints;
 pub mod traffic_signals;
 pub mod turn;
 pub mod zone;
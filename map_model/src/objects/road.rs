@@ -6,10 +6,11 @@ use serde::{Deserialize, Serialize};
 use abstutil::{deserialize_usize, serialize_usize, Tags};
 use geom::{Distance, PolyLine, Polygon, Speed};
 
+use crate::objects::tag_lints::lint_tags;
 use crate::raw::{OriginalRoad, RestrictionType};
 use crate::{
-    osm, AccessRestrictions, BusStopID, IntersectionID, Lane, LaneID, LaneType, Map,
-    PathConstraints, Zone,
+    osm, AccessRestrictions, BusStopID, DrivingSide, IntersectionID, Lane, LaneID, LaneType, Map,
+    PathConstraints, TagFinding, Zone,
 };
 
 #[derive(Clone, Copy, Debug, Eq, Hash, PartialEq, PartialOrd, Ord, Serialize, Deserialize)]
@@ -337,6 +338,17 @@ impl Road {
         self.osm_tags.is(osm::HIGHWAY, "service")
     }
 
+    /// Looks for OSM tags that don't match the lanes generated from them. If the road has been
+    /// edited, the findings compare against the edited lanes.
+    pub fn lint_tags(&self) -> Vec<TagFinding> {
+        let lanes: Vec<(Direction, LaneType)> = self
+            .lanes_ltr
+            .iter()
+            .map(|(_, dir, lt)| (*dir, *lt))
+            .collect();
+        lint_tags(&self.osm_tags, &lanes)
+    }
+
     pub fn is_cycleway(&self) -> bool {
         let mut bike = false;
         for (_, _, lt) in self.lanes_ltr() {
@@ -428,8 +440,9 @@ impl Road {
         result
     }
 
-    /// lane must belong to this road. Offset 0 is the centermost lane on each side of a road, then
-    /// it counts up from there.
+    /// lane must belong to this road. Offset 0 is the leftmost lane from the perspective of
+    /// somebody traveling in that direction, then it counts up from there. That's the centermost
+    /// lane when driving on the right, and the lane along the curb when driving on the left.
     pub(crate) fn dir_and_offset(&self, lane: LaneID) -> (Direction, usize) {
         for &dir in [Direction::Fwd, Direction::Back].iter() {
             if let Some(idx) = self.children(dir).iter().position(|pair| pair.0 == lane) {
@@ -448,6 +461,17 @@ impl Road {
         }
     }
 
+    /// Lanes going in one direction, starting from the curb (the right side when driving on the
+    /// right) and moving towards the center of the road.
+    pub(crate) fn children_from_curb(&self, dir: Direction, map: &Map) -> Vec<(LaneID, LaneType)> {
+        let mut lanes = self.children(dir);
+        // children() starts from the driver's left
+        if map.get_config().driving_side == DrivingSide::Right {
+            lanes.reverse();
+        }
+        lanes
+    }
+
     /// Returns lanes from the "center" going out
     pub(crate) fn incoming_lanes(&self, i: IntersectionID) -> Vec<(LaneID, LaneType)> {
         if self.src_i == i {
@@ -0,0 +1,301 @@
+//! A simple JSON format for traffic signal timing, meant to be read and edited by hand or by other
+//! tools. Unlike `traffic_signal_data`, movements are named by compass directions, so nothing
+//! refers to OSM IDs or the IDs of this map. For example:
+//!
+//! ```json
+//! {
+//!   "offset_seconds": 0.0,
+//!   "phases": [
+//!     {
+//!       "duration_seconds": 30.0,
+//!       "variable": null,
+//!       "protected": [
+//!         { "from": "N", "to": "S", "crosswalk": false },
+//!         { "from": "E", "to": "N", "crosswalk": true }
+//!       ],
+//!       "permitted": [
+//!         { "from": "N", "to": "E", "crosswalk": false }
+//!       ]
+//!     },
+//!     {
+//!       "duration_seconds": 10.0,
+//!       "variable": { "delay_seconds": 2.0, "additional_seconds": 20.0 },
+//!       "protected": [],
+//!       "permitted": []
+//!     }
+//!   ]
+//! }
+//! ```
+//!
+//! A road's direction is where it leaves the intersection, as seen from the middle: N, NE, E, SE,
+//! S, SW, W, or NW. If two roads at the intersection share one of those, then the finer N, NNE,
+//! NE, ENE, etc are used for all of them. `{ "from": "N", "to": "E" }` is traffic arriving on the
+//! road to the north and leaving on the road to the east. For crosswalks, `from` is the road being
+//! crossed, and `to` is the direction people walk, always one of the coarser 8.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use geom::{Angle, Duration};
+
+use crate::{
+    ControlTrafficSignal, IntersectionID, Map, Movement, MovementID, RoadID, Stage, StageType,
+};
+
+const COMPASS: [&str; 16] = [
+    "N", "NNE", "NE", "ENE", "E", "ESE", "SE", "SSE", "S", "SSW", "SW", "WSW", "W", "WNW", "NW",
+    "NNW",
+];
+
+/// The timing of one traffic signal
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SignalTiming {
+    /// Relative to a central clock, delay the first phase by this many seconds.
+    pub offset_seconds: f64,
+    /// The signal repeatedly cycles through these phases, in order.
+    pub phases: Vec<Phase>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Phase {
+    /// How long the phase lasts. If it's variable, this is the minimum.
+    pub duration_seconds: f64,
+    /// Only for phases that last longer when there's demand
+    pub variable: Option<VariableTiming>,
+    /// These movements have a green light.
+    pub protected: Vec<TurnGroup>,
+    /// These movements may go after yielding, like an unprotected left turn.
+    pub permitted: Vec<TurnGroup>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct VariableTiming {
+    /// The phase ends after this long without demand.
+    pub delay_seconds: f64,
+    /// The phase can be extended by up to this long.
+    pub additional_seconds: f64,
+}
+
+/// All of the turns from one road to another, or one direction over a crosswalk
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct TurnGroup {
+    pub from: String,
+    pub to: String,
+    pub crosswalk: bool,
+}
+
+impl fmt::Display for TurnGroup {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.crosswalk {
+            write!(
+                f,
+                "crosswalk over the {} road, heading {}",
+                self.from, self.to
+            )
+        } else {
+            write!(f, "movement from {} to {}", self.from, self.to)
+        }
+    }
+}
+
+impl ControlTrafficSignal {
+    /// Describes this signal's timing in the simple format. Fails if two movements through the
+    /// intersection can't be told apart by their directions.
+    pub fn export_timing(&self, map: &Map) -> Result<SignalTiming> {
+        let groups = turn_groups(self.id, &self.movements, map)?;
+        let describe = |movements: &BTreeSet<MovementID>| -> Vec<TurnGroup> {
+            movements.iter().map(|m| groups[m].clone()).collect()
+        };
+        Ok(SignalTiming {
+            offset_seconds: self.offset.inner_seconds(),
+            phases: self
+                .stages
+                .iter()
+                .map(|stage| {
+                    let (duration, variable) = match stage.stage_type {
+                        StageType::Fixed(d) => (d, None),
+                        StageType::Variable(min, delay, additional) => (
+                            min,
+                            Some(VariableTiming {
+                                delay_seconds: delay.inner_seconds(),
+                                additional_seconds: additional.inner_seconds(),
+                            }),
+                        ),
+                    };
+                    Phase {
+                        duration_seconds: duration.inner_seconds(),
+                        variable,
+                        protected: describe(&stage.protected_movements),
+                        permitted: describe(&stage.yield_movements),
+                    }
+                })
+                .collect(),
+        })
+    }
+
+    /// Builds a signal for an intersection from the simple format. The directions must match the
+    /// intersection's current geometry; every mismatch is reported along with its phase.
+    pub fn import_timing(
+        timing: SignalTiming,
+        id: IntersectionID,
+        map: &Map,
+    ) -> Result<ControlTrafficSignal> {
+        let movements = Movement::for_i(id, map)?;
+        let groups = turn_groups(id, &movements, map)?;
+        let by_group: BTreeMap<TurnGroup, MovementID> =
+            groups.iter().map(|(m, g)| (g.clone(), *m)).collect();
+
+        let mut errors = Vec::new();
+        let mut stages = Vec::new();
+        for (idx, phase) in timing.phases.into_iter().enumerate() {
+            let mut lookup = |list: Vec<TurnGroup>| -> BTreeSet<MovementID> {
+                let mut result = BTreeSet::new();
+                for group in list {
+                    match by_group.get(&group) {
+                        Some(m) => {
+                            result.insert(*m);
+                        }
+                        None => {
+                            errors.push(format!(
+                                "Phase {}: this intersection has no {}",
+                                idx + 1,
+                                group
+                            ));
+                        }
+                    }
+                }
+                result
+            };
+            let protected_movements = lookup(phase.protected);
+            let yield_movements = lookup(phase.permitted);
+
+            let mut durations = vec![phase.duration_seconds];
+            if let Some(ref v) = phase.variable {
+                durations.push(v.delay_seconds);
+                durations.push(v.additional_seconds);
+            }
+            if durations.into_iter().any(|x| x < 0.0) {
+                errors.push(format!("Phase {}: durations can't be negative", idx + 1));
+                continue;
+            }
+            let min = Duration::seconds(phase.duration_seconds);
+            stages.push(Stage {
+                protected_movements,
+                yield_movements,
+                stage_type: match phase.variable {
+                    Some(v) => StageType::Variable(
+                        min,
+                        Duration::seconds(v.delay_seconds),
+                        Duration::seconds(v.additional_seconds),
+                    ),
+                    None => StageType::Fixed(min),
+                },
+            });
+        }
+        if !errors.is_empty() {
+            bail!("{}", errors.join("\n"));
+        }
+
+        let signal = ControlTrafficSignal {
+            id,
+            stages,
+            offset: Duration::seconds(timing.offset_seconds),
+            movements,
+        };
+        // validate() would catch this too, but describe the movements the same way as the file
+        let missing = signal.missing_turns();
+        if !missing.is_empty() {
+            bail!(
+                "{}",
+                missing
+                    .into_iter()
+                    .map(|m| format!("No phase includes the {}", groups[&m]))
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            );
+        }
+        signal.validate()?;
+        Ok(signal)
+    }
+}
+
+/// Names every movement through an intersection by compass directions. Fails if two would have
+/// the same name.
+fn turn_groups(
+    i: IntersectionID,
+    movements: &BTreeMap<MovementID, Movement>,
+    map: &Map,
+) -> Result<BTreeMap<MovementID, TurnGroup>> {
+    let bearings: Vec<(RoadID, f64)> = map
+        .get_i(i)
+        .roads
+        .iter()
+        .map(|r| (*r, road_bearing(map, *r, i)))
+        .collect();
+    // Use the coarser directions, unless two roads would look the same
+    let mut names = None;
+    for points in &[8, 16] {
+        let candidate: BTreeMap<RoadID, &'static str> = bearings
+            .iter()
+            .map(|(r, bearing)| (*r, compass(*bearing, *points)))
+            .collect();
+        let unique: BTreeSet<&str> = candidate.values().cloned().collect();
+        if unique.len() == candidate.len() {
+            names = Some(candidate);
+            break;
+        }
+    }
+    let names = match names {
+        Some(x) => x,
+        None => bail!("Some roads meet {} from the same direction", i),
+    };
+
+    let mut groups = BTreeMap::new();
+    let mut seen = BTreeSet::new();
+    for m in movements.values() {
+        let group = if m.id.crosswalk {
+            TurnGroup {
+                from: names[&m.id.from.id].to_string(),
+                to: compass(bearing(m.angle), 8).to_string(),
+                crosswalk: true,
+            }
+        } else {
+            TurnGroup {
+                from: names[&m.id.from.id].to_string(),
+                to: names[&m.id.to.id].to_string(),
+                crosswalk: false,
+            }
+        };
+        if !seen.insert(group.clone()) {
+            bail!("{} has more than one {}", i, group);
+        }
+        groups.insert(m.id, group);
+    }
+    Ok(groups)
+}
+
+/// The direction a road leaves an intersection, in degrees clockwise from north
+fn road_bearing(map: &Map, r: RoadID, i: IntersectionID) -> f64 {
+    let road = map.get_r(r);
+    let pl = if road.src_i == i {
+        road.center_pts.clone()
+    } else {
+        road.center_pts.reversed()
+    };
+    bearing(pl.first_line().angle())
+}
+
+/// Map-space angles start pointing east and increase clockwise, because Y points south.
+fn bearing(angle: Angle) -> f64 {
+    (angle.normalized_degrees() + 90.0) % 360.0
+}
+
+/// The nearest of 8 or 16 compass points
+fn compass(bearing: f64, points: usize) -> &'static str {
+    let step = 360.0 / (points as f64);
+    let idx = ((bearing + step / 2.0) / step).floor() as usize % points;
+    COMPASS[idx * 16 / points]
+}
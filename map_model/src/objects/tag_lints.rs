@@ -0,0 +1,405 @@
+//! Compares a road's OSM tags against the lanes generated from them, to spot tagging that was
+//! misunderstood or ignored. Each finding comes with a suggestion for fixing the tags upstream.
+
+use abstutil::Tags;
+
+use crate::{osm, Direction, LaneType};
+
+/// Something about the tags that doesn't match the generated lanes.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TagFinding {
+    /// Which check found this
+    pub lint: &'static str,
+    pub problem: String,
+    pub suggestion: String,
+}
+
+struct Lint {
+    name: &'static str,
+    check: fn(&Tags, &[(Direction, LaneType)]) -> Vec<(String, String)>,
+}
+
+/// Every check, in the order findings are shown.
+const LINTS: [Lint; 6] = [
+    Lint {
+        name: "lane count",
+        check: lane_count,
+    },
+    Lint {
+        name: "lanes per direction",
+        check: lanes_per_direction,
+    },
+    Lint {
+        name: "sidewalks",
+        check: sidewalks,
+    },
+    Lint {
+        name: "cycleways",
+        check: cycleways,
+    },
+    Lint {
+        name: "parking",
+        check: parking,
+    },
+    Lint {
+        name: "bus lanes",
+        check: bus_lanes,
+    },
+];
+
+/// Runs every check against one road's tags and its lanes, listed left-to-right.
+pub fn lint_tags(tags: &Tags, lanes: &[(Direction, LaneType)]) -> Vec<TagFinding> {
+    // Paths and railways are special cases that ignore almost every tag checked here
+    if tags.is_any(
+        osm::HIGHWAY,
+        vec!["cycleway", "footway", "path", "pedestrian", "steps"],
+    ) || tags.contains_key("railway")
+    {
+        return Vec::new();
+    }
+
+    let mut findings = Vec::new();
+    for lint in &LINTS {
+        for (problem, suggestion) in (lint.check)(tags, lanes) {
+            findings.push(TagFinding {
+                lint: lint.name,
+                problem,
+                suggestion,
+            });
+        }
+    }
+    findings
+}
+
+fn count(lanes: &[(Direction, LaneType)], types: &[LaneType]) -> usize {
+    lanes.iter().filter(|(_, lt)| types.contains(lt)).count()
+}
+
+fn parse(tags: &Tags, key: &str) -> Option<usize> {
+    tags.get(key).and_then(|x| x.parse::<usize>().ok())
+}
+
+fn lane_count(tags: &Tags, lanes: &[(Direction, LaneType)]) -> Vec<(String, String)> {
+    let expected = match parse(tags, "lanes") {
+        Some(n) => n,
+        None => {
+            return Vec::new();
+        }
+    };
+    let actual = count(
+        lanes,
+        &[
+            LaneType::Driving,
+            LaneType::Bus,
+            LaneType::SharedLeftTurn,
+            LaneType::Construction,
+        ],
+    );
+    if actual == expected {
+        return Vec::new();
+    }
+
+    let oneway = tags.is_any("oneway", vec!["yes", "reversible"]);
+    let suggestion = if expected == 1 && !oneway {
+        "Two-way roads always get a lane in each direction. If this road is really one-way, tag \
+         oneway=yes; if traffic in both directions shares one lane, this is expected."
+    } else if count(lanes, &[LaneType::SharedLeftTurn]) > 0 {
+        "The center turn lane was added on top of lanes. In OSM, lanes should include it, so \
+         check lanes:forward and lanes:backward."
+    } else {
+        "Check that lanes counts every lane for motor vehicles, including bus and turn lanes, \
+         and that lanes:forward and lanes:backward agree with it."
+    };
+    vec![(
+        format!(
+            "lanes={}, but {} lanes for vehicles were generated",
+            expected, actual
+        ),
+        suggestion.to_string(),
+    )]
+}
+
+fn lanes_per_direction(tags: &Tags, _: &[(Direction, LaneType)]) -> Vec<(String, String)> {
+    if let (Some(total), Some(fwd), Some(back)) = (
+        parse(tags, "lanes"),
+        parse(tags, "lanes:forward"),
+        parse(tags, "lanes:backward"),
+    ) {
+        let both_ways = parse(tags, "lanes:both_ways").unwrap_or(0);
+        if fwd + back + both_ways != total {
+            return vec![(
+                format!(
+                    "lanes:forward={} and lanes:backward={} don't add up to lanes={}",
+                    fwd, back, total
+                ),
+                "Fix whichever is wrong. When they disagree, lanes:forward and lanes:backward are \
+                 used."
+                    .to_string(),
+            )];
+        }
+    }
+    Vec::new()
+}
+
+fn sidewalks(tags: &Tags, lanes: &[(Direction, LaneType)]) -> Vec<(String, String)> {
+    let actual = count(lanes, &[LaneType::Sidewalk]);
+    let value = match tags.get(osm::SIDEWALK) {
+        Some(x) => x.as_str(),
+        None => {
+            // The newer sidewalk:left/right/both scheme isn't understood yet
+            for key in &["sidewalk:both", "sidewalk:left", "sidewalk:right"] {
+                if let Some(value) = tags.get(key) {
+                    return vec![(
+                        format!("{}={} was ignored", key, value),
+                        format!(
+                            "Also tag sidewalk=both, left, right, or no. Right now {} sidewalks \
+                             exist.",
+                            actual
+                        ),
+                    )];
+                }
+            }
+            return Vec::new();
+        }
+    };
+    let expected = match value {
+        "both" => 2,
+        "left" | "right" => 1,
+        "no" | "none" => 0,
+        // separate sidewalks are sometimes approximated, and other values aren't understood
+        _ => {
+            return Vec::new();
+        }
+    };
+    if actual == expected {
+        return Vec::new();
+    }
+    vec![(
+        format!("sidewalk={}, but {} sidewalks exist", value, actual),
+        "If a sidewalk is mapped as its own footway, tag sidewalk=separate. Otherwise, check \
+         which sides of the road really have a sidewalk."
+            .to_string(),
+    )]
+}
+
+fn cycleways(tags: &Tags, lanes: &[(Direction, LaneType)]) -> Vec<(String, String)> {
+    if count(lanes, &[LaneType::Biking]) > 0 {
+        return Vec::new();
+    }
+    let mut findings = Vec::new();
+    for key in &[
+        "cycleway",
+        "cycleway:both",
+        "cycleway:left",
+        "cycleway:right",
+    ] {
+        if let Some(value) = tags.get(key) {
+            if ["lane", "track", "opposite_lane", "opposite_track"].contains(&value.as_str()) {
+                findings.push((
+                    format!("{}={}, but no bike lane was generated", key, value),
+                    "Only some combinations are understood. Prefer cycleway:left or \
+                     cycleway:right with lane or track, plus oneway:bicycle=no for contraflow."
+                        .to_string(),
+                ));
+            }
+        }
+    }
+    findings
+}
+
+fn parking(tags: &Tags, lanes: &[(Direction, LaneType)]) -> Vec<(String, String)> {
+    let mut findings = Vec::new();
+    let mut expected = 0;
+    for key in &[osm::PARKING_BOTH, osm::PARKING_LEFT, osm::PARKING_RIGHT] {
+        let value = match tags.get(key) {
+            Some(x) => x.as_str(),
+            None => continue,
+        };
+        match value {
+            "parallel" | "marked" | "diagonal" | "perpendicular" => {
+                expected += if *key == osm::PARKING_BOTH { 2 } else { 1 };
+            }
+            "no" | "no_parking" | "no_stopping" | "fire_lane" | "separate" => {}
+            _ => {
+                findings.push((
+                    format!(
+                        "{}={} isn't understood, so no parking was added",
+                        key, value
+                    ),
+                    "Use parallel, diagonal, or perpendicular for parking, or no_parking or \
+                     no_stopping where it's forbidden."
+                        .to_string(),
+                ));
+            }
+        }
+    }
+    let actual = count(lanes, &[LaneType::Parking]);
+    // Overlapping keys like parking:lane:both and parking:lane:left can count a side twice
+    if actual < expected.min(2) {
+        findings.push((
+            format!(
+                "Parking is tagged on {} sides, but {} parking lanes were generated",
+                expected, actual
+            ),
+            "Parking is only added beside normal driving lanes. Check access and bus lane tags, \
+             or tag both parking:lane:left and parking:lane:right instead of overlapping keys."
+                .to_string(),
+        ));
+    }
+    findings
+}
+
+fn bus_lanes(tags: &Tags, lanes: &[(Direction, LaneType)]) -> Vec<(String, String)> {
+    if count(lanes, &[LaneType::Bus]) > 0 {
+        return Vec::new();
+    }
+    let mut findings = Vec::new();
+    for (key, dir) in &[
+        ("bus:lanes", None),
+        ("psv:lanes", None),
+        ("bus:lanes:forward", Some(Direction::Fwd)),
+        ("psv:lanes:forward", Some(Direction::Fwd)),
+        ("bus:lanes:backward", Some(Direction::Back)),
+        ("psv:lanes:backward", Some(Direction::Back)),
+    ] {
+        let value = match tags.get(key) {
+            Some(x) => x,
+            None => continue,
+        };
+        if !value.split('|').any(|part| part == "designated") {
+            continue;
+        }
+        let num_parts = value.split('|').count();
+        let num_lanes = lanes
+            .iter()
+            .filter(|(d, lt)| {
+                dir.map(|dir| dir == *d).unwrap_or(true)
+                    && (*lt == LaneType::Driving || *lt == LaneType::Bus)
+            })
+            .count();
+        let suggestion = if dir.is_none() && !tags.is("oneway", "yes") {
+            format!(
+                "{} is only used on one-way roads. Use {}:forward and {}:backward instead.",
+                key, key, key
+            )
+        } else {
+            format!(
+                "{} lists {} lanes, but {} were generated in that direction. List one value per \
+                 lane.",
+                key, num_parts, num_lanes
+            )
+        };
+        findings.push((
+            format!(
+                "{}={} was ignored, so no bus lane was generated",
+                key, value
+            ),
+            suggestion,
+        ));
+    }
+    findings
+}
+
+#[cfg(test)]
+mod tests {
+    use geom::Distance;
+
+    use super::*;
+    use crate::make::initial::lane_specs::get_lane_specs_ltr;
+    use crate::{DrivingSide, MapConfig};
+
+    fn tags(kv: Vec<&str>) -> Tags {
+        let mut tags = Tags::empty();
+        for pair in kv {
+            let parts = pair.split('=').collect::<Vec<_>>();
+            tags.insert(parts[0], parts[1]);
+        }
+        tags
+    }
+
+    #[test]
+    fn test_tag_lints() {
+        let mut ok = true;
+        for (input, expected) in vec![
+            (
+                vec![
+                    "lanes=2",
+                    "oneway=yes",
+                    "sidewalk=both",
+                    "cycleway:left=lane",
+                ],
+                vec![],
+            ),
+            (vec!["lanes=1", "sidewalk=both"], vec!["lane count"]),
+            (
+                vec!["lanes=3", "centre_turn_lane=yes", "sidewalk=both"],
+                vec!["lane count"],
+            ),
+            (
+                vec![
+                    "lanes=4",
+                    "lanes:forward=2",
+                    "lanes:backward=1",
+                    "sidewalk=both",
+                ],
+                vec!["lane count", "lanes per direction"],
+            ),
+            (vec!["lanes=2", "sidewalk:right=yes"], vec!["sidewalks"]),
+            (
+                vec!["lanes=2", "highway=footway", "sidewalk:right=yes"],
+                vec![],
+            ),
+            (
+                vec!["lanes=2", "sidewalk=both", "cycleway=opposite_track"],
+                vec!["cycleways"],
+            ),
+            (
+                vec![
+                    "lanes=2",
+                    "sidewalk=both",
+                    "parking:lane:right=half_on_kerb",
+                ],
+                vec!["parking"],
+            ),
+            (
+                vec!["lanes=2", "access=no", "parking:lane:both=parallel"],
+                vec!["parking"],
+            ),
+            (
+                vec![
+                    "lanes=4",
+                    "sidewalk=both",
+                    "bus:lanes:forward=designated|none|none",
+                ],
+                vec!["bus lanes"],
+            ),
+        ] {
+            let cfg = MapConfig {
+                driving_side: DrivingSide::Right,
+                bikes_can_use_bus_lanes: true,
+                inferred_sidewalks: false,
+                separate_cycleways: false,
+                street_parking_spot_length: Distance::meters(8.0),
+            };
+            let tags = tags(input.clone());
+            let lanes: Vec<(Direction, LaneType)> = get_lane_specs_ltr(&tags, &cfg)
+                .into_iter()
+                .map(|spec| (spec.dir, spec.lt))
+                .collect();
+            let actual: Vec<&str> = lint_tags(&tags, &lanes)
+                .into_iter()
+                .map(|f| f.lint)
+                .collect();
+            if actual != expected {
+                ok = false;
+                println!("For input:");
+                for kv in input {
+                    println!("    {}", kv);
+                }
+                println!("Got {:?}, but expected {:?}", actual, expected);
+                println!();
+            }
+        }
+        assert!(ok);
+    }
+}
@@ -107,12 +107,12 @@ impl Turn {
         let from = map.get_l(self.id.src);
         let to = map.get_l(self.id.dst);
 
-        // Starting from the farthest from the center line (right in the US), where is this travel
+        // Starting from the curb (the right when driving on the right), where is this travel
         // lane? Filters by the lane type and ignores lanes that don't go to the target road.
         let from_idx = {
             let mut cnt = 0;
             let r = map.get_r(from.parent);
-            for (l, lt) in r.children(r.dir(from.id)).iter().rev() {
+            for (l, lt) in r.children_from_curb(r.dir(from.id), map).iter() {
                 if from.lane_type != *lt {
                     continue;
                 }
@@ -130,12 +130,11 @@ impl Turn {
             cnt
         };
 
-        // Starting from the farthest from the center line (right in the US), where is this travel
-        // lane? Filters by the lane type.
+        // Starting from the curb, where is this travel lane? Filters by the lane type.
         let to_idx = {
             let mut cnt = 0;
             let r = map.get_r(to.parent);
-            for (l, lt) in r.children(r.dir(to.id)).iter().rev() {
+            for (l, lt) in r.children_from_curb(r.dir(to.id), map).iter() {
                 if to.lane_type != *lt {
                     continue;
                 }
@@ -159,7 +158,7 @@ impl Turn {
         // matter.
         let lt_cost = if to.is_biking() || to.is_bus() { 0 } else { 1 };
 
-        // Keep right (in the US)
+        // Keep to the slow lane along the curb (right in the US, left in the UK)
         let slow_lane = if to_idx > 1 { 1 } else { 0 };
 
         (lt_cost, lc_cost, slow_lane)
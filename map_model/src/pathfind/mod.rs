@@ -264,6 +264,43 @@ impl Path {
         // TODO Maybe need to amend uber_turns?
     }
 
+    /// Keeps the first `keep` steps and replaces everything after them with another path, which
+    /// must start on the last kept step. The path then ends wherever the other one does. Used to
+    /// re-plan partway through a path. Nothing changes if the paths don't line up.
+    pub fn replace_remaining(&mut self, keep: usize, other: Path, map: &Map) -> Result<()> {
+        if self.currently_inside_ut.is_some() {
+            bail!("can't replace a path in the middle of an uber-turn");
+        }
+        if keep == 0 || keep > self.steps.len() {
+            bail!(
+                "can't keep {} steps of a path with {}",
+                keep,
+                self.steps.len()
+            );
+        }
+        if self.steps[keep - 1] != other.steps[0] {
+            bail!(
+                "the replacement starts at {:?}, not {:?}",
+                other.steps[0],
+                self.steps[keep - 1]
+            );
+        }
+
+        // The last kept step might've been the end of the path, so only partly crossed. Count it
+        // again after the end changes.
+        for step in self.steps.drain(keep - 1..).collect::<Vec<_>>() {
+            self.total_length -= self.dist_crossed_from_step(map, &step);
+        }
+        self.orig_req.end = other.orig_req.end;
+        for step in other.steps {
+            self.total_length += self.dist_crossed_from_step(map, &step);
+            self.steps.push_back(step);
+        }
+        // The caller shouldn't keep any steps belonging to an uber-turn
+        self.uber_turns = other.uber_turns;
+        Ok(())
+    }
+
     pub fn is_upcoming_uber_turn_component(&self, t: TurnID) -> bool {
         self.uber_turns
             .front()
@@ -12,7 +12,7 @@ use serde::{Deserialize, Serialize};
 
 use abstio::{CityName, MapName};
 use abstutil::{deserialize_btreemap, serialize_btreemap, Tags};
-use geom::{Circle, Distance, GPSBounds, PolyLine, Polygon, Pt2D};
+use geom::{Circle, Distance, GPSBounds, PolyLine, Polygon, Pt2D, Time};
 
 use crate::make::initial::lane_specs::get_lane_specs_ltr;
 use crate::{
@@ -488,6 +488,8 @@ pub struct RawBuilding {
     pub public_garage_name: Option<String>,
     pub num_parking_spots: usize,
     pub amenities: Vec<Amenity>,
+    /// Of the ground at the center. Zero when no elevation data was imported.
+    pub elevation: Distance,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -545,6 +547,9 @@ pub struct RawBusRoute {
     pub border_end: Option<osm::NodeID>,
     /// This is guaranteed to be in order and contiguous.
     pub all_pts: Vec<(osm::NodeID, Pt2D)>,
+    /// Times in order for one day when a vehicle should begin the route, if they're known from
+    /// GTFS. Otherwise, a default schedule is used.
+    pub spawn_times: Option<Vec<Time>>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -556,4 +561,6 @@ pub struct RawBusStop {
     pub matched_road: Option<(OriginalRoad, Direction)>,
     /// If it's not explicitly mapped, we'll do equiv_pos.
     pub ped_pos: Option<Pt2D>,
+    /// From the stop and its platform, if they're mapped in OSM
+    pub osm_tags: Tags,
 }
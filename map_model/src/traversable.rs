@@ -204,6 +204,15 @@ impl Traversable {
         }
     }
 
+    /// Returns [-1.0, 1.0], positive for uphill. Turns are treated as flat. This is meant for
+    /// things like the speed of bikes, which should slow down going uphill.
+    pub fn percent_incline(&self, map: &Map) -> f64 {
+        match *self {
+            Traversable::Lane(id) => map.get_l(id).percent_incline(map),
+            Traversable::Turn(_) => 0.0,
+        }
+    }
+
     pub fn get_zorder(&self, map: &Map) -> isize {
         match *self {
             Traversable::Lane(id) => map.get_parent(id).zorder,
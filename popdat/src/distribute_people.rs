@@ -3,7 +3,7 @@ use rand::Rng;
 use rand_xorshift::XorShiftRng;
 
 use abstutil::prettyprint_usize;
-use map_model::{BuildingID, Map};
+use map_model::{Building, BuildingID, Map};
 
 use crate::{CensusArea, CensusPerson, Config};
 
@@ -41,20 +41,14 @@ pub fn distribute_population_to_homes(
     map: &Map,
     rng: &mut XorShiftRng,
 ) -> Vec<(BuildingID, usize)> {
-    let map_boundary = geo::Polygon::from(map.get_boundary_polygon().clone());
-    let bldgs: Vec<map_model::BuildingID> = map
-        .all_buildings()
+    let bldgs: Vec<BuildingID> = homes_inside(&polygon, map)
         .into_iter()
-        .filter(|b| {
-            polygon.contains(&geo::Point::from(b.label_center)) && b.bldg_type.has_residents()
-        })
         .map(|b| b.id)
         .collect();
 
     // If the area is partly out-of-bounds, then scale down the number of residents linearly
     // based on area of the overlapping part of the polygon.
-    use geo_booleanop::boolean::BooleanOp;
-    let pct_overlap = polygon.intersection(&map_boundary).unsigned_area() / polygon.unsigned_area();
+    let pct_overlap = pct_inside_map(&polygon, map);
     let num_residents = (pct_overlap * (population as f64)) as usize;
     debug!(
         "Distributing {} residents to {} buildings. {}% of this area overlapped with the map, \
@@ -80,3 +74,58 @@ pub fn distribute_population_to_homes(
     }
     count_per_home
 }
+
+/// Like `distribute_population_to_homes`, but deterministic: residents are split between the
+/// residential buildings in the area proportional to their footprint, and the counts always add up
+/// to the population of the part of the area inside the map. An area with no residential buildings
+/// gets nobody. Returns that population along with the residents of each home, including homes
+/// that get nobody.
+pub fn distribute_population_by_footprint(
+    polygon: geo::Polygon<f64>,
+    population: usize,
+    map: &Map,
+) -> (usize, Vec<(BuildingID, usize)>) {
+    let num_residents = (pct_inside_map(&polygon, map) * (population as f64)).round() as usize;
+    let homes: Vec<(BuildingID, f64)> = homes_inside(&polygon, map)
+        .into_iter()
+        .map(|b| (b.id, b.polygon.area()))
+        .collect();
+    let total_area: f64 = homes.iter().map(|(_, area)| *area).sum();
+    if homes.is_empty() || total_area == 0.0 {
+        return (num_residents, Vec::new());
+    }
+
+    // Round everything down, then hand out the leftover people to the homes that lost the most
+    // from rounding. Break ties by ID, so the result is stable.
+    let mut count_per_home = Vec::new();
+    let mut remainders = Vec::new();
+    let mut assigned = 0;
+    for (idx, (b, area)) in homes.into_iter().enumerate() {
+        let exact = area / total_area * (num_residents as f64);
+        let n = exact.floor() as usize;
+        assigned += n;
+        count_per_home.push((b, n));
+        remainders.push((exact - exact.floor(), idx));
+    }
+    remainders.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap().then(a.1.cmp(&b.1)));
+    for (_, idx) in remainders.into_iter().take(num_residents - assigned) {
+        count_per_home[idx].1 += 1;
+    }
+    (num_residents, count_per_home)
+}
+
+fn homes_inside<'a>(polygon: &geo::Polygon<f64>, map: &'a Map) -> Vec<&'a Building> {
+    map.all_buildings()
+        .iter()
+        .filter(|b| {
+            polygon.contains(&geo::Point::from(b.label_center)) && b.bldg_type.has_residents()
+        })
+        .collect()
+}
+
+// What fraction of the polygon's area is inside the map boundary
+pub(crate) fn pct_inside_map(polygon: &geo::Polygon<f64>, map: &Map) -> f64 {
+    use geo_booleanop::boolean::BooleanOp;
+    let map_boundary = geo::Polygon::from(map.get_boundary_polygon().clone());
+    polygon.intersection(&map_boundary).unsigned_area() / polygon.unsigned_area()
+}
@@ -29,12 +29,16 @@ use geom::{Distance, Time};
 use map_model::{BuildingID, Map};
 use sim::Scenario;
 
-pub use self::distribute_people::distribute_population_to_homes;
+pub use self::distribute_people::{
+    distribute_population_by_footprint, distribute_population_to_homes,
+};
+pub use self::od::{read_demand, read_zones, synthesize_od_demand, DemandCell, DemandReport, Zone};
 
 mod activities;
 mod distribute_people;
 mod import_census;
 mod make_person;
+mod od;
 
 /// Represents aggregate demographic data for some part of a city. These could be census tracts or
 /// blocks, depending what data we find. All of the areas should roughly partition the map -- we
@@ -0,0 +1,225 @@
+//! Turns an origin-destination table into a scenario. Many transportation agencies publish travel
+//! demand this way: the number of trips between pairs of zones, broken down by hour and mode.
+
+use std::collections::BTreeMap;
+
+use anyhow::Result;
+use geo::algorithm::contains::Contains;
+use rand::seq::SliceRandom;
+use rand::Rng;
+use rand_xorshift::XorShiftRng;
+use serde::Deserialize;
+
+use abstutil::prettyprint_usize;
+use geom::{Duration, LonLat, Ring, Time};
+use map_model::{BuildingID, Map};
+use sim::{IndividTrip, PersonSpec, Scenario, TripEndpoint, TripMode, TripPurpose};
+
+use crate::distribute_people::pct_inside_map;
+
+/// An area that trips start or end in
+pub struct Zone {
+    pub polygon: geo::Polygon<f64>,
+    /// What fraction of the zone's area is inside the map boundary
+    pub pct_inside: f64,
+    /// Every building in the zone, in order of ID
+    pub buildings: Vec<BuildingID>,
+}
+
+/// One row of an origin-destination table
+#[derive(Debug, Deserialize)]
+pub struct DemandCell {
+    pub origin_zone: String,
+    pub destination_zone: String,
+    /// Trips depart sometime during this hour of the day, from 0 to 23
+    pub hour: usize,
+    /// Walk, Bike, Transit, or Drive
+    pub mode: TripMode,
+    pub count: usize,
+}
+
+/// How much of the requested demand made it into a scenario. Dropped trips are keyed by their
+/// origin and destination zone.
+#[derive(Debug, Default)]
+pub struct DemandReport {
+    pub requested: usize,
+    pub generated: usize,
+    /// The share of trips that would start or end in the part of a zone outside the map
+    pub dropped_outside_map: BTreeMap<(String, String), usize>,
+    /// Trips between zones that don't have any buildings in the map, or only have the building the
+    /// trip starts from
+    pub dropped_no_buildings: BTreeMap<(String, String), usize>,
+}
+
+impl DemandReport {
+    pub fn describe(&self) -> Vec<String> {
+        let mut lines = vec![format!(
+            "Generated {} of {} requested trips",
+            prettyprint_usize(self.generated),
+            prettyprint_usize(self.requested)
+        )];
+        for ((from, to), n) in &self.dropped_outside_map {
+            lines.push(format!(
+                "- dropped {} trips from {} to {}, because the zones are partly outside the map",
+                prettyprint_usize(*n),
+                from,
+                to
+            ));
+        }
+        for ((from, to), n) in &self.dropped_no_buildings {
+            lines.push(format!(
+                "- dropped {} trips from {} to {}, because there are no buildings to use",
+                prettyprint_usize(*n),
+                from,
+                to
+            ));
+        }
+        lines
+    }
+}
+
+/// Reads an origin-destination table from a CSV file with the columns origin_zone,
+/// destination_zone, hour, mode, and count.
+pub fn read_demand(path: &str) -> Result<Vec<DemandCell>> {
+    let mut cells = Vec::new();
+    for rec in csv::Reader::from_path(path)?.deserialize() {
+        let cell: DemandCell = rec?;
+        if cell.hour > 23 {
+            bail!(
+                "Trips from {} to {} depart during hour {}, but the day only has 24",
+                cell.origin_zone,
+                cell.destination_zone,
+                cell.hour
+            );
+        }
+        cells.push(cell);
+    }
+    Ok(cells)
+}
+
+/// Reads zones from a GeoJSON file. Each feature must be a polygon with an "id" property. Zones may
+/// be partly or completely outside the map.
+pub fn read_zones(path: &str, map: &Map) -> Result<BTreeMap<String, Zone>> {
+    let bytes = abstio::slurp_file(path)?;
+    let geojson = std::str::from_utf8(&bytes)?.parse::<geojson::GeoJson>()?;
+    let collection = match geojson {
+        geojson::GeoJson::FeatureCollection(collection) => collection,
+        _ => bail!("{} isn't a FeatureCollection", path),
+    };
+
+    let mut zones = BTreeMap::new();
+    for feature in collection.features {
+        // Some sources store IDs as numbers
+        let id = match feature
+            .properties
+            .as_ref()
+            .and_then(|props| props.get("id"))
+        {
+            Some(serde_json::Value::String(s)) => s.clone(),
+            Some(serde_json::Value::Number(n)) => n.to_string(),
+            _ => bail!("a zone in {} is missing an id", path),
+        };
+        // Only the outer ring is used
+        let raw_pts = match feature.geometry.map(|g| g.value) {
+            Some(geojson::Value::Polygon(mut rings)) => rings.remove(0),
+            _ => bail!("zone {} in {} isn't a polygon", id, path),
+        };
+        let gps_pts: Vec<LonLat> = raw_pts
+            .into_iter()
+            .map(|pt| LonLat::new(pt[0], pt[1]))
+            .collect();
+        let polygon =
+            geo::Polygon::from(Ring::new(map.get_gps_bounds().convert(&gps_pts))?.to_polygon());
+        let buildings = map
+            .all_buildings()
+            .iter()
+            .filter(|b| polygon.contains(&geo::Point::from(b.label_center)))
+            .map(|b| b.id)
+            .collect();
+        let pct_inside = pct_inside_map(&polygon, map);
+        if zones
+            .insert(
+                id.clone(),
+                Zone {
+                    polygon,
+                    pct_inside,
+                    buildings,
+                },
+            )
+            .is_some()
+        {
+            bail!("{} has more than one zone with id {}", path, id);
+        }
+    }
+    Ok(zones)
+}
+
+/// Creates one person for every trip in the table. Each trip starts and ends at buildings picked
+/// randomly from its zones, and departs at a random time during its hour. The same seed always
+/// produces the same scenario.
+///
+/// When a zone is partly outside the map, its trips are scaled down by how much of the zone's
+/// area is inside.
+pub fn synthesize_od_demand(
+    scenario_name: &str,
+    zones: &BTreeMap<String, Zone>,
+    demand: &[DemandCell],
+    map: &Map,
+    rng: &mut XorShiftRng,
+) -> Result<(Scenario, DemandReport)> {
+    let mut scenario = Scenario::empty(map, scenario_name);
+    // Include all buses/trains
+    scenario.only_seed_buses = None;
+    let mut report = DemandReport::default();
+
+    for cell in demand {
+        let key = (cell.origin_zone.clone(), cell.destination_zone.clone());
+        let (from, to) = match (zones.get(&key.0), zones.get(&key.1)) {
+            (Some(from), Some(to)) => (from, to),
+            _ => bail!("Trips from {} to {} use an unknown zone", key.0, key.1),
+        };
+        report.requested += cell.count;
+
+        if from.buildings.is_empty() || to.buildings.is_empty() {
+            *report.dropped_no_buildings.entry(key).or_insert(0) += cell.count;
+            continue;
+        }
+        let num_trips = ((cell.count as f64) * from.pct_inside * to.pct_inside).round() as usize;
+        if num_trips < cell.count {
+            *report.dropped_outside_map.entry(key.clone()).or_insert(0) += cell.count - num_trips;
+        }
+
+        for _ in 0..num_trips {
+            let origin = *from.buildings.choose(rng).unwrap();
+            let destinations: Vec<BuildingID> = to
+                .buildings
+                .iter()
+                .cloned()
+                .filter(|b| *b != origin)
+                .collect();
+            let destination = match destinations.choose(rng) {
+                Some(b) => *b,
+                None => {
+                    *report.dropped_no_buildings.entry(key.clone()).or_insert(0) += 1;
+                    continue;
+                }
+            };
+            let depart = Time::START_OF_DAY
+                + Duration::hours(cell.hour)
+                + Duration::seconds(rng.gen_range(0.0..3600.0));
+            scenario.people.push(PersonSpec {
+                orig_id: None,
+                origin: TripEndpoint::Bldg(origin),
+                // The table doesn't say why anybody is traveling
+                trips: vec![IndividTrip::new(
+                    depart,
+                    TripPurpose::Work,
+                    TripEndpoint::Bldg(destination),
+                    cell.mode,
+                )],
+            });
+            report.generated += 1;
+        }
+    }
+    Ok((scenario, report))
+}
@@ -3,14 +3,15 @@ use std::collections::{BTreeMap, BTreeSet, VecDeque};
 use serde::{Deserialize, Serialize};
 
 use abstutil::Counter;
-use geom::{Duration, Time};
+use geom::{Distance, Duration, Speed, Time};
 use map_model::{
-    BusRouteID, BusStopID, CompressedMovementID, IntersectionID, LaneID, Map, MovementID,
-    ParkingLotID, Path, PathRequest, RoadID, Traversable, TurnID,
+    BuildingID, BusRouteID, BusStopID, CompressedMovementID, IntersectionID, LaneID, Map,
+    MovementID, ParkingLotID, Path, PathRequest, RoadID, Traversable, TurnID, TurnType,
 };
 
 use crate::{
-    AgentID, AgentType, AlertLocation, CarID, Event, ParkingSpot, TripID, TripMode, TripPhaseType,
+    AgentID, AgentType, AlertLocation, CarID, Event, ParkingSpot, PedestrianID, PersonID, TripID,
+    TripMode, TripPhaseType,
 };
 
 /// As a simulation runs, different pieces emit Events. The Analytics object listens to these,
@@ -21,14 +22,29 @@ use crate::{
 /// results." These are just serialized Analytics after running the simulation on a map without any
 /// edits for the full day. This is the basis of A/B testing -- the player can edit the map, start
 /// running the simulation, and compare the live Analytics to the prebaked baseline Analytics.
+///
+/// Prebaked results and savestates are read with bincode, which can't fill in fields missing from
+/// older files. So newer fields are skipped, and only describe the live simulation.
 #[derive(Clone, Serialize, Deserialize)]
 pub struct Analytics {
     pub road_thruput: TimeSeriesCount<RoadID>,
+    /// Like road_thruput, but for each lane. Transit riders aren't counted.
+    #[serde(skip, default = "TimeSeriesCount::new")]
+    pub lane_thruput: TimeSeriesCount<LaneID>,
     pub intersection_thruput: TimeSeriesCount<IntersectionID>,
     // TODO For traffic signals, intersection_thruput could theoretically use this. But that
     // requires occasionally expensive or complicated summing or merging over all directions of an
     // intersection. So for now, eat the file size cost.
     pub traffic_signal_thruput: TimeSeriesCount<CompressedMovementID>,
+    /// Pedestrians walking through each sidewalk, split by how they used it. Bucketed by when
+    /// they enter the sidewalk.
+    #[serde(skip, default = "TimeSeriesCount::new")]
+    pub sidewalk_thruput: TimeSeriesCount<(LaneID, SidewalkUse)>,
+    /// When pedestrians stepped onto each sidewalk during roughly the last
+    /// RECENT_SIDEWALK_WINDOW, oldest first. Unlike the other counts, older entries are dropped,
+    /// and this only describes the live simulation.
+    #[serde(skip)]
+    pub recent_sidewalk_entries: VecDeque<(Time, LaneID)>,
 
     /// Most fields in Analytics are cumulative over time, but this is just for the current moment
     /// in time.
@@ -52,13 +68,44 @@ pub struct Analytics {
     /// If it is over a certain threshold (<95% of max speed)
     /// TripID, [(LaneID, Percent of maximum speed as an integer (0-100)]
     pub lane_speed_percentage: BTreeMap<TripID, BTreeMap<LaneID, u8>>,
+    /// Per lane, vehicle type, and window of LANE_SPEED_WINDOW, the total distance covered and
+    /// time spent by vehicles crossing the entire lane. Bucketed by when they leave the lane.
+    #[serde(skip)]
+    pub lane_speeds: BTreeMap<(LaneID, AgentType, usize), (Distance, Duration)>,
+    /// When each vehicle entered the lane it's currently on
+    #[serde(skip)]
+    lane_entries: BTreeMap<CarID, (LaneID, Time)>,
+    /// The sidewalk or turn each pedestrian last entered, when, and whether it was or came from a
+    /// crosswalk
+    #[serde(skip)]
+    ped_steps: BTreeMap<PedestrianID, (Traversable, Time, bool)>,
 
     // TODO This subsumes finished_trips
     pub trip_log: Vec<(Time, TripID, Option<PathRequest>, TripPhaseType)>,
+    /// For finished trips, when they finished, their mode, and how long was spent in each type of
+    /// phase.
+    #[serde(skip)]
+    pub trip_phase_durations: BTreeMap<TripID, (Time, TripMode, TripPhaseDurations)>,
+    /// For ongoing trips, when the current phase started, its type, and the durations of all
+    /// previous phases.
+    #[serde(skip)]
+    current_trip_phases: BTreeMap<TripID, (Time, TripPhaseType, TripPhaseDurations)>,
 
     // TODO Transit riders aren't represented here yet, just the vehicle they're riding.
     /// Only for traffic signals. The u8 is the movement index from a CompressedMovementID.
     pub intersection_delays: BTreeMap<IntersectionID, Vec<(u8, Time, Duration, AgentType)>>,
+    /// Only for traffic signals. When each stage started, and its index.
+    #[serde(skip)]
+    pub traffic_signal_stages: BTreeMap<IntersectionID, Vec<(Time, usize)>>,
+    /// Only for traffic signals. The periods when at least one vehicle was moving through.
+    #[serde(skip)]
+    pub traffic_signal_busy: BTreeMap<IntersectionID, Vec<(Time, Time)>>,
+    /// The traffic signal each vehicle is currently turning through
+    #[serde(skip)]
+    vehicles_in_signals: BTreeMap<CarID, IntersectionID>,
+    /// For traffic signals with vehicles turning through right now, since when, and how many
+    #[serde(skip)]
+    busy_signals: BTreeMap<IntersectionID, (Time, usize)>,
 
     /// Per parking lane or lot, when does a spot become filled (true) or free (false)
     pub parking_lane_changes: BTreeMap<LaneID, Vec<(Time, bool)>>,
@@ -66,6 +113,10 @@ pub struct Analytics {
 
     pub(crate) alerts: Vec<(Time, AlertLocation, String)>,
 
+    /// Only useful for debugging the live simulation, so it isn't part of prebaked results.
+    #[serde(skip)]
+    pub person_log: PersonLog,
+
     /// For benchmarking, we may want to disable collecting data.
     record_anything: bool,
 }
@@ -74,8 +125,11 @@ impl Analytics {
     pub fn new(record_anything: bool) -> Analytics {
         Analytics {
             road_thruput: TimeSeriesCount::new(),
+            lane_thruput: TimeSeriesCount::new(),
             intersection_thruput: TimeSeriesCount::new(),
             traffic_signal_thruput: TimeSeriesCount::new(),
+            sidewalk_thruput: TimeSeriesCount::new(),
+            recent_sidewalk_entries: VecDeque::new(),
             demand: BTreeMap::new(),
             bus_arrivals: Vec::new(),
             passengers_boarding: BTreeMap::new(),
@@ -84,11 +138,21 @@ impl Analytics {
             finished_trips: Vec::new(),
             trip_intersection_delays: BTreeMap::new(),
             lane_speed_percentage: BTreeMap::new(),
+            lane_speeds: BTreeMap::new(),
+            lane_entries: BTreeMap::new(),
+            ped_steps: BTreeMap::new(),
             trip_log: Vec::new(),
+            trip_phase_durations: BTreeMap::new(),
+            current_trip_phases: BTreeMap::new(),
             intersection_delays: BTreeMap::new(),
+            traffic_signal_stages: BTreeMap::new(),
+            traffic_signal_busy: BTreeMap::new(),
+            vehicles_in_signals: BTreeMap::new(),
+            busy_signals: BTreeMap::new(),
             parking_lane_changes: BTreeMap::new(),
             parking_lot_changes: BTreeMap::new(),
             alerts: Vec::new(),
+            person_log: PersonLog::default(),
             record_anything,
         }
     }
@@ -104,6 +168,7 @@ impl Analytics {
                 Traversable::Lane(l) => {
                     self.road_thruput
                         .record(time, map.get_l(l).parent, a.to_type(), 1);
+                    self.lane_thruput.record(time, l, a.to_type(), 1);
                     if let Some(n) = passengers {
                         self.road_thruput.record(
                             time,
@@ -151,6 +216,72 @@ impl Analytics {
             _ => {}
         }
 
+        // Lane speeds. Vehicles starting partway along a lane never enter it, so only full
+        // traversals get measured.
+        if let Event::AgentEntersTraversable(AgentID::Car(car), to, _) = ev {
+            match to {
+                Traversable::Lane(l) => {
+                    self.lane_entries.insert(car, (l, time));
+                }
+                Traversable::Turn(t) => {
+                    if let Some((l, entered)) = self.lane_entries.remove(&car) {
+                        if l == t.src && time > entered {
+                            let bucket = ((time - Time::START_OF_DAY) / LANE_SPEED_WINDOW) as usize;
+                            let total = self
+                                .lane_speeds
+                                .entry((l, AgentID::Car(car).to_type(), bucket))
+                                .or_insert((Distance::ZERO, Duration::ZERO));
+                            total.0 += map.get_l(l).length();
+                            total.1 += time - entered;
+                        }
+                    }
+                }
+            }
+        }
+
+        // Sidewalk use. Like lane speeds, pedestrians starting or ending partway along a sidewalk
+        // don't get counted there.
+        if let Event::AgentEntersTraversable(AgentID::Pedestrian(ped), to, _) = ev {
+            let prev = self.ped_steps.remove(&ped);
+            match to {
+                Traversable::Lane(l) => {
+                    if map.get_l(l).is_walkable() {
+                        self.recent_sidewalk_entries.push_back((time, l));
+                        while let Some((entered, _)) = self.recent_sidewalk_entries.front() {
+                            if time - *entered <= RECENT_SIDEWALK_WINDOW {
+                                break;
+                            }
+                            self.recent_sidewalk_entries.pop_front();
+                        }
+                        let from_crosswalk = match prev {
+                            Some((Traversable::Turn(t), _, crosswalk)) => t.dst == l && crosswalk,
+                            _ => false,
+                        };
+                        self.ped_steps.insert(ped, (to, time, from_crosswalk));
+                    }
+                }
+                Traversable::Turn(t) => {
+                    let crosswalk = map.get_t(t).turn_type == TurnType::Crosswalk;
+                    if let Some((Traversable::Lane(l), entered, from_crosswalk)) = prev {
+                        if l == t.src {
+                            let usage = if from_crosswalk || crosswalk {
+                                SidewalkUse::Crossing
+                            } else {
+                                SidewalkUse::Along
+                            };
+                            self.sidewalk_thruput.record(
+                                entered,
+                                (l, usage),
+                                AgentType::Pedestrian,
+                                1,
+                            );
+                        }
+                    }
+                    self.ped_steps.insert(ped, (to, time, crosswalk));
+                }
+            }
+        }
+
         // Bus arrivals
         if let Event::BusArrivedAtStop(bus, route, stop) = ev {
             self.bus_arrivals.push((time, bus, route, stop));
@@ -190,6 +321,35 @@ impl Analytics {
             self.finished_trips.push((time, id, mode, None));
         }
 
+        // Per-phase durations
+        match ev {
+            Event::TripPhaseStarting(id, _, _, phase_type) => {
+                if let Some((start, current, durations)) = self.current_trip_phases.get_mut(&id) {
+                    durations.add(*current, time - *start);
+                    *start = time;
+                    *current = phase_type;
+                } else if self.started_trips.get(&id) == Some(&time) {
+                    self.current_trip_phases
+                        .insert(id, (time, phase_type, TripPhaseDurations::default()));
+                }
+                // Otherwise the trip started before a savestate was loaded. Its earlier phases
+                // weren't saved, so don't give it an incomplete breakdown.
+            }
+            Event::TripFinished { trip, mode, .. } => {
+                if let Some((start, current, mut durations)) =
+                    self.current_trip_phases.remove(&trip)
+                {
+                    durations.add(current, time - start);
+                    self.trip_phase_durations
+                        .insert(trip, (time, mode, durations));
+                }
+            }
+            Event::TripCancelled(id, _) => {
+                self.current_trip_phases.remove(&id);
+            }
+            _ => {}
+        }
+
         // Trip Intersection delay
         if let Event::TripIntersectionDelay(trip_id, turn_id, agent, delay) = ev {
             match agent {
@@ -231,6 +391,27 @@ impl Analytics {
                 .push((id.idx, time, delay, agent.to_type()));
         }
 
+        // Traffic signal stage usage
+        if let Event::TrafficSignalStageChanged(i, stage) = ev {
+            self.traffic_signal_stages
+                .entry(i)
+                .or_insert_with(Vec::new)
+                .push((time, stage));
+        }
+        if let Event::AgentEntersTraversable(AgentID::Car(car), to, _) = ev {
+            match to {
+                Traversable::Turn(t) => {
+                    if map.get_i(t.parent).is_traffic_signal() {
+                        self.vehicles_in_signals.insert(car, t.parent);
+                        self.busy_signals.entry(t.parent).or_insert((time, 0)).1 += 1;
+                    }
+                }
+                Traversable::Lane(_) => {
+                    self.vehicle_left_signal(car, time);
+                }
+            }
+        }
+
         // Parking spot changes
         if let Event::CarReachedParkingSpot(_, spot) = ev {
             if let ParkingSpot::Onstreet(l, _) = spot {
@@ -259,6 +440,8 @@ impl Analytics {
             }
         }
 
+        self.person_log.event(&ev, time, map);
+
         // TODO Kinda hacky, but these all consume the event, so kinda bundle em.
         match ev {
             Event::TripPhaseStarting(id, _, maybe_req, phase_type) => {
@@ -282,6 +465,28 @@ impl Analytics {
         }
     }
 
+    /// A vehicle was deleted from the simulation, maybe in the middle of a lane or turn. There's no
+    /// event for this, so forget anything being tracked for it here.
+    pub(crate) fn vehicle_removed(&mut self, car: CarID, time: Time) {
+        self.lane_entries.remove(&car);
+        self.vehicle_left_signal(car, time);
+    }
+
+    // The signal is busy until the last vehicle in it leaves
+    fn vehicle_left_signal(&mut self, car: CarID, time: Time) {
+        if let Some(i) = self.vehicles_in_signals.remove(&car) {
+            let (since, cnt) = self.busy_signals.get_mut(&i).unwrap();
+            *cnt -= 1;
+            if *cnt == 0 {
+                self.traffic_signal_busy
+                    .entry(i)
+                    .or_insert_with(Vec::new)
+                    .push((*since, time));
+                self.busy_signals.remove(&i);
+            }
+        }
+    }
+
     pub fn record_demand(&mut self, path: &Path, map: &Map) {
         for step in path.get_steps() {
             if let Traversable::Turn(t) = step.as_traversable() {
@@ -336,6 +541,79 @@ impl Analytics {
         results
     }
 
+    /// Boardings and alightings per hour at a bus stop until some time, optionally only for one
+    /// route. Also returns the average time that people who boarded there waited, if anybody has.
+    pub fn bus_stop_activity(
+        &self,
+        stop: BusStopID,
+        route: Option<BusRouteID>,
+        now: Time,
+    ) -> (Vec<(Time, usize)>, Vec<(Time, usize)>, Option<Duration>) {
+        let matches = |r: &BusRouteID| route.map(|x| x == *r).unwrap_or(true);
+        let mut boardings = Vec::new();
+        let mut total_wait = Duration::ZERO;
+        if let Some(list) = self.passengers_boarding.get(&stop) {
+            for (t, r, wait) in list {
+                if *t <= now && matches(r) {
+                    boardings.push(*t);
+                    total_wait += *wait;
+                }
+            }
+        }
+        let mut alightings = Vec::new();
+        if let Some(list) = self.passengers_alighting.get(&stop) {
+            for (t, r) in list {
+                if *t <= now && matches(r) {
+                    alightings.push(*t);
+                }
+            }
+        }
+        let avg_wait = if boardings.is_empty() {
+            None
+        } else {
+            Some(total_wait / (boardings.len() as f64))
+        };
+        (
+            count_per_hour(&boardings, now),
+            count_per_hour(&alightings, now),
+            avg_wait,
+        )
+    }
+
+    /// The average speed of some types of vehicles crossing a lane, per window of
+    /// LANE_SPEED_WINDOW up to some time. Returns the start of each window that anybody crossed
+    /// in.
+    pub fn lane_speeds(
+        &self,
+        l: LaneID,
+        agent_types: &BTreeSet<AgentType>,
+        now: Time,
+    ) -> Vec<(Time, Speed)> {
+        let mut totals: BTreeMap<usize, (Distance, Duration)> = BTreeMap::new();
+        for agent_type in agent_types {
+            for ((_, _, bucket), (dist, dt)) in self
+                .lane_speeds
+                .range((l, *agent_type, 0)..=(l, *agent_type, usize::MAX))
+            {
+                let total = totals
+                    .entry(*bucket)
+                    .or_insert((Distance::ZERO, Duration::ZERO));
+                total.0 += *dist;
+                total.1 += *dt;
+            }
+        }
+        totals
+            .into_iter()
+            .map(|(bucket, (dist, dt))| {
+                (
+                    Time::START_OF_DAY + LANE_SPEED_WINDOW * (bucket as f64),
+                    Speed::from_dist_time(dist, dt),
+                )
+            })
+            .take_while(|(t, _)| *t <= now)
+            .collect()
+    }
+
     /// If calling on prebaked Analytics, be careful to pass in an unedited map, to match how the
     /// simulation was originally run. Otherwise the paths may be nonsense.
     pub fn get_trip_phases(&self, trip: TripID, map: &Map) -> Vec<TripPhase> {
@@ -388,6 +666,36 @@ impl Analytics {
         trips
     }
 
+    /// For trips of one mode finished by `now`, sums up the time spent in each type of phase,
+    /// grouped by windows of when the trip finished. Returns the end of each window, the number of
+    /// trips finishing in it, and the total durations. Empty windows are skipped.
+    pub fn trip_phase_durations_by_mode(
+        &self,
+        mode: TripMode,
+        now: Time,
+        window: Duration,
+    ) -> Vec<(Time, usize, TripPhaseDurations)> {
+        let mut windows: BTreeMap<usize, (usize, TripPhaseDurations)> = BTreeMap::new();
+        for (t, trip_mode, durations) in self.trip_phase_durations.values() {
+            if *t > now || *trip_mode != mode {
+                continue;
+            }
+            let idx = ((*t - Time::START_OF_DAY) / window) as usize;
+            let (cnt, sum) = windows
+                .entry(idx)
+                .or_insert_with(|| (0, TripPhaseDurations::default()));
+            *cnt += 1;
+            sum.add_all(durations);
+        }
+        windows
+            .into_iter()
+            .map(|(idx, (cnt, sum))| {
+                let end = (Time::START_OF_DAY + window * ((idx + 1) as f64)).min(now);
+                (end, cnt, sum)
+            })
+            .collect()
+    }
+
     pub fn active_agents(&self, now: Time) -> Vec<(Time, usize)> {
         let mut starts_stops: Vec<(Time, bool)> = Vec::new();
         for t in self.started_trips.values() {
@@ -430,6 +738,67 @@ impl Analytics {
         pts
     }
 
+    /// For a traffic signal, how much of the time each stage was active, bucketed by (stage index,
+    /// hour). Only counts from the first stage change recorded.
+    pub fn signal_stage_usage(
+        &self,
+        i: IntersectionID,
+        now: Time,
+    ) -> BTreeMap<(usize, usize), StageUsage> {
+        let mut results = BTreeMap::new();
+        let stages = match self.traffic_signal_stages.get(&i) {
+            Some(stages) => stages,
+            None => {
+                return results;
+            }
+        };
+
+        // Flip the busy periods around to find when nothing was moving through
+        let mut idle = Vec::new();
+        let mut last = Time::START_OF_DAY;
+        let ongoing = self.busy_signals.get(&i).map(|(since, _)| (*since, now));
+        for (start, end) in self
+            .traffic_signal_busy
+            .get(&i)
+            .into_iter()
+            .flatten()
+            .cloned()
+            .chain(ongoing)
+        {
+            if start > last {
+                idle.push((last, start));
+            }
+            last = last.max(end);
+        }
+        if last < now {
+            idle.push((last, now));
+        }
+
+        // Both lists are sorted, so walk through them together
+        let mut next_idle = 0;
+        for (idx, (start, stage)) in stages.iter().enumerate() {
+            let end = stages.get(idx + 1).map(|(t, _)| *t).unwrap_or(now).min(now);
+            let mut t1 = *start;
+            while t1 < end {
+                let hour = t1.get_parts().0;
+                let t2 = end.min(Time::START_OF_DAY + Duration::hours(hour + 1));
+                let usage: &mut StageUsage = results.entry((*stage, hour)).or_default();
+                usage.active += t2 - t1;
+
+                while next_idle < idle.len() && idle[next_idle].1 <= t1 {
+                    next_idle += 1;
+                }
+                for (from, to) in idle[next_idle..].iter().take_while(|(from, _)| *from < t2) {
+                    let from = t1.max(*from);
+                    usage.empty.push((from, t2.min(*to) - from));
+                }
+
+                t1 = t2;
+            }
+        }
+        results
+    }
+
     /// Returns the free spots over time
     pub fn parking_lane_availability(
         &self,
@@ -491,12 +860,200 @@ impl Analytics {
     }
 }
 
+/// How a pedestrian used a sidewalk.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum SidewalkUse {
+    /// Arrived from and continued on to somewhere other than a crosswalk
+    Along,
+    /// Arrived from or continued on to a crosswalk at one of the ends
+    Crossing,
+}
+
+/// How one traffic signal stage was used during some period.
+#[derive(Clone, Default)]
+pub struct StageUsage {
+    /// How long the stage was active
+    pub active: Duration,
+    /// While the stage was active, when no vehicles were moving through the intersection, and for
+    /// how long
+    pub empty: Vec<(Time, Duration)>,
+}
+
+impl StageUsage {
+    /// The fraction of the active time that some vehicle was moving through
+    pub fn pct_served(&self) -> f64 {
+        let empty: Duration = self.empty.iter().map(|(_, dt)| *dt).sum();
+        1.0 - empty / self.active
+    }
+}
+
+/// How finely lane speeds are bucketed over time
+pub const LANE_SPEED_WINDOW: Duration = Duration::const_seconds(15.0 * 60.0);
+
+/// How long pedestrians stepping onto a sidewalk are remembered in `recent_sidewalk_entries`
+pub const RECENT_SIDEWALK_WINDOW: Duration = Duration::const_seconds(15.0 * 60.0);
+
+// Buckets events into hours, as a step function up to now
+fn count_per_hour(times: &[Time], now: Time) -> Vec<(Time, usize)> {
+    let mut counts = vec![0; now.get_hours() + 1];
+    for t in times {
+        counts[t.get_hours()] += 1;
+    }
+    let mut pts = Vec::new();
+    for (hour, cnt) in counts.into_iter().enumerate() {
+        pts.push((Time::START_OF_DAY + Duration::hours(hour), cnt));
+        pts.push((Time::START_OF_DAY + Duration::hours(hour + 1), cnt));
+    }
+    pts.pop();
+    pts
+}
+
 impl Default for Analytics {
     fn default() -> Analytics {
         Analytics::new(false)
     }
 }
 
+/// The last few things that happened to each person, explaining the decisions they made.
+#[derive(Clone, Default)]
+pub struct PersonLog {
+    entries: BTreeMap<PersonID, VecDeque<PersonLogEntry>>,
+    // The person doing each ongoing trip, and the last phase it started
+    trips: BTreeMap<TripID, (PersonID, TripPhaseType)>,
+}
+
+/// Only the most recent entries per person are kept, so the log doesn't grow too large over a
+/// full day.
+const MAX_ENTRIES_PER_PERSON: usize = 100;
+
+#[derive(Clone, Debug)]
+pub struct PersonLogEntry {
+    pub time: Time,
+    pub description: String,
+    pub location: Option<PersonLogLocation>,
+}
+
+/// Where something in a person's log happened
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PersonLogLocation {
+    Building(BuildingID),
+    Intersection(IntersectionID),
+    Lane(LaneID),
+    BusStop(BusStopID),
+}
+
+impl PersonLog {
+    /// Oldest entries first
+    pub fn get(&self, person: PersonID) -> Vec<&PersonLogEntry> {
+        self.entries
+            .get(&person)
+            .map(|entries| entries.iter().collect())
+            .unwrap_or_else(Vec::new)
+    }
+
+    fn event(&mut self, ev: &Event, time: Time, map: &Map) {
+        let (person, description, location) = match ev {
+            Event::TripPhaseStarting(trip, person, maybe_req, phase) => {
+                let prev_phase = self
+                    .trips
+                    .insert(*trip, (*person, *phase))
+                    .map(|(_, phase)| phase);
+                let mut location = maybe_req
+                    .as_ref()
+                    .map(|req| PersonLogLocation::Lane(req.start.lane()));
+                let description = match phase {
+                    TripPhaseType::DelayedStart => format!(
+                        "Should start {} now, but is still finishing the previous trip",
+                        trip
+                    ),
+                    // The router starts another parking phase every time the spot it was heading
+                    // for gets taken
+                    TripPhaseType::Parking if prev_phase == Some(TripPhaseType::Parking) => {
+                        "Somebody took the parking spot; looking for another".to_string()
+                    }
+                    TripPhaseType::Parking => "Looking for parking".to_string(),
+                    TripPhaseType::WaitingForBus(_, stop) => {
+                        location = Some(PersonLogLocation::BusStop(*stop));
+                        phase.describe(map)
+                    }
+                    _ => format!("{} for {}", phase.describe(map), trip),
+                };
+                (*person, description, location)
+            }
+            Event::PassengerBoardsTransit(person, _, route, stop, waited) => (
+                *person,
+                format!(
+                    "Boarded {} after waiting {}",
+                    map.get_br(*route).short_name,
+                    waited
+                ),
+                Some(PersonLogLocation::BusStop(*stop)),
+            ),
+            Event::PassengerAlightsTransit(person, _, route, stop) => (
+                *person,
+                format!("Got off {}", map.get_br(*route).short_name),
+                Some(PersonLogLocation::BusStop(*stop)),
+            ),
+            Event::PersonEntersBuilding(person, b) => (
+                *person,
+                format!("Arrived at {}", map.get_b(*b).address),
+                Some(PersonLogLocation::Building(*b)),
+            ),
+            Event::PersonLeavesBuilding(person, b) => (
+                *person,
+                format!("Left {}", map.get_b(*b).address),
+                Some(PersonLogLocation::Building(*b)),
+            ),
+            Event::PersonEntersMap(person, _, i) => (
+                *person,
+                "Entered the map".to_string(),
+                Some(PersonLogLocation::Intersection(*i)),
+            ),
+            Event::PersonLeavesMap(person, agent, i) => (
+                *person,
+                if agent.is_some() {
+                    "Left the map".to_string()
+                } else {
+                    "Left the map after their trip was cancelled".to_string()
+                },
+                Some(PersonLogLocation::Intersection(*i)),
+            ),
+            Event::TripFinished {
+                trip, total_time, ..
+            } => match self.trips.remove(trip) {
+                Some((person, _)) => (
+                    person,
+                    format!("Finished {} after {}", trip, total_time),
+                    None,
+                ),
+                None => {
+                    return;
+                }
+            },
+            Event::TripCancelled(trip, _) => match self.trips.remove(trip) {
+                Some((person, _)) => (person, format!("{} was cancelled", trip), None),
+                None => {
+                    return;
+                }
+            },
+            Event::Alert(AlertLocation::Person(person), msg) => (*person, msg.clone(), None),
+            _ => {
+                return;
+            }
+        };
+
+        let entries = self.entries.entry(person).or_insert_with(VecDeque::new);
+        if entries.len() == MAX_ENTRIES_PER_PERSON {
+            entries.pop_front();
+        }
+        entries.push_back(PersonLogEntry {
+            time,
+            description,
+            location,
+        });
+    }
+}
+
 #[derive(Debug)]
 pub struct TripPhase {
     pub start_time: Time,
@@ -506,6 +1063,83 @@ pub struct TripPhase {
     pub phase_type: TripPhaseType,
 }
 
+/// How long one trip (or the sum over many trips) spent in each type of phase. Multiple phases of
+/// the same type, like walking to and from a parked car, are added together.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct TripPhaseDurations {
+    pub driving: Duration,
+    /// Looking for a parking spot after reaching the destination
+    pub parking: Duration,
+    pub walking: Duration,
+    pub biking: Duration,
+    pub waiting_for_bus: Duration,
+    pub riding_bus: Duration,
+    pub delayed_start: Duration,
+}
+
+impl TripPhaseDurations {
+    fn add(&mut self, phase_type: TripPhaseType, dt: Duration) {
+        match phase_type {
+            TripPhaseType::Driving => {
+                self.driving += dt;
+            }
+            TripPhaseType::Parking => {
+                self.parking += dt;
+            }
+            TripPhaseType::Walking => {
+                self.walking += dt;
+            }
+            TripPhaseType::Biking => {
+                self.biking += dt;
+            }
+            TripPhaseType::WaitingForBus(_, _) => {
+                self.waiting_for_bus += dt;
+            }
+            TripPhaseType::RidingBus(_, _, _) => {
+                self.riding_bus += dt;
+            }
+            TripPhaseType::DelayedStart => {
+                self.delayed_start += dt;
+            }
+            TripPhaseType::Cancelled | TripPhaseType::Finished => {}
+        }
+    }
+
+    pub fn add_all(&mut self, other: &TripPhaseDurations) {
+        self.driving += other.driving;
+        self.parking += other.parking;
+        self.walking += other.walking;
+        self.biking += other.biking;
+        self.waiting_for_bus += other.waiting_for_bus;
+        self.riding_bus += other.riding_bus;
+        self.delayed_start += other.delayed_start;
+    }
+
+    pub fn total(&self) -> Duration {
+        self.driving
+            + self.parking
+            + self.walking
+            + self.biking
+            + self.waiting_for_bus
+            + self.riding_bus
+            + self.delayed_start
+    }
+
+    /// Turns a sum over many trips into an average.
+    pub fn divide(&self, n: usize) -> TripPhaseDurations {
+        let n = n as f64;
+        TripPhaseDurations {
+            driving: self.driving / n,
+            parking: self.parking / n,
+            walking: self.walking / n,
+            biking: self.biking / n,
+            waiting_for_bus: self.waiting_for_bus / n,
+            riding_bus: self.riding_bus / n,
+            delayed_start: self.delayed_start / n,
+        }
+    }
+}
+
 /// See https://github.com/a-b-street/abstreet/issues/85
 #[derive(Clone, Serialize, Deserialize)]
 pub struct TimeSeriesCount<X: Ord + Clone> {
@@ -568,6 +1202,25 @@ impl<X: Ord + Clone> TimeSeriesCount<X> {
         cnt
     }
 
+    /// Approximates the count over the hour before `now`, for every object. Counts are only
+    /// bucketed by hour, so the previous hour is scaled by how much of it falls in the window.
+    pub fn all_counts_in_last_hour(&self, now: Time) -> Counter<X> {
+        let (hour, minute, second, _) = now.get_parts();
+        let pct_of_prev_hour = 1.0 - ((minute * 60 + second) as f64) / 3600.0;
+        let mut cnt = Counter::new();
+        for ((id, _, bucket), value) in &self.counts {
+            if *bucket == hour {
+                cnt.add(id.clone(), *value);
+            } else if *bucket + 1 == hour {
+                cnt.add(
+                    id.clone(),
+                    ((*value as f64) * pct_of_prev_hour).round() as usize,
+                );
+            }
+        }
+        cnt
+    }
+
     pub fn count_per_hour(&self, id: X, time: Time) -> Vec<(AgentType, Vec<(Time, usize)>)> {
         let hour = time.get_hours();
         let mut results = Vec::new();
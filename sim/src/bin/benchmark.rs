@@ -0,0 +1,190 @@
+//! Runs a simulation without rendering anything and reports how fast it went, to notice
+//! performance regressions. Results can be saved as a baseline with `--save_baseline`, then later
+//! runs can be checked against it with `--compare`, which exits with an error if throughput dropped
+//! by more than `--max_regression_pct`.
+//!
+//! Runs use a fixed RNG seed (overridable with --rng_seed), so they're comparable.
+
+use serde::{Deserialize, Serialize};
+
+use abstutil::{prettyprint_usize, CmdArgs, Timer};
+use geom::{Duration, Time};
+
+/// How often to record progress
+const SAMPLE_INTERVAL: Duration = Duration::const_seconds(15.0 * 60.0);
+
+#[derive(Serialize, Deserialize)]
+struct Results {
+    load: String,
+    rng_seed: u64,
+    end_time: Time,
+    load_seconds: f64,
+    simulate_seconds: f64,
+    /// Over the whole run
+    sim_seconds_per_wall_second: f64,
+    /// Per interval: when the interval ended, the throughput during it, and live agents at the end
+    samples: Vec<(Time, f64, usize)>,
+    max_live_agents: usize,
+    finished_trips: usize,
+    /// From the OS, if available
+    peak_memory_kb: Option<usize>,
+}
+
+fn main() {
+    let mut args = CmdArgs::new();
+    let hours = Duration::hours(args.required("--hours").parse::<usize>().unwrap());
+    let save_baseline = args.optional("--save_baseline");
+    let compare = args.optional("--compare");
+    let max_regression_pct = args
+        .optional_parse("--max_regression_pct", |s| s.parse::<f64>())
+        .unwrap_or(10.0);
+    let flags = sim::SimFlags::from_args(&mut args);
+    args.done();
+
+    let results = {
+        let mut timer = Timer::new("benchmark");
+        run(&flags, hours, &mut timer)
+    };
+    print_results(&results);
+
+    if let Some(path) = save_baseline {
+        abstio::write_json(path.clone(), &results);
+        println!("Saved baseline to {}", path);
+    }
+    if let Some(path) = compare {
+        let baseline: Results = abstio::read_json(path, &mut Timer::throwaway());
+        if !compare_results(&baseline, &results, max_regression_pct) {
+            std::process::exit(1);
+        }
+    }
+}
+
+fn run(flags: &sim::SimFlags, hours: Duration, timer: &mut Timer) -> Results {
+    let start = instant::Instant::now();
+    timer.start("load map and scenario");
+    let (map, mut sim, _) = flags.load(timer);
+    timer.stop("load map and scenario");
+    let load_seconds = Duration::realtime_elapsed(start).inner_seconds();
+
+    // Savestates might not start at midnight
+    let sim_started_at = sim.time();
+    let end_time = Time::START_OF_DAY + hours;
+    let mut samples = Vec::new();
+    let mut max_live_agents = 0;
+    timer.start(format!("simulate until {}", end_time));
+    let start = instant::Instant::now();
+    while sim.time() < end_time {
+        let interval_start = instant::Instant::now();
+        let sim_start = sim.time();
+        let dt = SAMPLE_INTERVAL.min(end_time - sim.time());
+        sim.timed_step(&map, dt, &mut None, &mut Timer::throwaway());
+
+        let wall = Duration::realtime_elapsed(interval_start).inner_seconds();
+        let live = sim.num_active_agents();
+        max_live_agents = max_live_agents.max(live);
+        samples.push((sim.time(), throughput(sim.time() - sim_start, wall), live));
+        if sim.time() == sim_start {
+            // Nothing left to simulate
+            break;
+        }
+    }
+    timer.stop(format!("simulate until {}", end_time));
+    let simulate_seconds = Duration::realtime_elapsed(start).inner_seconds();
+
+    Results {
+        load: flags.load.clone(),
+        rng_seed: flags.rng_seed,
+        end_time,
+        load_seconds,
+        simulate_seconds,
+        sim_seconds_per_wall_second: throughput(sim.time() - sim_started_at, simulate_seconds),
+        samples,
+        max_live_agents,
+        finished_trips: sim.get_analytics().finished_trips.len(),
+        peak_memory_kb: peak_memory_kb(),
+    }
+}
+
+fn throughput(sim_time: Duration, wall_seconds: f64) -> f64 {
+    if wall_seconds == 0.0 {
+        return 0.0;
+    }
+    sim_time.inner_seconds() / wall_seconds
+}
+
+fn print_results(results: &Results) {
+    println!();
+    println!("Benchmark of {} until {}", results.load, results.end_time);
+    println!("- Loading took {:.1}s", results.load_seconds);
+    println!(
+        "- Simulating took {:.1}s, {:.1} sim-seconds per second",
+        results.simulate_seconds, results.sim_seconds_per_wall_second
+    );
+    for (time, rate, live) in &results.samples {
+        println!(
+            "  - By {}: {:.1} sim-seconds per second, {} live agents",
+            time,
+            rate,
+            prettyprint_usize(*live)
+        );
+    }
+    println!(
+        "- At most {} live agents, {} finished trips",
+        prettyprint_usize(results.max_live_agents),
+        prettyprint_usize(results.finished_trips)
+    );
+    if let Some(kb) = results.peak_memory_kb {
+        println!("- Peak memory: {} MB", prettyprint_usize(kb / 1024));
+    }
+}
+
+// Returns false if the new run regressed
+fn compare_results(baseline: &Results, new: &Results, max_regression_pct: f64) -> bool {
+    if baseline.load != new.load
+        || baseline.rng_seed != new.rng_seed
+        || baseline.end_time != new.end_time
+    {
+        println!(
+            "Warning: the baseline ran {} with seed {} until {}, so this comparison isn't fair",
+            baseline.load, baseline.rng_seed, baseline.end_time
+        );
+    }
+    if baseline.finished_trips != new.finished_trips {
+        println!(
+            "Warning: the baseline finished {} trips, but this run finished {}. The simulation \
+             behaves differently now.",
+            prettyprint_usize(baseline.finished_trips),
+            prettyprint_usize(new.finished_trips)
+        );
+    }
+
+    let pct_change = 100.0
+        * (new.sim_seconds_per_wall_second - baseline.sim_seconds_per_wall_second)
+        / baseline.sim_seconds_per_wall_second;
+    println!(
+        "Throughput changed by {:.1}% ({:.1} to {:.1} sim-seconds per second)",
+        pct_change, baseline.sim_seconds_per_wall_second, new.sim_seconds_per_wall_second
+    );
+    if let (Some(before), Some(after)) = (baseline.peak_memory_kb, new.peak_memory_kb) {
+        println!(
+            "Peak memory changed from {} MB to {} MB",
+            prettyprint_usize(before / 1024),
+            prettyprint_usize(after / 1024)
+        );
+    }
+    if pct_change < -max_regression_pct {
+        println!(
+            "Throughput regressed by more than the allowed {}%",
+            max_regression_pct
+        );
+        return false;
+    }
+    true
+}
+
+// Only works on Linux
+fn peak_memory_kb() -> Option<usize> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    let line = status.lines().find(|line| line.starts_with("VmHWM:"))?;
+    line.split_whitespace().nth(1)?.parse().ok()
+}
@@ -0,0 +1,187 @@
+use std::collections::{BTreeSet, VecDeque};
+
+use geom::Time;
+use map_model::{BuildingID, IntersectionID, LaneID, Map};
+
+use crate::{AgentID, Event, TripID};
+
+/// An opt-in log of the most interesting things happening in the simulation, for debugging. Only
+/// a bounded number of the most recent events are kept, and they're stored as they happened, only
+/// described once somebody looks at them, so it's cheap enough to leave on.
+#[derive(Clone)]
+pub struct EventLog {
+    capacity: usize,
+    /// Oldest first
+    entries: VecDeque<EventLogEntry>,
+    /// Traffic signals change stages constantly, so only these are logged
+    watched_intersections: BTreeSet<IntersectionID>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum EventCategory {
+    Trips,
+    Agents,
+    Parking,
+    TrafficSignals,
+    Gridlock,
+}
+
+/// What an event is about
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum EventSubject {
+    Trip(TripID),
+    Agent(AgentID),
+    Building(BuildingID),
+    Intersection(IntersectionID),
+    Lane(LaneID),
+}
+
+#[derive(Clone)]
+pub struct EventLogEntry {
+    pub time: Time,
+    pub category: EventCategory,
+    event: Event,
+}
+
+impl EventLog {
+    pub(crate) fn new(capacity: usize) -> EventLog {
+        EventLog {
+            capacity,
+            entries: VecDeque::new(),
+            watched_intersections: BTreeSet::new(),
+        }
+    }
+
+    pub(crate) fn event(&mut self, ev: &Event, time: Time) {
+        let category = match ev {
+            Event::TripPhaseStarting(_, _, _, _)
+            | Event::TripFinished { .. }
+            | Event::TripCancelled(_, _) => EventCategory::Trips,
+            Event::PersonEntersMap(_, _, _)
+            | Event::PersonLeavesMap(_, _, _)
+            | Event::PersonEntersBuilding(_, _)
+            | Event::PersonLeavesBuilding(_, _) => EventCategory::Agents,
+            Event::ParkingSearchFailed(_, _) => EventCategory::Parking,
+            Event::TrafficSignalStageChanged(i, _) => {
+                if !self.watched_intersections.contains(i) {
+                    return;
+                }
+                EventCategory::TrafficSignals
+            }
+            Event::TurnConflictCycle(_, _) => EventCategory::Gridlock,
+            _ => {
+                return;
+            }
+        };
+        if self.entries.len() == self.capacity {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(EventLogEntry {
+            time,
+            category,
+            event: ev.clone(),
+        });
+    }
+
+    /// Newest first
+    pub fn entries(&self) -> impl Iterator<Item = &EventLogEntry> {
+        self.entries.iter().rev()
+    }
+
+    pub fn watched_intersections(&self) -> &BTreeSet<IntersectionID> {
+        &self.watched_intersections
+    }
+
+    /// Start or stop logging the stage changes of a traffic signal
+    pub fn toggle_watching(&mut self, i: IntersectionID) {
+        if !self.watched_intersections.remove(&i) {
+            self.watched_intersections.insert(i);
+        }
+    }
+}
+
+impl EventCategory {
+    pub fn all() -> Vec<EventCategory> {
+        vec![
+            EventCategory::Trips,
+            EventCategory::Agents,
+            EventCategory::Parking,
+            EventCategory::TrafficSignals,
+            EventCategory::Gridlock,
+        ]
+    }
+
+    pub fn describe(self) -> &'static str {
+        match self {
+            EventCategory::Trips => "trips",
+            EventCategory::Agents => "agents appearing and disappearing",
+            EventCategory::Parking => "parking failures",
+            EventCategory::TrafficSignals => "watched traffic signals",
+            EventCategory::Gridlock => "gridlock",
+        }
+    }
+}
+
+impl EventLogEntry {
+    pub fn describe(&self, map: &Map) -> String {
+        match self.event {
+            Event::TripPhaseStarting(trip, person, _, phase) => {
+                format!("{} ({}): {}", trip, person, phase.describe(map))
+            }
+            Event::TripFinished {
+                trip,
+                mode,
+                total_time,
+                ..
+            } => format!(
+                "{} finished {} after {}",
+                trip,
+                mode.ongoing_verb(),
+                total_time
+            ),
+            Event::TripCancelled(trip, mode) => {
+                format!("{} cancelled while {}", trip, mode.ongoing_verb())
+            }
+            Event::PersonEntersMap(person, agent, i) => {
+                format!("{} enters the map at {} as {}", person, i, agent)
+            }
+            Event::PersonLeavesMap(person, _, i) => format!("{} leaves the map at {}", person, i),
+            Event::PersonEntersBuilding(person, b) => {
+                format!("{} enters {}", person, map.get_b(b).address)
+            }
+            Event::PersonLeavesBuilding(person, b) => {
+                format!("{} leaves {}", person, map.get_b(b).address)
+            }
+            Event::ParkingSearchFailed(car, l) => {
+                format!("{} can't find parking anywhere reachable from {}", car, l)
+            }
+            Event::TrafficSignalStageChanged(i, stage) => {
+                format!("{} switches to stage {}", i, stage + 1)
+            }
+            Event::TurnConflictCycle(i, ref cars) => format!(
+                "{} vehicles are stuck in a cycle of conflicting turns at {}",
+                cars.len(),
+                i
+            ),
+            _ => unreachable!(),
+        }
+    }
+
+    pub fn subject(&self) -> Option<EventSubject> {
+        match self.event {
+            Event::TripPhaseStarting(trip, _, _, _)
+            | Event::TripFinished { trip, .. }
+            | Event::TripCancelled(trip, _) => Some(EventSubject::Trip(trip)),
+            Event::PersonEntersMap(_, agent, _) => Some(EventSubject::Agent(agent)),
+            Event::PersonLeavesMap(_, _, i) => Some(EventSubject::Intersection(i)),
+            Event::PersonEntersBuilding(_, b) | Event::PersonLeavesBuilding(_, b) => {
+                Some(EventSubject::Building(b))
+            }
+            Event::ParkingSearchFailed(_, l) => Some(EventSubject::Lane(l)),
+            Event::TrafficSignalStageChanged(i, _) | Event::TurnConflictCycle(i, _) => {
+                Some(EventSubject::Intersection(i))
+            }
+            _ => None,
+        }
+    }
+}
@@ -6,7 +6,7 @@ use map_model::{
     PathRequest, Traversable, TurnID,
 };
 
-use crate::{AgentID, CarID, ParkingSpot, PedestrianID, PersonID, TripID, TripMode};
+use crate::{AgentID, CarID, MergeManeuver, ParkingSpot, PedestrianID, PersonID, TripID, TripMode};
 
 /// As a simulation runs, different systems emit Events. This cleanly separates the internal
 /// mechanics of the simulation from consumers that just want to know what's happening.
@@ -55,6 +55,16 @@ pub enum Event {
     /// TripID, LaneID (Where the delay was encountered), Average Speed, Max Speed
     LaneSpeedPercentage(TripID, LaneID, Speed, Speed),
 
+    /// The vehicle is stuck at the end of this lane.
+    ParkingSearchFailed(CarID, LaneID),
+    /// The index of the new stage
+    TrafficSignalStageChanged(IntersectionID, usize),
+    /// Vehicles waiting on each other to turn, which was broken up by letting one of them through
+    TurnConflictCycle(IntersectionID, Vec<CarID>),
+    /// Only emitted for the road being watched. True if another vehicle had to brake because of
+    /// the maneuver.
+    Merge(CarID, TurnID, MergeManeuver, bool),
+
     /// Just use for parking replanning. Not happy about copying the full path in here, but the way
     /// to plumb info into Analytics is Event.
     PathAmended(Path),
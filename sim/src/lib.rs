@@ -30,15 +30,20 @@ pub use crate::render::{
     UnzoomedAgent,
 };
 
-pub use self::analytics::{Analytics, TripPhase};
+pub use self::analytics::{
+    Analytics, PersonLog, PersonLogEntry, PersonLogLocation, SidewalkUse, StageUsage, TripPhase,
+    TripPhaseDurations, LANE_SPEED_WINDOW, RECENT_SIDEWALK_WINDOW,
+};
 pub(crate) use self::cap::CapSimState;
+pub use self::event_log::{EventCategory, EventLog, EventLogEntry, EventSubject};
 pub(crate) use self::events::Event;
 pub use self::events::{AlertLocation, TripPhaseType};
 pub use self::make::{
-    fork_rng, BorderSpawnOverTime, ExternalPerson, ExternalTrip, ExternalTripEndpoint, IndividTrip,
-    MapBorders, PersonSpec, Scenario, ScenarioGenerator, ScenarioModifier, SimFlags, SpawnOverTime,
-    TripEndpoint, TripPurpose,
+    fork_rng, BorderSpawnOverTime, BuildingOverride, ExternalPerson, ExternalTrip,
+    ExternalTripEndpoint, IndividTrip, MapBorders, PersonSpec, Scenario, ScenarioGenerator,
+    ScenarioModifier, SimFlags, SpawnOverTime, TripEndpoint, TripPurpose,
 };
+pub use self::merge_watch::{MergeEvent, MergeManeuver, MergeWatch};
 pub(crate) use self::make::{StartTripArgs, TripSpec};
 pub(crate) use self::mechanics::{
     DrivingSimState, IntersectionSimState, ParkingSim, ParkingSimState, WalkingSimState,
@@ -47,7 +52,10 @@ pub(crate) use self::pandemic::PandemicModel;
 pub(crate) use self::recorder::TrafficRecorder;
 pub(crate) use self::router::{ActionAtEnd, Router};
 pub(crate) use self::scheduler::{Command, Scheduler};
-pub use self::sim::{AgentProperties, AlertHandler, DelayCause, Sim, SimCallback, SimOptions};
+pub use self::sim::{
+    AgentProperties, AgentSpeed, AlertHandler, DelayCause, Gridlock, QueueSegment,
+    QueueSpillback, RouteVehicle, Sim, SimCallback, SimDigest, SimOptions, SpeedConstraint,
+};
 pub(crate) use self::transit::TransitSimState;
 pub use self::trips::TripMode;
 pub use self::trips::{CommutersVehiclesCounts, Person, PersonState, TripInfo, TripResult};
@@ -55,9 +63,11 @@ pub(crate) use self::trips::{TripLeg, TripManager};
 
 mod analytics;
 mod cap;
+mod event_log;
 mod events;
 mod make;
 mod mechanics;
+mod merge_watch;
 mod pandemic;
 mod recorder;
 mod render;
@@ -337,7 +347,7 @@ impl VehicleSpec {
     }
 }
 
-#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 pub enum ParkingSpot {
     /// Lane and idx
     Onstreet(LaneID, usize),
@@ -0,0 +1,134 @@
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use rand_xorshift::XorShiftRng;
+use serde::{Deserialize, Serialize};
+
+use geom::{Distance, Duration, Time};
+use map_model::osm::OsmID;
+use map_model::{BuildingID, BuildingType, Map};
+
+use crate::{IndividTrip, PersonSpec, Scenario, TripEndpoint, TripMode, TripPurpose};
+
+/// Pretends a building has more households or jobs than the scenario accounts for, to quickly try
+/// out a land-use change, like a parking lot becoming an apartment building. Each household or
+/// job is one synthetic person with a simple schedule: commuting to work in the morning and home
+/// in the evening.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct BuildingOverride {
+    /// The building's OSM ID, which stays the same when the map is regenerated
+    pub building: OsmID,
+    /// People living here, who work somewhere else
+    pub households: usize,
+    /// People working here, who live somewhere else
+    pub jobs: usize,
+}
+
+impl BuildingOverride {
+    /// Adds the synthetic people to a scenario, with every trip marked as synthetic. The same
+    /// override always adds the same people. Returns how many people were added, which is 0 if
+    /// the building doesn't exist on this map anymore.
+    pub fn apply(&self, map: &Map, s: &mut Scenario) -> usize {
+        let b = match map.find_b_by_osm_id(self.building) {
+            Some(b) => b,
+            None => {
+                return 0;
+            }
+        };
+        // Every building has its own RNG, so adding or removing one override doesn't change the
+        // people generated for the others
+        let mut rng = XorShiftRng::seed_from_u64(self.building.inner() as u64);
+        let homes = other_buildings(map, b, |bldg_type| bldg_type.has_residents());
+        let workplaces = other_buildings(map, b, |bldg_type| match bldg_type {
+            BuildingType::Commercial(_) | BuildingType::ResidentialCommercial(_, _) => true,
+            BuildingType::Residential { .. } | BuildingType::Empty => false,
+        });
+
+        let mut added = 0;
+        for _ in 0..self.households {
+            if let Some(work) = workplaces.choose(&mut rng) {
+                s.people.push(commuter(map, b, *work, &mut rng));
+                added += 1;
+            }
+        }
+        for _ in 0..self.jobs {
+            if let Some(home) = homes.choose(&mut rng) {
+                s.people.push(commuter(map, *home, b, &mut rng));
+                added += 1;
+            }
+        }
+        added
+    }
+
+    pub fn describe(&self) -> String {
+        format!(
+            "{} extra households and {} extra jobs at {}",
+            self.households, self.jobs, self.building
+        )
+    }
+}
+
+/// Buildings besides the overridden one matching a type. If none do, any other building.
+fn other_buildings<F: Fn(&BuildingType) -> bool>(
+    map: &Map,
+    exclude: BuildingID,
+    matches: F,
+) -> Vec<BuildingID> {
+    let mut result: Vec<BuildingID> = map
+        .all_buildings()
+        .iter()
+        .filter(|b| b.id != exclude && matches(&b.bldg_type))
+        .map(|b| b.id)
+        .collect();
+    if result.is_empty() {
+        result = map
+            .all_buildings()
+            .iter()
+            .filter(|b| b.id != exclude)
+            .map(|b| b.id)
+            .collect();
+    }
+    result
+}
+
+/// Somebody leaving home between 7 and 9am, and leaving work between 4 and 6pm. They walk or bike
+/// short distances, and otherwise drive.
+fn commuter(map: &Map, home: BuildingID, work: BuildingID, rng: &mut XorShiftRng) -> PersonSpec {
+    let dist = map
+        .get_b(home)
+        .label_center
+        .dist_to(map.get_b(work).label_center);
+    let mode = if dist < Distance::miles(0.5) {
+        TripMode::Walk
+    } else if dist < Distance::miles(2.0) {
+        TripMode::Bike
+    } else {
+        TripMode::Drive
+    };
+    let leave_home =
+        Time::START_OF_DAY + Duration::hours(7) + Duration::seconds(rng.gen_range(0.0..7200.0));
+    let leave_work =
+        Time::START_OF_DAY + Duration::hours(16) + Duration::seconds(rng.gen_range(0.0..7200.0));
+
+    let mut trips = vec![
+        IndividTrip::new(
+            leave_home,
+            TripPurpose::Work,
+            TripEndpoint::Bldg(work),
+            mode,
+        ),
+        IndividTrip::new(
+            leave_work,
+            TripPurpose::Home,
+            TripEndpoint::Bldg(home),
+            mode,
+        ),
+    ];
+    for trip in &mut trips {
+        trip.synthetic = true;
+    }
+    PersonSpec {
+        orig_id: None,
+        origin: TripEndpoint::Bldg(home),
+        trips,
+    }
+}
@@ -1,12 +1,15 @@
 use std::collections::BTreeSet;
 
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+use rand_xorshift::XorShiftRng;
 use serde::{Deserialize, Serialize};
 
-use abstutil::Timer;
-use geom::{Duration, Time};
+use abstutil::{prettyprint_usize, Timer};
+use geom::{Distance, Duration, Time};
 use map_model::Map;
 
-use crate::{Scenario, TripMode};
+use crate::{Scenario, TripEndpoint, TripMode};
 
 /// Transforms an existing Scenario before instantiating it.
 #[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Serialize, Deserialize)]
@@ -21,6 +24,15 @@ pub enum ScenarioModifier {
     },
     /// Scenario name
     AddExtraTrips(String),
+    /// Switch some percent of the driving trips that're short enough and depart in a time window
+    /// to another mode. Trips that can't be made in the new mode are left alone.
+    ShiftDriveTrips {
+        pct_trips: usize,
+        max_distance: Distance,
+        departure_filter: (Time, Time),
+        to_mode: TripMode,
+        rng_seed: u64,
+    },
 }
 
 impl ScenarioModifier {
@@ -83,6 +95,21 @@ impl ScenarioModifier {
                 }
                 s
             }
+            ScenarioModifier::ShiftDriveTrips {
+                pct_trips,
+                max_distance,
+                departure_filter,
+                to_mode,
+                rng_seed,
+            } => shift_drive_trips(
+                map,
+                s,
+                *pct_trips,
+                *max_distance,
+                *departure_filter,
+                *to_mode,
+                *rng_seed,
+            ),
         }
     }
 
@@ -104,6 +131,22 @@ impl ScenarioModifier {
                 to_mode.map(|m| m.verb())
             ),
             ScenarioModifier::AddExtraTrips(name) => format!("Add extra trips from {}", name),
+            ScenarioModifier::ShiftDriveTrips {
+                pct_trips,
+                max_distance,
+                departure_filter,
+                to_mode,
+                rng_seed,
+            } => format!(
+                "change {}% of driving trips shorter than {} leaving between {} and {} to {} \
+                 (seed {})",
+                pct_trips,
+                max_distance,
+                departure_filter.0.ampm_tostring(),
+                departure_filter.1.ampm_tostring(),
+                to_mode.verb(),
+                rng_seed
+            ),
         }
     }
 }
@@ -134,3 +177,91 @@ fn repeat_days(mut s: Scenario, days: usize) -> Scenario {
     }
     s
 }
+
+fn shift_drive_trips(
+    map: &Map,
+    mut s: Scenario,
+    pct_trips: usize,
+    max_distance: Distance,
+    departure_filter: (Time, Time),
+    to_mode: TripMode,
+    rng_seed: u64,
+) -> Scenario {
+    // Find all of the driving trips that match, remembering the length of each drive. (person,
+    // trip) indices
+    let mut candidates: Vec<(usize, usize, Distance)> = Vec::new();
+    for (person_idx, person) in s.people.iter().enumerate() {
+        let mut from = person.origin;
+        for (trip_idx, trip) in person.trips.iter().enumerate() {
+            let to = trip.destination;
+            if trip.mode == TripMode::Drive
+                && !trip.cancelled
+                && trip.depart >= departure_filter.0
+                && trip.depart <= departure_filter.1
+            {
+                if let Some(req) = TripEndpoint::path_req(from, to, TripMode::Drive, map) {
+                    // A path can't be shorter than the straight line between its ends, and that's
+                    // much cheaper to check than pathfinding
+                    if req.start.pt(map).dist_to(req.end.pt(map)) <= max_distance {
+                        if let Ok(path) = map.pathfind(req) {
+                            let dist = path.total_length();
+                            if dist <= max_distance {
+                                candidates.push((person_idx, trip_idx, dist));
+                            }
+                        }
+                    }
+                }
+            }
+            from = to;
+        }
+    }
+
+    // The same seed always picks the same trips
+    let mut rng = XorShiftRng::seed_from_u64(rng_seed);
+    candidates.shuffle(&mut rng);
+    let num_picked = candidates.len() * pct_trips / 100;
+
+    let mut converted = 0;
+    let mut infeasible = 0;
+    let mut vehicle_distance = Distance::ZERO;
+    for (person_idx, trip_idx, dist) in candidates.into_iter().take(num_picked) {
+        let person = &mut s.people[person_idx];
+        let from = if trip_idx == 0 {
+            person.origin
+        } else {
+            person.trips[trip_idx - 1].destination
+        };
+        let trip = &mut person.trips[trip_idx];
+        if !feasible(from, trip.destination, to_mode, map) {
+            infeasible += 1;
+            continue;
+        }
+        trip.mode = to_mode;
+        trip.modified = true;
+        converted += 1;
+        vehicle_distance += dist;
+    }
+    info!(
+        "Changed {} driving trips to {}, removing {} of driving. {} more couldn't be made that way, \
+         so they still drive.",
+        prettyprint_usize(converted),
+        to_mode.noun(),
+        vehicle_distance,
+        prettyprint_usize(infeasible)
+    );
+    s
+}
+
+// Can a trip be made with this mode? Transit trips that'd just walk the whole way don't count.
+fn feasible(from: TripEndpoint, to: TripEndpoint, mode: TripMode, map: &Map) -> bool {
+    let req = match TripEndpoint::path_req(from, to, mode, map) {
+        Some(req) => req,
+        None => {
+            return false;
+        }
+    };
+    if mode == TripMode::Transit {
+        return map.should_use_transit(req.start, req.end).is_some();
+    }
+    map.pathfind(req).is_ok()
+}
@@ -4,12 +4,13 @@ use std::fmt;
 use anyhow::Result;
 use rand::seq::SliceRandom;
 use rand::{Rng, SeedableRng};
+use rand_distr::{Distribution, Normal};
 use rand_xorshift::XorShiftRng;
 use serde::{Deserialize, Serialize};
 
 use abstio::MapName;
 use abstutil::{prettyprint_usize, Counter, Timer};
-use geom::{Distance, Speed, Time};
+use geom::{Distance, Duration, Speed, Time};
 use map_model::{BuildingID, Map, OffstreetParking, RoadID};
 
 use crate::make::fork_rng;
@@ -48,6 +49,10 @@ pub struct IndividTrip {
     pub cancelled: bool,
     /// Did a ScenarioModifier affect this?
     pub modified: bool,
+    /// Was this made up by a `BuildingOverride`? These are added when a scenario is instantiated,
+    /// so they're never saved in a scenario file.
+    #[serde(skip)]
+    pub synthetic: bool,
 }
 
 impl IndividTrip {
@@ -64,12 +69,13 @@ impl IndividTrip {
             purpose,
             cancelled: false,
             modified: false,
+            synthetic: false,
         }
     }
 }
 
 /// Lifted from Seattle's Soundcast model, but seems general enough to use anyhere.
-#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
 pub enum TripPurpose {
     Home,
     Work,
@@ -140,7 +146,7 @@ impl Scenario {
             }
         }
 
-        timer.start_iter("trips for People", self.people.len());
+        timer.start_iter("spawn people", self.people.len());
         let mut parked_cars: Vec<(Vehicle, BuildingID)> = Vec::new();
         let mut schedule_trips = Vec::new();
         for p in &self.people {
@@ -167,6 +173,7 @@ impl Scenario {
                         end: trip.destination.clone(),
                         purpose: trip.purpose,
                         modified: trip.modified,
+                        synthetic: trip.synthetic,
                         capped: false,
                         cancellation_reason: if trip.cancelled {
                             Some(format!("cancelled by ScenarioModifier"))
@@ -264,6 +271,39 @@ impl Scenario {
         per_bldg
     }
 
+    /// Shifts every trip's departure by a random amount drawn from a normal distribution centered
+    /// on zero, for checking how sensitive results are to exact timing. Trips stay within the day
+    /// they originally started. If a shifted trip would leave before (or at the same time as) the
+    /// person's previous trip, it's pushed to just after that trip instead, so schedules stay in
+    /// order.
+    pub fn jitter_departures(mut self, stddev: Duration, rng: &mut XorShiftRng) -> Scenario {
+        let normal = Normal::new(0.0, stddev.inner_seconds()).unwrap();
+        let one_day = Duration::hours(24);
+        for person in &mut self.people {
+            let mut prev: Option<Time> = None;
+            for trip in &mut person.trips {
+                let day = (trip.depart.inner_seconds() / one_day.inner_seconds()).floor();
+                let start_of_day = day * one_day.inner_seconds();
+                let end_of_day = start_of_day + one_day.inner_seconds() - 1.0;
+                let offset: f64 = normal.sample(rng);
+                let mut depart = Time::START_OF_DAY
+                    + Duration::seconds(
+                        (trip.depart.inner_seconds() + offset)
+                            .max(start_of_day)
+                            .min(end_of_day),
+                    );
+                if let Some(prev) = prev {
+                    if depart <= prev {
+                        depart = prev + Duration::seconds(1.0);
+                    }
+                }
+                trip.depart = depart;
+                prev = Some(depart);
+            }
+        }
+        self
+    }
+
     pub fn remove_weird_schedules(mut self) -> Scenario {
         let orig = self.people.len();
         self.people.retain(|person| match person.check_schedule() {
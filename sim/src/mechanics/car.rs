@@ -21,13 +21,51 @@ pub(crate) struct Car {
     pub trip_and_person: Option<(TripID, PersonID)>,
     pub started_at: Time,
     pub total_blocked_time: Duration,
+    /// The running total of time that vehicles queued right behind this one lost while this one
+    /// was stopped at the front, sampled every time it starts moving again. Cars can live a long
+    /// time, so old samples are thinned out to keep this small. Not kept in savestates, so older
+    /// ones still load.
+    #[serde(skip)]
+    pub delay_caused: Vec<(Time, Duration)>,
 
     /// In reverse order -- most recently left is first. The sum length of these must be >=
     /// vehicle.length.
     pub last_steps: VecDeque<Traversable>,
 }
 
+// Every car has this history, so keep it short
+const MAX_DELAY_CAUSED_SAMPLES: usize = 64;
+
 impl Car {
+    pub fn record_delay_caused(&mut self, now: Time, delay: Duration) {
+        if delay == Duration::ZERO {
+            return;
+        }
+        let total = self.total_delay_caused() + delay;
+        self.delay_caused.push((now, total));
+        if self.delay_caused.len() > MAX_DELAY_CAUSED_SAMPLES {
+            // Halve the resolution, always keeping the latest total
+            let latest = *self.delay_caused.last().unwrap();
+            self.delay_caused = self
+                .delay_caused
+                .iter()
+                .skip(1)
+                .step_by(2)
+                .cloned()
+                .collect();
+            if self.delay_caused.last() != Some(&latest) {
+                self.delay_caused.push(latest);
+            }
+        }
+    }
+
+    pub fn total_delay_caused(&self) -> Duration {
+        self.delay_caused
+            .last()
+            .map(|(_, total)| *total)
+            .unwrap_or(Duration::ZERO)
+    }
+
     /// Assumes the current head of the path is the thing to cross.
     pub fn crossing_state(&self, start_dist: Distance, start_time: Time, map: &Map) -> CarState {
         let dist_int = DistanceInterval::new_driving(
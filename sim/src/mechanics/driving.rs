@@ -1,22 +1,26 @@
 use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
 
+use anyhow::Result;
 use serde::{Deserialize, Serialize};
 
 use abstutil::{deserialize_hashmap, serialize_hashmap, FixedMap, IndexableKey};
-use geom::{Distance, Duration, PolyLine, Speed, Time};
+use geom::{Distance, Duration, PolyLine, Speed, Time, EPSILON_DIST};
 use map_model::{IntersectionID, LaneID, Map, Path, Position, Traversable};
 
 use crate::mechanics::car::{Car, CarState};
 use crate::mechanics::Queue;
 use crate::sim::Ctx;
 use crate::{
-    ActionAtEnd, AgentID, AgentProperties, CarID, Command, CreateCar, DelayCause, DistanceInterval,
-    DrawCarInput, Event, IntersectionSimState, ParkedCar, ParkingSim, ParkingSpot, PersonID,
-    SimOptions, TimeInterval, TransitSimState, TripID, TripManager, UnzoomedAgent, Vehicle,
-    WalkingSimState, FOLLOWING_DISTANCE,
+    ActionAtEnd, AgentID, AgentProperties, AgentSpeed, CarID, Command, CreateCar, DelayCause,
+    DistanceInterval, DrawCarInput, DrivingGoal, Event, IntersectionSimState, ParkedCar,
+    ParkingSim, ParkingSpot, PersonID, QueueSegment, QueueSpillback, SimOptions, SpeedConstraint,
+    TimeInterval, TransitSimState, TripID, TripManager, UnzoomedAgent, Vehicle, WalkingSimState,
+    FOLLOWING_DISTANCE, MIN_CAR_LENGTH,
 };
 
 const TIME_TO_WAIT_AT_BUS_STOP: Duration = Duration::const_seconds(10.0);
+/// When rerouting, lanes at least this full are avoided if there's another way
+const CONGESTED: f64 = 0.8;
 
 // TODO Do something else.
 pub const BLIND_RETRY_TO_CREEP_FORWARDS: Duration = Duration::const_seconds(0.1);
@@ -119,6 +123,7 @@ impl DrivingSimState {
                 last_steps: VecDeque::new(),
                 started_at: now,
                 total_blocked_time: Duration::ZERO,
+                delay_caused: Vec::new(),
                 trip_and_person: params.trip_and_person,
             };
             if let Some(p) = params.maybe_parked_car {
@@ -311,7 +316,8 @@ impl DrivingSimState {
                     ctx.scheduler.push(now, Command::UpdateCar(car.vehicle.id));
                 }
             }
-            CarState::Unparking(front, _, _) => {
+            CarState::Unparking(front, _, time_int) => {
+                self.charge_delay_caused(car, now, time_int.start);
                 if car.router.last_step() {
                     // Actually, we need to do this first. Ignore the answer -- if we're doing
                     // something weird like vanishing or re-parking immediately (quite unlikely),
@@ -330,7 +336,8 @@ impl DrivingSimState {
                 ctx.scheduler
                     .push(car.state.get_end_time(), Command::UpdateCar(car.vehicle.id));
             }
-            CarState::IdlingAtStop(dist, _) => {
+            CarState::IdlingAtStop(dist, time_int) => {
+                self.charge_delay_caused(car, now, time_int.start);
                 car.router = transit.bus_departed_from_stop(car.vehicle.id, ctx.map);
                 self.events
                     .push(Event::PathAmended(car.router.get_path().clone()));
@@ -409,6 +416,15 @@ impl DrivingSimState {
                     }
                 }
 
+                // Waiting for a red light isn't the car's fault
+                let at_signal = match goto {
+                    Traversable::Turn(t) => ctx.map.maybe_get_traffic_signal(t.parent).is_some(),
+                    Traversable::Lane(_) => false,
+                };
+                if !at_signal {
+                    self.charge_delay_caused(car, now, blocked_since);
+                }
+
                 {
                     let mut queue = self.queues.get_mut(&from).unwrap();
                     assert_eq!(queue.cars.pop_front().unwrap(), car.vehicle.id);
@@ -1074,6 +1090,166 @@ impl DrivingSimState {
         }
     }
 
+    /// None if the car hasn't spawned yet.
+    pub fn agent_speed(
+        &self,
+        id: CarID,
+        now: Time,
+        map: &Map,
+        intersections: &IntersectionSimState,
+    ) -> Option<AgentSpeed> {
+        let car = self.cars.get(&id)?;
+        let constraint = match car.state {
+            CarState::Crossing(_, _) | CarState::Queued { .. } => {
+                let leader = if let CarState::Crossing(_, _) = car.state {
+                    self.bound_by(car, now)
+                } else {
+                    self.get_leader(car, now)
+                };
+                match leader {
+                    Some((leader, _))
+                        if matches!(
+                            self.cars[&leader].state,
+                            CarState::Parking(_, _, _) | CarState::Unparking(_, _, _)
+                        ) =>
+                    {
+                        SpeedConstraint::BlockedByParking(leader)
+                    }
+                    Some((leader, gap)) => SpeedConstraint::Following(leader, gap),
+                    // Queued with nobody in front means about to ask to start the next turn
+                    None if matches!(car.state, CarState::Queued { .. }) => {
+                        self.waiting_at_intersection(car, map, intersections)
+                    }
+                    None => SpeedConstraint::FreeFlow,
+                }
+            }
+            CarState::WaitingToAdvance { .. } => {
+                self.waiting_at_intersection(car, map, intersections)
+            }
+            CarState::Unparking(_, _, _) | CarState::Parking(_, _, _) => {
+                SpeedConstraint::Maneuvering
+            }
+            CarState::IdlingAtStop(_, _) => SpeedConstraint::AtTransitStop,
+        };
+        Some(AgentSpeed {
+            speed: self.current_speed(car, now, map, 0),
+            max_speed: max_speed(car, map),
+            constraint,
+        })
+    }
+
+    fn current_speed(&self, car: &Car, now: Time, map: &Map, depth: usize) -> Speed {
+        if let CarState::Crossing(_, _) = car.state {
+            if let Some((leader, _)) = self.bound_by(car, now) {
+                // A line of cars bound by each other all move at the speed of the first. Don't
+                // loop forever if the line wraps around a block.
+                if depth == 100 {
+                    return Speed::ZERO;
+                }
+                return self.current_speed(&self.cars[&leader], now, map, depth + 1);
+            }
+            return max_speed(car, map);
+        }
+        Speed::ZERO
+    }
+
+    /// If a car crossing a lane or turn is going slower than it could because of the vehicle in
+    /// front, returns that vehicle and the gap to its back.
+    fn bound_by(&self, car: &Car, now: Time) -> Option<(CarID, Distance)> {
+        if let CarState::Crossing(ref time_int, ref dist_int) = car.state {
+            let free_front = dist_int.lerp(time_int.percent_clamp_end(now));
+            if self.get_car_front(now, car) + EPSILON_DIST < free_front {
+                return self.get_leader(car, now);
+            }
+        }
+        None
+    }
+
+    /// The vehicle directly in front of a car, and the gap to its back.
+    fn get_leader(&self, car: &Car, now: Time) -> Option<(CarID, Distance)> {
+        let queue = &self.queues[&car.router.head()];
+        let positions = queue.get_car_positions(now, &self.cars, &self.queues);
+        let idx = positions
+            .iter()
+            .position(|(id, _)| *id == car.vehicle.id)
+            .unwrap();
+        if idx == 0 {
+            // The laggy head is partly in another queue, so measuring the gap is hard. Anybody
+            // bound by them is following as closely as possible.
+            return queue.laggy_head.map(|leader| (leader, FOLLOWING_DISTANCE));
+        }
+        let (leader, leader_front) = positions[idx - 1];
+        Some((
+            leader,
+            leader_front - self.cars[&leader].vehicle.length - positions[idx].1,
+        ))
+    }
+
+    fn waiting_at_intersection(
+        &self,
+        car: &Car,
+        map: &Map,
+        intersections: &IntersectionSimState,
+    ) -> SpeedConstraint {
+        let turn = match car.router.maybe_next() {
+            Some(Traversable::Turn(t)) => t,
+            _ => {
+                return SpeedConstraint::FreeFlow;
+            }
+        };
+        let target = &self.queues[&Traversable::Lane(turn.dst)];
+        match intersections.get_delay_cause(
+            AgentID::Car(car.vehicle.id),
+            turn.parent,
+            map,
+            &self.cars,
+            &self.queues,
+        ) {
+            Some(DelayCause::Agent(AgentID::Car(blocker)))
+                if target.cars.contains(&blocker) || target.laggy_head == Some(blocker) =>
+            {
+                SpeedConstraint::NoRoomAhead(blocker)
+            }
+            Some(DelayCause::Agent(agent)) => SpeedConstraint::Yielding(agent),
+            Some(DelayCause::Intersection(i)) => SpeedConstraint::Intersection(i),
+            // The request hasn't been made yet
+            None => SpeedConstraint::Intersection(turn.parent),
+        }
+    }
+
+    /// Re-plans the rest of a car's path from wherever it is now, avoiding congested lanes when
+    /// possible. Returns false if there's nothing to re-plan right now.
+    pub fn reroute_car(&mut self, id: CarID, map: &Map) -> Result<bool> {
+        let congested = self.congested_lanes();
+        match self.cars.get_mut(&id) {
+            Some(car) => car.router.reroute(map, congested),
+            None => Ok(false),
+        }
+    }
+
+    /// Sends a car somewhere else from wherever it is now. Returns false if it can't turn around
+    /// right now.
+    pub fn change_car_goal(&mut self, id: CarID, goal: &DrivingGoal, map: &Map) -> Result<bool> {
+        match self.cars.get_mut(&id) {
+            Some(car) => car.router.change_goal(goal, map),
+            None => Ok(false),
+        }
+    }
+
+    /// Lanes with little room left for more vehicles
+    fn congested_lanes(&self) -> BTreeSet<LaneID> {
+        // Collected into a BTreeSet, so iterating over the HashMap doesn't break determinism
+        self.queues
+            .iter()
+            .filter_map(|(on, queue)| match on {
+                Traversable::Lane(l) if queue.reserved_length >= CONGESTED * queue.geom_len => {
+                    Some(*l)
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
     pub fn get_path(&self, id: CarID) -> Option<&Path> {
         let car = self.cars.get(&id)?;
         Some(car.router.get_path())
@@ -1157,11 +1333,164 @@ impl DrivingSimState {
         }
     }
 
+    // The car is about to start moving again, after being stopped since `since`. Charge it for the
+    // time that the vehicle queued right behind it lost in the meantime. Vehicles further upstream
+    // are blocked by the queue itself, not directly by this car.
+    fn charge_delay_caused(&self, car: &mut Car, now: Time, since: Time) {
+        let queue = &self.queues[&car.router.head()];
+        let follower = match queue.cars.iter().position(|c| *c == car.vehicle.id) {
+            Some(idx) => queue.cars.get(idx + 1),
+            None => {
+                return;
+            }
+        };
+        if let Some(follower) = follower {
+            if let CarState::Queued { blocked_since } = self.cars[follower].state {
+                car.record_delay_caused(now, now - blocked_since.max(since));
+            }
+        }
+    }
+
+    pub fn delay_caused_by(&self, id: CarID) -> Option<Vec<(Time, Duration)>> {
+        Some(self.cars.get(&id)?.delay_caused.clone())
+    }
+
+    /// The vehicles on the map right now that have caused the most delay, worst first.
+    pub fn worst_delay_causers(&self, limit: usize) -> Vec<(CarID, Duration)> {
+        let mut all: Vec<(CarID, Duration)> = self
+            .cars
+            .values()
+            .map(|car| (car.vehicle.id, car.total_delay_caused()))
+            .filter(|(_, delay)| *delay > Duration::ZERO)
+            .collect();
+        all.sort_by_key(|(_, delay)| std::cmp::Reverse(*delay));
+        all.truncate(limit);
+        all
+    }
+
     pub fn debug_queue_lengths(&self, l: LaneID) -> Option<(Distance, Distance)> {
         let queue = self.queues.get(&Traversable::Lane(l))?;
         Some((queue.reserved_length, queue.geom_len))
     }
 
+    pub fn get_queue_spillback(
+        &self,
+        now: Time,
+        i: IntersectionID,
+        map: &Map,
+        max_depth: usize,
+    ) -> Vec<QueueSpillback> {
+        let mut results = Vec::new();
+        for l in &map.get_i(i).incoming_lanes {
+            // Sidewalks don't have queues
+            if !self.queues.contains_key(&Traversable::Lane(*l)) {
+                continue;
+            }
+            let mut spillback = QueueSpillback {
+                approach: *l,
+                segments: Vec::new(),
+                num_vehicles: 0,
+                truncated: false,
+            };
+            self.trace_queue(
+                now,
+                *l,
+                map,
+                max_depth,
+                &mut BTreeSet::new(),
+                &mut spillback,
+            );
+            if spillback.num_vehicles > 0 {
+                results.push(spillback);
+            }
+        }
+        results
+    }
+
+    // Starting from the front of a lane, find the vehicles stopped bumper-to-bumper. If they fill
+    // up the whole lane, keep going upstream through every lane whose first vehicle is waiting to
+    // turn into this one.
+    fn trace_queue(
+        &self,
+        now: Time,
+        l: LaneID,
+        map: &Map,
+        depth_left: usize,
+        visited: &mut BTreeSet<LaneID>,
+        spillback: &mut QueueSpillback,
+    ) {
+        // Gridlock can form a loop
+        if !visited.insert(l) {
+            return;
+        }
+        if depth_left == 0 {
+            spillback.truncated = true;
+            return;
+        }
+
+        let queue = &self.queues[&Traversable::Lane(l)];
+        let mut segment: Option<QueueSegment> = None;
+        for (id, front) in queue.get_car_positions(now, &self.cars, &self.queues) {
+            let car = &self.cars[&id];
+            if !matches!(
+                car.state,
+                CarState::Queued { .. } | CarState::WaitingToAdvance { .. }
+            ) {
+                break;
+            }
+            // Stopped, but not right behind the rest of the queue
+            if let Some(ref seg) = segment {
+                if seg.start - front > FOLLOWING_DISTANCE * 2.0 {
+                    break;
+                }
+            }
+            let back = (front - car.vehicle.length).max(Distance::ZERO);
+            let wait = car.state.time_spent_waiting(now);
+            match segment {
+                Some(ref mut seg) => {
+                    seg.start = back;
+                    seg.max_wait = seg.max_wait.max(wait);
+                }
+                None => {
+                    segment = Some(QueueSegment {
+                        lane: l,
+                        start: back,
+                        end: front,
+                        max_wait: wait,
+                    });
+                }
+            }
+            spillback.num_vehicles += 1;
+        }
+        let segment = match segment {
+            Some(seg) => seg,
+            None => {
+                return;
+            }
+        };
+        // Is there room for anybody else to enter the lane?
+        let full = segment.start < MIN_CAR_LENGTH + FOLLOWING_DISTANCE;
+        spillback.segments.push(segment);
+        if !full {
+            return;
+        }
+
+        for turn in map.get_turns_to_lane(l) {
+            let upstream = match self.queues.get(&Traversable::Lane(turn.id.src)) {
+                Some(q) => q,
+                None => continue,
+            };
+            let waiting_for_turn = upstream
+                .cars
+                .front()
+                .map(|c| self.cars[c].router.maybe_next() == Some(Traversable::Turn(turn.id)))
+                .unwrap_or(false);
+            if waiting_for_turn {
+                self.trace_queue(now, turn.id.src, map, depth_left - 1, visited, spillback);
+            }
+        }
+    }
+
     pub fn get_blocked_by_graph(
         &self,
         now: Time,
@@ -1214,3 +1543,12 @@ impl IndexableKey for CarID {
         self.0
     }
 }
+
+// The speed limit of wherever the car is, unless the vehicle itself can't go that fast
+fn max_speed(car: &Car, map: &Map) -> Speed {
+    let speed = car.router.head().speed_limit(map);
+    if let Some(s) = car.vehicle.max_speed {
+        return speed.min(s);
+    }
+    speed
+}
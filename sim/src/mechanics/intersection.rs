@@ -7,14 +7,15 @@ use abstutil::{
 };
 use geom::{Duration, Time};
 use map_model::{
-    ControlStopSign, ControlTrafficSignal, Intersection, IntersectionID, LaneID, Map, StageType,
-    Traversable, TurnID, TurnPriority, TurnType, UberTurn,
+    ControlStopSign, ControlTrafficSignal, Intersection, IntersectionID, LaneID, Map, RoadID,
+    StageType, Traversable, TurnID, TurnPriority, TurnType, UberTurn,
 };
 
 use crate::mechanics::car::Car;
 use crate::mechanics::Queue;
 use crate::{
-    AgentID, AlertLocation, CarID, Command, DelayCause, Event, Scheduler, SimOptions, Speed,
+    AgentID, AlertLocation, CarID, Command, DelayCause, Event, MergeManeuver, Scheduler,
+    SimOptions, Speed,
 };
 
 const WAIT_AT_STOP_SIGN: Duration = Duration::const_seconds(0.5);
@@ -47,6 +48,11 @@ pub(crate) struct IntersectionSimState {
     total_repeat_requests: usize,
     not_allowed_requests: usize,
     blocked_by_someone_requests: usize,
+
+    // Lane changes and yields are only checked for this road, if any. It's only watched
+    // interactively.
+    #[serde(skip_serializing, skip_deserializing)]
+    watched_merges: Option<RoadID>,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -103,6 +109,8 @@ impl IntersectionSimState {
             total_repeat_requests: 0,
             not_allowed_requests: 0,
             blocked_by_someone_requests: 0,
+
+            watched_merges: None,
         };
         if sim.disable_turn_conflicts {
             sim.use_freeform_policy_everywhere = true;
@@ -162,6 +170,14 @@ impl IntersectionSimState {
         }
     }
 
+    pub fn watch_merges(&mut self, road: Option<RoadID>) {
+        self.watched_merges = road;
+    }
+
+    pub fn watched_merges(&self) -> Option<RoadID> {
+        self.watched_merges
+    }
+
     /// For deleting cars
     pub fn cancel_request(&mut self, agent: AgentID, turn: TurnID) {
         let state = self.state.get_mut(&turn.parent).unwrap();
@@ -315,7 +331,8 @@ impl IntersectionSimState {
         let duration: Duration;
         // Switch to a new stage?
         assert_eq!(now, signal_state.stage_ends_at);
-        let old_stage = &signal.stages[signal_state.current_stage];
+        let old_idx = signal_state.current_stage;
+        let old_stage = &signal.stages[old_idx];
         match old_stage.stage_type {
             StageType::Fixed(_) => {
                 duration = advance(signal_state, signal, !ped_waiting);
@@ -362,6 +379,12 @@ impl IntersectionSimState {
             }
         }
 
+        if signal_state.current_stage != old_idx {
+            self.events.push(Event::TrafficSignalStageChanged(
+                id,
+                signal_state.current_stage,
+            ));
+        }
         signal_state.stage_ends_at = now + duration;
         scheduler.push(signal_state.stage_ends_at, Command::UpdateIntersection(id));
         self.wakeup_waiting(now, id, scheduler, map);
@@ -404,6 +427,12 @@ impl IntersectionSimState {
         if repeat_request {
             self.total_repeat_requests += 1;
         }
+        let watched_merge = match (agent, self.watched_merges) {
+            (AgentID::Car(_), Some(r)) => {
+                map.get_l(turn.src).parent == r || map.get_l(turn.dst).parent == r
+            }
+            _ => false,
+        };
 
         let shared_sidewalk_corner =
             map.get_t(req.turn).turn_type == TurnType::SharedSidewalkCorner;
@@ -447,6 +476,17 @@ impl IntersectionSimState {
         if !allowed {
             if repeat_request {
                 self.not_allowed_requests += 1;
+            } else if watched_merge {
+                let follower = maybe_cars_and_queues
+                    .as_ref()
+                    .map(|(_, _, queues)| queues[&Traversable::Lane(turn.src)].cars.len() > 1)
+                    .unwrap_or(false);
+                self.events.push(Event::Merge(
+                    agent.as_car(),
+                    turn,
+                    MergeManeuver::Yield,
+                    follower,
+                ));
             }
             // remove the reservation if we're about to start a UT and can't move
             if self.handle_uber_turns {
@@ -547,6 +587,21 @@ impl IntersectionSimState {
                 ));
             }
         }
+        if watched_merge && map.get_t(turn).penalty(map).1 > 0 {
+            // Somebody else headed into the same lane from a different one has to let this
+            // vehicle in
+            let cut_off = state
+                .accepted
+                .iter()
+                .chain(state.waiting.keys())
+                .any(|r| r.agent != agent && r.turn.dst == turn.dst && r.turn.src != turn.src);
+            self.events.push(Event::Merge(
+                agent.as_car(),
+                turn,
+                MergeManeuver::LaneChange,
+                cut_off,
+            ));
+        }
         state.accepted.insert(req);
         if self.break_turn_conflict_cycles {
             if let AgentID::Car(car) = agent {
@@ -739,34 +794,61 @@ impl IntersectionSimState {
         // This also assumes default values for handle_uber_turns, disable_turn_conflicts, etc!
         for state in self.state.values() {
             for (req, started_at) in &state.waiting {
-                let turn = map.get_t(req.turn);
-                // In the absence of other explanations, the agent must be pausing at a stop sign
-                // or before making an unprotected movement, aka, in the middle of
-                // WAIT_AT_STOP_SIGN or WAIT_BEFORE_YIELD_AT_TRAFFIC_SIGNAL. Or they're waiting for
-                // a signal to change.
-                let mut cause = DelayCause::Intersection(state.id);
-                if let Some(other) = state.accepted.iter().find(|other| {
-                    turn.conflicts_with(map.get_t(other.turn)) || turn.id == other.turn
-                }) {
-                    cause = DelayCause::Agent(other.agent);
-                } else if let AgentID::Car(car) = req.agent {
-                    let queue = &queues[&Traversable::Lane(req.turn.dst)];
-                    let car = cars.get(&car).unwrap();
-                    if !queue.room_for_car(car) {
-                        // TODO Or it's reserved due to an uber turn or something
-                        let blocker = queue.cars.back().cloned().or(queue.laggy_head).unwrap();
-                        cause = DelayCause::Agent(AgentID::Car(blocker));
-                    } else if let Some(ut) = car.router.get_path().about_to_start_ut() {
-                        if let Some(blocker) = self.check_for_conflicts_before_uber_turn(ut, map) {
-                            cause = DelayCause::Agent(blocker);
-                        }
-                    }
-                }
+                let cause = self.delay_cause(state, req, map, cars, queues);
                 graph.insert(req.agent, (now - *started_at, cause));
             }
         }
     }
 
+    /// Why is an agent waiting to start a turn at this intersection? None if they aren't.
+    pub fn get_delay_cause(
+        &self,
+        agent: AgentID,
+        i: IntersectionID,
+        map: &Map,
+        cars: &FixedMap<CarID, Car>,
+        queues: &HashMap<Traversable, Queue>,
+    ) -> Option<DelayCause> {
+        let state = &self.state[&i];
+        let req = state.waiting.keys().find(|req| req.agent == agent)?;
+        Some(self.delay_cause(state, req, map, cars, queues))
+    }
+
+    fn delay_cause(
+        &self,
+        state: &State,
+        req: &Request,
+        map: &Map,
+        cars: &FixedMap<CarID, Car>,
+        queues: &HashMap<Traversable, Queue>,
+    ) -> DelayCause {
+        let turn = map.get_t(req.turn);
+        // In the absence of other explanations, the agent must be pausing at a stop sign or before
+        // making an unprotected movement, aka, in the middle of WAIT_AT_STOP_SIGN or
+        // WAIT_BEFORE_YIELD_AT_TRAFFIC_SIGNAL. Or they're waiting for a signal to change.
+        let mut cause = DelayCause::Intersection(state.id);
+        if let Some(other) = state
+            .accepted
+            .iter()
+            .find(|other| turn.conflicts_with(map.get_t(other.turn)) || turn.id == other.turn)
+        {
+            cause = DelayCause::Agent(other.agent);
+        } else if let AgentID::Car(car) = req.agent {
+            let queue = &queues[&Traversable::Lane(req.turn.dst)];
+            let car = cars.get(&car).unwrap();
+            if !queue.room_for_car(car) {
+                // TODO Or it's reserved due to an uber turn or something
+                let blocker = queue.cars.back().cloned().or(queue.laggy_head).unwrap();
+                cause = DelayCause::Agent(AgentID::Car(blocker));
+            } else if let Some(ut) = car.router.get_path().about_to_start_ut() {
+                if let Some(blocker) = self.check_for_conflicts_before_uber_turn(ut, map) {
+                    cause = DelayCause::Agent(blocker);
+                }
+            }
+        }
+        cause
+    }
+
     /// See if any agent is currently performing a turn that conflicts with an uber-turn. Doesn't
     /// check for room on the queues.
     fn check_for_conflicts_before_uber_turn(&self, ut: &UberTurn, map: &Map) -> Option<AgentID> {
@@ -920,6 +1002,10 @@ impl IntersectionSimState {
                                     AlertLocation::Intersection(req.turn.parent),
                                     format!("Turn conflict cycle involving {:?}", cycle),
                                 ));
+                                self.events.push(Event::TurnConflictCycle(
+                                    req.turn.parent,
+                                    cycle.into_iter().collect(),
+                                ));
                                 cycle_detected = true;
                             }
                         }
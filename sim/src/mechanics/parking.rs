@@ -75,6 +75,8 @@ pub trait ParkingSim {
     fn collect_events(&mut self) -> Vec<Event>;
     fn all_parked_car_positions(&self, map: &Map) -> Vec<(Position, PersonID)>;
     fn bldg_to_parked_cars(&self, b: BuildingID) -> Vec<CarID>;
+    /// Cars on their way to park in a building, which have already claimed a spot there.
+    fn bldg_to_reserved_cars(&self, b: BuildingID) -> Vec<CarID>;
 }
 
 #[enum_dispatch]
@@ -633,6 +635,17 @@ impl ParkingSim for NormalParkingSimState {
         }
         cars
     }
+
+    fn bldg_to_reserved_cars(&self, b: BuildingID) -> Vec<CarID> {
+        let mut cars = Vec::new();
+        for idx in 0..self.num_spots_per_offstreet.get(&b).cloned().unwrap_or(0) {
+            let spot = ParkingSpot::Offstreet(b, idx);
+            if let Some(car) = self.reserved_spots.get(&spot) {
+                cars.push(*car);
+            }
+        }
+        cars
+    }
 }
 
 #[derive(Serialize, Deserialize, Clone)]
@@ -1028,4 +1041,17 @@ impl ParkingSim for InfiniteParkingSimState {
         }
         cars
     }
+
+    fn bldg_to_reserved_cars(&self, b: BuildingID) -> Vec<CarID> {
+        // TODO Same inefficiency as bldg_to_parked_cars
+        let mut cars = Vec::new();
+        for (spot, car) in &self.reserved_spots {
+            if let ParkingSpot::Offstreet(bldg, _) = spot {
+                if b == *bldg {
+                    cars.push(*car);
+                }
+            }
+        }
+        cars
+    }
 }
@@ -1,20 +1,22 @@
 use std::collections::{BTreeMap, BTreeSet};
 
+use anyhow::Result;
 use serde::{Deserialize, Serialize};
 
 use abstutil::{deserialize_multimap, serialize_multimap, FixedMap, IndexableKey, MultiMap};
 use geom::{Distance, Duration, Line, PolyLine, Speed, Time};
 use map_model::{
-    BuildingID, BusRouteID, DrivingSide, Map, ParkingLotID, Path, PathStep, Traversable,
-    SIDEWALK_THICKNESS,
+    BuildingID, BusRouteID, DrivingSide, Map, ParkingLotID, Path, PathConstraints, PathRequest,
+    PathStep, Position, Traversable, SIDEWALK_THICKNESS,
 };
 
 use crate::sim::Ctx;
 use crate::{
-    AgentID, AgentProperties, Command, CommutersVehiclesCounts, CreatePedestrian, DistanceInterval,
-    DrawPedCrowdInput, DrawPedestrianInput, Event, IntersectionSimState, ParkedCar, ParkingSpot,
-    PedCrowdLocation, PedestrianID, PersonID, Scheduler, SidewalkPOI, SidewalkSpot, TimeInterval,
-    TransitSimState, TripID, TripManager, UnzoomedAgent,
+    AgentID, AgentProperties, AgentSpeed, Command, CommutersVehiclesCounts, CreatePedestrian,
+    DistanceInterval, DrawPedCrowdInput, DrawPedestrianInput, Event, IntersectionSimState,
+    ParkedCar, ParkingSpot, PedCrowdLocation, PedestrianID, PersonID, Scheduler, SidewalkPOI,
+    SidewalkSpot, SpeedConstraint, TimeInterval, TransitSimState, TripID, TripManager,
+    UnzoomedAgent,
 };
 
 const TIME_TO_START_BIKING: Duration = Duration::const_seconds(30.0);
@@ -353,6 +355,64 @@ impl WalkingSimState {
         }
     }
 
+    /// Sends a pedestrian somewhere else from wherever they are now. Returns false if they can't
+    /// turn around right now, like in the middle of a crosswalk or a building's driveway.
+    pub fn change_ped_goal(
+        &mut self,
+        id: PedestrianID,
+        goal: SidewalkSpot,
+        now: Time,
+        ctx: &mut Ctx,
+    ) -> Result<bool> {
+        let ped = match self.peds.get_mut(&id) {
+            Some(ped) => ped,
+            None => {
+                return Ok(false);
+            }
+        };
+        // Pedestrians can turn around anywhere along a sidewalk, so unlike vehicles, none of the
+        // old path needs to be kept.
+        let lane = match (&ped.state, ped.path.current_step()) {
+            (PedState::Crossing(_, _), PathStep::Lane(l))
+            | (PedState::Crossing(_, _), PathStep::ContraflowLane(l)) => l,
+            _ => {
+                return Ok(false);
+            }
+        };
+        let dist = ped.get_dist_along(now, ctx.map);
+        let path = ctx.map.pathfind(PathRequest {
+            start: Position::new(lane, dist),
+            end: goal.sidewalk_pos,
+            constraints: PathConstraints::Pedestrian,
+        })?;
+        // peds_per_traversable doesn't need to change
+        if path.current_step().as_traversable() != Traversable::Lane(lane) {
+            bail!("the new path for {} doesn't start on {}", id, lane);
+        }
+
+        if let Some(PathStep::Turn(t)) = ped.path.maybe_next_step() {
+            ctx.intersections.cancel_request(AgentID::Pedestrian(id), t);
+        }
+        ped.path = path;
+        ped.goal = goal;
+        ped.state = ped.crossing_state(dist, now, ctx.map);
+        ctx.scheduler
+            .update(ped.state.get_end_time(), Command::UpdatePed(id));
+        Ok(true)
+    }
+
+    /// Re-plans the rest of a pedestrian's path to the same place, using the current state of the
+    /// map. Returns false if they can't turn around right now.
+    pub fn reroute_ped(&mut self, id: PedestrianID, now: Time, ctx: &mut Ctx) -> Result<bool> {
+        let goal = match self.peds.get(&id) {
+            Some(ped) => ped.goal.clone(),
+            None => {
+                return Ok(false);
+            }
+        };
+        self.change_ped_goal(id, goal, now, ctx)
+    }
+
     pub fn debug_ped(&self, id: PedestrianID) {
         if let Some(ped) = self.peds.get(&id) {
             println!("{}", abstutil::to_json(ped));
@@ -398,6 +458,30 @@ impl WalkingSimState {
         }
     }
 
+    pub fn agent_speed(&self, id: PedestrianID) -> Option<AgentSpeed> {
+        let p = self.peds.get(&id)?;
+        let (speed, constraint) = match p.state {
+            PedState::Crossing(_, _)
+            | PedState::LeavingBuilding(_, _)
+            | PedState::EnteringBuilding(_, _)
+            | PedState::LeavingParkingLot(_, _)
+            | PedState::EnteringParkingLot(_, _) => (p.speed, SpeedConstraint::FreeFlow),
+            PedState::WaitingToTurn(_, _) => (
+                Speed::ZERO,
+                SpeedConstraint::Intersection(p.path.next_step().as_turn().parent),
+            ),
+            PedState::StartingToBike(_, _, _) | PedState::FinishingBiking(_, _, _) => {
+                (Speed::ZERO, SpeedConstraint::Maneuvering)
+            }
+            PedState::WaitingForBus(_, _) => (Speed::ZERO, SpeedConstraint::AtTransitStop),
+        };
+        Some(AgentSpeed {
+            speed,
+            max_speed: p.speed,
+            constraint,
+        })
+    }
+
     pub fn trace_route(&self, now: Time, id: PedestrianID, map: &Map) -> Option<PolyLine> {
         let p = self.peds.get(&id)?;
         let body_radius = SIDEWALK_THICKNESS / 4.0;
@@ -0,0 +1,66 @@
+use serde::{Deserialize, Serialize};
+
+use geom::Time;
+use map_model::{RoadID, TurnID};
+
+use crate::{CarID, Event};
+
+/// How a vehicle merged into or out of a watched road.
+///
+/// Vehicles don't change lanes partway along a road; they pick a different lane while turning
+/// onto the next one. So a lane change is a turn shifting over at least one lane, and a yield is a
+/// vehicle at the front of its lane not being allowed to start its turn right away.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum MergeManeuver {
+    LaneChange,
+    Yield,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct MergeEvent {
+    pub time: Time,
+    pub car: CarID,
+    pub turn: TurnID,
+    pub maneuver: MergeManeuver,
+    /// Another vehicle had to brake because of this. For a lane change, somebody else is headed
+    /// into the same lane from another one. For a yield, somebody is queued behind.
+    pub forced_brake: bool,
+}
+
+/// Collects lane changes and yields on one road, until somebody takes them. Nothing is recorded
+/// for other roads, and stopping the watch stops the intersections from even checking.
+#[derive(Clone)]
+pub struct MergeWatch {
+    road: RoadID,
+    events: Vec<MergeEvent>,
+}
+
+impl MergeWatch {
+    pub(crate) fn new(road: RoadID) -> MergeWatch {
+        MergeWatch {
+            road,
+            events: Vec::new(),
+        }
+    }
+
+    pub(crate) fn event(&mut self, ev: &Event, time: Time) {
+        if let Event::Merge(car, turn, maneuver, forced_brake) = ev {
+            self.events.push(MergeEvent {
+                time,
+                car: *car,
+                turn: *turn,
+                maneuver: *maneuver,
+                forced_brake: *forced_brake,
+            });
+        }
+    }
+
+    pub fn road(&self) -> RoadID {
+        self.road
+    }
+
+    /// Everything recorded since the last call, oldest first
+    pub fn take_events(&mut self) -> Vec<MergeEvent> {
+        std::mem::replace(&mut self.events, Vec::new())
+    }
+}
@@ -1,8 +1,9 @@
 //! For vehicles only, not pedestrians. Follows a Path from map_model, but can opportunistically
 //! lane-change to avoid a slow lane, can can handle re-planning to look for available parking.
 
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap};
 
+use anyhow::Result;
 use serde::{Deserialize, Serialize};
 
 use geom::Distance;
@@ -13,8 +14,8 @@ use map_model::{
 
 use crate::mechanics::Queue;
 use crate::{
-    AlertLocation, CarID, Event, ParkingSim, ParkingSimState, ParkingSpot, PersonID, SidewalkSpot,
-    TripID, TripPhaseType, Vehicle, VehicleType,
+    AlertLocation, CarID, DrivingGoal, Event, ParkingSim, ParkingSimState, ParkingSpot, PersonID,
+    SidewalkSpot, TripID, TripPhaseType, Vehicle, VehicleType,
 };
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
@@ -298,6 +299,7 @@ impl Router {
                                 ));
                             }
                         } else {
+                            events.push(Event::ParkingSearchFailed(vehicle.id, current_lane));
                             if let Some((_, p)) = trip_and_person {
                                 events.push(Event::Alert(
                                     AlertLocation::Person(p),
@@ -468,6 +470,113 @@ impl Router {
         }
     }
 
+    /// Re-plans the rest of the path using the current state of the map, steering around
+    /// `congested` lanes when there's another way. Returns false if there's nothing to re-plan
+    /// right now, and an error if the destination can't be reached anymore.
+    pub fn reroute(&mut self, map: &Map, congested: BTreeSet<LaneID>) -> Result<bool> {
+        match self.goal {
+            // After looking for parking, the path no longer ends where it was requested to
+            Goal::ParkNearBuilding {
+                started_looking, ..
+            } if started_looking => {
+                return Ok(false);
+            }
+            Goal::FollowBusRoute { .. } => {
+                return Ok(false);
+            }
+            _ => {}
+        }
+        let end = self.path.get_req().end;
+        match self.replan(end, congested, map)? {
+            Some(path) => {
+                self.path = path;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    /// Heads somewhere else, keeping the steps the vehicle is already committed to. Returns false
+    /// if the vehicle can't turn around right now, like when it's already parking.
+    pub fn change_goal(&mut self, goal: &DrivingGoal, map: &Map) -> Result<bool> {
+        match self.goal {
+            Goal::ParkNearBuilding {
+                started_looking, ..
+            } if !started_looking => {}
+            Goal::BikeThenStop { .. } => {}
+            _ => {
+                return Ok(false);
+            }
+        }
+        let end = goal
+            .goal_pos(self.path.get_req().constraints, map)
+            .ok_or_else(|| anyhow!("{:?} can't be reached by {}", goal, self.owner))?;
+        match self.replan(end, BTreeSet::new(), map)? {
+            Some(path) => {
+                *self = goal.make_router(self.owner, path, map);
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    /// The vehicle might already be committed to its current step, the next turn, and the lane
+    /// after that, so those are kept, and a new path to `end` is found from there. Returns None if
+    /// the vehicle is in or about to start an uber-turn, or there's nothing left after the kept
+    /// steps.
+    fn replan(
+        &self,
+        end: Position,
+        mut avoid: BTreeSet<LaneID>,
+        map: &Map,
+    ) -> Result<Option<Path>> {
+        if self.path.currently_inside_ut().is_some() || self.path.about_to_start_ut().is_some() {
+            return Ok(None);
+        }
+
+        let steps = self.path.get_steps();
+        let keep = match steps
+            .iter()
+            .skip(1)
+            .position(|step| matches!(step, PathStep::Lane(_)))
+        {
+            Some(idx) => idx + 2,
+            None => {
+                return Ok(None);
+            }
+        };
+        if keep == steps.len() {
+            return Ok(None);
+        }
+        let from = steps[keep - 1].as_lane();
+        let req = PathRequest {
+            start: Position::start(from),
+            end,
+            constraints: self.path.get_req().constraints,
+        };
+
+        // Don't avoid the lanes the vehicle is stuck with anyway
+        for step in steps.iter().take(keep) {
+            if let PathStep::Lane(l) = step {
+                avoid.remove(l);
+            }
+        }
+        avoid.remove(&end.lane());
+        let avoiding = if avoid.is_empty() || req.constraints != PathConstraints::Car {
+            None
+        } else {
+            map.pathfind_avoiding_lanes(req.clone(), avoid)
+        };
+        let replacement = match avoiding {
+            Some(path) => path,
+            None => map.pathfind(req)?,
+        };
+
+        let mut path = self.path.clone();
+        path.replace_remaining(keep, replacement, map)?;
+        Ok(Some(path))
+    }
+
     pub fn is_parking(&self) -> bool {
         match self.goal {
             Goal::ParkNearBuilding {
@@ -0,0 +1,142 @@
+//! Fingerprints of the simulation state, for checking that two runs with the same inputs stay
+//! identical.
+
+use std::collections::BTreeMap;
+use std::hash::{Hash, Hasher};
+
+use abstutil::Fnv;
+use geom::Time;
+use map_model::Map;
+
+use crate::{ParkingSim, Sim, TripResult};
+
+/// Summarizes one moment of a simulation. Each category of state gets a count and a hash over
+/// everything in it, visited in a fixed order. Floating point values are hashed by their exact
+/// bits, so even the tiniest difference shows up.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SimDigest {
+    pub time: Time,
+    /// Category name -> (number of things, hash of all of them)
+    pub categories: BTreeMap<&'static str, (usize, u64)>,
+}
+
+impl SimDigest {
+    /// Describes each category that differs from another digest, using the count when that
+    /// differs.
+    pub fn differences(&self, other: &SimDigest) -> Vec<String> {
+        let mut result = Vec::new();
+        for (name, (count1, hash1)) in &self.categories {
+            let (count2, hash2) = other.categories[name];
+            if *count1 != count2 {
+                result.push(format!("{}: {} vs {}", name, count1, count2));
+            } else if *hash1 != hash2 {
+                result.push(format!(
+                    "{}: {} each, but their contents differ",
+                    name, count1
+                ));
+            }
+        }
+        result
+    }
+}
+
+impl Sim {
+    /// Fingerprints the current agent positions, trip states, and analytics counters. This is
+    /// slow, so only call it occasionally.
+    pub fn digest(&self, map: &Map) -> SimDigest {
+        let mut categories = BTreeMap::new();
+
+        let mut agents = self.get_unzoomed_agents(map);
+        agents.sort_by_key(|a| a.id);
+        categories.insert(
+            "agent positions",
+            digest(
+                agents
+                    .iter()
+                    .map(|a| (a.id, a.pos.x().to_bits(), a.pos.y().to_bits(), a.parking)),
+            ),
+        );
+
+        let mut parked = Vec::new();
+        for spot in self.parking.get_all_parking_spots().0 {
+            if let Some(car) = self.parking.get_car_at_spot(spot) {
+                parked.push((
+                    car.vehicle.id,
+                    spot,
+                    car.parked_since.inner_seconds().to_bits(),
+                ));
+            }
+        }
+        parked.sort();
+        categories.insert("parked cars", digest(parked.into_iter()));
+
+        // all_trip_info is already sorted by trip
+        categories.insert(
+            "trip states",
+            digest(self.all_trip_info().into_iter().map(|(id, _)| {
+                let state = match self.trip_to_agent(id) {
+                    TripResult::Ok(agent) => (0, Some(agent)),
+                    TripResult::ModeChange => (1, None),
+                    TripResult::TripDone => (2, None),
+                    TripResult::TripDoesntExist => (3, None),
+                    TripResult::TripNotStarted => (4, None),
+                    TripResult::TripCancelled => (5, None),
+                };
+                (id, state)
+            })),
+        );
+
+        let analytics = &self.analytics;
+        categories.insert(
+            "started trips",
+            digest(
+                analytics
+                    .started_trips
+                    .iter()
+                    .map(|(id, t)| (*id, t.inner_seconds().to_bits())),
+            ),
+        );
+        categories.insert(
+            "finished trips",
+            digest(analytics.finished_trips.iter().map(|(t, id, mode, dt)| {
+                (
+                    t.inner_seconds().to_bits(),
+                    *id,
+                    *mode as u8,
+                    dt.map(|dt| dt.inner_seconds().to_bits()),
+                )
+            })),
+        );
+        categories.insert(
+            "road throughput",
+            digest(analytics.road_thruput.counts.iter()),
+        );
+        categories.insert(
+            "intersection throughput",
+            digest(analytics.intersection_thruput.counts.iter()),
+        );
+        categories.insert(
+            "bus arrivals",
+            digest(
+                analytics.bus_arrivals.iter().map(|(t, car, route, stop)| {
+                    (t.inner_seconds().to_bits(), *car, *route, *stop)
+                }),
+            ),
+        );
+
+        SimDigest {
+            time: self.time,
+            categories,
+        }
+    }
+}
+
+fn digest<T: Hash, I: Iterator<Item = T>>(items: I) -> (usize, u64) {
+    let mut hasher = Fnv::new();
+    let mut count = 0;
+    for item in items {
+        item.hash(&mut hasher);
+        count += 1;
+    }
+    (count, hasher.finish())
+}
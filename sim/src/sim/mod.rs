@@ -14,19 +14,24 @@ use abstutil::{prettyprint_usize, serialized_size_bytes, CmdArgs, Timer};
 use geom::{Distance, Duration, Speed, Time};
 use map_model::{
     BuildingID, BusRoute, IntersectionID, LaneID, Map, ParkingLotID, Path, PathConstraints,
-    PathRequest, Position, Traversable,
+    PathRequest, Position, RoadID, Traversable,
 };
 
-pub use self::queries::{AgentProperties, DelayCause};
+pub use self::digest::SimDigest;
+pub use self::queries::{
+    AgentProperties, AgentSpeed, DelayCause, Gridlock, QueueSegment, QueueSpillback, RouteVehicle,
+    SpeedConstraint,
+};
 use crate::{
-    AgentID, AlertLocation, Analytics, CapSimState, CarID, Command, CreateCar, DrivingSimState,
-    Event, IntersectionSimState, OrigPersonID, PandemicModel, ParkedCar, ParkingSim,
-    ParkingSimState, ParkingSpot, Person, PersonID, Router, Scheduler, SidewalkPOI, SidewalkSpot,
-    StartTripArgs, TrafficRecorder, TransitSimState, TripID, TripInfo, TripManager, TripPhaseType,
-    Vehicle, VehicleSpec, VehicleType, WalkingSimState, BUS_LENGTH, LIGHT_RAIL_LENGTH,
-    MIN_CAR_LENGTH,
+    AgentID, AlertLocation, Analytics, CapSimState, CarID, Command, CreateCar, DrivingGoal,
+    DrivingSimState, Event, EventLog, IntersectionSimState, MergeWatch, OrigPersonID,
+    PandemicModel, ParkedCar, ParkingSim, ParkingSimState, ParkingSpot, Person, PersonID, Router,
+    Scheduler, SidewalkPOI, SidewalkSpot, StartTripArgs, TrafficRecorder, TransitSimState,
+    TripEndpoint, TripID, TripInfo, TripManager, TripPhaseType, TripResult, Vehicle, VehicleSpec,
+    VehicleType, WalkingSimState, BUS_LENGTH, LIGHT_RAIL_LENGTH, MIN_CAR_LENGTH,
 };
 
+mod digest;
 mod queries;
 
 // TODO Do something else.
@@ -59,6 +64,11 @@ pub struct Sim {
     // This is created interactively, and there's no reason to preserve one for savestates.
     #[serde(skip_serializing, skip_deserializing)]
     recorder: Option<TrafficRecorder>,
+    // Only used while debugging interactively
+    #[serde(skip_serializing, skip_deserializing)]
+    event_log: Option<EventLog>,
+    #[serde(skip_serializing, skip_deserializing)]
+    merge_watch: Option<MergeWatch>,
 
     #[serde(skip_serializing, skip_deserializing)]
     alerts: AlertHandler,
@@ -226,6 +236,8 @@ impl Sim {
 
             analytics: Analytics::new(!opts.skip_analytics),
             recorder: None,
+            event_log: None,
+            merge_watch: None,
         }
     }
 
@@ -277,6 +289,10 @@ impl Sim {
         self.parking.bldg_to_parked_cars(b)
     }
 
+    pub fn bldg_to_reserved_cars(&self, b: BuildingID) -> Vec<CarID> {
+        self.parking.bldg_to_reserved_cars(b)
+    }
+
     pub fn walking_path_to_nearest_parking_spot(&self, map: &Map, b: BuildingID) -> Option<Path> {
         let vehicle = Vehicle {
             id: CarID(0, VehicleType::Car),
@@ -372,6 +388,10 @@ impl Sim {
     pub fn set_name(&mut self, name: String) {
         self.run_name = name;
     }
+
+    pub fn get_run_name(&self) -> &String {
+        &self.run_name
+    }
 }
 
 // Running
@@ -649,6 +669,12 @@ impl Sim {
             if let Some(ref mut r) = self.recorder {
                 r.handle_event(self.time, &ev, map, &self.driving, &self.trips);
             }
+            if let Some(ref mut log) = self.event_log {
+                log.event(&ev, self.time);
+            }
+            if let Some(ref mut watch) = self.merge_watch {
+                watch.event(&ev, self.time);
+            }
 
             self.analytics.event(ev, self.time, map);
         }
@@ -833,17 +859,48 @@ impl Sim {
             .handle_live_edited_traffic_signals(self.time, map, &mut self.scheduler)
     }
 
-    /// Respond to arbitrary map edits without resetting the simulation. Returns the number of
-    /// (trips cancelled, parked cars displaced).
+    /// Respond to arbitrary map edits without resetting the simulation. Every trip crossing
+    /// something that changed is cancelled. Returns the number of (trips cancelled, parked cars
+    /// displaced).
     pub fn handle_live_edits(&mut self, map: &Map) -> (usize, usize) {
+        let (_, num_trips_cancelled, num_parked_cars) = self.update_for_live_edits(map, false);
+        (num_trips_cancelled, num_parked_cars)
+    }
+
+    /// Like `handle_live_edits`, but trips whose remaining path crosses something that changed are
+    /// first rerouted from wherever they are now. Trips are only cancelled when that fails, when
+    /// they're already physically on something that changed, or when their parking spot is gone.
+    /// Returns the number of (trips rerouted, trips cancelled, parked cars displaced).
+    pub fn handle_live_edits_by_rerouting(&mut self, map: &Map) -> (usize, usize, usize) {
+        self.update_for_live_edits(map, true)
+    }
+
+    fn update_for_live_edits(&mut self, map: &Map, reroute: bool) -> (usize, usize, usize) {
         self.edits_name = map.get_edits().edits_name.clone();
 
-        let (affected, num_parked_cars) = self.find_trips_affected_by_live_edits(map);
+        let (edited_lanes, _) = map.get_edits().changed_lanes(map);
+        let mut closed_intersections = HashSet::new();
+        for i in map.get_edits().original_intersections.keys() {
+            if map.get_i(*i).is_closed() {
+                closed_intersections.insert(*i);
+            }
+        }
+
+        let (crossing, mut affected, num_parked_cars) =
+            self.find_trips_affected_by_live_edits(map, &edited_lanes, &closed_intersections);
+        let mut num_trips_rerouted = 0;
+        for (agent, trip) in crossing {
+            if reroute
+                && self.reroute_around_live_edits(agent, map, &edited_lanes, &closed_intersections)
+            {
+                num_trips_rerouted += 1;
+            } else {
+                affected.insert((agent, trip));
+            }
+        }
         let num_trips_cancelled = affected.len();
         let affected_agents: BTreeSet<AgentID> = affected.iter().map(|(a, _)| *a).collect();
 
-        // V1: Just cancel every trip crossing an affected area.
-        // (V2 is probably rerouting everyone, only cancelling when that fails)
         // TODO If we delete a bus, deal with all its passengers
         let mut ctx = Ctx {
             parking: &mut self.parking,
@@ -857,6 +914,7 @@ impl Sim {
             match agent {
                 AgentID::Car(car) => {
                     let vehicle = self.driving.delete_car(car, self.time, &mut ctx);
+                    self.analytics.vehicle_removed(car, self.time);
                     // TODO Plumb more info about the reason
                     self.trips.cancel_trip(
                         self.time,
@@ -886,52 +944,40 @@ impl Sim {
         self.driving.handle_live_edits(map);
         self.intersections.handle_live_edits(map);
 
-        (num_trips_cancelled, num_parked_cars)
+        (num_trips_rerouted, num_trips_cancelled, num_parked_cars)
     }
 
-    /// Returns (trips affected, number of parked cars displaced)
+    /// Returns (trips whose remaining path crosses something that changed, trips that can't
+    /// continue at all, number of parked cars displaced). The two sets of trips don't overlap.
     fn find_trips_affected_by_live_edits(
         &mut self,
         map: &Map,
-    ) -> (BTreeSet<(AgentID, TripID)>, usize) {
+        edited_lanes: &BTreeSet<LaneID>,
+        closed_intersections: &HashSet<IntersectionID>,
+    ) -> (
+        BTreeSet<(AgentID, TripID)>,
+        BTreeSet<(AgentID, TripID)>,
+        usize,
+    ) {
+        let mut crossing: BTreeSet<(AgentID, TripID)> = BTreeSet::new();
         let mut affected: BTreeSet<(AgentID, TripID)> = BTreeSet::new();
 
         // TODO Handle changes to access restrictions
 
-        {
-            // Find every active trip whose path crosses a modified lane or intersection
-            let (edited_lanes, _) = map.get_edits().changed_lanes(map);
-            let mut closed_intersections = HashSet::new();
-            for i in map.get_edits().original_intersections.keys() {
-                if map.get_i(*i).is_closed() {
-                    closed_intersections.insert(*i);
+        // Find every active trip whose path crosses a modified lane or intersection
+        for (a, trip) in self.trips.active_agents_and_trips() {
+            if let Some(path) = self.get_path(*a) {
+                if path_crosses_live_edits(path, edited_lanes, closed_intersections) {
+                    crossing.insert((*a, *trip));
                 }
             }
-            for (a, trip) in self.trips.active_agents_and_trips() {
-                if let Some(path) = self.get_path(*a) {
-                    if path
-                        .get_steps()
-                        .iter()
-                        .any(|step| match step.as_traversable() {
-                            Traversable::Lane(l) => edited_lanes.contains(&l),
-                            Traversable::Turn(t) => {
-                                closed_intersections.contains(&t.parent)
-                                    || edited_lanes.contains(&t.src)
-                                    || edited_lanes.contains(&t.dst)
-                            }
-                        })
-                    {
-                        affected.insert((*a, *trip));
-                    }
-                }
-            }
-
-            affected.extend(
-                self.driving
-                    .find_vehicles_affected_by_live_edits(&closed_intersections, &edited_lanes),
-            );
         }
 
+        affected.extend(
+            self.driving
+                .find_vehicles_affected_by_live_edits(closed_intersections, edited_lanes),
+        );
+
         let num_evicted = {
             let (evicted_cars, cars_parking_in_the_void) = self.parking.handle_live_edits(map);
             let num_evicted = evicted_cars.len();
@@ -951,8 +997,58 @@ impl Sim {
             num_evicted
         };
 
-        (affected, num_evicted)
+        crossing.retain(|x| !affected.contains(x));
+        (crossing, affected, num_evicted)
     }
+
+    /// Re-plans an agent's path around live edits. Returns false if that's not possible right now,
+    /// or if the steps the agent is already committed to still cross something that changed.
+    fn reroute_around_live_edits(
+        &mut self,
+        agent: AgentID,
+        map: &Map,
+        edited_lanes: &BTreeSet<LaneID>,
+        closed_intersections: &HashSet<IntersectionID>,
+    ) -> bool {
+        let rerouted = match agent {
+            AgentID::Car(car) => self.driving.reroute_car(car, map),
+            AgentID::Pedestrian(ped) => {
+                let mut ctx = Ctx {
+                    parking: &mut self.parking,
+                    intersections: &mut self.intersections,
+                    cap: &mut self.cap,
+                    scheduler: &mut self.scheduler,
+                    map,
+                    handling_live_edits: None,
+                };
+                self.walking.reroute_ped(ped, self.time, &mut ctx)
+            }
+            AgentID::BusPassenger(_, _) => unreachable!(),
+        };
+        match (rerouted, self.get_path(agent)) {
+            (Ok(true), Some(path)) => {
+                !path_crosses_live_edits(path, edited_lanes, closed_intersections)
+            }
+            _ => false,
+        }
+    }
+}
+
+fn path_crosses_live_edits(
+    path: &Path,
+    edited_lanes: &BTreeSet<LaneID>,
+    closed_intersections: &HashSet<IntersectionID>,
+) -> bool {
+    path.get_steps()
+        .iter()
+        .any(|step| match step.as_traversable() {
+            Traversable::Lane(l) => edited_lanes.contains(&l),
+            Traversable::Turn(t) => {
+                closed_intersections.contains(&t.parent)
+                    || edited_lanes.contains(&t.src)
+                    || edited_lanes.contains(&t.dst)
+            }
+        })
 }
 
 // Invasive debugging
@@ -968,6 +1064,7 @@ impl Sim {
                 handling_live_edits: None,
             };
             let vehicle = self.driving.delete_car(id, self.time, &mut ctx);
+            self.analytics.vehicle_removed(id, self.time);
             self.trips.cancel_trip(
                 self.time,
                 trip,
@@ -980,6 +1077,136 @@ impl Sim {
         }
     }
 
+    /// Cancels a trip in progress. If it started at a building, the person turns around and heads
+    /// back there, parking their car near it first, and the trip counts as cancelled once they
+    /// arrive. Otherwise, or if the new route can't be found, the trip is cancelled abruptly, the
+    /// same way a live map edit does.
+    pub fn cancel_trip(&mut self, id: TripID, map: &Map) -> Result<()> {
+        let agent = match self.trip_to_agent(id) {
+            TripResult::Ok(a) => a,
+            TripResult::ModeChange => {
+                bail!("{} is changing modes right now; try again in a moment", id)
+            }
+            _ => bail!("{} isn't in progress", id),
+        };
+        if let AgentID::Car(car) = agent {
+            if self.driving.get_path(car).is_none() {
+                bail!("{} hasn't entered the map yet; try again in a moment", car);
+            }
+        }
+        if let AgentID::BusPassenger(_, _) = agent {
+            bail!("{} is riding transit; try again after they get off", id);
+        }
+        let reason = "cancelled manually through the UI".to_string();
+
+        if let TripEndpoint::Bldg(home) = self.trips.trip_info(id).start {
+            let mut ctx = Ctx {
+                parking: &mut self.parking,
+                intersections: &mut self.intersections,
+                cap: &mut self.cap,
+                scheduler: &mut self.scheduler,
+                map,
+                handling_live_edits: None,
+            };
+            let turned_around = match agent {
+                AgentID::Car(car) => {
+                    self.driving
+                        .change_car_goal(car, &DrivingGoal::ParkNear(home), map)
+                }
+                AgentID::Pedestrian(ped) => self.walking.change_ped_goal(
+                    ped,
+                    SidewalkSpot::building(home, map),
+                    self.time,
+                    &mut ctx,
+                ),
+                AgentID::BusPassenger(_, _) => unreachable!(),
+            };
+            match turned_around {
+                Ok(true) => {
+                    self.trips.head_home(id, agent, home, reason, map);
+                    return Ok(());
+                }
+                Ok(false) => bail!(
+                    "{} can't turn around right now. They might be crossing the street, parking, \
+                     or in the middle of a complicated turn; try again in a moment.",
+                    agent
+                ),
+                // There's no way home, so fall back to cancelling abruptly
+                Err(_) => {}
+            }
+        }
+
+        self.abruptly_cancel_trip(id, agent, reason, map);
+        Ok(())
+    }
+
+    fn abruptly_cancel_trip(&mut self, id: TripID, agent: AgentID, reason: String, map: &Map) {
+        let mut ctx = Ctx {
+            parking: &mut self.parking,
+            intersections: &mut self.intersections,
+            cap: &mut self.cap,
+            scheduler: &mut self.scheduler,
+            map,
+            handling_live_edits: None,
+        };
+        match agent {
+            AgentID::Car(car) => {
+                let vehicle = self.driving.delete_car(car, self.time, &mut ctx);
+                self.analytics.vehicle_removed(car, self.time);
+                self.trips
+                    .cancel_trip(self.time, id, reason, Some(vehicle), &mut ctx);
+            }
+            AgentID::Pedestrian(ped) => {
+                self.walking.delete_ped(ped, &mut ctx);
+                self.trips
+                    .cancel_trip(self.time, id, reason, None, &mut ctx);
+            }
+            AgentID::BusPassenger(_, _) => unreachable!(),
+        }
+        self.trips.trip_abruptly_cancelled(id, agent);
+    }
+
+    /// Cancels a trip that hasn't started yet. The person stays where they are, and nothing happens
+    /// when the trip was scheduled to start.
+    pub fn cancel_unstarted_trip(&mut self, id: TripID) -> Result<()> {
+        match self.trip_to_agent(id) {
+            TripResult::TripNotStarted => {
+                self.trips
+                    .cancel_unstarted_trip(id, "cancelled manually through the UI".to_string());
+                Ok(())
+            }
+            _ => bail!("{} has already started", id),
+        }
+    }
+
+    /// Re-plans the rest of a driving trip from wherever the vehicle is now, using the current
+    /// state of the map and steering around congested lanes when there's another way. The vehicle
+    /// stays committed to its next turn. If the destination can't be reached anymore, the trip is
+    /// cancelled instead.
+    pub fn reroute_trip(&mut self, id: TripID, map: &Map) -> Result<()> {
+        let car = match self.trip_to_agent(id) {
+            TripResult::Ok(AgentID::Car(car)) => car,
+            TripResult::Ok(_) => bail!("Only vehicles can be rerouted"),
+            _ => bail!("{} isn't in progress", id),
+        };
+        match self.driving.reroute_car(car, map) {
+            Ok(true) => Ok(()),
+            Ok(false) => bail!(
+                "{} can't be rerouted right now. It might be almost done, looking for parking, \
+                 or in the middle of a complicated turn.",
+                car
+            ),
+            Err(err) => {
+                self.cancel_trip(id, map)?;
+                bail!(
+                    "{} couldn't be rerouted ({}), so the trip was cancelled",
+                    car,
+                    err
+                )
+            }
+        }
+    }
+
     pub fn clear_alerts(&mut self) -> Vec<(Time, AlertLocation, String)> {
         std::mem::replace(&mut self.analytics.alerts, Vec::new())
     }
@@ -1021,3 +1248,50 @@ impl Sim {
         self.recorder.take().unwrap().save(map);
     }
 }
+
+// Event log
+impl Sim {
+    /// Starts keeping the most recent `capacity` interesting events. Does nothing if the log is
+    /// already on.
+    pub fn enable_event_log(&mut self, capacity: usize) {
+        if self.event_log.is_none() {
+            self.event_log = Some(EventLog::new(capacity));
+        }
+    }
+
+    pub fn disable_event_log(&mut self) {
+        self.event_log = None;
+    }
+
+    pub fn get_event_log(&self) -> Option<&EventLog> {
+        self.event_log.as_ref()
+    }
+
+    pub fn get_mut_event_log(&mut self) -> Option<&mut EventLog> {
+        self.event_log.as_mut()
+    }
+}
+
+// Watching merges
+impl Sim {
+    /// Starts recording lane changes and yields onto or off of one road, replacing any previous
+    /// watch.
+    pub fn watch_merges(&mut self, road: RoadID) {
+        self.merge_watch = Some(MergeWatch::new(road));
+        self.intersections.watch_merges(Some(road));
+    }
+
+    pub fn stop_watching_merges(&mut self) {
+        self.merge_watch = None;
+        self.intersections.watch_merges(None);
+    }
+
+    pub fn get_mut_merge_watch(&mut self) -> Option<&mut MergeWatch> {
+        self.merge_watch.as_mut()
+    }
+
+    /// The road that intersections are reporting merges for, if any.
+    pub fn watched_merges(&self) -> Option<RoadID> {
+        self.intersections.watched_merges()
+    }
+}
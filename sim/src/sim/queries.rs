@@ -2,10 +2,10 @@
 
 use anyhow::Result;
 use serde::Serialize;
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 
 use abstutil::Counter;
-use geom::{Distance, Duration, PolyLine, Pt2D, Time};
+use geom::{Distance, Duration, PolyLine, Pt2D, Speed, Time};
 use map_model::{
     BuildingID, BusRouteID, BusStopID, IntersectionID, Lane, LaneID, Map, Path, PathConstraints,
     Position, Traversable, TurnID,
@@ -83,6 +83,17 @@ impl Sim {
         }
     }
 
+    /// None for bus passengers and agents that aren't active.
+    pub fn agent_speed(&self, map: &Map, id: AgentID) -> Option<AgentSpeed> {
+        match id {
+            AgentID::Pedestrian(id) => self.walking.agent_speed(id),
+            AgentID::Car(id) => self
+                .driving
+                .agent_speed(id, self.time, map, &self.intersections),
+            AgentID::BusPassenger(_, _) => None,
+        }
+    }
+
     pub fn num_transit_passengers(&self, car: CarID) -> usize {
         self.transit.get_passengers(car).len()
     }
@@ -288,6 +299,53 @@ impl Sim {
         results
     }
 
+    /// Every vehicle serving a route, furthest along first.
+    pub fn vehicles_along_route(&self, route: BusRouteID) -> Vec<RouteVehicle> {
+        let mut vehicles: Vec<RouteVehicle> = self
+            .transit
+            .stops_reached(route)
+            .into_iter()
+            .map(|(bus, idx, at_stop)| RouteVehicle {
+                bus,
+                progress: if at_stop {
+                    idx as f64
+                } else {
+                    (idx as f64) - 1.0 + self.driving.percent_along_route(bus)
+                },
+                headway: None,
+            })
+            .collect();
+        vehicles.sort_by(|a, b| b.progress.partial_cmp(&a.progress).unwrap());
+
+        // When each vehicle arrived at each stop
+        let mut arrivals: BTreeMap<CarID, Vec<(Time, BusStopID)>> = BTreeMap::new();
+        for (t, bus, r, bs) in &self.analytics.bus_arrivals {
+            if *r == route {
+                arrivals
+                    .entry(*bus)
+                    .or_insert_with(Vec::new)
+                    .push((*t, *bs));
+            }
+        }
+        for idx in 1..vehicles.len() {
+            let ahead = vehicles[idx - 1].bus;
+            let behind = vehicles[idx].bus;
+            vehicles[idx].headway =
+                arrivals
+                    .get(&behind)
+                    .and_then(|list| list.last())
+                    .and_then(|(t2, bs)| {
+                        let (t1, _) = arrivals
+                            .get(&ahead)?
+                            .iter()
+                            .rev()
+                            .find(|(_, stop)| stop == bs)?;
+                        Some(*t2 - *t1)
+                    });
+        }
+        vehicles
+    }
+
     pub fn get_analytics(&self) -> &Analytics {
         &self.analytics
     }
@@ -299,6 +357,69 @@ impl Sim {
             .delayed_intersections(self.time, threshold)
     }
 
+    /// Looks for agents blocking each other in a loop, each waiting at least `threshold`, and for
+    /// intersections where somebody has waited that long. None if there's neither. Each agent is
+    /// blocked by at most one other, so this only walks the blocked-by graph once.
+    pub fn find_gridlock(&self, map: &Map, threshold: Duration) -> Option<Gridlock> {
+        let graph = self.get_blocked_by_graph(map);
+        let mut cycles = Vec::new();
+        // Which walk first reached each agent
+        let mut visited: HashMap<AgentID, usize> = HashMap::new();
+        for (walk, start) in graph.keys().enumerate() {
+            let mut path = Vec::new();
+            let mut current = *start;
+            loop {
+                if let Some(prev_walk) = visited.get(&current) {
+                    // Reaching an agent from an earlier walk means any cycle there was already
+                    // found
+                    if *prev_walk == walk {
+                        let idx = path.iter().position(|a| *a == current).unwrap();
+                        cycles.push(path.split_off(idx));
+                    }
+                    break;
+                }
+                visited.insert(current, walk);
+                path.push(current);
+                match graph.get(&current) {
+                    Some((waiting, DelayCause::Agent(next))) if *waiting >= threshold => {
+                        current = *next;
+                    }
+                    _ => break,
+                }
+            }
+        }
+
+        let mut cycle_intersections = BTreeSet::new();
+        for agent in cycles.iter().flatten() {
+            let (waiting_for_turn, on) = match agent {
+                AgentID::Car(c) => match self.get_draw_car(*c, map) {
+                    Some(draw) => (draw.waiting_for_turn, draw.on),
+                    None => continue,
+                },
+                AgentID::Pedestrian(p) => match self.get_draw_ped(*p, map) {
+                    Some(draw) => (draw.waiting_for_turn, draw.on),
+                    None => continue,
+                },
+                AgentID::BusPassenger(_, _) => continue,
+            };
+            cycle_intersections.insert(match (waiting_for_turn, on) {
+                (Some(t), _) | (None, Traversable::Turn(t)) => t.parent,
+                (None, Traversable::Lane(l)) => map.get_l(l).dst_i,
+            });
+        }
+
+        let stuck_intersections = self.delayed_intersections(threshold);
+        if cycles.is_empty() && stuck_intersections.is_empty() {
+            return None;
+        }
+        Some(Gridlock {
+            time: self.time,
+            cycles,
+            cycle_intersections,
+            stuck_intersections,
+        })
+    }
+
     pub fn bldg_to_people(&self, b: BuildingID) -> Vec<PersonID> {
         self.trips.bldg_to_people(b)
     }
@@ -402,10 +523,43 @@ impl Sim {
         self.driving.debug_queue_lengths(l)
     }
 
+    /// How much time vehicles lost while stuck right behind this one, as a running total sampled
+    /// whenever this vehicle started moving again after stopping for a turn, passengers, or
+    /// unparking. Only available while the vehicle is on the map.
+    pub fn delay_caused_by(&self, id: CarID) -> Option<Vec<(Time, Duration)>> {
+        self.driving.delay_caused_by(id)
+    }
+
+    /// The vehicles on the map right now that have caused the most delay to others, worst first.
+    pub fn worst_delay_causers(&self, limit: usize) -> Vec<(CarID, Duration)> {
+        self.driving.worst_delay_causers(limit)
+    }
+
+    /// Every queue of vehicles backed up behind an intersection. When a queue fills an entire lane,
+    /// it's followed upstream, but no more than `max_depth` lanes away, since during gridlock this
+    /// could otherwise cover most of the map.
+    pub fn get_queue_spillback(
+        &self,
+        map: &Map,
+        i: IntersectionID,
+        max_depth: usize,
+    ) -> Vec<QueueSpillback> {
+        self.driving
+            .get_queue_spillback(self.time, i, map, max_depth)
+    }
+
     /// Returns the best-case time for a trip in a world with no traffic or intersection delays.
     /// Might fail in some cases where the real trip succeeds, but the single-mode path can't be
     /// found. Assumes the TripID exists.
     pub fn get_trip_time_lower_bound(&self, map: &Map, id: TripID) -> Result<Duration> {
+        self.get_trip_free_flow_route(map, id)
+            .map(|(_, duration)| duration)
+    }
+
+    /// Returns the path a trip would take from its original start to its end in a world with no
+    /// traffic, and how long following it would take. Like `get_trip_time_lower_bound`, the path
+    /// uses a single mode the entire time. Assumes the TripID exists.
+    pub fn get_trip_free_flow_route(&self, map: &Map, id: TripID) -> Result<(Path, Duration)> {
         let info = self.trips.trip_info(id);
         match TripEndpoint::path_req(info.start, info.end, info.mode, map) {
             Some(req) => {
@@ -433,7 +587,8 @@ impl Sim {
                             .max_speed
                     }
                 };
-                Ok(path.estimate_duration(map, constraints, max_speed))
+                let duration = path.estimate_duration(map, constraints, max_speed);
+                Ok((path, duration))
             }
             None => bail!(
                 "can't figure out PathRequest from {:?} to {:?} via {}",
@@ -530,3 +685,85 @@ pub enum DelayCause {
     /// Waiting on a traffic signal to change, or pausing at a stop sign before proceeding
     Intersection(IntersectionID),
 }
+
+/// Agents stuck waiting on each other, found by `Sim::find_gridlock`.
+#[derive(Clone, Serialize)]
+pub struct Gridlock {
+    pub time: Time,
+    /// In each cycle, every agent is blocked by the next one, and the last by the first.
+    pub cycles: Vec<Vec<AgentID>>,
+    /// Where the agents in the cycles are waiting
+    pub cycle_intersections: BTreeSet<IntersectionID>,
+    /// Intersections with somebody waiting past the threshold, and since when. The longest delayed
+    /// is first.
+    pub stuck_intersections: Vec<(IntersectionID, Time)>,
+}
+
+/// Vehicles stopped bumper-to-bumper behind an intersection, possibly spilling back across upstream
+/// intersections.
+pub struct QueueSpillback {
+    /// The lane leading into the intersection
+    pub approach: LaneID,
+    /// Starting at the approach and working upstream
+    pub segments: Vec<QueueSegment>,
+    pub num_vehicles: usize,
+    /// The queue keeps going further upstream, but wasn't followed any further
+    pub truncated: bool,
+}
+
+/// The part of a queue on one lane
+pub struct QueueSegment {
+    pub lane: LaneID,
+    /// The back of the last vehicle in the queue
+    pub start: Distance,
+    /// The front of the first vehicle in the queue
+    pub end: Distance,
+    /// How long the vehicle stuck here the longest has been waiting
+    pub max_wait: Duration,
+}
+
+impl QueueSpillback {
+    pub fn length(&self) -> Distance {
+        self.segments.iter().map(|s| s.end - s.start).sum()
+    }
+}
+
+/// One vehicle serving a transit route
+pub struct RouteVehicle {
+    pub bus: CarID,
+    /// How far along the route, measured in stops. 2.5 means halfway between the third and fourth
+    /// stops, and -0.5 means halfway to the first stop.
+    pub progress: f64,
+    /// How long after the vehicle ahead of this one it arrived at the last stop it reached. None
+    /// for the vehicle furthest along, or before this one has reached any stops.
+    pub headway: Option<Duration>,
+}
+
+/// How fast an agent is moving right now, and what's stopping them from going faster.
+pub struct AgentSpeed {
+    pub speed: Speed,
+    /// The speed limit of wherever the agent is, or their own top speed if that's lower
+    pub max_speed: Speed,
+    pub constraint: SpeedConstraint,
+}
+
+/// What's limiting an agent's speed. If there are multiple reasons, arbitrarily pick one.
+#[derive(Debug, PartialEq, Clone)]
+pub enum SpeedConstraint {
+    /// Going as fast as allowed
+    FreeFlow,
+    /// Right behind another vehicle, this far from its back
+    Following(CarID, Distance),
+    /// Stuck behind another vehicle that's parking or unparking
+    BlockedByParking(CarID),
+    /// Waiting on a traffic signal to change, or pausing at a stop sign before proceeding
+    Intersection(IntersectionID),
+    /// Waiting for someone to finish a conflicting turn
+    Yielding(AgentID),
+    /// Waiting for room to open up on the next lane, currently filled up to this vehicle
+    NoRoomAhead(CarID),
+    /// Parking, unparking, or switching between walking and biking
+    Maneuvering,
+    /// A bus or train stopped to pick up and drop off passengers, or a pedestrian waiting for one
+    AtTransitStop,
+}
@@ -397,6 +397,25 @@ impl TransitSimState {
         }
     }
 
+    /// For each vehicle on the route, the index of the stop it's at or heading towards (which is
+    /// the number of stops past the end when it's driving off the map), and whether it's stopped
+    /// there.
+    pub fn stops_reached(&self, route: BusRouteID) -> Vec<(CarID, usize, bool)> {
+        if let Some(ref r) = self.routes.get(&route) {
+            r.active_vehicles
+                .iter()
+                .map(|bus| match self.buses[bus].state {
+                    BusState::DrivingToStop(idx) => (*bus, idx, false),
+                    BusState::AtStop(idx) => (*bus, idx, true),
+                    BusState::DrivingOffMap => (*bus, r.stops.len(), false),
+                    BusState::Done => unreachable!(),
+                })
+                .collect()
+        } else {
+            Vec::new()
+        }
+    }
+
     /// (buses, trains)
     pub fn active_vehicles(&self) -> (usize, usize) {
         let mut buses = 0;
@@ -103,6 +103,7 @@ impl TripManager {
             total_blocked_time: Duration::ZERO,
             total_distance: Distance::ZERO,
             legs: VecDeque::new(),
+            heading_home: None,
         };
         self.unfinished_trips += 1;
         let person = &mut self.people[trip.person.0];
@@ -131,7 +132,15 @@ impl TripManager {
     }
 
     pub fn start_trip(&mut self, now: Time, trip: TripID, args: StartTripArgs, ctx: &mut Ctx) {
-        assert!(self.trips[trip.0].info.cancellation_reason.is_none());
+        // The trip was cancelled before it had a chance to start. If it was delayed behind the
+        // person's previous trip, move on to their next one.
+        if self.trips[trip.0].info.cancellation_reason.is_some() {
+            let person = self.trips[trip.0].person;
+            if !matches!(self.people[person.0].state, PersonState::Trip(_)) {
+                self.start_delayed_trip(now, person, ctx);
+            }
+            return;
+        }
 
         let person = &mut self.people[self.trips[trip.0].person.0];
         if let PersonState::Trip(_) = person.state {
@@ -916,6 +925,10 @@ impl TripManager {
         let trip = &mut self.trips[id.0];
         assert!(trip.legs.is_empty());
         assert!(!trip.finished_at.is_some());
+        if let Some(reason) = trip.heading_home.take() {
+            self.arrived_home(now, id, reason, ctx);
+            return;
+        }
         trip.finished_at = Some(now);
         self.unfinished_trips -= 1;
         self.events.push(Event::TripFinished {
@@ -1117,6 +1130,65 @@ impl TripManager {
         self.start_delayed_trip(now, person, ctx);
     }
 
+    /// Cancel a trip after it's started, without warping anybody. The caller has already sent the
+    /// agent back towards the building where the trip began; a driver will park near there and
+    /// walk the rest of the way. The trip counts as cancelled once they arrive.
+    pub fn head_home(
+        &mut self,
+        id: TripID,
+        agent: AgentID,
+        home: BuildingID,
+        reason: String,
+        map: &Map,
+    ) {
+        let trip = &mut self.trips[id.0];
+        trip.legs.clear();
+        if let AgentID::Car(car) = agent {
+            trip.legs
+                .push_back(TripLeg::Drive(car, DrivingGoal::ParkNear(home)));
+        }
+        trip.legs
+            .push_back(TripLeg::Walk(SidewalkSpot::building(home, map)));
+        trip.heading_home = Some(reason);
+    }
+
+    fn arrived_home(&mut self, now: Time, id: TripID, reason: String, ctx: &mut Ctx) {
+        let trip = &mut self.trips[id.0];
+        self.unfinished_trips -= 1;
+        trip.info.cancellation_reason = Some(reason);
+        self.events
+            .push(Event::TripCancelled(trip.id, trip.info.mode));
+        let person = trip.person;
+        let home = match trip.info.start {
+            TripEndpoint::Bldg(b) => b,
+            _ => unreachable!(),
+        };
+
+        // The person's next trips might start from the destination they never reached. They stay
+        // home instead, until a trip that starts from there.
+        let later_trips: Vec<TripID> = self.people[person.0]
+            .trips
+            .iter()
+            .skip_while(|t| **t != id)
+            .skip(1)
+            .cloned()
+            .collect();
+        for t in later_trips {
+            let later = &self.trips[t.0];
+            if later.started || later.info.start == TripEndpoint::Bldg(home) {
+                break;
+            }
+            if later.info.cancellation_reason.is_none() {
+                self.cancel_unstarted_trip(
+                    t,
+                    format!("{} was cancelled, so the person stayed home", id),
+                );
+            }
+        }
+
+        self.start_delayed_trip(now, person, ctx);
+    }
+
     pub fn trip_abruptly_cancelled(&mut self, trip: TripID, agent: AgentID) {
         assert_eq!(self.active_trip_mode.remove(&agent), Some(trip));
     }
@@ -1367,6 +1439,9 @@ struct Trip {
     // Not filled out until the trip starts
     legs: VecDeque<TripLeg>,
     person: PersonID,
+    /// If the trip was cancelled partway through and the person is heading back to where they
+    /// started, the cancellation reason. It takes effect once they get there.
+    heading_home: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -1379,6 +1454,10 @@ pub struct TripInfo {
     pub purpose: TripPurpose,
     /// Did a ScenarioModifier apply to this?
     pub modified: bool,
+    /// Was this made up by a `BuildingOverride`, instead of coming from the original scenario?
+    /// Not kept in savestates, so older ones still load.
+    #[serde(skip)]
+    pub synthetic: bool,
     /// Was this trip affected by a congestion cap?
     pub capped: bool,
     pub cancellation_reason: Option<String>,
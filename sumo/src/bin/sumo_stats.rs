@@ -0,0 +1,116 @@
+//! Prints a summary of a SUMO network, or compares two of them side by side. Useful to check that
+//! re-running netconvert or an exporter didn't silently drop half the lanes.
+//!
+//! `cargo run --bin sumo_stats montlake.net.xml [new_montlake.net.xml] [--threshold=0.1]
+//! [--keep_classes=passenger,bus] [--drop_types=railway]`
+
+use anyhow::Result;
+
+use abstutil::{prettyprint_usize, CmdArgs, Timer};
+
+use sumo::{Network, NetworkFilter, ParseMode};
+
+/// How many of the IDs only in one network to list
+const MAX_EXAMPLES: usize = 10;
+
+fn main() -> Result<()> {
+    let mut args = CmdArgs::new();
+    let threshold = args
+        .optional_parse("--threshold", |s| s.parse::<f64>())
+        .unwrap_or(0.1);
+    let filter = NetworkFilter::from_args(&mut args);
+    let before_path = args.required_free();
+    let after_path = args.optional_free();
+    args.done();
+
+    let mut timer = Timer::new("summarize SUMO networks");
+    let before_network = load(&before_path, &filter, &mut timer)?;
+    let before = before_network.stats();
+    let after_path = match after_path {
+        Some(path) => path,
+        None => {
+            println!("{}", before_path);
+            for (name, value) in before.rows() {
+                println!("  {:<30} {:>12}", name, format_value(value));
+            }
+            let memory = before_network.id_memory();
+            println!();
+            println!(
+                "{} IDs are referenced {} times. Interned, they take {} bytes, instead of {} as \
+                 separate strings.",
+                prettyprint_usize(before_network.strings.len()),
+                prettyprint_usize(memory.references),
+                prettyprint_usize(memory.interned_bytes),
+                prettyprint_usize(memory.uninterned_bytes)
+            );
+            return Ok(());
+        }
+    };
+    let after = load(&after_path, &filter, &mut timer)?.stats();
+
+    let diff = before.diff(&after, threshold);
+    println!("Before: {}", before_path);
+    println!("After: {}", after_path);
+    println!(
+        "Changes above {}% are marked with *",
+        (threshold * 100.0).round()
+    );
+    println!();
+    println!(
+        "  {:<30} {:>12} {:>12} {:>12}",
+        "", "before", "after", "change"
+    );
+    for (name, before, after, flagged) in &diff.rows {
+        println!(
+            "{} {:<30} {:>12} {:>12} {:>12}",
+            if *flagged { "*" } else { " " },
+            name,
+            format_value(*before),
+            format_value(*after),
+            format_value(after - before)
+        );
+    }
+
+    for (label, ids) in vec![
+        ("Edges only in the first network", &diff.removed_edges),
+        ("Edges only in the second network", &diff.added_edges),
+        (
+            "Junctions only in the first network",
+            &diff.removed_junctions,
+        ),
+        (
+            "Junctions only in the second network",
+            &diff.added_junctions,
+        ),
+    ] {
+        if ids.is_empty() {
+            continue;
+        }
+        println!();
+        println!("{} ({} total):", label, ids.len());
+        for id in ids.iter().take(MAX_EXAMPLES) {
+            println!("  {}", id);
+        }
+        if ids.len() > MAX_EXAMPLES {
+            println!("  ...");
+        }
+    }
+    Ok(())
+}
+
+fn load(path: &str, filter: &NetworkFilter, timer: &mut Timer) -> Result<Network> {
+    let network = Network::load_filtered(path, ParseMode::Lenient, filter, timer)?;
+    if !network.filtered.is_empty() {
+        println!("{}: {}", path, network.filtered.describe());
+    }
+    Ok(network)
+}
+
+// Counts are whole numbers; lengths aren't
+fn format_value(x: f64) -> String {
+    if x.fract() == 0.0 {
+        format!("{}", x)
+    } else {
+        format!("{:.2}", x)
+    }
+}
@@ -0,0 +1,165 @@
+//! Optionally drops part of a `Network` while loading it, like rail lines or footpaths that some
+//! analyses don't care about.
+
+use std::collections::BTreeSet;
+
+use abstutil::CmdArgs;
+
+use crate::{Edge, EdgeID, InternalLaneID, LaneID, Network, NodeID, SumoID, VehicleClassSet};
+
+/// Which edges to keep. The default keeps everything.
+#[derive(Clone, Debug, Default)]
+pub struct NetworkFilter {
+    /// If set, only keep edges with at least one lane allowing one of these classes
+    pub allow_any: Option<VehicleClassSet>,
+    /// Drop edges with these types. "railway" matches "railway.rail", "railway.tram", etc, while
+    /// "railway.tram" only matches itself.
+    pub drop_types: BTreeSet<String>,
+}
+
+/// Everything a `NetworkFilter` removed, by the original IDs
+#[derive(Debug, Default)]
+pub struct FilterReport {
+    pub edges: Vec<String>,
+    /// Junctions left without any edges
+    pub junctions: Vec<String>,
+    pub connections: usize,
+}
+
+impl NetworkFilter {
+    /// Reads `--keep_classes` and `--drop_types`, both comma-separated lists, like
+    /// `--keep_classes=passenger,bus --drop_types=railway,highway.footway`.
+    pub fn from_args(args: &mut CmdArgs) -> NetworkFilter {
+        NetworkFilter {
+            allow_any: args.optional_parse("--keep_classes", |s| {
+                s.replace(',', " ").parse::<VehicleClassSet>()
+            }),
+            drop_types: args
+                .optional("--drop_types")
+                .map(|s| s.split(',').map(|t| t.to_string()).collect())
+                .unwrap_or_else(BTreeSet::new),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.allow_any.is_none() && self.drop_types.is_empty()
+    }
+
+    fn keeps(&self, edge: &Edge) -> bool {
+        let dropped_type = self.drop_types.iter().any(|t| {
+            edge.edge_type == *t
+                || (edge.edge_type.starts_with(t.as_str())
+                    && edge.edge_type[t.len()..].starts_with('.'))
+        });
+        if dropped_type {
+            return false;
+        }
+        match self.allow_any {
+            Some(classes) => edge.lanes.iter().any(|l| l.allow.intersects(classes)),
+            None => true,
+        }
+    }
+}
+
+impl FilterReport {
+    pub fn is_empty(&self) -> bool {
+        self.edges.is_empty() && self.junctions.is_empty() && self.connections == 0
+    }
+
+    pub fn describe(&self) -> String {
+        format!(
+            "Filtered out {} edges, {} junctions, and {} connections",
+            self.edges.len(),
+            self.junctions.len(),
+            self.connections
+        )
+    }
+}
+
+impl Network {
+    /// Removes edges the filter doesn't keep, along with connections to or from them and the
+    /// internal lanes only used by those connections. Junctions left without any edges are
+    /// removed too.
+    pub(crate) fn apply_filter(&mut self, filter: &NetworkFilter) -> FilterReport {
+        let mut report = FilterReport::default();
+        if filter.is_empty() {
+            return report;
+        }
+
+        let mut removed_edges: BTreeSet<EdgeID> = BTreeSet::new();
+        let mut removed_lanes: BTreeSet<LaneID> = BTreeSet::new();
+        let mut touched_junctions: BTreeSet<NodeID> = BTreeSet::new();
+        let strings = &self.strings;
+        self.normal_edges.retain(|id, edge| {
+            if filter.keeps(edge) {
+                return true;
+            }
+            removed_edges.insert(*id);
+            removed_lanes.extend(edge.lanes.iter().map(|l| l.id));
+            touched_junctions.insert(edge.from);
+            touched_junctions.insert(edge.to);
+            report.edges.push(strings.get(*id).to_string());
+            false
+        });
+        if removed_edges.is_empty() {
+            return report;
+        }
+
+        // Connections between normal edges go through an internal lane, and a second connection
+        // continues from that internal lane. Both have to go. All kinds of IDs share one
+        // StringTable, so a connection's lane can be checked against the internal lanes.
+        let mut orphaned_internal_lanes: BTreeSet<InternalLaneID> = BTreeSet::new();
+        let mut used_internal_lanes: BTreeSet<InternalLaneID> = BTreeSet::new();
+        for c in &self.connections {
+            if let Some(via) = c.via {
+                if removed_edges.contains(&c.from) || removed_edges.contains(&c.to) {
+                    orphaned_internal_lanes.insert(via);
+                } else {
+                    used_internal_lanes.insert(via);
+                }
+            }
+        }
+        let orphaned_internal_lanes: BTreeSet<InternalLaneID> = orphaned_internal_lanes
+            .difference(&used_internal_lanes)
+            .cloned()
+            .collect();
+        let before = self.connections.len();
+        self.connections.retain(|c| {
+            !removed_edges.contains(&c.from)
+                && !removed_edges.contains(&c.to)
+                && !orphaned_internal_lanes
+                    .contains(&InternalLaneID::from_index(c.from_lane.index()))
+        });
+        report.connections = before - self.connections.len();
+
+        self.internal_edges.retain(|_, e| {
+            !e.lanes
+                .iter()
+                .all(|l| orphaned_internal_lanes.contains(&l.id))
+        });
+
+        let mut still_used: BTreeSet<NodeID> = BTreeSet::new();
+        for edge in self.normal_edges.values() {
+            still_used.insert(edge.from);
+            still_used.insert(edge.to);
+        }
+        for j in touched_junctions {
+            if !still_used.contains(&j) {
+                self.junctions.remove(&j);
+                report.junctions.push(self.strings.get(j).to_string());
+            }
+        }
+        for junction in self.junctions.values_mut() {
+            junction
+                .incoming_lanes
+                .retain(|l| !removed_lanes.contains(l));
+            junction
+                .internal_lanes
+                .retain(|l| !orphaned_internal_lanes.contains(l));
+        }
+
+        report.edges.sort();
+        report.junctions.sort();
+        report
+    }
+}
@@ -2,30 +2,70 @@
 
 #[macro_use]
 extern crate anyhow;
+#[macro_use]
+extern crate log;
 
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, HashMap};
 
 use geom::{Distance, PolyLine, Polygon, Pt2D, Speed};
 
-pub use self::raw::{Connection, Direction, EdgeID, InternalLaneID, LaneID, NodeID};
+pub use self::filter::{FilterReport, NetworkFilter};
+pub use self::raw::{Direction, LightState, ParseMode, Phase, SchemaReport, TrafficLight};
+pub use self::stats::{IdMemory, NetworkDiff, NetworkStats};
+pub use self::vehicle_class::{VehicleClass, VehicleClassSet};
 
+mod filter;
 mod normalize;
 mod raw;
+mod simplify;
+mod stats;
+mod vehicle_class;
 
 /// A normalized form of a SUMO
 /// [network](https://sumo.dlr.de/docs/Networks/SUMO_Road_Networks.html). A `raw::Network` is a direct representation of a .net.xml file. That's further simplified to produce this structure, which should be easier to work with. The
 /// transformations:
 ///
 /// - Any unspecified edge and lane attributes are inherited from `types` or set to defaults
+/// - Lanes' `allow` and `disallow` lists are combined into one set of allowed vehicle classes
 /// - Internal edges are represented separately
 /// - Internal junctions are filtered out
 /// - The Y coordinate is inverted, so that Y decreases northbound
+/// - IDs are interned; `strings` resolves them back to the original strings
+/// - Edges missing a junction are snapped to the nearest one, or dropped if there's nothing close
+/// - Optionally, edges not matching a `NetworkFilter` are removed, along with anything only they
+///   used
 pub struct Network {
     pub location: raw::Location,
     pub normal_edges: BTreeMap<EdgeID, Edge>,
     pub internal_edges: BTreeMap<EdgeID, InternalEdge>,
     pub junctions: BTreeMap<NodeID, Junction>,
     pub connections: Vec<Connection>,
+    /// Keyed by the ID that a `Connection` refers to. If there are several programs for the same
+    /// light, only the last one is kept.
+    pub traffic_lights: BTreeMap<TrafficLightID, TrafficLight>,
+    pub strings: StringTable,
+    /// Every edge that was dropped or had to be snapped to a junction, and every lane with
+    /// vehicle classes that weren't understood
+    pub warnings: Vec<String>,
+    /// Elements and attributes in the file that were ignored because they're not understood
+    pub unknown_schema: SchemaReport,
+    /// Everything removed by the filter passed to `load_filtered`
+    pub filtered: FilterReport,
+}
+
+/// A connection from one lane to another across a junction. See
+/// <https://sumo.dlr.de/docs/Networks/SUMO_Road_Networks.html#connections>
+pub struct Connection {
+    pub from: EdgeID,
+    pub from_lane: LaneID,
+    pub to: EdgeID,
+    pub to_lane: LaneID,
+    pub via: Option<InternalLaneID>,
+    pub dir: Direction,
+    /// The traffic light controlling this connection, if any
+    pub tl: Option<TrafficLightID>,
+    /// Which character of the traffic light's phase states applies to this connection
+    pub link_index: Option<usize>,
 }
 
 pub struct Edge {
@@ -37,6 +77,9 @@ pub struct Edge {
     pub priority: usize,
     pub lanes: Vec<Lane>,
     pub center_line: PolyLine,
+    /// If this edge was formed by `merge_degenerate_junctions`, the original edges in order,
+    /// starting with this one. Otherwise empty.
+    pub merged_from: Vec<EdgeID>,
 }
 
 pub struct Lane {
@@ -47,7 +90,7 @@ pub struct Lane {
     pub length: Distance,
     pub width: Distance,
     pub center_line: PolyLine,
-    pub allow: Vec<VehicleClass>,
+    pub allow: VehicleClassSet,
 }
 
 /// See https://sumo.dlr.de/docs/Networks/SUMO_Road_Networks.html#internal_edges
@@ -62,7 +105,7 @@ pub struct InternalLane {
     pub speed: Speed,
     pub length: Distance,
     pub center_line: Option<PolyLine>,
-    pub allow: Vec<VehicleClass>,
+    pub allow: VehicleClassSet,
 }
 
 pub struct Junction {
@@ -74,12 +117,95 @@ pub struct Junction {
     pub shape: Polygon,
 }
 
-#[derive(PartialEq)]
-pub enum VehicleClass {
-    Pedestrian,
-    Bicycle,
-    RailUrban,
-    // TODO Use all values from
-    // https://sumo.dlr.de/docs/Definition_of_Vehicles,_Vehicle_Types,_and_Routes.html#abstract_vehicle_class
-    Other(String),
+/// SUMO IDs are arbitrary strings, and big networks have lots of them. They're interned into these
+/// IDs, which are cheap to copy and compare.
+pub trait SumoID: Copy {
+    fn from_index(idx: u32) -> Self;
+    fn index(self) -> u32;
+}
+
+macro_rules! sumo_id {
+    ($name:ident) => {
+        #[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+        pub struct $name(u32);
+
+        impl SumoID for $name {
+            fn from_index(idx: u32) -> $name {
+                $name(idx)
+            }
+
+            fn index(self) -> u32 {
+                self.0
+            }
+        }
+    };
+}
+
+sumo_id!(EdgeID);
+sumo_id!(LaneID);
+sumo_id!(InternalLaneID);
+sumo_id!(NodeID);
+sumo_id!(TrafficLightID);
+
+/// The original string of every interned ID, for display and writing SUMO files again. All kinds
+/// of IDs share one table.
+#[derive(Default)]
+pub struct StringTable {
+    strings: Vec<String>,
+}
+
+impl StringTable {
+    /// Returns the original string for any kind of ID.
+    pub fn get<I: SumoID>(&self, id: I) -> &str {
+        &self.strings[id.index() as usize]
+    }
+
+    /// How many strings are interned
+    pub fn len(&self) -> usize {
+        self.strings.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.strings.is_empty()
+    }
+
+    /// Roughly how many bytes the table takes, counting each string's heap allocation
+    pub fn memory_bytes(&self) -> usize {
+        self.strings
+            .iter()
+            .map(|s| std::mem::size_of::<String>() + s.capacity())
+            .sum()
+    }
+}
+
+/// Interns IDs while a network is being built. Finding the ID for a string is only needed then,
+/// so the lookup is thrown away afterwards, leaving one copy of each string in the `StringTable`.
+#[derive(Default)]
+struct Interner {
+    table: StringTable,
+    lookup: HashMap<String, u32>,
+}
+
+impl Interner {
+    fn intern<I: SumoID>(&mut self, s: String) -> I {
+        if let Some(idx) = self.lookup.get(&s) {
+            return I::from_index(*idx);
+        }
+        let idx = self.table.strings.len() as u32;
+        self.lookup.insert(s.clone(), idx);
+        self.table.strings.push(s);
+        I::from_index(idx)
+    }
+
+    fn get<I: SumoID>(&self, id: I) -> &str {
+        self.table.get(id)
+    }
+
+    /// Returns the finished table, and roughly how many bytes the dropped lookup took
+    fn finish(self) -> (StringTable, usize) {
+        let entry = std::mem::size_of::<String>() + std::mem::size_of::<u32>() + 1;
+        let lookup_bytes = self.lookup.capacity() * entry
+            + self.lookup.keys().map(|s| s.capacity()).sum::<usize>();
+        (self.table, lookup_bytes)
+    }
 }
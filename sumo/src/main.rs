@@ -12,15 +12,25 @@ use map_model::{
     LaneType, Map, Road, RoadID, Turn, TurnID, TurnType,
 };
 
-use sumo::{Direction, InternalLaneID, Network, NodeID, VehicleClass};
+use sumo::{
+    Direction, Edge, InternalLaneID, Network, NetworkFilter, NodeID, ParseMode, VehicleClass,
+    VehicleClassSet,
+};
 
 fn main() -> Result<()> {
     let mut timer = Timer::new("convert SUMO network");
     let mut args = CmdArgs::new();
+    let simplify = args.enabled("--simplify");
+    let filter = NetworkFilter::from_args(&mut args);
     let input = args.required_free();
     args.done();
 
-    let network = Network::load(&input, &mut timer).unwrap();
+    let mut network =
+        Network::load_filtered(&input, ParseMode::Lenient, &filter, &mut timer).unwrap();
+    if simplify {
+        let merged = network.merge_degenerate_junctions();
+        timer.add_result(0.0, format!("Merged {} edges at geometry nodes", merged));
+    }
     let map = convert(&input, network)?;
     map.save();
     Ok(())
@@ -64,12 +74,12 @@ fn convert(orig_path: &str, network: Network) -> Result<Map> {
         let mut lanes_rtl: Vec<(LaneID, map_model::Direction, LaneType)> = Vec::new();
         for lane in &edge.lanes {
             let lane_id = LaneID(lanes.len());
-            ids_lanes.insert(lane.id.clone(), lane_id);
-            let lane_type = if lane.allow == vec![VehicleClass::Pedestrian] {
+            ids_lanes.insert(lane.id, lane_id);
+            let lane_type = if lane.allow == VehicleClassSet::from(VehicleClass::Pedestrian) {
                 LaneType::Sidewalk
-            } else if lane.allow == vec![VehicleClass::Bicycle] {
+            } else if lane.allow == VehicleClassSet::from(VehicleClass::Bicycle) {
                 LaneType::Biking
-            } else if lane.allow == vec![VehicleClass::RailUrban] {
+            } else if lane.allow == VehicleClassSet::from(VehicleClass::RailUrban) {
                 LaneType::LightRail
             } else {
                 LaneType::Driving
@@ -100,10 +110,24 @@ fn convert(orig_path: &str, network: Network) -> Result<Map> {
             let speed_limit = edge.lanes[0].speed;
 
             let mut osm_tags = Tags::empty();
-            osm_tags.insert("id", edge.id.0.clone());
+            osm_tags.insert("id", network.strings.get(edge.id));
             if let Some(name) = &edge.name {
                 osm_tags.insert("name", name);
             }
+            // Keep enough of the edge around to trace a converted road back to the SUMO network
+            // from the lane's debug tab in the game.
+            osm_tags.insert("from_junction", network.strings.get(edge.from));
+            osm_tags.insert("to_junction", network.strings.get(edge.to));
+            osm_tags.insert("priority", edge.priority.to_string());
+            osm_tags.insert("allow:lanes", allow_per_lane(&edge));
+            if !edge.merged_from.is_empty() {
+                let ids: Vec<&str> = edge
+                    .merged_from
+                    .iter()
+                    .map(|id| network.strings.get(*id))
+                    .collect();
+                osm_tags.insert("merged_from", ids.join(";"));
+            }
             let parts: Vec<&str> = edge.edge_type.split(".").collect();
             // "highway.footway"
             if parts.len() != 2 {
@@ -139,6 +163,9 @@ fn convert(orig_path: &str, network: Network) -> Result<Map> {
             lanes_ltr.extend(roads[road_id.0].lanes_ltr.clone());
             // TODO Should we check that the attributes are the same for both directions?
             roads[road_id.0].lanes_ltr = lanes_ltr;
+            roads[road_id.0]
+                .osm_tags
+                .insert("allow:lanes:backward", allow_per_lane(&edge));
         }
     }
 
@@ -154,8 +181,8 @@ fn convert(orig_path: &str, network: Network) -> Result<Map> {
     let mut turns = Vec::new();
     for connection in network.connections {
         match (
-            ids_lanes.get(&connection.from_lane()),
-            ids_lanes.get(&connection.to_lane()),
+            ids_lanes.get(&connection.from_lane),
+            ids_lanes.get(&connection.to_lane),
             connection.via,
         ) {
             (Some(from), Some(to), Some(via)) => {
@@ -206,3 +233,9 @@ fn convert(orig_path: &str, network: Network) -> Result<Map> {
         turns,
     ))
 }
+
+/// The vehicle classes allowed on each lane of an edge, from right to left, separated by "|"
+fn allow_per_lane(edge: &Edge) -> String {
+    let parts: Vec<String> = edge.lanes.iter().map(|l| l.allow.to_string()).collect();
+    parts.join("|")
+}
@@ -8,26 +8,66 @@ use abstutil::Timer;
 use geom::{Distance, PolyLine, Pt2D, Ring};
 
 use crate::{
-    raw, Edge, InternalEdge, InternalLane, InternalLaneID, Junction, Lane, LaneID, Network,
+    raw, Connection, Edge, EdgeID, FilterReport, InternalEdge, InternalLane, Interner, Junction,
+    Lane, Network, NetworkFilter, NodeID, ParseMode, StringTable, TrafficLightID, VehicleClassSet,
 };
 
 impl Network {
-    /// Reads a .net.xml file and return the normalized SUMO network.
+    /// Reads a .net.xml file and return the normalized SUMO network. Anything not understood is
+    /// logged and recorded in `unknown_schema`.
     pub fn load(path: &str, timer: &mut Timer) -> Result<Network> {
-        let raw = raw::Network::parse(path, timer)?;
+        Network::load_with_mode(path, ParseMode::Lenient, timer)
+    }
+
+    /// Like `load`, but strict mode fails on anything not understood.
+    pub fn load_with_mode(path: &str, mode: ParseMode, timer: &mut Timer) -> Result<Network> {
+        Network::load_filtered(path, mode, &NetworkFilter::default(), timer)
+    }
+
+    /// Like `load_with_mode`, but only keeps the edges that pass a filter. What was removed is
+    /// recorded in `filtered`.
+    pub fn load_filtered(
+        path: &str,
+        mode: ParseMode,
+        filter: &NetworkFilter,
+        timer: &mut Timer,
+    ) -> Result<Network> {
+        let raw = raw::Network::parse(path, mode, timer)?;
         timer.start("normalize");
-        let network = Network::from_raw(raw);
+        let network = Network::from_raw(raw, filter, timer);
+        for warning in &network.warnings {
+            warn!("{}", warning);
+        }
         timer.stop("normalize");
         Ok(network)
     }
 
-    fn from_raw(raw: raw::Network) -> Network {
+    fn from_raw(raw: raw::Network, filter: &NetworkFilter, timer: &mut Timer) -> Network {
+        let mut strings = Interner::default();
+        // Intern junctions and edges in order of their original strings first, so iterating over
+        // them by ID happens in the same order as before IDs were interned.
+        let mut junction_ids: Vec<String> = raw.junctions.iter().map(|j| j.id.0.clone()).collect();
+        junction_ids.sort();
+        for id in junction_ids {
+            strings.intern::<NodeID>(id);
+        }
+        let mut edge_ids: Vec<String> = raw.edges.iter().map(|e| e.id.0.clone()).collect();
+        edge_ids.sort();
+        for id in edge_ids {
+            strings.intern::<EdgeID>(id);
+        }
+
         let mut network = Network {
             location: raw.location,
             normal_edges: BTreeMap::new(),
             internal_edges: BTreeMap::new(),
             junctions: BTreeMap::new(),
-            connections: raw.connections,
+            connections: Vec::new(),
+            traffic_lights: BTreeMap::new(),
+            strings: StringTable::default(),
+            warnings: Vec::new(),
+            unknown_schema: raw.unknown,
+            filtered: FilterReport::default(),
         };
 
         let types: BTreeMap<String, raw::Type> =
@@ -37,40 +77,99 @@ impl Network {
             if junction.junction_type == "internal" {
                 continue;
             }
+            let id = strings.intern(junction.id.0.clone());
             network.junctions.insert(
-                junction.id.clone(),
+                id,
                 Junction {
                     pt: junction.pt(),
-                    id: junction.id,
+                    id,
                     junction_type: junction.junction_type,
-                    incoming_lanes: junction.incoming_lanes,
-                    internal_lanes: junction.internal_lanes,
+                    incoming_lanes: junction
+                        .incoming_lanes
+                        .into_iter()
+                        .map(|l| strings.intern(l.0))
+                        .collect(),
+                    internal_lanes: junction
+                        .internal_lanes
+                        .into_iter()
+                        .map(|l| strings.intern(l.0))
+                        .collect(),
                     shape: junction.shape.unwrap(),
                 },
             );
         }
 
+        let mut num_recovered = 0;
+        let mut num_dropped = 0;
         for edge in raw.edges {
+            let id = strings.intern(edge.id.0);
             if edge.function == raw::Function::Internal {
                 let mut lanes = Vec::new();
                 for lane in edge.lanes {
+                    let allow = allowed_classes(&lane, None, &mut network.warnings);
                     lanes.push(InternalLane {
-                        id: InternalLaneID(lane.id),
+                        id: strings.intern(lane.id),
                         index: lane.index,
                         speed: lane.speed,
                         length: lane.length,
                         center_line: lane.shape.ok(),
-                        allow: lane.allow,
+                        allow,
                     });
                 }
                 network
                     .internal_edges
-                    .insert(edge.id.clone(), InternalEdge { id: edge.id, lanes });
+                    .insert(id, InternalEdge { id, lanes });
                 continue;
             }
 
-            let from = edge.from.unwrap();
-            let to = edge.to.unwrap();
+            let endpoints = edge
+                .shape
+                .as_ref()
+                .or_else(|| edge.lanes.iter().find_map(|l| l.shape.as_ref().ok()))
+                .map(|pl| (pl.first_pt(), pl.last_pt()));
+            let (from, to) = match (
+                find_junction(
+                    &network.junctions,
+                    &mut strings,
+                    edge.from,
+                    endpoints.map(|(pt, _)| pt),
+                ),
+                find_junction(
+                    &network.junctions,
+                    &mut strings,
+                    edge.to,
+                    endpoints.map(|(_, pt)| pt),
+                ),
+            ) {
+                (Ok((from, _)), Ok((to, _))) if from == to => {
+                    network.warnings.push(format!(
+                        "Dropping edge {}: both ends are at junction {}",
+                        strings.get(id),
+                        strings.get(from)
+                    ));
+                    num_dropped += 1;
+                    continue;
+                }
+                (Ok((from, snapped1)), Ok((to, snapped2))) => {
+                    if snapped1 || snapped2 {
+                        network.warnings.push(format!(
+                            "Edge {} was missing a junction, so it was snapped to go from {} to {}",
+                            strings.get(id),
+                            strings.get(from),
+                            strings.get(to)
+                        ));
+                        num_recovered += 1;
+                    }
+                    (from, to)
+                }
+                (Err(err), _) | (_, Err(err)) => {
+                    network
+                        .warnings
+                        .push(format!("Dropping edge {}: {}", strings.get(id), err));
+                    num_dropped += 1;
+                    continue;
+                }
+            };
             let template = &types[edge.edge_type.as_ref().unwrap()];
 
             let raw_center_line = match edge.shape {
@@ -89,22 +188,23 @@ impl Network {
 
             let mut lanes = Vec::new();
             for lane in edge.lanes {
+                let allow = allowed_classes(&lane, Some(template), &mut network.warnings);
                 lanes.push(Lane {
-                    id: LaneID(lane.id),
+                    id: strings.intern(lane.id),
                     index: lane.index,
                     speed: lane.speed,
                     length: lane.length,
                     // https://sumo.dlr.de/docs/Simulation/SublaneModel.html
                     width: lane.width.unwrap_or(Distance::meters(3.2)),
                     center_line: lane.shape.unwrap(),
-                    allow: lane.allow,
+                    allow,
                 });
             }
 
             network.normal_edges.insert(
-                edge.id.clone(),
+                id,
                 Edge {
-                    id: edge.id,
+                    id,
                     edge_type: edge.edge_type.unwrap(),
                     name: edge.name,
                     from,
@@ -112,10 +212,57 @@ impl Network {
                     priority: edge.priority.unwrap_or_else(|| template.priority),
                     lanes,
                     center_line,
+                    merged_from: Vec::new(),
                 },
             );
         }
 
+        timer.add_result(
+            0.0,
+            format!(
+                "{} edges missing a junction were recovered, {} were dropped",
+                num_recovered, num_dropped
+            ),
+        );
+
+        for connection in raw.connections {
+            network.connections.push(Connection {
+                from: strings.intern(connection.from.0.clone()),
+                from_lane: strings.intern(connection.from_lane().0),
+                to: strings.intern(connection.to.0.clone()),
+                to_lane: strings.intern(connection.to_lane().0),
+                via: connection.via.map(|l| strings.intern(l.0)),
+                dir: connection.dir,
+                tl: connection.tl.map(|tl| strings.intern(tl)),
+                link_index: connection.link_index,
+            });
+        }
+        for tl in raw.traffic_lights {
+            let id: TrafficLightID = strings.intern(tl.id.clone());
+            network.traffic_lights.insert(id, tl);
+        }
+
+        let (table, lookup_bytes) = strings.finish();
+        network.strings = table;
+        let memory = network.id_memory();
+        timer.add_result(
+            0.0,
+            format!(
+                "Interned {} IDs referenced {} times. They take {} bytes, instead of {} as separate \
+                 strings. Dropped a {} byte lookup after parsing.",
+                network.strings.len(),
+                memory.references,
+                memory.interned_bytes,
+                memory.uninterned_bytes,
+                lookup_bytes
+            ),
+        );
+
+        network.filtered = network.apply_filter(filter);
+        if !network.filtered.is_empty() {
+            timer.add_result(0.0, network.filtered.describe());
+        }
+
         network.fix_coordinates();
         network
     }
@@ -151,3 +298,65 @@ impl Network {
         }
     }
 }
+
+/// Combines a lane's `allow` and `disallow` lists. If the lane has neither, they're inherited from
+/// the edge's type. Classes that aren't understood are skipped with a warning.
+fn allowed_classes(
+    lane: &raw::Lane,
+    template: Option<&raw::Type>,
+    warnings: &mut Vec<String>,
+) -> VehicleClassSet {
+    let (allow, disallow) = match template {
+        Some(t) if lane.allow.is_none() && lane.disallow.is_none() => (&t.allow, &t.disallow),
+        _ => (&lane.allow, &lane.disallow),
+    };
+    let (set, unknown) =
+        VehicleClassSet::from_allow_disallow(allow.as_deref(), disallow.as_deref());
+    for class in unknown {
+        warnings.push(format!(
+            "Lane {} mentions unknown vehicle class {}, which was skipped",
+            lane.id, class
+        ));
+    }
+    set
+}
+
+/// Edges missing a junction are snapped to a junction whose center is at most this far from that
+/// end of the edge.
+const SNAP_TOLERANCE: Distance = Distance::const_meters(15.0);
+
+/// Looks up one end of an edge. If the junction isn't specified or doesn't exist (because it was
+/// merged or is outside the kept boundary, for example), guesses the nearest junction to that end
+/// of the edge's geometry. Returns the junction and whether it was guessed, or the reason no
+/// junction could be found.
+fn find_junction(
+    junctions: &BTreeMap<NodeID, Junction>,
+    strings: &mut Interner,
+    id: Option<raw::NodeID>,
+    endpoint: Option<Pt2D>,
+) -> Result<(NodeID, bool), String> {
+    let problem = match id {
+        Some(id) => {
+            let node = strings.intern(id.0.clone());
+            if junctions.contains_key(&node) {
+                return Ok((node, false));
+            }
+            format!("junction {} doesn't exist", id.0)
+        }
+        None => "no junction is specified".to_string(),
+    };
+    let pt =
+        endpoint.ok_or_else(|| format!("{}, and there's no geometry to guess from", problem))?;
+    junctions
+        .values()
+        .map(|j| (j.id, j.pt.dist_to(pt)))
+        .filter(|(_, dist)| *dist <= SNAP_TOLERANCE)
+        .min_by_key(|(_, dist)| *dist)
+        .map(|(id, _)| (id, true))
+        .ok_or_else(|| {
+            format!(
+                "{}, and no junction is within {} of {}",
+                problem, SNAP_TOLERANCE, pt
+            )
+        })
+}
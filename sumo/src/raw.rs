@@ -2,24 +2,23 @@
 //! subset of the structures and fields defined at
 //! <https://sumo.dlr.de/docs/Networks/PlainXML.html> are produced.
 
-use abstutil::Timer;
-use serde::Deserialize;
+use std::collections::BTreeMap;
 
-use geom::{Bounds, Distance, GPSBounds, PolyLine, Polygon, Pt2D, Ring, Speed};
+use quick_xml::events::{BytesStart, Event};
+use serde::Deserialize;
 
-use crate::VehicleClass;
+use abstutil::Timer;
+use geom::{Bounds, Distance, Duration, GPSBounds, PolyLine, Polygon, Pt2D, Ring, Speed};
 
-#[derive(Deserialize)]
 pub struct Network {
     pub location: Location,
-    #[serde(rename = "type")]
     pub types: Vec<Type>,
-    #[serde(rename = "edge")]
     pub edges: Vec<Edge>,
-    #[serde(rename = "junction")]
     pub junctions: Vec<Junction>,
-    #[serde(rename = "connection")]
     pub connections: Vec<Connection>,
+    pub traffic_lights: Vec<TrafficLight>,
+    /// Everything in the file that isn't part of the schema understood here
+    pub unknown: SchemaReport,
 }
 
 #[derive(Deserialize)]
@@ -30,21 +29,351 @@ pub struct Location {
     pub orig_boundary: GPSBounds,
 }
 
+/// How to handle elements and attributes that aren't in the schema understood here. Newer versions
+/// of SUMO add things, so this is how to find out what's being dropped.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ParseMode {
+    /// Collect everything unknown in a `SchemaReport` and keep going
+    Lenient,
+    /// Fail on the first unknown element or attribute
+    Strict,
+}
+
+impl std::default::Default for ParseMode {
+    fn default() -> ParseMode {
+        ParseMode::Lenient
+    }
+}
+
+/// Element and attribute names found in a file that aren't part of the known schema, with how many
+/// times each occurs.
+#[derive(Debug, Default)]
+pub struct SchemaReport {
+    pub unknown_elements: BTreeMap<String, usize>,
+    /// Keyed by (element, attribute)
+    pub unknown_attributes: BTreeMap<(String, String), usize>,
+}
+
+impl SchemaReport {
+    pub fn is_empty(&self) -> bool {
+        self.unknown_elements.is_empty() && self.unknown_attributes.is_empty()
+    }
+
+    /// One line per unknown element or attribute
+    pub fn describe(&self) -> Vec<String> {
+        let mut lines = Vec::new();
+        for (element, cnt) in &self.unknown_elements {
+            lines.push(format!(
+                "unknown element <{}> appears {} times",
+                element, cnt
+            ));
+        }
+        for ((element, attribute), cnt) in &self.unknown_attributes {
+            lines.push(format!(
+                "unknown attribute {} on <{}> appears {} times",
+                attribute, element, cnt
+            ));
+        }
+        lines
+    }
+
+    /// Returns the element's name. Fails in strict mode if it or any of its attributes are unknown.
+    fn check(
+        &mut self,
+        raw: &str,
+        pos: usize,
+        mode: ParseMode,
+        e: &BytesStart,
+    ) -> anyhow::Result<String> {
+        let element = std::str::from_utf8(e.name())?.to_string();
+        let known = match known_attributes(&element) {
+            Some(known) => known,
+            None => {
+                if mode == ParseMode::Strict {
+                    bail!(
+                        "unknown element <{}> at {}",
+                        element,
+                        describe_position(raw, pos)
+                    );
+                }
+                *self.unknown_elements.entry(element.clone()).or_insert(0) += 1;
+                return Ok(element);
+            }
+        };
+        for attr in e.attributes() {
+            let attr = attr.map_err(|err| {
+                anyhow!("bad attribute at {}: {}", describe_position(raw, pos), err)
+            })?;
+            let key = std::str::from_utf8(attr.key)?;
+            if known.contains(&key) {
+                continue;
+            }
+            if mode == ParseMode::Strict {
+                bail!(
+                    "unknown attribute {} on <{}> at {}",
+                    key,
+                    element,
+                    describe_position(raw, pos)
+                );
+            }
+            *self
+                .unknown_attributes
+                .entry((element.clone(), key.to_string()))
+                .or_insert(0) += 1;
+        }
+        Ok(element)
+    }
+}
+
 impl Network {
-    pub fn parse(path: &str, timer: &mut Timer) -> anyhow::Result<Network> {
+    pub fn parse(path: &str, mode: ParseMode, timer: &mut Timer) -> anyhow::Result<Network> {
         timer.start(format!("read {}", path));
         let bytes = abstio::slurp_file(path)?;
         let raw_string = std::str::from_utf8(&bytes)?;
-        let network = quick_xml::de::from_str(raw_string)?;
+        let network = Network::parse_str(raw_string, mode);
         timer.stop(format!("read {}", path));
+        let network = network.map_err(|err| anyhow!("{}: {}", path, err))?;
+
+        for line in network.unknown.describe() {
+            warn!("{}: {}", path, line);
+        }
         Ok(network)
     }
+
+    /// Each child of the root <net> element is deserialized separately, so errors can say where
+    /// in the file they happened.
+    fn parse_str(raw: &str, mode: ParseMode) -> anyhow::Result<Network> {
+        let mut location = None;
+        let mut types = Vec::new();
+        let mut edges = Vec::new();
+        let mut junctions = Vec::new();
+        let mut connections = Vec::new();
+        let mut traffic_lights = Vec::new();
+        let mut unknown = SchemaReport::default();
+
+        let mut reader = quick_xml::Reader::from_str(raw);
+        let mut buf = Vec::new();
+        // How many elements are open
+        let mut depth = 0;
+        // The name and byte offset of the child of <net> currently being read
+        let mut current: Option<(String, usize)> = None;
+        loop {
+            let pos = reader.buffer_position();
+            let opened = match reader.read_event(&mut buf) {
+                Ok(Event::Start(ref e)) => Some((unknown.check(raw, pos, mode, e)?, false)),
+                Ok(Event::Empty(ref e)) => Some((unknown.check(raw, pos, mode, e)?, true)),
+                Ok(Event::End(_)) => None,
+                Ok(Event::Eof) => {
+                    break;
+                }
+                Ok(_) => {
+                    buf.clear();
+                    continue;
+                }
+                Err(err) => {
+                    bail!(
+                        "bad XML at {}: {}",
+                        describe_position(raw, reader.buffer_position()),
+                        err
+                    );
+                }
+            };
+            buf.clear();
+
+            if let Some((name, is_empty)) = opened {
+                if depth == 1 {
+                    current = Some((name, pos));
+                }
+                if !is_empty {
+                    depth += 1;
+                    continue;
+                }
+            } else {
+                depth -= 1;
+            }
+
+            // Did a child of <net> just end?
+            if depth != 1 {
+                continue;
+            }
+            if let Some((element, start)) = current.take() {
+                let xml = &raw[start..reader.buffer_position()];
+                let result: anyhow::Result<()> = match element.as_ref() {
+                    "location" => quick_xml::de::from_str(xml)
+                        .map(|x| {
+                            location = Some(x);
+                        })
+                        .map_err(|err| err.into()),
+                    "type" => deserialize_into(xml, &mut types),
+                    "edge" => deserialize_into(xml, &mut edges),
+                    "junction" => deserialize_into(xml, &mut junctions),
+                    "connection" => deserialize_into(xml, &mut connections),
+                    "tlLogic" => deserialize_into(xml, &mut traffic_lights)
+                        .and_then(|_| traffic_lights.last().unwrap().validate()),
+                    // Known, but not used
+                    _ => Ok(()),
+                };
+                result.map_err(|err| {
+                    anyhow!(
+                        "bad <{}> at {}: {}",
+                        element,
+                        describe_position(raw, start),
+                        err
+                    )
+                })?;
+            }
+        }
+
+        Ok(Network {
+            location: location.ok_or_else(|| anyhow!("no <location> element"))?,
+            types,
+            edges,
+            junctions,
+            connections,
+            traffic_lights,
+            unknown,
+        })
+    }
+}
+
+fn deserialize_into<T: serde::de::DeserializeOwned>(
+    xml: &str,
+    list: &mut Vec<T>,
+) -> anyhow::Result<()> {
+    list.push(quick_xml::de::from_str(xml)?);
+    Ok(())
+}
+
+/// Turns a byte offset into a line and column, counting from 1.
+fn describe_position(raw: &str, offset: usize) -> String {
+    let before = &raw.as_bytes()[..offset.min(raw.len())];
+    let line = before.iter().filter(|b| **b == b'\n').count() + 1;
+    let column = before.iter().rev().take_while(|b| **b != b'\n').count() + 1;
+    format!("line {}, column {}", line, column)
+}
+
+/// Every element and attribute defined by SUMO 1.9's network schema, whether or not it's used
+/// here. See <https://sumo.dlr.de/docs/Networks/SUMO_Road_Networks.html>.
+fn known_attributes(element: &str) -> Option<&'static [&'static str]> {
+    Some(match element {
+        "net" => &[
+            "version",
+            "junctionCornerDetail",
+            "junctionLinkDetail",
+            "limitTurnSpeed",
+            "lefthand",
+            "rectangularLaneCut",
+            "walkingareas",
+            "checkLaneFoesAll",
+            "checkLaneFoesRoundabout",
+            "tlsIgnoreInternalJunctionJam",
+            "avoidOverlap",
+            "higherSpeed",
+            "internalJunctionsVehicleWidth",
+            "xmlns:xsi",
+            "xsi:noNamespaceSchemaLocation",
+        ],
+        "location" => &["netOffset", "convBoundary", "origBoundary", "projParameter"],
+        "type" => &[
+            "id",
+            "priority",
+            "numLanes",
+            "speed",
+            "width",
+            "allow",
+            "disallow",
+            "oneway",
+            "discard",
+            "sidewalkWidth",
+            "bikeLaneWidth",
+            "spreadType",
+        ],
+        "edge" => &[
+            "id",
+            "from",
+            "to",
+            "priority",
+            "function",
+            "type",
+            "spreadType",
+            "shape",
+            "name",
+            "length",
+            "crossingEdges",
+            "bidi",
+            "distance",
+        ],
+        "lane" => &[
+            "id",
+            "index",
+            "speed",
+            "length",
+            "width",
+            "shape",
+            "allow",
+            "disallow",
+            "endOffset",
+            "acceleration",
+            "customShape",
+            "changeLeft",
+            "changeRight",
+        ],
+        "junction" => &[
+            "id",
+            "type",
+            "x",
+            "y",
+            "z",
+            "incLanes",
+            "intLanes",
+            "shape",
+            "radius",
+            "customShape",
+            "rightOfWay",
+            "fringe",
+            "name",
+        ],
+        "request" => &["index", "response", "foes", "cont"],
+        "connection" => &[
+            "from",
+            "to",
+            "fromLane",
+            "toLane",
+            "via",
+            "tl",
+            "linkIndex",
+            "linkIndex2",
+            "dir",
+            "state",
+            "pass",
+            "keepClear",
+            "contPos",
+            "visibility",
+            "speed",
+            "length",
+            "shape",
+            "uncontrolled",
+            "indirect",
+            "changeLeft",
+            "changeRight",
+        ],
+        "tlLogic" => &["id", "type", "programID", "offset"],
+        "phase" => &["duration", "state", "minDur", "maxDur", "name", "next"],
+        "roundabout" => &["nodes", "edges"],
+        "neigh" => &["lane"],
+        "param" => &["key", "value"],
+        "stopOffset" => &["value", "vClasses", "exceptions"],
+        "prohibition" => &["prohibitor", "prohibited"],
+        _ => {
+            return None;
+        }
+    })
 }
 
 #[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Deserialize)]
 pub struct EdgeID(pub String);
 #[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Deserialize)]
-pub struct NodeID(String);
+pub struct NodeID(pub String);
 #[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Deserialize)]
 pub struct LaneID(pub String);
 #[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Deserialize)]
@@ -56,10 +385,9 @@ pub struct Type {
     pub priority: usize,
     pub speed: Speed,
     pub width: Option<Distance>,
-    #[serde(deserialize_with = "parse_list_vehicles", default)]
-    pub allow: Vec<VehicleClass>,
-    #[serde(deserialize_with = "parse_list_vehicles", default)]
-    pub disallow: Vec<VehicleClass>,
+    /// Space-separated vehicle classes, parsed during normalization
+    pub allow: Option<String>,
+    pub disallow: Option<String>,
 }
 
 #[derive(Deserialize)]
@@ -119,10 +447,9 @@ pub struct Lane {
     pub width: Option<Distance>,
     #[serde(deserialize_with = "parse_pl")]
     pub shape: anyhow::Result<PolyLine>,
-    #[serde(deserialize_with = "parse_list_vehicles", default)]
-    pub allow: Vec<VehicleClass>,
-    #[serde(deserialize_with = "parse_list_vehicles", default)]
-    pub disallow: Vec<VehicleClass>,
+    /// Space-separated vehicle classes, parsed during normalization
+    pub allow: Option<String>,
+    pub disallow: Option<String>,
 }
 
 #[derive(Deserialize)]
@@ -159,6 +486,11 @@ pub struct Connection {
     pub to_lane: usize,
     pub via: Option<InternalLaneID>,
     pub dir: Direction,
+    /// The traffic light controlling this connection, if any
+    pub tl: Option<String>,
+    /// Which character of the traffic light's phase states applies to this connection
+    #[serde(rename = "linkIndex")]
+    pub link_index: Option<usize>,
 }
 impl Connection {
     pub fn from_lane(&self) -> LaneID {
@@ -188,6 +520,101 @@ pub enum Direction {
     Invalid,
 }
 
+/// See <https://sumo.dlr.de/docs/Simulation/Traffic_Lights.html#defining_new_tls-programs>
+#[derive(Deserialize)]
+pub struct TrafficLight {
+    pub id: String,
+    #[serde(rename = "type")]
+    pub tl_type: String,
+    #[serde(rename = "programID")]
+    pub program_id: String,
+    #[serde(deserialize_with = "parse_duration", default = "zero_duration")]
+    pub offset: Duration,
+    #[serde(rename = "phase")]
+    pub phases: Vec<Phase>,
+}
+
+#[derive(Deserialize)]
+pub struct Phase {
+    #[serde(deserialize_with = "parse_duration")]
+    pub duration: Duration,
+    /// One character per connection controlled by the light, indexed by the connection's
+    /// `link_index`
+    pub state: String,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LightState {
+    Red,
+    /// Includes SUMO's red+yellow
+    Yellow,
+    /// Includes both priority and non-priority green, and green right-turn arrows
+    Green,
+    Off,
+}
+
+impl TrafficLight {
+    /// Programs without phases, or whose phases take no time, can't be cycled through.
+    fn validate(&self) -> anyhow::Result<()> {
+        if self.phases.is_empty() {
+            bail!("traffic light {} has no phases", self.id);
+        }
+        if let Some(phase) = self.phases.iter().find(|p| p.duration < Duration::ZERO) {
+            bail!(
+                "traffic light {} has a phase with negative duration {}",
+                self.id,
+                phase.duration
+            );
+        }
+        if self.cycle_length() == Duration::ZERO {
+            bail!("traffic light {} has a cycle length of 0", self.id);
+        }
+        Ok(())
+    }
+
+    /// How long it takes to go through every phase once.
+    pub fn cycle_length(&self) -> Duration {
+        self.phases.iter().map(|p| p.duration).sum()
+    }
+
+    /// The phase active at some time since the start of the simulation. Like SUMO, a positive
+    /// offset delays all phases, so the first phase starts at `offset`, and a negative offset
+    /// advances them.
+    pub fn current_phase(&self, time: Duration) -> &Phase {
+        let mut remaining = Duration::seconds(
+            (time - self.offset)
+                .inner_seconds()
+                .rem_euclid(self.cycle_length().inner_seconds()),
+        );
+        for phase in &self.phases {
+            if remaining < phase.duration {
+                return phase;
+            }
+            remaining -= phase.duration;
+        }
+        // Floating point could leave a tiny bit over at the very end of the cycle
+        self.phases.last().unwrap()
+    }
+
+    /// The light shown to one connection at some time since the start of the simulation.
+    pub fn light_at(&self, link_index: usize, time: Duration) -> anyhow::Result<LightState> {
+        let state = &self.current_phase(time).state;
+        match state.chars().nth(link_index) {
+            Some('G') | Some('g') | Some('s') => Ok(LightState::Green),
+            Some('y') | Some('u') => Ok(LightState::Yellow),
+            Some('r') => Ok(LightState::Red),
+            Some('o') | Some('O') => Ok(LightState::Off),
+            Some(x) => bail!("Unknown light state {} for {}", x, self.id),
+            None => bail!(
+                "{} has no link index {}; state is {}",
+                self.id,
+                link_index,
+                state
+            ),
+        }
+    }
+}
+
 fn parse_f64s<'de, D: serde::Deserializer<'de>>(d: D) -> Result<Vec<f64>, D::Error> {
     let raw = <String>::deserialize(d)?;
     let parts: Vec<&str> = raw.split(",").collect();
@@ -198,6 +625,16 @@ fn parse_f64s<'de, D: serde::Deserializer<'de>>(d: D) -> Result<Vec<f64>, D::Err
     Ok(result)
 }
 
+fn parse_duration<'de, D: serde::Deserializer<'de>>(d: D) -> Result<Duration, D::Error> {
+    let raw = <String>::deserialize(d)?;
+    let secs = raw.parse::<f64>().map_err(serde::de::Error::custom)?;
+    Ok(Duration::seconds(secs))
+}
+
+fn zero_duration() -> Duration {
+    Duration::ZERO
+}
+
 fn parse_bounds<'de, D: serde::Deserializer<'de>>(d: D) -> Result<Bounds, D::Error> {
     let nums = parse_f64s(d)?;
     if nums.len() != 4 {
@@ -224,22 +661,6 @@ fn parse_gps_bounds<'de, D: serde::Deserializer<'de>>(d: D) -> Result<GPSBounds,
     })
 }
 
-fn parse_list_vehicles<'de, D: serde::Deserializer<'de>>(
-    d: D,
-) -> Result<Vec<VehicleClass>, D::Error> {
-    let raw = <String>::deserialize(d)?;
-    let mut vehicles = Vec::new();
-    for x in raw.split(" ") {
-        vehicles.push(match x {
-            "pedestrian" => VehicleClass::Pedestrian,
-            "bicycle" => VehicleClass::Bicycle,
-            "rail_urban" => VehicleClass::RailUrban,
-            other => VehicleClass::Other(other.to_string()),
-        });
-    }
-    Ok(vehicles)
-}
-
 fn parse_list_lanes<'de, D: serde::Deserializer<'de>>(d: D) -> Result<Vec<LaneID>, D::Error> {
     let raw = <String>::deserialize(d)?;
     let parts: Vec<LaneID> = raw.split(" ").map(|x| LaneID(x.to_string())).collect();
@@ -298,3 +719,72 @@ fn parse_pt(pt: &str) -> anyhow::Result<Pt2D> {
     }
     Ok(Pt2D::new(parts[0], parts[1]))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn light(offset: f64, phases: Vec<(f64, &str)>) -> TrafficLight {
+        TrafficLight {
+            id: "tl".to_string(),
+            tl_type: "static".to_string(),
+            program_id: "0".to_string(),
+            offset: Duration::seconds(offset),
+            phases: phases
+                .into_iter()
+                .map(|(duration, state)| Phase {
+                    duration: Duration::seconds(duration),
+                    state: state.to_string(),
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_light_at() {
+        let tl = light(0.0, vec![(30.0, "Gr"), (5.0, "yr"), (25.0, "rG")]);
+        let at = |idx, secs| tl.light_at(idx, Duration::seconds(secs)).unwrap();
+
+        assert_eq!(at(0, 0.0), LightState::Green);
+        assert_eq!(at(1, 0.0), LightState::Red);
+        assert_eq!(at(0, 29.9), LightState::Green);
+        assert_eq!(at(0, 30.0), LightState::Yellow);
+        assert_eq!(at(0, 35.0), LightState::Red);
+        assert_eq!(at(1, 35.0), LightState::Green);
+        // The cycle repeats
+        assert_eq!(at(0, 60.0), LightState::Green);
+        assert_eq!(at(0, 92.0), LightState::Yellow);
+
+        assert!(tl.light_at(2, Duration::ZERO).is_err());
+    }
+
+    #[test]
+    fn test_offset() {
+        // A positive offset delays every phase
+        let tl = light(10.0, vec![(30.0, "G"), (30.0, "r")]);
+        let at = |secs| tl.light_at(0, Duration::seconds(secs)).unwrap();
+        assert_eq!(at(0.0), LightState::Red);
+        assert_eq!(at(9.9), LightState::Red);
+        assert_eq!(at(10.0), LightState::Green);
+        assert_eq!(at(40.0), LightState::Red);
+
+        // And a negative one advances them
+        let tl = light(-10.0, vec![(30.0, "G"), (30.0, "r")]);
+        let at = |secs| tl.light_at(0, Duration::seconds(secs)).unwrap();
+        assert_eq!(at(0.0), LightState::Green);
+        assert_eq!(at(20.0), LightState::Red);
+        assert_eq!(at(50.0), LightState::Green);
+    }
+
+    #[test]
+    fn test_reject_empty_cycles() {
+        assert!(light(0.0, vec![(30.0, "G"), (30.0, "r")])
+            .validate()
+            .is_ok());
+        assert!(light(0.0, Vec::new()).validate().is_err());
+        assert!(light(0.0, vec![(0.0, "G"), (0.0, "r")]).validate().is_err());
+        assert!(light(0.0, vec![(-5.0, "G"), (5.0, "r")])
+            .validate()
+            .is_err());
+    }
+}
@@ -0,0 +1,306 @@
+//! Optional passes that simplify a normalized `Network`.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use geom::PolyLine;
+
+use crate::{Edge, EdgeID, InternalLaneID, Lane, LaneID, Network, NodeID, SumoID};
+
+/// Only these junctions can be geometry nodes. Anything else, like traffic lights or all-way stops,
+/// is a real intersection, even with only one road passing through.
+const MERGEABLE_TYPES: [&str; 2] = ["priority", "unregulated"];
+
+/// For each edge, the edges it connects to, and the pairs of lanes connected
+type Links = BTreeMap<EdgeID, BTreeMap<EdgeID, BTreeSet<(LaneID, LaneID)>>>;
+
+impl Network {
+    /// netconvert often splits what's logically one road into many short edges at geometry
+    /// nodes. This collapses junctions where edges just continue into each other, in one or both
+    /// directions. Edges are only merged when they have the same type, name, priority, and lanes,
+    /// every lane continues straight into the matching lane, and no signal controls the junction.
+    ///
+    /// The merged edge keeps the ID of the first edge and lists all of the original edges in
+    /// `merged_from`. Connections, and the incoming lanes of junctions, are remapped to refer to
+    /// the merged edges and lanes. Returns the number of edges merged away.
+    pub fn merge_degenerate_junctions(&mut self) -> usize {
+        let mut incoming: BTreeMap<NodeID, Vec<EdgeID>> = BTreeMap::new();
+        let mut outgoing: BTreeMap<NodeID, Vec<EdgeID>> = BTreeMap::new();
+        for edge in self.normal_edges.values() {
+            incoming
+                .entry(edge.to)
+                .or_insert_with(Vec::new)
+                .push(edge.id);
+            outgoing
+                .entry(edge.from)
+                .or_insert_with(Vec::new)
+                .push(edge.id);
+        }
+
+        let mut links: Links = BTreeMap::new();
+        let mut signalled: BTreeSet<NodeID> = BTreeSet::new();
+        // Where each connection happens, before anything changes. Connections starting from an
+        // internal lane don't have a normal edge to look up.
+        let mut connection_junctions: Vec<Option<NodeID>> = Vec::new();
+        for c in &self.connections {
+            let junction = self.normal_edges.get(&c.from).map(|e| e.to);
+            if let Some(j) = junction {
+                if c.tl.is_some() {
+                    signalled.insert(j);
+                }
+                links
+                    .entry(c.from)
+                    .or_insert_with(BTreeMap::new)
+                    .entry(c.to)
+                    .or_insert_with(BTreeSet::new)
+                    .insert((c.from_lane, c.to_lane));
+            }
+            connection_junctions.push(junction);
+        }
+
+        // Merged-away edge or lane -> the one it was merged into
+        let mut edge_remap: BTreeMap<EdgeID, EdgeID> = BTreeMap::new();
+        let mut lane_remap: BTreeMap<LaneID, LaneID> = BTreeMap::new();
+        let mut removed_junctions: BTreeSet<NodeID> = BTreeSet::new();
+        let mut removed_internal_lanes: BTreeSet<InternalLaneID> = BTreeSet::new();
+
+        let junctions: Vec<NodeID> = self.junctions.keys().cloned().collect();
+        for j in junctions {
+            if signalled.contains(&j)
+                || !MERGEABLE_TYPES.contains(&self.junctions[&j].junction_type.as_str())
+            {
+                continue;
+            }
+            let pairs = match self.through_pairs(j, &incoming, &outgoing) {
+                Some(pairs) => pairs,
+                None => continue,
+            };
+            // Check every pair before changing anything, so the junction is either fully merged
+            // or untouched.
+            let geometry: Option<Vec<(PolyLine, Vec<PolyLine>)>> = pairs
+                .iter()
+                .map(|(a, b)| {
+                    let (a, b) = (&self.normal_edges[a], &self.normal_edges[b]);
+                    if compatible(a, b, &links) {
+                        merged_geometry(a, b)
+                    } else {
+                        None
+                    }
+                })
+                .collect();
+            let geometry = match geometry {
+                Some(geometry) => geometry,
+                None => continue,
+            };
+
+            for ((a, b), (center_line, lane_center_lines)) in pairs.into_iter().zip(geometry) {
+                let edge_a = self.normal_edges.remove(&a).unwrap();
+                let edge_b = self.normal_edges.remove(&b).unwrap();
+
+                edge_remap.insert(b, a);
+                for (lane_a, lane_b) in edge_a.lanes.iter().zip(edge_b.lanes.iter()) {
+                    lane_remap.insert(lane_b.id, lane_a.id);
+                }
+                // Wherever b went next, a does now
+                if let Some(mut next) = links.remove(&b) {
+                    for lanes in next.values_mut() {
+                        *lanes = lanes
+                            .iter()
+                            .map(|(from, to)| (lane_remap[from], *to))
+                            .collect();
+                    }
+                    links.insert(a, next);
+                } else {
+                    links.remove(&a);
+                }
+                for e in incoming.get_mut(&edge_b.to).unwrap() {
+                    if *e == b {
+                        *e = a;
+                    }
+                }
+
+                let merged = merge_edges(edge_a, edge_b, center_line, lane_center_lines);
+                self.normal_edges.insert(a, merged);
+            }
+
+            incoming.remove(&j);
+            outgoing.remove(&j);
+            let junction = self.junctions.remove(&j).unwrap();
+            removed_internal_lanes.extend(junction.internal_lanes);
+            removed_junctions.insert(j);
+        }
+
+        if edge_remap.is_empty() {
+            return 0;
+        }
+
+        // Connections across a removed junction don't exist anymore. Everything else has to refer
+        // to the merged edges and lanes.
+        let mut connections = Vec::new();
+        for (mut c, junction) in std::mem::take(&mut self.connections)
+            .into_iter()
+            .zip(connection_junctions)
+        {
+            // All kinds of IDs share one StringTable, so a lane ID can be checked against the
+            // internal lanes.
+            let from_internal = InternalLaneID::from_index(c.from_lane.index());
+            if junction
+                .map(|j| removed_junctions.contains(&j))
+                .unwrap_or(false)
+                || removed_internal_lanes.contains(&from_internal)
+                || c.via
+                    .map(|l| removed_internal_lanes.contains(&l))
+                    .unwrap_or(false)
+            {
+                continue;
+            }
+            c.from = resolve(&edge_remap, c.from);
+            c.to = resolve(&edge_remap, c.to);
+            c.from_lane = resolve(&lane_remap, c.from_lane);
+            c.to_lane = resolve(&lane_remap, c.to_lane);
+            connections.push(c);
+        }
+        self.connections = connections;
+
+        self.internal_edges.retain(|_, e| {
+            !e.lanes
+                .iter()
+                .all(|l| removed_internal_lanes.contains(&l.id))
+        });
+        for junction in self.junctions.values_mut() {
+            for l in &mut junction.incoming_lanes {
+                *l = resolve(&lane_remap, *l);
+            }
+        }
+
+        edge_remap.len()
+    }
+
+    /// If a junction just continues one or two edges, returns each incoming edge paired with the
+    /// outgoing edge it continues into.
+    fn through_pairs(
+        &self,
+        j: NodeID,
+        incoming: &BTreeMap<NodeID, Vec<EdgeID>>,
+        outgoing: &BTreeMap<NodeID, Vec<EdgeID>>,
+    ) -> Option<Vec<(EdgeID, EdgeID)>> {
+        let ins = incoming.get(&j)?;
+        let outs = outgoing.get(&j)?;
+        if ins.len() != outs.len() || ins.len() > 2 {
+            return None;
+        }
+        let mut pairs = Vec::new();
+        for a in ins {
+            // Continue into whichever edge doesn't just turn around
+            let from = self.normal_edges[a].from;
+            let mut candidates = outs.iter().filter(|b| self.normal_edges[*b].to != from);
+            let b = candidates.next()?;
+            if candidates.next().is_some() {
+                return None;
+            }
+            pairs.push((*a, *b));
+        }
+        if pairs.len() == 2 && pairs[0].1 == pairs[1].1 {
+            return None;
+        }
+        Some(pairs)
+    }
+}
+
+/// Does `a` continue into `b` without any change besides geometry?
+fn compatible(a: &Edge, b: &Edge, links: &Links) -> bool {
+    if a.edge_type != b.edge_type
+        || a.name != b.name
+        || a.priority != b.priority
+        || a.lanes.len() != b.lanes.len()
+    {
+        return false;
+    }
+    for (lane_a, lane_b) in a.lanes.iter().zip(b.lanes.iter()) {
+        if lane_a.index != lane_b.index
+            || lane_a.speed != lane_b.speed
+            || lane_a.allow != lane_b.allow
+        {
+            return false;
+        }
+    }
+    // Every lane has to continue straight into the matching lane, and nothing else
+    let expected: BTreeSet<(LaneID, LaneID)> = a
+        .lanes
+        .iter()
+        .zip(b.lanes.iter())
+        .map(|(lane_a, lane_b)| (lane_a.id, lane_b.id))
+        .collect();
+    links.get(&a.id).and_then(|next| next.get(&b.id)) == Some(&expected)
+}
+
+/// The center line of the merged edge and each of its lanes, or None if the geometry can't be
+/// joined.
+fn merged_geometry(a: &Edge, b: &Edge) -> Option<(PolyLine, Vec<PolyLine>)> {
+    let center_line = join(&a.center_line, &b.center_line)?;
+    let mut lanes = Vec::new();
+    for (lane_a, lane_b) in a.lanes.iter().zip(b.lanes.iter()) {
+        lanes.push(join(&lane_a.center_line, &lane_b.center_line)?);
+    }
+    Some((center_line, lanes))
+}
+
+fn merge_edges(a: Edge, b: Edge, center_line: PolyLine, lane_center_lines: Vec<PolyLine>) -> Edge {
+    let mut merged_from = if a.merged_from.is_empty() {
+        vec![a.id]
+    } else {
+        a.merged_from
+    };
+    if b.merged_from.is_empty() {
+        merged_from.push(b.id);
+    } else {
+        merged_from.extend(b.merged_from);
+    }
+
+    let lanes = a
+        .lanes
+        .into_iter()
+        .zip(b.lanes.into_iter())
+        .zip(lane_center_lines)
+        .map(|((lane_a, lane_b), pl)| Lane {
+            id: lane_a.id,
+            index: lane_a.index,
+            speed: lane_a.speed,
+            // The lanes end at the edge of the junction, so count the gap crossing it
+            length: lane_a.length
+                + lane_a
+                    .center_line
+                    .last_pt()
+                    .dist_to(lane_b.center_line.first_pt())
+                + lane_b.length,
+            width: lane_a.width,
+            center_line: pl,
+            allow: lane_a.allow,
+        })
+        .collect();
+
+    Edge {
+        id: a.id,
+        edge_type: a.edge_type,
+        name: a.name,
+        from: a.from,
+        to: b.to,
+        priority: a.priority,
+        lanes,
+        center_line,
+        merged_from,
+    }
+}
+
+fn join(pl1: &PolyLine, pl2: &PolyLine) -> Option<PolyLine> {
+    let mut pts = pl1.points().clone();
+    pts.extend(pl2.points().iter().cloned());
+    PolyLine::deduping_new(pts).ok()
+}
+
+/// Follows a chain of merges to the surviving ID
+fn resolve<I: SumoID + Ord>(remap: &BTreeMap<I, I>, mut id: I) -> I {
+    while let Some(next) = remap.get(&id) {
+        id = *next;
+    }
+    id
+}
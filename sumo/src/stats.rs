@@ -0,0 +1,253 @@
+//! Summarizes a `Network`, to sanity check that re-running netconvert or an exporter didn't
+//! silently drop part of it.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use geom::{Bounds, Distance};
+
+use crate::Network;
+
+/// Counts describing a whole network. Compare two of these with `NetworkStats::diff`.
+pub struct NetworkStats {
+    pub num_edges: usize,
+    pub num_lanes: usize,
+    /// How many lanes allow each vehicle class. Lanes that don't restrict anything are counted as
+    /// "any", and lanes allowing several classes are counted once for each.
+    pub lanes_per_class: BTreeMap<String, usize>,
+    /// The length of every lane on normal edges, added up
+    pub total_lane_length: Distance,
+    pub junctions_per_type: BTreeMap<String, usize>,
+    /// How many lanes have each speed limit, rounded to the nearest km/h
+    pub lanes_per_speed_limit: BTreeMap<usize, usize>,
+    /// The size of the box covering every junction and lane
+    pub width: Distance,
+    pub height: Distance,
+
+    edge_ids: BTreeSet<String>,
+    junction_ids: BTreeSet<String>,
+}
+
+/// How much memory the network's IDs take, compared to storing a separate string everywhere one
+/// is referenced, like before they were interned.
+pub struct IdMemory {
+    /// How many times any ID is referenced from edges, lanes, junctions, connections, and traffic
+    /// lights
+    pub references: usize,
+    /// The `StringTable`, plus the IDs themselves
+    pub interned_bytes: usize,
+    /// A `String` per reference instead
+    pub uninterned_bytes: usize,
+}
+
+/// The differences between two networks.
+pub struct NetworkDiff {
+    /// Every statistic from either network, as (name, before, after, flagged). A statistic is
+    /// flagged if it changed by more than the threshold.
+    pub rows: Vec<(String, f64, f64, bool)>,
+    pub removed_edges: Vec<String>,
+    pub added_edges: Vec<String>,
+    pub removed_junctions: Vec<String>,
+    pub added_junctions: Vec<String>,
+}
+
+impl Network {
+    /// Summarizes the network.
+    pub fn stats(&self) -> NetworkStats {
+        let mut num_lanes = 0;
+        let mut lanes_per_class = BTreeMap::new();
+        let mut total_lane_length = Distance::ZERO;
+        let mut lanes_per_speed_limit = BTreeMap::new();
+        let mut bounds = Bounds::new();
+        for edge in self.normal_edges.values() {
+            for lane in &edge.lanes {
+                num_lanes += 1;
+                if lane.allow.is_all() {
+                    *lanes_per_class.entry("any".to_string()).or_insert(0) += 1;
+                } else {
+                    for class in lane.allow.iter() {
+                        *lanes_per_class.entry(class.to_string()).or_insert(0) += 1;
+                    }
+                }
+                total_lane_length += lane.length;
+                let kmph = (lane.speed.inner_meters_per_second() * 3.6).round() as usize;
+                *lanes_per_speed_limit.entry(kmph).or_insert(0) += 1;
+                bounds.union(lane.center_line.get_bounds());
+            }
+        }
+
+        let mut junctions_per_type = BTreeMap::new();
+        for junction in self.junctions.values() {
+            *junctions_per_type
+                .entry(junction.junction_type.clone())
+                .or_insert(0) += 1;
+            bounds.union(junction.shape.get_bounds());
+        }
+
+        let (width, height) = if self.normal_edges.is_empty() && self.junctions.is_empty() {
+            (Distance::ZERO, Distance::ZERO)
+        } else {
+            (
+                Distance::meters(bounds.width()),
+                Distance::meters(bounds.height()),
+            )
+        };
+
+        NetworkStats {
+            num_edges: self.normal_edges.len(),
+            num_lanes,
+            lanes_per_class,
+            total_lane_length,
+            junctions_per_type,
+            lanes_per_speed_limit,
+            width,
+            height,
+
+            edge_ids: self
+                .normal_edges
+                .keys()
+                .map(|id| self.strings.get(*id).to_string())
+                .collect(),
+            junction_ids: self
+                .junctions
+                .keys()
+                .map(|id| self.strings.get(*id).to_string())
+                .collect(),
+        }
+    }
+}
+
+impl Network {
+    /// Measures how much memory interning IDs saves.
+    pub fn id_memory(&self) -> IdMemory {
+        let mut references = 0;
+        let mut uninterned_bytes = 0;
+        let mut add = |id: &str| {
+            references += 1;
+            uninterned_bytes += std::mem::size_of::<String>() + id.len();
+        };
+        for edge in self.normal_edges.values() {
+            add(self.strings.get(edge.id));
+            add(self.strings.get(edge.from));
+            add(self.strings.get(edge.to));
+            for lane in &edge.lanes {
+                add(self.strings.get(lane.id));
+            }
+            for id in &edge.merged_from {
+                add(self.strings.get(*id));
+            }
+        }
+        for edge in self.internal_edges.values() {
+            add(self.strings.get(edge.id));
+            for lane in &edge.lanes {
+                add(self.strings.get(lane.id));
+            }
+        }
+        for junction in self.junctions.values() {
+            add(self.strings.get(junction.id));
+            for id in &junction.incoming_lanes {
+                add(self.strings.get(*id));
+            }
+            for id in &junction.internal_lanes {
+                add(self.strings.get(*id));
+            }
+        }
+        for c in &self.connections {
+            add(self.strings.get(c.from));
+            add(self.strings.get(c.from_lane));
+            add(self.strings.get(c.to));
+            add(self.strings.get(c.to_lane));
+            if let Some(id) = c.via {
+                add(self.strings.get(id));
+            }
+            if let Some(id) = c.tl {
+                add(self.strings.get(id));
+            }
+        }
+        for id in self.traffic_lights.keys() {
+            add(self.strings.get(*id));
+        }
+
+        IdMemory {
+            references,
+            interned_bytes: self.strings.memory_bytes() + references * std::mem::size_of::<u32>(),
+            uninterned_bytes,
+        }
+    }
+}
+
+impl NetworkStats {
+    /// Every statistic as a (name, value) pair, in a fixed order.
+    pub fn rows(&self) -> Vec<(String, f64)> {
+        let mut rows = vec![
+            ("edges".to_string(), self.num_edges as f64),
+            ("lanes".to_string(), self.num_lanes as f64),
+        ];
+        for (class, cnt) in &self.lanes_per_class {
+            rows.push((format!("lanes allowing {}", class), *cnt as f64));
+        }
+        rows.push((
+            "lane-kilometers".to_string(),
+            self.total_lane_length.inner_meters() / 1000.0,
+        ));
+        rows.push(("junctions".to_string(), self.junction_ids.len() as f64));
+        for (junction_type, cnt) in &self.junctions_per_type {
+            rows.push((format!("{} junctions", junction_type), *cnt as f64));
+        }
+        for (kmph, cnt) in &self.lanes_per_speed_limit {
+            rows.push((format!("lanes at {} km/h", kmph), *cnt as f64));
+        }
+        rows.push(("width (m)".to_string(), self.width.inner_meters()));
+        rows.push(("height (m)".to_string(), self.height.inner_meters()));
+        rows
+    }
+
+    /// Compares against a later version of the network. `threshold` is the fraction a statistic
+    /// has to change by to be flagged; anything appearing or disappearing entirely is always
+    /// flagged.
+    pub fn diff(&self, after: &NetworkStats, threshold: f64) -> NetworkDiff {
+        let before_rows = self.rows();
+        let after_rows = after.rows();
+        let lookup = |rows: &[(String, f64)], name: &str| {
+            rows.iter()
+                .find(|(n, _)| n == name)
+                .map(|(_, value)| *value)
+                .unwrap_or(0.0)
+        };
+
+        // Keep the order of the first network, then add anything only in the second
+        let mut names: Vec<String> = before_rows.iter().map(|(n, _)| n.clone()).collect();
+        for (name, _) in &after_rows {
+            if !names.contains(name) {
+                names.push(name.clone());
+            }
+        }
+
+        let mut rows = Vec::new();
+        for name in names {
+            let before = lookup(&before_rows, &name);
+            let after = lookup(&after_rows, &name);
+            let flagged = if before == 0.0 {
+                after != 0.0
+            } else {
+                ((after - before) / before).abs() > threshold
+            };
+            rows.push((name, before, after, flagged));
+        }
+
+        NetworkDiff {
+            rows,
+            removed_edges: self.edge_ids.difference(&after.edge_ids).cloned().collect(),
+            added_edges: after.edge_ids.difference(&self.edge_ids).cloned().collect(),
+            removed_junctions: self
+                .junction_ids
+                .difference(&after.junction_ids)
+                .cloned()
+                .collect(),
+            added_junctions: after
+                .junction_ids
+                .difference(&self.junction_ids)
+                .cloned()
+                .collect(),
+        }
+    }
+}
@@ -0,0 +1,329 @@
+use std::fmt;
+use std::str::FromStr;
+
+use anyhow::Result;
+
+/// One of SUMO's [abstract vehicle
+/// classes](https://sumo.dlr.de/docs/Definition_of_Vehicles,_Vehicle_Types,_and_Routes.html#abstract_vehicle_class).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum VehicleClass {
+    Ignoring,
+    Private,
+    Emergency,
+    Authority,
+    Army,
+    Vip,
+    Pedestrian,
+    Passenger,
+    Hov,
+    Taxi,
+    Bus,
+    Coach,
+    Delivery,
+    Truck,
+    Trailer,
+    Motorcycle,
+    Moped,
+    Bicycle,
+    Evehicle,
+    Tram,
+    RailUrban,
+    Rail,
+    RailElectric,
+    RailFast,
+    Ship,
+    Custom1,
+    Custom2,
+}
+
+impl VehicleClass {
+    /// Every class, in the order SUMO documents them
+    pub const ALL: [VehicleClass; 27] = [
+        VehicleClass::Ignoring,
+        VehicleClass::Private,
+        VehicleClass::Emergency,
+        VehicleClass::Authority,
+        VehicleClass::Army,
+        VehicleClass::Vip,
+        VehicleClass::Pedestrian,
+        VehicleClass::Passenger,
+        VehicleClass::Hov,
+        VehicleClass::Taxi,
+        VehicleClass::Bus,
+        VehicleClass::Coach,
+        VehicleClass::Delivery,
+        VehicleClass::Truck,
+        VehicleClass::Trailer,
+        VehicleClass::Motorcycle,
+        VehicleClass::Moped,
+        VehicleClass::Bicycle,
+        VehicleClass::Evehicle,
+        VehicleClass::Tram,
+        VehicleClass::RailUrban,
+        VehicleClass::Rail,
+        VehicleClass::RailElectric,
+        VehicleClass::RailFast,
+        VehicleClass::Ship,
+        VehicleClass::Custom1,
+        VehicleClass::Custom2,
+    ];
+
+    /// The name used in SUMO files
+    pub fn name(self) -> &'static str {
+        match self {
+            VehicleClass::Ignoring => "ignoring",
+            VehicleClass::Private => "private",
+            VehicleClass::Emergency => "emergency",
+            VehicleClass::Authority => "authority",
+            VehicleClass::Army => "army",
+            VehicleClass::Vip => "vip",
+            VehicleClass::Pedestrian => "pedestrian",
+            VehicleClass::Passenger => "passenger",
+            VehicleClass::Hov => "hov",
+            VehicleClass::Taxi => "taxi",
+            VehicleClass::Bus => "bus",
+            VehicleClass::Coach => "coach",
+            VehicleClass::Delivery => "delivery",
+            VehicleClass::Truck => "truck",
+            VehicleClass::Trailer => "trailer",
+            VehicleClass::Motorcycle => "motorcycle",
+            VehicleClass::Moped => "moped",
+            VehicleClass::Bicycle => "bicycle",
+            VehicleClass::Evehicle => "evehicle",
+            VehicleClass::Tram => "tram",
+            VehicleClass::RailUrban => "rail_urban",
+            VehicleClass::Rail => "rail",
+            VehicleClass::RailElectric => "rail_electric",
+            VehicleClass::RailFast => "rail_fast",
+            VehicleClass::Ship => "ship",
+            VehicleClass::Custom1 => "custom1",
+            VehicleClass::Custom2 => "custom2",
+        }
+    }
+
+    fn bit(self) -> u32 {
+        1 << (self as u32)
+    }
+}
+
+impl fmt::Display for VehicleClass {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FromStr for VehicleClass {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<VehicleClass> {
+        VehicleClass::ALL
+            .iter()
+            .find(|c| c.name() == s)
+            .cloned()
+            .ok_or_else(|| anyhow!("unknown vehicle class {}", s))
+    }
+}
+
+/// Which vehicle classes may use a lane, as one bit per class. Checking membership is a single
+/// mask, cheap enough for routing and conversion loops.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct VehicleClassSet(u32);
+
+impl VehicleClassSet {
+    pub fn all() -> VehicleClassSet {
+        VehicleClassSet((1 << VehicleClass::ALL.len()) - 1)
+    }
+
+    pub fn none() -> VehicleClassSet {
+        VehicleClassSet(0)
+    }
+
+    /// Combines a lane or type's `allow` and `disallow` attributes, which are space-separated
+    /// lists of classes. Only one is meant to be specified; without either, everything is
+    /// allowed. Classes that aren't understood are skipped and returned separately, so one typo
+    /// doesn't change what happens to every other class.
+    pub fn from_allow_disallow(
+        allow: Option<&str>,
+        disallow: Option<&str>,
+    ) -> (VehicleClassSet, Vec<String>) {
+        let (mut set, mut unknown) = match allow {
+            Some(x) => VehicleClassSet::parse_lenient(x),
+            None => (VehicleClassSet::all(), Vec::new()),
+        };
+        if let Some(x) = disallow {
+            let (disallowed, more_unknown) = VehicleClassSet::parse_lenient(x);
+            set = set.difference(disallowed);
+            unknown.extend(more_unknown);
+        }
+        (set, unknown)
+    }
+
+    /// Parses a space-separated list of classes like `from_str`, but skips anything that isn't
+    /// understood, returning it separately.
+    pub fn parse_lenient(s: &str) -> (VehicleClassSet, Vec<String>) {
+        let mut set = VehicleClassSet::none();
+        let mut unknown = Vec::new();
+        for x in s.split_whitespace() {
+            match x {
+                "all" => {
+                    set = VehicleClassSet::all();
+                }
+                "none" => {}
+                _ => match x.parse() {
+                    Ok(class) => set.insert(class),
+                    Err(_) => unknown.push(x.to_string()),
+                },
+            }
+        }
+        (set, unknown)
+    }
+
+    pub fn contains(self, class: VehicleClass) -> bool {
+        self.0 & class.bit() != 0
+    }
+
+    /// Does any class belong to both sets?
+    pub fn intersects(self, other: VehicleClassSet) -> bool {
+        self.0 & other.0 != 0
+    }
+
+    pub fn insert(&mut self, class: VehicleClass) {
+        self.0 |= class.bit();
+    }
+
+    pub fn remove(&mut self, class: VehicleClass) {
+        self.0 &= !class.bit();
+    }
+
+    pub fn difference(self, other: VehicleClassSet) -> VehicleClassSet {
+        VehicleClassSet(self.0 & !other.0)
+    }
+
+    /// Every class not in this set. Displaying this produces the equivalent `disallow` list.
+    pub fn complement(self) -> VehicleClassSet {
+        VehicleClassSet::all().difference(self)
+    }
+
+    pub fn is_all(self) -> bool {
+        self == VehicleClassSet::all()
+    }
+
+    pub fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    pub fn len(self) -> usize {
+        self.0.count_ones() as usize
+    }
+
+    /// The classes in this set, in the order SUMO documents them
+    pub fn iter(self) -> impl Iterator<Item = VehicleClass> {
+        VehicleClass::ALL
+            .iter()
+            .cloned()
+            .filter(move |c| self.contains(*c))
+    }
+}
+
+impl From<VehicleClass> for VehicleClassSet {
+    fn from(class: VehicleClass) -> VehicleClassSet {
+        VehicleClassSet(class.bit())
+    }
+}
+
+/// The `allow` list for this set, or "all" or "none".
+impl fmt::Display for VehicleClassSet {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_all() {
+            return write!(f, "all");
+        }
+        if self.is_empty() {
+            return write!(f, "none");
+        }
+        let names: Vec<&str> = self.iter().map(|c| c.name()).collect();
+        write!(f, "{}", names.join(" "))
+    }
+}
+
+/// Parses a space-separated list of classes, as used by `allow` and `disallow`. "all" and "none"
+/// may appear too.
+impl FromStr for VehicleClassSet {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<VehicleClassSet> {
+        let (set, unknown) = VehicleClassSet::parse_lenient(s);
+        if !unknown.is_empty() {
+            bail!("unknown vehicle classes {}", unknown.join(", "));
+        }
+        Ok(set)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{Rng, SeedableRng};
+    use rand_xorshift::XorShiftRng;
+
+    use super::*;
+
+    /// Sets should display as the names SUMO uses, and parsing that again should give the same
+    /// set.
+    #[test]
+    fn test_round_trip() {
+        let mut sets = vec![VehicleClassSet::all(), VehicleClassSet::none()];
+        for class in &VehicleClass::ALL {
+            sets.push(VehicleClassSet::from(*class));
+            sets.push(VehicleClassSet::from(*class).complement());
+        }
+        let mut rng = XorShiftRng::seed_from_u64(42);
+        for _ in 0..100 {
+            let mut set = VehicleClassSet::none();
+            for class in &VehicleClass::ALL {
+                if rng.gen_bool(0.5) {
+                    set.insert(*class);
+                }
+            }
+            sets.push(set);
+        }
+        for set in sets {
+            let displayed = set.to_string();
+            let parsed: VehicleClassSet = displayed.parse().unwrap();
+            assert_eq!(parsed, set);
+            assert_eq!(parsed.to_string(), displayed);
+        }
+    }
+
+    #[test]
+    fn test_allow_disallow() {
+        let (set, unknown) = VehicleClassSet::from_allow_disallow(None, Some("bicycle pedestrian"));
+        assert!(unknown.is_empty());
+        assert!(!set.contains(VehicleClass::Pedestrian));
+        assert!(set.contains(VehicleClass::Bus));
+        assert_eq!(set.complement().to_string(), "pedestrian bicycle");
+
+        let buses: VehicleClassSet = "bus coach".parse().unwrap();
+        assert!(set.intersects(buses));
+        assert!(!VehicleClassSet::none().intersects(buses));
+    }
+
+    #[test]
+    fn test_unknown_classes() {
+        assert!("hovercraft".parse::<VehicleClassSet>().is_err());
+
+        // Only the unknown class is skipped; the lane doesn't open up to everything
+        let (set, unknown) = VehicleClassSet::from_allow_disallow(Some("bus hovercraft"), None);
+        assert_eq!(set, VehicleClassSet::from(VehicleClass::Bus));
+        assert_eq!(unknown, vec!["hovercraft".to_string()]);
+
+        let (set, unknown) =
+            VehicleClassSet::from_allow_disallow(None, Some("pedestrian jetpack hovercraft"));
+        assert_eq!(
+            set,
+            VehicleClassSet::from(VehicleClass::Pedestrian).complement()
+        );
+        assert_eq!(
+            unknown,
+            vec!["jetpack".to_string(), "hovercraft".to_string()]
+        );
+    }
+}
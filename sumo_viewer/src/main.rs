@@ -0,0 +1,35 @@
+//! A standalone viewer for SUMO networks, to check what the normalizer made of a .net.xml file
+//! before converting it. Click a lane or junction to inspect it, and scrub through traffic signal
+//! programs.
+//!
+//! `cargo run --bin sumo_viewer montlake.net.xml`
+
+use abstutil::{CmdArgs, Timer};
+use widgetry::SharedAppState;
+
+use sumo::Network;
+
+mod viewer;
+
+pub struct App {
+    network: Network,
+    path: String,
+}
+
+impl SharedAppState for App {}
+
+fn main() {
+    let mut args = CmdArgs::new();
+    let path = args.required_free();
+    args.done();
+
+    let network = Network::load(&path, &mut Timer::new("load SUMO network")).unwrap();
+    widgetry::run(
+        widgetry::Settings::new("SUMO network viewer").read_svg(Box::new(abstio::slurp_bytes)),
+        |ctx| {
+            let app = App { network, path };
+            let state = viewer::Viewer::new(ctx, &app);
+            (app, vec![state])
+        },
+    );
+}
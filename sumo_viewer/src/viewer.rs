@@ -0,0 +1,476 @@
+use std::collections::BTreeMap;
+
+use aabb_quadtree::QuadTree;
+
+use geom::{ArrowCap, Bounds, Circle, Distance, Duration, Percent, PolyLine, Polygon, UnitFmt};
+use sumo::{EdgeID, LightState, NodeID, VehicleClass, VehicleClassSet};
+use widgetry::{
+    Color, Drawable, EventCtx, GeomBatch, GfxCtx, HorizontalAlignment, Line, Outcome, Panel,
+    Slider, State, StyledButtons, Text, TextExt, Transition, VerticalAlignment, Widget,
+};
+
+use crate::App;
+
+// SUMO networks are always metric
+const UNITS: UnitFmt = UnitFmt {
+    round_durations: false,
+    metric: true,
+};
+
+const JUNCTION: Color = Color::grey(0.6);
+const INTERNAL_LANE: Color = Color::grey(0.4);
+const SIDEWALK: Color = Color::grey(0.8);
+const BIKE_LANE: Color = Color::rgb_f(0.06, 0.49, 0.29);
+const RAIL: Color = Color::rgb_f(0.55, 0.35, 0.24);
+const NO_CLASSES: Color = Color::rgb_f(0.63, 0.16, 0.16);
+const DRIVING_LANE: Color = Color::grey(0.25);
+
+/// Something that can be clicked to inspect it. Internal lanes are part of their junction.
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum Obj {
+    /// An edge, and the position of one of its lanes in `Edge::lanes`
+    Lane(EdgeID, usize),
+    Junction(NodeID),
+}
+
+pub struct Viewer {
+    top_panel: Panel,
+    info_panel: Option<Panel>,
+    draw_network: Drawable,
+    /// Every lane and junction, with the polygon used to hit-test it
+    objects: Vec<(Obj, Polygon)>,
+    /// Indexes into `objects`
+    quadtree: QuadTree<usize>,
+    hovering: Option<usize>,
+    /// The selected object, and its outline
+    selected: Option<(usize, Drawable)>,
+    /// Only present if the network has traffic lights
+    signals: Option<Signals>,
+}
+
+/// Arrows for every connection controlled by a traffic light, colored by the light it's shown at
+/// some time.
+struct Signals {
+    panel: Panel,
+    /// The slider covers one cycle of the longest program
+    max_time: Duration,
+    time: Duration,
+    draw: Drawable,
+}
+
+impl Signals {
+    fn new(ctx: &mut EventCtx, app: &App) -> Option<Signals> {
+        let max_time = app
+            .network
+            .traffic_lights
+            .values()
+            .map(|tl| tl.cycle_length())
+            .max()?;
+        let time = Duration::ZERO;
+        Some(Signals {
+            panel: Panel::new(Widget::col(vec![
+                Widget::row(vec![
+                    Line("Traffic signals").small_heading().draw(ctx),
+                    time_label(ctx, time),
+                ]),
+                Slider::area(ctx, 0.25 * ctx.canvas.window_width, 0.0).named("time"),
+            ]))
+            .aligned(HorizontalAlignment::Center, VerticalAlignment::Bottom)
+            .build(ctx),
+            max_time,
+            time,
+            draw: draw_signals(ctx, app, time),
+        })
+    }
+
+    fn event(&mut self, ctx: &mut EventCtx, app: &App) {
+        self.panel.event(ctx);
+        let time = self.panel.slider("time").get_percent() * self.max_time;
+        if time != self.time {
+            self.time = time;
+            self.panel.replace(ctx, "time label", time_label(ctx, time));
+            self.draw = draw_signals(ctx, app, time);
+        }
+    }
+}
+
+impl Viewer {
+    pub fn new(ctx: &mut EventCtx, app: &App) -> Box<dyn State<App>> {
+        let network = &app.network;
+        let mut batch = GeomBatch::new();
+        let mut objects = Vec::new();
+        for junction in network.junctions.values() {
+            batch.push(JUNCTION, junction.shape.clone());
+            objects.push((Obj::Junction(junction.id), junction.shape.clone()));
+        }
+        for edge in network.normal_edges.values() {
+            for (idx, lane) in edge.lanes.iter().enumerate() {
+                let polygon = lane.center_line.make_polygons(lane.width);
+                batch.push(lane_color(lane.allow), polygon.clone());
+                objects.push((Obj::Lane(edge.id, idx), polygon));
+            }
+        }
+        for edge in network.internal_edges.values() {
+            for lane in &edge.lanes {
+                if let Some(ref pl) = lane.center_line {
+                    batch.push(INTERNAL_LANE, pl.make_polygons(Distance::meters(0.5)));
+                }
+            }
+        }
+
+        let mut bounds = Bounds::new();
+        for (_, polygon) in &objects {
+            bounds.union(polygon.get_bounds());
+        }
+        // An empty network has nothing to bound
+        if objects.is_empty() {
+            bounds = Bounds::zero();
+        }
+        let mut quadtree = QuadTree::default(bounds.as_bbox());
+        for (idx, (_, polygon)) in objects.iter().enumerate() {
+            quadtree.insert_with_box(idx, polygon.get_bounds().as_bbox());
+        }
+
+        ctx.canvas.map_dims = (bounds.max_x, bounds.max_y);
+        // Fit the whole network on screen. A network that's just a point or a straight line has no
+        // size in some direction, so only fit the directions it has.
+        if let Some(zoom) = vec![
+            (ctx.canvas.window_width, bounds.width()),
+            (ctx.canvas.window_height, bounds.height()),
+        ]
+        .into_iter()
+        .filter(|(_, size)| *size > 0.0)
+        .map(|(window, size)| window / size)
+        .min_by(|a, b| a.partial_cmp(b).unwrap())
+        {
+            ctx.canvas.cam_zoom = zoom;
+        }
+        ctx.canvas.center_on_map_pt(bounds.center());
+
+        let top_panel = Panel::new(Widget::col(vec![
+            Line("SUMO network viewer").small_heading().draw(ctx),
+            app.path.as_str().draw_text(ctx),
+            format!(
+                "{} edges, {} junctions, {} connections",
+                network.normal_edges.len(),
+                network.junctions.len(),
+                network.connections.len()
+            )
+            .draw_text(ctx),
+            Line("Click a lane or junction to inspect it")
+                .secondary()
+                .draw(ctx),
+        ]))
+        .aligned(HorizontalAlignment::Left, VerticalAlignment::Top)
+        .build(ctx);
+
+        Box::new(Viewer {
+            top_panel,
+            info_panel: None,
+            draw_network: ctx.upload(batch),
+            objects,
+            quadtree,
+            hovering: None,
+            selected: None,
+            signals: Signals::new(ctx, app),
+        })
+    }
+
+    /// Lanes are drawn on top of junctions, so they win when both contain the cursor.
+    fn object_at(&self, ctx: &EventCtx) -> Option<usize> {
+        let pt = ctx.canvas.get_cursor_in_map_space()?;
+        let mut junction = None;
+        for &(idx, _, _) in &self.quadtree.query(
+            Circle::new(pt, Distance::meters(3.0))
+                .get_bounds()
+                .as_bbox(),
+        ) {
+            let (obj, ref polygon) = self.objects[*idx];
+            if !polygon.contains_pt(pt) {
+                continue;
+            }
+            match obj {
+                Obj::Lane(_, _) => {
+                    return Some(*idx);
+                }
+                Obj::Junction(_) => {
+                    junction = Some(*idx);
+                }
+            }
+        }
+        junction
+    }
+
+    fn select(&mut self, ctx: &mut EventCtx, app: &App, idx: usize) {
+        let (obj, ref polygon) = self.objects[idx];
+        let mut batch = GeomBatch::new();
+        batch.push(
+            Color::CYAN,
+            polygon
+                .to_outline(Distance::meters(0.5))
+                .unwrap_or_else(|_| polygon.clone()),
+        );
+        self.selected = Some((idx, ctx.upload(batch)));
+        self.info_panel = Some(info_panel(ctx, app, obj));
+    }
+
+    fn jump_to_junction(&mut self, ctx: &mut EventCtx, app: &App, id: NodeID) {
+        if let Some(idx) = self
+            .objects
+            .iter()
+            .position(|(obj, _)| *obj == Obj::Junction(id))
+        {
+            ctx.canvas.center_on_map_pt(app.network.junctions[&id].pt);
+            self.select(ctx, app, idx);
+        }
+    }
+}
+
+impl State<App> for Viewer {
+    fn event(&mut self, ctx: &mut EventCtx, app: &mut App) -> Transition<App> {
+        ctx.canvas_movement();
+
+        self.top_panel.event(ctx);
+        if let Some(ref mut signals) = self.signals {
+            signals.event(ctx, app);
+        }
+        let clicked = match self.info_panel {
+            Some(ref mut panel) => match panel.event(ctx) {
+                Outcome::Clicked(x) => Some(x),
+                _ => None,
+            },
+            None => None,
+        };
+        if let Some(x) = clicked {
+            match x.as_ref() {
+                "close" => {
+                    self.info_panel = None;
+                    self.selected = None;
+                }
+                "from junction" | "to junction" => {
+                    if let Some((idx, _)) = self.selected {
+                        if let Obj::Lane(e, _) = self.objects[idx].0 {
+                            let edge = &app.network.normal_edges[&e];
+                            let junction = if x == "from junction" {
+                                edge.from
+                            } else {
+                                edge.to
+                            };
+                            self.jump_to_junction(ctx, app, junction);
+                        }
+                    }
+                }
+                // Panels built by info_panel have no other buttons
+                _ => {}
+            }
+            return Transition::Keep;
+        }
+
+        if ctx.redo_mouseover() {
+            self.hovering = self.object_at(ctx);
+        }
+        if let Some(idx) = self.hovering {
+            if ctx.normal_left_click() {
+                self.select(ctx, app, idx);
+            }
+        }
+
+        Transition::Keep
+    }
+
+    fn draw(&self, g: &mut GfxCtx, _: &App) {
+        g.clear(Color::BLACK);
+        g.redraw(&self.draw_network);
+        if let Some(ref signals) = self.signals {
+            g.redraw(&signals.draw);
+        }
+        if let Some(idx) = self.hovering {
+            g.draw_polygon(Color::YELLOW.alpha(0.5), self.objects[idx].1.clone());
+        }
+        if let Some((_, ref draw)) = self.selected {
+            g.redraw(draw);
+        }
+
+        self.top_panel.draw(g);
+        if let Some(ref panel) = self.info_panel {
+            panel.draw(g);
+        }
+        if let Some(ref signals) = self.signals {
+            signals.panel.draw(g);
+        }
+    }
+}
+
+fn time_label(ctx: &mut EventCtx, time: Duration) -> Widget {
+    Line(time.to_string(&UNITS)).draw(ctx).named("time label")
+}
+
+fn draw_signals(ctx: &mut EventCtx, app: &App, time: Duration) -> Drawable {
+    let network = &app.network;
+    let mut lanes = BTreeMap::new();
+    for edge in network.normal_edges.values() {
+        for lane in &edge.lanes {
+            lanes.insert(lane.id, &lane.center_line);
+        }
+    }
+    let mut internal_lanes = BTreeMap::new();
+    for edge in network.internal_edges.values() {
+        for lane in &edge.lanes {
+            if let Some(ref pl) = lane.center_line {
+                internal_lanes.insert(lane.id, pl);
+            }
+        }
+    }
+
+    let mut batch = GeomBatch::new();
+    for connection in &network.connections {
+        let (tl, link_index) = match (connection.tl, connection.link_index) {
+            (Some(tl), Some(idx)) => (tl, idx),
+            _ => continue,
+        };
+        let color = match network
+            .traffic_lights
+            .get(&tl)
+            .map(|tl| tl.light_at(link_index, time))
+        {
+            Some(Ok(LightState::Green)) => Color::GREEN,
+            Some(Ok(LightState::Yellow)) => Color::YELLOW,
+            Some(Ok(LightState::Red)) => Color::RED,
+            Some(Ok(LightState::Off)) => Color::grey(0.5),
+            // Unknown lights and link indices are reported by light_at; just don't draw them
+            _ => continue,
+        };
+        // Follow the internal lane through the junction, if there is one. Otherwise connect the
+        // two lanes directly.
+        let pl = match connection.via.and_then(|id| internal_lanes.get(&id)) {
+            Some(pl) => Ok((*pl).clone()),
+            None => match (
+                lanes.get(&connection.from_lane),
+                lanes.get(&connection.to_lane),
+            ) {
+                (Some(from), Some(to)) => PolyLine::new(vec![from.last_pt(), to.first_pt()]),
+                _ => continue,
+            },
+        };
+        if let Ok(pl) = pl {
+            batch.push(
+                color,
+                pl.make_arrow(Distance::meters(0.5), ArrowCap::Triangle),
+            );
+        }
+    }
+    ctx.upload(batch)
+}
+
+fn lane_color(allow: VehicleClassSet) -> Color {
+    let rail: VehicleClassSet = vec![
+        VehicleClass::Tram,
+        VehicleClass::RailUrban,
+        VehicleClass::Rail,
+        VehicleClass::RailElectric,
+        VehicleClass::RailFast,
+    ]
+    .into_iter()
+    .fold(VehicleClassSet::none(), |mut set, class| {
+        set.insert(class);
+        set
+    });
+
+    if allow.is_empty() {
+        NO_CLASSES
+    } else if allow == VehicleClassSet::from(VehicleClass::Pedestrian) {
+        SIDEWALK
+    } else if allow == VehicleClassSet::from(VehicleClass::Bicycle) {
+        BIKE_LANE
+    } else if allow.difference(rail).is_empty() {
+        RAIL
+    } else {
+        DRIVING_LANE
+    }
+}
+
+/// Every field of a lane and its edge, or of a junction
+fn info_panel(ctx: &mut EventCtx, app: &App, obj: Obj) -> Panel {
+    let network = &app.network;
+    let strings = &network.strings;
+    let header = |ctx: &mut EventCtx, title: String| {
+        Widget::row(vec![
+            Line(title).small_heading().draw(ctx),
+            ctx.style().btn_close_widget(ctx),
+        ])
+    };
+
+    let mut col = Vec::new();
+    match obj {
+        Obj::Lane(e, idx) => {
+            let edge = &network.normal_edges[&e];
+            let lane = &edge.lanes[idx];
+            col.push(header(ctx, format!("Lane {}", strings.get(lane.id))));
+            col.push(
+                Text::from_multiline(vec![
+                    Line(format!("Index: {} (0 is the rightmost)", lane.index)),
+                    Line(format!("Speed: {}", lane.speed.to_string(&UNITS))),
+                    Line(format!("Width: {}", lane.width.to_string(&UNITS))),
+                    Line(format!("Length: {}", lane.length.to_string(&UNITS))),
+                    Line(format!("Allow: {}", lane.allow)),
+                ])
+                .draw(ctx),
+            );
+
+            col.push(
+                Line(format!("Edge {}", strings.get(e)))
+                    .small_heading()
+                    .draw(ctx),
+            );
+            let mut txt = Text::from_multiline(vec![
+                Line(format!("Type: {}", edge.edge_type)),
+                Line(format!(
+                    "Name: {}",
+                    edge.name.as_deref().unwrap_or("(none)")
+                )),
+                Line(format!("Priority: {}", edge.priority)),
+                Line(format!("Lanes: {}", edge.lanes.len())),
+                Line(format!("From junction: {}", strings.get(edge.from))),
+                Line(format!("To junction: {}", strings.get(edge.to))),
+            ]);
+            if !edge.merged_from.is_empty() {
+                let ids: Vec<&str> = edge.merged_from.iter().map(|id| strings.get(*id)).collect();
+                txt.add(Line(format!("Merged from: {}", ids.join(", "))));
+            }
+            col.push(txt.draw(ctx));
+            col.push(Widget::row(vec![
+                ctx.style()
+                    .btn_outline_light_text("from junction")
+                    .build_def(ctx),
+                ctx.style()
+                    .btn_outline_light_text("to junction")
+                    .build_def(ctx),
+            ]));
+        }
+        Obj::Junction(j) => {
+            let junction = &network.junctions[&j];
+            col.push(header(ctx, format!("Junction {}", strings.get(j))));
+            let mut txt = Text::from(Line(format!("Type: {}", junction.junction_type)));
+            txt.add(Line(format!(
+                "{} incoming lanes",
+                junction.incoming_lanes.len()
+            )));
+            for id in &junction.incoming_lanes {
+                txt.add(Line(format!("  {}", strings.get(*id))).secondary());
+            }
+            txt.add(Line(format!(
+                "{} internal lanes",
+                junction.internal_lanes.len()
+            )));
+            for id in &junction.internal_lanes {
+                txt.add(Line(format!("  {}", strings.get(*id))).secondary());
+            }
+            col.push(txt.draw(ctx));
+        }
+    }
+
+    Panel::new(Widget::col(col))
+        .aligned(HorizontalAlignment::Right, VerticalAlignment::Top)
+        .max_size(Percent::int(30), Percent::int(80))
+        .build(ctx)
+}
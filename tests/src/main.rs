@@ -3,20 +3,43 @@
 use std::fs::File;
 use std::io::Write;
 
-use anyhow::Result;
+use anyhow::{bail, Result};
 use rand::seq::SliceRandom;
+use rand::SeedableRng;
+use rand_xorshift::XorShiftRng;
 
 use abstio::{CityName, MapName};
 use abstutil::Timer;
-use geom::{Distance, Duration, Time};
-use map_model::{IntersectionID, Map};
-use sim::{IndividTrip, PersonSpec, Scenario, TripEndpoint, TripMode, TripPurpose};
+use geom::{Distance, Duration, Pt2D, Ring, Time};
+use map_model::{
+    osm, BuildingID, BusStopID, ControlTrafficSignal, DrivingSide, IntersectionID, LaneID,
+    LaneType, Map, SignalTiming, TurnType, NORMAL_LANE_THICKNESS,
+};
+use sim::{
+    BuildingOverride, IndividTrip, PersonSpec, Scenario, ScenarioModifier, TripEndpoint, TripMode,
+    TripPurpose,
+};
 
 fn main() -> Result<()> {
-    test_lane_changing(&import_map(abstio::path(
-        "../tests/input/lane_selection.osm",
-    )))?;
+    let lane_selection = import_map(abstio::path("../tests/input/lane_selection.osm"));
+    test_lane_changing(&lane_selection)?;
+    test_merge_watch(&lane_selection)?;
     test_map_importer()?;
+    test_parking_lanes()?;
+    test_osm_update()?;
+    test_left_hand_traffic()?;
+    test_gtfs_import()?;
+    test_census_blocks()?;
+    test_jitter_departures()?;
+    test_shift_drive_trips()?;
+    test_od_demand()?;
+    test_building_override()?;
+    test_signal_timing()?;
+    test_sumo_missing_junctions()?;
+    test_sumo_schema_drift()?;
+    test_sumo_merge_geometry_nodes()?;
+    test_sumo_stats()?;
+    test_sumo_filter()?;
     check_proposals()?;
     smoke_test()?;
     Ok(())
@@ -44,34 +67,912 @@ fn test_map_importer() -> Result<()> {
     Ok(())
 }
 
+/// On-street parking lanes should match the parking:lane tags on each side of a road, with angled
+/// parking getting a wider lane.
+fn test_parking_lanes() -> Result<()> {
+    let map = import_map(abstio::path("../tests/input/parking_lanes.osm"));
+    for (way, expected) in vec![(100, "spddps"), (101, "sdps"), (102, "sdds")] {
+        let r = match map
+            .all_roads()
+            .iter()
+            .find(|r| r.orig_id.osm_way_id == osm::WayID(way))
+        {
+            Some(r) => r,
+            None => bail!("way {} didn't become a road", way),
+        };
+        let actual: String = r
+            .lanes_ltr()
+            .into_iter()
+            .map(|(_, _, lt)| match lt {
+                LaneType::Driving => 'd',
+                LaneType::Parking => 'p',
+                LaneType::Sidewalk => 's',
+                _ => '?',
+            })
+            .collect();
+        if actual != expected {
+            bail!(
+                "way {} should have lanes {}, but has {}",
+                way,
+                expected,
+                actual
+            );
+        }
+        for (l, _, lt) in r.lanes_ltr() {
+            let width = map.get_l(l).width;
+            if lt == LaneType::Parking && (way == 101) != (width > NORMAL_LANE_THICKNESS) {
+                bail!("way {} has a parking lane {} wide", way, width);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Updating a map from a newer OSM extract should leave it alone if nothing changed, and if only
+/// the tags of one road changed, should patch just that road and wind up with the same result as
+/// importing the newer extract from scratch.
+fn test_osm_update() -> Result<()> {
+    let mut timer = Timer::new("update synthetic map");
+    let old_path = abstio::path("../tests/input/parking_lanes.osm");
+    let old_osm = std::fs::read_to_string(&old_path)?;
+    let old_opts = || import_options(old_path.clone(), None, DrivingSide::Right);
+    let original = convert_osm::convert(old_opts(), &mut timer);
+
+    let mut raw = convert_osm::convert(old_opts(), &mut timer);
+    match convert_osm::update(&mut raw, &old_path, &old_opts(), &mut timer)? {
+        convert_osm::Update::Unchanged => {}
+        _ => bail!("updating from the same .osm should change nothing"),
+    }
+    if abstutil::to_json(&raw) != abstutil::to_json(&original) {
+        bail!("updating from the same .osm modified the RawMap");
+    }
+
+    let renamed = "parking on both sides";
+    if !old_osm.contains(renamed) {
+        bail!("{} doesn't name a road in {}", renamed, old_path);
+    }
+    let new_path = std::env::temp_dir()
+        .join("parking_lanes_renamed.osm")
+        .to_string_lossy()
+        .to_string();
+    std::fs::write(&new_path, old_osm.replace(renamed, "renamed"))?;
+    let new_opts = || {
+        let mut opts = import_options(new_path.clone(), None, DrivingSide::Right);
+        // Compare against the original map, regardless of the file's name
+        opts.name = original.name.clone();
+        opts
+    };
+    let from_scratch = convert_osm::convert(new_opts(), &mut timer);
+
+    let mut raw = convert_osm::convert(old_opts(), &mut timer);
+    match convert_osm::update(&mut raw, &old_path, &new_opts(), &mut timer)? {
+        convert_osm::Update::PatchedRoads(ways) => {
+            if ways.into_iter().collect::<Vec<_>>() != vec![osm::WayID(100)] {
+                bail!("only way 100 should have been patched");
+            }
+        }
+        convert_osm::Update::Unchanged => bail!("renaming way 100 wasn't noticed"),
+        convert_osm::Update::NeedsFullRebuild(reason) => {
+            bail!("renaming way 100 needed a full rebuild: {}", reason)
+        }
+    }
+    for (id, r) in &raw.roads {
+        if id.osm_way_id != osm::WayID(100) && r.osm_tags != original.roads[id].osm_tags {
+            bail!("{} was touched, but only way 100 changed", id.osm_way_id);
+        }
+    }
+    if abstutil::to_json(&raw) != abstutil::to_json(&from_scratch) {
+        bail!("patching way 100 doesn't match importing the new .osm from scratch");
+    }
+    std::fs::remove_file(new_path)?;
+    Ok(())
+}
+
+/// When driving on the left, left turns should hug the curb, right turns should start from the
+/// lane closest to oncoming traffic, and buses should stop next to the sidewalk on the left.
+fn test_left_hand_traffic() -> Result<()> {
+    let map = import_map_with_config(
+        abstio::path("../tests/input/left_hand_traffic.osm"),
+        None,
+        DrivingSide::Left,
+    );
+    let along_curb = |l: LaneID| -> bool {
+        let r = map.get_parent(l);
+        let lanes = r.lanes_ltr();
+        let idx = r.offset(l);
+        (idx > 0 && lanes[idx - 1].2 == LaneType::Sidewalk)
+            || lanes
+                .get(idx + 1)
+                .map(|(_, _, lt)| *lt == LaneType::Sidewalk)
+                .unwrap_or(false)
+    };
+
+    let mut num_turns = 0;
+    for t in map.all_turns().values() {
+        if !map.get_l(t.id.src).is_driving() || !map.get_l(t.id.dst).is_driving() {
+            continue;
+        }
+        let from_curb = match t.turn_type {
+            TurnType::Left => true,
+            TurnType::Right => false,
+            _ => continue,
+        };
+        if along_curb(t.id.src) != from_curb {
+            bail!(
+                "{} is a {:?} turn, but {} start from the curb",
+                t.id,
+                t.turn_type,
+                if from_curb { "doesn't" } else { "does" }
+            );
+        }
+        num_turns += 1;
+    }
+    if num_turns == 0 {
+        bail!("No left or right turns between driving lanes");
+    }
+
+    let route = match map.all_bus_routes().get(0) {
+        Some(route) => route,
+        None => bail!("The bus route didn't import"),
+    };
+    if route.stops.len() != 2 {
+        bail!(
+            "The bus route should have 2 stops, but has {:?}",
+            route.stops
+        );
+    }
+    for bs in &route.stops {
+        let stop = map.get_bs(*bs);
+        if !along_curb(stop.driving_pos.lane()) {
+            bail!("{} isn't in the lane along the curb", bs);
+        }
+        // The route heads east, so it should stop on the north side of the road
+        if stop.sidewalk_pos.pt(&map).y() >= stop.driving_pos.pt(&map).y() {
+            bail!("{} is on the wrong side of the road", bs);
+        }
+    }
+    Ok(())
+}
+
+/// Bus routes and their schedules should come from a GTFS feed, skipping stops outside the map.
+/// Importing twice should produce exactly the same routes.
+fn test_gtfs_import() -> Result<()> {
+    let summarize = |map: &Map| -> Vec<(String, Vec<BusStopID>, Vec<Time>)> {
+        map.all_bus_routes()
+            .iter()
+            .map(|r| (r.short_name.clone(), r.stops.clone(), r.spawn_times.clone()))
+            .collect()
+    };
+    let import = || {
+        import_map_with_gtfs(
+            abstio::path("../tests/input/gtfs.osm"),
+            Some(abstio::path("../tests/input/gtfs")),
+        )
+    };
+    let routes = summarize(&import());
+
+    let at = |hours: usize, mins: usize| {
+        Time::START_OF_DAY + Duration::hours(hours) + Duration::minutes(mins)
+    };
+    let expected = vec![
+        // The tram and the weekend trip are skipped
+        ("A", vec![at(7, 0), at(7, 15), at(7, 30), at(8, 0)]),
+        // The first stop is outside the map, so buses start from the second
+        ("B", vec![at(7, 5), at(7, 35)]),
+    ];
+    let actual: Vec<(&str, usize, Vec<Time>)> = routes
+        .iter()
+        .map(|(name, stops, times)| (name.as_str(), stops.len(), times.clone()))
+        .collect();
+    let expected: Vec<(&str, usize, Vec<Time>)> = expected
+        .into_iter()
+        .map(|(name, times)| (name, 2, times))
+        .collect();
+    if actual != expected {
+        bail!("Expected GTFS routes {:?}, but got {:?}", expected, actual);
+    }
+
+    if summarize(&import()) != routes {
+        bail!("Importing the same GTFS feed twice produced different routes");
+    }
+    Ok(())
+}
+
+/// Census blocks should be split between homes by footprint, only counting the part of a block
+/// inside the map.
+fn test_census_blocks() -> Result<()> {
+    let map = import_map(abstio::path("../tests/input/census.osm"));
+    let bldg = |way: i64| {
+        map.all_buildings()
+            .iter()
+            .find(|b| b.orig_id == osm::OsmID::Way(osm::WayID(way)))
+            .map(|b| b.id)
+    };
+    let (house, apartments) = match (bldg(200), bldg(201)) {
+        (Some(h), Some(a)) => (h, a),
+        _ => bail!("The house and apartments in census.osm weren't imported"),
+    };
+
+    // The west half of this block is outside the map, and it covers every building
+    let bounds = map.get_bounds();
+    let block = Ring::must_new(vec![
+        Pt2D::new(-bounds.max_x, 0.0),
+        Pt2D::new(bounds.max_x, 0.0),
+        Pt2D::new(bounds.max_x, bounds.max_y),
+        Pt2D::new(-bounds.max_x, bounds.max_y),
+        Pt2D::new(-bounds.max_x, 0.0),
+    ])
+    .to_polygon();
+    let (inside, homes) =
+        popdat::distribute_population_by_footprint(geo::Polygon::from(block), 1000, &map);
+    if inside < 450 || inside > 550 {
+        bail!(
+            "Expected about half of 1000 people inside the map, but got {}",
+            inside
+        );
+    }
+    let total: usize = homes.iter().map(|(_, n)| *n).sum();
+    if total != inside {
+        bail!(
+            "Assigned {} people to homes, but {} live in the block",
+            total,
+            inside
+        );
+    }
+    let residents = |b: BuildingID| homes.iter().find(|(id, _)| *id == b).map(|(_, n)| *n);
+    match (residents(house), residents(apartments), homes.len()) {
+        (Some(h), Some(a), 2) if a > h && h > 0 => {}
+        _ => bail!(
+            "The shop got people, or the apartments didn't get more: {:?}",
+            homes
+        ),
+    }
+
+    // Nobody lives in this corner of the map
+    let empty = Ring::must_new(vec![
+        Pt2D::new(1.0, 1.0),
+        Pt2D::new(2.0, 1.0),
+        Pt2D::new(2.0, 2.0),
+        Pt2D::new(1.0, 2.0),
+        Pt2D::new(1.0, 1.0),
+    ])
+    .to_polygon();
+    let (_, homes) =
+        popdat::distribute_population_by_footprint(geo::Polygon::from(empty), 10, &map);
+    if !homes.is_empty() {
+        bail!("A block without homes assigned people to {:?}", homes);
+    }
+    Ok(())
+}
+
+/// Jittering departure times should be reproducible, stay within the day, and keep each person's
+/// trips in order.
+fn test_jitter_departures() -> Result<()> {
+    let map = import_map(abstio::path("../tests/input/census.osm"));
+    let home = TripEndpoint::Bldg(BuildingID(0));
+    let work = TripEndpoint::Bldg(BuildingID(1));
+    let at = |hours: usize, secs: f64| {
+        Time::START_OF_DAY + Duration::hours(hours) + Duration::seconds(secs)
+    };
+    let mut scenario = Scenario::empty(&map, "jitter");
+    scenario.people.push(PersonSpec {
+        orig_id: None,
+        origin: home.clone(),
+        trips: vec![
+            IndividTrip::new(at(0, 60.0), TripPurpose::Work, work.clone(), TripMode::Walk),
+            IndividTrip::new(at(8, 0.0), TripPurpose::Home, home.clone(), TripMode::Walk),
+            IndividTrip::new(at(8, 30.0), TripPurpose::Work, work, TripMode::Walk),
+            IndividTrip::new(at(23, 3590.0), TripPurpose::Home, home, TripMode::Walk),
+        ],
+    });
+
+    let jitter = |seed| -> Vec<Time> {
+        let mut rng = XorShiftRng::seed_from_u64(seed);
+        scenario
+            .clone()
+            .jitter_departures(Duration::minutes(30), &mut rng)
+            .people[0]
+            .trips
+            .iter()
+            .map(|t| t.depart)
+            .collect()
+    };
+    let times = jitter(42);
+    if times != jitter(42) {
+        bail!("Jittering with the same seed gave different times");
+    }
+    let orig: Vec<Time> = scenario.people[0].trips.iter().map(|t| t.depart).collect();
+    if times == orig {
+        bail!("Jittering didn't change any departure times");
+    }
+    for pair in times.windows(2) {
+        if pair[0] >= pair[1] {
+            bail!("Jittered trips are out of order: {:?}", times);
+        }
+    }
+    // The last trip can only spill past midnight if it's pushed after the previous one
+    if times[0] < Time::START_OF_DAY || times[3] > at(24, 0.0) {
+        bail!("Jittered trips left the day: {:?}", times);
+    }
+    Ok(())
+}
+
+/// Shifting driving trips should pick the same trips for the same seed, and only ones that're
+/// short enough.
+fn test_shift_drive_trips() -> Result<()> {
+    let map = import_map(abstio::path("../tests/input/census.osm"));
+    let mut scenario = Scenario::empty(&map, "shift");
+    for idx in 0..20 {
+        scenario.people.push(PersonSpec {
+            orig_id: None,
+            origin: TripEndpoint::Bldg(BuildingID(0)),
+            trips: vec![IndividTrip::new(
+                Time::START_OF_DAY + Duration::minutes(idx),
+                TripPurpose::Work,
+                TripEndpoint::Bldg(BuildingID(1)),
+                TripMode::Drive,
+            )],
+        });
+    }
+
+    let shift = |max_distance| -> Vec<TripMode> {
+        ScenarioModifier::ShiftDriveTrips {
+            pct_trips: 50,
+            max_distance,
+            departure_filter: (Time::START_OF_DAY, Time::START_OF_DAY + Duration::hours(1)),
+            to_mode: TripMode::Bike,
+            rng_seed: 42,
+        }
+        .apply(&map, scenario.clone())
+        .people
+        .iter()
+        .map(|p| p.trips[0].mode)
+        .collect()
+    };
+    let modes = shift(Distance::miles(5.0));
+    let num_bikes = modes.iter().filter(|m| **m == TripMode::Bike).count();
+    if num_bikes != 10 {
+        bail!(
+            "Expected half of 20 trips to become bike trips, but got {:?}",
+            modes
+        );
+    }
+    if shift(Distance::miles(5.0)) != modes {
+        bail!("Shifting trips with the same seed picked different trips");
+    }
+    if shift(Distance::meters(1.0)).contains(&TripMode::Bike) {
+        bail!("Shifted trips longer than the maximum distance");
+    }
+    Ok(())
+}
+
+/// Synthesizing a scenario from an origin-destination table should produce the expected number
+/// of trips between the right buildings, drop demand outside the map, and be reproducible.
+fn test_od_demand() -> Result<()> {
+    let map = import_map(abstio::path("../tests/input/census.osm"));
+    let bldg = |way: i64| {
+        map.all_buildings()
+            .iter()
+            .find(|b| b.orig_id == osm::OsmID::Way(osm::WayID(way)))
+            .map(|b| TripEndpoint::Bldg(b.id))
+    };
+    let (house, apartments, shop) = match (bldg(200), bldg(201), bldg(202)) {
+        (Some(h), Some(a), Some(s)) => (h, a, s),
+        _ => bail!("The buildings in census.osm weren't imported"),
+    };
+
+    // Each zone has one building. Half of the east zone is outside the map.
+    let zones = popdat::read_zones(&abstio::path("../tests/input/od_zones.geojson"), &map)?;
+    let demand = popdat::read_demand(&abstio::path("../tests/input/od_demand.csv"))?;
+    let synthesize = |seed| {
+        let mut rng = XorShiftRng::seed_from_u64(seed);
+        popdat::synthesize_od_demand("od", &zones, &demand, &map, &mut rng)
+    };
+    let (scenario, report) = synthesize(42)?;
+
+    // Summarize each trip as (origin, destination, hour, mode)
+    let trips: Vec<(TripEndpoint, TripEndpoint, usize, TripMode)> = scenario
+        .people
+        .iter()
+        .map(|p| {
+            let trip = &p.trips[0];
+            let hour = (trip.depart - Time::START_OF_DAY).inner_seconds() as usize / 3600;
+            (p.origin, trip.destination, hour, trip.mode)
+        })
+        .collect();
+    let mut expected = Vec::new();
+    expected.extend(vec![(house, apartments, 7, TripMode::Walk); 3]);
+    expected.extend(vec![(apartments, shop, 8, TripMode::Drive); 2]);
+    expected.extend(vec![(shop, house, 17, TripMode::Bike); 3]);
+    if trips != expected {
+        bail!("Expected trips {:?}, but got {:?}", expected, trips);
+    }
+
+    if report.requested != 15 || report.generated != 8 {
+        bail!("Unexpected totals: {:?}", report);
+    }
+    let pair = |from: &str, to: &str| (from.to_string(), to.to_string());
+    let dropped: Vec<((String, String), usize)> =
+        report.dropped_outside_map.clone().into_iter().collect();
+    if dropped != vec![(pair("east", "west"), 3), (pair("middle", "east"), 2)] {
+        bail!(
+            "Expected half of the trips to or from the east zone to be dropped: {:?}",
+            report
+        );
+    }
+    // The west zone only has the house, so there's nowhere to go within it
+    if report.dropped_no_buildings.get(&pair("west", "west")) != Some(&2) {
+        bail!("Trips within the west zone weren't dropped: {:?}", report);
+    }
+
+    let departures =
+        |s: &Scenario| -> Vec<Time> { s.people.iter().map(|p| p.trips[0].depart).collect() };
+    if departures(&synthesize(42)?.0) != departures(&scenario) {
+        bail!("Synthesizing with the same seed gave different departure times");
+    }
+
+    // The scenario should run
+    let mut timer = Timer::throwaway();
+    let mut opts = sim::SimOptions::new("test_od_demand");
+    opts.alerts = sim::AlertHandler::Silence;
+    let mut sim = sim::Sim::new(&map, opts);
+    let mut rng = XorShiftRng::seed_from_u64(42);
+    scenario.instantiate(&mut sim, &map, &mut rng, &mut timer);
+    sim.timed_step(&map, Duration::hours(18), &mut None, &mut timer);
+    Ok(())
+}
+
+/// A building override should add one synthetic person per household and job, commuting from or
+/// to the building, and always the same people.
+fn test_building_override() -> Result<()> {
+    let map = import_map(abstio::path("../tests/input/census.osm"));
+    let b = BuildingID(0);
+    let generate = || {
+        let mut scenario = Scenario::empty(&map, "override");
+        let added = BuildingOverride {
+            building: map.get_b(b).orig_id,
+            households: 5,
+            jobs: 3,
+        }
+        .apply(&map, &mut scenario);
+        (added, scenario)
+    };
+
+    let (added, scenario) = generate();
+    if added != 8 || scenario.people.len() != 8 {
+        bail!(
+            "Expected 8 synthetic people, but {} were added, and the scenario has {}",
+            added,
+            scenario.people.len()
+        );
+    }
+    let num_residents = scenario
+        .people
+        .iter()
+        .filter(|p| p.origin == TripEndpoint::Bldg(b))
+        .count();
+    let num_workers = scenario
+        .people
+        .iter()
+        .filter(|p| p.trips[0].destination == TripEndpoint::Bldg(b))
+        .count();
+    if num_residents != 5 || num_workers != 3 {
+        bail!(
+            "Expected 5 people living at and 3 working at {}, but got {} and {}",
+            b,
+            num_residents,
+            num_workers
+        );
+    }
+    for p in &scenario.people {
+        if p.trips.len() != 2
+            || p.trips.iter().any(|t| !t.synthetic)
+            || p.trips[1].destination != p.origin
+        {
+            bail!("A synthetic person doesn't commute there and back: {:?}", p);
+        }
+    }
+
+    let describe = |s: &Scenario| format!("{:?}", s.people);
+    if describe(&generate().1) != describe(&scenario) {
+        bail!("The same override generated different people");
+    }
+    Ok(())
+}
+
+/// Exporting a traffic signal's timing and importing it again should change nothing, and timing
+/// that doesn't match the intersection should be rejected, naming the phase.
+fn test_signal_timing() -> Result<()> {
+    let map = import_map(abstio::path("../tests/input/lane_selection.osm"));
+    let mut num_signals = 0;
+    for i in map.all_intersections() {
+        if !i.is_traffic_signal() {
+            continue;
+        }
+        num_signals += 1;
+        let signal = map.get_traffic_signal(i.id);
+        let timing = signal.export_timing(&map)?;
+        // Go through JSON too, since that's how the timing is shared
+        let timing: SignalTiming = abstutil::from_json(&abstutil::to_json(&timing).into_bytes())?;
+        let imported = ControlTrafficSignal::import_timing(timing.clone(), i.id, &map)?;
+        if &imported != signal {
+            bail!("Exporting and importing the timing of {} changed it", i.id);
+        }
+
+        let mut bad = timing.clone();
+        bad.phases[0].protected[0].from = "nowhere".to_string();
+        match ControlTrafficSignal::import_timing(bad, i.id, &map) {
+            Ok(_) => bail!("Imported timing for {} with a made-up direction", i.id),
+            Err(err) => {
+                if !err.to_string().starts_with("Phase 1: ") {
+                    bail!("The mismatch at {} wasn't reported by phase: {}", i.id, err);
+                }
+            }
+        }
+
+        if timing.phases.len() > 1 {
+            let mut incomplete = timing;
+            incomplete.phases.truncate(1);
+            if ControlTrafficSignal::import_timing(incomplete, i.id, &map).is_ok() {
+                bail!("Imported timing for {} that's missing movements", i.id);
+            }
+        }
+    }
+    if num_signals == 0 {
+        bail!("lane_selection.osm doesn't have any traffic signals");
+    }
+    Ok(())
+}
+
+/// SUMO edges missing a junction should be snapped to a nearby one if possible, and otherwise
+/// dropped with a warning.
+fn test_sumo_missing_junctions() -> Result<()> {
+    let network = sumo::Network::load_with_mode(
+        &abstio::path("../tests/input/sumo_missing_junctions.net.xml"),
+        sumo::ParseMode::Strict,
+        &mut Timer::throwaway(),
+    )?;
+    let edges: Vec<(&str, &str, &str)> = network
+        .normal_edges
+        .values()
+        .map(|e| {
+            (
+                network.strings.get(e.id),
+                network.strings.get(e.from),
+                network.strings.get(e.to),
+            )
+        })
+        .collect();
+    let expected = vec![("normal", "J1", "J2"), ("recovered", "J2", "J3")];
+    if edges != expected {
+        bail!("Expected SUMO edges {:?}, but got {:?}", expected, edges);
+    }
+    if network.warnings.len() != 2 {
+        bail!(
+            "Expected one recovered and one dropped edge, but got warnings {:?}",
+            network.warnings
+        );
+    }
+    Ok(())
+}
+
+/// SUMO elements and attributes that aren't understood should be reported in lenient mode, and
+/// fail with their position in strict mode.
+fn test_sumo_schema_drift() -> Result<()> {
+    let path = abstio::path("../tests/input/sumo_schema_drift.net.xml");
+    let network = sumo::Network::load(&path, &mut Timer::throwaway())?;
+    let expected = vec![
+        "unknown element <hologram> appears 1 times".to_string(),
+        "unknown attribute friction on <lane> appears 1 times".to_string(),
+    ];
+    if network.unknown_schema.describe() != expected {
+        bail!(
+            "Expected SUMO schema report {:?}, but got {:?}",
+            expected,
+            network.unknown_schema.describe()
+        );
+    }
+
+    match sumo::Network::load_with_mode(&path, sumo::ParseMode::Strict, &mut Timer::throwaway()) {
+        Ok(_) => bail!("Strict mode should reject {}", path),
+        Err(err) => {
+            if !err.to_string().contains("line 12") {
+                bail!(
+                    "Strict mode error should point at line 12, but got: {}",
+                    err
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+/// SUMO edges split only by a geometry node should be merged, but not across a traffic light.
+fn test_sumo_merge_geometry_nodes() -> Result<()> {
+    let mut network = sumo::Network::load_with_mode(
+        &abstio::path("../tests/input/sumo_geometry_nodes.net.xml"),
+        sumo::ParseMode::Strict,
+        &mut Timer::throwaway(),
+    )?;
+    let merged = network.merge_degenerate_junctions();
+    if merged != 1 {
+        bail!("Expected 1 edge to be merged, but got {}", merged);
+    }
+
+    let edges: Vec<(&str, &str, &str, Vec<&str>)> = network
+        .normal_edges
+        .values()
+        .map(|e| {
+            (
+                network.strings.get(e.id),
+                network.strings.get(e.from),
+                network.strings.get(e.to),
+                e.merged_from
+                    .iter()
+                    .map(|id| network.strings.get(*id))
+                    .collect(),
+            )
+        })
+        .collect();
+    let expected = vec![
+        ("first", "J1", "J3", vec!["first", "second"]),
+        ("third", "J3", "J4", Vec::new()),
+    ];
+    if edges != expected {
+        bail!("Expected SUMO edges {:?}, but got {:?}", expected, edges);
+    }
+
+    let connections: Vec<(&str, &str, &str, &str)> = network
+        .connections
+        .iter()
+        .map(|c| {
+            (
+                network.strings.get(c.from),
+                network.strings.get(c.from_lane),
+                network.strings.get(c.to),
+                network.strings.get(c.to_lane),
+            )
+        })
+        .collect();
+    let expected = vec![("first", "first_0", "third", "third_0")];
+    if connections != expected {
+        bail!(
+            "Expected SUMO connections {:?}, but got {:?}",
+            expected,
+            connections
+        );
+    }
+    Ok(())
+}
+
+/// Summarizing a SUMO network should count lanes by class and speed, junctions by type, and total
+/// lane length. Comparing against a simplified network should flag the edges that disappeared.
+fn test_sumo_stats() -> Result<()> {
+    let stats = sumo::Network::load_with_mode(
+        &abstio::path("../tests/input/sumo_stats.net.xml"),
+        sumo::ParseMode::Strict,
+        &mut Timer::throwaway(),
+    )?
+    .stats();
+    let expected: Vec<(&str, f64)> = vec![
+        ("edges", 2.0),
+        ("lanes", 4.0),
+        ("lanes allowing any", 1.0),
+        ("lanes allowing bicycle", 1.0),
+        ("lanes allowing bus", 1.0),
+        ("lanes allowing passenger", 1.0),
+        ("lanes allowing pedestrian", 1.0),
+        ("lane-kilometers", 0.31),
+        ("junctions", 3.0),
+        ("dead_end junctions", 2.0),
+        ("traffic_light junctions", 1.0),
+        ("lanes at 10 km/h", 1.0),
+        ("lanes at 30 km/h", 1.0),
+        ("lanes at 50 km/h", 2.0),
+        ("width (m)", 160.0),
+        ("height (m)", 10.0),
+    ];
+    let actual = stats.rows();
+    let matches = actual.len() == expected.len()
+        && actual
+            .iter()
+            .zip(expected.iter())
+            .all(|((n1, v1), (n2, v2))| n1 == n2 && (v1 - v2).abs() < 0.001);
+    if !matches {
+        bail!("Expected SUMO stats {:?}, but got {:?}", expected, actual);
+    }
+
+    let path = abstio::path("../tests/input/sumo_geometry_nodes.net.xml");
+    let before = sumo::Network::load(&path, &mut Timer::throwaway())?.stats();
+    let mut network = sumo::Network::load(&path, &mut Timer::throwaway())?;
+    network.merge_degenerate_junctions();
+    let diff = before.diff(&network.stats(), 0.1);
+    let flagged: Vec<&str> = diff
+        .rows
+        .iter()
+        .filter(|(_, _, _, flagged)| *flagged)
+        .map(|(name, _, _, _)| name.as_str())
+        .collect();
+    // The merged lane also covers the junction it crossed, so the total length barely changes
+    let expected = vec![
+        "edges",
+        "lanes",
+        "lanes allowing any",
+        "junctions",
+        "priority junctions",
+        "lanes at 50 km/h",
+    ];
+    if flagged != expected {
+        bail!(
+            "Expected SUMO stats {:?} to change, but got {:?}",
+            expected,
+            flagged
+        );
+    }
+    if diff.removed_edges != vec!["second".to_string()]
+        || diff.removed_junctions != vec!["J2".to_string()]
+        || !diff.added_edges.is_empty()
+        || !diff.added_junctions.is_empty()
+    {
+        bail!(
+            "Expected only edge second and junction J2 to disappear, but removed {:?} and {:?}, \
+             added {:?} and {:?}",
+            diff.removed_edges,
+            diff.removed_junctions,
+            diff.added_edges,
+            diff.added_junctions
+        );
+    }
+    Ok(())
+}
+
+/// Filtering a SUMO network by vehicle class or edge type should remove the rail lines and
+/// footpaths, the junctions only they used, and every connection touching them.
+fn test_sumo_filter() -> Result<()> {
+    use std::collections::BTreeSet;
+    use sumo::{NetworkFilter, VehicleClass, VehicleClassSet};
+
+    let path = abstio::path("../tests/input/sumo_mixed_modes.net.xml");
+    let load = |filter: NetworkFilter| {
+        sumo::Network::load_filtered(
+            &path,
+            sumo::ParseMode::Strict,
+            &filter,
+            &mut Timer::throwaway(),
+        )
+    };
+    let names = |network: &sumo::Network| -> (Vec<String>, Vec<String>) {
+        let mut edges: Vec<String> = network
+            .normal_edges
+            .keys()
+            .map(|e| network.strings.get(*e).to_string())
+            .collect();
+        edges.sort();
+        let mut junctions: Vec<String> = network
+            .junctions
+            .keys()
+            .map(|j| network.strings.get(*j).to_string())
+            .collect();
+        junctions.sort();
+        (edges, junctions)
+    };
+
+    let unfiltered = load(NetworkFilter::default())?;
+    if unfiltered.junctions.len() != 6 || unfiltered.connections.len() != 5 {
+        bail!(
+            "Expected 6 junctions and 5 connections without a filter, but got {} and {}",
+            unfiltered.junctions.len(),
+            unfiltered.connections.len()
+        );
+    }
+
+    let network = load(NetworkFilter {
+        allow_any: Some(VehicleClassSet::from(VehicleClass::Passenger)),
+        drop_types: BTreeSet::new(),
+    })?;
+    let (edges, junctions) = names(&network);
+    if edges != vec!["road1", "road2"] || junctions != vec!["J1", "J2", "J3"] {
+        bail!(
+            "Keeping passenger edges left {:?} and junctions {:?}",
+            edges,
+            junctions
+        );
+    }
+    // Only road1 -> road2 and its continuation through J2 are left
+    if network.connections.len() != 2
+        || network.filtered.connections != 3
+        || network.internal_edges.len() != 1
+    {
+        bail!(
+            "Keeping passenger edges left {} connections and {} internal edges, and reported {} \
+             removed connections",
+            network.connections.len(),
+            network.internal_edges.len(),
+            network.filtered.connections
+        );
+    }
+
+    let network = load(NetworkFilter {
+        allow_any: None,
+        drop_types: vec!["railway".to_string()].into_iter().collect(),
+    })?;
+    let (edges, junctions) = names(&network);
+    if edges != vec!["path", "road1", "road2"] || junctions != vec!["F1", "J1", "J2", "J3"] {
+        bail!(
+            "Dropping railways left {:?} and junctions {:?}",
+            edges,
+            junctions
+        );
+    }
+    if network.filtered.edges != vec!["rail1", "rail2"]
+        || network.filtered.junctions != vec!["R1", "R2"]
+        || network.filtered.connections != 2
+    {
+        bail!("Dropping railways reported {}", network.filtered.describe());
+    }
+    let j2 = network
+        .junctions
+        .values()
+        .find(|j| network.strings.get(j.id) == "J2")
+        .unwrap();
+    if j2.incoming_lanes.len() != 1 || j2.internal_lanes.len() != 1 {
+        bail!("J2 still refers to the railway's lanes");
+    }
+    for c in &network.connections {
+        for id in &[network.strings.get(c.from), network.strings.get(c.to)] {
+            if id.starts_with("rail") || id.starts_with(":J2_1") {
+                bail!("A connection still refers to {}", id);
+            }
+        }
+    }
+    Ok(())
+}
+
 /// Run the contents of a .osm through the full map importer with default options.
 fn import_map(path: String) -> Map {
+    import_map_with_gtfs(path, None)
+}
+
+/// Like import_map, but with bus routes from a directory of GTFS files.
+fn import_map_with_gtfs(path: String, gtfs: Option<String>) -> Map {
+    import_map_with_config(path, gtfs, DrivingSide::Right)
+}
+
+/// Like import_map_with_gtfs, but also choosing the side of the road to drive on.
+fn import_map_with_config(path: String, gtfs: Option<String>, driving_side: DrivingSide) -> Map {
     let mut timer = Timer::new("convert synthetic map");
-    let raw = convert_osm::convert(
-        convert_osm::Options {
-            name: MapName::new("zz", "oneshot", &abstutil::basename(&path)),
-            osm_input: path,
-            clip: None,
-            map_config: map_model::MapConfig {
-                driving_side: map_model::DrivingSide::Right,
-                bikes_can_use_bus_lanes: true,
-                inferred_sidewalks: true,
-                separate_cycleways: false,
-                street_parking_spot_length: Distance::meters(8.0),
-            },
-            onstreet_parking: convert_osm::OnstreetParking::JustOSM,
-            public_offstreet_parking: convert_osm::PublicOffstreetParking::None,
-            private_offstreet_parking: convert_osm::PrivateOffstreetParking::FixedPerBldg(0),
-            elevation: None,
-            include_railroads: true,
-            extra_buildings: None,
-        },
-        &mut timer,
-    );
+    let raw = convert_osm::convert(import_options(path, gtfs, driving_side), &mut timer);
     let map = Map::create_from_raw(raw, true, true, &mut timer);
     map
 }
 
+/// The options used to import a .osm in these tests.
+fn import_options(
+    path: String,
+    gtfs: Option<String>,
+    driving_side: DrivingSide,
+) -> convert_osm::Options {
+    convert_osm::Options {
+        name: MapName::new("zz", "oneshot", &abstutil::basename(&path)),
+        osm_input: path,
+        clip: None,
+        map_config: map_model::MapConfig {
+            driving_side,
+            bikes_can_use_bus_lanes: true,
+            inferred_sidewalks: true,
+            separate_cycleways: false,
+            street_parking_spot_length: Distance::meters(8.0),
+        },
+        onstreet_parking: convert_osm::OnstreetParking::JustOSM,
+        public_offstreet_parking: convert_osm::PublicOffstreetParking::None,
+        private_offstreet_parking: convert_osm::PrivateOffstreetParking::FixedPerBldg(0),
+        elevation: None,
+        include_railroads: true,
+        extra_buildings: None,
+        gtfs,
+    }
+}
+
 /// Verify what turns are generated by writing (from lane, to lane, turn type).
 fn dump_turn_goldenfile(map: &Map) -> Result<()> {
     let path = abstio::path(format!("../tests/goldenfiles/{}.txt", map.get_name().map));
@@ -184,10 +1085,42 @@ fn test_lane_changing(map: &Map) -> Result<()> {
         od.push((north, west));
         od.push((east, west));
     }
-    // Shuffling here is critical, since the loop below creates a car/bike and chooses spawn time
-    // based on index.
+    // Shuffling here is critical, since lane_changing_scenario creates a car/bike and chooses spawn
+    // time based on index.
     od.shuffle(&mut rng);
 
+    let scenario = lane_changing_scenario(map, od);
+    // Enable to manually watch the scenario
+    if false {
+        map.save();
+        scenario.save();
+    }
+
+    let mut opts = sim::SimOptions::new("test_lane_changing");
+    opts.alerts = sim::AlertHandler::Silence;
+    let mut sim = sim::Sim::new(&map, opts);
+    let mut rng = sim::SimFlags::for_test("test_lane_changing").make_rng();
+    scenario.instantiate(&mut sim, &map, &mut rng, &mut Timer::throwaway());
+    while !sim.is_done() {
+        sim.tiny_step(&map, &mut None);
+    }
+    // This time limit was determined by watching the scenario manually. This test prevents the
+    // time from regressing, which would probably indicate something breaking related to lane
+    // selection.
+    let limit = Duration::minutes(8) + Duration::seconds(10.0);
+    if sim.time() > Time::START_OF_DAY + limit {
+        panic!(
+            "Lane-changing scenario took {} to complete; it should be under {}",
+            sim.time(),
+            limit
+        );
+    }
+
+    Ok(())
+}
+
+/// Cars and bikes spawning at one border and heading to another, a second apart
+fn lane_changing_scenario(map: &Map, od: Vec<(IntersectionID, IntersectionID)>) -> Scenario {
     let mut scenario = Scenario::empty(map, "lane_changing");
     for (idx, (from, to)) in od.into_iter().enumerate() {
         scenario.people.push(PersonSpec {
@@ -208,29 +1141,61 @@ fn test_lane_changing(map: &Map) -> Result<()> {
             )],
         });
     }
-    // Enable to manually watch the scenario
-    if false {
-        map.save();
-        scenario.save();
+    scenario
+}
+
+/// Watch merges onto the road leading to the south border, where traffic from the north and east
+/// comes together. Once the watch stops, nothing else is recorded.
+fn test_merge_watch(map: &Map) -> Result<()> {
+    let south = IntersectionID(0);
+    let north = IntersectionID(8);
+    let east = IntersectionID(2);
+    let mut od = Vec::new();
+    for _ in 0..50 {
+        od.push((north, south));
+        od.push((east, south));
     }
+    let scenario = lane_changing_scenario(map, od);
 
-    let mut opts = sim::SimOptions::new("test_lane_changing");
+    let mut opts = sim::SimOptions::new("test_merge_watch");
     opts.alerts = sim::AlertHandler::Silence;
-    let mut sim = sim::Sim::new(&map, opts);
-    let mut rng = sim::SimFlags::for_test("test_lane_changing").make_rng();
-    scenario.instantiate(&mut sim, &map, &mut rng, &mut Timer::throwaway());
-    while !sim.is_done() {
-        sim.tiny_step(&map, &mut None);
+    let mut sim = sim::Sim::new(map, opts);
+    let mut rng = sim::SimFlags::for_test("test_merge_watch").make_rng();
+    let mut timer = Timer::throwaway();
+    scenario.instantiate(&mut sim, map, &mut rng, &mut timer);
+
+    let road = map.get_i(south).roads.iter().next().cloned().unwrap();
+    sim.watch_merges(road);
+    if sim.watched_merges() != Some(road) {
+        bail!(
+            "Asked to watch merges onto {}, but intersections aren't",
+            road
+        );
     }
-    // This time limit was determined by watching the scenario manually. This test prevents the
-    // time from regressing, which would probably indicate something breaking related to lane
-    // selection.
-    let limit = Duration::minutes(8) + Duration::seconds(10.0);
-    if sim.time() > Time::START_OF_DAY + limit {
-        panic!(
-            "Lane-changing scenario took {} to complete; it should be under {}",
-            sim.time(),
-            limit
+    sim.timed_step(map, Duration::minutes(2), &mut None, &mut timer);
+    let events = sim.get_mut_merge_watch().unwrap().take_events();
+    if events.is_empty() {
+        bail!("Nobody merged onto or off of {}", road);
+    }
+    for ev in &events {
+        let turn = map.get_t(ev.turn);
+        if map.get_l(turn.id.src).parent != road && map.get_l(turn.id.dst).parent != road {
+            bail!("{:?} doesn't involve the watched {}", ev, road);
+        }
+    }
+
+    sim.stop_watching_merges();
+    if let Some(r) = sim.watched_merges() {
+        bail!(
+            "Intersections still report merges onto {} after stopping",
+            r
+        );
+    }
+    sim.timed_step(map, Duration::minutes(2), &mut None, &mut timer);
+    if let Some(r) = sim.watched_merges() {
+        bail!(
+            "Intersections report merges onto {} again without being asked to",
+            r
         );
     }
 
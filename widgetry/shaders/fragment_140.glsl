@@ -6,6 +6,10 @@ uniform vec3 transform;
 uniform vec3 window;
 // textures grid
 uniform sampler2DArray textures;
+// A single image, bound to texture unit 1
+uniform sampler2D raster;
+// See the vertex shader
+uniform vec4 raster_rect;
 
 in vec4 fs_color;
 in vec3 fs_texture_coord;
@@ -13,6 +17,12 @@ in vec3 fs_texture_coord;
 out vec4 out_color;
 
 void main() {
-    vec4 x = fs_color * texture(textures, fs_texture_coord);
+    vec4 tex_color;
+    if (raster_rect[2] > 0.0) {
+        tex_color = texture(raster, fs_texture_coord.xy);
+    } else {
+        tex_color = texture(textures, fs_texture_coord);
+    }
+    vec4 x = fs_color * tex_color;
     out_color = vec4(x.a * x.r, x.a * x.g, x.a * x.b, x.a);
 }
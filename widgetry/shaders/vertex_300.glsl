@@ -9,6 +9,9 @@ uniform vec3 transform;
 uniform vec3 window;
 // textures grid
 uniform sampler2DArray textures;
+// (min x, min y, width, height) of a raster image stretched over the geometry, or all zeroes when
+// the sprite sheet is used instead
+uniform vec4 raster_rect;
 
 layout (location = 0) in vec3 position;
 layout (location = 1) in vec4 color;
@@ -52,4 +55,8 @@ void main() {
     float t_x = ((position[0] * zoom)) / texture_scale / zoom;
     float t_y = ((position[1] * zoom)) / texture_scale / zoom;
     fs_texture_coord = vec3(vec2(t_x, t_y), texture_index);
+
+    if (raster_rect[2] > 0.0) {
+        fs_texture_coord = vec3((position.xy - raster_rect.xy) / raster_rect.zw, 0.0);
+    }
 }
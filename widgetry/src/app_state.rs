@@ -11,7 +11,7 @@ use crate::{Canvas, Color, EventCtx, GfxCtx, Outcome, Panel};
 /// implementing this trait.
 pub trait SharedAppState {
     /// Before `State::event` is called, call this.
-    fn before_event(&mut self) {}
+    fn before_event(&mut self, _: &mut EventCtx) {}
     /// When DrawBaselayer::DefaultDraw is called, run this.
     fn draw_default(&self, _: &mut GfxCtx) {}
 
@@ -29,7 +29,7 @@ pub(crate) struct App<A: SharedAppState> {
 
 impl<A: SharedAppState> App<A> {
     pub(crate) fn event(&mut self, ctx: &mut EventCtx) {
-        self.shared_app_state.before_event();
+        self.shared_app_state.before_event(ctx);
 
         let transition = self
             .states
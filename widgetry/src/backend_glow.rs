@@ -4,6 +4,8 @@ use std::rc::Rc;
 use glow::HasContext;
 
 use crate::drawing::Uniforms;
+use geom::Bounds;
+
 use crate::{Canvas, Color, EventCtx, GeomBatch, ScreenDims, ScreenRectangle};
 
 #[cfg(feature = "native-backend")]
@@ -40,6 +42,10 @@ pub(crate) unsafe fn build_program(
     }
 
     gl.use_program(Some(program));
+    // The sprite sheet uses texture unit 0. Shaders without raster support don't have this.
+    if let Some(loc) = gl.get_uniform_location(program, "raster") {
+        gl.uniform_1_i32(Some(&loc), 1);
+    }
 
     gl.enable(glow::SCISSOR_TEST);
     gl.enable(glow::DEPTH_TEST);
@@ -114,10 +120,26 @@ impl<'a> GfxCtxInnards<'a> {
             self.gl
                 .uniform_3_f32_slice(Some(&window_loc), &uniforms.window);
 
+            let raster_loc = if let Some(ref raster) = obj.raster {
+                self.gl.active_texture(glow::TEXTURE1);
+                self.gl.bind_texture(glow::TEXTURE_2D, Some(raster.id));
+                self.gl.active_texture(glow::TEXTURE0);
+                let loc = self.gl.get_uniform_location(*self.program, "raster_rect");
+                self.gl.uniform_4_f32_slice(loc.as_ref(), &raster.rect);
+                loc
+            } else {
+                None
+            };
+
             self.gl.bind_vertex_array(Some(obj.vert_array.id));
             self.gl
                 .draw_elements(glow::TRIANGLES, obj.num_indices, glow::UNSIGNED_INT, 0);
             self.gl.bind_vertex_array(None);
+
+            if raster_loc.is_some() {
+                self.gl
+                    .uniform_4_f32_slice(raster_loc.as_ref(), &[0.0, 0.0, 0.0, 0.0]);
+            }
         }
     }
 
@@ -171,12 +193,25 @@ pub struct Drawable {
     vert_buffer: Buffer,
     elem_buffer: Buffer,
     num_indices: i32,
+    raster: Option<Raster>,
     gl: Rc<glow::Context>,
 }
 
+/// An image stretched over a Drawable's geometry, instead of using the sprite sheet
+struct Raster {
+    id: <glow::Context as glow::HasContext>::Texture,
+    // (min x, min y, width, height) in map-space
+    rect: [f32; 4],
+}
+
 impl Drop for Drawable {
     #[inline]
     fn drop(&mut self) {
+        if let Some(ref raster) = self.raster {
+            unsafe {
+                self.gl.delete_texture(raster.id);
+            }
+        }
         self.elem_buffer.destroy(&self.gl);
         self.vert_buffer.destroy(&self.gl);
         self.vert_array.destroy(&self.gl);
@@ -258,6 +293,11 @@ type WindowAdapter = crate::backend_glow_wasm::WindowAdapter;
 #[cfg(feature = "native-backend")]
 type WindowAdapter = crate::backend_glow_native::WindowAdapter;
 
+/// How many screenshots can wait to be written before new ones are dropped. Each one holds the
+/// raw pixels of a full frame.
+#[cfg(not(target_arch = "wasm32"))]
+const SCREENCAP_QUEUE_SIZE: usize = 4;
+
 pub struct PrerenderInnards {
     gl: Rc<glow::Context>,
     window_adapter: WindowAdapter,
@@ -266,6 +306,12 @@ pub struct PrerenderInnards {
     // TODO Prerender doesn't know what things are temporary and permanent. Could make the API more
     // detailed.
     pub total_bytes_uploaded: Cell<usize>,
+
+    // One background thread writes every screenshot, started the first time one is taken
+    #[cfg(not(target_arch = "wasm32"))]
+    screencap_queue:
+        std::cell::RefCell<Option<std::sync::mpsc::SyncSender<(image::DynamicImage, String)>>>,
+    pub screencaps_dropped: Cell<usize>,
 }
 
 impl PrerenderInnards {
@@ -279,6 +325,9 @@ impl PrerenderInnards {
             program,
             window_adapter,
             total_bytes_uploaded: Cell::new(0),
+            #[cfg(not(target_arch = "wasm32"))]
+            screencap_queue: std::cell::RefCell::new(None),
+            screencaps_dropped: Cell::new(0),
         }
     }
 
@@ -373,10 +422,73 @@ impl PrerenderInnards {
             vert_buffer,
             elem_buffer,
             num_indices,
+            raster: None,
             gl: self.gl.clone(),
         }
     }
 
+    /// Uploads an image stretched over a rectangle in map-space, with the first row of pixels at
+    /// `bounds.min_y`. Fails if the shaders in use can't draw images, like with WebGL 1.0.
+    pub fn upload_raster(
+        &self,
+        img: &image::RgbaImage,
+        bounds: &Bounds,
+        opacity: f64,
+    ) -> anyhow::Result<Drawable> {
+        if self
+            .gl
+            .get_uniform_location(self.program, "raster_rect")
+            .is_none()
+        {
+            bail!("These shaders can't draw raster images; WebGL 2.0 is needed");
+        }
+        let id = unsafe {
+            let id = self.gl.create_texture().map_err(|err| anyhow!(err))?;
+            // Don't disturb the sprite sheet on unit 0
+            self.gl.active_texture(glow::TEXTURE1);
+            self.gl.bind_texture(glow::TEXTURE_2D, Some(id));
+            self.gl.tex_image_2d(
+                glow::TEXTURE_2D,
+                0,
+                glow::RGBA as i32,
+                img.width() as i32,
+                img.height() as i32,
+                0,
+                glow::RGBA,
+                glow::UNSIGNED_BYTE,
+                Some(img.as_raw().as_slice()),
+            );
+            // No mipmaps, so the minification filter can't use them
+            for (param, value) in [
+                (glow::TEXTURE_MIN_FILTER, glow::LINEAR),
+                (glow::TEXTURE_MAG_FILTER, glow::LINEAR),
+                (glow::TEXTURE_WRAP_S, glow::CLAMP_TO_EDGE),
+                (glow::TEXTURE_WRAP_T, glow::CLAMP_TO_EDGE),
+            ]
+            .iter()
+            {
+                self.gl
+                    .tex_parameter_i32(glow::TEXTURE_2D, *param, *value as i32);
+            }
+            self.gl.active_texture(glow::TEXTURE0);
+            id
+        };
+
+        let mut batch = GeomBatch::new();
+        batch.push(Color::WHITE.alpha(opacity as f32), bounds.get_rectangle());
+        let mut drawable = self.actually_upload(true, batch);
+        drawable.raster = Some(Raster {
+            id,
+            rect: [
+                bounds.min_x as f32,
+                bounds.min_y as f32,
+                bounds.width() as f32,
+                bounds.height() as f32,
+            ],
+        });
+        Ok(drawable)
+    }
+
     fn window(&self) -> &winit::window::Window {
         self.window_adapter.window()
     }
@@ -422,6 +534,132 @@ impl PrerenderInnards {
     }
 
     pub(crate) fn screencap(&self, dims: ScreenDims, filename: String) -> anyhow::Result<()> {
+        let img = self.read_pixels(dims);
+        save_png(img, &filename)
+    }
+
+    /// Like `screencap`, but only reads the pixels back synchronously. Encoding and writing the
+    /// PNG happens in a background thread (on native), so the caller isn't blocked by disk IO. If
+    /// that thread falls too far behind, the screenshot is dropped and counted in
+    /// `screencaps_dropped`.
+    pub(crate) fn screencap_in_background(&self, dims: ScreenDims, filename: String) {
+        let img = self.read_pixels(dims);
+
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            use std::sync::mpsc::TrySendError;
+
+            let mut queue = self.screencap_queue.borrow_mut();
+            let tx = queue.get_or_insert_with(|| {
+                let (tx, rx) = std::sync::mpsc::sync_channel::<(image::DynamicImage, String)>(
+                    SCREENCAP_QUEUE_SIZE,
+                );
+                std::thread::spawn(move || {
+                    for (img, filename) in rx {
+                        if let Err(err) = save_png(img, &filename) {
+                            error!("Couldn't save {}: {}", filename, err);
+                        }
+                    }
+                });
+                tx
+            });
+            if let Err(err) = tx.try_send((img, filename)) {
+                self.screencaps_dropped
+                    .set(self.screencaps_dropped.get() + 1);
+                match err {
+                    TrySendError::Full((_, filename)) => {
+                        warn!("Screenshots are piling up; dropped {}", filename);
+                    }
+                    TrySendError::Disconnected((_, filename)) => {
+                        error!("Screenshot writer stopped; dropped {}", filename);
+                    }
+                }
+            }
+        }
+
+        #[cfg(target_arch = "wasm32")]
+        {
+            if let Err(err) = save_png(img, &filename) {
+                error!("Couldn't save {}: {}", filename, err);
+            }
+        }
+    }
+
+    /// Redirects drawing into a new framebuffer of exactly `dims` pixels, instead of the window.
+    /// Call `finish_offscreen` to read the result back and restore drawing to the window.
+    pub(crate) fn start_offscreen(&self, dims: ScreenDims) -> anyhow::Result<OffscreenTarget> {
+        let width = dims.width as i32;
+        let height = dims.height as i32;
+        unsafe {
+            let framebuffer = self.gl.create_framebuffer().map_err(|err| anyhow!(err))?;
+            self.gl
+                .bind_framebuffer(glow::FRAMEBUFFER, Some(framebuffer));
+
+            let color = self.gl.create_renderbuffer().map_err(|err| anyhow!(err))?;
+            self.gl.bind_renderbuffer(glow::RENDERBUFFER, Some(color));
+            self.gl
+                .renderbuffer_storage(glow::RENDERBUFFER, glow::RGBA8, width, height);
+            self.gl.framebuffer_renderbuffer(
+                glow::FRAMEBUFFER,
+                glow::COLOR_ATTACHMENT0,
+                glow::RENDERBUFFER,
+                Some(color),
+            );
+
+            let depth = self.gl.create_renderbuffer().map_err(|err| anyhow!(err))?;
+            self.gl.bind_renderbuffer(glow::RENDERBUFFER, Some(depth));
+            self.gl.renderbuffer_storage(
+                glow::RENDERBUFFER,
+                glow::DEPTH_COMPONENT16,
+                width,
+                height,
+            );
+            self.gl.framebuffer_renderbuffer(
+                glow::FRAMEBUFFER,
+                glow::DEPTH_ATTACHMENT,
+                glow::RENDERBUFFER,
+                Some(depth),
+            );
+            self.gl.bind_renderbuffer(glow::RENDERBUFFER, None);
+
+            let target = OffscreenTarget {
+                framebuffer,
+                color,
+                depth,
+            };
+            if self.gl.check_framebuffer_status(glow::FRAMEBUFFER) != glow::FRAMEBUFFER_COMPLETE {
+                self.delete_offscreen(target);
+                bail!("Offscreen framebuffer of {:?} isn't complete", dims);
+            }
+
+            self.gl.viewport(0, 0, width, height);
+            self.gl.scissor(0, 0, width, height);
+            Ok(target)
+        }
+    }
+
+    /// Reads back what was drawn into the offscreen target, saves it as a PNG in the background,
+    /// and goes back to drawing into the window.
+    pub(crate) fn finish_offscreen(
+        &self,
+        target: OffscreenTarget,
+        dims: ScreenDims,
+        filename: String,
+    ) {
+        self.screencap_in_background(dims, filename);
+        self.delete_offscreen(target);
+    }
+
+    fn delete_offscreen(&self, target: OffscreenTarget) {
+        unsafe {
+            self.gl.bind_framebuffer(glow::FRAMEBUFFER, None);
+            self.gl.delete_renderbuffer(target.color);
+            self.gl.delete_renderbuffer(target.depth);
+            self.gl.delete_framebuffer(target.framebuffer);
+        }
+    }
+
+    fn read_pixels(&self, dims: ScreenDims) -> image::DynamicImage {
         let width = dims.width as u32;
         let height = dims.height as u32;
 
@@ -441,18 +679,31 @@ impl PrerenderInnards {
                 glow::PixelPackData::Slice(pixels),
             );
         }
-
-        image::save_buffer(
-            &filename,
-            &image::imageops::flip_vertical(&img),
-            width,
-            height,
-            image::ColorType::Rgba8,
-        )?;
-        Ok(())
+        img
     }
 }
 
+/// A framebuffer that drawing is temporarily redirected into. See `start_offscreen`.
+pub(crate) struct OffscreenTarget {
+    framebuffer: <glow::Context as glow::HasContext>::Framebuffer,
+    color: <glow::Context as glow::HasContext>::Renderbuffer,
+    depth: <glow::Context as glow::HasContext>::Renderbuffer,
+}
+
+fn save_png(img: image::DynamicImage, filename: &str) -> anyhow::Result<()> {
+    use image::GenericImageView;
+
+    let (width, height) = (img.width(), img.height());
+    image::save_buffer(
+        filename,
+        &image::imageops::flip_vertical(&img),
+        width,
+        height,
+        image::ColorType::Rgba8,
+    )?;
+    Ok(())
+}
+
 /// Uploads a sprite sheet of textures to the GPU so they can be used by Fill::Texture and
 /// friends to paint shapes.
 ///
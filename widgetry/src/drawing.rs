@@ -114,6 +114,22 @@ impl<'a> GfxCtx<'a> {
         self.num_forks += 1;
     }
 
+    /// Draw map-space things shifted on the screen by some number of pixels, keeping the current
+    /// camera zoom. Up to the caller to call unfork()!
+    pub fn fork_shifted(&mut self, dx: f64, dy: f64) {
+        self.uniforms.transform = [
+            (self.canvas.cam_x - dx) as f32,
+            (self.canvas.cam_y - dy) as f32,
+            self.canvas.cam_zoom as f32,
+        ];
+        self.uniforms.window = [
+            self.canvas.window_width as f32,
+            self.canvas.window_height as f32,
+            MAPSPACE_Z,
+        ];
+        self.num_forks += 1;
+    }
+
     pub fn unfork(&mut self) {
         self.uniforms = Uniforms::new(&self.canvas);
         self.num_forks += 1;
@@ -258,10 +274,27 @@ impl Prerender {
         self.actually_upload(false, batch)
     }
 
+    /// Uploads an image stretched over a rectangle in map-space, as one textured quad. The first
+    /// row of pixels is drawn at `bounds.min_y`.
+    pub fn upload_raster(
+        &self,
+        img: &image::RgbaImage,
+        bounds: &Bounds,
+        opacity: f64,
+    ) -> anyhow::Result<Drawable> {
+        self.num_uploads.set(self.num_uploads.get() + 1);
+        self.inner.upload_raster(img, bounds, opacity)
+    }
+
     pub fn get_total_bytes_uploaded(&self) -> usize {
         self.inner.total_bytes_uploaded.get()
     }
 
+    /// How many screenshots weren't saved because writing them fell behind.
+    pub fn get_num_screencaps_dropped(&self) -> usize {
+        self.inner.screencaps_dropped.get()
+    }
+
     fn actually_upload(&self, permanent: bool, batch: GeomBatch) -> Drawable {
         self.num_uploads.set(self.num_uploads.get() + 1);
         self.inner.actually_upload(permanent, batch)
@@ -2,7 +2,7 @@ use std::collections::VecDeque;
 
 use instant::Instant;
 
-use abstutil::{elapsed_seconds, Timer, TimerSink};
+use abstutil::{elapsed_seconds, prettyprint_time, Timer, TimerSink};
 use geom::{Percent, Polygon};
 
 use crate::{
@@ -23,6 +23,21 @@ pub enum UpdateType {
         /// optional drawing suffix returned by the app.
         leaflet_naming: bool,
     },
+    /// Draw the current view and save it as a PNG. Writing the file happens in the background.
+    ScreenCaptureCurrentShot {
+        filename: String,
+        /// If specified, temporarily move the camera here (cam_x, cam_y, cam_zoom) for the
+        /// capture.
+        camera: Option<(f64, f64, f64)>,
+    },
+    /// Draw the map from the given camera (cam_x, cam_y, cam_zoom) into an offscreen buffer of
+    /// exactly `dims`, and save it as a PNG. The result doesn't depend on the window's size or
+    /// scale factor. Writing the file happens in the background.
+    ScreenCaptureOffscreen {
+        filename: String,
+        camera: (f64, f64, f64),
+        dims: ScreenDims,
+    },
 }
 
 pub struct EventCtx<'a> {
@@ -199,6 +214,9 @@ struct LoadingScreen<'a> {
     max_capacity: usize,
     last_drawn: Instant,
     title: String,
+    // (label, processed, total, seconds elapsed) for the work happening right now, if the total
+    // is known
+    progress: Option<(String, usize, usize, f64)>,
 }
 
 impl<'a> LoadingScreen<'a> {
@@ -220,6 +238,7 @@ impl<'a> LoadingScreen<'a> {
             title,
             canvas,
             style,
+            progress: None,
         }
     }
 
@@ -242,11 +261,46 @@ impl<'a> LoadingScreen<'a> {
         for l in &self.lines {
             txt.add(Line(l));
         }
+        if let Some((ref label, processed, total, elapsed)) = self.progress {
+            txt.add(Line(""));
+            let pct = Percent::of(processed, total);
+            // The rate early on is too noisy to extrapolate from
+            if elapsed >= 1.0 && processed > 0 {
+                let remaining = elapsed * ((total - processed) as f64) / (processed as f64);
+                txt.add(Line(format!(
+                    "{}: {}, about {} left",
+                    label,
+                    pct,
+                    prettyprint_time(remaining)
+                )));
+            } else {
+                txt.add(Line(format!("{}: {}", label, pct)));
+            }
+        }
         let panel = ctx.make_loading_screen(txt);
 
         let mut g = GfxCtx::new(self.prerender, &self.canvas, &self.style, false);
         g.clear(Color::BLACK);
         panel.draw(&mut g);
+        if let Some((_, processed, total, _)) = self.progress {
+            let window = self.canvas.get_window_dims();
+            let width = 0.5 * window.width;
+            let height = 20.0;
+            let (x, y) = (0.25 * window.width, window.height - 2.0 * height);
+            g.fork_screenspace();
+            g.draw_polygon(
+                Color::grey(0.3),
+                Polygon::rectangle(width, height).translate(x, y),
+            );
+            if processed > 0 {
+                g.draw_polygon(
+                    Color::hex("#F4DA22"),
+                    Polygon::rectangle(width * (processed as f64) / (total as f64), height)
+                        .translate(x, y),
+                );
+            }
+            g.unfork();
+        }
         g.prerender.inner.draw_finished(g.inner);
     }
 }
@@ -254,6 +308,8 @@ impl<'a> LoadingScreen<'a> {
 impl<'a> TimerSink for LoadingScreen<'a> {
     // TODO Do word wrap. Assume the window is fixed during loading, if it makes things easier.
     fn println(&mut self, line: String) {
+        // Any progress bar belonged to work that's over now
+        self.progress = None;
         if self.lines.len() == self.max_capacity {
             self.lines.pop_front();
         }
@@ -266,4 +322,10 @@ impl<'a> TimerSink for LoadingScreen<'a> {
         self.lines.push_back(line);
         self.redraw();
     }
+
+    fn progress(&mut self, label: &str, processed: usize, total: usize, elapsed: f64) {
+        self.progress = Some((label.to_string(), processed, total, elapsed));
+        // Most calls return immediately, since redrawing is throttled
+        self.redraw();
+    }
 }
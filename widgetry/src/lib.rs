@@ -55,7 +55,7 @@ pub use crate::widgets::fan_chart::FanChart;
 pub use crate::widgets::filler::Filler;
 pub use crate::widgets::just_draw::DrawWithTooltips;
 pub(crate) use crate::widgets::just_draw::{DeferDraw, JustDraw};
-pub use crate::widgets::line_plot::{LinePlot, PlotOptions, Series};
+pub use crate::widgets::line_plot::{LinePlot, PlotOptions, Series, Yvalue};
 pub use crate::widgets::menu::Menu;
 pub use crate::widgets::persistent_split::PersistentSplit;
 pub use crate::widgets::scatter_plot::ScatterPlot;
@@ -10,7 +10,7 @@ use geom::Duration;
 
 use crate::app_state::App;
 use crate::assets::Assets;
-use crate::tools::screenshot::screenshot_everything;
+use crate::tools::screenshot::{screenshot_current, screenshot_everything, screenshot_offscreen};
 use crate::{
     Canvas, Event, EventCtx, GfxCtx, Prerender, SharedAppState, Style, Text, UpdateType, UserInput,
 };
@@ -126,6 +126,21 @@ impl<A: SharedAppState> State<A> {
 
     /// Returns naming hint. Logically consumes the number of uploads.
     pub(crate) fn draw(&mut self, prerender: &Prerender, screenshot: bool) -> Option<String> {
+        self.draw_frame(prerender, screenshot, true)
+    }
+
+    /// Draws in screenshot mode into whatever's currently bound, without presenting anything to
+    /// the window.
+    pub(crate) fn draw_offscreen(&mut self, prerender: &Prerender) {
+        self.draw_frame(prerender, true, false);
+    }
+
+    fn draw_frame(
+        &mut self,
+        prerender: &Prerender,
+        screenshot: bool,
+        present: bool,
+    ) -> Option<String> {
         let mut g = GfxCtx::new(prerender, &self.canvas, &self.style, screenshot);
 
         self.canvas.start_drawing();
@@ -149,7 +164,9 @@ impl<A: SharedAppState> State<A> {
             );
         }
 
-        prerender.inner.draw_finished(g.inner);
+        if present {
+            prerender.inner.draw_finished(g.inner);
+        }
         naming_hint
     }
 }
@@ -375,6 +392,23 @@ pub fn run<
                         error!("Couldn't screenshot everything: {}", err);
                     }
                 }
+                UpdateType::ScreenCaptureCurrentShot { filename, camera } => {
+                    if let Err(err) = screenshot_current(&mut state, filename, &prerender, camera)
+                    {
+                        error!("Couldn't screenshot the current view: {}", err);
+                    }
+                }
+                UpdateType::ScreenCaptureOffscreen {
+                    filename,
+                    camera,
+                    dims,
+                } => {
+                    if let Err(err) =
+                        screenshot_offscreen(&mut state, filename, &prerender, camera, dims)
+                    {
+                        error!("Couldn't take an offscreen screenshot: {}", err);
+                    }
+                }
             }
         }
     });
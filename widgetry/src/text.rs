@@ -529,9 +529,11 @@ impl TextSpan {
         // We need to subtract and account for the length of the text
         let start_offset = (path.length() / 2.0).inner_meters()
             - (Text::from(Line(&self.text)).dims(assets).width * scale) / 2.0;
+        // Shift the text down by roughly half of its height, so it's centered on the path instead
+        // of sitting on top of it
         write!(
             &mut svg,
-            r##"<text xml:space="preserve" font-size="{}" font-family="{}" {} fill="{}" fill-opacity="{}" startOffset="{}">"##,
+            r##"<text xml:space="preserve" font-size="{}" font-family="{}" {} fill="{}" fill-opacity="{}" dy="{}">"##,
             // This is seemingly the easiest way to do this. We could .scale() the whole batch
             // after, but then we have to re-translate it to the proper spot
             (self.size as f64) * scale,
@@ -543,14 +545,15 @@ impl TextSpan {
             },
             self.fg_color.to_hex(),
             self.fg_color.a,
-            start_offset,
+            0.35 * (self.size as f64) * scale,
         )
             .unwrap();
 
+        // startOffset only works on the textPath
         write!(
             &mut svg,
-            r##"<textPath href="#txtpath">{}</textPath></text></svg>"##,
-            self.text
+            r##"<textPath href="#txtpath" startOffset="{}">{}</textPath></text></svg>"##,
+            start_offset, self.text
         )
         .unwrap();
 
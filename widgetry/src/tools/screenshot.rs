@@ -65,3 +65,82 @@ pub(crate) fn screenshot_everything<A: SharedAppState>(
     state.canvas.cam_y = orig_y;
     Ok(())
 }
+
+/// Take a screenshot of the current view. Only reading the pixels happens synchronously; the PNG
+/// is written in the background.
+pub(crate) fn screenshot_current<A: SharedAppState>(
+    state: &mut State<A>,
+    filename: String,
+    prerender: &Prerender,
+    camera: Option<(f64, f64, f64)>,
+) -> anyhow::Result<()> {
+    if let Some(parent) = std::path::Path::new(&filename).parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    let orig_camera = (
+        state.canvas.cam_x,
+        state.canvas.cam_y,
+        state.canvas.cam_zoom,
+    );
+    if let Some((x, y, zoom)) = camera {
+        state.canvas.cam_x = x;
+        state.canvas.cam_y = y;
+        state.canvas.cam_zoom = zoom;
+    }
+
+    state.draw(prerender, true);
+    prerender
+        .inner
+        .screencap_in_background(state.canvas.get_window_dims(), filename);
+
+    state.canvas.cam_x = orig_camera.0;
+    state.canvas.cam_y = orig_camera.1;
+    state.canvas.cam_zoom = orig_camera.2;
+    Ok(())
+}
+
+/// Draw from the given camera into an offscreen buffer of exactly `dims`, independent of the
+/// window's size, and save it as a PNG in the background.
+pub(crate) fn screenshot_offscreen<A: SharedAppState>(
+    state: &mut State<A>,
+    filename: String,
+    prerender: &Prerender,
+    camera: (f64, f64, f64),
+    dims: ScreenDims,
+) -> anyhow::Result<()> {
+    if let Some(parent) = std::path::Path::new(&filename).parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    let target = prerender.inner.start_offscreen(dims)?;
+
+    // The canvas and scale factor determine how everything's projected, so pretend the window is
+    // exactly the size of the capture
+    let orig_camera = (
+        state.canvas.cam_x,
+        state.canvas.cam_y,
+        state.canvas.cam_zoom,
+    );
+    let orig_window = state.canvas.get_window_dims();
+    let orig_scale_factor = prerender.get_scale_factor();
+    state.canvas.cam_x = camera.0;
+    state.canvas.cam_y = camera.1;
+    state.canvas.cam_zoom = camera.2;
+    state.canvas.window_width = dims.width;
+    state.canvas.window_height = dims.height;
+    *prerender.scale_factor.borrow_mut() = 1.0;
+
+    state.draw_offscreen(prerender);
+    prerender.inner.finish_offscreen(target, dims, filename);
+
+    state.canvas.cam_x = orig_camera.0;
+    state.canvas.cam_y = orig_camera.1;
+    state.canvas.cam_zoom = orig_camera.2;
+    state.canvas.window_width = orig_window.width;
+    state.canvas.window_height = orig_window.height;
+    *prerender.scale_factor.borrow_mut() = orig_scale_factor;
+    // Point the viewport back at the window
+    prerender.window_resized(orig_window);
+    Ok(())
+}
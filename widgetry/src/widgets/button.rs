@@ -18,6 +18,8 @@ pub struct Button {
 
     pub(crate) hotkey: Option<MultiKey>,
     tooltip: Text,
+    // Shown instead while disabled, if present
+    disabled_tooltip: Option<Text>,
     // Screenspace, top-left always at the origin. Also, probably not a box. :P
     hitbox: Polygon,
 
@@ -54,6 +56,7 @@ impl Button {
             } else {
                 Text::tooltip(ctx, hotkey.clone(), action)
             },
+            disabled_tooltip: None,
             hotkey,
             hitbox,
 
@@ -115,6 +118,11 @@ impl WidgetImpl for Button {
     fn draw(&self, g: &mut GfxCtx) {
         if self.is_disabled {
             g.redraw_at(self.top_left, &self.draw_disabled);
+            if self.hovering {
+                if let Some(ref tooltip) = self.disabled_tooltip {
+                    g.draw_mouse_tooltip(tooltip.clone());
+                }
+            }
         } else if self.hovering {
             g.redraw_at(self.top_left, &self.draw_hovered);
             if !self.tooltip.is_empty() {
@@ -132,6 +140,7 @@ pub struct ButtonBuilder<'a> {
     stack_spacing: f64,
     hotkey: Option<MultiKey>,
     tooltip: Option<Text>,
+    disabled_tooltip: Option<Text>,
     stack_axis: Option<geom_batch_stack::Axis>,
     is_label_before_image: bool,
     corner_rounding: Option<CornerRounding>,
@@ -409,6 +418,15 @@ impl<'b, 'a: 'b> ButtonBuilder<'a> {
         self
     }
 
+    /// Set a tooltip [`Text`] to appear when hovering over the button while it's disabled, usually
+    /// to explain why it can't be clicked.
+    ///
+    /// Disabled buttons have no tooltip otherwise.
+    pub fn disabled_tooltip(mut self, tooltip: Text) -> Self {
+        self.disabled_tooltip = Some(tooltip);
+        self
+    }
+
     /// The button's items will be rendered in a vertical column
     ///
     /// If the button doesn't have both an image and label, this has no effect.
@@ -492,7 +510,7 @@ impl<'b, 'a: 'b> ButtonBuilder<'a> {
             "button was empty"
         );
         let hitbox = normal.get_bounds().get_rectangle();
-        Button::new(
+        let mut button = Button::new(
             ctx,
             normal,
             hovered,
@@ -502,7 +520,9 @@ impl<'b, 'a: 'b> ButtonBuilder<'a> {
             self.tooltip.clone(),
             hitbox,
             self.is_disabled,
-        )
+        );
+        button.disabled_tooltip = self.disabled_tooltip.clone();
+        button
     }
 
     /// Shorthand method to build a Button wrapped in a Widget
@@ -58,8 +58,13 @@ impl FanChart {
 
         // TODO Tuned to fit the info panel. Instead these should somehow stretch to fill their
         // container.
-        let width = 0.22 * ctx.canvas.window_width;
-        let height = 0.2 * ctx.canvas.window_height;
+        let (width, height) = match opts.dims {
+            Some(dims) => (dims.width, dims.height),
+            None => (
+                0.22 * ctx.canvas.window_width,
+                0.2 * ctx.canvas.window_height,
+            ),
+        };
 
         let mut batch = GeomBatch::new();
         // Grid lines for the Y scale. Draw up to 10 lines max to cover the order of magnitude of
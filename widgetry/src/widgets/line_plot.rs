@@ -2,8 +2,8 @@ use std::collections::HashSet;
 
 use abstutil::prettyprint_usize;
 use geom::{
-    Angle, Bounds, Circle, Distance, Duration, FindClosest, Percent, PolyLine, Polygon, Pt2D, Time,
-    UnitFmt,
+    Angle, Bounds, Circle, Distance, Duration, FindClosest, Percent, PolyLine, Polygon, Pt2D,
+    Speed, Time, UnitFmt,
 };
 
 use crate::{
@@ -29,6 +29,9 @@ pub struct PlotOptions<T: Yvalue<T>> {
     pub max_x: Option<Time>,
     pub max_y: Option<T>,
     pub disabled: HashSet<String>,
+    /// The size of the plot itself, not counting the legend or axis labels. If None, it's sized to
+    /// fit the default info panel.
+    pub dims: Option<ScreenDims>,
 }
 
 impl<T: Yvalue<T>> PlotOptions<T> {
@@ -38,6 +41,7 @@ impl<T: Yvalue<T>> PlotOptions<T> {
             max_x: None,
             max_y: None,
             disabled: HashSet::new(),
+            dims: None,
         }
     }
 
@@ -47,6 +51,7 @@ impl<T: Yvalue<T>> PlotOptions<T> {
             max_x: None,
             max_y: None,
             disabled: HashSet::new(),
+            dims: None,
         }
     }
 }
@@ -86,8 +91,13 @@ impl<T: Yvalue<T>> LinePlot<T> {
 
         // TODO Tuned to fit the info panel. Instead these should somehow stretch to fill their
         // container.
-        let width = 0.23 * ctx.canvas.window_width;
-        let height = 0.2 * ctx.canvas.window_height;
+        let (width, height) = match opts.dims {
+            Some(dims) => (dims.width, dims.height),
+            None => (
+                0.23 * ctx.canvas.window_width,
+                0.2 * ctx.canvas.window_height,
+            ),
+        };
 
         let mut batch = GeomBatch::new();
         // Grid lines for the Y scale. Draw up to 10 lines max to cover the order of magnitude of
@@ -312,6 +322,34 @@ impl Yvalue<Duration> for Duration {
     }
 }
 
+impl Yvalue<Speed> for Speed {
+    fn from_percent(&self, percent: f64) -> Speed {
+        *self * percent
+    }
+    fn to_percent(self, max: Speed) -> f64 {
+        if max == Speed::ZERO {
+            0.0
+        } else {
+            self / max
+        }
+    }
+    fn prettyprint(self) -> String {
+        self.to_string(&UnitFmt {
+            metric: false,
+            round_durations: true,
+        })
+    }
+    fn to_f64(self) -> f64 {
+        self.inner_meters_per_second()
+    }
+    fn from_f64(&self, x: f64) -> Speed {
+        Speed::meters_per_second(x)
+    }
+    fn zero() -> Speed {
+        Speed::ZERO
+    }
+}
+
 pub struct Series<T> {
     pub label: String,
     pub color: Color,
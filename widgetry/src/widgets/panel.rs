@@ -196,7 +196,7 @@ impl Panel {
         assert!(nodes.is_empty());
     }
 
-    fn scroll_offset(&self) -> (f64, f64) {
+    pub fn scroll_offset(&self) -> (f64, f64) {
         let x = if self.scrollable_x {
             self.slider("horiz scrollbar").get_percent()
                 * (self.contents_dims.width - self.container_dims.width).max(0.0)
@@ -237,7 +237,7 @@ impl Panel {
         changed
     }
 
-    fn set_scroll_offset(&mut self, ctx: &EventCtx, offset: (f64, f64)) {
+    pub fn set_scroll_offset(&mut self, ctx: &EventCtx, offset: (f64, f64)) {
         if self.update_scroll_sliders(ctx, offset) {
             self.recompute_layout(ctx, false);
         }
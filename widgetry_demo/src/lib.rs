@@ -106,6 +106,7 @@ impl Demo {
                     max_x: Some(Time::START_OF_DAY + self.elapsed),
                     max_y: None,
                     disabled: HashSet::new(),
+                    dims: None,
                 },
             ),
         ]))